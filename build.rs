@@ -0,0 +1,26 @@
+use std::process::Command;
+
+/// Set TVC_TRACKER_GIT_SHA for the startup banner: GIT_SHA if set (Docker
+/// builds have no .git), else the checked out commit, else "unknown"
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    if std::path::Path::new(".git/HEAD").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/refs");
+    }
+    let sha = std::env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| {
+            let output = Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=TVC_TRACKER_GIT_SHA={}", sha);
+}
@@ -0,0 +1,200 @@
+use crate::events::TrackerEvent;
+use crate::ws::{TrackerBaseline, UnobservedSlots};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Version of the `/status` and bus event payloads. Removing, renaming or
+/// retyping a field bumps it; adding one doesn't.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Response of `/status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StatusResponse {
+    pub schema_version: u32,
+    pub vote_pubkey: String,
+    pub tracker: TrackerBaseline,
+    /// Estimated bytes held by the tracker's history and pending votes
+    pub tracker_state_bytes: usize,
+    /// Age of the current WebSocket connection (None while disconnected)
+    pub ws_connection_age_seconds: Option<f64>,
+    /// Slots rooted while the vote subscription was down
+    pub unobserved: UnobservedSlots,
+}
+
+/// Message published to the bus for each event: the event's fields plus
+/// where and when it happened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EventMessage {
+    pub schema_version: u32,
+    pub vote_pubkey: String,
+    /// Unix seconds at which the event was published
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: TrackerEvent,
+}
+
+/// JSON Schema of the payloads, for the `schema` subcommand
+#[cfg(feature = "schema")]
+pub fn json_schema() -> serde_json::Value {
+    serde_json::json!({
+        "schema_version": SCHEMA_VERSION,
+        "status": schemars::schema_for!(StatusResponse),
+        "event": schemars::schema_for!(EventMessage),
+    })
+}
+
+/// Pretty-printed [`json_schema`] (requires the `schema` feature)
+pub fn json_schema_pretty() -> Result<String> {
+    #[cfg(feature = "schema")]
+    return Ok(serde_json::to_string_pretty(&json_schema())?);
+    #[cfg(not(feature = "schema"))]
+    anyhow::bail!("The schema subcommand requires building with the `schema` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::ClusterAverage;
+    use crate::ws::EpochComparison;
+
+    fn status() -> StatusResponse {
+        StatusResponse {
+            schema_version: SCHEMA_VERSION,
+            vote_pubkey: "Vote111".to_string(),
+            tracker: TrackerBaseline {
+                epoch: Some(500),
+                root_slot: Some(500 * 432_000 + 1234),
+                epoch_credits: 19_744,
+                epoch_missed: 80,
+                tracked_credits: 6_019_744,
+                tracked_missed: 1_280,
+                epoch_first_tracked_slot: Some(216_000_000),
+                epoch_missed_untracked: 0,
+            },
+            tracker_state_bytes: 4096,
+            ws_connection_age_seconds: Some(12.5),
+            unobserved: UnobservedSlots {
+                rooted_slots: 10,
+                expected_credits: 160,
+            },
+        }
+    }
+
+    fn events() -> Vec<EventMessage> {
+        [
+            TrackerEvent::EpochRollover {
+                epoch: 500,
+                credits: 6_812_345,
+                missed: 99_655,
+                perfect_slot_streak_max: None,
+                first_tracked_slot: Some(216_000_000),
+                last_tracked_slot: Some(216_431_999),
+                comparison: Some(EpochComparison {
+                    previous_epoch: 499,
+                    credits_delta: 12_345,
+                    missed_delta: -12_345,
+                    efficiency_delta: Some(0.0018),
+                    avg_latency_delta: Some(-0.03),
+                }),
+                cluster: Some(ClusterAverage {
+                    epoch: 500,
+                    stake_weighted_mean: 6_700_000.0,
+                    ratio: Some(1.0167),
+                    excludes_self: true,
+                }),
+            },
+            TrackerEvent::DelinquencyChanged { delinquent: true },
+            TrackerEvent::StakeDecreased {
+                epoch: 500,
+                delta_lamports: -1_000,
+            },
+            TrackerEvent::ValidatorRestart {
+                last_vote_slot: 100,
+                resumed_vote_slot: 400,
+            },
+        ]
+        .into_iter()
+        .map(|event| EventMessage {
+            schema_version: SCHEMA_VERSION,
+            vote_pubkey: "Vote111".to_string(),
+            timestamp: 1_700_000_000,
+            event,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_payloads_round_trip() {
+        let json = serde_json::to_string(&status()).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StatusResponse>(&json).unwrap(),
+            status()
+        );
+        for message in events() {
+            let json = serde_json::to_value(&message).unwrap();
+            assert_eq!(json["type"], message.event.kind());
+            assert_eq!(
+                serde_json::from_value::<EventMessage>(json).unwrap(),
+                message
+            );
+        }
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_schema_covers_every_field() {
+        let schema = json_schema();
+        assert_eq!(schema["schema_version"], SCHEMA_VERSION);
+
+        let status_schema = &schema["status"];
+        let status = serde_json::to_value(status()).unwrap();
+        for (field, value) in status.as_object().unwrap() {
+            assert!(
+                status_schema["properties"].get(field).is_some(),
+                "status.{field} missing"
+            );
+            // Nested structs are described in $defs
+            if let Some(nested) = value.as_object() {
+                let def = status_schema["$defs"]
+                    .as_object()
+                    .unwrap()
+                    .values()
+                    .find(|def| {
+                        nested
+                            .keys()
+                            .all(|key| def["properties"].get(key).is_some())
+                    });
+                assert!(def.is_some(), "fields of status.{field} missing");
+            }
+        }
+
+        // Each event variant lists its own fields next to the common ones
+        let event_schema = &schema["event"];
+        for message in events() {
+            let kind = message.event.kind();
+            let variant = event_schema["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|variant| variant["properties"]["type"]["const"] == kind)
+                .unwrap_or_else(|| panic!("event {kind} missing"));
+            let message = serde_json::to_value(message).unwrap();
+            for field in message.as_object().unwrap().keys() {
+                assert!(
+                    event_schema["properties"].get(field).is_some()
+                        || variant["properties"].get(field).is_some(),
+                    "{kind}.{field} missing"
+                );
+            }
+        }
+    }
+
+    #[cfg(not(feature = "schema"))]
+    #[test]
+    fn test_schema_requires_feature() {
+        assert!(json_schema_pretty().is_err());
+    }
+}
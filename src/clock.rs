@@ -0,0 +1,174 @@
+use prometheus::IntCounter;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Steps back of the wall clock up to this (ms) are read jitter, not jumps
+pub const CLOCK_JUMP_TOLERANCE_MS: u64 = 1000;
+
+/// Source of wall-clock time for the rolling histories, in unix milliseconds
+pub trait Clock: Debug + Send + Sync {
+    fn now_millis(&self) -> u64;
+}
+
+/// The system's wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+/// A clock that only moves when told to (for tests and replays)
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    millis: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(millis: u64) -> Self {
+        Self {
+            millis: AtomicU64::new(millis),
+        }
+    }
+
+    pub fn set(&self, millis: u64) {
+        self.millis.store(millis, Ordering::SeqCst);
+    }
+
+    pub fn advance_secs(&self, secs: u64) {
+        self.millis.fetch_add(secs * 1000, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+/// Clock handle shared between the tracker and the poller
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall clock that never goes back: a read earlier than the latest one
+/// returned gives that one again, until the clock catches up. Steps back by
+/// more than [`CLOCK_JUMP_TOLERANCE_MS`] are counted as jumps.
+#[derive(Debug)]
+pub struct TimeSource {
+    clock: SharedClock,
+    /// Latest time returned
+    last: AtomicU64,
+    /// Latest reading of `clock`, to count each step back once
+    last_read: AtomicU64,
+    jumps: AtomicU64,
+    /// Exports the jumps once attached (`clock_jumps_detected_total`)
+    jump_counter: OnceLock<IntCounter>,
+}
+
+impl TimeSource {
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            last: AtomicU64::new(0),
+            last_read: AtomicU64::new(0),
+            jumps: AtomicU64::new(0),
+            jump_counter: OnceLock::new(),
+        }
+    }
+
+    /// Backward jumps seen so far
+    pub fn jumps(&self) -> u64 {
+        self.jumps.load(Ordering::SeqCst)
+    }
+
+    /// Count the jumps seen from now on in `counter` as well
+    pub fn report_jumps_to(&self, counter: IntCounter) {
+        let _ = self.jump_counter.set(counter);
+    }
+}
+
+impl Clock for TimeSource {
+    fn now_millis(&self) -> u64 {
+        let read = self.clock.now_millis();
+        let prev_read = self.last_read.swap(read, Ordering::SeqCst);
+        if prev_read.saturating_sub(read) > CLOCK_JUMP_TOLERANCE_MS {
+            warn!(
+                "System clock stepped back by {} ms; holding time until it catches up",
+                prev_read - read
+            );
+            self.jumps.fetch_add(1, Ordering::SeqCst);
+            if let Some(counter) = self.jump_counter.get() {
+                counter.inc();
+            }
+        }
+        let last = self.last.fetch_max(read, Ordering::SeqCst);
+        last.max(read)
+    }
+}
+
+/// The process-wide time source over the system's wall clock
+pub fn time_source() -> &'static Arc<TimeSource> {
+    static TIME_SOURCE: OnceLock<Arc<TimeSource>> = OnceLock::new();
+    TIME_SOURCE.get_or_init(|| Arc::new(TimeSource::new(Arc::new(SystemClock))))
+}
+
+/// The default clock: system wall-clock time through the process-wide
+/// [`TimeSource`], so it never goes back
+pub fn system_clock() -> SharedClock {
+    time_source().clone()
+}
+
+/// Current unix time in seconds from the process-wide [`TimeSource`]
+pub fn unix_now() -> u64 {
+    time_source().now_millis() / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripted(start: u64) -> (TimeSource, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(start));
+        (TimeSource::new(clock.clone()), clock)
+    }
+
+    #[test]
+    fn test_forward_jump_is_followed() {
+        let (time, clock) = scripted(1_700_000_000_000);
+        assert_eq!(time.now_millis(), 1_700_000_000_000);
+        clock.advance_secs(3600);
+        assert_eq!(time.now_millis(), 1_700_003_600_000);
+        assert_eq!(time.jumps(), 0);
+    }
+
+    #[test]
+    fn test_backward_jump_is_clamped_and_counted() {
+        let (time, clock) = scripted(1_700_000_000_000);
+        let counter = IntCounter::new("clock_jumps_detected_total", "test").unwrap();
+        time.report_jumps_to(counter.clone());
+        assert_eq!(time.now_millis(), 1_700_000_000_000);
+
+        // Jitter within the tolerance is clamped but not a jump
+        clock.set(1_699_999_999_500);
+        assert_eq!(time.now_millis(), 1_700_000_000_000);
+        assert_eq!(time.jumps(), 0);
+
+        // A 30s step back holds the time, counted once while catching up
+        clock.set(1_699_999_970_000);
+        assert_eq!(time.now_millis(), 1_700_000_000_000);
+        clock.advance_secs(10);
+        assert_eq!(time.now_millis(), 1_700_000_000_000);
+        assert_eq!((time.jumps(), counter.get()), (1, 1));
+
+        clock.set(1_700_000_005_000);
+        assert_eq!(time.now_millis(), 1_700_000_005_000);
+        assert_eq!(time.jumps(), 1);
+    }
+}
@@ -0,0 +1,494 @@
+use crate::metrics::Metrics;
+use crate::rpc::{ClusterScan, RpcClient, RpcVoteAccounts};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Epochs of cluster averages kept for the epoch summaries
+const CLUSTER_AVERAGE_EPOCHS: usize = 4;
+
+/// Deepest rank of `--cluster-credits-ranks`, the bound of the heap kept
+/// while a cluster-wide response streams by
+pub const MAX_CREDITS_RANK: usize = 1000;
+
+/// Credits this epoch of the best vote accounts in a cluster-wide
+/// `getVoteAccounts`, without keeping the accounts themselves
+#[derive(Debug, Clone)]
+pub struct TopCredits {
+    capacity: usize,
+    /// Min-heap of the best (epoch, credits) of the latest epochCredits
+    /// entries seen; the worst of them is the first to go
+    heap: BinaryHeap<Reverse<(u64, u64)>>,
+}
+
+impl Default for TopCredits {
+    fn default() -> Self {
+        Self::new(MAX_CREDITS_RANK)
+    }
+}
+
+impl TopCredits {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            heap: BinaryHeap::with_capacity(capacity + 1),
+        }
+    }
+
+    /// Add a vote account by its `epochCredits`
+    pub fn push(&mut self, epoch_credits: &[(u64, u64, u64)]) {
+        if let Some(&(epoch, credits, previous)) = epoch_credits.iter().max_by_key(|e| e.0) {
+            self.insert((epoch, credits.saturating_sub(previous)));
+        }
+    }
+
+    /// Add the accounts of another list
+    pub fn merge(&mut self, other: TopCredits) {
+        for Reverse(entry) in other.heap {
+            self.insert(entry);
+        }
+    }
+
+    fn insert(&mut self, entry: (u64, u64)) {
+        self.heap.push(Reverse(entry));
+        if self.heap.len() > self.capacity {
+            self.heap.pop();
+        }
+    }
+
+    /// Latest epoch among the accounts
+    pub fn epoch(&self) -> Option<u64> {
+        self.heap.iter().map(|Reverse((epoch, _))| *epoch).max()
+    }
+
+    /// Credits this epoch of the `n`th best account (1 is the best); 0 if
+    /// it has not voted in the latest epoch yet, None if there are fewer
+    /// than `n` accounts
+    pub fn rank(&self, n: usize) -> Option<u64> {
+        let latest = self.epoch()?;
+        let (epoch, credits) = *self.ranked().get(n.checked_sub(1)?)?;
+        Some(if epoch == latest { credits } else { 0 })
+    }
+
+    /// (epoch, credits) from the best account down
+    fn ranked(&self) -> Vec<(u64, u64)> {
+        let mut ranked: Vec<(u64, u64)> = self.heap.iter().map(|Reverse(entry)| *entry).collect();
+        ranked.sort_unstable_by(|a, b| b.cmp(a));
+        ranked
+    }
+}
+
+impl PartialEq for TopCredits {
+    fn eq(&self, other: &Self) -> bool {
+        self.capacity == other.capacity && self.ranked() == other.ranked()
+    }
+}
+
+impl Eq for TopCredits {}
+
+/// Stake-weighted sum of the credits this epoch of a list of vote accounts,
+/// folded one account at a time. An account that has not voted in the latest
+/// epoch yet weighs in with 0 credits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StakeWeightedCredits {
+    /// Latest epoch among the accounts
+    epoch: Option<u64>,
+    /// Sum of stake × credits over the accounts in `epoch`
+    weighted: u128,
+    /// Stake of all the accounts
+    stake: u128,
+}
+
+impl StakeWeightedCredits {
+    /// Add a vote account by its stake and `epochCredits`
+    pub fn push(&mut self, stake: u64, epoch_credits: &[(u64, u64, u64)]) {
+        self.stake += stake as u128;
+        let Some((epoch, credits)) = latest_credits(epoch_credits) else {
+            return;
+        };
+        // A newer epoch starts the sum over: the accounts seen so far have
+        // no credits in it
+        if self.epoch < Some(epoch) {
+            self.epoch = Some(epoch);
+            self.weighted = 0;
+        }
+        if self.epoch == Some(epoch) {
+            self.weighted += stake as u128 * credits as u128;
+        }
+    }
+
+    /// Take out an account added before
+    pub fn remove(&mut self, stake: u64, epoch_credits: &[(u64, u64, u64)]) {
+        self.stake = self.stake.saturating_sub(stake as u128);
+        if let Some((epoch, credits)) = latest_credits(epoch_credits) {
+            if self.epoch == Some(epoch) {
+                self.weighted = self
+                    .weighted
+                    .saturating_sub(stake as u128 * credits as u128);
+            }
+        }
+    }
+
+    /// Latest epoch among the accounts
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    /// Stake-weighted mean credits this epoch (None without stake)
+    pub fn mean(&self) -> Option<f64> {
+        (self.stake > 0).then(|| self.weighted as f64 / self.stake as f64)
+    }
+}
+
+/// Epoch and credits in it of the newest `epochCredits` entry
+fn latest_credits(epoch_credits: &[(u64, u64, u64)]) -> Option<(u64, u64)> {
+    epoch_credits
+        .iter()
+        .max_by_key(|e| e.0)
+        .map(|&(epoch, credits, previous)| (epoch, credits.saturating_sub(previous)))
+}
+
+/// The own credits this epoch against the stake-weighted mean of the current
+/// validators, as of the last cluster scan in the epoch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClusterAverage {
+    pub epoch: u64,
+    /// Stake-weighted mean credits this epoch of the current validators
+    pub stake_weighted_mean: f64,
+    /// Own credits this epoch over the mean (None while the mean is 0)
+    pub ratio: Option<f64>,
+    /// Whether the own validator was left out of the mean
+    pub excludes_self: bool,
+}
+
+impl ClusterAverage {
+    /// Compare the own account in `own` (the unfiltered `getVoteAccounts`
+    /// or one filtered to it) against `cluster`, the current validators.
+    /// None if the own account is not in the cluster's epoch.
+    pub fn compute(
+        cluster: &StakeWeightedCredits,
+        own: &RpcVoteAccounts,
+        vote_pubkey: &str,
+        exclude_self: bool,
+    ) -> Option<Self> {
+        let (account, delinquent) = own.find(vote_pubkey)?;
+        let (epoch, credits) = latest_credits(&account.epoch_credits)?;
+        if cluster.epoch() != Some(epoch) {
+            return None;
+        }
+        let mut baseline = cluster.clone();
+        // A delinquent account is not part of the current validators anyway
+        if exclude_self && !delinquent {
+            baseline.remove(account.activated_stake, &account.epoch_credits);
+        }
+        let mean = baseline.mean()?;
+        Some(Self {
+            epoch,
+            stake_weighted_mean: mean,
+            ratio: (mean > 0.0).then(|| credits as f64 / mean),
+            excludes_self: exclude_self,
+        })
+    }
+}
+
+impl fmt::Display for ClusterAverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ratio) = self.ratio {
+            write!(f, "{:+.2}% vs ", (ratio - 1.0) * 100.0)?;
+        }
+        write!(
+            f,
+            "the stake-weighted cluster mean of {:.0} credits",
+            self.stake_weighted_mean
+        )?;
+        if self.excludes_self {
+            write!(f, " (self excluded)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The latest cluster average of the last few epochs, shared between the
+/// cluster scan and the data source that writes the epoch summaries
+#[derive(Debug, Default)]
+pub struct ClusterAverages {
+    by_epoch: Mutex<BTreeMap<u64, ClusterAverage>>,
+}
+
+impl ClusterAverages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `average` as the latest of its epoch
+    pub fn record(&self, average: ClusterAverage) {
+        let mut by_epoch = self.by_epoch.lock().unwrap_or_else(|e| e.into_inner());
+        by_epoch.insert(average.epoch, average);
+        while by_epoch.len() > CLUSTER_AVERAGE_EPOCHS {
+            by_epoch.pop_first();
+        }
+    }
+
+    /// The latest average of `epoch`
+    pub fn for_epoch(&self, epoch: u64) -> Option<ClusterAverage> {
+        let by_epoch = self.by_epoch.lock().unwrap_or_else(|e| e.into_inner());
+        by_epoch.get(&epoch).copied()
+    }
+}
+
+/// What the cluster scans compare the own validator by
+#[derive(Debug, Clone)]
+pub struct ClusterComparison {
+    pub vote_pubkey: String,
+    /// Ranks to export the credits of and the gap to
+    pub ranks: Vec<usize>,
+    /// Leave the own validator out of the stake-weighted mean
+    pub exclude_self: bool,
+    /// Receives the cluster average of every scan
+    pub averages: Arc<ClusterAverages>,
+}
+
+impl ClusterComparison {
+    /// Export the ranks and the cluster average from `scan`, with the own
+    /// account looked up in `own` (no average without it)
+    pub fn export(&self, scan: &ClusterScan, own: Option<&RpcVoteAccounts>, metrics: &Metrics) {
+        export_credits_ranks(metrics, &scan.top_credits, &self.ranks);
+        let Some(own) = own else {
+            return;
+        };
+        let Some(average) = ClusterAverage::compute(
+            &scan.current_credits,
+            own,
+            &self.vote_pubkey,
+            self.exclude_self,
+        ) else {
+            return;
+        };
+        metrics.set_cluster_average(&average);
+        self.averages.record(average);
+    }
+}
+
+/// Export the credits of the ranks in `ranks` and the gap to each from the
+/// own epoch credits exported by the data source. The gap is left out while
+/// the data source is in another epoch than the cluster.
+pub fn export_credits_ranks(metrics: &Metrics, top: &TopCredits, ranks: &[usize]) {
+    let own_epoch = metrics.epoch.get() as u64;
+    let own_credits = metrics.total_epoch_credits.get();
+    for &n in ranks {
+        let Some(credits) = top.rank(n) else {
+            continue;
+        };
+        let label = n.to_string();
+        metrics
+            .cluster_credits_rank
+            .with_label_values(&[&label])
+            .set(credits as i64);
+        if top.epoch() == Some(own_epoch) {
+            metrics
+                .credits_gap_to_rank
+                .with_label_values(&[&label])
+                .set(credits as i64 - own_credits);
+        }
+    }
+}
+
+/// Fetch the cluster's current and delinquent stake once and export it,
+/// with the own validator compared against the cluster. The own account is
+/// fetched on its own, the scan doesn't keep the accounts.
+pub async fn scan_cluster_health<R: RpcClient>(
+    rpc: &R,
+    comparison: &ClusterComparison,
+    metrics: &Metrics,
+) -> Result<ClusterScan> {
+    let scan = rpc.get_cluster_scan().await?;
+    metrics.set_cluster_stake(&scan.stake);
+    let own = rpc.get_vote_accounts(Some(&comparison.vote_pubkey)).await;
+    comparison.export(&scan, own.as_ref().ok(), metrics);
+    own.context("own vote account for the cluster average")?;
+    Ok(scan)
+}
+
+/// Export the cluster stake (and comparison) metrics every `interval`
+/// forever (without a bulk watchlist, whose scan exports them too)
+pub async fn run_cluster_health_scan<R: RpcClient>(
+    rpc: &R,
+    comparison: &ClusterComparison,
+    interval: Duration,
+    metrics: &Metrics,
+) {
+    info!("Scanning cluster stake every {:?}", interval);
+    loop {
+        if let Err(e) = scan_cluster_health(rpc, comparison, metrics).await {
+            metrics.rpc_errors.inc();
+            warn!("Cluster health getVoteAccounts failed: {:#}", e);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poller::tests::{MutableTestRpc, vote_account};
+
+    fn comparison(ranks: &[usize], exclude_self: bool) -> ClusterComparison {
+        ClusterComparison {
+            vote_pubkey: "A".to_string(),
+            ranks: ranks.to_vec(),
+            exclude_self,
+            averages: Arc::new(ClusterAverages::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_exports_cluster_stake() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = MutableTestRpc::new(vec![
+            vote_account("A", vec![], 100),
+            vote_account("B", vec![], 100),
+            vote_account("C", vec![], 100),
+        ]);
+        rpc.accounts.lock().unwrap().delinquent = vec![vote_account("D", vec![], 50)];
+
+        let stake = scan_cluster_health(&rpc, &comparison(&[], false), &metrics)
+            .await
+            .unwrap()
+            .stake;
+        assert_eq!(stake.current, 3_000_000_000);
+        assert_eq!(stake.delinquent, 1_000_000_000);
+        assert_eq!(metrics.cluster_active_stake.get(), 3_000_000_000);
+        assert_eq!(metrics.cluster_delinquent_stake.get(), 1_000_000_000);
+        assert_eq!(metrics.cluster_delinquent_stake_ratio.get(), 0.25);
+    }
+
+    #[test]
+    fn test_top_credits_rank_selection() {
+        // Credits this epoch 0, 10, ..., 990 in shuffled order, kept to the top 10
+        let mut top = TopCredits::new(10);
+        for i in (0..100u64).map(|i| (i * 37) % 100) {
+            top.push(&[(811, 5000, 0), (812, 5000 + i * 10, 5000)]);
+        }
+        // An account without credits and one not voting this epoch yet
+        top.push(&[]);
+        top.push(&[(811, 9_000, 0)]);
+
+        assert_eq!(top.epoch(), Some(812));
+        assert_eq!(top.rank(1), Some(990));
+        assert_eq!(top.rank(2), Some(980));
+        assert_eq!(top.rank(10), Some(900));
+        assert_eq!(top.rank(11), None);
+        assert_eq!(top.rank(0), None);
+
+        // With room for it, the account behind by an epoch ranks last with 0
+        let mut top = TopCredits::new(3);
+        top.push(&[(812, 100, 0)]);
+        top.push(&[(811, 9_000, 0)]);
+        assert_eq!(top.rank(1), Some(100));
+        assert_eq!(top.rank(2), Some(0));
+        assert_eq!(top.rank(3), None);
+    }
+
+    #[tokio::test]
+    async fn test_scan_exports_ranks_and_gaps() {
+        let metrics = Metrics::new().unwrap();
+        let account = |pubkey: &str, credits: u64| {
+            vote_account(pubkey, vec![(812, 5000 + credits, 5000)], 100)
+        };
+        let rpc = MutableTestRpc::new(vec![
+            account("A", 6_000),
+            account("B", 9_000),
+            account("C", 7_000),
+        ]);
+        rpc.accounts.lock().unwrap().delinquent = vec![account("D", 8_000)];
+        metrics.epoch.set(812);
+        metrics.total_epoch_credits.set(7_500);
+
+        scan_cluster_health(&rpc, &comparison(&[1, 2, 10], false), &metrics)
+            .await
+            .unwrap();
+        let rank = |n: &str| metrics.cluster_credits_rank.with_label_values(&[n]).get();
+        let gap = |n: &str| metrics.credits_gap_to_rank.with_label_values(&[n]).get();
+        assert_eq!(rank("1"), 9_000);
+        assert_eq!(rank("2"), 8_000);
+        assert_eq!(gap("1"), 1_500);
+        assert_eq!(gap("2"), 500);
+        // Fewer accounts than the rank: not exported
+        assert_eq!(rank("10"), 0);
+
+        // No gap against a data source still in the previous epoch
+        metrics.epoch.set(811);
+        metrics.total_epoch_credits.set(100);
+        scan_cluster_health(&rpc, &comparison(&[1], false), &metrics)
+            .await
+            .unwrap();
+        assert_eq!(gap("1"), 1_500);
+    }
+
+    #[test]
+    fn test_stake_weighted_mean() {
+        let mut credits = StakeWeightedCredits::default();
+        assert_eq!(credits.mean(), None);
+        // Not voting this epoch yet: weighs in with 0 credits
+        credits.push(200, &[(811, 9_000, 0)]);
+        credits.push(100, &[(811, 5_000, 0), (812, 6_000, 5_000)]);
+        credits.push(300, &[(812, 7_000, 5_000)]);
+        credits.push(600, &[(812, 6_500, 5_000)]);
+        credits.push(0, &[(812, 9_999, 0)]);
+        assert_eq!(credits.epoch(), Some(812));
+        // (100 × 1000 + 300 × 2000 + 600 × 1500) / 1200
+        assert_eq!(credits.mean(), Some(1_600_000.0 / 1_200.0));
+
+        credits.remove(600, &[(812, 6_500, 5_000)]);
+        assert_eq!(credits.mean(), Some(700_000.0 / 600.0));
+    }
+
+    #[tokio::test]
+    async fn test_credits_vs_cluster_average() {
+        let metrics = Metrics::new().unwrap();
+        let account = |pubkey: &str, stake: u64, credits: u64| {
+            let mut account = vote_account(pubkey, vec![(812, 5_000 + credits, 5_000)], 100);
+            account.activated_stake = stake;
+            account
+        };
+        let rpc = MutableTestRpc::new(vec![
+            account("A", 1_000, 8_000),
+            account("B", 3_000, 6_000),
+            account("C", 1_000, 7_000),
+        ]);
+        // Delinquent validators are not part of the baseline
+        rpc.accounts.lock().unwrap().delinquent = vec![account("D", 5_000, 0)];
+
+        // (8000 + 3 × 6000 + 7000) / 5
+        let with_self = comparison(&[], false);
+        scan_cluster_health(&rpc, &with_self, &metrics)
+            .await
+            .unwrap();
+        assert_eq!(metrics.cluster_credits_stake_weighted_mean.get(), 6_600.0);
+        assert_eq!(metrics.credits_vs_cluster_ratio.get(), 8_000.0 / 6_600.0);
+
+        // (3 × 6000 + 7000) / 4
+        let without_self = comparison(&[], true);
+        scan_cluster_health(&rpc, &without_self, &metrics)
+            .await
+            .unwrap();
+        assert_eq!(metrics.cluster_credits_stake_weighted_mean.get(), 6_250.0);
+        assert_eq!(metrics.credits_vs_cluster_ratio.get(), 1.28);
+
+        let average = without_self.averages.for_epoch(812).unwrap();
+        assert!(average.excludes_self);
+        assert_eq!(average.ratio, Some(1.28));
+        assert_eq!(
+            average.to_string(),
+            "+28.00% vs the stake-weighted cluster mean of 6250 credits (self excluded)"
+        );
+        assert_eq!(without_self.averages.for_epoch(811), None);
+    }
+}
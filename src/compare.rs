@@ -0,0 +1,306 @@
+use crate::credits::CreditsModel;
+use crate::poller::{AccountSnapshot, snapshot_from_vote_account};
+use crate::report::format_percent;
+use crate::rpc::{RpcClient, RpcVoteAccount};
+use crate::ws::SLOTS_PER_EPOCH;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Completed epochs shown per account
+pub const COMPARE_HISTORY_EPOCHS: usize = 5;
+
+/// Width of the row labels in the text output
+const LABEL_WIDTH: usize = 20;
+
+/// Credits earned in one completed epoch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EpochCreditsEntry {
+    pub epoch: u64,
+    pub credits: u64,
+    /// Credits / max credits of a full epoch
+    pub efficiency: f64,
+}
+
+/// One vote account as shown by `compare`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccountComparison {
+    pub vote_pubkey: String,
+    pub delinquent: bool,
+    pub epoch: u64,
+    pub credits_this_epoch: u64,
+    pub expected_max_rooted: u64,
+    /// Credits / rooted expectation this epoch
+    pub efficiency: f64,
+    pub last_vote: u64,
+    /// Slots between the last vote and the tip
+    pub last_vote_distance: u64,
+    /// Last completed epochs, oldest first
+    pub history: Vec<EpochCreditsEntry>,
+}
+
+impl AccountComparison {
+    pub fn new(
+        account: &RpcVoteAccount,
+        delinquent: bool,
+        tip_slot: u64,
+        model: CreditsModel,
+    ) -> Self {
+        let snapshot = snapshot_from_vote_account(account, delinquent, model);
+        Self::from_snapshot(&snapshot, account, tip_slot)
+    }
+
+    fn from_snapshot(snapshot: &AccountSnapshot, account: &RpcVoteAccount, tip_slot: u64) -> Self {
+        let epoch = snapshot.epoch_info.epoch;
+        let full_epoch = snapshot.credits_model.expected_credits(SLOTS_PER_EPOCH);
+        let completed: Vec<_> = account
+            .epoch_credits
+            .iter()
+            .filter(|(e, _, _)| *e < epoch)
+            .collect();
+        let history = completed[completed.len().saturating_sub(COMPARE_HISTORY_EPOCHS)..]
+            .iter()
+            .map(|(e, credits, previous)| {
+                let credits = credits.saturating_sub(*previous);
+                EpochCreditsEntry {
+                    epoch: *e,
+                    credits,
+                    efficiency: credits as f64 / full_epoch as f64,
+                }
+            })
+            .collect();
+
+        Self {
+            vote_pubkey: snapshot.vote_pubkey.clone(),
+            delinquent: snapshot.delinquent,
+            epoch,
+            credits_this_epoch: snapshot.credits_this_epoch,
+            expected_max_rooted: snapshot.expected_max_rooted(),
+            efficiency: snapshot.efficiency(),
+            last_vote: snapshot.last_vote,
+            last_vote_distance: tip_slot.saturating_sub(snapshot.last_vote),
+            history,
+        }
+    }
+}
+
+/// Side-by-side view of vote accounts at one tip slot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub tip_slot: u64,
+    pub accounts: Vec<AccountComparison>,
+}
+
+/// Fetch each vote account and the tip, and build the comparison
+pub async fn fetch_comparison<R: RpcClient>(
+    rpc: &R,
+    vote_pubkeys: &[String],
+    model: CreditsModel,
+) -> Result<Comparison> {
+    let tip_slot = rpc.get_slot().await?;
+    let mut accounts = Vec::with_capacity(vote_pubkeys.len());
+    for pubkey in vote_pubkeys {
+        let found = rpc.get_vote_accounts(Some(pubkey)).await?;
+        let (account, delinquent) = found
+            .find(pubkey)
+            .ok_or_else(|| anyhow!("Vote account {} not found in getVoteAccounts", pubkey))?;
+        accounts.push(AccountComparison::new(account, delinquent, tip_slot, model));
+    }
+    Ok(Comparison { tip_slot, accounts })
+}
+
+/// Format the comparison as a table with one column per account
+pub fn format_comparison(comparison: &Comparison) -> String {
+    let accounts = &comparison.accounts;
+    let width = accounts
+        .iter()
+        .map(|a| a.vote_pubkey.len())
+        .max()
+        .unwrap_or(0)
+        .max(12);
+
+    let mut out = String::new();
+    let mut row = |label: &str, values: Vec<String>| {
+        let _ = write!(out, "{:<LABEL_WIDTH$}", label);
+        for value in values {
+            let _ = write!(out, "  {:>width$}", value);
+        }
+        out.push('\n');
+    };
+
+    row("", accounts.iter().map(|a| a.vote_pubkey.clone()).collect());
+    row(
+        "Delinquent",
+        accounts
+            .iter()
+            .map(|a| if a.delinquent { "yes" } else { "no" }.to_string())
+            .collect(),
+    );
+    row(
+        "Epoch",
+        accounts.iter().map(|a| a.epoch.to_string()).collect(),
+    );
+    row(
+        "Credits this epoch",
+        accounts
+            .iter()
+            .map(|a| a.credits_this_epoch.to_string())
+            .collect(),
+    );
+    row(
+        "Expected (rooted)",
+        accounts
+            .iter()
+            .map(|a| a.expected_max_rooted.to_string())
+            .collect(),
+    );
+    row(
+        "Efficiency",
+        accounts
+            .iter()
+            .map(|a| format_percent(a.efficiency))
+            .collect(),
+    );
+    row(
+        "Last vote distance",
+        accounts
+            .iter()
+            .map(|a| format!("{} slots", a.last_vote_distance))
+            .collect(),
+    );
+
+    // One row per completed epoch any of the accounts has credits for
+    let epochs: BTreeSet<u64> = accounts
+        .iter()
+        .flat_map(|a| a.history.iter().map(|h| h.epoch))
+        .collect();
+    for epoch in epochs {
+        row(
+            &format!("Epoch {}", epoch),
+            accounts
+                .iter()
+                .map(|a| {
+                    a.history
+                        .iter()
+                        .find(|h| h.epoch == epoch)
+                        .map(|h| format!("{} ({})", h.credits, format_percent(h.efficiency)))
+                        .unwrap_or_else(|| "-".to_string())
+                })
+                .collect(),
+        );
+    }
+
+    let _ = write!(out, "Tip slot: {}", comparison.tip_slot);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::RpcVoteAccounts;
+
+    fn account(
+        pubkey: &str,
+        epoch_credits: Vec<(u64, u64, u64)>,
+        root_slot: u64,
+    ) -> RpcVoteAccount {
+        RpcVoteAccount {
+            vote_pubkey: pubkey.to_string(),
+            node_pubkey: String::new(),
+            activated_stake: 0,
+            commission: 0,
+            epoch_vote_account: true,
+            epoch_credits,
+            last_vote: root_slot + 32,
+            root_slot,
+        }
+    }
+
+    fn comparison() -> Comparison {
+        let full = 16 * SLOTS_PER_EPOCH;
+        // A has 7 completed epochs (only the last 5 are shown), B only 2
+        let history_a: Vec<_> = (805..=811)
+            .map(|e| (e, (e - 804) * full, (e - 805) * full))
+            .collect();
+        let a = account("A", history_a, 812 * SLOTS_PER_EPOCH + 999);
+        let b = account(
+            "B",
+            vec![
+                (810, full / 2, 0),
+                (811, full, full / 2),
+                (812, full + 8000, full),
+            ],
+            812 * SLOTS_PER_EPOCH + 999,
+        );
+        let accounts = RpcVoteAccounts {
+            current: vec![a],
+            delinquent: vec![b],
+        };
+        let tip_slot = 812 * SLOTS_PER_EPOCH + 1040;
+        Comparison {
+            tip_slot,
+            accounts: ["A", "B"]
+                .iter()
+                .map(|pubkey| {
+                    let (account, delinquent) = accounts.find(pubkey).unwrap();
+                    AccountComparison::new(account, delinquent, tip_slot, CreditsModel::default())
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_account_comparison() {
+        let comparison = comparison();
+        let a = &comparison.accounts[0];
+        // No entry for 812 yet: the root's epoch with zero credits
+        assert_eq!(a.epoch, 812);
+        assert_eq!(a.credits_this_epoch, 0);
+        assert_eq!(a.expected_max_rooted, 16_000);
+        assert_eq!(a.last_vote_distance, 9);
+        let epochs: Vec<u64> = a.history.iter().map(|h| h.epoch).collect();
+        assert_eq!(epochs, vec![807, 808, 809, 810, 811]);
+        assert!(a.history.iter().all(|h| h.efficiency == 1.0));
+
+        let b = &comparison.accounts[1];
+        assert!(b.delinquent);
+        assert_eq!(b.credits_this_epoch, 8000);
+        assert_eq!(b.efficiency, 0.5);
+        assert_eq!(b.history.len(), 2);
+        assert_eq!(b.history[0].efficiency, 0.5);
+    }
+
+    #[test]
+    fn test_format_comparison() {
+        let text = format_comparison(&comparison());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0].split_whitespace().collect::<Vec<_>>(),
+            vec!["A", "B"]
+        );
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<_>>(),
+            vec!["Delinquent", "no", "yes"]
+        );
+        assert_eq!(
+            lines[5].split_whitespace().collect::<Vec<_>>(),
+            vec!["Efficiency", "0.00%", "50.00%"]
+        );
+        assert!(text.contains("Epoch 807"));
+        let epoch_809 = lines.iter().find(|l| l.starts_with("Epoch 809")).unwrap();
+        assert!(epoch_809.trim_end().ends_with("-"));
+        let epoch_810 = lines.iter().find(|l| l.starts_with("Epoch 810")).unwrap();
+        assert!(epoch_810.contains("(50.00%)"));
+        assert!(text.ends_with(&format!("Tip slot: {}", 812 * SLOTS_PER_EPOCH + 1040)));
+    }
+
+    #[test]
+    fn test_comparison_serializes_to_json() {
+        let json = serde_json::to_value(comparison()).unwrap();
+        assert_eq!(json["accounts"][1]["vote_pubkey"], "B");
+        assert_eq!(json["accounts"][1]["credits_this_epoch"], 8000);
+        assert_eq!(json["accounts"][0]["history"].as_array().unwrap().len(), 5);
+    }
+}
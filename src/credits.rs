@@ -0,0 +1,159 @@
+/// Maximum credits per slot (TVC: 16 for fastest vote, 0 for slowest)
+pub const MAX_CREDITS_PER_SLOT: u64 = 16;
+
+/// How vote credits are awarded per rooted slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreditsModel {
+    /// Credits for a vote landing with the minimum latency of 1 slot
+    /// (16 with TVC, 1 on clusters where TVC is not active)
+    pub max_credits_per_slot: u64,
+}
+
+impl Default for CreditsModel {
+    fn default() -> Self {
+        Self::new(MAX_CREDITS_PER_SLOT)
+    }
+}
+
+impl CreditsModel {
+    pub fn new(max_credits_per_slot: u64) -> Self {
+        Self {
+            max_credits_per_slot,
+        }
+    }
+
+    /// Maximum credits for a number of rooted slots
+    pub fn expected_credits(&self, slots: u64) -> u64 {
+        slots * self.max_credits_per_slot
+    }
+
+    /// Credits earned by a vote with the given latency: latency 1 earns the
+    /// maximum, each further slot one less, down to 0 at max + 1. A latency of
+    /// 0 (not recorded by older vote programs) counts as the fastest.
+    pub fn credits_from_latency(&self, latency: u32) -> u64 {
+        (self.max_credits_per_slot + 1)
+            .saturating_sub(latency as u64)
+            .min(self.max_credits_per_slot)
+    }
+
+    /// Credits inferred for a vote without a recorded latency, from the slots
+    /// between the vote and the slot it was first seen at, one credit less per
+    /// slot. Errs low by a credit: a vote seen at the next slot landed with
+    /// latency 1 at best.
+    pub fn credits_from_gap(&self, gap: u64) -> u64 {
+        self.max_credits_per_slot.saturating_sub(gap)
+    }
+
+    /// Average latency in slots for an average of credits per slot, the
+    /// inverse of [`CreditsModel::credits_from_latency`] (max + 1 - credits),
+    /// within the possible latencies 1 to max + 1
+    pub fn implied_latency(&self, credits_per_slot: f64) -> f64 {
+        let max = self.max_credits_per_slot as f64;
+        max + 1.0 - credits_per_slot.clamp(0.0, max)
+    }
+
+    /// Credits at the end of an epoch of `slots_in_epoch` slots: `credits`
+    /// so far plus `credits_per_slot` for each of the `remaining_slots`,
+    /// capped at the most the epoch can earn (but not below `credits`)
+    pub fn projected_credits(
+        &self,
+        credits: u64,
+        credits_per_slot: f64,
+        remaining_slots: u64,
+        slots_in_epoch: u64,
+    ) -> u64 {
+        let rate = credits_per_slot.clamp(0.0, self.max_credits_per_slot as f64);
+        let projected = credits.saturating_add((rate * remaining_slots as f64) as u64);
+        projected.min(self.expected_credits(slots_in_epoch).max(credits))
+    }
+}
+
+/// [`CreditsModel::credits_from_latency`] under TVC
+pub fn credits_from_latency(latency: u32) -> u64 {
+    CreditsModel::default().credits_from_latency(latency)
+}
+
+/// [`CreditsModel::credits_from_gap`] under TVC
+pub fn credits_from_gap(gap: u64) -> u64 {
+    CreditsModel::default().credits_from_gap(gap)
+}
+
+/// [`CreditsModel::implied_latency`] under TVC
+pub fn implied_latency(credits_per_slot: f64) -> f64 {
+    CreditsModel::default().implied_latency(credits_per_slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credits_from_latency_boundaries() {
+        assert_eq!(credits_from_latency(0), 16);
+        assert_eq!(credits_from_latency(1), 16);
+        assert_eq!(credits_from_latency(2), 15);
+        assert_eq!(credits_from_latency(16), 1);
+        assert_eq!(credits_from_latency(17), 0);
+        assert_eq!(credits_from_latency(18), 0);
+        assert_eq!(credits_from_latency(u32::MAX), 0);
+    }
+
+    #[test]
+    fn test_credits_from_latency_every_latency() {
+        for latency in 1..=17u32 {
+            assert_eq!(credits_from_latency(latency), 17 - latency as u64);
+        }
+    }
+
+    #[test]
+    fn test_credits_from_gap_boundaries() {
+        assert_eq!(credits_from_gap(0), 16);
+        assert_eq!(credits_from_gap(1), 15);
+        assert_eq!(credits_from_gap(16), 0);
+        assert_eq!(credits_from_gap(17), 0);
+        assert_eq!(credits_from_gap(u32::MAX as u64), 0);
+        assert_eq!(credits_from_gap(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_implied_latency_inverts_credits() {
+        for latency in 1..=17u32 {
+            let credits = credits_from_latency(latency) as f64;
+            assert_eq!(implied_latency(credits), latency as f64);
+        }
+        assert_eq!(implied_latency(15.5), 1.5);
+        // Out of range averages are clamped to the possible latencies
+        assert_eq!(implied_latency(16.5), 1.0);
+        assert_eq!(implied_latency(-1.0), 17.0);
+    }
+
+    #[test]
+    fn test_pre_tvc_model() {
+        let model = CreditsModel::new(1);
+        assert_eq!(model.credits_from_latency(0), 1);
+        assert_eq!(model.credits_from_latency(1), 1);
+        assert_eq!(model.credits_from_latency(2), 0);
+        assert_eq!(model.credits_from_latency(u32::MAX), 0);
+        assert_eq!(model.credits_from_gap(0), 1);
+        assert_eq!(model.credits_from_gap(1), 0);
+        assert_eq!(model.implied_latency(1.0), 1.0);
+        assert_eq!(model.implied_latency(0.0), 2.0);
+        assert_eq!(model.expected_credits(10), 10);
+    }
+
+    #[test]
+    fn test_projected_credits_stay_under_the_ceiling() {
+        let model = CreditsModel::default();
+        assert_eq!(model.projected_credits(1000, 15.0, 100, 432_000), 2500);
+        // A rate above the maximum counts as the maximum
+        assert_eq!(model.projected_credits(1000, 40.0, 100, 432_000), 2600);
+        assert_eq!(model.projected_credits(1000, f64::NAN, 100, 432_000), 1000);
+        // Credits beyond what the epoch's slots allow (a longer epoch than
+        // assumed) are kept, but nothing is added on top
+        assert_eq!(model.projected_credits(160_000, 16.0, 100, 10_000), 160_000);
+        assert_eq!(
+            model.projected_credits(6_000_000, 16.0, 100_000, 432_000),
+            6_912_000
+        );
+    }
+}
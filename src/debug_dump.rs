@@ -0,0 +1,198 @@
+use crate::clock::{SharedClock, system_clock};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Default number of dumps kept in the dump directory
+pub const DEFAULT_MAX_DUMPS: usize = 20;
+
+/// Dump files are named `dump-<unix millis>-<payload>.json`
+const DUMP_PREFIX: &str = "dump-";
+
+/// Latest raw payloads received from the RPC node, written out on demand by
+/// `POST /debug/dump`. Only the most recent payload of each kind is kept.
+#[derive(Debug)]
+pub struct DebugState {
+    dump_dir: PathBuf,
+    max_dumps: usize,
+    clock: SharedClock,
+    /// Raw text of the latest accountSubscribe notification
+    ws_notification: Mutex<Option<String>>,
+    /// Latest getVoteAccounts result for the tracked account
+    vote_accounts: Mutex<Option<String>>,
+}
+
+impl DebugState {
+    pub fn new(dump_dir: impl Into<PathBuf>, max_dumps: usize) -> Self {
+        Self {
+            dump_dir: dump_dir.into(),
+            max_dumps: max_dumps.max(1),
+            clock: system_clock(),
+            ws_notification: Mutex::new(None),
+            vote_accounts: Mutex::new(None),
+        }
+    }
+
+    /// Use a different clock for the dump timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn record_ws_notification(&self, text: &str) {
+        *self.ws_notification.lock().unwrap() = Some(text.to_string());
+    }
+
+    pub fn record_vote_accounts(&self, result: &Value) {
+        *self.vote_accounts.lock().unwrap() = Some(result.to_string());
+    }
+
+    /// Write the retained payloads to timestamped files, then delete the oldest
+    /// dumps beyond the limit. Returns the files written (none if nothing was
+    /// received yet).
+    pub fn dump(&self) -> Result<Vec<PathBuf>> {
+        let payloads = [
+            (
+                "ws_notification",
+                self.ws_notification.lock().unwrap().clone(),
+            ),
+            (
+                "get_vote_accounts",
+                self.vote_accounts.lock().unwrap().clone(),
+            ),
+        ];
+        if payloads.iter().all(|(_, payload)| payload.is_none()) {
+            return Ok(Vec::new());
+        }
+
+        std::fs::create_dir_all(&self.dump_dir).with_context(|| {
+            format!(
+                "Failed to create dump directory {}",
+                self.dump_dir.display()
+            )
+        })?;
+        let stamp = self.clock.now_millis();
+        let mut written = Vec::new();
+        for (name, payload) in payloads {
+            let Some(payload) = payload else { continue };
+            let path = self
+                .dump_dir
+                .join(format!("{DUMP_PREFIX}{stamp:013}-{name}.json"));
+            std::fs::write(&path, payload)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            written.push(path);
+        }
+
+        prune_dumps(&self.dump_dir, self.max_dumps)?;
+        Ok(written)
+    }
+}
+
+/// Delete the files of all but the newest `keep` dumps in `dir`
+fn prune_dumps(dir: &Path, keep: usize) -> Result<()> {
+    let mut dumps: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let stamp = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(DUMP_PREFIX))
+            .and_then(|rest| rest.split_once('-'))
+            .and_then(|(stamp, _)| stamp.parse().ok());
+        if let Some(stamp) = stamp {
+            dumps.entry(stamp).or_default().push(path);
+        }
+    }
+
+    while dumps.len() > keep {
+        if let Some((_, files)) = dumps.pop_first() {
+            for file in files {
+                std::fs::remove_file(&file)
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    /// Empty scratch directory for one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tvc_tracker-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_dump_writes_latest_payloads() {
+        let dir = scratch_dir("dump");
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let debug = DebugState::new(&dir, 5).with_clock(clock.clone());
+        assert!(debug.dump().unwrap().is_empty());
+
+        debug.record_ws_notification(r#"{"method":"accountNotification","n":1}"#);
+        debug.record_ws_notification(r#"{"method":"accountNotification","n":2}"#);
+        let files = debug.dump().unwrap();
+        assert_eq!(
+            files,
+            vec![dir.join("dump-1700000000000-ws_notification.json")]
+        );
+        assert!(
+            std::fs::read_to_string(&files[0])
+                .unwrap()
+                .contains(r#""n":2"#)
+        );
+
+        debug.record_vote_accounts(&serde_json::json!({ "current": [], "delinquent": [] }));
+        clock.advance_secs(1);
+        let files = debug.dump().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&files[1]).unwrap(),
+            r#"{"current":[],"delinquent":[]}"#
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dump_keeps_newest_dumps() {
+        let dir = scratch_dir("prune");
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let debug = DebugState::new(&dir, 2).with_clock(clock.clone());
+        debug.record_ws_notification("{}");
+        debug.record_vote_accounts(&serde_json::json!({}));
+
+        for _ in 0..4 {
+            debug.dump().unwrap();
+            clock.advance_secs(1);
+        }
+        // Unrelated files are left alone
+        std::fs::write(dir.join("notes.txt"), "keep me").unwrap();
+        debug.dump().unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "dump-1700000003000-get_vote_accounts.json",
+                "dump-1700000003000-ws_notification.json",
+                "dump-1700000004000-get_vote_accounts.json",
+                "dump-1700000004000-ws_notification.json",
+                "notes.txt",
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
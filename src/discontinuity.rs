@@ -0,0 +1,336 @@
+use crate::clock::{SharedClock, system_clock};
+use crate::maintenance::MaintenanceWindow;
+use crate::metrics::Metrics;
+use crate::ws::TrackerBaseline;
+
+use prometheus::IntCounterVec;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Discontinuities kept for `GET /discontinuities`
+pub const DISCONTINUITIES_CAPACITY: usize = 1000;
+
+/// File in --log-dir the discontinuities are appended to with --discontinuity-log
+pub const DISCONTINUITY_LOG_FILE: &str = "discontinuities.jsonl";
+
+/// A change of state that breaks the continuity of the tracked numbers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiscontinuityEvent {
+    /// The vote account moved into a new epoch; the per-epoch numbers start over
+    EpochRollover {
+        from_epoch: u64,
+        to_epoch: Option<u64>,
+        /// Credits earned and missed in the finished epoch
+        credits: u64,
+        missed: u64,
+    },
+    /// The WebSocket subscription was re-established; the slots rooted while
+    /// it was down are caught up as unobserved
+    WsReconnect {
+        /// The connection closed normally rather than failing
+        closed_normally: bool,
+    },
+    /// `POST /admin/reconcile` realigned the epoch credits and misses with a
+    /// fresh fetch
+    Reseed {
+        before: TrackerBaseline,
+        after: TrackerBaseline,
+    },
+    /// The tracker was restored from --state-file at startup. `before` is the
+    /// saved baseline, `after` includes the slots rooted while down.
+    StateRestore {
+        before: TrackerBaseline,
+        after: TrackerBaseline,
+    },
+    /// A maintenance window started, replacing the one in effect if any
+    MaintenanceStarted {
+        replaced: Option<MaintenanceWindow>,
+        window: MaintenanceWindow,
+    },
+    /// A maintenance window expired or was ended early
+    MaintenanceEnded {
+        window: MaintenanceWindow,
+        early: bool,
+    },
+    /// `POST /admin/reset` cleared the tracker and re-seeded it
+    Reset {
+        before: TrackerBaseline,
+        after: TrackerBaseline,
+    },
+}
+
+impl DiscontinuityEvent {
+    /// Every `kind` label of `tvc_discontinuities_total`
+    pub const KINDS: [&'static str; 7] = [
+        "epoch_rollover",
+        "ws_reconnect",
+        "reseed",
+        "state_restore",
+        "maintenance_started",
+        "maintenance_ended",
+        "reset",
+    ];
+
+    /// The `kind` of the JSON record and the metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::EpochRollover { .. } => "epoch_rollover",
+            Self::WsReconnect { .. } => "ws_reconnect",
+            Self::Reseed { .. } => "reseed",
+            Self::StateRestore { .. } => "state_restore",
+            Self::MaintenanceStarted { .. } => "maintenance_started",
+            Self::MaintenanceEnded { .. } => "maintenance_ended",
+            Self::Reset { .. } => "reset",
+        }
+    }
+}
+
+/// A discontinuity and when it happened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Discontinuity {
+    /// Unix millis
+    pub timestamp_ms: u64,
+    /// Epoch and root slot of the tracker when it happened (unknown for
+    /// maintenance windows)
+    pub epoch: Option<u64>,
+    pub slot: Option<u64>,
+    #[serde(flatten)]
+    pub event: DiscontinuityEvent,
+}
+
+/// Append-only record of the discontinuities since startup, the newest
+/// [`DISCONTINUITIES_CAPACITY`] in memory and all of them in the log file
+#[derive(Debug)]
+pub struct Discontinuities {
+    records: Mutex<VecDeque<Discontinuity>>,
+    log: Option<PathBuf>,
+    /// `tvc_discontinuities_total`
+    counter: Option<IntCounterVec>,
+    clock: SharedClock,
+}
+
+impl Default for Discontinuities {
+    fn default() -> Self {
+        Self {
+            records: Mutex::new(VecDeque::new()),
+            log: None,
+            counter: None,
+            clock: system_clock(),
+        }
+    }
+}
+
+impl Discontinuities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the discontinuities in `tvc_discontinuities_total`
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.counter = Some(metrics.discontinuities.clone());
+        self
+    }
+
+    /// Also append each one as a JSON line to [`DISCONTINUITY_LOG_FILE`] in `dir`
+    pub fn with_log_dir(mut self, dir: Option<&Path>) -> Self {
+        self.log = dir.map(|dir| dir.join(DISCONTINUITY_LOG_FILE));
+        self
+    }
+
+    /// Use `clock` for the timestamps instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record `event`, which happened at `epoch` and root `slot`
+    pub fn record(
+        &self,
+        epoch: Option<u64>,
+        slot: Option<u64>,
+        event: DiscontinuityEvent,
+    ) -> Discontinuity {
+        let record = Discontinuity {
+            timestamp_ms: self.clock.now_millis(),
+            epoch,
+            slot,
+            event,
+        };
+        if let Some(counter) = &self.counter {
+            counter.with_label_values(&[record.event.kind()]).inc();
+        }
+        if let Some(path) = &self.log {
+            if let Err(e) = append(path, &record) {
+                warn!("Failed to append to {}: {:#}", path.display(), e);
+            }
+        }
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == DISCONTINUITIES_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+        record
+    }
+
+    /// Record `event` at the tracker's epoch and root slot
+    pub fn record_at(
+        &self,
+        baseline: &TrackerBaseline,
+        event: DiscontinuityEvent,
+    ) -> Discontinuity {
+        self.record(baseline.epoch, baseline.root_slot, event)
+    }
+
+    /// The discontinuities kept, oldest first
+    pub fn recent(&self) -> Vec<Discontinuity> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().cloned().collect()
+    }
+}
+
+fn append(path: &Path, record: &Discontinuity) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    fn baseline(epoch: u64, root_slot: u64, epoch_credits: u64) -> TrackerBaseline {
+        TrackerBaseline {
+            epoch: Some(epoch),
+            root_slot: Some(root_slot),
+            epoch_credits,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_is_counted_and_kept() {
+        let metrics = Metrics::new().unwrap();
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let discontinuities = Discontinuities::new()
+            .with_metrics(&metrics)
+            .with_clock(clock.clone());
+
+        let record = discontinuities.record_at(
+            &baseline(500, 216_000_100, 64),
+            DiscontinuityEvent::Reset {
+                before: baseline(500, 216_000_100, 64),
+                after: baseline(500, 216_000_200, 0),
+            },
+        );
+        assert_eq!(record.timestamp_ms, 1_700_000_000_000);
+        assert_eq!(discontinuities.recent(), vec![record]);
+        assert_eq!(
+            metrics.discontinuities.with_label_values(&["reset"]).get(),
+            1
+        );
+        assert_eq!(
+            metrics.discontinuities.with_label_values(&["reseed"]).get(),
+            0
+        );
+
+        let json = serde_json::to_value(&discontinuities.recent()[0]).unwrap();
+        assert_eq!(json["kind"], "reset");
+        assert_eq!(json["epoch"], 500);
+        assert_eq!(json["slot"], 216_000_100);
+        assert_eq!(json["before"]["epoch_credits"], 64);
+        assert_eq!(json["after"]["root_slot"], 216_000_200);
+    }
+
+    #[test]
+    fn test_kinds_match_labels() {
+        let window = MaintenanceWindow {
+            reason: "upgrade".to_string(),
+            started_at_ms: 0,
+            ends_at_ms: 1,
+        };
+        let events = [
+            DiscontinuityEvent::EpochRollover {
+                from_epoch: 1,
+                to_epoch: Some(2),
+                credits: 0,
+                missed: 0,
+            },
+            DiscontinuityEvent::WsReconnect {
+                closed_normally: true,
+            },
+            DiscontinuityEvent::Reseed {
+                before: TrackerBaseline::default(),
+                after: TrackerBaseline::default(),
+            },
+            DiscontinuityEvent::StateRestore {
+                before: TrackerBaseline::default(),
+                after: TrackerBaseline::default(),
+            },
+            DiscontinuityEvent::MaintenanceStarted {
+                replaced: None,
+                window: window.clone(),
+            },
+            DiscontinuityEvent::MaintenanceEnded {
+                window,
+                early: false,
+            },
+            DiscontinuityEvent::Reset {
+                before: TrackerBaseline::default(),
+                after: TrackerBaseline::default(),
+            },
+        ];
+        for (event, kind) in events.iter().zip(DiscontinuityEvent::KINDS) {
+            assert_eq!(event.kind(), kind);
+            assert_eq!(serde_json::to_value(event).unwrap()["kind"], kind);
+        }
+    }
+
+    #[test]
+    fn test_list_is_bounded_and_log_keeps_everything() {
+        let dir = std::env::temp_dir().join(format!("tvc_discontinuities_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let discontinuities = Discontinuities::new().with_log_dir(Some(&dir));
+
+        let total = DISCONTINUITIES_CAPACITY + 5;
+        for slot in 0..total as u64 {
+            discontinuities.record(
+                Some(500),
+                Some(slot),
+                DiscontinuityEvent::WsReconnect {
+                    closed_normally: false,
+                },
+            );
+        }
+        let recent = discontinuities.recent();
+        assert_eq!(recent.len(), DISCONTINUITIES_CAPACITY);
+        assert_eq!(recent[0].slot, Some(5));
+        assert_eq!(recent.last().unwrap().slot, Some(total as u64 - 1));
+
+        let logged = fs::read_to_string(dir.join(DISCONTINUITY_LOG_FILE)).unwrap();
+        let lines: Vec<Discontinuity> = logged
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), total);
+        assert_eq!(lines[0].slot, Some(0));
+        assert_eq!(
+            lines[0].event,
+            DiscontinuityEvent::WsReconnect {
+                closed_normally: false
+            }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::cluster::ClusterAverage;
+use crate::ws::{EpochComparison, EpochSummary, SuspectedRestart};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Events buffered per receiver before the slowest one starts losing them
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Sending half of the event channel, cloned into the data paths
+pub type EventSender = broadcast::Sender<TrackerEvent>;
+
+/// Something worth reacting to, emitted by the data paths as it happens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TrackerEvent {
+    /// An epoch finished
+    EpochRollover {
+        epoch: u64,
+        credits: u64,
+        missed: u64,
+        /// Longest run of perfect slots in the epoch (WebSocket mode only)
+        perfect_slot_streak_max: Option<u64>,
+        /// First and last root slot tracked in the epoch
+        first_tracked_slot: Option<u64>,
+        last_tracked_slot: Option<u64>,
+        /// Change from the previous finished epoch (None for the first one seen)
+        comparison: Option<EpochComparison>,
+        /// Credits against the cluster's stake-weighted mean (None without a
+        /// cluster scan)
+        cluster: Option<ClusterAverage>,
+    },
+    /// The vote account entered or left the delinquent set (poll mode)
+    DelinquencyChanged { delinquent: bool },
+    /// Activated stake dropped by more than --stake-decrease-pct at an epoch
+    /// boundary (poll mode)
+    StakeDecreased { epoch: u64, delta_lamports: i64 },
+    /// Vote pattern that looks like a validator restart (WebSocket mode)
+    ValidatorRestart {
+        last_vote_slot: u64,
+        resumed_vote_slot: u64,
+    },
+}
+
+impl TrackerEvent {
+    /// Subject suffix of the event, same as its `type` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TrackerEvent::EpochRollover { .. } => "epoch_rollover",
+            TrackerEvent::DelinquencyChanged { .. } => "delinquency_changed",
+            TrackerEvent::StakeDecreased { .. } => "stake_decreased",
+            TrackerEvent::ValidatorRestart { .. } => "validator_restart",
+        }
+    }
+}
+
+impl From<&EpochSummary> for TrackerEvent {
+    fn from(summary: &EpochSummary) -> Self {
+        TrackerEvent::EpochRollover {
+            epoch: summary.epoch,
+            credits: summary.credits,
+            missed: summary.missed,
+            perfect_slot_streak_max: summary.perfect_slot_streak.map(|streak| streak.epoch_max),
+            first_tracked_slot: summary.first_tracked_slot,
+            last_tracked_slot: summary.last_tracked_slot,
+            comparison: summary.comparison,
+            cluster: summary.cluster,
+        }
+    }
+}
+
+impl From<&SuspectedRestart> for TrackerEvent {
+    fn from(restart: &SuspectedRestart) -> Self {
+        TrackerEvent::ValidatorRestart {
+            last_vote_slot: restart.last_vote_slot,
+            resumed_vote_slot: restart.resumed_vote_slot,
+        }
+    }
+}
+
+/// Create the event channel; receivers come from [`broadcast::Sender::subscribe`]
+pub fn event_channel() -> EventSender {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
+
+/// Send `event` if there is a channel. Never blocks; without receivers the
+/// event is dropped.
+pub fn emit(events: Option<&EventSender>, event: TrackerEvent) {
+    if let Some(events) = events {
+        let _ = events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_is_tagged_with_its_kind() {
+        let event = TrackerEvent::StakeDecreased {
+            epoch: 500,
+            delta_lamports: -1_000,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], event.kind());
+        assert_eq!(json["delta_lamports"], -1_000);
+    }
+
+    #[tokio::test]
+    async fn test_emit_without_receivers_is_dropped() {
+        emit(None, TrackerEvent::DelinquencyChanged { delinquent: true });
+
+        let events = event_channel();
+        emit(
+            Some(&events),
+            TrackerEvent::DelinquencyChanged { delinquent: true },
+        );
+        let mut rx = events.subscribe();
+        emit(
+            Some(&events),
+            TrackerEvent::DelinquencyChanged { delinquent: false },
+        );
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            TrackerEvent::DelinquencyChanged { delinquent: false }
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::clock::unix_now;
+use crate::config::Mode;
+use crate::metrics::Metrics;
+
+use anyhow::{Result, bail};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Data older than this (in seconds) stops the heartbeat in WS mode, where
+/// notifications normally arrive every few hundred milliseconds
+pub const DEFAULT_WS_HEARTBEAT_MAX_AGE_SECS: u64 = 120;
+
+/// Poll intervals without a successful poll that stop the heartbeat in poll mode
+const POLL_HEARTBEAT_MAX_AGE_INTERVALS: u64 = 3;
+
+/// Dead man's switch: GET a URL periodically while the data path is healthy
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    client: reqwest::Client,
+    url: String,
+    mode: Mode,
+    /// Age of the last successful poll or notification beyond which pings stop
+    max_age: Duration,
+}
+
+impl Heartbeat {
+    pub fn new(client: reqwest::Client, url: &str, mode: Mode, max_age: Duration) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+            mode,
+            max_age,
+        }
+    }
+
+    /// Default data age beyond which pings stop: a fixed allowance in WS mode,
+    /// a few missed polls in poll mode
+    pub fn default_max_age(mode: Mode, poll_interval: Duration) -> Duration {
+        match mode {
+            Mode::Ws => Duration::from_secs(DEFAULT_WS_HEARTBEAT_MAX_AGE_SECS),
+            Mode::Poll => poll_interval * POLL_HEARTBEAT_MAX_AGE_INTERVALS as u32,
+        }
+    }
+
+    /// Whether the data path delivered within `max_age` of `now` (unix seconds)
+    pub fn is_fresh(&self, metrics: &Metrics, now: u64) -> bool {
+        metrics
+            .last_data_update(self.mode)
+            .is_some_and(|last| now.saturating_sub(last) <= self.max_age.as_secs())
+    }
+
+    /// Ping if the data path is fresh at `now`. Failures are only counted and
+    /// logged; they never reach the data path.
+    pub async fn beat(&self, metrics: &Metrics, now: u64) {
+        if !self.is_fresh(metrics, now) {
+            debug!("Data path is stale, skipping heartbeat");
+            metrics.heartbeats.with_label_values(&["skipped"]).inc();
+            return;
+        }
+        match self.ping().await {
+            Ok(()) => metrics.heartbeats.with_label_values(&["success"]).inc(),
+            Err(e) => {
+                warn!("Heartbeat to {} failed: {:#}", self.url, e);
+                metrics.heartbeats.with_label_values(&["failure"]).inc();
+            }
+        }
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let status = self.client.get(&self.url).send().await?.status();
+        if !status.is_success() {
+            bail!("HTTP {}", status);
+        }
+        Ok(())
+    }
+}
+
+/// Ping every `interval` forever
+pub async fn run_heartbeat(heartbeat: Heartbeat, interval: Duration, metrics: &Metrics) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        heartbeat.beat(metrics, unix_now()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tokio::net::TcpListener;
+
+    const NOW: u64 = 1_700_000_000;
+
+    /// Count GETs to `/ping` on a local port, answering with `status`
+    async fn mock_snitch(status: StatusCode) -> (String, Arc<AtomicU64>) {
+        let pings = Arc::new(AtomicU64::new(0));
+        let counter = pings.clone();
+        let app = axum::Router::new().route(
+            "/ping",
+            axum::routing::get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                status
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/ping", addr), pings)
+    }
+
+    fn heartbeat(url: &str, mode: Mode) -> Heartbeat {
+        Heartbeat::new(reqwest::Client::new(), url, mode, Duration::from_secs(120))
+    }
+
+    #[tokio::test]
+    async fn test_pings_stop_when_data_goes_stale() {
+        let (url, pings) = mock_snitch(StatusCode::OK).await;
+        let metrics = Metrics::new().unwrap();
+        let heartbeat = heartbeat(&url, Mode::Ws);
+
+        // Nothing received yet
+        heartbeat.beat(&metrics, NOW).await;
+        assert_eq!(pings.load(Ordering::SeqCst), 0);
+
+        metrics.ws_last_message.set(NOW as i64);
+        heartbeat.beat(&metrics, NOW + 10).await;
+        heartbeat.beat(&metrics, NOW + 120).await;
+        assert_eq!(pings.load(Ordering::SeqCst), 2);
+
+        // Notifications stopped: the snitch stops hearing from us
+        heartbeat.beat(&metrics, NOW + 121).await;
+        assert_eq!(pings.load(Ordering::SeqCst), 2);
+
+        let count = |outcome: &str| metrics.heartbeats.with_label_values(&[outcome]).get();
+        assert_eq!(count("success"), 2);
+        assert_eq!(count("skipped"), 2);
+        assert_eq!(count("failure"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_pings_are_counted() {
+        let (url, pings) = mock_snitch(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let metrics = Metrics::new().unwrap();
+        metrics.rpc_last_success.set(NOW as i64);
+
+        heartbeat(&url, Mode::Poll).beat(&metrics, NOW).await;
+        assert_eq!(pings.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.heartbeats.with_label_values(&["failure"]).get(), 1);
+
+        // Unreachable URL
+        heartbeat("http://127.0.0.1:1/ping", Mode::Poll)
+            .beat(&metrics, NOW)
+            .await;
+        assert_eq!(metrics.heartbeats.with_label_values(&["failure"]).get(), 2);
+    }
+
+    #[test]
+    fn test_default_max_age() {
+        let interval = Duration::from_secs(60);
+        assert_eq!(
+            Heartbeat::default_max_age(Mode::Ws, interval),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            Heartbeat::default_max_age(Mode::Poll, interval),
+            Duration::from_secs(180)
+        );
+    }
+}
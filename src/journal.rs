@@ -0,0 +1,660 @@
+use crate::clock::ManualClock;
+use crate::credits::CreditsModel;
+use crate::ws::{ExpectedAnchor, UpdateResult, VoteTracker};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+/// Journal records buffered between the WS loop and the writer; records
+/// arriving while the buffer is full are dropped rather than waited for
+pub const JOURNAL_CHANNEL_CAPACITY: usize = 4096;
+
+/// Format version written to the header
+const JOURNAL_VERSION: u32 = 1;
+
+const MILLIS_PER_DAY: u64 = 24 * 3600 * 1000;
+
+/// When journal writes are forced to disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum JournalFsync {
+    /// After every record
+    #[default]
+    Always,
+    /// Only when a file is closed (daily rotation and shutdown)
+    Rotate,
+    /// Never; the OS decides when to write back
+    Never,
+}
+
+/// Tracker settings a replay needs to reproduce the live tracker
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JournalConfig {
+    pub max_credits_per_slot: u64,
+    pub exclude_catch_up: bool,
+    pub projection_alpha: f64,
+    pub expected_anchor: ExpectedAnchor,
+    pub tip_allowance_slots: u64,
+    #[serde(default)]
+    pub max_state_bytes: Option<usize>,
+}
+
+impl JournalConfig {
+    /// Fresh tracker with these settings on a manual clock
+    fn tracker(&self) -> (VoteTracker, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::default());
+        let tracker = VoteTracker::new(CreditsModel::new(self.max_credits_per_slot))
+            .with_projection_alpha(self.projection_alpha)
+            .with_exclude_catch_up(self.exclude_catch_up)
+            .with_expected_anchor(self.expected_anchor, self.tip_allowance_slots)
+            .with_max_state_bytes(self.max_state_bytes)
+            .with_clock(clock.clone());
+        (tracker, clock)
+    }
+}
+
+/// One `VoteTracker::process_update` call: its inputs and what it computed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalUpdate {
+    /// Tracker clock when the update was applied (unix millis)
+    pub t: u64,
+    pub context_slot: u64,
+    pub root_slot: Option<u64>,
+    pub epoch: Option<u64>,
+    pub epoch_credits: u64,
+    /// Credits earned since the previous record (filled in by the writer)
+    #[serde(default)]
+    pub credits_delta: u64,
+    /// The whole tower: (slot, confirmation count, latency)
+    pub votes: Vec<(u64, u32, Option<u32>)>,
+    /// Votes new in this update: (slot, latency)
+    pub new_votes: Vec<(u64, Option<u32>)>,
+    pub missed: u64,
+    /// Updates dropped right before this one because the buffer was full
+    /// (filled in by [`Journal::record`])
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub dropped_before: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl JournalUpdate {
+    pub fn new(
+        t: u64,
+        context_slot: u64,
+        votes: &[(u64, u32, Option<u32>)],
+        root_slot: Option<u64>,
+        epoch_credits: u64,
+        epoch: Option<u64>,
+        result: &UpdateResult,
+    ) -> Self {
+        let new_votes = result
+            .new_vote_slots
+            .iter()
+            .map(|slot| {
+                let latency = votes
+                    .iter()
+                    .find(|(s, _, _)| s == slot)
+                    .and_then(|(_, _, latency)| *latency);
+                (*slot, latency)
+            })
+            .collect();
+        Self {
+            t,
+            context_slot,
+            root_slot,
+            epoch,
+            epoch_credits,
+            credits_delta: 0,
+            votes: votes.to_vec(),
+            new_votes,
+            missed: result.missed_credits,
+            dropped_before: 0,
+        }
+    }
+
+    /// Apply the update to `tracker` as the live tracker did
+    fn replay(&self, tracker: &mut VoteTracker, clock: &ManualClock) -> UpdateResult {
+        clock.set(self.t);
+        tracker.process_update(
+            self.context_slot,
+            &self.votes,
+            self.root_slot,
+            self.epoch_credits,
+            self.epoch,
+        )
+    }
+}
+
+/// One line of a journal file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JournalRecord {
+    Header {
+        version: u32,
+        created_at: u64,
+        config: JournalConfig,
+    },
+    Update(JournalUpdate),
+    /// Written when the file is closed: the number of updates, the
+    /// checksum of the tracker state they replay to and the updates that
+    /// never made it into the file
+    Footer {
+        records: u64,
+        checksum: String,
+        #[serde(default)]
+        dropped: u64,
+    },
+}
+
+/// Checksum of the tracker snapshot and window history (FNV-1a over their JSON)
+pub fn tracker_checksum(tracker: &VoteTracker) -> String {
+    let state = serde_json::to_vec(&(tracker.snapshot(), tracker.history()))
+        .expect("tracker state serializes");
+    let hash = state.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Non-blocking handle to the journal writer thread
+#[derive(Debug)]
+pub struct Journal {
+    tx: Mutex<Option<mpsc::Sender<JournalUpdate>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    /// Updates dropped since the last one queued, carried by the next one
+    /// queued or, at shutdown, by the last footer
+    pending_drops: Arc<AtomicU64>,
+}
+
+impl Journal {
+    /// Start a writer appending to daily files in `dir`
+    pub fn start(
+        dir: impl Into<PathBuf>,
+        fsync: JournalFsync,
+        config: JournalConfig,
+    ) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create journal directory {}", dir.display()))?;
+        let (tx, mut rx) = mpsc::channel(JOURNAL_CHANNEL_CAPACITY);
+        let mut writer = JournalWriter::new(dir, fsync, config);
+        let pending_drops = Arc::new(AtomicU64::new(0));
+        let dropped_at_close = pending_drops.clone();
+        let handle = std::thread::Builder::new()
+            .name("journal-writer".to_string())
+            .spawn(move || {
+                while let Some(update) = rx.blocking_recv() {
+                    if let Err(e) = writer.write(update) {
+                        error!("Failed to write journal record: {:#}", e);
+                    }
+                }
+                if let Some(open) = &mut writer.open {
+                    open.dropped += dropped_at_close.swap(0, Ordering::Relaxed);
+                }
+                if let Err(e) = writer.close_file() {
+                    error!("Failed to close journal file: {:#}", e);
+                }
+            })
+            .context("Failed to start the journal writer")?;
+        Ok(Self {
+            tx: Mutex::new(Some(tx)),
+            writer: Mutex::new(Some(handle)),
+            pending_drops,
+        })
+    }
+
+    /// Queue an update without waiting. Returns false if it was dropped
+    /// (buffer full or journal closed); the next update queued records the
+    /// gap.
+    pub fn record(&self, mut update: JournalUpdate) -> bool {
+        let tx = self.tx.lock().unwrap();
+        let Some(tx) = tx.as_ref() else {
+            return false;
+        };
+        update.dropped_before = self.pending_drops.swap(0, Ordering::Relaxed);
+        let dropped_before = update.dropped_before;
+        match tx.try_send(update) {
+            Ok(()) => true,
+            Err(_) => {
+                self.pending_drops
+                    .fetch_add(dropped_before + 1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Stop accepting records, then wait for the writer to write the queued
+    /// ones and the footer
+    pub fn close(&self) {
+        drop(self.tx.lock().unwrap().take());
+        if let Some(handle) = self.writer.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// The journal file being written and the tracker replaying it
+struct OpenJournal {
+    path: PathBuf,
+    file: BufWriter<File>,
+    /// Day (days since the unix epoch) the file covers
+    day: u64,
+    records: u64,
+    /// Updates dropped before the records of this file
+    dropped: u64,
+    /// Replays the records so the footer checksum can be verified later
+    shadow: VoteTracker,
+    clock: Arc<ManualClock>,
+}
+
+/// Writes updates to `journal-<date>-<first record millis>.jsonl` files
+/// in `dir`, starting a new file on each UTC day
+struct JournalWriter {
+    dir: PathBuf,
+    fsync: JournalFsync,
+    config: JournalConfig,
+    open: Option<OpenJournal>,
+    /// Epoch and credits of the previous record, for credits_delta
+    prev_credits: Option<(Option<u64>, u64)>,
+}
+
+impl JournalWriter {
+    fn new(dir: PathBuf, fsync: JournalFsync, config: JournalConfig) -> Self {
+        Self {
+            dir,
+            fsync,
+            config,
+            open: None,
+            prev_credits: None,
+        }
+    }
+
+    fn write(&mut self, mut update: JournalUpdate) -> Result<()> {
+        update.credits_delta = match self.prev_credits {
+            Some((epoch, credits)) if epoch == update.epoch => {
+                update.epoch_credits.saturating_sub(credits)
+            }
+            _ => update.epoch_credits,
+        };
+        self.prev_credits = Some((update.epoch, update.epoch_credits));
+
+        let day = update.t / MILLIS_PER_DAY;
+        if self.open.as_ref().is_some_and(|open| open.day != day) {
+            self.close_file()?;
+        }
+        let fsync = self.fsync;
+        let open = match &mut self.open {
+            Some(open) => open,
+            None => self
+                .open
+                .insert(Self::open_file(&self.dir, &self.config, &update)?),
+        };
+
+        update.replay(&mut open.shadow, &open.clock);
+        open.dropped += update.dropped_before;
+        write_record(&mut open.file, &JournalRecord::Update(update))?;
+        open.records += 1;
+        if fsync == JournalFsync::Always {
+            open.file.flush()?;
+            open.file.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn open_file(dir: &Path, config: &JournalConfig, first: &JournalUpdate) -> Result<OpenJournal> {
+        let date = OffsetDateTime::from_unix_timestamp((first.t / 1000) as i64)
+            .map(|t| t.date().to_string())
+            .unwrap_or_else(|_| (first.t / MILLIS_PER_DAY).to_string());
+        let path = dir.join(format!("journal-{}-{}.jsonl", date, first.t));
+        let file = File::options()
+            .create_new(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        write_record(
+            &mut file,
+            &JournalRecord::Header {
+                version: JOURNAL_VERSION,
+                created_at: first.t,
+                config: *config,
+            },
+        )?;
+        info!("Journaling tracker updates to {}", path.display());
+
+        let (shadow, clock) = config.tracker();
+        Ok(OpenJournal {
+            path,
+            file,
+            day: first.t / MILLIS_PER_DAY,
+            records: 0,
+            dropped: 0,
+            shadow,
+            clock,
+        })
+    }
+
+    /// Write the footer and close the current file, if any
+    fn close_file(&mut self) -> Result<()> {
+        let Some(mut open) = self.open.take() else {
+            return Ok(());
+        };
+        write_record(
+            &mut open.file,
+            &JournalRecord::Footer {
+                records: open.records,
+                checksum: tracker_checksum(&open.shadow),
+                dropped: open.dropped,
+            },
+        )?;
+        open.file.flush()?;
+        if self.fsync != JournalFsync::Never {
+            open.file.get_ref().sync_data()?;
+        }
+        info!(
+            "Closed journal {} after {} records",
+            open.path.display(),
+            open.records
+        );
+        Ok(())
+    }
+}
+
+fn write_record(file: &mut impl Write, record: &JournalRecord) -> Result<()> {
+    serde_json::to_writer(&mut *file, record)?;
+    file.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Result of a successful `journal-verify`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalSummary {
+    pub records: u64,
+    pub checksum: String,
+}
+
+/// Replay a journal file through a fresh tracker and check the footer's
+/// record count and checksum against it. The checksum shows the file is
+/// whole and replays consistently, not that it matches the live tracker: at
+/// the start of a file the live tracker already has state the fresh one
+/// lacks, so the recorded missed credits and new votes are not re-checked
+/// either. A file missing updates dropped while it was written fails, as its
+/// replay is incomplete.
+pub fn verify_journal(path: &Path) -> Result<JournalSummary> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut replay: Option<(VoteTracker, Arc<ManualClock>)> = None;
+    let mut records = 0;
+    let mut dropped = 0;
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line_no = index + 1;
+        let record: JournalRecord = serde_json::from_str(&line?)
+            .with_context(|| format!("Line {}: not a journal record", line_no))?;
+        match (record, &mut replay) {
+            (
+                JournalRecord::Header {
+                    version, config, ..
+                },
+                None,
+            ) => {
+                if version != JOURNAL_VERSION {
+                    bail!("Unsupported journal version {}", version);
+                }
+                replay = Some(config.tracker());
+            }
+            (JournalRecord::Update(update), Some((tracker, clock))) => {
+                update.replay(tracker, clock);
+                records += 1;
+                dropped += update.dropped_before;
+            }
+            (
+                JournalRecord::Footer {
+                    records: expected,
+                    checksum,
+                    dropped: footer_dropped,
+                },
+                Some((tracker, _)),
+            ) => {
+                if expected != records {
+                    bail!(
+                        "Footer counts {} records, journal has {}",
+                        expected,
+                        records
+                    );
+                }
+                let replayed = tracker_checksum(tracker);
+                if replayed != checksum {
+                    bail!(
+                        "Checksum mismatch: footer has {}, replay gives {}",
+                        checksum,
+                        replayed
+                    );
+                }
+                // The footer also counts updates dropped after the last record
+                let dropped = dropped.max(footer_dropped);
+                if dropped > 0 {
+                    bail!(
+                        "{} updates were dropped while the journal was written (buffer full), the replay is incomplete",
+                        dropped
+                    );
+                }
+                return Ok(JournalSummary { records, checksum });
+            }
+            (_, None) => bail!("Line {}: journal does not start with a header", line_no),
+            (JournalRecord::Header { .. }, Some(_)) => bail!("Line {}: second header", line_no),
+        }
+    }
+    Err(anyhow!(
+        "{} has no footer (still being written or not closed cleanly)",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01 23:59:00 UTC
+    const BEFORE_MIDNIGHT: u64 = 1_704_153_540_000;
+
+    const CONFIG: JournalConfig = JournalConfig {
+        max_credits_per_slot: 16,
+        exclude_catch_up: false,
+        projection_alpha: 0.0002,
+        expected_anchor: ExpectedAnchor::Root,
+        tip_allowance_slots: 32,
+        max_state_bytes: None,
+    };
+
+    /// Empty scratch directory for one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tvc_tracker-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Updates from a live tracker: a vote per slot, rooted 2 slots later,
+    /// one every 10s, with every 4th vote earning only 12 credits
+    fn live_updates(count: u64, start_millis: u64) -> Vec<JournalUpdate> {
+        let (mut tracker, clock) = CONFIG.tracker();
+        let mut credits = 0;
+        (0..count)
+            .map(|i| {
+                clock.set(start_millis + i * 10_000);
+                let slot = 1000 + i;
+                let latency = |slot: u64| Some(if slot % 4 == 0 { 5 } else { 1 });
+                let votes = [(slot - 1, 2, latency(slot - 1)), (slot, 1, latency(slot))];
+                let root = slot - 2;
+                if i >= 1 {
+                    credits += if root % 4 == 0 { 12 } else { 16 };
+                }
+                let t = tracker.now_millis();
+                let result = tracker.process_update(slot + 1, &votes, Some(root), credits, Some(0));
+                JournalUpdate::new(t, slot + 1, &votes, Some(root), credits, Some(0), &result)
+            })
+            .collect()
+    }
+
+    fn journal_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_journal_replays_to_footer_checksum() {
+        let dir = scratch_dir("journal");
+        let journal = Journal::start(&dir, JournalFsync::Always, CONFIG).unwrap();
+        for update in live_updates(20, 1_704_067_200_000) {
+            assert!(journal.record(update));
+        }
+        journal.close();
+        assert!(!journal.record(live_updates(1, 1_704_067_200_000).remove(0)));
+
+        let files = journal_files(&dir);
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_str().unwrap();
+        assert_eq!(name, "journal-2024-01-01-1704067200000.jsonl");
+
+        let summary = verify_journal(&files[0]).unwrap();
+        assert_eq!(summary.records, 20);
+
+        let lines: Vec<String> = std::fs::read_to_string(&files[0])
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        assert!(lines[0].contains(r#""type":"header""#));
+        let fourth: JournalRecord = serde_json::from_str(&lines[4]).unwrap();
+        let JournalRecord::Update(fourth) = fourth else {
+            panic!("not an update: {}", lines[4]);
+        };
+        assert_eq!(fourth.credits_delta, 16);
+        assert_eq!(fourth.new_votes, vec![(1003, Some(1))]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_journal_rotates_daily() {
+        let dir = scratch_dir("journal-rotate");
+        let mut writer = JournalWriter::new(dir.clone(), JournalFsync::Never, CONFIG);
+        std::fs::create_dir_all(&dir).unwrap();
+        // 12 updates 10s apart straddle midnight
+        for update in live_updates(12, BEFORE_MIDNIGHT) {
+            writer.write(update).unwrap();
+        }
+        writer.close_file().unwrap();
+
+        let files = journal_files(&dir);
+        assert_eq!(files.len(), 2);
+        assert!(files[0].to_str().unwrap().contains("journal-2024-01-01-"));
+        assert!(files[1].to_str().unwrap().contains("journal-2024-01-02-"));
+        assert_eq!(verify_journal(&files[0]).unwrap().records, 6);
+        assert_eq!(verify_journal(&files[1]).unwrap().records, 6);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_fails_on_dropped_updates() {
+        let dir = scratch_dir("journal-dropped");
+        let mut writer = JournalWriter::new(dir.clone(), JournalFsync::Never, CONFIG);
+        std::fs::create_dir_all(&dir).unwrap();
+        // The live tracker applied all 10, the buffer let through 8
+        let mut updates = live_updates(10, 1_704_067_200_000);
+        updates.remove(4);
+        updates.remove(4);
+        updates[4].dropped_before = 2;
+        for update in updates {
+            writer.write(update).unwrap();
+        }
+        writer.close_file().unwrap();
+
+        let path = journal_files(&dir).remove(0);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(r#""dropped_before":2"#));
+        assert!(content.contains(r#""dropped":2}"#));
+        let err = verify_journal(&path).unwrap_err();
+        assert!(
+            err.to_string().contains("2 updates were dropped"),
+            "{:#}",
+            err
+        );
+
+        // Dropped after the last record: only the footer knows
+        let footer_only = content
+            .replace(r#","dropped_before":2"#, "")
+            .replace(r#""dropped":2}"#, r#""dropped":1}"#);
+        std::fs::write(&path, footer_only).unwrap();
+        let err = verify_journal(&path).unwrap_err();
+        assert!(
+            err.to_string().contains("1 updates were dropped"),
+            "{:#}",
+            err
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pending_drops_ride_on_the_next_record() {
+        let dir = scratch_dir("journal-pending");
+        let journal = Journal::start(&dir, JournalFsync::Never, CONFIG).unwrap();
+        journal.pending_drops.store(3, Ordering::Relaxed);
+        let mut updates = live_updates(2, 1_704_067_200_000).into_iter();
+        assert!(journal.record(updates.next().unwrap()));
+        assert_eq!(journal.pending_drops.load(Ordering::Relaxed), 0);
+        // Dropped at shutdown, after the last record
+        journal.pending_drops.store(1, Ordering::Relaxed);
+        journal.close();
+
+        let path = journal_files(&dir).remove(0);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(r#""dropped_before":3"#));
+        assert!(content.contains(r#""dropped":4}"#));
+        assert!(verify_journal(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_detects_tampering_and_truncation() {
+        let dir = scratch_dir("journal-tamper");
+        let mut writer = JournalWriter::new(dir.clone(), JournalFsync::Never, CONFIG);
+        std::fs::create_dir_all(&dir).unwrap();
+        for update in live_updates(10, 1_704_067_200_000) {
+            writer.write(update).unwrap();
+        }
+        writer.close_file().unwrap();
+        let path = journal_files(&dir).remove(0);
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        // A different recorded credit count changes the replayed state
+        let tampered = original.replacen(r#""epoch_credits":44,"#, r#""epoch_credits":40,"#, 1);
+        assert_ne!(tampered, original);
+        std::fs::write(&path, &tampered).unwrap();
+        assert!(verify_journal(&path).is_err());
+
+        // Without the footer there is nothing to verify against
+        let truncated: String = original
+            .lines()
+            .take(5)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        std::fs::write(&path, truncated).unwrap();
+        let err = verify_journal(&path).unwrap_err();
+        assert!(err.to_string().contains("no footer"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
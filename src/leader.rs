@@ -0,0 +1,164 @@
+use crate::rpc::RpcClient;
+use crate::ws::{SLOTS_PER_EPOCH, VoteTracker};
+
+use anyhow::{Result, anyhow};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// How often the tracker's epoch is checked for a missing leader schedule
+const LEADER_SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Fetch the validator's own leader slots for the tracker's current epoch,
+/// unless the tracker already has them. Returns the epoch fetched, if any.
+pub async fn refresh_leader_schedule<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    tracker: &RwLock<VoteTracker>,
+) -> Result<Option<u64>> {
+    let epoch = {
+        let tracker = tracker.read().await;
+        match tracker.epoch_info() {
+            Some(info) if tracker.leader_schedule_epoch() != Some(info.epoch) => info.epoch,
+            _ => return Ok(None),
+        }
+    };
+
+    // The schedule is keyed by the validator identity, not the vote account
+    let accounts = rpc.get_vote_accounts(Some(vote_pubkey)).await?;
+    let (account, _) = accounts
+        .find(vote_pubkey)
+        .ok_or_else(|| anyhow!("Vote account {} not found", vote_pubkey))?;
+    let slots = rpc
+        .get_leader_schedule(epoch * SLOTS_PER_EPOCH, &account.node_pubkey)
+        .await?;
+
+    info!(
+        "Leader schedule for epoch {}: {} slots for identity {}",
+        epoch,
+        slots.len(),
+        account.node_pubkey
+    );
+    tracker.write().await.set_leader_slots(epoch, &slots);
+    Ok(Some(epoch))
+}
+
+/// Keep the tracker's leader schedule on the current epoch, for the
+/// leader-adjusted efficiency metrics
+pub async fn run_leader_schedule<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    tracker: Arc<RwLock<VoteTracker>>,
+) {
+    loop {
+        if let Err(e) = refresh_leader_schedule(rpc, vote_pubkey, &tracker).await {
+            warn!("Leader schedule refresh failed: {:#}", e);
+        }
+        tokio::time::sleep(LEADER_SCHEDULE_CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{RpcVersion, RpcVoteAccount, RpcVoteAccounts};
+    use std::sync::Mutex;
+
+    const VOTE: &str = "Vote1111111111111111111111111111111111111111";
+    const NODE: &str = "Node1111111111111111111111111111111111111111";
+
+    /// Serves one vote account and records the leader schedule requests
+    #[derive(Default)]
+    struct ScheduleRpc {
+        requests: Mutex<Vec<(u64, String)>>,
+    }
+
+    impl RpcClient for ScheduleRpc {
+        async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+            Ok(RpcVoteAccounts {
+                current: vec![RpcVoteAccount {
+                    vote_pubkey: VOTE.to_string(),
+                    node_pubkey: NODE.to_string(),
+                    activated_stake: 0,
+                    commission: 0,
+                    epoch_vote_account: true,
+                    epoch_credits: vec![],
+                    last_vote: 0,
+                    root_slot: 0,
+                }],
+                delinquent: vec![],
+            })
+        }
+
+        async fn get_version(&self) -> Result<RpcVersion> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_slot(&self) -> Result<u64> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_leader_schedule(&self, slot: u64, identity: &str) -> Result<Vec<u64>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((slot, identity.to_string()));
+            Ok(vec![4, 5, 6, 7])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_fetches_schedule_once_per_epoch() {
+        let rpc = ScheduleRpc::default();
+        let tracker = RwLock::new(VoteTracker::default());
+
+        // No epoch known yet: nothing to fetch
+        assert_eq!(
+            refresh_leader_schedule(&rpc, VOTE, &tracker).await.unwrap(),
+            None
+        );
+
+        tracker
+            .write()
+            .await
+            .reset(5, 5 * SLOTS_PER_EPOCH + 100, 1600);
+        assert_eq!(
+            refresh_leader_schedule(&rpc, VOTE, &tracker).await.unwrap(),
+            Some(5)
+        );
+        assert_eq!(
+            refresh_leader_schedule(&rpc, VOTE, &tracker).await.unwrap(),
+            None
+        );
+
+        assert_eq!(
+            *rpc.requests.lock().unwrap(),
+            vec![(5 * SLOTS_PER_EPOCH, NODE.to_string())]
+        );
+        let tracker = tracker.read().await;
+        assert_eq!(tracker.leader_schedule_epoch(), Some(5));
+        assert_eq!(tracker.leader_slot_count(), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_fails_for_unknown_vote_account() {
+        let rpc = ScheduleRpc::default();
+        let tracker = RwLock::new(VoteTracker::default());
+        tracker
+            .write()
+            .await
+            .reset(5, 5 * SLOTS_PER_EPOCH + 100, 1600);
+
+        assert!(
+            refresh_leader_schedule(&rpc, "Other", &tracker)
+                .await
+                .is_err()
+        );
+        assert_eq!(tracker.read().await.leader_schedule_epoch(), None);
+    }
+}
@@ -1,6 +1,44 @@
+pub mod api;
+pub mod banner;
+#[cfg(feature = "nats")]
+pub mod bus;
+pub mod clock;
+pub mod cluster;
+pub mod compare;
 pub mod config;
+pub mod credits;
+pub mod debug_dump;
+pub mod discontinuity;
+pub mod events;
+pub mod heartbeat;
+pub mod journal;
+pub mod leader;
 pub mod logging;
+pub mod maintenance;
 pub mod metrics;
+pub mod notify;
+pub mod poller;
+pub mod preflight;
+pub mod proxy;
+pub mod rate_limit;
+pub mod rent;
+pub mod report;
+pub mod resolve;
+pub mod rpc;
+pub mod rpc_health;
+pub mod self_test;
+pub mod series;
+#[cfg(feature = "metrics-server")]
+pub mod server;
+pub mod skipped;
+pub mod snapshot;
+pub mod state;
+pub mod verify;
+pub mod version;
+pub mod vote_txs;
+pub mod warmup;
+pub mod watchlist;
 pub mod ws;
 
-pub use metrics::{MAX_CREDITS_PER_SLOT, Metrics};
+pub use credits::{CreditsModel, MAX_CREDITS_PER_SLOT};
+pub use metrics::Metrics;
@@ -1,19 +1,418 @@
-use tracing_subscriber::EnvFilter;
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
-pub fn init_logging(log_dir: &str) -> anyhow::Result<tracing_appender::non_blocking::WorkerGuard> {
+/// Messages with the same template logged within this window after the first
+/// are suppressed by [`throttled_warn!`] and [`throttled_error!`]
+pub const LOG_THROTTLE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Throttle shared by the throttled log macros
+pub static LOG_THROTTLE: LogThrottle = LogThrottle::new(LOG_THROTTLE_WINDOW);
+
+/// Whether a throttled message is logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throttled {
+    /// Log it, after a summary of the `suppressed` messages of the previous window
+    Emit {
+        suppressed: u64,
+    },
+    Suppress,
+}
+
+/// Per-template log suppression: the first message of a template opens a
+/// window in which further ones are only counted. The count is reported with
+/// the first message after the window, so a burst that stops for good goes
+/// unsummarized.
+pub struct LogThrottle {
+    window: Duration,
+    /// Template -> (window start, messages suppressed in it)
+    windows: Mutex<BTreeMap<&'static str, (Instant, u64)>>,
+}
+
+impl LogThrottle {
+    pub const fn new(window: Duration) -> Self {
+        Self {
+            window,
+            windows: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Decide on a message with `template` logged at `now`
+    pub fn check(&self, template: &'static str, now: Instant) -> Throttled {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        match windows.get_mut(template) {
+            Some((start, suppressed)) if now.duration_since(*start) < self.window => {
+                *suppressed += 1;
+                Throttled::Suppress
+            }
+            Some((start, suppressed)) => {
+                let previous = std::mem::take(suppressed);
+                *start = now;
+                Throttled::Emit {
+                    suppressed: previous,
+                }
+            }
+            None => {
+                windows.insert(template, (now, 0));
+                Throttled::Emit { suppressed: 0 }
+            }
+        }
+    }
+}
+
+/// Log through [`LOG_THROTTLE`] at `$level`, keyed by the format string
+#[doc(hidden)]
+#[macro_export]
+macro_rules! throttled_log {
+    ($level:expr, $template:literal $(, $arg:expr)* $(,)?) => {
+        match $crate::logging::LOG_THROTTLE.check($template, ::std::time::Instant::now()) {
+            $crate::logging::Throttled::Emit { suppressed } => {
+                if suppressed > 0 {
+                    ::tracing::event!(
+                        $level,
+                        "Suppressed {} similar messages in the last {}s: {}",
+                        suppressed,
+                        $crate::logging::LOG_THROTTLE.window().as_secs(),
+                        $template
+                    );
+                }
+                ::tracing::event!($level, $template $(, $arg)*);
+            }
+            $crate::logging::Throttled::Suppress => {}
+        }
+    };
+}
+
+/// `warn!` that logs a message template at most once per
+/// [`LOG_THROTTLE_WINDOW`], for retry loops that can fail many times a minute
+#[macro_export]
+macro_rules! throttled_warn {
+    ($($arg:tt)+) => {
+        $crate::throttled_log!(::tracing::Level::WARN, $($arg)+)
+    };
+}
+
+/// `error!` counterpart of [`throttled_warn!`]
+#[macro_export]
+macro_rules! throttled_error {
+    ($($arg:tt)+) => {
+        $crate::throttled_log!(::tracing::Level::ERROR, $($arg)+)
+    };
+}
+
+/// Keeps the log writer (and trace exporter, if any) alive; flushes both on drop
+pub struct LogGuard {
+    _writer: tracing_appender::non_blocking::WorkerGuard,
+    #[cfg(feature = "otlp")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.tracer_provider.take() {
+            // export whatever spans are still batched
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Log filters of the outputs (--log-file-filter, --log-stdout-filter), in
+/// RUST_LOG syntax. Unset ones fall back to RUST_LOG, then `info`.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilters {
+    pub file: Option<String>,
+    pub stdout: Option<String>,
+}
+
+/// Parse the filter configured with `flag`, falling back to `rust_log` and
+/// then `info`. Invalid filters are errors rather than silently `info`.
+pub fn log_filter(
+    flag: &str,
+    configured: Option<&str>,
+    rust_log: Option<&str>,
+) -> anyhow::Result<EnvFilter> {
+    let (source, directives) = match (configured, rust_log) {
+        (Some(directives), _) => (flag, directives),
+        (None, Some(directives)) => ("RUST_LOG", directives),
+        (None, None) => return Ok(EnvFilter::new("info")),
+    };
+    EnvFilter::builder()
+        .parse(directives)
+        .with_context(|| format!("Invalid {} '{}'", source, directives))
+}
+
+/// The file and stdout outputs, each behind its own filter
+fn output_layers<S, F, O>(
+    file: F,
+    file_filter: EnvFilter,
+    stdout: O,
+    stdout_filter: EnvFilter,
+) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    F: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+    O: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .with_writer(file)
+        .with_ansi(false) // log files don't need rainbow control codes
+        .with_filter(file_filter)
+        .and_then(
+            tracing_subscriber::fmt::layer()
+                .with_writer(stdout)
+                .with_filter(stdout_filter),
+        )
+}
+
+pub fn init_logging(
+    log_dir: &str,
+    filters: &LogFilters,
+    otlp_traces_endpoint: Option<&str>,
+) -> anyhow::Result<LogGuard> {
+    let rust_log = std::env::var("RUST_LOG").ok();
+    let file_filter = log_filter(
+        "--log-file-filter",
+        filters.file.as_deref(),
+        rust_log.as_deref(),
+    )?;
+    let stdout_filter = log_filter(
+        "--log-stdout-filter",
+        filters.stdout.as_deref(),
+        rust_log.as_deref(),
+    )?;
     std::fs::create_dir_all(log_dir)?;
 
     // daily rotating file: logs/tvc_tracker.YYYY-MM-DD
     let file_appender = tracing_appender::rolling::daily(log_dir, "tvc_tracker.log");
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
 
-    tracing_subscriber::fmt()
-        .with_writer(non_blocking)
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
-        )
-        .with_ansi(false) // log files don't need rainbow control codes
-        .init();
+    let registry = tracing_subscriber::registry().with(output_layers(
+        non_blocking,
+        file_filter,
+        std::io::stdout,
+        stdout_filter,
+    ));
+
+    #[cfg(feature = "otlp")]
+    {
+        let (otel_layer, tracer_provider) = match otlp_traces_endpoint {
+            Some(endpoint) => {
+                let (layer, provider) = otlp::layer(endpoint)?;
+                // Spans are exported at the level RUST_LOG (or info) allows
+                let filter = log_filter("RUST_LOG", None, rust_log.as_deref())?;
+                (Some(layer.with_filter(filter)), Some(provider))
+            }
+            None => (None, None),
+        };
+        registry.with(otel_layer).init();
+
+        Ok(LogGuard {
+            _writer: guard,
+            tracer_provider,
+        }) // keep this alive or logs may not flush
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        if otlp_traces_endpoint.is_some() {
+            anyhow::bail!("--otlp-traces-endpoint requires building with the `otlp` feature");
+        }
+        registry.init();
+
+        Ok(LogGuard { _writer: guard }) // keep this alive or logs may not flush
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Log writer appending to a shared buffer
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Captures everything logged on this thread while the guard lives
+    pub(crate) fn capture_logs() -> (Arc<Mutex<Vec<u8>>>, tracing::subscriber::DefaultGuard) {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let buffer = Buffer(logs.clone());
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_writer(move || buffer.clone())
+            .finish();
+        (logs, tracing::subscriber::set_default(subscriber))
+    }
+
+    #[test]
+    fn test_throttle_suppresses_within_window_and_summarizes() {
+        let throttle = LogThrottle::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(
+            throttle.check("a {}", at(0)),
+            Throttled::Emit { suppressed: 0 }
+        );
+        assert_eq!(throttle.check("a {}", at(1)), Throttled::Suppress);
+        assert_eq!(throttle.check("a {}", at(59)), Throttled::Suppress);
+        // Other templates have windows of their own
+        assert_eq!(
+            throttle.check("b {}", at(30)),
+            Throttled::Emit { suppressed: 0 }
+        );
+
+        assert_eq!(
+            throttle.check("a {}", at(60)),
+            Throttled::Emit { suppressed: 2 }
+        );
+        assert_eq!(throttle.check("a {}", at(70)), Throttled::Suppress);
+        assert_eq!(
+            throttle.check("a {}", at(200)),
+            Throttled::Emit { suppressed: 1 }
+        );
+        assert_eq!(
+            throttle.check("a {}", at(300)),
+            Throttled::Emit { suppressed: 0 }
+        );
+    }
+
+    #[test]
+    fn test_throttled_warn_logs_first_message_only() {
+        let (logs, _guard) = capture_logs();
+        for attempt in 1..=5 {
+            crate::throttled_warn!("Throttle test failure (attempt {}/5)", attempt);
+        }
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("WARN"), "{}", logs);
+        assert!(
+            logs.contains("Throttle test failure (attempt 1/5)"),
+            "{}",
+            logs
+        );
+        assert_eq!(logs.matches("Throttle test failure").count(), 1, "{}", logs);
+    }
+
+    #[test]
+    fn test_outputs_filter_independently() {
+        let (file, stdout) = (Buffer::default(), Buffer::default());
+        let layers = output_layers(
+            {
+                let file = file.clone();
+                move || file.clone()
+            },
+            log_filter("--log-file-filter", Some("info"), None).unwrap(),
+            {
+                let stdout = stdout.clone();
+                move || stdout.clone()
+            },
+            log_filter(
+                "--log-stdout-filter",
+                Some("info,tvc_tracker::ws=debug"),
+                None,
+            )
+            .unwrap(),
+        );
+        let subscriber = tracing_subscriber::registry().with(layers);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "tvc_tracker::ws::client", "ws debug");
+            tracing::debug!(target: "tvc_tracker::poller", "poller debug");
+            tracing::info!(target: "tvc_tracker::poller", "poller info");
+        });
+
+        let (file, stdout) = (file.contents(), stdout.contents());
+        assert!(file.contains("poller info"), "{}", file);
+        assert!(!file.contains("ws debug"), "{}", file);
+        assert!(!file.contains("poller debug"), "{}", file);
+        assert!(stdout.contains("poller info"), "{}", stdout);
+        assert!(stdout.contains("ws debug"), "{}", stdout);
+        assert!(!stdout.contains("poller debug"), "{}", stdout);
+    }
+
+    #[test]
+    fn test_log_filter_fallback_and_errors() {
+        assert_eq!(
+            log_filter("--log-file-filter", None, None)
+                .unwrap()
+                .to_string(),
+            "info"
+        );
+        assert_eq!(
+            log_filter("--log-file-filter", None, Some("warn"))
+                .unwrap()
+                .to_string(),
+            "warn"
+        );
+        // The flag wins over RUST_LOG, even an invalid one
+        assert!(log_filter("--log-file-filter", Some("debug"), Some("x=loud")).is_ok());
+
+        let err = log_filter("--log-stdout-filter", Some("tvc_tracker=loud"), None).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Invalid --log-stdout-filter 'tvc_tracker=loud'"),
+            "{:#}",
+            err
+        );
+        let err = log_filter("--log-file-filter", None, Some("x=loud")).unwrap_err();
+        assert!(err.to_string().contains("RUST_LOG"), "{:#}", err);
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use anyhow::Context;
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+    use tracing::Subscriber;
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::registry::LookupSpan;
 
-    Ok(guard) // keep this alive or logs may not flush
+    /// Bridge tracing spans to an OTLP/HTTP collector, e.g. http://localhost:4318/v1/traces
+    pub fn layer<S>(
+        endpoint: &str,
+    ) -> anyhow::Result<(OpenTelemetryLayer<S, SdkTracer>, SdkTracerProvider)>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .context("Failed to build OTLP span exporter")?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name("tvc_tracker").build())
+            .build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("tvc_tracker"));
+        Ok((layer, provider))
+    }
 }
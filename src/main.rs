@@ -1,15 +1,68 @@
-use tvc_tracker::config::Args;
+use tvc_tracker::api::json_schema_pretty;
+use tvc_tracker::banner::StartupBanner;
+use tvc_tracker::cluster::{ClusterAverages, ClusterComparison, run_cluster_health_scan};
+use tvc_tracker::compare::{fetch_comparison, format_comparison};
+#[cfg(feature = "metrics-server")]
+use tvc_tracker::config::PortConflictPolicy;
+use tvc_tracker::config::{
+    Args, Command, CompareArgs, JournalVerifyArgs, Mode, OutputFormat, VerifyArgs,
+};
+use tvc_tracker::debug_dump::DebugState;
+use tvc_tracker::discontinuity::Discontinuities;
+use tvc_tracker::events::event_channel;
+use tvc_tracker::heartbeat::{Heartbeat, run_heartbeat};
+use tvc_tracker::journal::{Journal, JournalConfig, verify_journal};
+use tvc_tracker::leader::run_leader_schedule;
 use tvc_tracker::logging::init_logging;
-use tvc_tracker::metrics::metrics_handler;
-use tvc_tracker::ws::{VoteTracker, run_vote_subscription};
+use tvc_tracker::maintenance::Maintenance;
+use tvc_tracker::notify::{NOTIFY_RETRY_BACKOFF, Sink, run_sink, sink_labels};
+use tvc_tracker::poller::{ADAPTIVE_INTERVAL_MAX, PollStateSlot};
+#[cfg(feature = "http-poll")]
+use tvc_tracker::poller::{PollState, run_poll};
+use tvc_tracker::preflight::{ensure_capabilities, format_capabilities, run_preflight};
+use tvc_tracker::rate_limit::RateLimiter;
+#[cfg(feature = "ws")]
+use tvc_tracker::rent::{RentExemption, VOTE_ACCOUNT_SPACE};
+use tvc_tracker::report::{ReportCounters, format_final_report};
+use tvc_tracker::rpc::HttpRpcClient;
+use tvc_tracker::rpc_health::{RpcHealthWatch, run_health_check};
+use tvc_tracker::self_test::{format_self_test, run_self_test};
+#[cfg(feature = "metrics-server")]
+use tvc_tracker::series::{MetricSeries, run_series_sampler};
+#[cfg(feature = "metrics-server")]
+use tvc_tracker::server::{
+    AppState, PortHandover, ServiceDiscovery, bind, router, takeover_router,
+};
+use tvc_tracker::skipped::run_skipped_slots;
+#[cfg(feature = "http-poll")]
+use tvc_tracker::state::restore_poll;
+use tvc_tracker::state::{
+    STATE_SAVE_INTERVAL, catch_up_offline_gap, record_restore, restore_tracker, run_state_saver,
+    save_tracker,
+};
+use tvc_tracker::verify::{fetch_verification, format_verification};
+use tvc_tracker::version::{resolve_credits_model, run_version_check};
+use tvc_tracker::vote_txs::{VoteFees, run_vote_tx_counter};
+use tvc_tracker::watchlist::{
+    Watchlist, WatchlistFetch, load_watchlist, run_watchlist, run_watchlist_per_validator,
+};
+use tvc_tracker::ws::{PriorVotersWatch, SHUTDOWN_GRACE, VoteTracker, WsConnectionClock};
+#[cfg(feature = "ws")]
+use tvc_tracker::ws::{SubscriptionOptions, run_vote_subscription};
 
 use clap::Parser;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+#[cfg(feature = "metrics-server")]
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+#[cfg(not(any(feature = "ws", feature = "http-poll")))]
+compile_error!("tvc_tracker needs a data source: enable the `ws` or `http-poll` feature");
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Install rustls crypto provider (ring) before any TLS operations
@@ -17,43 +70,579 @@ async fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
+    let started_at = Instant::now();
     let args = Args::parse();
+    match &args.command {
+        Some(Command::Compare(compare)) => return run_compare(compare).await,
+        Some(Command::JournalVerify(verify)) => return run_journal_verify(verify),
+        Some(Command::Verify(verify)) => return run_verify(verify).await,
+        Some(Command::Schema) => {
+            println!("{}", json_schema_pretty()?);
+            return Ok(());
+        }
+        Some(Command::SelfTest) => return run_self_test_command().await,
+        None => {}
+    }
     args.validate()?;
-    println!("tvc_tracker v{VERSION} starting with args:\n{:#?}", args);
 
-    let metrics = Arc::new(tvc_tracker::metrics::Metrics::new()?);
-    let _log_guard = init_logging(&args.log_dir)?;
+    let metrics = Arc::new(
+        tvc_tracker::metrics::Metrics::with_disabled(args.disabled_metrics())?
+            .with_histogram_windows(&args.histogram_windows)
+            .with_deprecated_metrics(args.emit_deprecated_metrics)
+            .with_warmup(args.warmup()),
+    );
+    metrics.set_config_info(&args);
+    tvc_tracker::clock::time_source().report_jumps_to(metrics.clock_jumps_detected.clone());
+    let _log_guard = init_logging(
+        &args.log_dir,
+        &args.log_filters(),
+        args.otlp_traces_endpoint.as_deref(),
+    )?;
+
+    // Bind before anything else runs, so that a previous instance still holding
+    // the port stops this one before it touches the state file or journal. Also
+    // gives the banner the port picked for --metrics-port 0.
+    #[cfg(feature = "metrics-server")]
+    let listener = bind(
+        args.metrics_port,
+        args.port_conflict_policy,
+        Duration::from_secs(args.port_wait_timeout_secs),
+    )
+    .await?;
+
+    let debug = Arc::new(DebugState::new(&args.debug_dump_dir, args.debug_dump_keep));
+    if let Some(proxy) = &args.proxy_url {
+        tracing::info!("Sending RPC and WebSocket traffic through proxy {}", proxy);
+    }
+    let mut rpc =
+        HttpRpcClient::new_with_resolve(&args.rpc_url, args.proxy_url.as_ref(), &args.rpc_resolve)?
+            .with_debug_state(debug.clone())
+            .with_response_sizes(&metrics, args.rpc_response_warn_bytes);
+    if let Some(max_rps) = args.rpc_max_rps {
+        rpc = rpc.with_rate_limiter(RateLimiter::new(
+            max_rps,
+            Some(metrics.rpc_throttle_wait.clone()),
+        ));
+    }
+    let rpc = Arc::new(rpc);
+
+    // Fail early with a clear message if the RPC lacks a method or encoding we rely on
+    if !args.skip_preflight {
+        let results = run_preflight(&rpc, &args.rpc_url, &args.vote_pubkey, args.mode).await;
+        println!("{}", format_capabilities(&results));
+        ensure_capabilities(&results)?;
+    }
+
+    // Max credits per slot: configured, or detected from the TVC feature gate
+    let credits_model =
+        resolve_credits_model(rpc.as_ref(), args.max_credits_per_slot, &metrics).await;
+    tracing::info!(
+        "Using {} max credits per slot",
+        credits_model.max_credits_per_slot
+    );
+
+    // Epoch rollovers, reconnects, restores, maintenance windows and resets
+    let discontinuities = Arc::new(
+        Discontinuities::new()
+            .with_metrics(&metrics)
+            .with_log_dir(args.discontinuity_log.then(|| Path::new(&args.log_dir))),
+    );
+
+    // Planned maintenance, from the flag or POST /admin/maintenance
+    let maintenance =
+        Arc::new(Maintenance::new().with_discontinuities(Some(discontinuities.clone())));
+    if let Some(secs) = args.start_in_maintenance {
+        maintenance.start(Duration::from_secs(secs), "--start-in-maintenance");
+    }
+
+    // Create vote tracker for WebSocket histogram tracking
+    let tracker = Arc::new(RwLock::new(
+        VoteTracker::new(credits_model)
+            .with_maintenance(Some(maintenance.clone()))
+            .with_projection_alpha(args.projection_alpha)
+            .with_exclude_catch_up(args.exclude_catch_up_votes)
+            .with_credit_stall_updates(args.votes_without_credits_updates)
+            .with_expected_anchor(args.expected_anchor, args.tip_allowance_slots)
+            .with_max_state_bytes(args.max_state_bytes)
+            .with_skipped_slot_tracking(args.track_skipped_slots && args.mode == Mode::Ws),
+    ));
+    #[cfg_attr(
+        not(any(feature = "ws", feature = "metrics-server")),
+        allow(unused_variables)
+    )]
+    let ws_connection = Arc::new(WsConnectionClock::default());
+    // Voter rotations, seen by the subscription and served by /debug/prior_voters
+    #[cfg_attr(
+        not(any(feature = "ws", feature = "metrics-server")),
+        allow(unused_variables)
+    )]
+    let prior_voters = Arc::new(PriorVotersWatch::default());
 
-    // Set up metrics HTTP server
-    let app = axum::Router::new().route(
-        "/metrics",
-        axum::routing::get({
-            let metrics = metrics.clone();
-            move || metrics_handler(metrics.clone())
+    // Audit trail of every tracker update, written off the WS loop
+    let journal = match &args.journal_dir {
+        Some(dir) if args.mode == Mode::Ws => {
+            let config = JournalConfig {
+                max_credits_per_slot: credits_model.max_credits_per_slot,
+                exclude_catch_up: args.exclude_catch_up_votes,
+                projection_alpha: args.projection_alpha,
+                expected_anchor: args.expected_anchor,
+                tip_allowance_slots: args.tip_allowance_slots,
+                max_state_bytes: args.max_state_bytes,
+            };
+            Some(Arc::new(Journal::start(dir, args.journal_fsync, config)?))
+        }
+        Some(_) => {
+            tracing::warn!("--journal-dir only applies in ws mode, not journaling");
+            None
+        }
+        None => None,
+    };
+
+    // Window history and hourly profile survive restarts when a state file is
+    // set, as do the poller's counters in poll mode
+    let poll_state_slot = PollStateSlot::default();
+    if let Some(path) = &args.state_file {
+        let path = PathBuf::from(path);
+        let saved = restore_tracker(&path, &tracker).await;
+        // Account for the slots rooted while down (the tracker is fed in WS mode only)
+        if let Some(saved) = saved.filter(|_| args.mode == Mode::Ws) {
+            let caught_up =
+                catch_up_offline_gap(rpc.as_ref(), &args.vote_pubkey, &saved, &tracker, &metrics)
+                    .await;
+            if let Err(e) = caught_up {
+                tracing::warn!("Not catching up the offline gap: {:#}", e);
+            }
+        }
+        if let Some(saved) = saved {
+            record_restore(&discontinuities, &saved, &tracker).await;
+        }
+        let tracker = tracker.clone();
+        let poll = poll_state_slot.clone();
+        tokio::spawn(
+            async move { run_state_saver(&path, tracker, poll, STATE_SAVE_INTERVAL).await },
+        );
+    }
+
+    // One-minute samples for the sparklines of GET /series, taken whether
+    // or not Prometheus scrapes
+    #[cfg(feature = "metrics-server")]
+    let series = Arc::new(MetricSeries::new());
+    #[cfg(feature = "metrics-server")]
+    {
+        let series = series.clone();
+        let metrics = metrics.clone();
+        let mode = args.mode;
+        tokio::spawn(async move { run_series_sampler(&series, &metrics, mode).await });
+    }
+
+    // Set up metrics HTTP server (and admin endpoints if enabled)
+    #[cfg(feature = "metrics-server")]
+    let app = router(
+        Arc::new(AppState {
+            metrics: metrics.clone(),
+            tracker: tracker.clone(),
+            rpc: rpc.clone(),
+            vote_pubkey: args.vote_pubkey.clone(),
+            mode: args.mode,
+            metrics_max_staleness: args.metrics_max_staleness_secs.map(Duration::from_secs),
+            ws_connection: ws_connection.clone(),
+            debug: debug.clone(),
+            admin_token: args.admin_token.clone().map(|t| t.0),
+            maintenance: maintenance.clone(),
+            discontinuities: discontinuities.clone(),
+            series,
+            prior_voters: prior_voters.clone(),
+            service_discovery: ServiceDiscovery::new(
+                args.advertise_addr.clone(),
+                &args.vote_pubkey,
+                args.cluster.as_deref(),
+                args.instance_name.as_deref(),
+            ),
         }),
+        args.debug_endpoints,
     );
+    // Under takeover, a newer instance asks this one to stop accepting on the port
+    #[cfg(feature = "metrics-server")]
+    let handover = Arc::new(PortHandover::default());
+    #[cfg(feature = "metrics-server")]
+    let app = match args.port_conflict_policy {
+        PortConflictPolicy::Takeover => app.merge(takeover_router(handover.clone())),
+        PortConflictPolicy::Exit | PortConflictPolicy::Wait => app,
+    };
 
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), args.metrics_port);
+    #[cfg(feature = "metrics-server")]
+    let metrics_addr = Some(listener.local_addr()?);
+    #[cfg(not(feature = "metrics-server"))]
+    let metrics_addr = None;
 
+    let banner = StartupBanner::new(&args, metrics_addr).to_json_line();
+    println!("{}", banner);
+    tracing::info!("Startup: {}", banner);
+
+    #[cfg(feature = "metrics-server")]
     tokio::spawn(async move {
-        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-        tracing::info!("Metrics server listening on {}", addr);
-        axum::serve(listener, app).await.unwrap();
+        tracing::info!(
+            "Metrics server listening on {}",
+            listener.local_addr().unwrap()
+        );
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(handover.requested())
+        .await
+        .unwrap();
     });
+    #[cfg(not(feature = "metrics-server"))]
+    tracing::warn!("Built without the `metrics-server` feature, not serving /metrics");
 
-    // Create vote tracker for WebSocket histogram tracking
-    let tracker = Arc::new(RwLock::new(VoteTracker::new()));
+    // RPC version and TVC activation, refreshed daily
+    {
+        let rpc = rpc.clone();
+        let auto_tracker = args.max_credits_per_slot.is_none().then(|| tracker.clone());
+        let metrics = metrics.clone();
+        tokio::spawn(async move { run_version_check(rpc.as_ref(), auto_tracker, &metrics).await });
+    }
 
-    // Start WebSocket subscription - this is now the primary data source
-    // All metrics are derived from WebSocket updates
-    // Epoch info is calculated from slot numbers (no HTTP needed)
-    tracing::info!(
-        "Starting WebSocket subscription for vote account {}",
-        args.vote_pubkey
-    );
+    // getHealth of the RPC node, consulted by the poller before it vouches for the data
+    let rpc_health = Arc::new(RpcHealthWatch::new());
+    {
+        let rpc = rpc.clone();
+        let rpc_health = rpc_health.clone();
+        let metrics = metrics.clone();
+        tokio::spawn(async move { run_health_check(rpc.as_ref(), &rpc_health, &metrics).await });
+    }
+
+    // Own leader slots for the leader-adjusted efficiency (the tracker is fed in WS mode only)
+    if args.mode == Mode::Ws {
+        let rpc = rpc.clone();
+        let vote_pubkey = args.vote_pubkey.clone();
+        let tracker = tracker.clone();
+        tokio::spawn(async move { run_leader_schedule(rpc.as_ref(), &vote_pubkey, tracker).await });
+    }
+
+    // Blocks of the rooted slots for the skip-adjusted missed credits
+    if args.track_skipped_slots && args.mode == Mode::Ws {
+        let rpc = rpc.clone();
+        let tracker = tracker.clone();
+        let metrics = metrics.clone();
+        tokio::spawn(async move { run_skipped_slots(rpc.as_ref(), tracker, &metrics).await });
+    }
+
+    // Landed vote transactions, for the fee side of voting
+    if args.count_vote_txs {
+        let rpc = rpc.clone();
+        let vote_pubkey = args.vote_pubkey.clone();
+        let fees = VoteFees {
+            lamports_per_signature: args.lamports_per_signature,
+            signatures_per_tx: args.signatures_per_vote_tx,
+        };
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            run_vote_tx_counter(rpc.as_ref(), &vote_pubkey, fees, &metrics).await
+        });
+    }
+
+    // Optional event stream to a message bus, published off the data path
+    let events = (args.nats_url.is_some() || !args.notify.is_empty()).then(event_channel);
+    #[cfg(feature = "nats")]
+    if let (Some(url), Some(events)) = (&args.nats_url, &events) {
+        let publisher = tvc_tracker::bus::NatsPublisher::connect(url).await?;
+        let rx = events.subscribe();
+        let prefix = args.nats_subject_prefix.clone();
+        let vote_pubkey = args.vote_pubkey.clone();
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            tvc_tracker::bus::run_event_publisher(rx, publisher, &prefix, &vote_pubkey, &metrics)
+                .await
+        });
+    }
+
+    // Optional webhook sinks, each delivering in a task of its own
+    if let Some(events) = &events {
+        for (target, label) in args.notify.iter().zip(sink_labels(&args.notify)) {
+            let sink = Sink::new(target, &label, args.notify_routing_key.as_deref())?
+                .with_retries(args.notify_max_attempts, NOTIFY_RETRY_BACKOFF);
+            tracing::info!("Delivering events to {} ({})", target, label);
+            tokio::spawn(run_sink(
+                events.subscribe(),
+                sink,
+                rpc.http_client(),
+                args.vote_pubkey.clone(),
+                metrics.clone(),
+                maintenance.clone(),
+            ));
+        }
+    }
 
-    // Run WebSocket subscription with automatic reconnection
-    run_vote_subscription(&args.rpc_url, &args.vote_pubkey, metrics, tracker).await?;
+    // Optional dead man's switch, pinged only while the data path delivers
+    if let Some(url) = &args.heartbeat_url {
+        let interval = Duration::from_secs(args.heartbeat_interval_secs);
+        // Adaptive polling may stretch the interval up to its maximum
+        let poll_interval_bound = match args.interval_secs {
+            0 => ADAPTIVE_INTERVAL_MAX,
+            secs => Duration::from_secs(secs),
+        };
+        let max_age = args.heartbeat_max_age_secs.map_or_else(
+            || Heartbeat::default_max_age(args.mode, poll_interval_bound),
+            Duration::from_secs,
+        );
+        let heartbeat = Heartbeat::new(rpc.http_client(), url, args.mode, max_age);
+        let metrics = metrics.clone();
+        tokio::spawn(async move { run_heartbeat(heartbeat, interval, &metrics).await });
+    }
 
+    // Optional watchlist of additional validators from cluster-wide snapshots,
+    // which also exports the cluster stake, or fetched one at a time
+    let bulk_watchlist = args.watchlist_fetch == WatchlistFetch::Bulk;
+    let cluster_averages = Arc::new(ClusterAverages::new());
+    let comparison = ClusterComparison {
+        vote_pubkey: args.vote_pubkey.clone(),
+        ranks: args.cluster_credits_ranks.clone(),
+        exclude_self: args.exclude_self_from_cluster_avg,
+        averages: cluster_averages.clone(),
+    };
+    if let Some(path) = &args.watchlist_file {
+        let watchlist = Watchlist::new(load_watchlist(path)?, credits_model).with_file(path);
+        let interval = Duration::from_secs(args.watchlist_interval_secs);
+        let rpc = rpc.clone();
+        let metrics = metrics.clone();
+        let comparison = comparison.clone();
+        tokio::spawn(async move {
+            if bulk_watchlist {
+                run_watchlist(rpc.as_ref(), watchlist, &comparison, interval, &metrics).await
+            } else {
+                run_watchlist_per_validator(rpc.as_ref(), watchlist, interval, &metrics).await
+            }
+        });
+    }
+    if (args.watchlist_file.is_none() || !bulk_watchlist)
+        && (args.cluster_health_scan || !args.cluster_credits_ranks.is_empty())
+    {
+        // Without a bulk watchlist the cluster stake and ranks need a scan of their own
+        let interval = Duration::from_secs(args.watchlist_interval_secs);
+        let rpc = rpc.clone();
+        let metrics = metrics.clone();
+        let comparison = comparison.clone();
+        tokio::spawn(async move {
+            run_cluster_health_scan(rpc.as_ref(), &comparison, interval, &metrics).await
+        });
+    }
+
+    // Lets the WebSocket subscription unsubscribe before the process exits
+    #[cfg_attr(not(feature = "ws"), allow(unused_variables))]
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let data_source = async {
+        match args.mode {
+            #[cfg(feature = "ws")]
+            Mode::Ws => {
+                // All metrics are derived from WebSocket updates
+                // Epoch info is calculated from slot numbers (no HTTP needed)
+                tracing::info!(
+                    "Starting WebSocket subscription for vote account {}",
+                    args.vote_pubkey
+                );
+                // The balance comes with each notification, the minimum it must cover once
+                let rent_exemption =
+                    match RentExemption::fetch(rpc.as_ref(), VOTE_ACCOUNT_SPACE).await {
+                        Ok(rent) => Some(rent),
+                        Err(e) => {
+                            tracing::warn!("Not checking rent exemption: {:#}", e);
+                            None
+                        }
+                    };
+                run_vote_subscription(
+                    &args.rpc_url,
+                    &args.vote_pubkey,
+                    metrics.clone(),
+                    tracker.clone(),
+                    ws_connection.clone(),
+                    debug.clone(),
+                    SubscriptionOptions {
+                        journal: journal.clone(),
+                        min_credit_history_epochs: args.min_credit_history_epochs,
+                        warmup_rpc: args.ws_warmup_fetch.then(|| rpc.clone()),
+                        check_rpc: Some(rpc.clone()),
+                        first_message_timeout: Duration::from_secs(
+                            args.ws_first_message_timeout_secs,
+                        ),
+                        proxy: args.proxy_url.clone(),
+                        resolve: args.rpc_resolve.clone(),
+                        events: events.clone(),
+                        discontinuities: Some(discontinuities.clone()),
+                        cluster_averages: Some(cluster_averages.clone()),
+                        shutdown: Some(shutdown_rx),
+                        rent_exemption,
+                        fork_pressure: args.fork_pressure(),
+                        notification_queue_capacity: args.ws_notification_queue_capacity,
+                        prior_voters: prior_voters.clone(),
+                        ..Default::default()
+                    },
+                )
+                .await
+            }
+            #[cfg(feature = "http-poll")]
+            Mode::Poll => {
+                let interval = Duration::from_secs(args.interval_secs);
+                let state = PollState::new()
+                    .with_stake_decrease_pct(args.stake_decrease_pct)
+                    .with_min_credit_history_epochs(args.min_credit_history_epochs)
+                    .with_expected_anchor(args.expected_anchor, args.tip_allowance_slots)
+                    .with_adaptive_interval(
+                        (args.interval_secs == 0).then_some(args.target_slots_per_poll),
+                    )
+                    .with_maintenance(Some(maintenance.clone()))
+                    .with_events(events.clone())
+                    .with_discontinuities(Some(discontinuities.clone()))
+                    .with_restored(
+                        args.state_file
+                            .as_deref()
+                            .and_then(|path| restore_poll(Path::new(path))),
+                    )
+                    .with_persistence(args.state_file.is_some().then(|| poll_state_slot.clone()))
+                    .with_rpc_health(Some(rpc_health.clone()))
+                    .with_cluster_averages(Some(cluster_averages.clone()));
+                run_poll(
+                    rpc.as_ref(),
+                    &args.vote_pubkey,
+                    state,
+                    credits_model,
+                    interval,
+                    &metrics,
+                )
+                .await
+            }
+            // Rejected by Args::validate
+            #[allow(unreachable_patterns)]
+            mode => Err(anyhow::anyhow!(
+                "--mode {:?} requires building with the `{}` feature",
+                mode,
+                mode.feature()
+            )),
+        }
+    };
+
+    // Run the data source (with automatic reconnection / retries) until shutdown is requested
+    tokio::pin!(data_source);
+    tokio::select! {
+        result = &mut data_source => result?,
+        _ = shutdown_signal() => {
+            tracing::info!("Shutdown signal received");
+            let _ = shutdown_tx.send(true);
+            // The poller has nothing to close
+            if args.mode == Mode::Ws
+                && tokio::time::timeout(SHUTDOWN_GRACE, &mut data_source).await.is_err()
+            {
+                tracing::warn!("WebSocket subscription did not close within {:?}", SHUTDOWN_GRACE);
+            }
+        }
+    }
+
+    if let Some(path) = &args.state_file {
+        save_tracker(Path::new(path), &tracker, &poll_state_slot).await;
+    }
+    if let Some(journal) = &journal {
+        journal.close();
+    }
+
+    if !args.no_final_report {
+        let snapshot = tracker.read().await.snapshot();
+        let counters = ReportCounters::sample(&metrics, started_at.elapsed());
+        println!("{}", format_final_report(&snapshot, &counters));
+    }
+
+    Ok(())
+}
+
+/// `tvc_tracker compare`: print two vote accounts side by side and exit
+async fn run_compare(args: &CompareArgs) -> anyhow::Result<()> {
+    args.validate()?;
+    let rpc = HttpRpcClient::new(&args.rpc_url)?;
+    let metrics = tvc_tracker::metrics::Metrics::new()?;
+    let credits_model = resolve_credits_model(&rpc, args.max_credits_per_slot, &metrics).await;
+    let comparison = fetch_comparison(&rpc, &args.vote_pubkeys, credits_model).await?;
+    match args.output {
+        OutputFormat::Text => println!("{}", format_comparison(&comparison)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&comparison)?),
+    }
+    Ok(())
+}
+
+/// `tvc_tracker verify`: cross-check the accounting of one vote account
+async fn run_verify(args: &VerifyArgs) -> anyhow::Result<()> {
+    args.validate()?;
+    let rpc = HttpRpcClient::new(&args.rpc_url)?;
+    let metrics = tvc_tracker::metrics::Metrics::new()?;
+    let credits_model = resolve_credits_model(&rpc, args.max_credits_per_slot, &metrics).await;
+    let verification = fetch_verification(
+        &rpc,
+        &args.vote_pubkey,
+        args.tip_allowance_slots,
+        credits_model,
+    )
+    .await?;
+    match args.output {
+        OutputFormat::Text => println!("{}", format_verification(&verification)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&verification)?),
+    }
+    if !verification.is_consistent() {
+        anyhow::bail!(
+            "{} inconsistencies in the accounting of {}",
+            verification.inconsistencies.len(),
+            args.vote_pubkey
+        );
+    }
     Ok(())
 }
+
+/// `tvc_tracker self-test`: check the pipeline against the bundled fixtures
+async fn run_self_test_command() -> anyhow::Result<()> {
+    let report = run_self_test().await?;
+    print!("{}", format_self_test(&report));
+    if !report.passed() {
+        anyhow::bail!(
+            "self-test failed: {} of {} checks",
+            report.failed(),
+            report.checks.len()
+        );
+    }
+    println!("tvc_tracker v{VERSION} self-test passed");
+    Ok(())
+}
+
+/// `tvc_tracker journal-verify`: replay a journal file and check its footer
+fn run_journal_verify(args: &JournalVerifyArgs) -> anyhow::Result<()> {
+    let summary = verify_journal(&args.file)?;
+    println!(
+        "{}: {} records, checksum {} OK",
+        args.file.display(),
+        summary.records,
+        summary.checksum
+    );
+    Ok(())
+}
+
+/// Resolve when the process receives Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -0,0 +1,234 @@
+use crate::clock::{SharedClock, system_clock};
+use crate::discontinuity::{Discontinuities, DiscontinuityEvent};
+use crate::metrics::Metrics;
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+/// Planned maintenance set with `POST /admin/maintenance` or --start-in-maintenance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub reason: String,
+    /// Unix millis the window started at
+    pub started_at_ms: u64,
+    /// Unix millis the window expires at
+    pub ends_at_ms: u64,
+}
+
+/// Whether the validator is in planned maintenance. While it is, missed
+/// credits are counted as maintenance misses instead of into the main totals.
+#[derive(Debug)]
+pub struct Maintenance {
+    window: Mutex<Option<MaintenanceWindow>>,
+    clock: SharedClock,
+    discontinuities: Option<Arc<Discontinuities>>,
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self {
+            window: Mutex::new(None),
+            clock: system_clock(),
+            discontinuities: None,
+        }
+    }
+}
+
+impl Maintenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `clock` for the window start and expiry instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Record windows starting, ending early and expiring in `discontinuities`
+    pub fn with_discontinuities(mut self, discontinuities: Option<Arc<Discontinuities>>) -> Self {
+        self.discontinuities = discontinuities;
+        self
+    }
+
+    fn record(&self, event: DiscontinuityEvent) {
+        if let Some(discontinuities) = &self.discontinuities {
+            discontinuities.record(None, None, event);
+        }
+    }
+
+    /// Start a window of `duration`, replacing the current one. A zero
+    /// duration ends the current window instead.
+    pub fn start(&self, duration: Duration, reason: &str) -> Option<MaintenanceWindow> {
+        let now = self.clock.now_millis();
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if duration.is_zero() {
+            if let Some(ended) = window.take() {
+                info!("Maintenance ended early: {}", ended.reason);
+                self.record(DiscontinuityEvent::MaintenanceEnded {
+                    window: ended,
+                    early: true,
+                });
+            }
+            return None;
+        }
+        let started = MaintenanceWindow {
+            reason: reason.to_string(),
+            started_at_ms: now,
+            ends_at_ms: now.saturating_add(duration.as_millis() as u64),
+        };
+        info!(
+            "Maintenance started for {}s: {}",
+            duration.as_secs(),
+            started.reason
+        );
+        let replaced = window.replace(started.clone());
+        self.record(DiscontinuityEvent::MaintenanceStarted {
+            replaced,
+            window: started.clone(),
+        });
+        Some(started)
+    }
+
+    /// The window in effect, if any. A window past its end expires here.
+    pub fn active(&self) -> Option<MaintenanceWindow> {
+        let now = self.clock.now_millis();
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if window.as_ref().is_some_and(|w| now >= w.ends_at_ms) {
+            let expired = window.take().expect("checked above");
+            info!("Maintenance expired: {}", expired.reason);
+            self.record(DiscontinuityEvent::MaintenanceEnded {
+                window: expired,
+                early: false,
+            });
+        }
+        window.clone()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active().is_some()
+    }
+
+    /// Time left in the window in effect (zero outside maintenance)
+    pub fn remaining(&self) -> Duration {
+        self.active().map_or(Duration::ZERO, |w| {
+            Duration::from_millis(w.ends_at_ms.saturating_sub(self.clock.now_millis()))
+        })
+    }
+
+    /// Set `tvc_maintenance_active` and `tvc_maintenance_remaining_seconds`
+    pub fn export(&self, metrics: &Metrics) {
+        let remaining = self.remaining();
+        metrics
+            .maintenance_active
+            .set((!remaining.is_zero()) as i64);
+        metrics
+            .maintenance_remaining
+            .set(remaining.as_secs_f64().ceil() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn maintenance_with_clock() -> (Maintenance, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        (Maintenance::new().with_clock(clock.clone()), clock)
+    }
+
+    #[test]
+    fn test_window_expires_after_duration() {
+        let (maintenance, clock) = maintenance_with_clock();
+        let metrics = Metrics::new().unwrap();
+        assert!(!maintenance.is_active());
+
+        let window = maintenance
+            .start(Duration::from_secs(3600), "upgrade")
+            .unwrap();
+        assert_eq!(window.ends_at_ms - window.started_at_ms, 3_600_000);
+        clock.advance_secs(3599);
+        assert_eq!(maintenance.active(), Some(window));
+        maintenance.export(&metrics);
+        assert_eq!(metrics.maintenance_active.get(), 1);
+        assert_eq!(metrics.maintenance_remaining.get(), 1);
+
+        clock.advance_secs(1);
+        assert!(!maintenance.is_active());
+        maintenance.export(&metrics);
+        assert_eq!(metrics.maintenance_active.get(), 0);
+        assert_eq!(metrics.maintenance_remaining.get(), 0);
+    }
+
+    #[test]
+    fn test_zero_duration_ends_window() {
+        let (maintenance, _) = maintenance_with_clock();
+        maintenance.start(Duration::from_secs(60), "upgrade");
+        // A new window replaces the current one
+        let window = maintenance.start(Duration::from_secs(600), "longer upgrade");
+        assert_eq!(maintenance.active(), window);
+
+        assert_eq!(maintenance.start(Duration::ZERO, "done"), None);
+        assert!(!maintenance.is_active());
+    }
+
+    #[test]
+    fn test_windows_are_recorded_as_discontinuities() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let discontinuities = Arc::new(Discontinuities::new().with_clock(clock.clone()));
+        let maintenance = Maintenance::new()
+            .with_clock(clock.clone())
+            .with_discontinuities(Some(discontinuities.clone()));
+
+        let first = maintenance
+            .start(Duration::from_secs(60), "upgrade")
+            .unwrap();
+        let second = maintenance
+            .start(Duration::from_secs(600), "longer upgrade")
+            .unwrap();
+        maintenance.start(Duration::ZERO, "done");
+        let third = maintenance
+            .start(Duration::from_secs(60), "reboot")
+            .unwrap();
+        clock.advance_secs(60);
+        assert!(!maintenance.is_active());
+
+        let events: Vec<DiscontinuityEvent> = discontinuities
+            .recent()
+            .into_iter()
+            .map(|record| record.event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                DiscontinuityEvent::MaintenanceStarted {
+                    replaced: None,
+                    window: first.clone(),
+                },
+                DiscontinuityEvent::MaintenanceStarted {
+                    replaced: Some(first),
+                    window: second.clone(),
+                },
+                DiscontinuityEvent::MaintenanceEnded {
+                    window: second,
+                    early: true,
+                },
+                DiscontinuityEvent::MaintenanceStarted {
+                    replaced: None,
+                    window: third.clone(),
+                },
+                DiscontinuityEvent::MaintenanceEnded {
+                    window: third,
+                    early: false,
+                },
+            ]
+        );
+        assert_eq!(
+            discontinuities.recent().last().unwrap().timestamp_ms,
+            1_700_000_060_000
+        );
+    }
+}
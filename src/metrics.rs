@@ -1,15 +1,466 @@
+use crate::cluster::ClusterAverage;
+use crate::config::{Args, Mode};
+use crate::discontinuity::DiscontinuityEvent;
+use crate::poller::CreditHistory;
+use crate::rpc::{COMMITMENT, ClusterStake};
+use crate::warmup::{Warmup, WarmupConfig};
+use crate::ws::{
+    AcknowledgedSubscription, EffectiveCommitment, ExpectedAnchor, HourlyProfile, MissedAccounting,
+    TipExpectation,
+};
+
 use anyhow::Result;
-use axum::http::{HeaderMap, HeaderValue};
+use clap::ValueEnum;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::{MetricFamily, MetricType};
 use prometheus::{
-    Encoder, Gauge, GaugeVec, IntCounter, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+    Counter, Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TEXT_FORMAT, TextEncoder,
 };
-use std::sync::Arc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::info;
+
+/// Buckets of the tracker lock timings, from microseconds (uncontended) to
+/// the better part of a second
+const LOCK_BUCKETS: [f64; 9] = [1e-6, 1e-5, 5e-5, 1e-4, 5e-4, 1e-3, 1e-2, 0.1, 0.5];
+
+bitflags::bitflags! {
+    /// Metric families left unregistered and not updated (`--disable-metrics`)
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct DisabledMetrics: u8 {
+        const HISTOGRAM = 1;
+        const FRACTIONS = 1 << 1;
+        const PROJECTIONS = 1 << 2;
+        /// Tracker lock timings, off unless `--lock-metrics`
+        const LOCKS = 1 << 3;
+    }
+}
+
+/// Named groups of metric families for `--disable-metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MetricGroup {
+    /// solana_vote_credits_histogram_count (17 series per window)
+    Histogram,
+    /// solana_vote_credits_histogram_fraction (17 series per window)
+    Fractions,
+    /// solana_vote_credits_projected_{5m,1h,smoothed}
+    Projections,
+}
+
+impl From<MetricGroup> for DisabledMetrics {
+    fn from(group: MetricGroup) -> Self {
+        match group {
+            MetricGroup::Histogram => Self::HISTOGRAM,
+            MetricGroup::Fractions => Self::FRACTIONS,
+            MetricGroup::Projections => Self::PROJECTIONS,
+        }
+    }
+}
+
+/// Windows of the per-credit histogram series (`--histogram-windows`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistogramWindow {
+    #[value(name = "5m")]
+    FiveMinutes,
+    #[value(name = "1h")]
+    OneHour,
+    Epoch,
+}
+
+impl HistogramWindow {
+    pub const ALL: [Self; 3] = [Self::FiveMinutes, Self::OneHour, Self::Epoch];
+
+    /// Value of the `window` label
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::FiveMinutes => "5m",
+            Self::OneHour => "1h",
+            Self::Epoch => "epoch",
+        }
+    }
+
+    /// Length of the window, None for the epoch
+    pub fn seconds(self) -> Option<u64> {
+        match self {
+            Self::FiveMinutes => Some(300),
+            Self::OneHour => Some(3600),
+            Self::Epoch => None,
+        }
+    }
+}
+
+/// History spanning less than this fraction of a window leaves the window's
+/// families out of `/metrics`
+pub const MIN_WINDOW_COVERAGE: f64 = 0.2;
+
+/// Families computed over the 5m and 1h windows, exported as
+/// `<name>_<window>`. Left out of `/metrics` while the history covers less
+/// than [`MIN_WINDOW_COVERAGE`] of their window (after startup, or after a
+/// cluster halt aged it out): an empty window would read as zero.
+pub const WINDOWED_FAMILIES: &[&str] = &[
+    "solana_vote_credits_efficiency",
+    "solana_vote_credits_efficiency_adjusted",
+    "solana_vote_credits_per_slot",
+    "solana_vote_credits_projected",
+    "solana_vote_latency_slots",
+    "solana_missed_vote_credits",
+    "missed_vote_credits",
+];
+
+/// A renamed metric family and the names it was exported under before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricAlias {
+    pub name: &'static str,
+    pub deprecated: &'static [&'static str],
+}
+
+/// Renamed families, still exported under their old names while
+/// `--emit-deprecated-metrics` is on (to be dropped in 0.8.0)
+pub const METRIC_ALIASES: &[MetricAlias] = &[
+    MetricAlias {
+        name: "solana_missed_vote_credits_current_epoch",
+        deprecated: &["missed_vote_credits_current_epoch"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_5m",
+        deprecated: &["missed_vote_credits_5m"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_1h",
+        deprecated: &["missed_vote_credits_1h"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_total",
+        deprecated: &["missed_vote_credits_total"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_maintenance_total",
+        deprecated: &["missed_vote_credits_maintenance_total"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_current_epoch_maintenance",
+        deprecated: &["missed_vote_credits_current_epoch_maintenance"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_last_epoch",
+        deprecated: &["missed_vote_credits_last_epoch"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_last_epoch_adjusted",
+        deprecated: &["missed_vote_credits_last_epoch_adjusted"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_current_epoch_by_anchor",
+        deprecated: &["missed_vote_credits_current_epoch_by_anchor"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_by_fork_regime",
+        deprecated: &["missed_vote_credits_by_fork_regime"],
+    },
+    MetricAlias {
+        name: "solana_missed_vote_credits_current_epoch_skip_adjusted",
+        deprecated: &["missed_vote_credits_current_epoch_skip_adjusted"],
+    },
+];
+
+/// Old name -> current name of every renamed family (`/metrics/aliases`)
+pub fn alias_map() -> BTreeMap<&'static str, &'static str> {
+    METRIC_ALIASES
+        .iter()
+        .flat_map(|alias| alias.deprecated.iter().map(|old| (*old, alias.name)))
+        .collect()
+}
+
+/// What [`Metrics::render`] leaves out for now: the performance families
+/// during the warm-up and a window's families while its history is too short.
+/// Shared with the `<prefix>_*` mirrors of [`Metrics::register_into`].
+#[derive(Clone, Default)]
+struct RenderFilter {
+    warmup: Arc<RwLock<Warmup>>,
+    /// Windows whose history is too short for their families to be rendered
+    insufficient_windows: Arc<Mutex<Vec<HistogramWindow>>>,
+}
+
+impl RenderFilter {
+    fn warmup(&self) -> RwLockReadGuard<'_, Warmup> {
+        self.warmup.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the family `name` is left out
+    fn hides(&self, name: &str) -> bool {
+        if self.warmup().suppresses(name) {
+            return true;
+        }
+        let insufficient = self
+            .insufficient_windows
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        insufficient.iter().any(|window| {
+            name.strip_suffix(window.label())
+                .and_then(|family| family.strip_suffix('_'))
+                .is_some_and(|family| WINDOWED_FAMILIES.contains(&family))
+        })
+    }
+}
+
+/// Exports a family under another name, mirroring the primary collector
+struct AliasCollector {
+    primary: Arc<dyn Collector>,
+    desc: Desc,
+    /// Applied to the primary's own name, see [`AliasCollector::prefixed`]
+    filter: Option<RenderFilter>,
+}
+
+impl AliasCollector {
+    fn new(primary: Arc<dyn Collector>, alias: &str, help: String) -> Result<Self> {
+        let source = primary.desc()[0];
+        let desc = Desc::new(
+            alias.to_string(),
+            help,
+            source.variable_labels.clone(),
+            source
+                .const_label_pairs
+                .iter()
+                .map(|pair| (pair.get_name().to_string(), pair.get_value().to_string()))
+                .collect(),
+        )?;
+        Ok(Self {
+            primary,
+            desc,
+            filter: None,
+        })
+    }
+
+    /// `primary` under its deprecated name `alias`
+    fn deprecated(primary: Arc<dyn Collector>, alias: &str) -> Result<Self> {
+        let source = primary.desc()[0];
+        let help = format!("Deprecated, renamed to {}. {}", source.fq_name, source.help);
+        Self::new(primary, alias, help)
+    }
+
+    /// `primary` with its name prefixed by `prefix_`, left out whenever
+    /// `filter` hides the primary
+    fn prefixed(primary: Arc<dyn Collector>, prefix: &str, filter: RenderFilter) -> Result<Self> {
+        let source = primary.desc()[0];
+        let (name, help) = (
+            format!("{}_{}", prefix, source.fq_name),
+            source.help.clone(),
+        );
+        Ok(Self {
+            filter: Some(filter),
+            ..Self::new(primary, &name, help)?
+        })
+    }
+}
+
+impl Collector for AliasCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        self.primary
+            .collect()
+            .into_iter()
+            .filter(|family| match &self.filter {
+                Some(filter) => !filter.hides(family.get_name()),
+                None => true,
+            })
+            .map(|mut family| {
+                family.set_name(self.desc.fq_name.clone());
+                family.set_help(self.desc.help.clone());
+                family
+            })
+            .collect()
+    }
+}
+
+/// Where a family's values come from, exported as its `source` label. The
+/// validator's families (`solana_*`) are observed or derived; the exporter's
+/// own (connections, RPC errors, scrapes) carry no label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricSource {
+    /// Read as is from the RPC: epochCredits, root slot, stake, balance
+    Observed,
+    /// Inferred by the tracker: missed credits, efficiency, latency, projections
+    Derived,
+    /// About the exporter itself
+    Exporter,
+}
+
+impl MetricSource {
+    /// Value of the `source` label, None for the exporter's families
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            MetricSource::Observed => Some("observed"),
+            MetricSource::Derived => Some("derived"),
+            MetricSource::Exporter => None,
+        }
+    }
+
+    fn from_label(value: Option<&str>) -> Self {
+        match value {
+            Some("observed") => MetricSource::Observed,
+            Some("derived") => MetricSource::Derived,
+            _ => MetricSource::Exporter,
+        }
+    }
+}
+
+/// Options of a family, labelled with its source
+fn opts(source: MetricSource, name: &str, help: &str) -> Opts {
+    let opts = Opts::new(name, help);
+    match source.label() {
+        Some(label) => opts.const_label("source", label),
+        None => opts,
+    }
+}
 
-pub const MAX_CREDITS_PER_SLOT: u64 = 16;
+fn histogram_opts(source: MetricSource, name: &str, help: &str) -> HistogramOpts {
+    HistogramOpts::from(opts(source, name, help))
+}
+
+/// One registered family in `GET /metrics/catalog`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub source: MetricSource,
+    pub help: String,
+    /// Base unit, from the name's suffix (None for counts and flags)
+    pub unit: Option<&'static str>,
+    /// Variable labels, besides `source`
+    pub labels: Vec<String>,
+}
+
+impl CatalogEntry {
+    fn from_desc(desc: &Desc) -> Self {
+        let source = desc
+            .const_label_pairs
+            .iter()
+            .find(|pair| pair.get_name() == "source")
+            .map(|pair| pair.get_value());
+        Self {
+            name: desc.fq_name.clone(),
+            source: MetricSource::from_label(source),
+            help: desc.help.clone(),
+            unit: unit_of(&desc.fq_name),
+            labels: desc.variable_labels.clone(),
+        }
+    }
+}
+
+/// Base unit of a family from its name, following the Prometheus suffixes
+fn unit_of(name: &str) -> Option<&'static str> {
+    let name = name.strip_suffix("_total").unwrap_or(name);
+    const UNITS: [(&str, &str); 8] = [
+        ("_seconds", "seconds"),
+        ("_timestamp", "seconds"),
+        ("_lamports", "lamports"),
+        ("_bytes", "bytes"),
+        ("_ratio", "ratio"),
+        ("_epochs", "epochs"),
+        ("_slots", "slots"),
+        ("_slot", "slot"),
+    ];
+    if let Some((_, unit)) = UNITS.iter().find(|(suffix, _)| name.ends_with(suffix)) {
+        return Some(unit);
+    }
+    if name.contains("efficiency") || name.contains("fraction") {
+        Some("ratio")
+    } else if name.contains("latency_slots") || name.contains("_slots_") {
+        Some("slots")
+    } else if name.contains("credits") {
+        Some("credits")
+    } else {
+        None
+    }
+}
+
+/// Families a registry already had, with the same or other labels, so they
+/// could not be registered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateMetrics {
+    pub names: Vec<String>,
+}
+
+impl fmt::Display for DuplicateMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "metrics already registered: {}", self.names.join(", "))
+    }
+}
+
+impl std::error::Error for DuplicateMetrics {}
+
+impl DuplicateMetrics {
+    /// The families of `collector`, which a registry refused: registering
+    /// a collector of a single family only fails on a name in use
+    fn of(collector: &dyn Collector) -> Self {
+        Self {
+            names: collector.desc().iter().map(|d| d.fq_name.clone()).collect(),
+        }
+    }
+}
+
+/// The registered families, for the catalog and [`Metrics::register_into`]
+#[derive(Default)]
+struct Catalog {
+    entries: Vec<CatalogEntry>,
+    collectors: Vec<Arc<dyn Collector>>,
+}
+
+/// Register `collector` and record its families for the catalog
+fn register<C: Collector + Clone + 'static>(
+    registry: &Registry,
+    catalog: &mut Catalog,
+    collector: &C,
+) -> Result<()> {
+    registry
+        .register(Box::new(collector.clone()))
+        .map_err(|_| DuplicateMetrics::of(collector))?;
+    catalog
+        .entries
+        .extend(collector.desc().into_iter().map(CatalogEntry::from_desc));
+    catalog.collectors.push(Arc::new(collector.clone()));
+    Ok(())
+}
+
+/// Labels of `tvc_config_info`
+const CONFIG_INFO_LABELS: [&str; 9] = [
+    "version",
+    "mode",
+    "commitment",
+    "interval_secs",
+    "cluster",
+    "expected_anchor",
+    "max_credits_per_slot",
+    "histogram_windows",
+    "disabled_metrics",
+];
 
 #[derive(Clone)]
 pub struct Metrics {
     pub registry: Registry,
+    /// Every registered family with its source, sorted by name
+    catalog: Vec<CatalogEntry>,
+    /// Collectors of the registered families
+    collectors: Vec<Arc<dyn Collector>>,
+    /// Deprecated aliases of renamed families (see [`METRIC_ALIASES`])
+    deprecated: Registry,
+    /// Whether the deprecated aliases are rendered
+    emit_deprecated: bool,
+    /// Families left out of `/metrics` and the prefixed mirrors for now
+    filter: RenderFilter,
+    /// Held for writing while an update cycle publishes its gauges and for
+    /// reading while `/metrics` gathers, so a scrape sees one cycle whole
+    publication: Arc<RwLock<()>>,
+    /// Families that are not registered; their update paths are skipped
+    pub disabled: DisabledMetrics,
+    /// Windows the per-credit histogram count and fraction series are set for
+    pub histogram_windows: Vec<HistogramWindow>,
 
     // === Epoch Info ===
     pub epoch: IntGauge,
@@ -20,20 +471,218 @@ pub struct Metrics {
     pub total_epoch_credits: IntGauge,
     /// Maximum possible credits at current slot = (slot_index + 1) × 16
     pub epoch_expected_max: IntGauge,
+    /// First and latest root slot tracked this epoch, the range the epoch numbers cover
+    pub epoch_first_tracked_slot: IntGauge,
+    pub epoch_last_tracked_slot: IntGauge,
     /// Projected credits at epoch end: actual + (remaining_slots × 5m_rate)
     pub projected_credits_5m: IntGauge,
     /// Projected credits at epoch end: actual + (remaining_slots × 1h_rate)
     pub projected_credits_1h: IntGauge,
+    /// Projected credits at epoch end: actual + (remaining_slots × EMA of the rate)
+    pub projected_credits_smoothed: IntGauge,
 
     // === WebSocket Health ===
     pub ws_connected: IntGauge,
     pub ws_errors: IntCounter,
+    /// accountSubscribe rejected by the provider or not confirmed in time
+    pub ws_subscribe_errors: IntCounter,
+    /// Connect, read and write failures
+    pub ws_connection_errors: IntCounter,
+    /// 1 after repeated consecutive subscribe errors, until a subscription is confirmed
+    pub ws_subscription_failed: IntGauge,
+    /// Confirmed subscriptions that delivered no notification in time
+    pub ws_silent_subscriptions: IntCounter,
+    /// 1 when the subscription delivers another encoding than getAccountInfo
+    pub ws_encoding_mismatch: IntGauge,
+    /// 1 for the commitment the subscription's notifications are delivered at
+    pub ws_commitment_effective: IntGaugeVec,
+    /// Notifications dropped unprocessed because newer ones filled the queue
+    pub ws_notifications_shed: IntCounter,
+    /// Notifications processed, no-ops included
+    pub ws_notifications: IntCounter,
+    /// Notifications with the same tower and credits as the previous one
+    pub ws_noop_notifications: IntCounter,
+    /// No-op notifications over all notifications processed
+    pub ws_noop_ratio: Gauge,
+    pub ws_reconnects: IntCounter,
     pub ws_last_message: IntGauge,
+    /// Time spent without a WebSocket connection (after the first attempt)
+    pub ws_disconnected_seconds: Counter,
+    /// Age of the current WebSocket connection, 0 while disconnected
+    pub ws_connection_uptime: Gauge,
+    /// Tracker updates not journaled because the writer fell behind
+    pub journal_records_dropped: IntCounter,
+
+    // === RPC Health (poll mode and watchlist) ===
+    pub rpc_up: IntGauge,
+    pub rpc_errors: IntCounter,
+    pub rpc_last_success: IntGauge,
+    /// Polls whose root slot was behind one seen earlier (a lagging node behind a load balancer)
+    pub rpc_slot_regressions: IntCounter,
+    /// Time RPC requests spent waiting for the --rpc-max-rps budget
+    pub rpc_throttle_wait: Histogram,
+    /// Size of the RPC response bodies by method
+    pub rpc_response_bytes: HistogramVec,
+    /// Bytes of all RPC response bodies
+    pub rpc_received_bytes: IntCounter,
+    /// 1 for the status of the last getHealth, 0 for the others
+    pub rpc_health: IntGaugeVec,
+    /// Slots the RPC node said it is behind the cluster
+    pub rpc_health_behind_slots: IntGauge,
+    /// Configured polling interval (poll mode)
+    pub poll_interval: Gauge,
+    /// End-to-end duration of each poll, retries included
+    pub poll_duration: Histogram,
+    /// Polls by outcome (success, error)
+    pub polls: IntCounterVec,
+    /// Root slot advance between consecutive polls
+    pub rooted_slots_per_poll: Histogram,
+    /// Root slot advance per second over the last poll interval
+    pub root_slot_advance_rate: Gauge,
+    /// Polls whose root slot did not move since the previous one
+    pub polls_no_root_progress: IntCounter,
+    /// Heartbeat pings by outcome (success, failure, skipped while stale)
+    pub heartbeats: IntCounterVec,
+    /// Events that could not be published to the message bus
+    pub bus_publish_errors: IntCounter,
+    /// Events delivered to the --notify sinks, by sink and outcome
+    pub notify_deliveries: IntCounterVec,
+    /// Failed deliveries to a --notify sink that were retried
+    pub notify_retries: IntCounterVec,
+    /// Epoch rollovers, reconnects, restores, maintenance windows and resets by kind
+    pub discontinuities: IntCounterVec,
+
+    // === HTTP Server ===
+    /// Requests served, by route and status code
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    /// Unix timestamp of the last successful /metrics scrape
+    pub metrics_last_scraped: IntGauge,
+    /// 1 while the performance families are held back after startup
+    pub warming: IntGauge,
+    /// Update cycles published, see [`Metrics::begin_publication`]
+    pub update_generation: IntGauge,
+
+    // === Expected Max Anchoring ===
+    /// Expected max credits this epoch, anchored at the root and at the tip
+    pub epoch_expected_max_by_anchor: IntGaugeVec,
+    /// Missed credits this epoch against each anchor (provisional for the tip)
+    pub missed_current_epoch_by_anchor: IntGaugeVec,
+    /// Slots between the root and the tip anchor whose credits are not decided yet
+    pub pending_slots: IntGauge,
+
+    // === Cluster Version ===
+    /// Always 1, labelled with the RPC node's version and feature set
+    pub rpc_version_info: IntGaugeVec,
+    /// 1 if the timely vote credits feature is active on the cluster
+    pub tvc_feature_active: IntGauge,
+
+    // === Configuration ===
+    /// Always 1, labelled with the effective configuration (no secrets)
+    pub config_info: IntGaugeVec,
+    /// Polling interval configured (0 adapts it)
+    pub config_interval: Gauge,
+    /// Length of each histogram window exported (`window` label)
+    pub config_window: GaugeVec,
+    /// Steps back of the system clock beyond the tolerance
+    pub clock_jumps_detected: IntCounter,
+
+    // === Cluster Health ===
+    /// Activated stake of the current (non-delinquent) vote accounts
+    pub cluster_active_stake: IntGauge,
+    /// Activated stake of the delinquent vote accounts
+    pub cluster_delinquent_stake: IntGauge,
+    /// Delinquent / total activated stake
+    pub cluster_delinquent_stake_ratio: Gauge,
+    /// Credits this epoch of the cluster's nth best vote account, by rank `n`
+    pub cluster_credits_rank: IntGaugeVec,
+    /// Credits of the nth best vote account minus the own epoch credits, by rank `n`
+    pub credits_gap_to_rank: IntGaugeVec,
+    /// Stake-weighted mean credits this epoch of the current validators
+    pub cluster_credits_stake_weighted_mean: Gauge,
+    /// Own credits this epoch over that mean
+    pub credits_vs_cluster_ratio: Gauge,
+
+    // === Vote Account History ===
+    /// Number of epochCredits entries of the vote account
+    pub epoch_credits_history_len: IntGauge,
+    /// Oldest epoch with an epochCredits entry
+    pub epoch_credits_oldest_epoch: IntGauge,
+    pub epoch_credits_unsorted: IntCounter,
+    /// Epochs since the oldest entry, present only while the history is
+    /// shorter than the configured minimum
+    pub vote_account_age_epochs: IntGaugeVec,
 
     // === Missed Credits ===
     pub missed_current_epoch: IntGauge,
     pub missed_5m: IntGauge,
     pub missed_1h: IntGauge,
+    /// Cumulative missed credits observed while polling
+    pub missed_total: IntCounter,
+    /// Missed credits observed during maintenance, kept out of the totals
+    pub missed_maintenance_total: IntCounter,
+    /// Missed credits this epoch during maintenance
+    pub missed_current_epoch_maintenance: IntGauge,
+    /// 1 while a maintenance window is in effect
+    pub maintenance_active: IntGauge,
+    /// Seconds left in the maintenance window
+    pub maintenance_remaining: IntGauge,
+    /// Missed credits in the last completed epoch (poll mode)
+    pub missed_last_epoch: IntGauge,
+    /// Missed credits in the last completed epoch, over the slots the account was active in
+    pub missed_last_epoch_adjusted: IntGauge,
+
+    // === Stake (poll mode) ===
+    /// Change in activated stake at the last epoch boundary
+    pub activated_stake_delta: IntGauge,
+    /// Epoch boundaries at which activated stake dropped by more than the configured percentage
+    pub stake_decreases: IntCounter,
+
+    // === Validator Restarts (WebSocket mode) ===
+    /// Vote patterns that looked like a validator restart
+    pub validator_restarts: IntCounter,
+    /// New votes landed while the validator was catching up
+    pub votes_catch_up: IntCounter,
+    /// 1 while recent votes are classified as catch-up
+    pub vote_regime_catch_up: IntGauge,
+    /// 1 while new votes keep landing without the epoch credits increasing
+    pub votes_without_credits: IntGauge,
+    /// 1 while the root slot and epochCredits disagree about the epoch
+    pub epoch_source_disagreement: IntGauge,
+    /// Votes that left the tower without being rooted (abandoned forks, expired lockouts)
+    pub votes_never_rooted: IntCounter,
+    /// Consecutive rooted slots without missed credits
+    pub perfect_slot_streak_current: IntGauge,
+    /// Longest run of rooted slots without missed credits this epoch
+    pub perfect_slot_streak_epoch_max: IntGauge,
+    /// Tracker updates contributing to each window
+    pub updates_in_window: IntGaugeVec,
+    /// Rooted slots covered by each window
+    pub slots_in_window: IntGaugeVec,
+    /// Fraction of each window the history spans
+    pub window_history_coverage: GaugeVec,
+    /// Difference of the 5m efficiency computed from the histogram and from the credit counter
+    pub efficiency_computation_skew_5m: Gauge,
+    /// Difference of the epoch's missed credits from a snapshot and accumulated per update
+    pub missed_accounting_skew: IntGauge,
+    /// `missed_accounting_skew` over the larger of the two counts
+    pub missed_accounting_skew_ratio: Gauge,
+    /// Whether the last update was accounted as turbulent (forking)
+    pub fork_regime_turbulent: IntGauge,
+    /// 5m/1h efficiency of the updates accounted as calm / turbulent
+    pub vote_credits_efficiency_by_fork_regime: GaugeVec,
+    /// 5m/1h missed credits of the updates accounted as calm / turbulent
+    pub missed_by_fork_regime: IntGaugeVec,
+    /// Time from first seeing a vote to its slot being rooted
+    pub slot_root_delay: Histogram,
+    /// Estimated bytes held by the vote tracker's history and pending votes
+    pub tracker_state_bytes: IntGauge,
+    /// Window history entries dropped to stay under --max-state-bytes
+    pub state_evictions: IntCounter,
+    /// Time spent waiting for the shared tracker lock, by op (read, write)
+    pub tracker_lock_wait: HistogramVec,
+    /// Time the shared tracker lock was held, by op (read, write)
+    pub tracker_lock_hold: HistogramVec,
 
     // === Performance Metrics ===
     pub vote_credits_efficiency_5m: Gauge,
@@ -45,205 +694,2285 @@ pub struct Metrics {
     pub vote_latency_slots_5m: Gauge,
     pub vote_latency_slots_1h: Gauge,
     pub vote_latency_slots_epoch: Gauge,
+    /// Lowest 5m efficiency observed this epoch
+    pub vote_credits_efficiency_5m_epoch_min: Gauge,
+    /// Unix timestamp at which the lowest 5m efficiency this epoch was observed
+    pub vote_credits_efficiency_5m_epoch_min_timestamp: IntGauge,
+    /// Rolling ~7-day efficiency per UTC hour of day
+    pub vote_efficiency_by_hour: GaugeVec,
+    /// Efficiency with own leader slots left out (set once the leader schedule is known)
+    pub vote_credits_efficiency_adjusted_5m: Gauge,
+    pub vote_credits_efficiency_adjusted_1h: Gauge,
+    pub vote_credits_efficiency_adjusted_epoch: Gauge,
+    /// Own leader slots in the current epoch's leader schedule
+    pub leader_slots_current_epoch: IntGauge,
+    /// Rooted slots without a block (--track-skipped-slots)
+    pub cluster_skipped_slots: IntCounter,
+    /// Missed credits this epoch less those on skipped slots (--track-skipped-slots)
+    pub missed_current_epoch_skip_adjusted: IntGauge,
+    /// Rooted slots passed while the vote subscription was down
+    pub unobserved_rooted_slots: IntCounter,
+    /// Credits those unobserved slots could have earned
+    pub unobserved_expected_credits: IntCounter,
+    /// Credits earned while the exporter was down (--state-file)
+    pub offline_catchup_credits: IntGauge,
+    /// Credits missed while the exporter was down (--state-file)
+    pub offline_catchup_missed: IntGauge,
+    /// Balance of the vote account (WebSocket mode only)
+    pub vote_account_lamports: IntGauge,
+    /// Length of the vote account data (WebSocket mode only)
+    pub vote_account_data_bytes: IntGauge,
+    /// Parsed account type and detected vote state version (WebSocket mode only)
+    pub vote_account_state_info: IntGaugeVec,
+    /// Prior authorized voters kept by the vote account (WebSocket mode only)
+    pub prior_voter_entries: IntGauge,
+    /// Whether the balance covers the rent-exempt minimum (WebSocket mode only)
+    pub vote_account_rent_exempt: IntGauge,
+    /// Balance above the rent-exempt minimum, negative below it (WebSocket mode only)
+    pub vote_account_rent_exempt_margin: IntGauge,
+    /// Vote transactions landed for the vote account (--count-vote-txs)
+    pub vote_transactions: IntCounter,
+    /// Vote transactions landed per minute between the last two checks (--count-vote-txs)
+    pub vote_transactions_per_minute: Gauge,
+    /// Fees paid for the counted vote transactions; no labels, so absent
+    /// until a check counted transactions (--count-vote-txs)
+    pub vote_fees_lamports: IntCounterVec,
+    /// Vote fees per day at the last hour's transaction rate; no labels,
+    /// absent without a counted check in the last hour (--count-vote-txs)
+    pub vote_fees_per_day_estimate: GaugeVec,
 
     // === Histograms (detailed per-vote data) ===
     /// Histogram: vote count by credits earned (0-16) per window (5m, 1h, epoch)
     pub vote_credits_histogram_count: IntGaugeVec,
     /// Histogram: relative fraction by credits earned (0-16) per window
     pub vote_credits_histogram_fraction: GaugeVec,
+    /// Votes by credits earned (0-16) since the tracker started, never reset
+    pub votes_by_credits: IntCounterVec,
+
+    // === Watchlist (per vote_pubkey) ===
+    pub watchlist_credits_epoch: IntGaugeVec,
+    pub watchlist_efficiency_epoch: GaugeVec,
+    pub watchlist_missed_epoch: IntGaugeVec,
+    pub watchlist_delinquent: IntGaugeVec,
+    /// Unix timestamp each watchlist validator was last refreshed at
+    pub watchlist_last_refresh: IntGaugeVec,
 }
 
 impl Metrics {
+    /// Metrics with the default families (no lock timings)
     pub fn new() -> Result<Self> {
+        Self::with_disabled(DisabledMetrics::LOCKS)
+    }
+
+    /// Metrics with the default families, also exported into an application's
+    /// own `registry` with every name prefixed by `prefix_`, so one scrape
+    /// endpoint serves both. See [`Metrics::register_into`].
+    pub fn new_with_registry(registry: Registry, prefix: &str) -> Result<Self> {
+        let metrics = Self::new()?;
+        metrics.register_into(&registry, prefix)?;
+        Ok(metrics)
+    }
+
+    /// Metrics without the `disabled` families
+    pub fn with_disabled(disabled: DisabledMetrics) -> Result<Self> {
         let registry = Registry::new();
+        let mut catalog = Catalog::default();
 
-        let epoch = IntGauge::with_opts(Opts::new("solana_epoch", "Current epoch number"))?;
+        let epoch = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_epoch",
+            "Current epoch number",
+        ))?;
 
-        let slot_index = IntGauge::with_opts(Opts::new(
+        let slot_index = IntGauge::with_opts(opts(
+            MetricSource::Observed,
             "solana_slot_index",
             "Current slot index within the epoch (0 to 431999)",
         ))?;
 
-        let total_epoch_credits = IntGauge::with_opts(Opts::new(
+        let total_epoch_credits = IntGauge::with_opts(opts(
+            MetricSource::Observed,
             "solana_vote_credits_epoch",
             "Vote credits earned this epoch from vote account (credits - previous_credits)",
         ))?;
 
-        let epoch_expected_max = IntGauge::with_opts(Opts::new(
+        let epoch_expected_max = IntGauge::with_opts(opts(
+            MetricSource::Derived,
             "solana_vote_credits_epoch_expected_max",
             "Maximum possible vote credits at current slot = (slot_index + 1) × 16",
         ))?;
 
-        let projected_credits_5m = IntGauge::with_opts(Opts::new(
+        let epoch_first_tracked_slot = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_epoch_first_tracked_slot",
+            "First root slot tracked this epoch",
+        ))?;
+
+        let epoch_last_tracked_slot = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_epoch_last_tracked_slot",
+            "Latest root slot tracked this epoch",
+        ))?;
+
+        let projected_credits_5m = IntGauge::with_opts(opts(
+            MetricSource::Derived,
             "solana_vote_credits_projected_5m",
             "Projected credits at epoch end: actual + (remaining_slots × 5m_rate)",
         ))?;
 
-        let projected_credits_1h = IntGauge::with_opts(Opts::new(
+        let projected_credits_1h = IntGauge::with_opts(opts(
+            MetricSource::Derived,
             "solana_vote_credits_projected_1h",
             "Projected credits at epoch end: actual + (remaining_slots × 1h_rate)",
         ))?;
 
-        let ws_connected = IntGauge::with_opts(Opts::new(
+        let projected_credits_smoothed = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_projected_smoothed",
+            "Projected credits at epoch end: actual + (remaining_slots × exponentially smoothed rate)",
+        ))?;
+
+        let ws_connected = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
             "ws_connected",
             "1 if WebSocket is connected, 0 otherwise",
         ))?;
 
-        let ws_errors = IntCounter::with_opts(Opts::new(
+        let ws_errors = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
             "ws_errors",
             "Number of WebSocket connection/message errors",
         ))?;
 
-        let ws_last_message = IntGauge::with_opts(Opts::new(
+        let ws_subscribe_errors = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_subscribe_errors_total",
+            "accountSubscribe requests rejected by the RPC or not confirmed in time",
+        ))?;
+
+        let ws_connection_errors = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_connection_errors_total",
+            "WebSocket connect, read and write failures",
+        ))?;
+
+        let ws_subscription_failed = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_subscription_failed",
+            "1 if accountSubscribe failed repeatedly in a row, 0 otherwise",
+        ))?;
+
+        let ws_notifications_shed = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_notifications_shed_total",
+            "Notifications dropped unprocessed because processing fell behind and newer ones filled the queue",
+        ))?;
+
+        let ws_notifications = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_notifications_total",
+            "Account notifications processed, no-ops included",
+        ))?;
+
+        let ws_noop_notifications = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_noop_notifications_total",
+            "Notifications with the same root, newest vote and credits as the previous one, skipped",
+        ))?;
+
+        let ws_noop_ratio = Gauge::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_noop_ratio",
+            "Fraction of the notifications processed since startup that were no-ops",
+        ))?;
+
+        let ws_silent_subscriptions = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_silent_subscriptions_total",
+            "Subscriptions confirmed but without a notification within --ws-first-message-timeout-secs",
+        ))?;
+
+        let ws_encoding_mismatch = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_encoding_mismatch",
+            "1 if the subscription's first notification came in another encoding than getAccountInfo with the same parameters",
+        ))?;
+
+        let ws_commitment_effective = IntGaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "ws_commitment_effective",
+                "1 for the commitment the subscription's notifications are delivered at (finalized, or unfinalized when ahead of the finalized slot)",
+            ),
+            &["level"],
+        )?;
+
+        let ws_reconnects = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_reconnects",
+            "Number of WebSocket reconnects",
+        ))?;
+
+        let ws_last_message = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
             "ws_last_message",
             "Unix timestamp of last successful WebSocket message",
         ))?;
 
-        let missed_current_epoch = IntGauge::with_opts(Opts::new(
-            "missed_vote_credits_current_epoch",
-            "Number of timely vote credits missed this epoch",
+        let ws_disconnected_seconds = Counter::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_disconnected_seconds_total",
+            "Seconds spent without a WebSocket connection while reconnecting",
         ))?;
 
-        let missed_5m = IntGauge::with_opts(Opts::new(
-            "missed_vote_credits_5m",
-            "Number of timely vote credits missed the past 5 minutes",
+        let ws_connection_uptime = Gauge::with_opts(opts(
+            MetricSource::Exporter,
+            "ws_connection_uptime_seconds",
+            "Seconds since the current WebSocket connection was established",
         ))?;
 
-        let missed_1h = IntGauge::with_opts(Opts::new(
-            "missed_vote_credits_1h",
-            "Number of timely vote credits missed the past 1 hour",
+        let journal_records_dropped = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "journal_records_dropped_total",
+            "Tracker updates not written to --journal-dir because its buffer was full",
         ))?;
 
-        let vote_credits_efficiency_5m = Gauge::with_opts(Opts::new(
-            "solana_vote_credits_efficiency_5m",
-            "Fraction of max vote credits earned (5-minute window, 1.0 = 100%)",
+        let rpc_up = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "rpc_up",
+            "1 if the last RPC poll succeeded, 0 otherwise",
         ))?;
 
-        let vote_credits_efficiency_1h = Gauge::with_opts(Opts::new(
-            "solana_vote_credits_efficiency_1h",
-            "Fraction of max vote credits earned (1-hour window, 1.0 = 100%)",
+        let rpc_errors = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "rpc_errors",
+            "Number of RPC errors",
         ))?;
 
-        let vote_credits_efficiency_epoch = Gauge::with_opts(Opts::new(
-            "solana_vote_credits_efficiency_epoch",
-            "Fraction of max vote credits earned this epoch (1.0 = 100%)",
+        let rpc_slot_regressions = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "rpc_slot_regressions_total",
+            "Polls skipped because the RPC reported a root slot behind one seen earlier",
         ))?;
 
-        let vote_credits_per_slot_5m = Gauge::with_opts(Opts::new(
-            "solana_vote_credits_per_slot_5m",
-            "Average vote credits earned per slot (5-minute window, max 16)",
+        let rpc_last_success = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "rpc_last_success",
+            "Unix timestamp of last successful RPC poll",
         ))?;
 
-        let vote_credits_per_slot_1h = Gauge::with_opts(Opts::new(
-            "solana_vote_credits_per_slot_1h",
-            "Average vote credits earned per slot (1-hour window, max 16)",
+        let rpc_throttle_wait = Histogram::with_opts(
+            histogram_opts(
+                MetricSource::Exporter,
+                "rpc_throttle_wait_seconds",
+                "Time RPC requests waited for the rate limiter",
+            )
+            .buckets(vec![0.001, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
+        )?;
+
+        let rpc_response_bytes = HistogramVec::new(
+            histogram_opts(
+                MetricSource::Exporter,
+                "rpc_response_bytes",
+                "Size of the RPC response bodies",
+            )
+            .buckets(prometheus::exponential_buckets(256.0, 4.0, 10)?),
+            &["method"],
+        )?;
+
+        let rpc_received_bytes = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "rpc_received_bytes_total",
+            "Bytes of RPC response bodies received",
         ))?;
 
-        let vote_credits_per_slot_epoch = Gauge::with_opts(Opts::new(
-            "solana_vote_credits_per_slot_epoch",
-            "Average vote credits earned per slot this epoch (max 16)",
+        let rpc_health = IntGaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "rpc_health",
+                "1 for the status getHealth of the RPC node last answered with (ok, behind, unknown)",
+            ),
+            &["status"],
+        )?;
+
+        let rpc_health_behind_slots = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "rpc_health_behind_slots",
+            "Slots the RPC node reported being behind the cluster (0 while healthy)",
         ))?;
 
-        let vote_latency_slots_5m = Gauge::with_opts(Opts::new(
-            "solana_vote_latency_slots_5m",
-            "Average vote latency in slots (5-minute window, 1 = fastest)",
+        let poll_interval = Gauge::with_opts(opts(
+            MetricSource::Exporter,
+            "poll_interval_seconds",
+            "Configured interval between vote account polls (poll mode)",
         ))?;
 
-        let vote_latency_slots_1h = Gauge::with_opts(Opts::new(
-            "solana_vote_latency_slots_1h",
-            "Average vote latency in slots (1-hour window, 1 = fastest)",
+        let poll_duration = Histogram::with_opts(
+            histogram_opts(
+                MetricSource::Exporter,
+                "poll_duration_seconds",
+                "End-to-end duration of each vote account poll, retries included",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+        )?;
+
+        let polls = IntCounterVec::new(
+            opts(
+                MetricSource::Exporter,
+                "polls_total",
+                "Vote account polls by outcome",
+            ),
+            &["outcome"],
+        )?;
+
+        let rooted_slots_per_poll = Histogram::with_opts(
+            histogram_opts(
+                MetricSource::Observed,
+                "solana_rooted_slots_per_poll",
+                "Root slot advance between consecutive vote account polls",
+            )
+            .buckets(vec![
+                0.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0, 512.0, 1024.0, 2048.0,
+            ]),
+        )?;
+
+        let root_slot_advance_rate = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_root_slot_advance_rate",
+            "Root slot advance per second over the last poll interval",
         ))?;
 
-        let vote_latency_slots_epoch = Gauge::with_opts(Opts::new(
-            "solana_vote_latency_slots_epoch",
-            "Average vote latency in slots this epoch (1 = fastest)",
+        let polls_no_root_progress = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "polls_with_no_root_progress_total",
+            "Polls whose root slot did not move since the previous poll",
         ))?;
 
-        let vote_credits_histogram_count = IntGaugeVec::new(
-            Opts::new(
-                "solana_vote_credits_histogram_count",
-                "Number of votes earning each credit value (0-16) per window",
+        let heartbeats = IntCounterVec::new(
+            opts(
+                MetricSource::Exporter,
+                "heartbeats_total",
+                "Heartbeat pings to --heartbeat-url by outcome (skipped while the data is stale)",
             ),
-            &["window", "credits"],
+            &["outcome"],
         )?;
 
-        let vote_credits_histogram_fraction = GaugeVec::new(
-            Opts::new(
-                "solana_vote_credits_histogram_fraction",
-                "Fraction of votes earning each credit value (0-16) per window",
+        let bus_publish_errors = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "bus_publish_errors_total",
+            "Events not published to --nats-url (publish failures and events dropped while behind)",
+        ))?;
+
+        let notify_deliveries = IntCounterVec::new(
+            opts(
+                MetricSource::Exporter,
+                "notify_deliveries_total",
+                "Events delivered to the --notify sinks, by sink and outcome (failure after the retries, dropped while behind, alerts suppressed during maintenance)",
             ),
-            &["window", "credits"],
+            &["sink", "outcome"],
         )?;
 
-        // Register all metrics
-        registry.register(Box::new(epoch.clone()))?;
-        registry.register(Box::new(slot_index.clone()))?;
-        registry.register(Box::new(total_epoch_credits.clone()))?;
-        registry.register(Box::new(epoch_expected_max.clone()))?;
-        registry.register(Box::new(projected_credits_5m.clone()))?;
-        registry.register(Box::new(projected_credits_1h.clone()))?;
-        registry.register(Box::new(ws_connected.clone()))?;
-        registry.register(Box::new(ws_errors.clone()))?;
-        registry.register(Box::new(ws_last_message.clone()))?;
-        registry.register(Box::new(missed_current_epoch.clone()))?;
-        registry.register(Box::new(missed_5m.clone()))?;
-        registry.register(Box::new(missed_1h.clone()))?;
-        registry.register(Box::new(vote_credits_efficiency_5m.clone()))?;
-        registry.register(Box::new(vote_credits_efficiency_1h.clone()))?;
-        registry.register(Box::new(vote_credits_efficiency_epoch.clone()))?;
-        registry.register(Box::new(vote_credits_per_slot_5m.clone()))?;
-        registry.register(Box::new(vote_credits_per_slot_1h.clone()))?;
-        registry.register(Box::new(vote_credits_per_slot_epoch.clone()))?;
-        registry.register(Box::new(vote_latency_slots_5m.clone()))?;
-        registry.register(Box::new(vote_latency_slots_1h.clone()))?;
-        registry.register(Box::new(vote_latency_slots_epoch.clone()))?;
-        registry.register(Box::new(vote_credits_histogram_count.clone()))?;
-        registry.register(Box::new(vote_credits_histogram_fraction.clone()))?;
+        let notify_retries = IntCounterVec::new(
+            opts(
+                MetricSource::Exporter,
+                "notify_retries_total",
+                "Failed deliveries to a --notify sink that were retried",
+            ),
+            &["sink"],
+        )?;
 
-        Ok(Self {
-            registry,
-            epoch,
-            slot_index,
-            total_epoch_credits,
-            epoch_expected_max,
-            projected_credits_5m,
-            projected_credits_1h,
-            ws_connected,
-            ws_errors,
-            ws_last_message,
-            missed_current_epoch,
-            missed_5m,
-            missed_1h,
-            vote_credits_efficiency_5m,
-            vote_credits_efficiency_1h,
-            vote_credits_efficiency_epoch,
-            vote_credits_per_slot_5m,
-            vote_credits_per_slot_1h,
-            vote_credits_per_slot_epoch,
-            vote_latency_slots_5m,
-            vote_latency_slots_1h,
-            vote_latency_slots_epoch,
-            vote_credits_histogram_count,
-            vote_credits_histogram_fraction,
-        })
-    }
+        let discontinuities = IntCounterVec::new(
+            opts(
+                MetricSource::Exporter,
+                "discontinuities_total",
+                "State changes that break the continuity of the numbers, by kind (see /discontinuities)",
+            ),
+            &["kind"],
+        )?;
+        for kind in DiscontinuityEvent::KINDS {
+            discontinuities.with_label_values(&[kind]);
+        }
 
-    pub fn render(&self) -> (HeaderMap, String) {
-        let families = self.registry.gather();
-        let encoder = TextEncoder::new();
-        let mut buf = Vec::new();
-        encoder.encode(&families, &mut buf).expect("encode metrics");
-        let body = String::from_utf8(buf).expect("utf8 metrics");
+        let http_requests = IntCounterVec::new(
+            opts(
+                MetricSource::Exporter,
+                "http_requests_total",
+                "HTTP requests served",
+            ),
+            &["path", "status"],
+        )?;
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            axum::http::header::CONTENT_TYPE,
-            HeaderValue::from_str(encoder.format_type()).unwrap(),
-        );
-        (headers, body)
-    }
-}
+        let http_request_duration = HistogramVec::new(
+            histogram_opts(
+                MetricSource::Exporter,
+                "http_request_duration_seconds",
+                "Time to serve HTTP requests",
+            )
+            .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]),
+            &["path"],
+        )?;
+
+        let warming = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_warming",
+            "1 while the performance metrics are left out after startup, until enough data was seen",
+        ))?;
+
+        let update_generation = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_update_generation",
+            "Number of metric update cycles (polls or processed notifications) published",
+        ))?;
+
+        let metrics_last_scraped = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "metrics_last_scraped_seconds",
+            "Unix timestamp of the last successful /metrics scrape",
+        ))?;
+
+        let epoch_expected_max_by_anchor = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_credits_expected_max_by_anchor",
+                "Max credits this epoch up to the root or up to the tip minus the allowance",
+            ),
+            &["anchor"],
+        )?;
+
+        let missed_current_epoch_by_anchor = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_missed_vote_credits_current_epoch_by_anchor",
+                "Credits missed this epoch against the root- or tip-anchored expectation",
+            ),
+            &["anchor"],
+        )?;
+
+        let pending_slots = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_vote_credits_pending_slots",
+            "Slots between the root and the tip anchor whose credits are not decided yet",
+        ))?;
+
+        let rpc_version_info = IntGaugeVec::new(
+            opts(
+                MetricSource::Observed,
+                "solana_rpc_version_info",
+                "Version and feature set reported by the RPC node (always 1)",
+            ),
+            &["version", "feature_set"],
+        )?;
+
+        let config_info = IntGaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "tvc_config_info",
+                "Effective configuration of the tracker (always 1)",
+            ),
+            &CONFIG_INFO_LABELS,
+        )?;
+
+        let config_interval = Gauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_config_interval_seconds",
+            "Polling interval configured with --interval-secs (0: adaptive)",
+        ))?;
+
+        let config_window = GaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "tvc_config_window_seconds",
+                "Length of each histogram window exported",
+            ),
+            &["window"],
+        )?;
+
+        let clock_jumps_detected = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "clock_jumps_detected_total",
+            "Steps back of the system clock by more than a second; time is held until it catches up",
+        ))?;
+
+        let tvc_feature_active = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_tvc_feature_active",
+            "1 if the timely vote credits feature is active on the cluster, 0 otherwise",
+        ))?;
 
-pub async fn metrics_handler(metrics: Arc<Metrics>) -> (HeaderMap, String) {
-    metrics.render()
+        let cluster_active_stake = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_cluster_active_stake_lamports",
+            "Activated stake of the cluster's current (non-delinquent) vote accounts",
+        ))?;
+
+        let cluster_delinquent_stake = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_cluster_delinquent_stake_lamports",
+            "Activated stake of the cluster's delinquent vote accounts",
+        ))?;
+
+        let cluster_delinquent_stake_ratio = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_cluster_delinquent_stake_ratio",
+            "Delinquent / total activated stake of the cluster",
+        ))?;
+
+        let cluster_credits_rank = IntGaugeVec::new(
+            opts(
+                MetricSource::Observed,
+                "solana_cluster_credits_rank_n",
+                "Credits this epoch of the cluster's nth best vote account",
+            ),
+            &["n"],
+        )?;
+
+        let credits_gap_to_rank = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_credits_gap_to_rank",
+                "Credits this epoch of the cluster's nth best vote account minus the own",
+            ),
+            &["n"],
+        )?;
+
+        let cluster_credits_stake_weighted_mean = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_cluster_credits_stake_weighted_mean",
+            "Stake-weighted mean credits this epoch of the current (non-delinquent) validators",
+        ))?;
+
+        let credits_vs_cluster_ratio = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_credits_vs_cluster_ratio",
+            "Own credits this epoch over the cluster's stake-weighted mean",
+        ))?;
+
+        let epoch_credits_history_len = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_epoch_credits_history_len",
+            "Number of epochs of credit history in the vote account",
+        ))?;
+
+        let epoch_credits_oldest_epoch = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_epoch_credits_oldest_epoch",
+            "Oldest epoch in the vote account's credit history",
+        ))?;
+
+        let epoch_credits_unsorted = IntCounter::with_opts(opts(
+            MetricSource::Observed,
+            "solana_epoch_credits_unsorted_total",
+            "Vote account updates whose epochCredits entries were out of epoch order",
+        ))?;
+
+        let vote_account_age_epochs = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_account_age_epochs",
+                "Epochs since the oldest credit history entry, exported only while the history is shorter than min_epochs",
+            ),
+            &["min_epochs"],
+        )?;
+
+        let missed_current_epoch = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_current_epoch",
+            "Number of timely vote credits missed this epoch",
+        ))?;
+
+        let missed_5m = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_5m",
+            "Number of timely vote credits missed the past 5 minutes",
+        ))?;
+
+        let missed_1h = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_1h",
+            "Number of timely vote credits missed the past 1 hour",
+        ))?;
+
+        let missed_total = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_total",
+            "Cumulative number of timely vote credits missed while polling",
+        ))?;
+
+        let missed_maintenance_total = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_maintenance_total",
+            "Timely vote credits missed during maintenance, not counted in the other missed metrics",
+        ))?;
+
+        let missed_current_epoch_maintenance = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_current_epoch_maintenance",
+            "Timely vote credits missed this epoch during maintenance",
+        ))?;
+
+        let maintenance_active = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_maintenance_active",
+            "1 while a maintenance window is in effect",
+        ))?;
+
+        let maintenance_remaining = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_maintenance_remaining_seconds",
+            "Seconds left in the maintenance window",
+        ))?;
+
+        let missed_last_epoch = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_last_epoch",
+            "Number of timely vote credits missed in the last completed epoch",
+        ))?;
+
+        let missed_last_epoch_adjusted = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_last_epoch_adjusted",
+            "Timely vote credits missed in the last completed epoch, counted only over the slots an account created during it can have voted in",
+        ))?;
+
+        let activated_stake_delta = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_activated_stake_delta_lamports",
+            "Change in activated stake (lamports) at the last epoch boundary",
+        ))?;
+
+        let stake_decreases = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_stake_decreases_total",
+            "Number of epoch boundaries at which activated stake dropped by more than the configured percentage",
+        ))?;
+
+        let validator_restarts = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_validator_restarts_detected_total",
+            "Vote gaps followed by a collapsed tower, suggesting a validator restart",
+        ))?;
+
+        let votes_catch_up = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_votes_catch_up_total",
+            "Votes landed while the median latency of recent votes indicated catch-up",
+        ))?;
+
+        let vote_regime_catch_up = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_regime_catch_up",
+            "1 while recent votes are classified as catch-up, 0 when steady",
+        ))?;
+
+        let votes_without_credits = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_votes_without_credits",
+            "1 while consecutive updates root new votes without the epoch credits increasing",
+        ))?;
+
+        let epoch_source_disagreement = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_epoch_source_disagreement",
+            "1 while the root slot is in another epoch than the newest epochCredits entry",
+        ))?;
+
+        let perfect_slot_streak_current = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_perfect_slot_streak_current",
+            "Consecutive rooted slots that earned the full credits",
+        ))?;
+
+        let perfect_slot_streak_epoch_max = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_perfect_slot_streak_epoch_max",
+            "Longest run of rooted slots that earned the full credits this epoch",
+        ))?;
+
+        let updates_in_window = IntGaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "tvc_updates_in_window",
+                "Tracker updates contributing to each window",
+            ),
+            &["window"],
+        )?;
+
+        let slots_in_window = IntGaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "tvc_slots_in_window",
+                "Rooted slots covered by each window",
+            ),
+            &["window"],
+        )?;
+
+        let window_history_coverage = GaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "tvc_window_history_coverage",
+                "Fraction of each window the history spans; under 0.2 the window's gauges are not exported",
+            ),
+            &["window"],
+        )?;
+
+        let efficiency_computation_skew_5m = Gauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_efficiency_computation_skew_5m",
+            "Absolute difference between the 5m efficiency from the credits histogram and from the account's credit counter over the same window",
+        ))?;
+
+        let missed_accounting_skew = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_missed_accounting_skew",
+            "Absolute difference between the missed credits this epoch from the account snapshot (expected minus actual) and accumulated update by update",
+        ))?;
+
+        let missed_accounting_skew_ratio = Gauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_missed_accounting_skew_ratio",
+            "tvc_missed_accounting_skew over the larger of the two missed credit counts (0 when both are 0)",
+        ))?;
+
+        let fork_regime_turbulent = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_fork_regime_turbulent",
+            "Whether the cluster looked to be forking at the last update (1) or calm (0)",
+        ))?;
+
+        let vote_credits_efficiency_by_fork_regime = GaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_credits_efficiency_by_fork_regime",
+                "Windowed efficiency of the updates accounted while the cluster was calm or forking",
+            ),
+            &["window", "regime"],
+        )?;
+
+        let missed_by_fork_regime = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_missed_vote_credits_by_fork_regime",
+                "Windowed missed credits of the updates accounted while the cluster was calm or forking",
+            ),
+            &["window", "regime"],
+        )?;
+
+        let votes_never_rooted = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_votes_never_rooted_total",
+            "Votes seen in the tower that were never rooted and so earned no credits",
+        ))?;
+
+        let slot_root_delay = Histogram::with_opts(
+            histogram_opts(
+                MetricSource::Derived,
+                "solana_slot_root_delay_seconds",
+                "Wall time from a vote first appearing in the tower to its slot being rooted",
+            )
+            .buckets(vec![
+                5.0, 10.0, 12.5, 15.0, 17.5, 20.0, 25.0, 30.0, 45.0, 60.0, 120.0, 300.0,
+            ]),
+        )?;
+
+        let tracker_state_bytes = IntGauge::with_opts(opts(
+            MetricSource::Exporter,
+            "tvc_tracker_state_bytes",
+            "Estimated bytes held by the vote tracker's window history, pending votes and leader slots",
+        ))?;
+
+        let state_evictions = IntCounter::with_opts(opts(
+            MetricSource::Exporter,
+            "state_evictions_total",
+            "Window history entries dropped to keep the vote tracker under --max-state-bytes",
+        ))?;
+
+        let tracker_lock_wait = HistogramVec::new(
+            histogram_opts(
+                MetricSource::Exporter,
+                "tracker_lock_wait_seconds",
+                "Time spent waiting to lock the shared vote tracker",
+            )
+            .buckets(LOCK_BUCKETS.to_vec()),
+            &["op"],
+        )?;
+
+        let tracker_lock_hold = HistogramVec::new(
+            histogram_opts(
+                MetricSource::Exporter,
+                "tracker_lock_hold_seconds",
+                "Time the shared vote tracker lock was held",
+            )
+            .buckets(LOCK_BUCKETS.to_vec()),
+            &["op"],
+        )?;
+
+        let vote_credits_efficiency_5m = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_5m",
+            "Fraction of max vote credits earned (5-minute window, 1.0 = 100%)",
+        ))?;
+
+        let vote_credits_efficiency_1h = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_1h",
+            "Fraction of max vote credits earned (1-hour window, 1.0 = 100%)",
+        ))?;
+
+        let vote_credits_efficiency_epoch = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_epoch",
+            "Fraction of max vote credits earned this epoch (1.0 = 100%)",
+        ))?;
+
+        let vote_credits_efficiency_adjusted_5m = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_adjusted_5m",
+            "Fraction of max vote credits earned excluding own leader slots (5-minute window)",
+        ))?;
+
+        let vote_credits_efficiency_adjusted_1h = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_adjusted_1h",
+            "Fraction of max vote credits earned excluding own leader slots (1-hour window)",
+        ))?;
+
+        let vote_credits_efficiency_adjusted_epoch = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_adjusted_epoch",
+            "Fraction of max vote credits earned excluding own leader slots (epoch, while tracked)",
+        ))?;
+
+        let leader_slots_current_epoch = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_leader_slots_current_epoch",
+            "Own leader slots in the current epoch's leader schedule",
+        ))?;
+
+        let cluster_skipped_slots = IntCounter::with_opts(opts(
+            MetricSource::Observed,
+            "solana_cluster_skipped_slots_total",
+            "Rooted slots the cluster produced no block for, from getBlocks",
+        ))?;
+
+        let missed_current_epoch_skip_adjusted = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_missed_vote_credits_current_epoch_skip_adjusted",
+            "Timely vote credits missed this epoch, less those on slots the cluster skipped",
+        ))?;
+
+        let unobserved_rooted_slots = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_unobserved_rooted_slots_total",
+            "Rooted slots passed between a vote subscription dropping and the next root seen",
+        ))?;
+
+        let unobserved_expected_credits = IntCounter::with_opts(opts(
+            MetricSource::Derived,
+            "solana_unobserved_expected_credits_total",
+            "Vote credits the unobserved rooted slots could have earned",
+        ))?;
+
+        let offline_catchup_credits = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_offline_catchup_credits",
+            "Vote credits earned between the root in the state file and the root at startup",
+        ))?;
+
+        let offline_catchup_missed = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_offline_catchup_missed",
+            "Vote credits missed between the root in the state file and the root at startup",
+        ))?;
+
+        let vote_account_lamports = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_vote_account_lamports",
+            "Balance of the vote account in lamports",
+        ))?;
+
+        let vote_account_data_bytes = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_vote_account_data_bytes",
+            "Length of the vote account data in bytes",
+        ))?;
+
+        let prior_voter_entries = IntGauge::with_opts(opts(
+            MetricSource::Observed,
+            "solana_prior_voter_entries",
+            "Prior authorized voters kept by the vote account, up to 32",
+        ))?;
+
+        let vote_account_state_info = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_account_state_info",
+                "Parsed type of the vote account and the vote state version its fields point to (always 1)",
+            ),
+            &["account_type", "version"],
+        )?;
+
+        let vote_account_rent_exempt = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_account_rent_exempt",
+            "Whether the vote account balance covers the rent-exempt minimum (1) or not (0)",
+        ))?;
+
+        let vote_account_rent_exempt_margin = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_account_rent_exempt_margin_lamports",
+            "Vote account balance above the rent-exempt minimum (negative below it)",
+        ))?;
+
+        let vote_transactions = IntCounter::with_opts(opts(
+            MetricSource::Observed,
+            "solana_vote_transactions_total",
+            "Vote transactions landed for the vote account, from getSignaturesForAddress",
+        ))?;
+
+        let vote_transactions_per_minute = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_transactions_per_minute",
+            "Vote transactions landed per minute between the last two checks",
+        ))?;
+
+        let vote_fees_lamports = IntCounterVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_fees_lamports_total",
+                "Fees in lamports of the vote transactions counted",
+            ),
+            &[],
+        )?;
+
+        let vote_fees_per_day_estimate = GaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_fees_per_day_estimate",
+                "Vote fees in lamports per day at the vote transaction rate of the last hour",
+            ),
+            &[],
+        )?;
+
+        let vote_credits_per_slot_5m = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_per_slot_5m",
+            "Average vote credits earned per slot (5-minute window, max 16)",
+        ))?;
+
+        let vote_credits_per_slot_1h = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_per_slot_1h",
+            "Average vote credits earned per slot (1-hour window, max 16)",
+        ))?;
+
+        let vote_credits_per_slot_epoch = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_per_slot_epoch",
+            "Average vote credits earned per slot this epoch (max 16)",
+        ))?;
+
+        let vote_latency_slots_5m = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_latency_slots_5m",
+            "Average vote latency in slots (5-minute window, 1 = fastest)",
+        ))?;
+
+        let vote_latency_slots_1h = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_latency_slots_1h",
+            "Average vote latency in slots (1-hour window, 1 = fastest)",
+        ))?;
+
+        let vote_latency_slots_epoch = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_latency_slots_epoch",
+            "Average vote latency in slots this epoch (1 = fastest)",
+        ))?;
+
+        let vote_credits_efficiency_5m_epoch_min = Gauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_5m_epoch_min",
+            "Lowest 5-minute efficiency observed this epoch (1.0 until a full window was observed)",
+        ))?;
+
+        let vote_credits_efficiency_5m_epoch_min_timestamp = IntGauge::with_opts(opts(
+            MetricSource::Derived,
+            "solana_vote_credits_efficiency_5m_epoch_min_timestamp",
+            "Unix timestamp at which the lowest 5-minute efficiency this epoch was observed",
+        ))?;
+
+        let vote_efficiency_by_hour = GaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_efficiency_by_hour",
+                "Efficiency per UTC hour of day, averaged over about a week with older days decayed",
+            ),
+            &["hour"],
+        )?;
+
+        let vote_credits_histogram_count = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_credits_histogram_count",
+                "Number of votes earning each credit value (0-16) per window",
+            ),
+            &["window", "credits"],
+        )?;
+
+        let vote_credits_histogram_fraction = GaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_vote_credits_histogram_fraction",
+                "Fraction of votes earning each credit value (0-16) per window",
+            ),
+            &["window", "credits"],
+        )?;
+
+        let votes_by_credits = IntCounterVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_votes_by_credits_total",
+                "Votes earning each credit value (0-16) since the tracker started",
+            ),
+            &["credits"],
+        )?;
+
+        let watchlist_credits_epoch = IntGaugeVec::new(
+            opts(
+                MetricSource::Observed,
+                "solana_watchlist_vote_credits_epoch",
+                "Vote credits earned this epoch by each watchlist validator",
+            ),
+            &["vote_pubkey"],
+        )?;
+
+        let watchlist_efficiency_epoch = GaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_watchlist_vote_credits_efficiency_epoch",
+                "Fraction of max vote credits for rooted slots earned this epoch by each watchlist validator",
+            ),
+            &["vote_pubkey"],
+        )?;
+
+        let watchlist_missed_epoch = IntGaugeVec::new(
+            opts(
+                MetricSource::Derived,
+                "solana_watchlist_missed_vote_credits_current_epoch",
+                "Vote credits missed this epoch by each watchlist validator",
+            ),
+            &["vote_pubkey"],
+        )?;
+
+        let watchlist_delinquent = IntGaugeVec::new(
+            opts(
+                MetricSource::Observed,
+                "solana_watchlist_delinquent",
+                "1 if the watchlist validator is delinquent, 0 otherwise",
+            ),
+            &["vote_pubkey"],
+        )?;
+
+        let watchlist_last_refresh = IntGaugeVec::new(
+            opts(
+                MetricSource::Exporter,
+                "tvc_watchlist_last_refresh_timestamp",
+                "Unix timestamp at which each watchlist validator's vote account was last fetched",
+            ),
+            &["vote_pubkey"],
+        )?;
+
+        // Register all metrics
+        register(&registry, &mut catalog, &epoch)?;
+        register(&registry, &mut catalog, &slot_index)?;
+        register(&registry, &mut catalog, &total_epoch_credits)?;
+        register(&registry, &mut catalog, &epoch_expected_max)?;
+        register(&registry, &mut catalog, &epoch_first_tracked_slot)?;
+        register(&registry, &mut catalog, &epoch_last_tracked_slot)?;
+        if !disabled.contains(DisabledMetrics::PROJECTIONS) {
+            register(&registry, &mut catalog, &projected_credits_5m)?;
+            register(&registry, &mut catalog, &projected_credits_1h)?;
+            register(&registry, &mut catalog, &projected_credits_smoothed)?;
+        }
+        register(&registry, &mut catalog, &ws_connected)?;
+        register(&registry, &mut catalog, &ws_errors)?;
+        register(&registry, &mut catalog, &ws_subscribe_errors)?;
+        register(&registry, &mut catalog, &ws_connection_errors)?;
+        register(&registry, &mut catalog, &ws_subscription_failed)?;
+        register(&registry, &mut catalog, &ws_silent_subscriptions)?;
+        register(&registry, &mut catalog, &ws_encoding_mismatch)?;
+        register(&registry, &mut catalog, &ws_commitment_effective)?;
+        register(&registry, &mut catalog, &ws_notifications_shed)?;
+        register(&registry, &mut catalog, &ws_notifications)?;
+        register(&registry, &mut catalog, &ws_noop_notifications)?;
+        register(&registry, &mut catalog, &ws_noop_ratio)?;
+        register(&registry, &mut catalog, &ws_reconnects)?;
+        register(&registry, &mut catalog, &ws_last_message)?;
+        register(&registry, &mut catalog, &ws_disconnected_seconds)?;
+        register(&registry, &mut catalog, &ws_connection_uptime)?;
+        register(&registry, &mut catalog, &journal_records_dropped)?;
+        register(&registry, &mut catalog, &rpc_up)?;
+        register(&registry, &mut catalog, &rpc_errors)?;
+        register(&registry, &mut catalog, &rpc_last_success)?;
+        register(&registry, &mut catalog, &rpc_slot_regressions)?;
+        register(&registry, &mut catalog, &rpc_throttle_wait)?;
+        register(&registry, &mut catalog, &rpc_response_bytes)?;
+        register(&registry, &mut catalog, &rpc_received_bytes)?;
+        register(&registry, &mut catalog, &rpc_health)?;
+        register(&registry, &mut catalog, &rpc_health_behind_slots)?;
+        register(&registry, &mut catalog, &poll_interval)?;
+        register(&registry, &mut catalog, &poll_duration)?;
+        register(&registry, &mut catalog, &polls)?;
+        register(&registry, &mut catalog, &rooted_slots_per_poll)?;
+        register(&registry, &mut catalog, &root_slot_advance_rate)?;
+        register(&registry, &mut catalog, &polls_no_root_progress)?;
+        register(&registry, &mut catalog, &heartbeats)?;
+        register(&registry, &mut catalog, &bus_publish_errors)?;
+        register(&registry, &mut catalog, &notify_deliveries)?;
+        register(&registry, &mut catalog, &notify_retries)?;
+        register(&registry, &mut catalog, &discontinuities)?;
+        register(&registry, &mut catalog, &http_requests)?;
+        register(&registry, &mut catalog, &http_request_duration)?;
+        register(&registry, &mut catalog, &metrics_last_scraped)?;
+        register(&registry, &mut catalog, &warming)?;
+        register(&registry, &mut catalog, &update_generation)?;
+        register(&registry, &mut catalog, &epoch_expected_max_by_anchor)?;
+        register(&registry, &mut catalog, &missed_current_epoch_by_anchor)?;
+        register(&registry, &mut catalog, &pending_slots)?;
+        register(&registry, &mut catalog, &rpc_version_info)?;
+        register(&registry, &mut catalog, &config_info)?;
+        register(&registry, &mut catalog, &config_interval)?;
+        register(&registry, &mut catalog, &config_window)?;
+        register(&registry, &mut catalog, &clock_jumps_detected)?;
+        register(&registry, &mut catalog, &tvc_feature_active)?;
+        register(&registry, &mut catalog, &cluster_active_stake)?;
+        register(&registry, &mut catalog, &cluster_delinquent_stake)?;
+        register(&registry, &mut catalog, &cluster_delinquent_stake_ratio)?;
+        register(&registry, &mut catalog, &cluster_credits_rank)?;
+        register(&registry, &mut catalog, &credits_gap_to_rank)?;
+        register(
+            &registry,
+            &mut catalog,
+            &cluster_credits_stake_weighted_mean,
+        )?;
+        register(&registry, &mut catalog, &credits_vs_cluster_ratio)?;
+        register(&registry, &mut catalog, &epoch_credits_history_len)?;
+        register(&registry, &mut catalog, &epoch_credits_oldest_epoch)?;
+        register(&registry, &mut catalog, &epoch_credits_unsorted)?;
+        register(&registry, &mut catalog, &vote_account_age_epochs)?;
+        register(&registry, &mut catalog, &missed_current_epoch)?;
+        register(&registry, &mut catalog, &missed_5m)?;
+        register(&registry, &mut catalog, &missed_1h)?;
+        register(&registry, &mut catalog, &missed_total)?;
+        register(&registry, &mut catalog, &missed_maintenance_total)?;
+        register(&registry, &mut catalog, &missed_current_epoch_maintenance)?;
+        register(&registry, &mut catalog, &maintenance_active)?;
+        register(&registry, &mut catalog, &maintenance_remaining)?;
+        register(&registry, &mut catalog, &missed_last_epoch)?;
+        register(&registry, &mut catalog, &missed_last_epoch_adjusted)?;
+        register(&registry, &mut catalog, &activated_stake_delta)?;
+        register(&registry, &mut catalog, &stake_decreases)?;
+        register(&registry, &mut catalog, &validator_restarts)?;
+        register(&registry, &mut catalog, &votes_catch_up)?;
+        register(&registry, &mut catalog, &vote_regime_catch_up)?;
+        register(&registry, &mut catalog, &votes_without_credits)?;
+        register(&registry, &mut catalog, &epoch_source_disagreement)?;
+        register(&registry, &mut catalog, &votes_never_rooted)?;
+        register(&registry, &mut catalog, &perfect_slot_streak_current)?;
+        register(&registry, &mut catalog, &perfect_slot_streak_epoch_max)?;
+        register(&registry, &mut catalog, &updates_in_window)?;
+        register(&registry, &mut catalog, &fork_regime_turbulent)?;
+        register(
+            &registry,
+            &mut catalog,
+            &vote_credits_efficiency_by_fork_regime,
+        )?;
+        register(&registry, &mut catalog, &missed_by_fork_regime)?;
+        register(&registry, &mut catalog, &slots_in_window)?;
+        register(&registry, &mut catalog, &window_history_coverage)?;
+        register(&registry, &mut catalog, &efficiency_computation_skew_5m)?;
+        register(&registry, &mut catalog, &missed_accounting_skew)?;
+        register(&registry, &mut catalog, &missed_accounting_skew_ratio)?;
+        register(&registry, &mut catalog, &slot_root_delay)?;
+        register(&registry, &mut catalog, &tracker_state_bytes)?;
+        register(&registry, &mut catalog, &state_evictions)?;
+        if !disabled.contains(DisabledMetrics::LOCKS) {
+            register(&registry, &mut catalog, &tracker_lock_wait)?;
+            register(&registry, &mut catalog, &tracker_lock_hold)?;
+        }
+        register(&registry, &mut catalog, &vote_credits_efficiency_5m)?;
+        register(&registry, &mut catalog, &vote_credits_efficiency_1h)?;
+        register(&registry, &mut catalog, &vote_credits_efficiency_epoch)?;
+        register(&registry, &mut catalog, &vote_credits_per_slot_5m)?;
+        register(&registry, &mut catalog, &vote_credits_per_slot_1h)?;
+        register(&registry, &mut catalog, &vote_credits_per_slot_epoch)?;
+        register(&registry, &mut catalog, &vote_latency_slots_5m)?;
+        register(&registry, &mut catalog, &vote_latency_slots_1h)?;
+        register(&registry, &mut catalog, &vote_latency_slots_epoch)?;
+        register(
+            &registry,
+            &mut catalog,
+            &vote_credits_efficiency_5m_epoch_min,
+        )?;
+        register(
+            &registry,
+            &mut catalog,
+            &vote_credits_efficiency_5m_epoch_min_timestamp,
+        )?;
+        register(&registry, &mut catalog, &vote_efficiency_by_hour)?;
+        register(
+            &registry,
+            &mut catalog,
+            &vote_credits_efficiency_adjusted_5m,
+        )?;
+        register(
+            &registry,
+            &mut catalog,
+            &vote_credits_efficiency_adjusted_1h,
+        )?;
+        register(
+            &registry,
+            &mut catalog,
+            &vote_credits_efficiency_adjusted_epoch,
+        )?;
+        register(&registry, &mut catalog, &leader_slots_current_epoch)?;
+        register(&registry, &mut catalog, &cluster_skipped_slots)?;
+        register(&registry, &mut catalog, &missed_current_epoch_skip_adjusted)?;
+        register(&registry, &mut catalog, &unobserved_rooted_slots)?;
+        register(&registry, &mut catalog, &unobserved_expected_credits)?;
+        register(&registry, &mut catalog, &offline_catchup_credits)?;
+        register(&registry, &mut catalog, &offline_catchup_missed)?;
+        register(&registry, &mut catalog, &vote_account_lamports)?;
+        register(&registry, &mut catalog, &vote_account_data_bytes)?;
+        register(&registry, &mut catalog, &vote_account_state_info)?;
+        register(&registry, &mut catalog, &prior_voter_entries)?;
+        register(&registry, &mut catalog, &vote_account_rent_exempt)?;
+        register(&registry, &mut catalog, &vote_account_rent_exempt_margin)?;
+        register(&registry, &mut catalog, &vote_transactions)?;
+        register(&registry, &mut catalog, &vote_transactions_per_minute)?;
+        register(&registry, &mut catalog, &vote_fees_lamports)?;
+        register(&registry, &mut catalog, &vote_fees_per_day_estimate)?;
+        if !disabled.contains(DisabledMetrics::HISTOGRAM) {
+            register(&registry, &mut catalog, &vote_credits_histogram_count)?;
+        }
+        if !disabled.contains(DisabledMetrics::FRACTIONS) {
+            register(&registry, &mut catalog, &vote_credits_histogram_fraction)?;
+        }
+        register(&registry, &mut catalog, &votes_by_credits)?;
+        register(&registry, &mut catalog, &watchlist_credits_epoch)?;
+        register(&registry, &mut catalog, &watchlist_efficiency_epoch)?;
+        register(&registry, &mut catalog, &watchlist_missed_epoch)?;
+        register(&registry, &mut catalog, &watchlist_delinquent)?;
+        register(&registry, &mut catalog, &watchlist_last_refresh)?;
+
+        let deprecated = Registry::new();
+        let renamed: [Arc<dyn Collector>; 11] = [
+            Arc::new(missed_current_epoch.clone()),
+            Arc::new(missed_5m.clone()),
+            Arc::new(missed_1h.clone()),
+            Arc::new(missed_total.clone()),
+            Arc::new(missed_maintenance_total.clone()),
+            Arc::new(missed_current_epoch_maintenance.clone()),
+            Arc::new(missed_last_epoch.clone()),
+            Arc::new(missed_last_epoch_adjusted.clone()),
+            Arc::new(missed_current_epoch_by_anchor.clone()),
+            Arc::new(missed_by_fork_regime.clone()),
+            Arc::new(missed_current_epoch_skip_adjusted.clone()),
+        ];
+        for primary in renamed {
+            let name = primary.desc()[0].fq_name.clone();
+            let aliases = METRIC_ALIASES.iter().filter(|alias| alias.name == name);
+            for old in aliases.flat_map(|alias| alias.deprecated) {
+                deprecated.register(Box::new(AliasCollector::deprecated(primary.clone(), old)?))?;
+            }
+        }
+
+        let Catalog {
+            entries: mut catalog,
+            collectors,
+        } = catalog;
+        catalog.sort_by(|a: &CatalogEntry, b| a.name.cmp(&b.name));
+        Ok(Self {
+            registry,
+            catalog,
+            collectors,
+            deprecated,
+            emit_deprecated: true,
+            filter: RenderFilter::default(),
+            publication: Arc::new(RwLock::new(())),
+            disabled,
+            histogram_windows: HistogramWindow::ALL.to_vec(),
+            epoch,
+            slot_index,
+            total_epoch_credits,
+            epoch_expected_max,
+            epoch_first_tracked_slot,
+            epoch_last_tracked_slot,
+            projected_credits_5m,
+            projected_credits_1h,
+            projected_credits_smoothed,
+            ws_connected,
+            ws_errors,
+            ws_subscribe_errors,
+            ws_connection_errors,
+            ws_subscription_failed,
+            ws_silent_subscriptions,
+            ws_encoding_mismatch,
+            ws_commitment_effective,
+            ws_notifications_shed,
+            ws_notifications,
+            ws_noop_notifications,
+            ws_noop_ratio,
+            ws_reconnects,
+            ws_last_message,
+            ws_disconnected_seconds,
+            ws_connection_uptime,
+            journal_records_dropped,
+            rpc_up,
+            rpc_errors,
+            rpc_slot_regressions,
+            rpc_last_success,
+            rpc_throttle_wait,
+            rpc_response_bytes,
+            rpc_received_bytes,
+            rpc_health,
+            rpc_health_behind_slots,
+            poll_interval,
+            poll_duration,
+            polls,
+            rooted_slots_per_poll,
+            root_slot_advance_rate,
+            polls_no_root_progress,
+            heartbeats,
+            bus_publish_errors,
+            notify_deliveries,
+            notify_retries,
+            discontinuities,
+            http_requests,
+            http_request_duration,
+            metrics_last_scraped,
+            warming,
+            update_generation,
+            epoch_expected_max_by_anchor,
+            missed_current_epoch_by_anchor,
+            pending_slots,
+            rpc_version_info,
+            config_info,
+            config_interval,
+            config_window,
+            clock_jumps_detected,
+            tvc_feature_active,
+            cluster_active_stake,
+            cluster_delinquent_stake,
+            cluster_delinquent_stake_ratio,
+            cluster_credits_rank,
+            credits_gap_to_rank,
+            cluster_credits_stake_weighted_mean,
+            credits_vs_cluster_ratio,
+            epoch_credits_history_len,
+            epoch_credits_oldest_epoch,
+            epoch_credits_unsorted,
+            vote_account_age_epochs,
+            missed_current_epoch,
+            missed_5m,
+            missed_1h,
+            missed_total,
+            missed_maintenance_total,
+            missed_current_epoch_maintenance,
+            maintenance_active,
+            maintenance_remaining,
+            missed_last_epoch,
+            missed_last_epoch_adjusted,
+            activated_stake_delta,
+            stake_decreases,
+            validator_restarts,
+            votes_catch_up,
+            vote_regime_catch_up,
+            votes_without_credits,
+            epoch_source_disagreement,
+            votes_never_rooted,
+            perfect_slot_streak_current,
+            perfect_slot_streak_epoch_max,
+            updates_in_window,
+            fork_regime_turbulent,
+            vote_credits_efficiency_by_fork_regime,
+            missed_by_fork_regime,
+            slots_in_window,
+            window_history_coverage,
+            efficiency_computation_skew_5m,
+            missed_accounting_skew,
+            missed_accounting_skew_ratio,
+            slot_root_delay,
+            tracker_state_bytes,
+            state_evictions,
+            tracker_lock_wait,
+            tracker_lock_hold,
+            vote_credits_efficiency_5m,
+            vote_credits_efficiency_1h,
+            vote_credits_efficiency_epoch,
+            vote_credits_per_slot_5m,
+            vote_credits_per_slot_1h,
+            vote_credits_per_slot_epoch,
+            vote_latency_slots_5m,
+            vote_latency_slots_1h,
+            vote_latency_slots_epoch,
+            vote_credits_efficiency_5m_epoch_min,
+            vote_credits_efficiency_5m_epoch_min_timestamp,
+            vote_efficiency_by_hour,
+            vote_credits_efficiency_adjusted_5m,
+            vote_credits_efficiency_adjusted_1h,
+            vote_credits_efficiency_adjusted_epoch,
+            leader_slots_current_epoch,
+            cluster_skipped_slots,
+            missed_current_epoch_skip_adjusted,
+            unobserved_rooted_slots,
+            unobserved_expected_credits,
+            offline_catchup_credits,
+            offline_catchup_missed,
+            vote_account_lamports,
+            vote_account_data_bytes,
+            vote_account_state_info,
+            prior_voter_entries,
+            vote_account_rent_exempt,
+            vote_account_rent_exempt_margin,
+            vote_transactions,
+            vote_transactions_per_minute,
+            vote_fees_lamports,
+            vote_fees_per_day_estimate,
+            vote_credits_histogram_count,
+            vote_credits_histogram_fraction,
+            votes_by_credits,
+            watchlist_credits_epoch,
+            watchlist_efficiency_epoch,
+            watchlist_missed_epoch,
+            watchlist_delinquent,
+            watchlist_last_refresh,
+        })
+    }
+
+    /// Set the histogram count and fraction series for `windows` only
+    pub fn with_histogram_windows(mut self, windows: &[HistogramWindow]) -> Self {
+        self.histogram_windows = windows.to_vec();
+        self
+    }
+
+    /// Render the deprecated aliases of renamed families or not
+    pub fn with_deprecated_metrics(mut self, emit: bool) -> Self {
+        self.emit_deprecated = emit;
+        self
+    }
+
+    /// Leave the performance families out of `/metrics` until `config` is met
+    pub fn with_warmup(self, config: WarmupConfig) -> Self {
+        let warmup = Warmup::new(config);
+        self.warming.set(!warmup.is_warm() as i64);
+        *self
+            .filter
+            .warmup
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = warmup;
+        self
+    }
+
+    /// Count a processed notification towards the warm-up
+    pub fn observe_warmup_notification(&self, root_slot: Option<u64>) {
+        if self.filter.warmup().observe_notification(root_slot) {
+            self.warming.set(0);
+        }
+    }
+
+    /// End the warm-up after a full poll
+    pub fn finish_warmup(&self) {
+        if self.filter.warmup().finish() {
+            info!("Warmed up after the first full poll");
+            self.warming.set(0);
+        }
+    }
+
+    /// Also export every family into `registry` as `<prefix>_<name>`. The
+    /// families stay in [`Metrics::registry`] under their own names; the
+    /// exported ones are left out while `render` leaves out the originals
+    /// (warm-up, windows without enough data). The deprecated aliases are not
+    /// exported. If `registry` already has any of the names, nothing is
+    /// registered and the error is a [`DuplicateMetrics`] listing them all.
+    pub fn register_into(&self, registry: &Registry, prefix: &str) -> Result<()> {
+        let mut registered = Vec::new();
+        let mut duplicates = Vec::new();
+        for primary in &self.collectors {
+            let prefixed = AliasCollector::prefixed(primary.clone(), prefix, self.filter.clone())?;
+            let name = prefixed.desc.fq_name.clone();
+            match registry.register(Box::new(prefixed)) {
+                Ok(()) => registered.push(primary.clone()),
+                Err(_) => duplicates.push(name),
+            }
+        }
+        if duplicates.is_empty() {
+            return Ok(());
+        }
+        // Leave `registry` as it was
+        for primary in registered {
+            let prefixed = AliasCollector::prefixed(primary, prefix, self.filter.clone())?;
+            registry.unregister(Box::new(prefixed))?;
+        }
+        Err(DuplicateMetrics { names: duplicates }.into())
+    }
+
+    /// Every registered family with its source, help and unit (`/metrics/catalog`)
+    pub fn catalog(&self) -> &[CatalogEntry] {
+        &self.catalog
+    }
+
+    /// Start publishing an update cycle: bump `tvc_update_generation` and
+    /// hold back gathering until the returned guard is dropped. Only the
+    /// registry of [`Metrics::render`] and [`Metrics::snapshot`] waits, not
+    /// one the families were exported into with [`Metrics::register_into`].
+    /// The guard can't be held across an await in a spawned task.
+    pub fn begin_publication(&self) -> RwLockWriteGuard<'_, ()> {
+        let guard = self.publication.write().unwrap_or_else(|e| e.into_inner());
+        self.update_generation.inc();
+        guard
+    }
+
+    /// Every registered family, with the deprecated aliases if emitted
+    fn gather(&self) -> Vec<MetricFamily> {
+        let _publication = self.publication.read().unwrap_or_else(|e| e.into_inner());
+        let mut families = self.registry.gather();
+        if self.emit_deprecated {
+            families.extend(self.deprecated.gather());
+            families.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        }
+        families
+    }
+
+    /// Export how much of `window` the history spans (0 to 1), and leave the
+    /// window's [`WINDOWED_FAMILIES`] out of `/metrics` while that is under
+    /// [`MIN_WINDOW_COVERAGE`]. Returns whether the window has enough data
+    /// for its gauges to be set.
+    pub fn set_window_coverage(&self, window: HistogramWindow, coverage: f64) -> bool {
+        self.window_history_coverage
+            .with_label_values(&[window.label()])
+            .set(coverage);
+        let sufficient = coverage >= MIN_WINDOW_COVERAGE;
+        let mut insufficient = self
+            .filter
+            .insufficient_windows
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        insufficient.retain(|w| *w != window);
+        if !sufficient {
+            insufficient.push(window);
+        }
+        sufficient
+    }
+
+    /// Whether the history covered `window` well enough at the last update
+    pub fn has_window_data(&self, window: HistogramWindow) -> bool {
+        let insufficient = self
+            .filter
+            .insufficient_windows
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        !insufficient.contains(&window)
+    }
+
+    /// Whether none of `families` is disabled
+    pub fn is_enabled(&self, families: DisabledMetrics) -> bool {
+        !self.disabled.intersects(families)
+    }
+
+    /// Export the root- and tip-anchored expectations side by side. Returns the
+    /// tip expectation if it drives the primary expected max / missed metrics.
+    pub fn set_anchored_expectations(
+        &self,
+        anchor: ExpectedAnchor,
+        root_expected: u64,
+        credits: u64,
+        tip: Option<TipExpectation>,
+    ) -> Option<TipExpectation> {
+        self.epoch_expected_max_by_anchor
+            .with_label_values(&["root"])
+            .set(root_expected as i64);
+        self.missed_current_epoch_by_anchor
+            .with_label_values(&["root"])
+            .set(root_expected.saturating_sub(credits) as i64);
+        if let Some(tip) = tip {
+            self.epoch_expected_max_by_anchor
+                .with_label_values(&["tip"])
+                .set(tip.expected_max as i64);
+            self.missed_current_epoch_by_anchor
+                .with_label_values(&["tip"])
+                .set(tip.provisional_missed as i64);
+            self.pending_slots.set(tip.pending_slots as i64);
+        }
+        tip.filter(|_| anchor == ExpectedAnchor::Tip)
+    }
+
+    /// Export how far the snapshot and incremental missed credit counts differ
+    pub fn set_missed_accounting(&self, accounting: &MissedAccounting) {
+        self.missed_accounting_skew.set(accounting.skew() as i64);
+        self.missed_accounting_skew_ratio
+            .set(accounting.skew_ratio());
+    }
+
+    /// Export the cluster's current and delinquent stake
+    pub fn set_cluster_stake(&self, stake: &ClusterStake) {
+        self.cluster_active_stake.set(stake.current as i64);
+        self.cluster_delinquent_stake.set(stake.delinquent as i64);
+        self.cluster_delinquent_stake_ratio
+            .set(stake.delinquent_ratio());
+    }
+
+    /// Export the own credits against the cluster's stake-weighted mean (the
+    /// ratio is left as is while the mean is 0)
+    pub fn set_cluster_average(&self, average: &ClusterAverage) {
+        self.cluster_credits_stake_weighted_mean
+            .set(average.stake_weighted_mean);
+        if let Some(ratio) = average.ratio {
+            self.credits_vs_cluster_ratio.set(ratio);
+        }
+    }
+
+    /// Export the slot range tracked this epoch (left as is until a root is seen)
+    pub fn set_tracked_slots(&self, first: Option<u64>, last: Option<u64>) {
+        if let (Some(first), Some(last)) = (first, last) {
+            self.epoch_first_tracked_slot.set(first as i64);
+            self.epoch_last_tracked_slot.set(last as i64);
+        }
+    }
+
+    /// Export what the provider delivers on the subscription
+    pub fn set_acknowledged_subscription(&self, ack: &AcknowledgedSubscription) {
+        self.ws_encoding_mismatch
+            .set(ack.encoding_mismatch() as i64);
+        let effective = ack.commitment();
+        for level in EffectiveCommitment::ALL {
+            self.ws_commitment_effective
+                .with_label_values(&[level.label()])
+                .set((level == effective) as i64);
+        }
+    }
+
+    /// Export the credit history length and oldest epoch, count it if it was
+    /// out of order, and export the account age while the history is shorter
+    /// than `min_epochs`
+    pub fn set_credit_history(
+        &self,
+        history: &CreditHistory,
+        current_epoch: u64,
+        min_epochs: usize,
+    ) {
+        self.epoch_credits_history_len.set(history.len as i64);
+        if let Some(oldest) = history.oldest_epoch {
+            self.epoch_credits_oldest_epoch.set(oldest as i64);
+        }
+        if history.unsorted {
+            self.epoch_credits_unsorted.inc();
+        }
+        self.vote_account_age_epochs.reset();
+        if history.len < min_epochs {
+            self.vote_account_age_epochs
+                .with_label_values(&[&min_epochs.to_string()])
+                .set(history.age_epochs(current_epoch) as i64);
+        }
+    }
+
+    /// Export the hours of the profile with recent data, dropping stale ones
+    pub fn set_hourly_profile(&self, profile: &HourlyProfile, now_millis: u64) {
+        for hour in 0..24 {
+            let label = hour.to_string();
+            match profile.efficiency(hour, now_millis) {
+                Some(efficiency) => self
+                    .vote_efficiency_by_hour
+                    .with_label_values(&[&label])
+                    .set(efficiency),
+                None => {
+                    let _ = self.vote_efficiency_by_hour.remove_label_values(&[&label]);
+                }
+            }
+        }
+    }
+
+    /// Export the effective configuration: `args` after parsing, with the
+    /// defaults applied. URLs, tokens and paths are left out, as they may
+    /// carry credentials.
+    pub fn set_config_info(&self, args: &Args) {
+        let histogram_windows: Vec<&str> =
+            args.histogram_windows.iter().map(|w| w.label()).collect();
+        let disabled_metrics: Vec<String> = args.disable_metrics.iter().map(value_name).collect();
+        let labels = [
+            env!("CARGO_PKG_VERSION").to_string(),
+            value_name(&args.mode),
+            COMMITMENT.to_string(),
+            args.interval_secs.to_string(),
+            args.cluster.clone().unwrap_or_default(),
+            value_name(&args.expected_anchor),
+            args.max_credits_per_slot
+                .map_or_else(|| "auto".to_string(), |max| max.to_string()),
+            histogram_windows.join(","),
+            disabled_metrics.join(","),
+        ];
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        self.config_info.reset();
+        self.config_info.with_label_values(&labels).set(1);
+
+        self.config_interval.set(args.interval_secs as f64);
+        self.config_window.reset();
+        for window in &args.histogram_windows {
+            if let Some(seconds) = window.seconds() {
+                self.config_window
+                    .with_label_values(&[window.label()])
+                    .set(seconds as f64);
+            }
+        }
+    }
+
+    /// Every sample in the registry, in a deterministic order
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut families: Vec<FamilySnapshot> = self
+            .gather()
+            .iter()
+            .map(FamilySnapshot::from_family)
+            .collect();
+        families.sort_by(|a, b| a.name.cmp(&b.name));
+        MetricsSnapshot { families }
+    }
+
+    /// The text exposition and its content type
+    pub fn render(&self) -> (&'static str, String) {
+        let mut families = self.gather();
+        families.retain(|family| !self.filter.hides(family.get_name()));
+        let encoder = TextEncoder::new();
+        let mut buf = Vec::new();
+        encoder.encode(&families, &mut buf).expect("encode metrics");
+        let body = String::from_utf8(buf).expect("utf8 metrics");
+        (TEXT_FORMAT, body)
+    }
+
+    /// Unix time of the last notification (WS mode) or successful poll (poll
+    /// mode), None before the first
+    pub fn last_data_update(&self, mode: Mode) -> Option<u64> {
+        let last = match mode {
+            Mode::Ws => self.ws_last_message.get(),
+            Mode::Poll => self.rpc_last_success.get(),
+        };
+        (last > 0).then_some(last as u64)
+    }
+}
+
+/// Serializable view of all metric families, sorted by name and labels
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub families: Vec<FamilySnapshot>,
+}
+
+impl MetricsSnapshot {
+    /// One `# TYPE` line per family and one `name{labels} value` line per
+    /// sample, like the exposition format without the help texts
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for family in &self.families {
+            let _ = writeln!(out, "# TYPE {} {}", family.name, family.kind);
+            for sample in &family.samples {
+                out.push_str(&sample.name);
+                if !sample.labels.is_empty() {
+                    let labels: Vec<String> = sample
+                        .labels
+                        .iter()
+                        .map(|(name, value)| format!("{}=\"{}\"", name, value))
+                        .collect();
+                    let _ = write!(out, "{{{}}}", labels.join(","));
+                }
+                let _ = writeln!(out, " {}", sample.value);
+            }
+        }
+        out
+    }
+}
+
+/// One metric family with its samples
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FamilySnapshot {
+    pub name: String,
+    pub help: String,
+    /// counter, gauge, histogram, summary or untyped
+    pub kind: &'static str,
+    pub samples: Vec<SampleSnapshot>,
+}
+
+/// One time series value; histograms expand to their `_bucket`, `_sum` and
+/// `_count` series
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SampleSnapshot {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+impl FamilySnapshot {
+    fn from_family(family: &MetricFamily) -> Self {
+        let name = family.get_name();
+        let mut metrics: Vec<(BTreeMap<String, String>, _)> = family
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                let labels = metric
+                    .get_label()
+                    .iter()
+                    .map(|l| (l.get_name().to_string(), l.get_value().to_string()))
+                    .collect();
+                (labels, metric)
+            })
+            .collect();
+        // Series by labels; buckets stay in ascending bound order within one
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut samples = Vec::new();
+        for (labels, metric) in metrics {
+            let mut push = |suffix: &str, extra: Option<(&str, String)>, value: f64| {
+                let mut labels = labels.clone();
+                if let Some((label, label_value)) = extra {
+                    labels.insert(label.to_string(), label_value);
+                }
+                samples.push(SampleSnapshot {
+                    name: format!("{}{}", name, suffix),
+                    labels,
+                    value,
+                });
+            };
+            match family.get_field_type() {
+                MetricType::COUNTER => push("", None, metric.get_counter().get_value()),
+                MetricType::GAUGE => push("", None, metric.get_gauge().get_value()),
+                // Not registered by this exporter
+                MetricType::UNTYPED => {}
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    for bucket in histogram.get_bucket() {
+                        let le = bucket.get_upper_bound().to_string();
+                        push(
+                            "_bucket",
+                            Some(("le", le)),
+                            bucket.get_cumulative_count() as f64,
+                        );
+                    }
+                    let count = histogram.get_sample_count() as f64;
+                    push("_bucket", Some(("le", "+Inf".to_string())), count);
+                    push("_sum", None, histogram.get_sample_sum());
+                    push("_count", None, count);
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let q = quantile.get_quantile().to_string();
+                        push("", Some(("quantile", q)), quantile.get_value());
+                    }
+                    push("_sum", None, summary.get_sample_sum());
+                    push("_count", None, summary.get_sample_count() as f64);
+                }
+            }
+        }
+        Self {
+            name: name.to_string(),
+            help: family.get_help().to_string(),
+            kind: match family.get_field_type() {
+                MetricType::COUNTER => "counter",
+                MetricType::GAUGE => "gauge",
+                MetricType::HISTOGRAM => "histogram",
+                MetricType::SUMMARY => "summary",
+                MetricType::UNTYPED => "untyped",
+            },
+            samples,
+        }
+    }
+}
+
+/// Command line name of a value
+pub(crate) fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_disabled_families_are_not_rendered() {
+        let metrics =
+            Metrics::with_disabled(DisabledMetrics::HISTOGRAM | DisabledMetrics::PROJECTIONS)
+                .unwrap();
+        metrics
+            .vote_credits_histogram_fraction
+            .with_label_values(&["5m", "16"])
+            .set(1.0);
+        metrics.epoch.set(500);
+
+        let (_, body) = metrics.render();
+        assert!(!body.contains("solana_vote_credits_histogram_count"));
+        assert!(!body.contains("solana_vote_credits_projected"));
+        assert!(body.contains(
+            r#"solana_vote_credits_histogram_fraction{credits="16",source="derived",window="5m"} 1"#
+        ));
+        assert!(body.contains(r#"solana_epoch{source="observed"} 500"#));
+
+        assert!(!metrics.is_enabled(DisabledMetrics::HISTOGRAM));
+        assert!(metrics.is_enabled(DisabledMetrics::FRACTIONS));
+        assert!(!metrics.is_enabled(DisabledMetrics::FRACTIONS | DisabledMetrics::PROJECTIONS));
+    }
+
+    #[test]
+    fn test_metric_groups_map_to_flags() {
+        let disabled: DisabledMetrics = [MetricGroup::Fractions, MetricGroup::Projections]
+            .into_iter()
+            .map(DisabledMetrics::from)
+            .collect();
+        assert_eq!(
+            disabled,
+            DisabledMetrics::FRACTIONS | DisabledMetrics::PROJECTIONS
+        );
+        assert_eq!(
+            MetricGroup::from_str("histogram", false),
+            Ok(MetricGroup::Histogram)
+        );
+    }
+
+    #[test]
+    fn test_config_info_labels() {
+        let args = Args::try_parse_from([
+            "tvc_tracker",
+            "--vote-pubkey",
+            "Vote111",
+            "--rpc-url",
+            "https://rpc.example/?api-key=hunter2",
+            "--admin-token",
+            "hunter2",
+            "--mode",
+            "poll",
+            "--interval-secs",
+            "30",
+            "--cluster",
+            "mainnet",
+            "--histogram-windows",
+            "5m,epoch",
+            "--disable-metrics",
+            "fractions",
+        ])
+        .unwrap();
+        let metrics = Metrics::new().unwrap();
+        metrics.set_config_info(&args);
+
+        let (_, body) = metrics.render();
+        let expected = format!(
+            r#"tvc_config_info{{cluster="mainnet",commitment="finalized",disabled_metrics="fractions",expected_anchor="root",histogram_windows="5m,epoch",interval_secs="30",max_credits_per_slot="auto",mode="poll",version="{}"}} 1"#,
+            env!("CARGO_PKG_VERSION")
+        );
+        assert!(body.contains(&expected), "{}", body);
+        assert!(body.contains("tvc_config_interval_seconds 30"));
+        assert!(body.contains(r#"tvc_config_window_seconds{window="5m"} 300"#));
+        assert!(!body.contains(r#"window="1h"} 3600"#));
+        assert!(!body.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_histogram_window_names() {
+        for window in HistogramWindow::ALL {
+            assert_eq!(HistogramWindow::from_str(window.label(), false), Ok(window));
+        }
+        assert!(HistogramWindow::from_str("15m", false).is_err());
+    }
+
+    fn gauge_value(registry: &Registry, name: &str) -> Option<f64> {
+        registry
+            .gather()
+            .iter()
+            .find(|family| family.get_name() == name)
+            .map(|family| family.get_metric()[0].get_gauge().get_value())
+    }
+
+    #[test]
+    fn test_prefixed_metrics_share_a_registry() {
+        let shared = Registry::new();
+        let a = Metrics::new_with_registry(shared.clone(), "a").unwrap();
+        let b = Metrics::new_with_registry(shared.clone(), "b").unwrap();
+        a.epoch.set(500);
+        b.epoch.set(501);
+        assert_eq!(gauge_value(&shared, "a_solana_epoch"), Some(500.0));
+        assert_eq!(gauge_value(&shared, "b_solana_epoch"), Some(501.0));
+        assert_eq!(gauge_value(&shared, "b_tvc_warming"), Some(0.0));
+
+        // Each keeps its own unprefixed families for /metrics
+        assert_eq!(gauge_value(&a.registry, "solana_epoch"), Some(500.0));
+        assert!(
+            a.render()
+                .1
+                .contains("solana_epoch{source=\"observed\"} 500")
+        );
+        assert!(!a.render().1.contains("b_solana_epoch"));
+    }
+
+    #[test]
+    fn test_prefixed_families_are_held_back_like_render() {
+        let shared = Registry::new();
+        let metrics = Metrics::new_with_registry(shared.clone(), "tvc")
+            .unwrap()
+            .with_warmup(WarmupConfig {
+                notifications: 1,
+                rooted_slots: 0,
+            });
+        metrics.missed_5m.set(3);
+        metrics.missed_1h.set(7);
+        assert_eq!(
+            gauge_value(&shared, "tvc_solana_missed_vote_credits_5m"),
+            None
+        );
+        assert_eq!(gauge_value(&shared, "tvc_tvc_warming"), Some(1.0));
+
+        metrics.observe_warmup_notification(Some(100));
+        assert!(!metrics.set_window_coverage(HistogramWindow::FiveMinutes, 0.1));
+        assert_eq!(
+            gauge_value(&shared, "tvc_solana_missed_vote_credits_5m"),
+            None
+        );
+        assert_eq!(
+            gauge_value(&shared, "tvc_solana_missed_vote_credits_1h"),
+            Some(7.0)
+        );
+
+        assert!(metrics.set_window_coverage(HistogramWindow::FiveMinutes, 0.5));
+        assert_eq!(
+            gauge_value(&shared, "tvc_solana_missed_vote_credits_5m"),
+            Some(3.0)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_registration_lists_the_names() {
+        let shared = Registry::new();
+        Metrics::new_with_registry(shared.clone(), "a").unwrap();
+        let families = shared.gather().len();
+
+        let error = Metrics::new_with_registry(shared.clone(), "a")
+            .err()
+            .unwrap();
+        let duplicates = error.downcast_ref::<DuplicateMetrics>().unwrap();
+        assert_eq!(
+            duplicates.names.len(),
+            Metrics::new().unwrap().collectors.len()
+        );
+        assert!(duplicates.names.contains(&"a_solana_epoch".to_string()));
+        assert!(
+            error
+                .to_string()
+                .starts_with("metrics already registered: a_")
+        );
+        assert_eq!(shared.gather().len(), families);
+
+        // A single clash with the application's own family registers nothing
+        let app = Registry::new();
+        let clash = IntGauge::new("tvc_solana_epoch", "The application's epoch").unwrap();
+        app.register(Box::new(clash)).unwrap();
+        let error = Metrics::new_with_registry(app.clone(), "tvc")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.downcast_ref::<DuplicateMetrics>().unwrap().names,
+            vec!["tvc_solana_epoch"]
+        );
+        assert_eq!(app.gather().len(), 1);
+        Metrics::new_with_registry(app.clone(), "tracker").unwrap();
+
+        // Registering a family twice in one registry names it too
+        let registry = Registry::new();
+        let mut catalog = Catalog::default();
+        let gauge = IntGauge::new("solana_epoch", "Current epoch number").unwrap();
+        register(&registry, &mut catalog, &gauge).unwrap();
+        let error = register(&registry, &mut catalog, &gauge).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<DuplicateMetrics>().unwrap().names,
+            vec!["solana_epoch"]
+        );
+        assert_eq!(catalog.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_catalog_covers_every_registered_family() {
+        let metrics = Metrics::new().unwrap();
+        // Vectors show up in the registry once they have a child
+        metrics.set_anchored_expectations(ExpectedAnchor::Root, 1_600, 1_500, None);
+        metrics.polls.with_label_values(&["ok"]).inc();
+        metrics
+            .vote_efficiency_by_hour
+            .with_label_values(&["13"])
+            .set(0.9);
+
+        let catalog = metrics.catalog();
+        for family in metrics.registry.gather() {
+            assert!(
+                catalog.iter().any(|entry| entry.name == family.get_name()),
+                "{} missing from the catalog",
+                family.get_name()
+            );
+        }
+        // Validator families say where they come from, the exporter's own don't
+        for entry in catalog {
+            assert_eq!(
+                entry.name.starts_with("solana_"),
+                entry.source != MetricSource::Exporter,
+                "{} classified as {:?}",
+                entry.name,
+                entry.source
+            );
+        }
+
+        let (_, body) = metrics.render();
+        assert!(body.contains(r#"solana_missed_vote_credits_total{source="derived"} 0"#));
+        assert!(body.contains(r#"polls_total{outcome="ok"} 1"#));
+    }
+
+    #[test]
+    fn test_units_from_names() {
+        assert_eq!(unit_of("ws_disconnected_seconds_total"), Some("seconds"));
+        assert_eq!(unit_of("solana_vote_account_lamports"), Some("lamports"));
+        assert_eq!(unit_of("solana_epoch_first_tracked_slot"), Some("slot"));
+        assert_eq!(unit_of("solana_vote_latency_slots_5m"), Some("slots"));
+        assert_eq!(
+            unit_of("solana_vote_credits_histogram_fraction"),
+            Some("ratio")
+        );
+        assert_eq!(unit_of("solana_missed_vote_credits_total"), Some("credits"));
+        assert_eq!(unit_of("ws_connected"), None);
+    }
+
+    #[test]
+    fn test_deprecated_aliases_mirror_their_primaries() {
+        let metrics = Metrics::new().unwrap();
+        metrics.missed_5m.set(12);
+        metrics.missed_total.inc_by(40);
+        metrics
+            .missed_by_fork_regime
+            .with_label_values(&["5m", "turbulent"])
+            .set(7);
+        metrics.set_anchored_expectations(ExpectedAnchor::Root, 1_600, 1_500, None);
+
+        let snapshot = metrics.snapshot();
+        let family = |name: &str| {
+            snapshot
+                .families
+                .iter()
+                .find(|family| family.name == name)
+                .unwrap_or_else(|| panic!("{} not rendered", name))
+        };
+        for (old, new) in alias_map() {
+            let (alias, primary) = (family(old), family(new));
+            assert_eq!(alias.kind, primary.kind);
+            let values = |family: &FamilySnapshot| {
+                family
+                    .samples
+                    .iter()
+                    .map(|sample| (sample.labels.clone(), sample.value))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(values(alias), values(primary), "{}", old);
+        }
+        assert_eq!(
+            family("missed_vote_credits_current_epoch_by_anchor").samples[0].value,
+            100.0
+        );
+
+        let (_, body) = metrics.render();
+        assert!(body.contains("\nmissed_vote_credits_5m{source=\"derived\"} 12\n"));
+        assert!(body.contains("\nsolana_missed_vote_credits_5m{source=\"derived\"} 12\n"));
+        assert!(body.contains("# HELP missed_vote_credits_total Deprecated, renamed to"));
+    }
+
+    #[test]
+    fn test_performance_families_held_back_while_warming() {
+        let metrics = Metrics::new().unwrap().with_warmup(WarmupConfig {
+            notifications: 2,
+            rooted_slots: 10,
+        });
+        metrics.vote_credits_efficiency_5m.set(0.0);
+        metrics.missed_5m.set(0);
+        metrics.ws_connected.set(1);
+
+        let (_, body) = metrics.render();
+        assert!(body.contains("\ntvc_warming 1\n"));
+        assert!(body.contains("\nws_connected 1\n"));
+        assert!(!body.contains("solana_vote_credits_efficiency_5m"));
+        assert!(!body.contains("missed_vote_credits_5m"));
+
+        metrics.observe_warmup_notification(Some(100));
+        metrics.observe_warmup_notification(Some(110));
+        let (_, body) = metrics.render();
+        assert!(body.contains("\ntvc_warming 0\n"));
+        assert!(body.contains("\nsolana_vote_credits_efficiency_5m{source=\"derived\"} 0\n"));
+        assert!(body.contains("\nsolana_missed_vote_credits_5m{source=\"derived\"} 0\n"));
+        assert!(body.contains("\nmissed_vote_credits_5m{source=\"derived\"} 0\n"));
+    }
+
+    #[test]
+    fn test_windows_without_data_are_held_back() {
+        let metrics = Metrics::new().unwrap();
+        metrics.missed_5m.set(3);
+        metrics.missed_1h.set(7);
+        metrics.vote_credits_efficiency_5m_epoch_min.set(0.9);
+        assert!(!metrics.set_window_coverage(HistogramWindow::FiveMinutes, 0.1));
+        assert!(metrics.set_window_coverage(HistogramWindow::OneHour, MIN_WINDOW_COVERAGE));
+
+        let (_, body) = metrics.render();
+        assert!(body.contains("\ntvc_window_history_coverage{window=\"5m\"} 0.1\n"));
+        assert!(!body.contains("missed_vote_credits_5m"));
+        assert!(!body.contains("solana_vote_credits_efficiency_adjusted_5m"));
+        assert!(body.contains("\nsolana_missed_vote_credits_1h{source=\"derived\"} 7\n"));
+        // Not computed over the 5m window alone
+        assert!(
+            body.contains(
+                "\nsolana_vote_credits_efficiency_5m_epoch_min{source=\"derived\"} 0.9\n"
+            )
+        );
+
+        assert!(metrics.set_window_coverage(HistogramWindow::FiveMinutes, 0.5));
+        let (_, body) = metrics.render();
+        assert!(body.contains("\nsolana_missed_vote_credits_5m{source=\"derived\"} 3\n"));
+        assert!(body.contains("\nmissed_vote_credits_5m{source=\"derived\"} 3\n"));
+    }
+
+    #[test]
+    fn test_scrape_waits_for_the_publication() {
+        let metrics = Metrics::new().unwrap();
+        let publication = metrics.begin_publication();
+        metrics.missed_5m.set(3);
+
+        let scrape = std::thread::scope(|scope| {
+            let scrape = scope.spawn(|| metrics.render().1);
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(!scrape.is_finished());
+            metrics.missed_1h.set(7);
+            drop(publication);
+            scrape.join().unwrap()
+        });
+        assert!(scrape.contains("\ntvc_update_generation 1\n"));
+        assert!(scrape.contains("\nsolana_missed_vote_credits_5m{source=\"derived\"} 3\n"));
+        assert!(scrape.contains("\nsolana_missed_vote_credits_1h{source=\"derived\"} 7\n"));
+    }
+
+    #[test]
+    fn test_first_poll_ends_warmup() {
+        let metrics = Metrics::new().unwrap().with_warmup(WarmupConfig::default());
+        assert!(!metrics.render().1.contains("solana_vote_credits_per_slot"));
+        metrics.finish_warmup();
+        assert_eq!(metrics.warming.get(), 0);
+        assert!(metrics.render().1.contains("solana_vote_credits_per_slot"));
+    }
+
+    #[test]
+    fn test_deprecated_aliases_can_be_dropped() {
+        let metrics = Metrics::new().unwrap().with_deprecated_metrics(false);
+        metrics.missed_5m.set(12);
+        let (_, body) = metrics.render();
+        assert!(body.contains("\nsolana_missed_vote_credits_5m{source=\"derived\"} 12\n"));
+        assert!(!body.contains("\nmissed_vote_credits"));
+    }
 }
@@ -0,0 +1,679 @@
+use crate::api::{EventMessage, SCHEMA_VERSION};
+use crate::clock::unix_now;
+use crate::events::TrackerEvent;
+use crate::maintenance::Maintenance;
+use crate::metrics::Metrics;
+use crate::proxy::redact_endpoint;
+
+use anyhow::{Result, anyhow, bail};
+use reqwest::Url;
+use serde::{Serialize, Serializer};
+use serde_json::{Value, json};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// Deliveries of one event to one sink before it is given up on
+pub const DEFAULT_NOTIFY_MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after it
+pub const NOTIFY_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Format the events are delivered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyKind {
+    /// The event message as published to the message bus
+    Json,
+    /// Slack incoming webhook, a markdown `text`
+    Slack,
+    /// PagerDuty Events API v2
+    Pagerduty,
+}
+
+impl NotifyKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            NotifyKind::Json => "json",
+            NotifyKind::Slack => "slack",
+            NotifyKind::Pagerduty => "pagerduty",
+        }
+    }
+
+    /// Whether the sink alerts people (held back during maintenance) rather
+    /// than feeding a collector
+    pub fn is_alert(self) -> bool {
+        matches!(self, NotifyKind::Slack | NotifyKind::Pagerduty)
+    }
+}
+
+/// `--notify kind=url`: deliver every event to `url` in the format of `kind`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifyTarget {
+    pub kind: NotifyKind,
+    pub url: String,
+}
+
+impl FromStr for NotifyTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, url) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected kind=url, got {}", redact_endpoint(s)))?;
+        let kind = match kind {
+            "json" => NotifyKind::Json,
+            "slack" => NotifyKind::Slack,
+            "pagerduty" => NotifyKind::Pagerduty,
+            other => bail!("unknown sink {}, expected json, slack or pagerduty", other),
+        };
+        let parsed = Url::parse(url).map_err(|e| anyhow!("invalid {} URL: {}", kind.name(), e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            bail!("{} URL must be http:// or https://", kind.name());
+        }
+        Ok(Self {
+            kind,
+            url: url.to_string(),
+        })
+    }
+}
+
+/// The URL is redacted: webhook URLs carry their credentials in the path
+impl fmt::Display for NotifyTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.kind.name(), redact_endpoint(&self.url))
+    }
+}
+
+impl Serialize for NotifyTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Formats an event for one kind of sink
+pub trait Notifier: Send + Sync {
+    /// Body of the POST delivering `message`
+    fn body(&self, message: &EventMessage) -> Value;
+}
+
+/// The event message unchanged, for collectors of the bus format
+pub struct GenericJson;
+
+impl Notifier for GenericJson {
+    fn body(&self, message: &EventMessage) -> Value {
+        serde_json::to_value(message).expect("serialize event")
+    }
+}
+
+/// Slack incoming webhook
+pub struct Slack;
+
+impl Notifier for Slack {
+    fn body(&self, message: &EventMessage) -> Value {
+        json!({ "text": slack_text(message) })
+    }
+}
+
+/// PagerDuty Events API v2. Delinquency triggers an incident that the
+/// recovery resolves; everything else triggers one of its own.
+pub struct Pagerduty {
+    pub routing_key: String,
+}
+
+impl Notifier for Pagerduty {
+    fn body(&self, message: &EventMessage) -> Value {
+        let event = &message.event;
+        let mut body = json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "payload": {
+                "summary": summary(event),
+                "source": message.vote_pubkey,
+                "severity": severity(event),
+                "component": "tvc_tracker",
+                "class": event.kind(),
+                "custom_details": message,
+            },
+        });
+        if let TrackerEvent::DelinquencyChanged { delinquent } = event {
+            body["dedup_key"] = format!("tvc_tracker/{}/delinquent", message.vote_pubkey).into();
+            if !delinquent {
+                body["event_action"] = "resolve".into();
+            }
+        }
+        body
+    }
+}
+
+fn severity(event: &TrackerEvent) -> &'static str {
+    match event {
+        TrackerEvent::EpochRollover { .. } => "info",
+        TrackerEvent::DelinquencyChanged { delinquent: true } => "critical",
+        TrackerEvent::DelinquencyChanged { delinquent: false } => "info",
+        TrackerEvent::StakeDecreased { .. } | TrackerEvent::ValidatorRestart { .. } => "warning",
+    }
+}
+
+/// One line describing `event`
+pub fn summary(event: &TrackerEvent) -> String {
+    match event {
+        TrackerEvent::EpochRollover {
+            epoch,
+            credits,
+            missed,
+            ..
+        } => {
+            let mut line = format!(
+                "Epoch {} finished with {} credits, {} missed",
+                epoch, credits, missed
+            );
+            let expected = credits + missed;
+            if expected > 0 {
+                line.push_str(&format!(
+                    " ({:.2}% efficiency)",
+                    *credits as f64 / expected as f64 * 100.0
+                ));
+            }
+            line
+        }
+        TrackerEvent::DelinquencyChanged { delinquent: true } => {
+            "Vote account is delinquent".to_string()
+        }
+        TrackerEvent::DelinquencyChanged { delinquent: false } => {
+            "Vote account is no longer delinquent".to_string()
+        }
+        TrackerEvent::StakeDecreased {
+            epoch,
+            delta_lamports,
+        } => format!(
+            "Activated stake dropped by {:.2} SOL at the start of epoch {}",
+            delta_lamports.unsigned_abs() as f64 / 1e9,
+            epoch
+        ),
+        TrackerEvent::ValidatorRestart {
+            last_vote_slot,
+            resumed_vote_slot,
+        } => format!(
+            "Suspected validator restart: votes resumed at slot {} after slot {}",
+            resumed_vote_slot, last_vote_slot
+        ),
+    }
+}
+
+/// Slack markdown for `message`: the summary in bold with the vote account,
+/// then the details of an epoch rollover
+pub fn slack_text(message: &EventMessage) -> String {
+    let mut text = format!(
+        "*{}*\nVote account `{}`",
+        summary(&message.event),
+        message.vote_pubkey
+    );
+    if let TrackerEvent::EpochRollover {
+        perfect_slot_streak_max,
+        first_tracked_slot,
+        last_tracked_slot,
+        comparison,
+        ..
+    } = &message.event
+    {
+        if let (Some(first), Some(last)) = (first_tracked_slot, last_tracked_slot) {
+            text.push_str(&format!("\nTracked slots {}..={}", first, last));
+        }
+        if let Some(streak) = perfect_slot_streak_max {
+            text.push_str(&format!("\nLongest perfect streak: {} slots", streak));
+        }
+        if let Some(comparison) = comparison {
+            text.push_str(&format!("\n{}", comparison));
+        }
+    }
+    text
+}
+
+/// A configured destination: where to POST, in which format and with what
+/// retry budget
+pub struct Sink {
+    /// `sink` label of the delivery counters
+    pub label: String,
+    url: String,
+    notifier: Box<dyn Notifier>,
+    /// Slack or PagerDuty, suppressed during maintenance
+    alerts: bool,
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+impl Sink {
+    /// Sink for `target`; PagerDuty needs `routing_key`
+    pub fn new(target: &NotifyTarget, label: &str, routing_key: Option<&str>) -> Result<Self> {
+        let notifier: Box<dyn Notifier> = match target.kind {
+            NotifyKind::Json => Box::new(GenericJson),
+            NotifyKind::Slack => Box::new(Slack),
+            NotifyKind::Pagerduty => Box::new(Pagerduty {
+                routing_key: routing_key
+                    .ok_or_else(|| anyhow!("a pagerduty sink requires --notify-routing-key"))?
+                    .to_string(),
+            }),
+        };
+        Ok(Self {
+            label: label.to_string(),
+            url: target.url.clone(),
+            notifier,
+            alerts: target.kind.is_alert(),
+            max_attempts: DEFAULT_NOTIFY_MAX_ATTEMPTS,
+            backoff: NOTIFY_RETRY_BACKOFF,
+        })
+    }
+
+    /// Give up on an event after `max_attempts` deliveries, waiting `backoff`
+    /// before the first retry and twice as long before each one after it
+    pub fn with_retries(mut self, max_attempts: u32, backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    /// POST `message`, retrying within the budget. The outcome is counted
+    /// and logged, never returned.
+    pub async fn deliver(
+        &self,
+        client: &reqwest::Client,
+        message: &EventMessage,
+        metrics: &Metrics,
+    ) {
+        let body = self.notifier.body(message);
+        let mut backoff = self.backoff;
+        for attempt in 1..=self.max_attempts {
+            match self.post(client, &body).await {
+                Ok(()) => {
+                    debug!("Delivered {} to {}", message.event.kind(), self.label);
+                    metrics
+                        .notify_deliveries
+                        .with_label_values(&[&self.label, "success"])
+                        .inc();
+                    return;
+                }
+                Err(e) if attempt < self.max_attempts => {
+                    debug!(
+                        "Delivering {} to {} failed (attempt {}): {:#}",
+                        message.event.kind(),
+                        self.label,
+                        attempt,
+                        e
+                    );
+                    metrics
+                        .notify_retries
+                        .with_label_values(&[&self.label])
+                        .inc();
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    warn!(
+                        "Giving up delivering {} to {} after {} attempts: {:#}",
+                        message.event.kind(),
+                        self.label,
+                        attempt,
+                        e
+                    );
+                    metrics
+                        .notify_deliveries
+                        .with_label_values(&[&self.label, "failure"])
+                        .inc();
+                }
+            }
+        }
+    }
+
+    async fn post(&self, client: &reqwest::Client, body: &Value) -> Result<()> {
+        let status = client.post(&self.url).json(body).send().await?.status();
+        if !status.is_success() {
+            bail!("HTTP {}", status);
+        }
+        Ok(())
+    }
+}
+
+/// `sink` label of each target: its kind, numbered from the second sink of
+/// the same kind on (`slack`, `slack_2`, ...)
+pub fn sink_labels(targets: &[NotifyTarget]) -> Vec<String> {
+    targets
+        .iter()
+        .enumerate()
+        .map(|(i, target)| {
+            let same_kind = targets[..i]
+                .iter()
+                .filter(|earlier| earlier.kind == target.kind)
+                .count();
+            match same_kind {
+                0 => target.kind.name().to_string(),
+                n => format!("{}_{}", target.kind.name(), n + 1),
+            }
+        })
+        .collect()
+}
+
+/// Deliver every event from `events` to `sink` until the channel closes.
+/// Each sink runs in a task of its own, so a slow or failing one neither
+/// delays the others nor spends their retry budget. Alert sinks drop the
+/// events raised during a maintenance window.
+pub async fn run_sink(
+    mut events: broadcast::Receiver<TrackerEvent>,
+    sink: Sink,
+    client: reqwest::Client,
+    vote_pubkey: String,
+    metrics: Arc<Metrics>,
+    maintenance: Arc<Maintenance>,
+) {
+    for outcome in ["success", "failure", "dropped", "suppressed"] {
+        metrics
+            .notify_deliveries
+            .with_label_values(&[&sink.label, outcome]);
+    }
+    metrics.notify_retries.with_label_values(&[&sink.label]);
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!(
+                    "Sink {} fell behind, {} events dropped",
+                    sink.label, skipped
+                );
+                metrics
+                    .notify_deliveries
+                    .with_label_values(&[&sink.label, "dropped"])
+                    .inc_by(skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if sink.alerts && maintenance.is_active() {
+            debug!(
+                "Not alerting {} of {} during maintenance",
+                sink.label,
+                event.kind()
+            );
+            metrics
+                .notify_deliveries
+                .with_label_values(&[&sink.label, "suppressed"])
+                .inc();
+            continue;
+        }
+        let message = EventMessage {
+            schema_version: SCHEMA_VERSION,
+            vote_pubkey: vote_pubkey.clone(),
+            timestamp: unix_now(),
+            event,
+        };
+        sink.deliver(&client, &message, &metrics).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::ws::EpochComparison;
+    use axum::http::StatusCode;
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+
+    fn message(event: TrackerEvent) -> EventMessage {
+        EventMessage {
+            schema_version: SCHEMA_VERSION,
+            vote_pubkey: "Vote111".to_string(),
+            timestamp: 1_700_000_000,
+            event,
+        }
+    }
+
+    fn rollover() -> TrackerEvent {
+        TrackerEvent::EpochRollover {
+            epoch: 501,
+            credits: 7_000,
+            missed: 1_000,
+            perfect_slot_streak_max: Some(420),
+            first_tracked_slot: Some(216_432_000),
+            last_tracked_slot: Some(216_863_999),
+            comparison: Some(EpochComparison {
+                previous_epoch: 500,
+                credits_delta: 1_000,
+                missed_delta: -1_000,
+                efficiency_delta: None,
+                avg_latency_delta: None,
+            }),
+            cluster: None,
+        }
+    }
+
+    /// Record the bodies POSTed to `/hook`, failing the first `failures`
+    async fn mock_hook(failures: usize) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let recorded = received.clone();
+        let app = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move |axum::Json(body): axum::Json<Value>| async move {
+                let mut received = recorded.lock().unwrap();
+                received.push(body);
+                if received.len() <= failures {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                } else {
+                    StatusCode::OK
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/hook", addr), received)
+    }
+
+    #[test]
+    fn test_slack_text_template() {
+        assert_eq!(
+            slack_text(&message(rollover())),
+            "*Epoch 501 finished with 7000 credits, 1000 missed (87.50% efficiency)*\n\
+             Vote account `Vote111`\n\
+             Tracked slots 216432000..=216863999\n\
+             Longest perfect streak: 420 slots\n\
+             vs epoch 500: credits +1000, missed -1000"
+        );
+        assert_eq!(
+            slack_text(&message(TrackerEvent::StakeDecreased {
+                epoch: 502,
+                delta_lamports: -1_500_000_000,
+            })),
+            "*Activated stake dropped by 1.50 SOL at the start of epoch 502*\nVote account `Vote111`"
+        );
+    }
+
+    #[test]
+    fn test_sink_formats() {
+        let json = GenericJson.body(&message(rollover()));
+        assert_eq!(json["type"], "epoch_rollover");
+        assert_eq!(json["vote_pubkey"], "Vote111");
+
+        let pagerduty = Pagerduty {
+            routing_key: "R0UTING".to_string(),
+        };
+        let body = pagerduty.body(&message(TrackerEvent::DelinquencyChanged {
+            delinquent: true,
+        }));
+        assert_eq!(body["routing_key"], "R0UTING");
+        assert_eq!(body["event_action"], "trigger");
+        assert_eq!(body["payload"]["severity"], "critical");
+        assert_eq!(body["payload"]["source"], "Vote111");
+        assert_eq!(body["payload"]["summary"], "Vote account is delinquent");
+        let recovered = pagerduty.body(&message(TrackerEvent::DelinquencyChanged {
+            delinquent: false,
+        }));
+        assert_eq!(recovered["event_action"], "resolve");
+        assert_eq!(recovered["dedup_key"], body["dedup_key"]);
+        assert!(
+            pagerduty
+                .body(&message(rollover()))
+                .get("dedup_key")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_targets_and_labels() {
+        let targets: Vec<NotifyTarget> = [
+            "slack=https://hooks.slack.com/services/T0/B0/secret",
+            "json=http://collector:8080/events",
+            "slack=https://hooks.slack.com/services/T1/B1/secret",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        assert_eq!(targets[0].kind, NotifyKind::Slack);
+        assert_eq!(targets[1].url, "http://collector:8080/events");
+        assert_eq!(targets[0].to_string(), "slack=https://hooks.slack.com/***");
+        assert_eq!(sink_labels(&targets), ["slack", "json", "slack_2"]);
+
+        for invalid in [
+            "https://hooks.slack.com/x",
+            "teams=https://example.com/x",
+            "json=collector:8080",
+            "json=ftp://collector/x",
+        ] {
+            assert!(invalid.parse::<NotifyTarget>().is_err(), "{}", invalid);
+        }
+        let pagerduty: NotifyTarget = "pagerduty=https://events.pagerduty.com/v2/enqueue"
+            .parse()
+            .unwrap();
+        assert!(Sink::new(&pagerduty, "pagerduty", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_within_its_budget() {
+        let metrics = Metrics::new().unwrap();
+        let client = reqwest::Client::new();
+        let count = |label: &str, outcome: &str| {
+            metrics
+                .notify_deliveries
+                .with_label_values(&[label, outcome])
+                .get()
+        };
+
+        // Fails twice, then accepts the third attempt
+        let (url, received) = mock_hook(2).await;
+        let target: NotifyTarget = format!("slack={}", url).parse().unwrap();
+        let sink = Sink::new(&target, "slack", None)
+            .unwrap()
+            .with_retries(3, Duration::from_millis(10));
+        sink.deliver(&client, &message(rollover()), &metrics).await;
+        assert_eq!(received.lock().unwrap().len(), 3);
+        assert!(
+            received.lock().unwrap()[2]["text"]
+                .as_str()
+                .unwrap()
+                .starts_with("*Epoch 501 finished")
+        );
+        assert_eq!(count("slack", "success"), 1);
+        assert_eq!(
+            metrics.notify_retries.with_label_values(&["slack"]).get(),
+            2
+        );
+
+        // Never accepts: given up on after its own budget of two
+        let (url, received) = mock_hook(usize::MAX).await;
+        let target: NotifyTarget = format!("json={}", url).parse().unwrap();
+        let sink = Sink::new(&target, "json", None)
+            .unwrap()
+            .with_retries(2, Duration::from_millis(10));
+        sink.deliver(&client, &message(rollover()), &metrics).await;
+        assert_eq!(received.lock().unwrap().len(), 2);
+        assert_eq!(count("json", "failure"), 1);
+        assert_eq!(count("json", "success"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_every_sink_receives_each_event() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let events = crate::events::event_channel();
+        let mut hooks = Vec::new();
+        for kind in ["json", "slack"] {
+            let (url, received) = mock_hook(0).await;
+            let target: NotifyTarget = format!("{}={}", kind, url).parse().unwrap();
+            let sink = Sink::new(&target, kind, None).unwrap();
+            tokio::spawn(run_sink(
+                events.subscribe(),
+                sink,
+                reqwest::Client::new(),
+                "Vote111".to_string(),
+                metrics.clone(),
+                Arc::new(Maintenance::new()),
+            ));
+            hooks.push(received);
+        }
+
+        events.send(rollover()).unwrap();
+        for _ in 0..200 {
+            if hooks.iter().all(|hook| !hook.lock().unwrap().is_empty()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(hooks[0].lock().unwrap()[0]["epoch"], 501);
+        assert!(hooks[1].lock().unwrap()[0]["text"].is_string());
+    }
+
+    /// Poll `done` until it holds, for up to two seconds
+    async fn wait_until(done: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+
+    #[tokio::test]
+    async fn test_alerts_are_held_back_during_maintenance() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let maintenance = Arc::new(Maintenance::new().with_clock(clock.clone()));
+        let events = crate::events::event_channel();
+        let mut hooks = Vec::new();
+        for kind in ["slack", "json"] {
+            let (url, received) = mock_hook(0).await;
+            let target: NotifyTarget = format!("{}={}", kind, url).parse().unwrap();
+            tokio::spawn(run_sink(
+                events.subscribe(),
+                Sink::new(&target, kind, None).unwrap(),
+                reqwest::Client::new(),
+                "Vote111".to_string(),
+                metrics.clone(),
+                maintenance.clone(),
+            ));
+            hooks.push(received);
+        }
+        let (slack, json) = (&hooks[0], &hooks[1]);
+        let count = |label: &str, outcome: &str| {
+            metrics
+                .notify_deliveries
+                .with_label_values(&[label, outcome])
+                .get()
+        };
+
+        // Inside the window the collector still gets the event, Slack doesn't
+        maintenance.start(Duration::from_secs(3600), "upgrade");
+        events.send(rollover()).unwrap();
+        wait_until(|| count("slack", "suppressed") == 1 && json.lock().unwrap().len() == 1).await;
+        assert!(slack.lock().unwrap().is_empty());
+        assert_eq!(count("json", "suppressed"), 0);
+
+        // Once it expired, alerts are delivered again
+        clock.advance_secs(3600);
+        events.send(rollover()).unwrap();
+        wait_until(|| slack.lock().unwrap().len() == 1 && json.lock().unwrap().len() == 2).await;
+        assert!(slack.lock().unwrap()[0]["text"].is_string());
+        assert_eq!(count("slack", "success"), 1);
+        assert_eq!(count("slack", "suppressed"), 1);
+    }
+}
@@ -0,0 +1,2636 @@
+use crate::clock::{SharedClock, system_clock, unix_now};
+use crate::cluster::ClusterAverages;
+use crate::credits::CreditsModel;
+use crate::discontinuity::{Discontinuities, DiscontinuityEvent};
+use crate::events::{EventSender, TrackerEvent, emit};
+use crate::maintenance::Maintenance;
+use crate::metrics::{DisabledMetrics, HistogramWindow, Metrics};
+use crate::rpc::{RpcClient, RpcVoteAccount};
+use crate::rpc_health::RpcHealthWatch;
+use crate::ws::{
+    DEFAULT_TIP_ALLOWANCE_SLOTS, EpochInfo, EpochResult, EpochSummary, ExpectedAnchor,
+    MissedAccounting, SLOTS_PER_EPOCH, TipExpectation, interpolate_counter,
+};
+use crate::{throttled_error, throttled_warn};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::{Span, info, instrument, warn};
+
+/// Delay before the first retry after a failed poll, doubled per consecutive failure
+const ERROR_BACKOFF_BASE: Duration = Duration::from_secs(5);
+
+/// Upper bound for the error backoff
+const ERROR_BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// Bounds of the adaptive polling interval (--interval-secs 0)
+pub const ADAPTIVE_INTERVAL_MIN: Duration = Duration::from_secs(5);
+pub const ADAPTIVE_INTERVAL_MAX: Duration = Duration::from_secs(300);
+
+/// Adaptive interval before the first two polls measured the chain's progress
+const ADAPTIVE_INTERVAL_INITIAL: Duration = Duration::from_secs(30);
+
+/// Polling interval while an epoch boundary is near
+pub const BOUNDARY_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Nominal slot time, for the slots a poll interval spans until the root
+/// has been seen advancing
+const SLOT_DURATION: Duration = Duration::from_millis(400);
+
+/// Default new rooted slots per poll targeted by adaptive polling
+pub const DEFAULT_TARGET_SLOTS_PER_POLL: u64 = 64;
+
+/// Default drop in activated stake (percent) between epochs counted as a stake decrease
+pub const DEFAULT_STAKE_DECREASE_PCT: f64 = 10.0;
+
+/// Credit history shorter than this (in epochs) flags the vote account as young.
+/// `getVoteAccounts` returns at most 5 entries, so poll mode cannot see more.
+pub const DEFAULT_MIN_CREDIT_HISTORY_EPOCHS: usize = 5;
+
+/// How long the rolling history is kept (longest window)
+const HISTORY_RETENTION_MS: u64 = 3600 * 1000;
+
+/// Polling interval assumed before `run_poll` sets the one in effect
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// History entry: (unix millis, cumulative credits earned, cumulative missed credits)
+type PollHistEntry = (u64, u64, u64);
+
+/// Saved poll state older than this is not restored
+pub const POLL_STATE_MAX_AGE: Duration = Duration::from_secs(6 * 3600);
+
+/// Poll-mode counters kept across restarts in `--state-file`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedPoll {
+    /// Unix milliseconds of the last poll
+    pub saved_at: u64,
+    pub epoch: u64,
+    /// Epoch credits and missed credits at the last poll
+    pub credits: u64,
+    pub missed: u64,
+    pub credits_total: u64,
+    pub missed_total: u64,
+    #[serde(default)]
+    pub epoch_missed_maintenance: u64,
+    /// Missed credits during maintenance across polls
+    #[serde(default)]
+    pub missed_maintenance_total: u64,
+    #[serde(default)]
+    pub epoch_first_seen: Option<(u64, u64)>,
+    #[serde(default)]
+    pub epoch_first_root_slot: Option<u64>,
+    /// Window history: (unix millis, credits total, missed total)
+    #[serde(default)]
+    pub history: Vec<PollHistEntry>,
+    /// Result of the last finished epoch, restored even when the rest is discarded
+    #[serde(default)]
+    pub previous_epoch: Option<EpochResult>,
+}
+
+/// Latest poll state, shared with the state file saver
+pub type PollStateSlot = Arc<Mutex<Option<PersistedPoll>>>;
+
+/// Poll durations kept for the median
+const POLL_DURATION_SAMPLES: usize = 32;
+
+/// Polls timed before the interval is checked against their median
+const POLL_DURATION_MIN_SAMPLES: usize = 5;
+
+/// Poll interval steered towards a number of new rooted slots per poll
+#[derive(Debug, Clone)]
+struct AdaptiveInterval {
+    target_slots: u64,
+    current: Duration,
+    /// Root slot at the previous poll
+    prev_root: Option<u64>,
+}
+
+impl AdaptiveInterval {
+    fn new(target_slots: u64) -> Self {
+        Self {
+            target_slots,
+            current: ADAPTIVE_INTERVAL_INITIAL,
+            prev_root: None,
+        }
+    }
+
+    /// Scale the interval by the square root of target over observed new
+    /// slots, so it settles within a few polls without chasing one noisy
+    /// poll. A root that didn't move doubles it; one that went back (a
+    /// lagging node) is ignored.
+    fn observe(&mut self, root_slot: u64) {
+        let prev_root = self.prev_root;
+        if prev_root.is_some_and(|prev| root_slot < prev) {
+            return;
+        }
+        self.prev_root = Some(root_slot);
+        let Some(prev_root) = prev_root else {
+            return;
+        };
+        let factor = match root_slot - prev_root {
+            0 => 2.0,
+            slots => (self.target_slots as f64 / slots as f64).sqrt(),
+        };
+        self.current = self
+            .current
+            .mul_f64(factor)
+            .clamp(ADAPTIVE_INTERVAL_MIN, ADAPTIVE_INTERVAL_MAX);
+    }
+}
+
+/// Polling cadence around an epoch boundary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoundaryPhase {
+    #[default]
+    Normal,
+    /// The epoch ends within the slots of one interval: poll every
+    /// [`BOUNDARY_WATCH_INTERVAL`] until a poll sees a later epoch
+    BoundaryWatch { epoch: u64 },
+}
+
+/// Median of the most recent samples
+#[derive(Debug, Clone)]
+pub struct RollingMedian {
+    samples: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl RollingMedian {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add a sample, dropping the oldest one when full
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Median of the retained samples (mean of the middle two for an even count)
+    pub fn median(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let mid = sorted.len() / 2;
+        Some(if sorted.len() % 2 == 0 {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        })
+    }
+}
+
+/// Point-in-time view of a vote account derived from `getVoteAccounts`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountSnapshot {
+    pub vote_pubkey: String,
+    pub epoch_info: EpochInfo,
+    /// Credits earned this epoch (credits - previous_credits, matches `solana vote-account`)
+    pub credits_this_epoch: u64,
+    /// Credits earned in the previous epoch, if the account has an entry for it
+    pub last_epoch_credits: Option<u64>,
+    /// Epochs present in the account's epochCredits
+    pub credit_history: CreditHistory,
+    pub root_slot: u64,
+    pub last_vote: u64,
+    pub activated_stake: u64,
+    pub delinquent: bool,
+    /// Credits model used for the expected/missed credits of this snapshot
+    pub credits_model: CreditsModel,
+}
+
+impl AccountSnapshot {
+    /// Maximum possible credits for the rooted slots of this epoch
+    pub fn expected_max_rooted(&self) -> u64 {
+        self.epoch_info
+            .expected_max_credits(self.root_slot, &self.credits_model)
+    }
+
+    /// Credits missed this epoch relative to the rooted expectation
+    pub fn missed_this_epoch(&self) -> u64 {
+        self.expected_max_rooted()
+            .saturating_sub(self.credits_this_epoch)
+    }
+
+    /// Fraction of the rooted expectation earned this epoch
+    pub fn efficiency(&self) -> f64 {
+        let expected = self.expected_max_rooted();
+        if expected == 0 {
+            return 0.0;
+        }
+        self.credits_this_epoch as f64 / expected as f64
+    }
+}
+
+/// Epochs of credit history a vote account carries (up to 64 on chain)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CreditHistory {
+    /// Number of epochCredits entries
+    pub len: usize,
+    /// Oldest epoch with an entry
+    pub oldest_epoch: Option<u64>,
+    /// Whether the entries were out of epoch order. Some providers
+    /// occasionally return them unsorted, so the current entry is the one with
+    /// the highest epoch rather than the last.
+    pub unsorted: bool,
+}
+
+impl CreditHistory {
+    /// From the epochs of the epochCredits entries
+    pub fn from_epochs(epochs: impl IntoIterator<Item = u64>) -> Self {
+        epochs
+            .into_iter()
+            .fold((Self::default(), None), |(history, newest), epoch| {
+                let history = Self {
+                    len: history.len + 1,
+                    oldest_epoch: Some(history.oldest_epoch.map_or(epoch, |e| e.min(epoch))),
+                    unsorted: history.unsorted || newest.is_some_and(|n| epoch <= n),
+                };
+                (history, newest.max(Some(epoch)))
+            })
+            .0
+    }
+
+    /// Epochs from the oldest entry through `current_epoch` (0 without entries)
+    pub fn age_epochs(&self, current_epoch: u64) -> u64 {
+        self.oldest_epoch
+            .map_or(0, |oldest| (current_epoch + 1).saturating_sub(oldest))
+    }
+}
+
+/// Build a snapshot from a `getVoteAccounts` entry.
+///
+/// The epoch comes from the newest epochCredits entry (by epoch, the entries
+/// may be out of order); if the account has not
+/// earned anything yet in the epoch its root slot is in, that epoch is used
+/// with zero credits instead.
+pub fn snapshot_from_vote_account(
+    account: &RpcVoteAccount,
+    delinquent: bool,
+    credits_model: CreditsModel,
+) -> AccountSnapshot {
+    let root_epoch = account.root_slot / SLOTS_PER_EPOCH;
+    let newest = account
+        .epoch_credits
+        .iter()
+        .max_by_key(|(epoch, _, _)| *epoch);
+
+    let (epoch, credits_this_epoch) = match newest {
+        Some(&(epoch, credits, previous)) if epoch >= root_epoch => {
+            (epoch, credits.saturating_sub(previous))
+        }
+        _ => (root_epoch, 0),
+    };
+
+    let last_epoch_credits = account
+        .epoch_credits
+        .iter()
+        .find(|(e, _, _)| *e + 1 == epoch)
+        .map(|(_, credits, previous)| credits.saturating_sub(*previous));
+
+    AccountSnapshot {
+        vote_pubkey: account.vote_pubkey.clone(),
+        epoch_info: EpochInfo {
+            epoch,
+            slot_index: account.root_slot % SLOTS_PER_EPOCH,
+            epoch_start_slot: epoch * SLOTS_PER_EPOCH,
+            slots_in_epoch: SLOTS_PER_EPOCH,
+        },
+        credits_this_epoch,
+        last_epoch_credits,
+        credit_history: CreditHistory::from_epochs(account.epoch_credits.iter().map(|e| e.0)),
+        root_slot: account.root_slot,
+        last_vote: account.last_vote,
+        activated_stake: account.activated_stake,
+        delinquent,
+        credits_model,
+    }
+}
+
+/// Credits earned and missed between two polls
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PollUpdate {
+    pub credits_delta: u64,
+    pub missed_delta: u64,
+    /// Missed credits of the epoch that just ended (set on epoch rollover)
+    pub missed_last_epoch: Option<u64>,
+    /// Same, counted only over the slots the account can have voted in when
+    /// that epoch was its first (set on epoch rollover)
+    pub missed_last_epoch_adjusted: Option<u64>,
+    /// Change in activated stake since the end of the previous epoch (set on epoch rollover)
+    pub stake_delta_lamports: Option<i64>,
+    /// Activated stake dropped by more than the configured percentage at the rollover
+    pub stake_decreased: bool,
+    /// The account entered or left the delinquent set since the last poll
+    pub delinquency_changed: bool,
+    /// Summary of the epoch that just ended (set on epoch rollover)
+    pub epoch_summary: Option<EpochSummary>,
+    /// Missed credits during maintenance, left out of `missed_delta`
+    pub maintenance_missed: u64,
+    /// Missed credits total restored from the state file (set on the first poll)
+    pub restored_missed_total: u64,
+    /// Maintenance missed credits total restored from the state file (set on
+    /// the first poll)
+    pub restored_maintenance_missed_total: u64,
+    /// Root slot progress since the previous poll (None on the first one)
+    pub root_advance: Option<RootAdvance>,
+}
+
+/// Root slot progress between two polls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootAdvance {
+    pub slots: u64,
+    pub elapsed: Duration,
+}
+
+impl RootAdvance {
+    /// Slots rooted per second (None if no time passed)
+    pub fn rate(&self) -> Option<f64> {
+        let secs = self.elapsed.as_secs_f64();
+        (secs > 0.0).then(|| self.slots as f64 / secs)
+    }
+}
+
+/// Credits earned and missed over a time window
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WindowStats {
+    pub credits: u64,
+    pub missed: u64,
+}
+
+impl WindowStats {
+    pub fn expected(&self) -> u64 {
+        self.credits + self.missed
+    }
+
+    /// Fraction of max credits earned (None when nothing was rooted in the window)
+    pub fn efficiency(&self) -> Option<f64> {
+        let expected = self.expected();
+        if expected == 0 {
+            return None;
+        }
+        Some(self.credits as f64 / expected as f64)
+    }
+
+    /// Average credits per rooted slot
+    pub fn credits_per_slot(&self, model: &CreditsModel) -> Option<f64> {
+        self.efficiency()
+            .map(|e| e * model.max_credits_per_slot as f64)
+    }
+}
+
+/// Per-account state carried between polls
+#[derive(Debug)]
+pub struct PollState {
+    /// Epoch seen at the previous poll
+    prev_epoch: Option<u64>,
+    /// Credits earned this epoch at the previous poll
+    prev_credits: Option<u64>,
+    /// Missed credits this epoch at the previous poll
+    prev_missed: Option<u64>,
+    /// Credits earned across polls (for window calculations)
+    credits_total_acc: u64,
+    /// Missed credits across polls (for window calculations)
+    missed_total_acc: u64,
+    /// Missed credits this epoch during maintenance
+    epoch_missed_maintenance: u64,
+    /// Missed credits during maintenance across polls
+    missed_maintenance_total_acc: u64,
+    /// Missed credits this epoch added up poll by poll from the first poll
+    /// of the epoch, against the latest snapshot's expected minus actual
+    epoch_missed_incremental: u64,
+    /// Planned maintenance, whose missed credits stay out of the totals
+    maintenance: Option<Arc<Maintenance>>,
+    /// Missed credits in the last completed epoch
+    missed_last_epoch: Option<u64>,
+    /// Result of the last completed epoch, compared against at the next rollover
+    previous_epoch: Option<EpochResult>,
+    /// Missed credits in the last completed epoch, over its active slots only
+    missed_last_epoch_adjusted: Option<u64>,
+    /// Slot index and epoch credits at the first poll of the current epoch
+    epoch_first_seen: Option<(u64, u64)>,
+    /// Root slot at the first poll of the current epoch
+    epoch_first_root_slot: Option<u64>,
+    /// Activated stake at the previous poll (the end-of-epoch stake once the epoch rolls over)
+    prev_stake: Option<u64>,
+    /// Whether the account was delinquent at the previous poll
+    prev_delinquent: Option<bool>,
+    /// Highest root slot seen, to spot RPC nodes lagging behind it
+    max_root_slot: Option<u64>,
+    /// Root slot and wall-clock millis at the previous poll
+    prev_root_slot: Option<(u64, u64)>,
+    /// Drop in activated stake (percent) counted as a stake decrease
+    stake_decrease_pct: f64,
+    /// Rolling history for time-windowed stats
+    hist: VecDeque<PollHistEntry>,
+    /// Polling interval in effect; a window baseline older than the window
+    /// start by more than this lies before a gap in the polls
+    update_interval: Duration,
+    /// Consecutive failed polls (drives the error backoff)
+    consecutive_errors: u32,
+    /// Interval following the chain's progress, replacing the fixed one
+    adaptive_interval: Option<AdaptiveInterval>,
+    /// Cadence around the epoch boundary
+    boundary_phase: BoundaryPhase,
+    /// Slot time measured from the root's advance between polls
+    slot_duration: Duration,
+    /// Poll again right away (the epoch just rolled over)
+    poll_now: bool,
+    /// Wall clock for history timestamps
+    clock: SharedClock,
+    /// Anchor of the primary expected max / missed metrics
+    expected_anchor: ExpectedAnchor,
+    /// Slots behind the tip for the tip-anchored expectation
+    tip_allowance_slots: u64,
+    /// End-to-end durations of recent polls
+    poll_durations: RollingMedian,
+    /// Whether the interval was already reported as too short
+    interval_warned: bool,
+    /// Credit history shorter than this flags the account as young
+    min_credit_history_epochs: usize,
+    /// Receives epoch rollovers, stake decreases and delinquency changes
+    events: Option<EventSender>,
+    /// Records epoch rollovers
+    discontinuities: Option<Arc<Discontinuities>>,
+    /// Saved state to resume from, checked against the first poll
+    restored: Option<PersistedPoll>,
+    /// Receives the state after every poll, for the state file
+    persist_to: Option<PollStateSlot>,
+    /// Health of the RPC node; polls while it is behind are not fresh data
+    rpc_health: Option<Arc<RpcHealthWatch>>,
+    /// Cluster averages for the epoch summaries
+    cluster_averages: Option<Arc<ClusterAverages>>,
+}
+
+impl Default for PollState {
+    fn default() -> Self {
+        Self {
+            prev_epoch: None,
+            prev_credits: None,
+            prev_missed: None,
+            credits_total_acc: 0,
+            missed_total_acc: 0,
+            epoch_missed_maintenance: 0,
+            missed_maintenance_total_acc: 0,
+            epoch_missed_incremental: 0,
+            maintenance: None,
+            missed_last_epoch: None,
+            previous_epoch: None,
+            missed_last_epoch_adjusted: None,
+            epoch_first_seen: None,
+            epoch_first_root_slot: None,
+            prev_stake: None,
+            prev_delinquent: None,
+            max_root_slot: None,
+            prev_root_slot: None,
+            stake_decrease_pct: DEFAULT_STAKE_DECREASE_PCT,
+            hist: VecDeque::new(),
+            update_interval: DEFAULT_UPDATE_INTERVAL,
+            consecutive_errors: 0,
+            adaptive_interval: None,
+            boundary_phase: BoundaryPhase::Normal,
+            slot_duration: SLOT_DURATION,
+            poll_now: false,
+            clock: system_clock(),
+            expected_anchor: ExpectedAnchor::Root,
+            tip_allowance_slots: DEFAULT_TIP_ALLOWANCE_SLOTS,
+            poll_durations: RollingMedian::new(POLL_DURATION_SAMPLES),
+            interval_warned: false,
+            min_credit_history_epochs: DEFAULT_MIN_CREDIT_HISTORY_EPOCHS,
+            events: None,
+            discontinuities: None,
+            restored: None,
+            persist_to: None,
+            rpc_health: None,
+            cluster_averages: None,
+        }
+    }
+}
+
+impl PollState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the drop in activated stake (percent) counted as a stake decrease
+    pub fn with_stake_decrease_pct(mut self, pct: f64) -> Self {
+        self.stake_decrease_pct = pct;
+        self
+    }
+
+    /// Anchor the primary expected max / missed metrics at the root or the tip
+    pub fn with_expected_anchor(
+        mut self,
+        anchor: ExpectedAnchor,
+        tip_allowance_slots: u64,
+    ) -> Self {
+        self.expected_anchor = anchor;
+        self.tip_allowance_slots = tip_allowance_slots;
+        self
+    }
+
+    /// Use `clock` for history timestamps instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Flag the account as young while its credit history is shorter than `epochs`
+    pub fn with_min_credit_history_epochs(mut self, epochs: usize) -> Self {
+        self.min_credit_history_epochs = epochs;
+        self
+    }
+
+    /// Adapt the interval to about `target_slots` new rooted slots per poll
+    /// instead of polling at a fixed one (--interval-secs 0)
+    pub fn with_adaptive_interval(mut self, target_slots: Option<u64>) -> Self {
+        self.adaptive_interval = target_slots.map(AdaptiveInterval::new);
+        self
+    }
+
+    /// Count missed credits during `maintenance` separately from the totals
+    pub fn with_maintenance(mut self, maintenance: Option<Arc<Maintenance>>) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    /// Send epoch rollovers, stake decreases and delinquency changes to `events`
+    pub fn with_events(mut self, events: Option<EventSender>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Record epoch rollovers in `discontinuities`
+    pub fn with_discontinuities(mut self, discontinuities: Option<Arc<Discontinuities>>) -> Self {
+        self.discontinuities = discontinuities;
+        self
+    }
+
+    /// Resume from a saved state. It is restored at the first poll if it is
+    /// at most [`POLL_STATE_MAX_AGE`] old and from the same epoch, and
+    /// discarded otherwise.
+    pub fn with_restored(mut self, restored: Option<PersistedPoll>) -> Self {
+        self.restored = restored;
+        self
+    }
+
+    /// Publish the state after every poll to `slot`, for the state file
+    pub fn with_persistence(mut self, slot: Option<PollStateSlot>) -> Self {
+        self.persist_to = slot;
+        self
+    }
+
+    /// Don't count polls as fresh data while `rpc_health` says the node is
+    /// behind the cluster
+    pub fn with_rpc_health(mut self, rpc_health: Option<Arc<RpcHealthWatch>>) -> Self {
+        self.rpc_health = rpc_health;
+        self
+    }
+
+    /// Record the cluster average of the finished epoch in its summary
+    pub fn with_cluster_averages(mut self, averages: Option<Arc<ClusterAverages>>) -> Self {
+        self.cluster_averages = averages;
+        self
+    }
+
+    /// Whether the RPC node last reported being behind the cluster
+    fn rpc_behind(&self) -> bool {
+        self.rpc_health
+            .as_ref()
+            .is_some_and(|health| health.is_behind())
+    }
+
+    /// The state to save, None before the first poll
+    pub fn persisted(&self) -> Option<PersistedPoll> {
+        Some(PersistedPoll {
+            saved_at: self.hist.back().map_or(0, |(t, _, _)| *t),
+            epoch: self.prev_epoch?,
+            credits: self.prev_credits?,
+            missed: self.prev_missed?,
+            credits_total: self.credits_total_acc,
+            missed_total: self.missed_total_acc,
+            epoch_missed_maintenance: self.epoch_missed_maintenance,
+            missed_maintenance_total: self.missed_maintenance_total_acc,
+            epoch_first_seen: self.epoch_first_seen,
+            epoch_first_root_slot: self.epoch_first_root_slot,
+            history: self.hist.iter().copied().collect(),
+            previous_epoch: self.previous_epoch,
+        })
+    }
+
+    /// Take over the saved state if it is fresh and from `epoch`. Returns
+    /// the restored missed credits total.
+    fn restore(&mut self, epoch: u64) -> Option<u64> {
+        let saved = self.restored.take()?;
+        self.previous_epoch = saved.previous_epoch;
+        let age = Duration::from_millis(self.clock.now_millis().saturating_sub(saved.saved_at));
+        if saved.epoch != epoch {
+            info!(
+                "Discarding saved poll state from epoch {}, now in epoch {}",
+                saved.epoch, epoch
+            );
+            return None;
+        }
+        if age > POLL_STATE_MAX_AGE {
+            info!("Discarding saved poll state from {:?} ago", age);
+            return None;
+        }
+        info!(
+            "Restored poll state from {:?} ago: {} missed credits in total",
+            age, saved.missed_total
+        );
+        self.prev_epoch = Some(saved.epoch);
+        self.prev_credits = Some(saved.credits);
+        self.prev_missed = Some(saved.missed);
+        self.credits_total_acc = saved.credits_total;
+        self.missed_total_acc = saved.missed_total;
+        self.epoch_missed_maintenance = saved.epoch_missed_maintenance;
+        self.missed_maintenance_total_acc = saved.missed_maintenance_total;
+        self.epoch_missed_incremental = saved.missed;
+        self.epoch_first_seen = saved.epoch_first_seen;
+        self.epoch_first_root_slot = saved.epoch_first_root_slot;
+        self.hist = saved.history.into();
+        Some(saved.missed_total)
+    }
+
+    /// Slots `root_slot` is behind the highest root seen so far (None if it
+    /// is not behind, in which case it becomes the new highest)
+    pub fn root_slot_regression(&mut self, root_slot: u64) -> Option<u64> {
+        match self.max_root_slot {
+            Some(max) if root_slot < max => Some(max - root_slot),
+            _ => {
+                self.max_root_slot = Some(root_slot);
+                None
+            }
+        }
+    }
+
+    /// Fold a new snapshot into the state and return what changed since the last poll
+    pub fn apply(&mut self, snapshot: &AccountSnapshot) -> PollUpdate {
+        let epoch = snapshot.epoch_info.epoch;
+        let credits = snapshot.credits_this_epoch;
+        let missed = snapshot.missed_this_epoch();
+
+        let mut update = PollUpdate::default();
+        if let Some(missed_total) = self.restore(epoch) {
+            update.restored_missed_total = missed_total;
+            update.restored_maintenance_missed_total = self.missed_maintenance_total_acc;
+        }
+        match (self.prev_epoch, self.prev_credits, self.prev_missed) {
+            (Some(prev_epoch), Some(prev_credits), Some(prev_missed)) if prev_epoch == epoch => {
+                update.credits_delta = credits.saturating_sub(prev_credits);
+                update.missed_delta = missed.saturating_sub(prev_missed);
+            }
+            (Some(prev_epoch), Some(prev_credits), Some(prev_missed)) if epoch > prev_epoch => {
+                // Epoch rollover: close out the previous epoch, then count the new one
+                let (earned_last, missed_last) =
+                    if let Some(earned_last) = snapshot.last_epoch_credits {
+                        let missed_last = snapshot
+                            .credits_model
+                            .expected_credits(SLOTS_PER_EPOCH)
+                            .saturating_sub(earned_last);
+                        let missed_adjusted = match self.epoch_first_seen {
+                            Some(first_seen)
+                                if snapshot.credit_history.oldest_epoch == Some(prev_epoch) =>
+                            {
+                                let model = &snapshot.credits_model;
+                                model
+                                    .expected_credits(first_epoch_active_slots(first_seen, model))
+                                    .saturating_sub(earned_last)
+                            }
+                            _ => missed_last,
+                        };
+                        self.missed_last_epoch = Some(missed_last);
+                        self.missed_last_epoch_adjusted = Some(missed_adjusted);
+                        update.missed_last_epoch = Some(missed_last);
+                        update.missed_last_epoch_adjusted = Some(missed_adjusted);
+                        update.credits_delta = earned_last.saturating_sub(prev_credits) + credits;
+                        update.missed_delta = missed_last.saturating_sub(prev_missed) + missed;
+                        (earned_last, missed_last)
+                    } else {
+                        update.credits_delta = credits;
+                        update.missed_delta = missed;
+                        (prev_credits, prev_missed)
+                    };
+
+                // Stake activates at epoch boundaries: compare against the end-of-epoch value
+                if let Some(prev_stake) = self.prev_stake {
+                    let delta = snapshot.activated_stake as i64 - prev_stake as i64;
+                    update.stake_delta_lamports = Some(delta);
+                    update.stake_decreased =
+                        is_stake_decrease(prev_stake, delta, self.stake_decrease_pct);
+                }
+
+                update.epoch_summary = Some(EpochSummary {
+                    epoch: prev_epoch,
+                    credits: earned_last,
+                    missed: missed_last.saturating_sub(self.epoch_missed_maintenance),
+                    worst_window_5m: None,
+                    perfect_slot_streak: None,
+                    stake_delta_lamports: update.stake_delta_lamports,
+                    unobserved: None,
+                    first_tracked_slot: self.epoch_first_root_slot,
+                    last_tracked_slot: self.epoch_last_tracked_slot(),
+                    avg_latency: None,
+                    comparison: None,
+                    cluster: None,
+                });
+                if let Some(summary) = &mut update.epoch_summary {
+                    summary.compare_with_previous(&mut self.previous_epoch);
+                    summary.set_cluster_average(self.cluster_averages.as_deref());
+                }
+                info!(
+                    "Epoch rollover {} -> {} for {}",
+                    prev_epoch, epoch, snapshot.vote_pubkey
+                );
+            }
+            // First poll (or an epoch regression from a lagging RPC node): baseline only
+            _ => {}
+        }
+
+        if self.prev_epoch != Some(epoch) {
+            self.epoch_first_seen = Some((snapshot.epoch_info.slot_index, credits));
+            self.epoch_first_root_slot = Some(snapshot.root_slot);
+            self.epoch_missed_maintenance = 0;
+        }
+        // Missed credits during maintenance stay out of the totals and windows
+        if self.maintenance.as_ref().is_some_and(|m| m.is_active()) {
+            update.maintenance_missed = std::mem::take(&mut update.missed_delta);
+            self.epoch_missed_maintenance += update.maintenance_missed;
+            self.missed_maintenance_total_acc += update.maintenance_missed;
+        }
+        if self.prev_epoch == Some(epoch) {
+            self.epoch_missed_incremental += update.missed_delta + update.maintenance_missed;
+        } else {
+            self.epoch_missed_incremental = missed;
+        }
+        self.credits_total_acc += update.credits_delta;
+        self.missed_total_acc += update.missed_delta;
+        self.prev_epoch = Some(epoch);
+        self.prev_credits = Some(credits);
+        self.prev_missed = Some(missed);
+        self.prev_stake = Some(snapshot.activated_stake);
+        update.delinquency_changed = self
+            .prev_delinquent
+            .is_some_and(|prev| prev != snapshot.delinquent);
+        self.prev_delinquent = Some(snapshot.delinquent);
+
+        // Keep the history ordered if the wall clock jumps backwards
+        let now = self
+            .clock
+            .now_millis()
+            .max(self.hist.back().map_or(0, |(t, _, _)| *t));
+        self.hist
+            .push_back((now, self.credits_total_acc, self.missed_total_acc));
+        update.root_advance = self.prev_root_slot.map(|(prev_root, prev_t)| RootAdvance {
+            slots: snapshot.root_slot.saturating_sub(prev_root),
+            elapsed: Duration::from_millis(now - prev_t),
+        });
+        if let Some(advance) = update.root_advance {
+            self.observe_slot_duration(advance);
+        }
+        self.prev_root_slot = Some((snapshot.root_slot, now));
+        let cutoff = now.saturating_sub(HISTORY_RETENTION_MS);
+        while let Some((t, _, _)) = self.hist.front() {
+            if *t < cutoff {
+                self.hist.pop_front();
+            } else {
+                break;
+            }
+        }
+        if let Some(slot) = &self.persist_to {
+            *slot.lock().unwrap_or_else(|e| e.into_inner()) = self.persisted();
+        }
+
+        update
+    }
+
+    /// Credits earned and missed over a time window
+    pub fn window_stats(&self, window_secs: u64) -> WindowStats {
+        if self.hist.is_empty() {
+            return WindowStats::default();
+        }
+
+        // Entries stamped after now (clock went backwards) count as in the window
+        let start = self.clock.now_millis().saturating_sub(window_secs * 1000);
+
+        // Baseline is the last entry BEFORE the window start (the oldest if
+        // none), interpolated towards the first entry in the window if it
+        // lies before a gap in the polls
+        let in_window = self.hist.partition_point(|(t, _, _)| *t < start);
+        let max_lag = self.update_interval.as_millis() as u64;
+        let (base_credits, base_missed) = match in_window.checked_sub(1) {
+            // The first poll adds nothing to the counters, so the oldest
+            // entry is a baseline too, also once older ones aged out
+            None => (self.hist[0].1, self.hist[0].2),
+            Some(i) => match (self.hist[i], self.hist.get(in_window)) {
+                ((base_t, credits, missed), Some(&(next_t, next_credits, next_missed)))
+                    if start - base_t > max_lag =>
+                {
+                    (
+                        interpolate_counter((base_t, credits), (next_t, next_credits), start),
+                        interpolate_counter((base_t, missed), (next_t, next_missed), start),
+                    )
+                }
+                ((_, credits, missed), _) => (credits, missed),
+            },
+        };
+
+        WindowStats {
+            credits: self.credits_total_acc.saturating_sub(base_credits),
+            missed: self.missed_total_acc.saturating_sub(base_missed),
+        }
+    }
+
+    /// Fraction of a time window the history spans, from its oldest entry
+    /// (or the window start) to its newest. Starts over from the first poll
+    /// after an outage longer than the history is kept.
+    pub fn window_coverage(&self, window_secs: u64) -> f64 {
+        let (Some((oldest, _, _)), Some((newest, _, _))) = (self.hist.front(), self.hist.back())
+        else {
+            return 0.0;
+        };
+        let start = self.clock.now_millis().saturating_sub(window_secs * 1000);
+        let end = (*newest).min(self.clock.now_millis());
+        let spanned = end.saturating_sub((*oldest).max(start));
+        (spanned as f64 / (window_secs * 1000) as f64).min(1.0)
+    }
+
+    /// Root slot at the first poll of the current epoch
+    pub fn epoch_first_tracked_slot(&self) -> Option<u64> {
+        self.epoch_first_root_slot
+    }
+
+    /// Root slot at the latest poll
+    pub fn epoch_last_tracked_slot(&self) -> Option<u64> {
+        self.prev_root_slot.map(|(root, _)| root)
+    }
+
+    /// Missed credits in the last completed epoch (if a rollover was observed)
+    pub fn missed_last_epoch(&self) -> Option<u64> {
+        self.missed_last_epoch
+    }
+
+    /// Missed credits in the last completed epoch over the slots the account
+    /// was active in (if a rollover was observed)
+    pub fn missed_last_epoch_adjusted(&self) -> Option<u64> {
+        self.missed_last_epoch_adjusted
+    }
+
+    /// Record how long a poll took. Returns the median poll duration the first
+    /// time the interval is shorter than twice that median (over-polling).
+    pub fn record_poll_duration(
+        &mut self,
+        duration: Duration,
+        interval: Duration,
+    ) -> Option<Duration> {
+        self.poll_durations.push(duration.as_secs_f64());
+        if self.interval_warned || self.poll_durations.len() < POLL_DURATION_MIN_SAMPLES {
+            return None;
+        }
+        let median = Duration::from_secs_f64(self.poll_durations.median()?);
+        if interval >= median * 2 {
+            return None;
+        }
+        self.interval_warned = true;
+        Some(median)
+    }
+
+    /// Feed the adaptive interval the root slot of a successful poll
+    pub fn observe_root_slot(&mut self, root_slot: u64) {
+        if let Some(adaptive) = &mut self.adaptive_interval {
+            adaptive.observe(root_slot);
+        }
+    }
+
+    /// Step the boundary cadence on the epoch position of a successful poll,
+    /// polling at `interval` otherwise. The watch starts once the epoch ends
+    /// within the slots `interval` spans, so the boundary isn't slept through.
+    /// It ends at the first poll of a later epoch, which asks for one more
+    /// poll right away: the root slot and the epochCredits entry don't always
+    /// roll over in the same poll.
+    pub fn observe_epoch_position(&mut self, info: &EpochInfo, interval: Duration) {
+        match self.boundary_phase {
+            BoundaryPhase::Normal => {
+                let remaining = info.slots_in_epoch.saturating_sub(info.slot_index + 1);
+                let interval_slots =
+                    (interval.as_secs_f64() / self.slot_duration.as_secs_f64()) as u64;
+                if remaining <= interval_slots {
+                    info!(
+                        "Epoch {} ends in {} slots, polling every {:?}",
+                        info.epoch, remaining, BOUNDARY_WATCH_INTERVAL
+                    );
+                    self.boundary_phase = BoundaryPhase::BoundaryWatch { epoch: info.epoch };
+                }
+            }
+            BoundaryPhase::BoundaryWatch { epoch } if info.epoch > epoch => {
+                info!("Epoch {} started, back to the normal interval", info.epoch);
+                self.boundary_phase = BoundaryPhase::Normal;
+                self.poll_now = true;
+            }
+            BoundaryPhase::BoundaryWatch { .. } => {}
+        }
+    }
+
+    /// Measure the slot time over a root advance. A poll without a new root
+    /// only says slots take at least as long as the poll did, which while
+    /// the cluster is halted keeps the boundary watch from starting early.
+    fn observe_slot_duration(&mut self, advance: RootAdvance) {
+        match advance.rate() {
+            Some(rate) if rate > 0.0 => self.slot_duration = Duration::from_secs_f64(1.0 / rate),
+            Some(_) => self.slot_duration = self.slot_duration.max(advance.elapsed),
+            None => {}
+        }
+    }
+
+    /// Slot time the boundary watch converts the poll interval with: as
+    /// measured from the root's last advance, nominal before one was seen
+    pub fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
+    /// Current cadence around the epoch boundary
+    pub fn boundary_phase(&self) -> BoundaryPhase {
+        self.boundary_phase
+    }
+
+    /// The adaptive interval if enabled, else the fixed `interval`; at most
+    /// [`BOUNDARY_WATCH_INTERVAL`] near an epoch boundary
+    pub fn poll_interval(&self, interval: Duration) -> Duration {
+        let interval = self
+            .adaptive_interval
+            .as_ref()
+            .map_or(interval, |adaptive| adaptive.current);
+        match self.boundary_phase {
+            BoundaryPhase::Normal => interval,
+            BoundaryPhase::BoundaryWatch { .. } => interval.min(BOUNDARY_WATCH_INTERVAL),
+        }
+    }
+
+    /// Delay before the next poll: the poll interval, none right after an
+    /// epoch rollover, or the error backoff after failures (even when
+    /// shorter than the adaptive interval)
+    pub fn next_delay(&self, interval: Duration) -> Duration {
+        if self.consecutive_errors == 0 {
+            if self.poll_now {
+                return Duration::ZERO;
+            }
+            return self.poll_interval(interval);
+        }
+        let exponent = (self.consecutive_errors - 1).min(16);
+        (ERROR_BACKOFF_BASE * 2u32.pow(exponent)).min(ERROR_BACKOFF_MAX)
+    }
+}
+
+/// Slots of its first epoch an account can have voted in, from the first poll
+/// of that epoch: (slot index, credits earned by then). The account is taken to
+/// have earned those credits at the full rate right before the poll, the
+/// latest it can have started; a new account is expected nothing before that.
+fn first_epoch_active_slots(first_seen: (u64, u64), model: &CreditsModel) -> u64 {
+    let (slot_index, credits) = first_seen;
+    let max_per_slot = model.max_credits_per_slot.max(1);
+    let slots_voted = credits.div_ceil(max_per_slot);
+    let start = (slot_index + 1).saturating_sub(slots_voted);
+    SLOTS_PER_EPOCH.saturating_sub(start)
+}
+
+/// Whether a stake change is a drop of more than `pct` percent of the previous stake
+fn is_stake_decrease(prev_stake: u64, delta: i64, pct: f64) -> bool {
+    delta < 0 && prev_stake > 0 && (-delta) as f64 / prev_stake as f64 * 100.0 > pct
+}
+
+/// Fetch the vote account once and update the state and metrics
+#[instrument(name = "poll", skip_all, fields(epoch = Empty, slot = Empty))]
+pub async fn poll_once<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    state: &mut PollState,
+    credits_model: CreditsModel,
+    metrics: &Metrics,
+) -> Result<AccountSnapshot> {
+    let accounts = rpc.get_vote_accounts(Some(vote_pubkey)).await?;
+    let (account, delinquent) = accounts
+        .find(vote_pubkey)
+        .ok_or_else(|| anyhow!("Vote account {} not found in getVoteAccounts", vote_pubkey))?;
+
+    let snapshot = snapshot_from_vote_account(account, delinquent, credits_model);
+    Span::current()
+        .record("epoch", snapshot.epoch_info.epoch)
+        .record("slot", snapshot.root_slot);
+
+    // A load-balanced pool may answer from a node behind the one that answered
+    // the last poll. Its epoch position and credits would pull the gauges
+    // back, so the previous values stand until the pool catches up.
+    if let Some(behind) = state.root_slot_regression(snapshot.root_slot) {
+        warn!(
+            "RPC reported root slot {}, {} slots behind one seen earlier; keeping the previous values",
+            snapshot.root_slot, behind
+        );
+        metrics.rpc_slot_regressions.inc();
+        return Ok(snapshot);
+    }
+    let update = state.apply(&snapshot);
+
+    // The tip-anchored variant is optional: keep polling if getSlot fails
+    let tip = match rpc.get_slot().await {
+        Ok(tip_slot) => Some(TipExpectation::new(
+            &snapshot.epoch_info,
+            snapshot.root_slot,
+            tip_slot,
+            state.tip_allowance_slots,
+            snapshot.credits_this_epoch,
+            &snapshot.credits_model,
+        )),
+        Err(e) => {
+            warn!("getSlot failed, skipping tip-anchored expectation: {:#}", e);
+            None
+        }
+    };
+
+    if let Some(summary) = &update.epoch_summary {
+        info!(
+            "Epoch {} finished: {} credits, {} missed, stake delta {}",
+            summary.epoch,
+            summary.credits,
+            summary.missed,
+            summary
+                .stake_delta_lamports
+                .map(|d| format!("{:+} lamports", d))
+                .unwrap_or_else(|| "n/a".to_string())
+        );
+        if let Some(comparison) = &summary.comparison {
+            info!("Epoch {} {}", summary.epoch, comparison);
+        }
+        if let Some(cluster) = &summary.cluster {
+            info!("Epoch {} credits {}", summary.epoch, cluster);
+        }
+        emit(state.events.as_ref(), TrackerEvent::from(summary));
+        if let Some(discontinuities) = &state.discontinuities {
+            discontinuities.record(
+                Some(snapshot.epoch_info.epoch),
+                Some(snapshot.root_slot),
+                DiscontinuityEvent::EpochRollover {
+                    from_epoch: summary.epoch,
+                    to_epoch: Some(snapshot.epoch_info.epoch),
+                    credits: summary.credits,
+                    missed: summary.missed,
+                },
+            );
+        }
+    }
+    if update.delinquency_changed {
+        emit(
+            state.events.as_ref(),
+            TrackerEvent::DelinquencyChanged {
+                delinquent: snapshot.delinquent,
+            },
+        );
+    }
+    if update.stake_decreased {
+        warn!(
+            "Activated stake of {} dropped by {} lamports at the start of epoch {}",
+            vote_pubkey,
+            update
+                .stake_delta_lamports
+                .unwrap_or_default()
+                .unsigned_abs(),
+            snapshot.epoch_info.epoch
+        );
+        emit(
+            state.events.as_ref(),
+            TrackerEvent::StakeDecreased {
+                epoch: snapshot.epoch_info.epoch,
+                delta_lamports: update.stake_delta_lamports.unwrap_or_default(),
+            },
+        );
+    }
+
+    update_poll_metrics(metrics, &snapshot, &update, state, tip);
+    metrics.finish_warmup();
+    Ok(snapshot)
+}
+
+/// Publish the metrics of one poll as one update cycle (see
+/// [`Metrics::begin_publication`])
+fn update_poll_metrics(
+    metrics: &Metrics,
+    snapshot: &AccountSnapshot,
+    update: &PollUpdate,
+    state: &PollState,
+    tip: Option<TipExpectation>,
+) {
+    let _publication = metrics.begin_publication();
+    let info = snapshot.epoch_info;
+    let model = snapshot.credits_model;
+    let slots_elapsed = info.slot_index + 1;
+    let remaining_slots = info.slots_in_epoch.saturating_sub(slots_elapsed);
+
+    metrics.epoch.set(info.epoch as i64);
+    metrics.slot_index.set(info.slot_index as i64);
+    metrics
+        .total_epoch_credits
+        .set(snapshot.credits_this_epoch as i64);
+    metrics.set_credit_history(
+        &snapshot.credit_history,
+        info.epoch,
+        state.min_credit_history_epochs,
+    );
+
+    let root_expected = snapshot.expected_max_rooted();
+    let primary_tip = metrics.set_anchored_expectations(
+        state.expected_anchor,
+        root_expected,
+        snapshot.credits_this_epoch,
+        tip,
+    );
+    let (expected, missed) = match primary_tip {
+        Some(tip) => (tip.expected_max, tip.provisional_missed),
+        None => (root_expected, snapshot.missed_this_epoch()),
+    };
+    metrics.epoch_expected_max.set(expected as i64);
+    metrics.set_tracked_slots(
+        state.epoch_first_tracked_slot(),
+        state.epoch_last_tracked_slot(),
+    );
+    metrics
+        .missed_current_epoch
+        .set(missed.saturating_sub(state.epoch_missed_maintenance) as i64);
+    metrics
+        .missed_current_epoch_maintenance
+        .set(state.epoch_missed_maintenance as i64);
+    metrics.set_missed_accounting(&MissedAccounting {
+        snapshot: snapshot.missed_this_epoch(),
+        incremental: state.epoch_missed_incremental,
+    });
+    metrics
+        .missed_total
+        .inc_by(update.restored_missed_total + update.missed_delta);
+    if let Some(advance) = update.root_advance {
+        metrics.rooted_slots_per_poll.observe(advance.slots as f64);
+        if advance.slots == 0 {
+            metrics.polls_no_root_progress.inc();
+        }
+        if let Some(rate) = advance.rate() {
+            metrics.root_slot_advance_rate.set(rate);
+        }
+    }
+    metrics
+        .missed_maintenance_total
+        .inc_by(update.restored_maintenance_missed_total + update.maintenance_missed);
+    if let Some(missed_last) = state.missed_last_epoch() {
+        metrics.missed_last_epoch.set(missed_last as i64);
+    }
+    if let Some(missed_last) = state.missed_last_epoch_adjusted() {
+        metrics.missed_last_epoch_adjusted.set(missed_last as i64);
+    }
+    if let Some(delta) = update.stake_delta_lamports {
+        metrics.activated_stake_delta.set(delta);
+    }
+    if update.stake_decreased {
+        metrics.stake_decreases.inc();
+    }
+
+    // Epoch-level metrics from the anchored expectation
+    let efficiency_epoch = if expected > 0 {
+        snapshot.credits_this_epoch as f64 / expected as f64
+    } else {
+        0.0
+    };
+    let per_slot_epoch = efficiency_epoch * model.max_credits_per_slot as f64;
+    metrics.vote_credits_efficiency_epoch.set(efficiency_epoch);
+    metrics.vote_credits_per_slot_epoch.set(per_slot_epoch);
+    metrics
+        .vote_latency_slots_epoch
+        .set(model.implied_latency(per_slot_epoch));
+
+    // Windowed metrics, left unset while the history barely covers the window
+    let projected = |per_slot: f64| {
+        model.projected_credits(
+            snapshot.credits_this_epoch,
+            per_slot,
+            remaining_slots,
+            info.slots_in_epoch,
+        ) as i64
+    };
+    let has_data_5m =
+        metrics.set_window_coverage(HistogramWindow::FiveMinutes, state.window_coverage(300));
+    let has_data_1h =
+        metrics.set_window_coverage(HistogramWindow::OneHour, state.window_coverage(3600));
+
+    if has_data_5m {
+        let stats_5m = state.window_stats(300);
+        metrics.missed_5m.set(stats_5m.missed as i64);
+        if let (Some(eff), Some(per_slot)) =
+            (stats_5m.efficiency(), stats_5m.credits_per_slot(&model))
+        {
+            metrics.vote_credits_efficiency_5m.set(eff);
+            metrics.vote_credits_per_slot_5m.set(per_slot);
+            metrics
+                .vote_latency_slots_5m
+                .set(model.implied_latency(per_slot));
+            if metrics.is_enabled(DisabledMetrics::PROJECTIONS) {
+                metrics.projected_credits_5m.set(projected(per_slot));
+            }
+        }
+    }
+
+    if has_data_1h {
+        let stats_1h = state.window_stats(3600);
+        metrics.missed_1h.set(stats_1h.missed as i64);
+        if let (Some(eff), Some(per_slot)) =
+            (stats_1h.efficiency(), stats_1h.credits_per_slot(&model))
+        {
+            metrics.vote_credits_efficiency_1h.set(eff);
+            metrics.vote_credits_per_slot_1h.set(per_slot);
+            metrics
+                .vote_latency_slots_1h
+                .set(model.implied_latency(per_slot));
+            if metrics.is_enabled(DisabledMetrics::PROJECTIONS) {
+                metrics.projected_credits_1h.set(projected(per_slot));
+            }
+        }
+    }
+
+    metrics.rpc_up.set(1);
+    // A node behind the cluster answers with old state: export it, but leave
+    // the data to go stale rather than vouch for it
+    if state.rpc_behind() {
+        throttled_warn!("RPC node is behind the cluster; not counting the poll as fresh data");
+    } else {
+        metrics.rpc_last_success.set(unix_now() as i64);
+    }
+}
+
+/// Poll the vote account forever at the given interval
+pub async fn run_poll<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    mut state: PollState,
+    credits_model: CreditsModel,
+    interval: Duration,
+    metrics: &Metrics,
+) -> Result<()> {
+    match &state.adaptive_interval {
+        Some(adaptive) => info!(
+            "Starting HTTP polling of vote account {}, adapting the interval to {} new rooted slots per poll",
+            vote_pubkey, adaptive.target_slots
+        ),
+        None => info!(
+            "Starting HTTP polling of vote account {} every {:?}",
+            vote_pubkey, interval
+        ),
+    }
+
+    loop {
+        let poll_interval = state.poll_interval(interval);
+        state.update_interval = poll_interval;
+        metrics.poll_interval.set(poll_interval.as_secs_f64());
+        let started = Instant::now();
+        let result = poll_once(rpc, vote_pubkey, &mut state, credits_model, metrics).await;
+        let elapsed = started.elapsed();
+        metrics.poll_duration.observe(elapsed.as_secs_f64());
+        if let Some(median) = state.record_poll_duration(elapsed, poll_interval) {
+            warn!(
+                "Polling interval {:?} is less than twice the median poll duration {:?}; the RPC can't keep up, consider a longer --interval-secs",
+                poll_interval, median
+            );
+        }
+
+        match result {
+            Ok(snapshot) => {
+                state.poll_now = false;
+                state.observe_root_slot(snapshot.root_slot);
+                state.observe_epoch_position(&snapshot.epoch_info, state.poll_interval(interval));
+                state.consecutive_errors = 0;
+                metrics.polls.with_label_values(&["success"]).inc();
+            }
+            Err(e) => {
+                metrics.polls.with_label_values(&["error"]).inc();
+                state.consecutive_errors += 1;
+                metrics.rpc_errors.inc();
+                metrics.rpc_up.set(0);
+                throttled_error!(
+                    "Poll failed ({} consecutive): {:#}",
+                    state.consecutive_errors,
+                    e
+                );
+            }
+        }
+        tokio::time::sleep(state.next_delay(interval)).await;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::cluster::ClusterAverage;
+    use crate::credits::MAX_CREDITS_PER_SLOT;
+    use crate::rpc::{RpcHealth, RpcVersion, RpcVoteAccounts};
+    use crate::rpc_health::check_health;
+    use std::sync::Mutex;
+
+    pub(crate) fn vote_account(
+        pubkey: &str,
+        epoch_credits: Vec<(u64, u64, u64)>,
+        root_slot: u64,
+    ) -> RpcVoteAccount {
+        RpcVoteAccount {
+            vote_pubkey: pubkey.to_string(),
+            node_pubkey: format!("node-{}", pubkey),
+            activated_stake: 1_000_000_000,
+            commission: 0,
+            epoch_vote_account: true,
+            epoch_credits,
+            last_vote: root_slot + 32,
+            root_slot,
+        }
+    }
+
+    /// Fake RPC whose vote account response can be swapped between polls
+    pub(crate) struct MutableTestRpc {
+        pub accounts: Mutex<RpcVoteAccounts>,
+        /// Pubkey filter of every getVoteAccounts call
+        pub vote_account_requests: Mutex<Vec<Option<String>>>,
+        /// Answer of getHealth, None to fail it
+        pub health: Mutex<Option<RpcHealth>>,
+    }
+
+    impl MutableTestRpc {
+        pub fn new(accounts: Vec<RpcVoteAccount>) -> Self {
+            Self {
+                accounts: Mutex::new(RpcVoteAccounts {
+                    current: accounts,
+                    delinquent: vec![],
+                }),
+                vote_account_requests: Mutex::new(vec![]),
+                health: Mutex::new(Some(RpcHealth::Ok)),
+            }
+        }
+
+        pub fn set(&self, accounts: Vec<RpcVoteAccount>) {
+            self.accounts.lock().unwrap().current = accounts;
+        }
+
+        pub fn set_health(&self, health: Option<RpcHealth>) {
+            *self.health.lock().unwrap() = health;
+        }
+    }
+
+    impl RpcClient for MutableTestRpc {
+        async fn get_vote_accounts(&self, vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+            self.vote_account_requests
+                .lock()
+                .unwrap()
+                .push(vote_pubkey.map(str::to_string));
+            let mut accounts = self.accounts.lock().unwrap().clone();
+            if let Some(pubkey) = vote_pubkey {
+                accounts.current.retain(|a| a.vote_pubkey == pubkey);
+                accounts.delinquent.retain(|a| a.vote_pubkey == pubkey);
+            }
+            Ok(accounts)
+        }
+
+        async fn get_version(&self) -> Result<RpcVersion> {
+            Ok(RpcVersion {
+                solana_core: "2.1.0".to_string(),
+                feature_set: Some(1),
+            })
+        }
+
+        async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+            Ok(true)
+        }
+
+        /// The tip runs 32 slots ahead of the highest root
+        async fn get_slot(&self) -> Result<u64> {
+            let accounts = self.accounts.lock().unwrap();
+            let max_root = accounts.current.iter().map(|a| a.root_slot).max();
+            Ok(max_root.unwrap_or_default() + 32)
+        }
+
+        async fn get_health(&self) -> Result<RpcHealth> {
+            self.health
+                .lock()
+                .unwrap()
+                .ok_or_else(|| anyhow!("connection refused"))
+        }
+    }
+
+    #[test]
+    fn test_snapshot_from_vote_account() {
+        let root = 812 * SLOTS_PER_EPOCH + 99;
+        let account = vote_account("A", vec![(811, 5000, 1000), (812, 6500, 5000)], root);
+        let snapshot = snapshot_from_vote_account(&account, false, CreditsModel::default());
+
+        assert_eq!(snapshot.epoch_info.epoch, 812);
+        assert_eq!(snapshot.epoch_info.slot_index, 99);
+        assert_eq!(snapshot.credits_this_epoch, 1500);
+        assert_eq!(snapshot.last_epoch_credits, Some(4000));
+        assert_eq!(snapshot.expected_max_rooted(), 100 * MAX_CREDITS_PER_SLOT);
+        assert_eq!(snapshot.missed_this_epoch(), 100);
+    }
+
+    #[test]
+    fn test_snapshot_from_unsorted_epoch_credits() {
+        let root = 812 * SLOTS_PER_EPOCH + 99;
+        let account = vote_account(
+            "A",
+            vec![(812, 6500, 5000), (810, 1000, 0), (811, 5000, 1000)],
+            root,
+        );
+        let snapshot = snapshot_from_vote_account(&account, false, CreditsModel::default());
+
+        assert_eq!(snapshot.epoch_info.epoch, 812);
+        assert_eq!(snapshot.credits_this_epoch, 1500);
+        assert_eq!(snapshot.last_epoch_credits, Some(4000));
+        assert!(snapshot.credit_history.unsorted);
+        assert_eq!(snapshot.credit_history.oldest_epoch, Some(810));
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_with_unsorted_epoch_credits() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 812 * SLOTS_PER_EPOCH;
+        let sorted = vec![(811, 5000, 1000), (812, 6500, 5000)];
+        let shuffled = vec![(812, 6600, 5000), (811, 5000, 1000)];
+        let rpc = MutableTestRpc::new(vec![vote_account("A", sorted, epoch_start + 99)]);
+        let mut state = PollState::new();
+
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        rpc.set(vec![vote_account("A", shuffled, epoch_start + 199)]);
+        let snapshot = poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+
+        // No rollover back to 811
+        assert_eq!(snapshot.epoch_info.epoch, 812);
+        assert_eq!(metrics.epoch.get(), 812);
+        assert_eq!(metrics.total_epoch_credits.get(), 1600);
+        assert_eq!(metrics.epoch_credits_unsorted.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_poll_state_restored_after_restart() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let account =
+            |credits: u64, root: u64| vote_account("A", vec![(10, credits, 0)], epoch_start + root);
+        let maintenance = Arc::new(Maintenance::new().with_clock(clock.clone()));
+        let mut before = PollState::new()
+            .with_clock(clock.clone())
+            .with_maintenance(Some(maintenance.clone()));
+        before.apply(&snapshot_from_vote_account(
+            &account(1600, 99),
+            false,
+            CreditsModel::default(),
+        ));
+        clock.advance_secs(60);
+        before.apply(&snapshot_from_vote_account(
+            &account(3100, 199),
+            false,
+            CreditsModel::default(),
+        ));
+        // Stopped for an upgrade, with 20 credits missed in the maintenance window
+        maintenance.start(Duration::from_secs(60), "upgrade");
+        before.apply(&snapshot_from_vote_account(
+            &account(3160, 204),
+            false,
+            CreditsModel::default(),
+        ));
+
+        // Down for 5 minutes, in which 95 more slots rooted with 50 credits missed
+        clock.advance_secs(300);
+        let metrics = Metrics::new().unwrap();
+        let rpc = MutableTestRpc::new(vec![account(4630, 299)]);
+        let mut state = PollState::new()
+            .with_clock(clock.clone())
+            .with_restored(before.persisted());
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+
+        assert_eq!(metrics.missed_total.get(), 150);
+        assert_eq!(metrics.missed_maintenance_total.get(), 20);
+        assert_eq!(state.window_stats(3600).missed, 150);
+        assert_eq!(state.window_stats(3600).credits, 3030);
+        assert_eq!(state.epoch_first_tracked_slot(), Some(epoch_start + 99));
+    }
+
+    #[test]
+    fn test_poll_state_not_restored_from_other_epoch_or_stale() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let poll = |epoch: u64, credits: u64, root: u64| {
+            snapshot_from_vote_account(
+                &vote_account(
+                    "A",
+                    vec![(epoch, credits, 0)],
+                    epoch * SLOTS_PER_EPOCH + root,
+                ),
+                false,
+                CreditsModel::default(),
+            )
+        };
+        let mut before = PollState::new().with_clock(clock.clone());
+        before.apply(&poll(10, 1600, 99));
+        clock.advance_secs(60);
+        before.apply(&poll(10, 3100, 199));
+        let saved = before.persisted();
+
+        // Restarted after the epoch rolled over: the first poll is a baseline again
+        clock.advance_secs(60);
+        let mut state = PollState::new()
+            .with_clock(clock.clone())
+            .with_restored(saved.clone());
+        assert_eq!(state.apply(&poll(11, 1600, 99)), PollUpdate::default());
+        assert_eq!(state.window_stats(3600).missed, 0);
+
+        // Same epoch, but saved too long ago
+        clock.advance_secs(POLL_STATE_MAX_AGE.as_secs());
+        let mut state = PollState::new()
+            .with_clock(clock.clone())
+            .with_restored(saved);
+        assert_eq!(state.apply(&poll(10, 4650, 299)), PollUpdate::default());
+        assert_eq!(state.persisted().unwrap().missed_total, 0);
+    }
+
+    #[test]
+    fn test_snapshot_without_tvc() {
+        let root = 812 * SLOTS_PER_EPOCH + 99;
+        let account = vote_account("A", vec![(812, 5090, 5000)], root);
+        let snapshot = snapshot_from_vote_account(&account, false, CreditsModel::new(1));
+
+        assert_eq!(snapshot.expected_max_rooted(), 100);
+        assert_eq!(snapshot.missed_this_epoch(), 10);
+        assert_eq!(snapshot.efficiency(), 0.9);
+    }
+
+    #[test]
+    fn test_snapshot_without_entry_for_current_epoch() {
+        // Root is already in epoch 813 but the account has not earned credits there yet
+        let root = 813 * SLOTS_PER_EPOCH + 5;
+        let account = vote_account("A", vec![(812, 6500, 5000)], root);
+        let snapshot = snapshot_from_vote_account(&account, true, CreditsModel::default());
+
+        assert_eq!(snapshot.epoch_info.epoch, 813);
+        assert_eq!(snapshot.credits_this_epoch, 0);
+        assert_eq!(snapshot.last_epoch_credits, Some(1500));
+        assert!(snapshot.delinquent);
+    }
+
+    #[test]
+    fn test_window_stats_interpolated_across_poll_gap() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut state = PollState::new().with_clock(clock.clone());
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let poll = |credits: u64, root: u64| {
+            snapshot_from_vote_account(
+                &vote_account("A", vec![(10, credits, 0)], epoch_start + root),
+                false,
+                CreditsModel::default(),
+            )
+        };
+
+        state.apply(&poll(1600, 99));
+        // No successful poll for 20 minutes, in which 3000 slots rooted without credits
+        clock.advance_secs(1200);
+        state.apply(&poll(1600, 3099));
+        clock.advance_secs(60);
+        state.apply(&poll(1600 + 2400, 3249));
+
+        // The 5m window starts 960s into the gap: only the last 240s of it count
+        let stats = state.window_stats(300);
+        assert_eq!(stats.credits, 2400);
+        assert_eq!(stats.missed, 48_000 - 48_000 * 960 / 1200);
+        assert_eq!(state.window_stats(3600).missed, 48_000);
+    }
+
+    #[test]
+    fn test_poll_state_deltas() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut state = PollState::new().with_clock(clock.clone());
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+
+        // First poll: baseline only
+        let s1 = snapshot_from_vote_account(
+            &vote_account("A", vec![(10, 1600, 0)], epoch_start + 99),
+            false,
+            CreditsModel::default(),
+        );
+        assert_eq!(state.apply(&s1), PollUpdate::default());
+
+        // 100 more slots rooted, 1500 credits earned: 100 missed
+        let s2 = snapshot_from_vote_account(
+            &vote_account("A", vec![(10, 3100, 0)], epoch_start + 199),
+            false,
+            CreditsModel::default(),
+        );
+        let update = state.apply(&s2);
+        assert_eq!(update.credits_delta, 1500);
+        assert_eq!(update.missed_delta, 100);
+
+        let stats = state.window_stats(300);
+        assert_eq!(stats.credits, 1500);
+        assert_eq!(stats.missed, 100);
+        assert_eq!(stats.efficiency(), Some(0.9375));
+
+        clock.advance_secs(301);
+        assert_eq!(state.window_stats(300), WindowStats::default());
+        assert_eq!(state.window_stats(3600).credits, 1500);
+    }
+
+    #[test]
+    fn test_poll_state_epoch_rollover() {
+        let mut state = PollState::new();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+
+        let s1 = snapshot_from_vote_account(
+            &vote_account("A", vec![(10, 1600, 0)], epoch_start + 99),
+            false,
+            CreditsModel::default(),
+        );
+        state.apply(&s1);
+
+        // Next epoch: epoch 10 finished with 6_900_000 credits
+        let s2 = snapshot_from_vote_account(
+            &vote_account(
+                "A",
+                vec![(10, 6_900_000, 0), (11, 6_900_160, 6_900_000)],
+                epoch_start + SLOTS_PER_EPOCH + 9,
+            ),
+            false,
+            CreditsModel::default(),
+        );
+        let update = state.apply(&s2);
+
+        let missed_last = SLOTS_PER_EPOCH * MAX_CREDITS_PER_SLOT - 6_900_000;
+        assert_eq!(update.missed_last_epoch, Some(missed_last));
+        assert_eq!(state.missed_last_epoch(), Some(missed_last));
+        // Voting since the epoch started (1600 credits by slot 99): nothing to adjust
+        assert_eq!(update.missed_last_epoch_adjusted, Some(missed_last));
+        assert_eq!(update.credits_delta, 6_900_000 - 1600 + 160);
+        // Remaining misses of epoch 10 (minus those already counted) + none in epoch 11
+        assert_eq!(update.missed_delta, missed_last);
+
+        // Epoch 10 was tracked at a single poll, epoch 11 starts at the new root
+        let summary = update.epoch_summary.expect("summary at rollover");
+        assert_eq!(summary.first_tracked_slot, Some(epoch_start + 99));
+        assert_eq!(summary.last_tracked_slot, Some(epoch_start + 99));
+        let root = epoch_start + SLOTS_PER_EPOCH + 9;
+        assert_eq!(state.epoch_first_tracked_slot(), Some(root));
+        assert_eq!(state.epoch_last_tracked_slot(), Some(root));
+    }
+
+    #[test]
+    fn test_epoch_summary_records_cluster_average() {
+        let averages = Arc::new(ClusterAverages::new());
+        let mut state = PollState::new().with_cluster_averages(Some(averages.clone()));
+        let poll = |epoch_credits: Vec<(u64, u64, u64)>, root: u64| {
+            snapshot_from_vote_account(
+                &vote_account("A", epoch_credits, root),
+                false,
+                CreditsModel::default(),
+            )
+        };
+        let average = ClusterAverage {
+            epoch: 10,
+            stake_weighted_mean: 6_800_000.0,
+            ratio: Some(1.01),
+            excludes_self: false,
+        };
+        averages.record(average);
+        state.apply(&poll(vec![(10, 1600, 0)], 10 * SLOTS_PER_EPOCH + 99));
+        let update = state.apply(&poll(
+            vec![(10, 6_868_000, 0), (11, 6_868_160, 6_868_000)],
+            11 * SLOTS_PER_EPOCH + 9,
+        ));
+        assert_eq!(update.epoch_summary.unwrap().cluster, Some(average));
+    }
+
+    #[test]
+    fn test_epoch_summary_compared_with_previous_epoch() {
+        let mut state = PollState::new();
+        let poll = |epoch_credits: Vec<(u64, u64, u64)>, root: u64| {
+            snapshot_from_vote_account(
+                &vote_account("A", epoch_credits, root),
+                false,
+                CreditsModel::default(),
+            )
+        };
+        let expected = SLOTS_PER_EPOCH * MAX_CREDITS_PER_SLOT;
+        state.apply(&poll(vec![(10, 1600, 0)], 10 * SLOTS_PER_EPOCH + 99));
+
+        // First rollover seen: nothing to compare against
+        let update = state.apply(&poll(
+            vec![(10, 6_900_000, 0), (11, 6_900_160, 6_900_000)],
+            11 * SLOTS_PER_EPOCH + 9,
+        ));
+        assert_eq!(update.epoch_summary.unwrap().comparison, None);
+
+        // Epoch 11 earned 12_000 more credits than epoch 10
+        let update = state.apply(&poll(
+            vec![
+                (10, 6_900_000, 0),
+                (11, 13_812_000, 6_900_000),
+                (12, 13_812_160, 13_812_000),
+            ],
+            12 * SLOTS_PER_EPOCH + 9,
+        ));
+        let comparison = update.epoch_summary.unwrap().comparison.unwrap();
+        assert_eq!(comparison.previous_epoch, 10);
+        assert_eq!(comparison.credits_delta, 12_000);
+        assert_eq!(comparison.missed_delta, -12_000);
+        let efficiency_delta = 12_000.0 / expected as f64;
+        assert!((comparison.efficiency_delta.unwrap() - efficiency_delta).abs() < 1e-12);
+        assert_eq!(comparison.avg_latency_delta, None);
+
+        // The previous epoch survives a restart through the state file
+        let mut restarted = PollState::new().with_restored(state.persisted());
+        restarted.apply(&poll(
+            vec![(11, 13_812_000, 6_900_000), (12, 13_812_320, 13_812_000)],
+            12 * SLOTS_PER_EPOCH + 19,
+        ));
+        let update = restarted.apply(&poll(
+            vec![(12, 20_724_000, 13_812_000), (13, 20_724_160, 20_724_000)],
+            13 * SLOTS_PER_EPOCH + 9,
+        ));
+        let comparison = update.epoch_summary.unwrap().comparison.unwrap();
+        assert_eq!(comparison.previous_epoch, 11);
+        assert_eq!(comparison.credits_delta, 0);
+    }
+
+    #[test]
+    fn test_missed_last_epoch_adjusted_for_new_account() {
+        let model = CreditsModel::default();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let half = SLOTS_PER_EPOCH / 2;
+        // First seen halfway through epoch 10, 100 slots' worth of credits in
+        let first = vote_account("A", vec![(10, 1600, 0)], epoch_start + half - 1);
+        // It then missed 10_000 credits by the end of the epoch
+        let active_slots = half + 100;
+        let earned = model.expected_credits(active_slots) - 10_000;
+        let next = vote_account(
+            "A",
+            vec![(10, earned, 0), (11, earned + 160, earned)],
+            epoch_start + SLOTS_PER_EPOCH + 9,
+        );
+        let rollover = |first: &RpcVoteAccount, next: &RpcVoteAccount| {
+            let mut state = PollState::new();
+            state.apply(&snapshot_from_vote_account(first, false, model));
+            state.apply(&snapshot_from_vote_account(next, false, model))
+        };
+
+        let update = rollover(&first, &next);
+        let naive = model.expected_credits(SLOTS_PER_EPOCH) - earned;
+        assert_eq!(update.missed_last_epoch, Some(naive));
+        assert_eq!(update.missed_last_epoch_adjusted, Some(10_000));
+        // The expectation is bounded to about half the epoch
+        let adjusted_expected = earned + 10_000;
+        let naive_expected = earned + naive;
+        assert!((adjusted_expected as f64 / naive_expected as f64 - 0.5).abs() < 0.001);
+
+        // An account with credits in earlier epochs was active all epoch
+        let mut old_first = first.clone();
+        old_first.epoch_credits.insert(0, (9, 0, 0));
+        let mut old_next = next.clone();
+        old_next.epoch_credits.insert(0, (9, 0, 0));
+        let update = rollover(&old_first, &old_next);
+        assert_eq!(update.missed_last_epoch_adjusted, Some(naive));
+    }
+
+    /// Poll epoch 10 with `stake_10`, then roll over into epoch 11 with `stake_11`
+    fn stake_rollover(stake_10: u64, stake_11: u64) -> PollUpdate {
+        let mut state = PollState::new().with_stake_decrease_pct(10.0);
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+
+        let mut a1 = vote_account("A", vec![(10, 1600, 0)], epoch_start + 99);
+        a1.activated_stake = stake_10;
+        state.apply(&snapshot_from_vote_account(
+            &a1,
+            false,
+            CreditsModel::default(),
+        ));
+
+        let mut a2 = vote_account(
+            "A",
+            vec![(10, 6_900_000, 0), (11, 6_900_160, 6_900_000)],
+            epoch_start + SLOTS_PER_EPOCH + 9,
+        );
+        a2.activated_stake = stake_11;
+        state.apply(&snapshot_from_vote_account(
+            &a2,
+            false,
+            CreditsModel::default(),
+        ))
+    }
+
+    #[test]
+    fn test_stake_increase_at_rollover() {
+        let update = stake_rollover(1_000_000, 1_500_000);
+        assert_eq!(update.stake_delta_lamports, Some(500_000));
+        assert!(!update.stake_decreased);
+
+        let summary = update.epoch_summary.expect("summary at rollover");
+        assert_eq!(summary.epoch, 10);
+        assert_eq!(summary.credits, 6_900_000);
+        assert_eq!(summary.stake_delta_lamports, Some(500_000));
+    }
+
+    #[test]
+    fn test_stake_decrease_at_rollover() {
+        // 5% drop is below the threshold
+        let update = stake_rollover(1_000_000, 950_000);
+        assert_eq!(update.stake_delta_lamports, Some(-50_000));
+        assert!(!update.stake_decreased);
+
+        // 25% drop is counted
+        let update = stake_rollover(1_000_000, 750_000);
+        assert_eq!(update.stake_delta_lamports, Some(-250_000));
+        assert!(update.stake_decreased);
+    }
+
+    #[test]
+    fn test_stake_first_epoch_has_no_baseline() {
+        let mut state = PollState::new();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+
+        let update = state.apply(&snapshot_from_vote_account(
+            &vote_account("A", vec![(10, 1600, 0)], epoch_start + 99),
+            false,
+            CreditsModel::default(),
+        ));
+        assert_eq!(update.stake_delta_lamports, None);
+        assert!(!update.stake_decreased);
+        assert!(update.epoch_summary.is_none());
+    }
+
+    #[test]
+    fn test_next_delay_backoff() {
+        let interval = Duration::from_secs(60);
+        let mut state = PollState::new();
+        assert_eq!(state.next_delay(interval), interval);
+
+        state.consecutive_errors = 1;
+        assert_eq!(state.next_delay(interval), Duration::from_secs(5));
+        state.consecutive_errors = 3;
+        assert_eq!(state.next_delay(interval), Duration::from_secs(20));
+        state.consecutive_errors = 30;
+        assert_eq!(state.next_delay(interval), ERROR_BACKOFF_MAX);
+    }
+
+    /// Poll intervals of an adaptive controller on a chain rooting 2.5 slots
+    /// per second, starting from `start`
+    fn adaptive_intervals(start: Duration, polls: usize) -> Vec<Duration> {
+        let mut state = PollState::new().with_adaptive_interval(Some(64));
+        state.adaptive_interval.as_mut().unwrap().current = start;
+        let mut root = 1_000_000;
+        state.observe_root_slot(root);
+        (0..polls)
+            .map(|_| {
+                root += (state.next_delay(Duration::ZERO).as_secs_f64() * 2.5) as u64;
+                state.observe_root_slot(root);
+                state.next_delay(Duration::ZERO)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_adaptive_interval_converges_from_both_sides() {
+        // 64 slots at 2.5 slots/s
+        let target = Duration::from_secs_f64(25.6);
+        for start in [ADAPTIVE_INTERVAL_MIN, ADAPTIVE_INTERVAL_MAX] {
+            let intervals = adaptive_intervals(start, 10);
+            let last = *intervals.last().unwrap();
+            assert!(
+                last.abs_diff(target) < Duration::from_millis(500),
+                "{:?}",
+                intervals
+            );
+            // Monotonic approach, without overshooting
+            if start < target {
+                assert!(
+                    intervals
+                        .windows(2)
+                        .all(|w| w[0] <= w[1] && w[1] <= target + Duration::from_millis(500))
+                );
+            } else {
+                assert!(
+                    intervals
+                        .windows(2)
+                        .all(|w| w[0] >= w[1] && w[1] + Duration::from_millis(500) >= target)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_adaptive_interval_bounds() {
+        let mut state = PollState::new().with_adaptive_interval(Some(64));
+        assert_eq!(
+            state.poll_interval(Duration::from_secs(60)),
+            ADAPTIVE_INTERVAL_INITIAL
+        );
+
+        // A stalled root backs off up to the maximum, a lagging node is ignored
+        for _ in 0..10 {
+            state.observe_root_slot(1000);
+        }
+        assert_eq!(state.next_delay(Duration::ZERO), ADAPTIVE_INTERVAL_MAX);
+        state.observe_root_slot(900);
+        assert_eq!(state.next_delay(Duration::ZERO), ADAPTIVE_INTERVAL_MAX);
+
+        // A burst shrinks it down to the minimum
+        for step in 1..=10 {
+            state.observe_root_slot(1000 + step * 100_000);
+        }
+        assert_eq!(state.next_delay(Duration::ZERO), ADAPTIVE_INTERVAL_MIN);
+    }
+
+    #[test]
+    fn test_error_backoff_overrides_adaptive_interval() {
+        let mut state = PollState::new().with_adaptive_interval(Some(64));
+        state.adaptive_interval.as_mut().unwrap().current = ADAPTIVE_INTERVAL_MAX;
+        state.consecutive_errors = 1;
+        assert_eq!(state.next_delay(Duration::ZERO), ERROR_BACKOFF_BASE);
+
+        state.adaptive_interval.as_mut().unwrap().current = ADAPTIVE_INTERVAL_MIN;
+        state.consecutive_errors = 3;
+        assert_eq!(state.next_delay(Duration::ZERO), Duration::from_secs(20));
+
+        state.consecutive_errors = 0;
+        assert_eq!(state.next_delay(Duration::ZERO), ADAPTIVE_INTERVAL_MIN);
+    }
+
+    fn account_at(root_slot: u64) -> Vec<RpcVoteAccount> {
+        let epoch = root_slot / SLOTS_PER_EPOCH;
+        vec![vote_account("A", vec![(epoch, 1600, 0)], root_slot)]
+    }
+
+    #[tokio::test]
+    async fn test_boundary_watch_across_rollover() {
+        let interval = Duration::from_secs(300);
+        let rpc = MutableTestRpc::new(vec![]);
+        let metrics = Metrics::new().unwrap();
+        let clock = Arc::new(ManualClock::new(0));
+        let mut state = PollState::new().with_clock(clock.clone());
+        let boundary = 11 * SLOTS_PER_EPOCH;
+        let poll = async |state: &mut PollState, root_slot: u64| {
+            // Slots of 400ms
+            clock.set(root_slot * 400);
+            rpc.set(account_at(root_slot));
+            let snapshot = poll_once(&rpc, "A", state, CreditsModel::default(), &metrics)
+                .await
+                .unwrap();
+            state.poll_now = false;
+            state.observe_epoch_position(&snapshot.epoch_info, state.poll_interval(interval));
+            state.next_delay(interval)
+        };
+
+        // 300s span 750 slots: more than that left keeps the normal cadence
+        assert_eq!(poll(&mut state, boundary - 2000).await, interval);
+        assert_eq!(state.boundary_phase(), BoundaryPhase::Normal);
+        assert_eq!(
+            poll(&mut state, boundary - 700).await,
+            BOUNDARY_WATCH_INTERVAL
+        );
+        assert_eq!(
+            state.boundary_phase(),
+            BoundaryPhase::BoundaryWatch { epoch: 10 }
+        );
+        assert_eq!(
+            poll(&mut state, boundary - 1).await,
+            BOUNDARY_WATCH_INTERVAL
+        );
+
+        // The first poll of epoch 11 asks for another one right away
+        assert_eq!(poll(&mut state, boundary + 20).await, Duration::ZERO);
+        assert_eq!(state.boundary_phase(), BoundaryPhase::Normal);
+        assert_eq!(metrics.epoch.get(), 11);
+        assert_eq!(poll(&mut state, boundary + 25).await, interval);
+    }
+
+    #[test]
+    fn test_boundary_watch_follows_observed_slot_time() {
+        let interval = Duration::from_secs(300);
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut state = PollState::new().with_clock(clock.clone());
+        let boundary = 11 * SLOTS_PER_EPOCH;
+        let poll = |state: &mut PollState, root_slot: u64, after_secs: u64| {
+            clock.advance_secs(after_secs);
+            let snapshot =
+                snapshot_from_vote_account(&account_at(root_slot)[0], false, Default::default());
+            state.apply(&snapshot);
+            state.observe_epoch_position(&snapshot.epoch_info, interval);
+            state.boundary_phase()
+        };
+        assert_eq!(poll(&mut state, boundary - 2000, 0), BoundaryPhase::Normal);
+        assert_eq!(state.slot_duration(), SLOT_DURATION);
+
+        // The cluster halts, then roots 100 slots in the first 40s after it
+        // restarts 6 hours later: 700 slots would take 280s at 400ms, but
+        // are measured as most of the halt
+        assert_eq!(
+            poll(&mut state, boundary - 2000, 300),
+            BoundaryPhase::Normal
+        );
+        assert_eq!(state.slot_duration(), Duration::from_secs(300));
+        assert_eq!(
+            poll(&mut state, boundary - 700, 6 * 3600),
+            BoundaryPhase::Normal
+        );
+        assert!(state.slot_duration() > Duration::from_secs(16));
+
+        // Back to 400ms slots, the 599 slots left are within one interval
+        assert_eq!(
+            poll(&mut state, boundary - 600, 40),
+            BoundaryPhase::BoundaryWatch { epoch: 10 }
+        );
+        assert_eq!(state.slot_duration(), SLOT_DURATION);
+    }
+
+    #[test]
+    fn test_boundary_watch_caps_adaptive_interval() {
+        let mut state = PollState::new().with_adaptive_interval(Some(64));
+        let info = EpochInfo::from_slot(11 * SLOTS_PER_EPOCH - 10);
+        state.observe_epoch_position(&info, state.poll_interval(Duration::ZERO));
+        assert_eq!(state.poll_interval(Duration::ZERO), BOUNDARY_WATCH_INTERVAL);
+        // A lagging node's older epoch doesn't end the watch
+        state.observe_epoch_position(
+            &EpochInfo::from_slot(10 * SLOTS_PER_EPOCH - 5),
+            Duration::ZERO,
+        );
+        assert_eq!(
+            state.boundary_phase(),
+            BoundaryPhase::BoundaryWatch { epoch: 10 }
+        );
+    }
+
+    #[test]
+    fn test_rolling_median() {
+        let mut median = RollingMedian::new(4);
+        assert_eq!(median.median(), None);
+        median.push(3.0);
+        assert_eq!(median.median(), Some(3.0));
+        median.push(1.0);
+        median.push(10.0);
+        assert_eq!(median.median(), Some(3.0));
+        median.push(2.0);
+        assert_eq!(median.median(), Some(2.5));
+        // The oldest sample (3.0) drops out
+        median.push(20.0);
+        assert_eq!(median.len(), 4);
+        assert_eq!(median.median(), Some(6.0));
+    }
+
+    #[test]
+    fn test_record_poll_duration_warns_once() {
+        let interval = Duration::from_secs(5);
+        let mut state = PollState::new();
+        // Not enough samples yet, however slow
+        for _ in 0..POLL_DURATION_MIN_SAMPLES - 1 {
+            assert_eq!(
+                state.record_poll_duration(Duration::from_secs(4), interval),
+                None
+            );
+        }
+        assert_eq!(
+            state.record_poll_duration(Duration::from_secs(1), interval),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            state.record_poll_duration(Duration::from_secs(4), interval),
+            None
+        );
+
+        // Fast polls leave a generous interval alone
+        let mut state = PollState::new();
+        for _ in 0..10 {
+            assert_eq!(
+                state.record_poll_duration(Duration::from_millis(200), interval),
+                None
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_poll_counts_polls() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, 1600, 0)],
+            10 * SLOTS_PER_EPOCH + 99,
+        )]);
+        let interval = Duration::from_secs(60);
+        let run = run_poll(
+            &rpc,
+            "A",
+            PollState::new(),
+            CreditsModel::default(),
+            interval,
+            &metrics,
+        );
+        // Polls at 0s, 60s and 120s
+        let _ = tokio::time::timeout(Duration::from_secs(150), run).await;
+
+        assert_eq!(metrics.poll_interval.get(), 60.0);
+        assert_eq!(metrics.polls.with_label_values(&["success"]).get(), 3);
+        assert_eq!(metrics.polls.with_label_values(&["error"]).get(), 0);
+        assert_eq!(metrics.poll_duration.get_sample_count(), 3);
+
+        rpc.set(vec![]);
+        let run = run_poll(
+            &rpc,
+            "A",
+            PollState::new(),
+            CreditsModel::default(),
+            interval,
+            &metrics,
+        );
+        let _ = tokio::time::timeout(Duration::from_secs(1), run).await;
+        assert_eq!(metrics.polls.with_label_values(&["error"]).get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_updates_metrics() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let rpc = MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, 1600, 0)],
+            epoch_start + 99,
+        )]);
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut state = PollState::new().with_clock(clock.clone());
+
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        rpc.set(vec![vote_account(
+            "A",
+            vec![(10, 3100, 0)],
+            epoch_start + 199,
+        )]);
+        clock.advance_secs(60);
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+
+        assert_eq!(metrics.epoch.get(), 10);
+        assert_eq!(metrics.slot_index.get(), 199);
+        assert_eq!(metrics.total_epoch_credits.get(), 3100);
+        assert_eq!(metrics.missed_current_epoch.get(), 100);
+        assert_eq!(metrics.missed_total.get(), 100);
+        assert_eq!(metrics.missed_5m.get(), 100);
+        assert_eq!(metrics.rpc_up.get(), 1);
+        assert_eq!(metrics.update_generation.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_windows_and_projections_recover_after_cluster_halt() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let rpc = MutableTestRpc::new(vec![]);
+        let mut state = PollState::new().with_clock(clock.clone());
+        // 150 slots a minute, 15 credits each
+        let mut poll = async |root_index: u64| {
+            rpc.set(vec![vote_account(
+                "A",
+                vec![(10, root_index * 15, 0)],
+                epoch_start + root_index,
+            )]);
+            poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+                .await
+                .unwrap();
+            clock.advance_secs(60);
+        };
+        let rendered = |family: &str| metrics.render().1.contains(&format!("{} ", family));
+        let ceiling = CreditsModel::default().expected_credits(SLOTS_PER_EPOCH) as i64;
+
+        for minute in 0..20 {
+            poll(100_000 + minute * 150).await;
+        }
+        assert_eq!(metrics.vote_credits_efficiency_5m.get(), 15.0 / 16.0);
+        assert!(rendered("solana_vote_credits_efficiency_1h"));
+        let projected = metrics.projected_credits_1h.get();
+        assert_eq!(projected, 15 * (SLOTS_PER_EPOCH as i64 - 1));
+
+        // Halted for 6 hours; the first poll after the restart has nothing
+        // in either window to go by
+        clock.advance_secs(6 * 3600);
+        poll(100_000 + 20 * 150).await;
+        for family in [
+            "solana_vote_credits_efficiency_5m",
+            "solana_missed_vote_credits_5m",
+            "solana_vote_credits_projected_5m",
+            "solana_vote_latency_slots_1h",
+            "solana_vote_credits_projected_1h",
+        ] {
+            assert!(!rendered(family), "{}", family);
+        }
+        assert_eq!(metrics.projected_credits_1h.get(), projected);
+        let coverage = |window: &str| {
+            metrics
+                .window_history_coverage
+                .with_label_values(&[window])
+                .get()
+        };
+        assert_eq!((coverage("5m"), coverage("1h")), (0.0, 0.0));
+
+        // A minute of data is a fifth of the 5m window
+        poll(100_000 + 21 * 150).await;
+        assert!(rendered("solana_vote_credits_efficiency_5m"));
+        assert_eq!(metrics.vote_credits_efficiency_5m.get(), 15.0 / 16.0);
+        assert_eq!(metrics.missed_5m.get(), 150);
+        assert!(metrics.projected_credits_5m.get() <= ceiling);
+        assert!(!rendered("solana_vote_credits_efficiency_1h"));
+
+        // ...and 12 minutes a fifth of the 1h window
+        for minute in 22..33 {
+            poll(100_000 + minute * 150).await;
+        }
+        assert!(rendered("solana_vote_credits_efficiency_1h"));
+        assert_eq!(metrics.vote_credits_efficiency_1h.get(), 15.0 / 16.0);
+        assert_eq!(metrics.missed_1h.get(), 12 * 150);
+        assert_eq!(
+            metrics.projected_credits_1h.get(),
+            15 * (SLOTS_PER_EPOCH as i64 - 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_sets_aside_maintenance_misses() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let maintenance = Arc::new(Maintenance::new().with_clock(clock.clone()));
+        let rpc = MutableTestRpc::new(vec![]);
+        let mut state = PollState::new()
+            .with_clock(clock.clone())
+            .with_maintenance(Some(maintenance.clone()));
+        let mut poll = async |root_index: u64, credits: u64| {
+            rpc.set(vec![vote_account(
+                "A",
+                vec![(10, credits, 0)],
+                epoch_start + root_index,
+            )]);
+            poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+                .await
+                .unwrap();
+        };
+
+        poll(99, 1600).await;
+        maintenance.start(Duration::from_secs(60), "upgrade");
+        poll(199, 3100).await;
+        assert_eq!(metrics.missed_total.get(), 0);
+        assert_eq!(metrics.missed_maintenance_total.get(), 100);
+        assert_eq!(metrics.missed_current_epoch.get(), 0);
+        assert_eq!(metrics.missed_current_epoch_maintenance.get(), 100);
+        assert_eq!(metrics.missed_5m.get(), 0);
+
+        // After the window expires misses count towards the totals again
+        clock.advance_secs(60);
+        poll(299, 4500).await;
+        assert_eq!(metrics.missed_total.get(), 200);
+        assert_eq!(metrics.missed_maintenance_total.get(), 100);
+        assert_eq!(metrics.missed_current_epoch.get(), 200);
+        assert_eq!(metrics.missed_5m.get(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_exports_missed_accounting_skew() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let rpc = MutableTestRpc::new(vec![]);
+        let mut state = PollState::new();
+        let mut poll = async |root_index: u64, credits: u64| {
+            rpc.set(vec![vote_account(
+                "A",
+                vec![(10, credits, 0)],
+                epoch_start + root_index,
+            )]);
+            poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+                .await
+                .unwrap();
+        };
+
+        // The first poll of the epoch is the baseline of both counts
+        poll(99, 1500).await;
+        assert_eq!(metrics.missed_accounting_skew.get(), 0);
+        poll(199, 2900).await;
+        assert_eq!(metrics.missed_current_epoch.get(), 300);
+        assert_eq!(metrics.missed_accounting_skew.get(), 0);
+        assert_eq!(metrics.missed_accounting_skew_ratio.get(), 0.0);
+
+        // Credits the previous snapshot lacked: the snapshot shrinks to 100,
+        // the sum of the per-poll misses can't
+        poll(299, 4700).await;
+        assert_eq!(metrics.missed_current_epoch.get(), 100);
+        assert_eq!(metrics.missed_accounting_skew.get(), 200);
+        assert!((metrics.missed_accounting_skew_ratio.get() - 200.0 / 300.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_observes_root_advance() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let rpc = MutableTestRpc::new(vec![]);
+        let mut state = PollState::new().with_clock(clock.clone());
+        let mut poll = async |root_index: u64| {
+            rpc.set(vec![vote_account(
+                "A",
+                vec![(10, root_index * 16, 0)],
+                epoch_start + root_index,
+            )]);
+            poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+                .await
+                .unwrap();
+            clock.advance_secs(10);
+        };
+
+        // The first poll has nothing to compare against
+        poll(100).await;
+        assert_eq!(metrics.rooted_slots_per_poll.get_sample_count(), 0);
+
+        poll(125).await;
+        assert_eq!(metrics.root_slot_advance_rate.get(), 2.5);
+        poll(225).await;
+        assert_eq!(metrics.root_slot_advance_rate.get(), 10.0);
+        assert_eq!(metrics.polls_no_root_progress.get(), 0);
+
+        // A stalled root counts as a poll without progress
+        poll(225).await;
+        poll(225).await;
+        assert_eq!(metrics.polls_no_root_progress.get(), 2);
+        assert_eq!(metrics.root_slot_advance_rate.get(), 0.0);
+        assert_eq!(metrics.rooted_slots_per_poll.get_sample_count(), 4);
+        assert_eq!(metrics.rooted_slots_per_poll.get_sample_sum(), 125.0);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_skips_root_slot_regressions() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let rpc = MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, 1600, 0)],
+            epoch_start + 99,
+        )]);
+        let mut state = PollState::new();
+        let mut poll = async |root_index: u64, credits: u64| {
+            rpc.set(vec![vote_account(
+                "A",
+                vec![(10, credits, 0)],
+                epoch_start + root_index,
+            )]);
+            poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+                .await
+                .unwrap();
+        };
+
+        poll(99, 1600).await;
+        poll(199, 3100).await;
+        assert_eq!(metrics.epoch_expected_max.get(), 3200);
+        assert_eq!(metrics.missed_total.get(), 100);
+
+        // A node 150 slots behind answers: nothing moves backwards
+        poll(49, 800).await;
+        assert_eq!(metrics.rpc_slot_regressions.get(), 1);
+        assert_eq!(metrics.epoch_expected_max.get(), 3200);
+        assert_eq!(metrics.slot_index.get(), 199);
+        assert_eq!(metrics.total_epoch_credits.get(), 3100);
+
+        // Back on a current node, the missed credits aren't counted twice
+        poll(299, 4700).await;
+        assert_eq!(metrics.rpc_slot_regressions.get(), 1);
+        assert_eq!(metrics.epoch_expected_max.get(), 4800);
+        assert_eq!(metrics.missed_total.get(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_is_not_fresh_while_rpc_behind() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let rpc = MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, 1600, 0)],
+            epoch_start + 99,
+        )]);
+        let health = Arc::new(RpcHealthWatch::new());
+        let mut state = PollState::new().with_rpc_health(Some(health.clone()));
+
+        rpc.set_health(Some(RpcHealth::Behind(Some(150))));
+        check_health(&rpc, &health, &metrics).await;
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        assert_eq!(metrics.total_epoch_credits.get(), 1600);
+        assert_eq!(metrics.rpc_up.get(), 1);
+        assert_eq!(metrics.rpc_last_success.get(), 0);
+
+        rpc.set_health(Some(RpcHealth::Ok));
+        check_health(&rpc, &health, &metrics).await;
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        assert!(metrics.rpc_last_success.get() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_records_epoch_rollover() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, 1600, 0)],
+            10 * SLOTS_PER_EPOCH + 99,
+        )]);
+        let discontinuities = Arc::new(Discontinuities::new());
+        let mut state = PollState::new().with_discontinuities(Some(discontinuities.clone()));
+
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        rpc.accounts.lock().unwrap().current = vec![vote_account(
+            "A",
+            vec![(10, 1600, 0), (11, 1632, 1600)],
+            11 * SLOTS_PER_EPOCH + 1,
+        )];
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+
+        let recent = discontinuities.recent();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].epoch, Some(11));
+        assert_eq!(recent[0].slot, Some(11 * SLOTS_PER_EPOCH + 1));
+        assert_eq!(
+            recent[0].event,
+            DiscontinuityEvent::EpochRollover {
+                from_epoch: 10,
+                to_epoch: Some(11),
+                credits: 1600,
+                // The unpolled rest of epoch 10 counts as missed
+                missed: SLOTS_PER_EPOCH * 16 - 1600,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_emits_delinquency_changes() {
+        let metrics = Metrics::new().unwrap();
+        let account = vote_account("A", vec![(10, 1600, 0)], 10 * SLOTS_PER_EPOCH + 99);
+        let rpc = MutableTestRpc::new(vec![account.clone()]);
+        let events = crate::events::event_channel();
+        let mut rx = events.subscribe();
+        let mut state = PollState::new().with_events(Some(events));
+
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        {
+            let mut accounts = rpc.accounts.lock().unwrap();
+            accounts.current.clear();
+            accounts.delinquent = vec![account];
+        }
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+
+        // Only the transition is an event, not the first poll or a repeat
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            TrackerEvent::DelinquencyChanged { delinquent: true }
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_tip_anchor() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let rpc = MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, 3100, 0)],
+            epoch_start + 199,
+        )]);
+        let mut state = PollState::new().with_expected_anchor(ExpectedAnchor::Tip, 2);
+
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+
+        // Tip is 32 slots past the root, anchored 2 behind it: 30 pending slots
+        let by_anchor = |anchor: &str| {
+            (
+                metrics
+                    .epoch_expected_max_by_anchor
+                    .with_label_values(&[anchor])
+                    .get(),
+                metrics
+                    .missed_current_epoch_by_anchor
+                    .with_label_values(&[anchor])
+                    .get(),
+            )
+        };
+        assert_eq!(by_anchor("root"), (200 * 16, 100));
+        assert_eq!(by_anchor("tip"), (230 * 16, 230 * 16 - 3100));
+        assert_eq!(metrics.pending_slots.get(), 30);
+
+        // The tip drives the primary metrics
+        assert_eq!(metrics.epoch_expected_max.get(), 230 * 16);
+        assert_eq!(metrics.missed_current_epoch.get(), 230 * 16 - 3100);
+        assert_eq!(
+            metrics.vote_credits_efficiency_epoch.get(),
+            3100.0 / (230.0 * 16.0)
+        );
+    }
+
+    #[test]
+    fn test_credit_history() {
+        let history = CreditHistory::from_epochs([8, 9, 10]);
+        assert_eq!(history.len, 3);
+        assert_eq!(history.oldest_epoch, Some(8));
+        assert_eq!(history.age_epochs(10), 3);
+        assert_eq!(CreditHistory::from_epochs([]).age_epochs(10), 0);
+        assert!(!history.unsorted);
+        assert!(CreditHistory::from_epochs([8, 10, 9]).unsorted);
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_flags_young_account() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let young: Vec<_> = (8..=10)
+            .map(|e| (e, (e - 7) * 1000, (e - 8) * 1000))
+            .collect();
+        let rpc = MutableTestRpc::new(vec![vote_account("A", young, epoch_start + 99)]);
+        let mut state = PollState::new().with_min_credit_history_epochs(5);
+
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        assert_eq!(metrics.epoch_credits_history_len.get(), 3);
+        assert_eq!(metrics.epoch_credits_oldest_epoch.get(), 8);
+        assert_eq!(
+            metrics
+                .vote_account_age_epochs
+                .with_label_values(&["5"])
+                .get(),
+            3
+        );
+
+        // Once the history reaches the minimum the age metric goes away
+        let full: Vec<_> = (6..=10)
+            .map(|e| (e, (e - 5) * 1000, (e - 6) * 1000))
+            .collect();
+        rpc.set(vec![vote_account("A", full, epoch_start + 199)]);
+        poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+            .await
+            .unwrap();
+        assert_eq!(metrics.epoch_credits_history_len.get(), 5);
+        assert_eq!(metrics.epoch_credits_oldest_epoch.get(), 6);
+        assert!(
+            !metrics
+                .render()
+                .1
+                .contains("solana_vote_account_age_epochs{")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_once_missing_account() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = MutableTestRpc::new(vec![]);
+        let mut state = PollState::new();
+        assert!(
+            poll_once(&rpc, "A", &mut state, CreditsModel::default(), &metrics)
+                .await
+                .is_err()
+        );
+    }
+}
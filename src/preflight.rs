@@ -0,0 +1,384 @@
+use crate::config::Mode;
+use crate::rpc::{HttpRpcClient, RpcClient};
+use crate::ws::{AccountData, AccountValue, RpcResult, http_to_ws_url};
+#[cfg(feature = "ws")]
+use crate::{rpc::COMMITMENT, ws::WsMessage};
+
+#[cfg(feature = "ws")]
+use anyhow::{Context, anyhow};
+use anyhow::{Result, bail};
+#[cfg(feature = "ws")]
+use futures_util::{SinkExt, StreamExt};
+use std::fmt::Write;
+#[cfg(feature = "ws")]
+use std::time::Duration;
+#[cfg(feature = "ws")]
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+/// How long to wait for the accountSubscribe confirmation
+#[cfg(feature = "ws")]
+const WS_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a single capability probe
+#[derive(Debug)]
+pub struct ProbeResult {
+    pub capability: &'static str,
+    /// Short description on success, the error on failure
+    pub outcome: Result<String>,
+}
+
+impl ProbeResult {
+    fn new(capability: &'static str, outcome: Result<String>) -> Self {
+        Self {
+            capability,
+            outcome,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// `getVersion`: the node answers JSON-RPC at all
+pub async fn probe_version(rpc: &HttpRpcClient) -> Result<String> {
+    let version = rpc.get_version().await?;
+    Ok(format!("solana-core {}", version.solana_core))
+}
+
+/// `getHealth`: the node reports itself healthy
+pub async fn probe_health(rpc: &HttpRpcClient) -> Result<String> {
+    let health: String = rpc.call("getHealth", serde_json::json!([])).await?;
+    if health != "ok" {
+        bail!("node reports {}", health);
+    }
+    Ok(health)
+}
+
+/// `getVoteAccounts` filtered to the tracked vote account
+pub async fn probe_vote_accounts(rpc: &HttpRpcClient, vote_pubkey: &str) -> Result<String> {
+    let accounts = rpc.get_vote_accounts(Some(vote_pubkey)).await?;
+    match accounts.find(vote_pubkey) {
+        Some((_, false)) => Ok("vote account is current".to_string()),
+        Some((_, true)) => Ok("vote account is delinquent".to_string()),
+        None => bail!("vote account {} not returned", vote_pubkey),
+    }
+}
+
+/// `getAccountInfo` with `jsonParsed` encoding for the vote account
+pub async fn probe_account_info(rpc: &HttpRpcClient, vote_pubkey: &str) -> Result<String> {
+    let response: RpcResult<Option<AccountValue>> = rpc
+        .call(
+            "getAccountInfo",
+            serde_json::json!([vote_pubkey, { "encoding": "jsonParsed" }]),
+        )
+        .await?;
+
+    match response.value.map(|account| account.data) {
+        Some(AccountData::Parsed { program, .. }) => Ok(format!("parsed as {}", program)),
+        Some(AccountData::Raw(_, encoding)) => {
+            bail!("jsonParsed not supported, account returned as {}", encoding)
+        }
+        None => bail!("account {} not found", vote_pubkey),
+    }
+}
+
+/// Open the WebSocket and check that `accountSubscribe` is accepted
+#[cfg(feature = "ws")]
+pub async fn probe_account_subscribe(ws_url: &str, vote_pubkey: &str) -> Result<String> {
+    let (mut ws_stream, _) = connect_async(ws_url)
+        .await
+        .context("Failed to connect to WebSocket")?;
+
+    let subscribe_msg = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "accountSubscribe",
+        "params": [vote_pubkey, { "encoding": "jsonParsed", "commitment": COMMITMENT }]
+    });
+    ws_stream
+        .send(Message::Text(subscribe_msg.to_string()))
+        .await
+        .context("Failed to send subscribe message")?;
+
+    let confirmation = async {
+        while let Some(msg) = ws_stream.next().await {
+            let Message::Text(text) = msg.context("WebSocket receive error")? else {
+                continue;
+            };
+            match serde_json::from_str::<WsMessage>(&text) {
+                Ok(WsMessage::SubscriptionResult { result, .. }) => return Ok(result),
+                Ok(WsMessage::Error { error, .. }) => {
+                    return Err(anyhow!("RPC error {}: {}", error.code, error.message));
+                }
+                _ => {}
+            }
+        }
+        Err(anyhow!(
+            "WebSocket closed before the subscription was confirmed"
+        ))
+    };
+
+    let subscription_id = tokio::time::timeout(WS_PROBE_TIMEOUT, confirmation)
+        .await
+        .map_err(|_| anyhow!("no subscription confirmation within {:?}", WS_PROBE_TIMEOUT))??;
+
+    let _ = ws_stream.close(None).await;
+    Ok(format!("subscription id {}", subscription_id))
+}
+
+/// Without the `ws` feature there is no WebSocket client to probe with
+#[cfg(not(feature = "ws"))]
+pub async fn probe_account_subscribe(_ws_url: &str, _vote_pubkey: &str) -> Result<String> {
+    bail!("built without the `ws` feature")
+}
+
+/// Run every probe relevant for the mode, in order
+pub async fn run_preflight(
+    rpc: &HttpRpcClient,
+    rpc_url: &str,
+    vote_pubkey: &str,
+    mode: Mode,
+) -> Vec<ProbeResult> {
+    let mut results = vec![
+        ProbeResult::new("getVersion", probe_version(rpc).await),
+        ProbeResult::new("getHealth", probe_health(rpc).await),
+        ProbeResult::new(
+            "getVoteAccounts (votePubkey filter)",
+            probe_vote_accounts(rpc, vote_pubkey).await,
+        ),
+        ProbeResult::new(
+            "getAccountInfo (jsonParsed)",
+            probe_account_info(rpc, vote_pubkey).await,
+        ),
+    ];
+    if mode == Mode::Ws {
+        results.push(ProbeResult::new(
+            "accountSubscribe (WebSocket)",
+            probe_account_subscribe(&http_to_ws_url(rpc_url), vote_pubkey).await,
+        ));
+    }
+    results
+}
+
+/// Format the probe results as a capability table
+pub fn format_capabilities(results: &[ProbeResult]) -> String {
+    let width = results
+        .iter()
+        .map(|r| r.capability.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut out = String::from("RPC capabilities:");
+    for result in results {
+        let (status, detail) = match &result.outcome {
+            Ok(detail) => ("ok", detail.clone()),
+            Err(e) => ("MISSING", format!("{:#}", e)),
+        };
+        let _ = write!(
+            out,
+            "\n  {:<width$}  {:<7}  {}",
+            result.capability,
+            status,
+            detail,
+            width = width
+        );
+    }
+    out
+}
+
+/// Fail with the list of missing capabilities, if any
+pub fn ensure_capabilities(results: &[ProbeResult]) -> Result<()> {
+    let missing: Vec<String> = results
+        .iter()
+        .filter_map(|r| {
+            r.outcome
+                .as_ref()
+                .err()
+                .map(|e| format!("{} ({:#})", r.capability, e))
+        })
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "RPC preflight failed, missing capabilities: {} (use --skip-preflight to bypass)",
+        missing.join("; ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    const VOTE_PUBKEY: &str = "Vote1111111111111111111111111111111111111111";
+
+    /// Serve JSON-RPC on a local port, answering each method with `respond(method)`
+    async fn mock_rpc(respond: fn(&str) -> Value) -> HttpRpcClient {
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |axum::Json(body): axum::Json<Value>| async move {
+                let result = respond(body["method"].as_str().unwrap_or_default());
+                let mut response = json!({ "jsonrpc": "2.0", "id": 1 });
+                match result.get("error") {
+                    Some(error) => response["error"] = error.clone(),
+                    None => response["result"] = result,
+                }
+                axum::Json(response)
+            }),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        HttpRpcClient::new(&format!("http://{}", addr)).unwrap()
+    }
+
+    /// Accept one WebSocket connection and answer the first message with `reply`
+    #[cfg(feature = "ws")]
+    async fn mock_ws(reply: Value) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let _ = ws.next().await;
+            ws.send(Message::Text(reply.to_string())).await.unwrap();
+            let _ = ws.next().await;
+        });
+        format!("ws://{}", addr)
+    }
+
+    fn method_not_found() -> Value {
+        json!({ "error": { "code": -32601, "message": "Method not found" } })
+    }
+
+    fn full_node(method: &str) -> Value {
+        match method {
+            "getVersion" => json!({ "solana-core": "2.1.0", "feature-set": 1 }),
+            "getHealth" => json!("ok"),
+            "getVoteAccounts" => json!({
+                "current": [{
+                    "activatedStake": 1, "commission": 0, "epochCredits": [],
+                    "epochVoteAccount": true, "lastVote": 0, "rootSlot": 0,
+                    "nodePubkey": "Node", "votePubkey": VOTE_PUBKEY
+                }],
+                "delinquent": []
+            }),
+            "getAccountInfo" => json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "lamports": 1, "owner": "Vote111111111111111111111111111111111111111",
+                    "executable": false, "rentEpoch": 0,
+                    "data": {
+                        "program": "vote",
+                        "parsed": {
+                            "type": "vote",
+                            "info": { "votes": [], "rootSlot": null, "epochCredits": [] }
+                        }
+                    }
+                }
+            }),
+            _ => method_not_found(),
+        }
+    }
+
+    fn light_node(method: &str) -> Value {
+        match method {
+            "getVersion" | "getHealth" => full_node(method),
+            "getAccountInfo" => json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "lamports": 1, "owner": "Vote111111111111111111111111111111111111111",
+                    "executable": false, "rentEpoch": 0,
+                    "data": ["AAAA", "base64"]
+                }
+            }),
+            _ => method_not_found(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probes_against_full_node() {
+        let rpc = mock_rpc(full_node).await;
+        assert_eq!(probe_version(&rpc).await.unwrap(), "solana-core 2.1.0");
+        assert_eq!(probe_health(&rpc).await.unwrap(), "ok");
+        assert!(probe_vote_accounts(&rpc, VOTE_PUBKEY).await.is_ok());
+        assert_eq!(
+            probe_account_info(&rpc, VOTE_PUBKEY).await.unwrap(),
+            "parsed as vote"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probes_against_light_node() {
+        let rpc = mock_rpc(light_node).await;
+        assert!(probe_version(&rpc).await.is_ok());
+
+        let err = probe_vote_accounts(&rpc, VOTE_PUBKEY).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("Method not found"));
+
+        let err = probe_account_info(&rpc, VOTE_PUBKEY).await.unwrap_err();
+        assert!(err.to_string().contains("jsonParsed not supported"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_vote_accounts_unknown_pubkey() {
+        let rpc = mock_rpc(full_node).await;
+        assert!(probe_vote_accounts(&rpc, "Unknown").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_probe_health_unhealthy() {
+        let rpc = mock_rpc(|_| json!("behind")).await;
+        assert!(probe_health(&rpc).await.is_err());
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test]
+    async fn test_probe_account_subscribe() {
+        let url = mock_ws(json!({ "jsonrpc": "2.0", "result": 42, "id": 1 })).await;
+        assert_eq!(
+            probe_account_subscribe(&url, VOTE_PUBKEY).await.unwrap(),
+            "subscription id 42"
+        );
+
+        let url = mock_ws(json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32601, "message": "Method not found" },
+            "id": 1
+        }))
+        .await;
+        let err = probe_account_subscribe(&url, VOTE_PUBKEY)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Method not found"));
+    }
+
+    #[tokio::test]
+    async fn test_run_preflight_reports_missing_capabilities() {
+        let rpc = mock_rpc(light_node).await;
+        let results = run_preflight(&rpc, "http://unused", VOTE_PUBKEY, Mode::Poll).await;
+        assert_eq!(results.len(), 4);
+
+        let table = format_capabilities(&results);
+        assert!(table.contains("getVersion"));
+        assert!(table.contains("MISSING"));
+
+        let err = ensure_capabilities(&results).unwrap_err().to_string();
+        assert!(err.contains("getVoteAccounts (votePubkey filter)"));
+        assert!(err.contains("getAccountInfo (jsonParsed)"));
+        assert!(!err.contains("getHealth"));
+    }
+
+    #[tokio::test]
+    async fn test_run_preflight_full_node_passes() {
+        let rpc = mock_rpc(full_node).await;
+        let results = run_preflight(&rpc, "http://unused", VOTE_PUBKEY, Mode::Poll).await;
+        assert!(results.iter().all(ProbeResult::is_ok));
+        assert!(ensure_capabilities(&results).is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+use prometheus::Histogram;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Token bucket shared by all RPC callers: one token per request, refilled at
+/// `max_rps` tokens per second up to a burst of one second worth of requests
+#[derive(Debug)]
+pub struct RateLimiter {
+    tokens: Arc<Semaphore>,
+    max_rps: u32,
+    /// Time spent waiting for a token
+    wait_seconds: Option<Histogram>,
+}
+
+impl RateLimiter {
+    /// Create a full bucket and start the refill task (stops when the limiter is dropped)
+    pub fn new(max_rps: u32, wait_seconds: Option<Histogram>) -> Arc<Self> {
+        let limiter = Arc::new(Self {
+            tokens: Arc::new(Semaphore::new(max_rps as usize)),
+            max_rps,
+            wait_seconds,
+        });
+        tokio::spawn(refill(
+            Arc::downgrade(&limiter),
+            Duration::from_secs(1) / max_rps,
+        ));
+        limiter
+    }
+
+    /// Wait for a token and return how long that took
+    pub async fn acquire(&self) -> Duration {
+        let start = Instant::now();
+        if let Ok(permit) = self.tokens.acquire().await {
+            permit.forget();
+        }
+        let waited = start.elapsed();
+        if let Some(histogram) = &self.wait_seconds {
+            histogram.observe(waited.as_secs_f64());
+        }
+        waited
+    }
+}
+
+async fn refill(limiter: Weak<RateLimiter>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let Some(limiter) = limiter.upgrade() else {
+            return;
+        };
+        if limiter.tokens.available_permits() < limiter.max_rps as usize {
+            limiter.tokens.add_permits(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_are_spaced_after_burst() {
+        let limiter = RateLimiter::new(10, None);
+        let start = tokio::time::Instant::now();
+
+        // The first second worth of requests goes out immediately
+        for _ in 0..10 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(1));
+
+        // The next 20 are spaced at 100ms
+        for _ in 0..20 {
+            limiter.acquire().await;
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(1900), "{:?}", elapsed);
+        assert!(elapsed <= Duration::from_millis(2100), "{:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bucket_does_not_exceed_burst() {
+        let limiter = RateLimiter::new(5, None);
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(limiter.tokens.available_permits(), 5);
+    }
+}
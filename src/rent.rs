@@ -0,0 +1,97 @@
+use crate::metrics::Metrics;
+use crate::rpc::RpcClient;
+
+use anyhow::Result;
+
+/// Data length of a vote account (a serialized VoteState)
+pub const VOTE_ACCOUNT_SPACE: u64 = 3762;
+
+/// Minimum balance that keeps the vote account rent exempt, fetched once at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RentExemption {
+    pub minimum_balance: u64,
+}
+
+impl RentExemption {
+    /// `getMinimumBalanceForRentExemption` for an account of `data_len` bytes
+    pub async fn fetch<R: RpcClient>(rpc: &R, data_len: u64) -> Result<Self> {
+        Ok(Self {
+            minimum_balance: rpc.get_minimum_balance_for_rent_exemption(data_len).await?,
+        })
+    }
+
+    pub fn is_exempt(&self, lamports: u64) -> bool {
+        lamports >= self.minimum_balance
+    }
+
+    /// Lamports above the minimum (negative below it)
+    pub fn margin(&self, lamports: u64) -> i64 {
+        lamports as i64 - self.minimum_balance as i64
+    }
+}
+
+/// Export the vote account balance and, if the minimum is known, its rent-exempt status
+pub fn export_balance(metrics: &Metrics, lamports: u64, rent: Option<RentExemption>) {
+    metrics.vote_account_lamports.set(lamports as i64);
+    if let Some(rent) = rent {
+        metrics
+            .vote_account_rent_exempt
+            .set(rent.is_exempt(lamports) as i64);
+        metrics
+            .vote_account_rent_exempt_margin
+            .set(rent.margin(lamports));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::HttpRpcClient;
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_fetch_asks_for_the_data_length() {
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                assert_eq!(body["method"], "getMinimumBalanceForRentExemption");
+                let data_len = body["params"][0].as_u64().unwrap();
+                // 128 bytes of account overhead, 3480 lamports per byte-year, 2 years
+                let result = (data_len + 128) * 3480 * 2;
+                axum::Json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let rpc = HttpRpcClient::new(&format!("http://{}", addr)).unwrap();
+
+        let rent = RentExemption::fetch(&rpc, VOTE_ACCOUNT_SPACE)
+            .await
+            .unwrap();
+        assert_eq!(rent.minimum_balance, 27_074_400);
+    }
+
+    #[test]
+    fn test_rent_exempt_threshold() {
+        let metrics = Metrics::new().unwrap();
+        let rent = RentExemption {
+            minimum_balance: 27_074_400,
+        };
+
+        export_balance(&metrics, 27_074_400, Some(rent));
+        assert_eq!(metrics.vote_account_lamports.get(), 27_074_400);
+        assert_eq!(metrics.vote_account_rent_exempt.get(), 1);
+        assert_eq!(metrics.vote_account_rent_exempt_margin.get(), 0);
+
+        export_balance(&metrics, 27_074_399, Some(rent));
+        assert_eq!(metrics.vote_account_rent_exempt.get(), 0);
+        assert_eq!(metrics.vote_account_rent_exempt_margin.get(), -1);
+
+        // Without the minimum only the balance is known
+        export_balance(&metrics, 1_000_000_000, None);
+        assert_eq!(metrics.vote_account_lamports.get(), 1_000_000_000);
+        assert_eq!(metrics.vote_account_rent_exempt_margin.get(), -1);
+    }
+}
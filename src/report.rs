@@ -0,0 +1,281 @@
+use crate::config::Mode;
+use crate::metrics::Metrics;
+use crate::ws::{SLOTS_PER_EPOCH, TrackerSnapshot};
+
+use std::fmt::Write;
+use std::time::Duration;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Process-level counters sampled from the registry for the final report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReportCounters {
+    pub run_duration: Duration,
+    pub reconnects: u64,
+    pub rpc_errors: u64,
+}
+
+impl ReportCounters {
+    /// Sample the counters from the metrics registry
+    pub fn sample(metrics: &Metrics, run_duration: Duration) -> Self {
+        Self {
+            run_duration,
+            reconnects: metrics.ws_reconnects.get(),
+            rpc_errors: metrics.ws_errors.get(),
+        }
+    }
+}
+
+/// Format the human-readable report printed on shutdown
+pub fn format_final_report(snapshot: &TrackerSnapshot, counters: &ReportCounters) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "==================== tvc_tracker final report ===================="
+    );
+    let _ = writeln!(
+        out,
+        "Run duration:      {}",
+        format_duration(counters.run_duration)
+    );
+
+    let epochs = match (snapshot.first_epoch, snapshot.epoch_info) {
+        (Some(first), Some(info)) if first != info.epoch => format!("{} - {}", first, info.epoch),
+        (_, Some(info)) => info.epoch.to_string(),
+        _ => "none".to_string(),
+    };
+    let _ = writeln!(out, "Epochs covered:    {}", epochs);
+
+    let _ = writeln!(
+        out,
+        "Credits earned:    {} of {} expected (while tracked)",
+        snapshot.tracked_credits,
+        snapshot.tracked_expected()
+    );
+    let _ = writeln!(out, "Credits missed:    {}", snapshot.tracked_missed);
+
+    let efficiency = snapshot
+        .tracked_efficiency()
+        .map(format_percent)
+        .unwrap_or_else(|| "n/a".to_string());
+    let _ = writeln!(out, "Efficiency:        {}", efficiency);
+
+    let worst = snapshot
+        .worst_window_5m
+        .map(|w| {
+            format!(
+                "{} at {}",
+                format_percent(w.efficiency),
+                format_timestamp(w.timestamp)
+            )
+        })
+        .unwrap_or_else(|| "n/a".to_string());
+    let _ = writeln!(out, "Worst 5m window:   {}", worst);
+
+    let _ = writeln!(out, "Reconnects:        {}", counters.reconnects);
+    let _ = write!(out, "RPC errors:        {}", counters.rpc_errors);
+    out
+}
+
+/// State of the data source in the summary line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceState {
+    /// Subscribed for this long
+    WsUp(Duration),
+    WsDown,
+    /// Poll mode, up if the last poll succeeded
+    Rpc {
+        up: bool,
+    },
+}
+
+/// Values of the one-line summary served by `GET /summary.txt`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SummarySnapshot {
+    /// Epoch and the fraction of it passed, None before the first update
+    pub epoch: Option<(u64, f64)>,
+    pub efficiency_5m: f64,
+    pub efficiency_1h: f64,
+    pub missed_5m: u64,
+    pub latency_5m: f64,
+    pub source: SourceState,
+}
+
+impl SummarySnapshot {
+    /// Sample the gauges; `ws_connection_age` is None while disconnected
+    pub fn sample(metrics: &Metrics, mode: Mode, ws_connection_age: Option<Duration>) -> Self {
+        let epoch = metrics.last_data_update(mode).map(|_| {
+            let passed = (metrics.slot_index.get() + 1) as f64 / SLOTS_PER_EPOCH as f64;
+            (metrics.epoch.get() as u64, passed)
+        });
+        let source = match (mode, ws_connection_age) {
+            (Mode::Ws, Some(age)) => SourceState::WsUp(age),
+            (Mode::Ws, None) => SourceState::WsDown,
+            (Mode::Poll, _) => SourceState::Rpc {
+                up: metrics.rpc_up.get() == 1,
+            },
+        };
+        Self {
+            epoch,
+            efficiency_5m: metrics.vote_credits_efficiency_5m.get(),
+            efficiency_1h: metrics.vote_credits_efficiency_1h.get(),
+            missed_5m: metrics.missed_5m.get().max(0) as u64,
+            latency_5m: metrics.vote_latency_slots_5m.get(),
+            source,
+        }
+    }
+}
+
+/// Format the summary as a single line for status bars, e.g.
+/// `epoch 812 42.3% | eff 5m 98.7% 1h 99.1% | missed 5m 12 | lat 1.4 | ws up 3h`
+pub fn format_summary(summary: &SummarySnapshot) -> String {
+    let source = match summary.source {
+        SourceState::WsUp(age) => format!("ws up {}", format_age(age)),
+        SourceState::WsDown => "ws down".to_string(),
+        SourceState::Rpc { up: true } => "rpc up".to_string(),
+        SourceState::Rpc { up: false } => "rpc down".to_string(),
+    };
+    let Some((epoch, passed)) = summary.epoch else {
+        return format!("no data yet | {}", source);
+    };
+    format!(
+        "epoch {} {:.1}% | eff 5m {:.1}% 1h {:.1}% | missed 5m {} | lat {:.1} | {}",
+        epoch,
+        passed * 100.0,
+        summary.efficiency_5m * 100.0,
+        summary.efficiency_1h * 100.0,
+        summary.missed_5m,
+        summary.latency_5m,
+        source
+    )
+}
+
+/// Largest whole unit of `age`: `45s`, `12m`, `3h` or `2d`
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86_400 {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}d", secs / 86_400)
+    }
+}
+
+pub(crate) fn format_percent(fraction: f64) -> String {
+    format!("{:.2}%", fraction * 100.0)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{}h {:02}m {:02}s",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+fn format_timestamp(unix_secs: u64) -> String {
+    OffsetDateTime::from_unix_timestamp(unix_secs as i64)
+        .ok()
+        .and_then(|t| t.format(&Rfc3339).ok())
+        .unwrap_or_else(|| unix_secs.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::{EpochInfo, SLOTS_PER_EPOCH, WorstWindow};
+
+    #[test]
+    fn test_format_final_report() {
+        let snapshot = TrackerSnapshot {
+            epoch_info: Some(EpochInfo::from_slot(813 * SLOTS_PER_EPOCH + 10)),
+            first_epoch: Some(812),
+            tracked_credits: 1500,
+            tracked_missed: 100,
+            worst_window_5m: Some(WorstWindow {
+                efficiency: 0.8125,
+                timestamp: 1_700_000_000,
+            }),
+            ..Default::default()
+        };
+        let counters = ReportCounters {
+            run_duration: Duration::from_secs(2 * 3600 + 3 * 60 + 15),
+            reconnects: 2,
+            rpc_errors: 1,
+        };
+
+        let report = format_final_report(&snapshot, &counters);
+        assert!(report.contains("Run duration:      2h 03m 15s"));
+        assert!(report.contains("Epochs covered:    812 - 813"));
+        assert!(report.contains("Credits earned:    1500 of 1600 expected"));
+        assert!(report.contains("Efficiency:        93.75%"));
+        assert!(report.contains("Worst 5m window:   81.25% at 2023-11-14T22:13:20Z"));
+        assert!(report.contains("Reconnects:        2"));
+        assert!(report.contains("RPC errors:        1"));
+    }
+
+    fn summary(source: SourceState) -> SummarySnapshot {
+        SummarySnapshot {
+            epoch: Some((812, 0.4234)),
+            efficiency_5m: 0.98712,
+            efficiency_1h: 0.991,
+            missed_5m: 12,
+            latency_5m: 1.44,
+            source,
+        }
+    }
+
+    #[test]
+    fn test_format_summary() {
+        let up = summary(SourceState::WsUp(Duration::from_secs(3 * 3600 + 59 * 60)));
+        assert_eq!(
+            format_summary(&up),
+            "epoch 812 42.3% | eff 5m 98.7% 1h 99.1% | missed 5m 12 | lat 1.4 | ws up 3h"
+        );
+        let down = summary(SourceState::WsDown);
+        assert!(format_summary(&down).ends_with("| lat 1.4 | ws down"));
+        let poll = summary(SourceState::Rpc { up: false });
+        assert!(format_summary(&poll).ends_with("| rpc down"));
+    }
+
+    #[test]
+    fn test_format_summary_edge_cases() {
+        // Nothing received yet
+        let empty = SummarySnapshot {
+            epoch: None,
+            ..summary(SourceState::WsDown)
+        };
+        assert_eq!(format_summary(&empty), "no data yet | ws down");
+
+        // First update of a new epoch, windows still empty
+        let rolled = SummarySnapshot {
+            epoch: Some((813, 1.0 / SLOTS_PER_EPOCH as f64)),
+            efficiency_5m: 0.0,
+            efficiency_1h: 0.0,
+            missed_5m: 0,
+            latency_5m: 0.0,
+            source: SourceState::WsUp(Duration::from_secs(5)),
+        };
+        assert_eq!(
+            format_summary(&rolled),
+            "epoch 813 0.0% | eff 5m 0.0% 1h 0.0% | missed 5m 0 | lat 0.0 | ws up 5s"
+        );
+
+        assert_eq!(format_age(Duration::from_secs(59)), "59s");
+        assert_eq!(format_age(Duration::from_secs(60)), "1m");
+        assert_eq!(format_age(Duration::from_secs(2 * 86_400 + 5)), "2d");
+    }
+
+    #[test]
+    fn test_format_final_report_without_data() {
+        let report = format_final_report(&TrackerSnapshot::default(), &ReportCounters::default());
+        assert!(report.contains("Epochs covered:    none"));
+        assert!(report.contains("Efficiency:        n/a"));
+        assert!(report.contains("Worst 5m window:   n/a"));
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Result, anyhow, bail};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// `--rpc-resolve host:ip`: connect to `addr` whenever a URL names `host`,
+/// like curl's `--resolve` without the port. TLS still verifies the
+/// certificate for `host`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveOverride {
+    pub host: String,
+    pub addr: IpAddr,
+}
+
+impl ResolveOverride {
+    /// Address for reqwest's resolver; port 0 keeps the port of the URL
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.addr, 0)
+    }
+}
+
+impl FromStr for ResolveOverride {
+    type Err = anyhow::Error;
+
+    /// Parse `host:ip`, with IPv6 addresses optionally in brackets
+    fn from_str(s: &str) -> Result<Self> {
+        let (host, addr) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("expected host:ip, got {}", s))?;
+        if host.is_empty() {
+            bail!("no host in {}", s);
+        }
+        let addr = addr.trim_start_matches('[').trim_end_matches(']');
+        let addr = addr
+            .parse()
+            .map_err(|_| anyhow!("invalid IP address {} for {}", addr, host))?;
+        Ok(Self {
+            host: host.to_ascii_lowercase(),
+            addr,
+        })
+    }
+}
+
+impl fmt::Display for ResolveOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.addr)
+    }
+}
+
+impl Serialize for ResolveOverride {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The pinned address of `host`, if any
+pub fn resolve_override(overrides: &[ResolveOverride], host: &str) -> Option<IpAddr> {
+    overrides
+        .iter()
+        .find(|o| o.host.eq_ignore_ascii_case(host))
+        .map(|o| o.addr)
+}
+
+/// A host pinned twice, for [`crate::config::Args::validate`]
+pub fn duplicate_host(overrides: &[ResolveOverride]) -> Option<&str> {
+    overrides.iter().enumerate().find_map(|(i, o)| {
+        overrides[..i]
+            .iter()
+            .any(|earlier| earlier.host == o.host)
+            .then_some(o.host.as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolve_override() {
+        let parsed: ResolveOverride = "RPC.example.com:10.0.0.7".parse().unwrap();
+        assert_eq!(parsed.host, "rpc.example.com");
+        assert_eq!(parsed.addr, IpAddr::from([10, 0, 0, 7]));
+        assert_eq!(parsed.to_string(), "rpc.example.com:10.0.0.7");
+
+        let v6: ResolveOverride = "rpc.example.com:[::1]".parse().unwrap();
+        assert_eq!(v6.addr, "::1".parse::<IpAddr>().unwrap());
+        let bare_v6: ResolveOverride = "rpc.example.com:2001:db8::1".parse().unwrap();
+        assert_eq!(bare_v6.addr, "2001:db8::1".parse::<IpAddr>().unwrap());
+
+        for invalid in [
+            "rpc.example.com",
+            ":10.0.0.7",
+            "rpc.example.com:10.0.0",
+            "h:host",
+        ] {
+            assert!(invalid.parse::<ResolveOverride>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_duplicates() {
+        let overrides: Vec<ResolveOverride> = ["a.example:10.0.0.1", "b.example:10.0.0.2"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(
+            resolve_override(&overrides, "B.example"),
+            Some(IpAddr::from([10, 0, 0, 2]))
+        );
+        assert_eq!(resolve_override(&overrides, "c.example"), None);
+        assert_eq!(duplicate_host(&overrides), None);
+
+        let mut twice = overrides.clone();
+        twice.push("A.EXAMPLE:10.0.0.3".parse().unwrap());
+        assert_eq!(duplicate_host(&twice), Some("a.example"));
+    }
+}
@@ -0,0 +1,1027 @@
+use crate::cluster::{StakeWeightedCredits, TopCredits};
+use crate::debug_dump::DebugState;
+use crate::metrics::Metrics;
+use crate::proxy::ProxyUrl;
+use crate::rate_limit::RateLimiter;
+use crate::resolve::ResolveOverride;
+use crate::throttled_warn;
+use crate::ws::{AccountData, AccountShape, DataEncoding, FetchedAccount, RpcResult};
+
+use anyhow::{Context, Result, anyhow};
+use prometheus::{HistogramVec, IntCounter};
+use serde::de::{DeserializeOwned, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{Instrument, info_span, instrument};
+
+/// Maximum attempts per RPC call (first try + retries)
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled on every further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Per-request HTTP timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Response bodies larger than this are logged by default
+pub const DEFAULT_RESPONSE_WARN_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Bytes of a response body quoted in a parse error
+const BODY_EXCERPT_BYTES: usize = 256;
+
+/// Commitment of the vote account subscription and the RPC requests (bar the
+/// tip from getSlot)
+pub const COMMITMENT: &str = "finalized";
+
+/// Vote account entry from `getVoteAccounts`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcVoteAccount {
+    pub vote_pubkey: String,
+    pub node_pubkey: String,
+    pub activated_stake: u64,
+    pub commission: u8,
+    pub epoch_vote_account: bool,
+    /// (epoch, credits, previous_credits) for up to the last 5 epochs
+    pub epoch_credits: Vec<(u64, u64, u64)>,
+    pub last_vote: u64,
+    pub root_slot: u64,
+}
+
+/// `getVoteAccounts` result
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RpcVoteAccounts {
+    pub current: Vec<RpcVoteAccount>,
+    pub delinquent: Vec<RpcVoteAccount>,
+}
+
+impl RpcVoteAccounts {
+    /// Find a vote account by pubkey, returning it with its delinquency flag
+    pub fn find(&self, vote_pubkey: &str) -> Option<(&RpcVoteAccount, bool)> {
+        self.current
+            .iter()
+            .find(|a| a.vote_pubkey == vote_pubkey)
+            .map(|a| (a, false))
+            .or_else(|| {
+                self.delinquent
+                    .iter()
+                    .find(|a| a.vote_pubkey == vote_pubkey)
+                    .map(|a| (a, true))
+            })
+    }
+}
+
+/// Activated stake of all vote accounts in `getVoteAccounts`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClusterStake {
+    /// Stake of the current (non-delinquent) vote accounts, in lamports
+    pub current: u64,
+    /// Stake of the delinquent vote accounts, in lamports
+    pub delinquent: u64,
+}
+
+impl ClusterStake {
+    /// Sum an already fetched `getVoteAccounts` result
+    pub fn from_accounts(accounts: &RpcVoteAccounts) -> Self {
+        let sum = |list: &[RpcVoteAccount]| list.iter().map(|a| a.activated_stake).sum();
+        Self {
+            current: sum(&accounts.current),
+            delinquent: sum(&accounts.delinquent),
+        }
+    }
+
+    /// Fraction of the total stake that is delinquent (0 without stake)
+    pub fn delinquent_ratio(&self) -> f64 {
+        let total = self.current.saturating_add(self.delinquent);
+        if total == 0 {
+            return 0.0;
+        }
+        self.delinquent as f64 / total as f64
+    }
+}
+
+/// Cluster stake and the best credits this epoch from one unfiltered
+/// `getVoteAccounts`, folded while the response is parsed so the accounts
+/// themselves are never kept
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "ScannedVoteAccounts")]
+pub struct ClusterScan {
+    pub stake: ClusterStake,
+    /// Current and delinquent accounts alike
+    pub top_credits: TopCredits,
+    /// Credits this epoch of the current accounts, by stake
+    pub current_credits: StakeWeightedCredits,
+}
+
+impl ClusterScan {
+    /// Fold an already fetched `getVoteAccounts` result
+    pub fn from_accounts(accounts: &RpcVoteAccounts) -> Self {
+        let mut top_credits = TopCredits::default();
+        for account in accounts.current.iter().chain(&accounts.delinquent) {
+            top_credits.push(&account.epoch_credits);
+        }
+        let mut current_credits = StakeWeightedCredits::default();
+        for account in &accounts.current {
+            current_credits.push(account.activated_stake, &account.epoch_credits);
+        }
+        Self {
+            stake: ClusterStake::from_accounts(accounts),
+            top_credits,
+            current_credits,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ScannedVoteAccounts {
+    current: ScannedList,
+    delinquent: ScannedList,
+}
+
+impl From<ScannedVoteAccounts> for ClusterScan {
+    fn from(accounts: ScannedVoteAccounts) -> Self {
+        let mut top_credits = accounts.current.top_credits;
+        top_credits.merge(accounts.delinquent.top_credits);
+        Self {
+            stake: ClusterStake {
+                current: accounts.current.stake,
+                delinquent: accounts.delinquent.stake,
+            },
+            top_credits,
+            current_credits: accounts.current.credits,
+        }
+    }
+}
+
+/// The fields of a vote account [`ClusterScan`] needs
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScannedVoteAccount {
+    activated_stake: u64,
+    #[serde(default)]
+    epoch_credits: Vec<(u64, u64, u64)>,
+}
+
+/// Stake summed and credits ranked and weighted over a list of vote accounts
+struct ScannedList {
+    stake: u64,
+    top_credits: TopCredits,
+    credits: StakeWeightedCredits,
+}
+
+impl<'de> Deserialize<'de> for ScannedList {
+    /// One element at a time
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ScanVisitor;
+
+        impl<'de> Visitor<'de> for ScanVisitor {
+            type Value = ScannedList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of vote accounts")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ScannedList, A::Error> {
+                let mut list = ScannedList {
+                    stake: 0,
+                    top_credits: TopCredits::default(),
+                    credits: StakeWeightedCredits::default(),
+                };
+                while let Some(account) = seq.next_element::<ScannedVoteAccount>()? {
+                    list.stake = list.stake.saturating_add(account.activated_stake);
+                    list.top_credits.push(&account.epoch_credits);
+                    list.credits
+                        .push(account.activated_stake, &account.epoch_credits);
+                }
+                Ok(list)
+            }
+        }
+
+        deserializer.deserialize_seq(ScanVisitor)
+    }
+}
+
+/// `getVersion` result
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RpcVersion {
+    pub solana_core: String,
+    pub feature_set: Option<u32>,
+}
+
+/// JSON-RPC error code of `getHealth` when the node is unhealthy
+pub const NODE_UNHEALTHY_CODE: i64 = -32005;
+
+/// `getHealth` of the RPC node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcHealth {
+    Ok,
+    /// Behind the cluster, by this many slots if the node said
+    Behind(Option<u64>),
+    /// Answered with another error
+    Unknown,
+}
+
+impl RpcHealth {
+    /// Every `status` label of `rpc_health`
+    pub const STATUSES: [&'static str; 3] = ["ok", "behind", "unknown"];
+
+    /// Health from the error `getHealth` answered with
+    pub fn from_error(error: &RpcError) -> Self {
+        if error.code != NODE_UNHEALTHY_CODE {
+            return Self::Unknown;
+        }
+        let slots = error
+            .data
+            .as_ref()
+            .and_then(|data| data["numSlotsBehind"].as_u64());
+        Self::Behind(slots)
+    }
+
+    /// The `status` label of `rpc_health`
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Behind(_) => "behind",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Entry of `getSignaturesForAddress`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSignature {
+    pub signature: String,
+    pub slot: u64,
+    /// Error of a transaction that landed but failed
+    #[serde(default)]
+    pub err: Option<Value>,
+}
+
+/// Account with its data as an encoded string (`[data, encoding]`)
+#[derive(Debug, Deserialize)]
+struct EncodedAccount {
+    data: (String, String),
+}
+
+/// Account with its data left undecoded
+#[derive(Debug, Deserialize)]
+struct RawAccount {
+    data: Value,
+}
+
+/// Response with context (`{"context": {...}, "value": ...}`)
+#[derive(Debug, Deserialize)]
+struct RpcValue<T> {
+    value: T,
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default)]
+    pub data: Option<Value>,
+}
+
+/// Error the RPC node answered a call with, as returned by [`HttpRpcClient::call`]
+#[derive(Debug, Clone)]
+pub struct RpcCallError {
+    pub method: String,
+    pub error: RpcError,
+}
+
+impl fmt::Display for RpcCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RPC error {} from {}: {}",
+            self.error.code, self.method, self.error.message
+        )
+    }
+}
+
+impl std::error::Error for RpcCallError {}
+
+/// JSON-RPC response envelope
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+/// Error of a method the client does not implement
+fn unsupported<T>(method: &str) -> Result<T> {
+    Err(anyhow!("{} is not supported by this RPC client", method))
+}
+
+/// Solana JSON-RPC methods used by the pollers (implemented by fakes in tests).
+/// The methods only some features need fail as unsupported unless implemented.
+pub trait RpcClient: Send + Sync {
+    /// `getVoteAccounts`, optionally filtered to a single vote account
+    fn get_vote_accounts(
+        &self,
+        vote_pubkey: Option<&str>,
+    ) -> impl Future<Output = Result<RpcVoteAccounts>> + Send;
+
+    /// Activated stake and best credits of the whole cluster from an
+    /// unfiltered `getVoteAccounts`
+    fn get_cluster_scan(&self) -> impl Future<Output = Result<ClusterScan>> + Send {
+        async {
+            Ok(ClusterScan::from_accounts(
+                &self.get_vote_accounts(None).await?,
+            ))
+        }
+    }
+
+    /// `getVersion` of the RPC node
+    fn get_version(&self) -> impl Future<Output = Result<RpcVersion>> + Send;
+
+    /// Whether a feature gate account exists and has been activated
+    fn is_feature_active(&self, feature_id: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Latest slot the node has processed (the tip)
+    fn get_slot(&self) -> impl Future<Output = Result<u64>> + Send;
+
+    /// `getHealth` of the node. Err if it could not be asked.
+    fn get_health(&self) -> impl Future<Output = Result<RpcHealth>> + Send {
+        async { unsupported("getHealth") }
+    }
+
+    /// `getLeaderSchedule` of the epoch containing `slot` for one validator
+    /// identity: its leader slots as indices into the epoch
+    fn get_leader_schedule(
+        &self,
+        _slot: u64,
+        _identity: &str,
+    ) -> impl Future<Output = Result<Vec<u64>>> + Send {
+        async { unsupported("getLeaderSchedule") }
+    }
+
+    /// `getBlocks`: finalized slots in `start..=end` that have a block
+    fn get_blocks(&self, _start: u64, _end: u64) -> impl Future<Output = Result<Vec<u64>>> + Send {
+        async { unsupported("getBlocks") }
+    }
+
+    /// `getSignaturesForAddress`: up to `limit` finalized signatures involving
+    /// `address`, newest first, starting below `before` when given
+    fn get_signatures_for_address(
+        &self,
+        _address: &str,
+        _before: Option<&str>,
+        _limit: usize,
+    ) -> impl Future<Output = Result<Vec<RpcSignature>>> + Send {
+        async { unsupported("getSignaturesForAddress") }
+    }
+
+    /// `getMinimumBalanceForRentExemption` for an account of `data_len` bytes
+    fn get_minimum_balance_for_rent_exemption(
+        &self,
+        _data_len: u64,
+    ) -> impl Future<Output = Result<u64>> + Send {
+        async { unsupported("getMinimumBalanceForRentExemption") }
+    }
+}
+
+/// JSON-RPC over HTTP with retries
+#[derive(Debug, Clone)]
+pub struct HttpRpcClient {
+    client: reqwest::Client,
+    url: String,
+    /// Shared by all clones, so every caller draws from the same budget
+    limiter: Option<Arc<RateLimiter>>,
+    /// Keeps the latest getVoteAccounts result for `POST /debug/dump`
+    debug: Option<Arc<DebugState>>,
+    response_sizes: Option<ResponseSizes>,
+}
+
+/// Records the size of every response body, see
+/// [`HttpRpcClient::with_response_sizes`]
+#[derive(Debug, Clone)]
+struct ResponseSizes {
+    /// `rpc_response_bytes`
+    bytes: HistogramVec,
+    /// `rpc_received_bytes_total`
+    total: IntCounter,
+    /// Larger bodies are logged (0: never)
+    warn_bytes: u64,
+}
+
+impl ResponseSizes {
+    fn record(&self, method: &str, len: usize) {
+        self.bytes.with_label_values(&[method]).observe(len as f64);
+        self.total.inc_by(len as u64);
+        if self.warn_bytes > 0 && len as u64 > self.warn_bytes {
+            throttled_warn!(
+                "RPC {} response of {} bytes exceeds --rpc-response-warn-bytes {}",
+                method,
+                len,
+                self.warn_bytes
+            );
+        }
+    }
+}
+
+impl HttpRpcClient {
+    pub fn new(url: &str) -> Result<Self> {
+        Self::new_with_proxy(url, None)
+    }
+
+    /// Client sending every request through `proxy`. Without one, reqwest
+    /// still honors HTTP_PROXY / HTTPS_PROXY / ALL_PROXY and NO_PROXY.
+    pub fn new_with_proxy(url: &str, proxy: Option<&ProxyUrl>) -> Result<Self> {
+        Self::new_with_resolve(url, proxy, &[])
+    }
+
+    /// Client connecting to the pinned addresses of the hosts in `resolve`
+    /// instead of looking them up
+    pub fn new_with_resolve(
+        url: &str,
+        proxy: Option<&ProxyUrl>,
+        resolve: &[ResolveOverride],
+    ) -> Result<Self> {
+        let mut builder = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
+        if let Some(proxy) = proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_url().clone())
+                .with_context(|| format!("Invalid proxy {}", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+        for pinned in resolve {
+            builder = builder.resolve(&pinned.host, pinned.socket_addr());
+        }
+        let client = builder.build().context("Failed to build HTTP client")?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            limiter: None,
+            debug: None,
+            response_sizes: None,
+        })
+    }
+
+    /// The underlying HTTP client, for other requests sharing its connection pool
+    pub fn http_client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
+    /// Limit outgoing requests (retries included) to `limiter`'s budget
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Export the size of every response body in `rpc_response_bytes` and
+    /// `rpc_received_bytes_total`, and log bodies over `warn_bytes` (0: none)
+    pub fn with_response_sizes(mut self, metrics: &Metrics, warn_bytes: u64) -> Self {
+        self.response_sizes = Some(ResponseSizes {
+            bytes: metrics.rpc_response_bytes.clone(),
+            total: metrics.rpc_received_bytes.clone(),
+            warn_bytes,
+        });
+        self
+    }
+
+    /// Retain the raw getVoteAccounts result for the tracked account in `debug`
+    pub fn with_debug_state(mut self, debug: Arc<DebugState>) -> Self {
+        self.debug = Some(debug);
+        self
+    }
+
+    /// Fetch an account with getAccountInfo, discarding its data. Some providers
+    /// only stream accountSubscribe notifications for accounts fetched over HTTP first.
+    pub async fn warm_up_account(&self, pubkey: &str) -> Result<()> {
+        self.call::<IgnoredAny>(
+            "getAccountInfo",
+            serde_json::json!([pubkey, { "encoding": "base64", "commitment": COMMITMENT }]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fetch an account with getAccountInfo using the vote subscription's
+    /// encoding and commitment, keeping the context slot and the data's
+    /// encoding and shape. Like [`Self::warm_up_account`], it gets providers
+    /// that need an HTTP fetch first streaming.
+    pub async fn fetch_subscribed_account(&self, pubkey: &str) -> Result<FetchedAccount> {
+        let response: RpcResult<Option<RawAccount>> = self
+            .call(
+                "getAccountInfo",
+                serde_json::json!([pubkey, { "encoding": "jsonParsed", "commitment": COMMITMENT }]),
+            )
+            .await?;
+        let data = response.value.map(|account| account.data);
+        Ok(FetchedAccount {
+            slot: response.context.slot,
+            encoding: data.as_ref().map(DataEncoding::of_value),
+            shape: data
+                .and_then(|data| serde_json::from_value::<AccountData>(data).ok())
+                .map(|data| AccountShape::of(&data)),
+        })
+    }
+
+    /// Call a JSON-RPC method, retrying transport failures with exponential backoff.
+    /// RPC-level errors are returned immediately since retrying won't change them.
+    #[instrument(name = "rpc_call", skip(self, params))]
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let mut attempt = 1;
+        let response = loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            match rpc_post_json(&self.client, &self.url, &body, self.response_sizes.as_ref())
+                .instrument(info_span!("rpc_attempt", attempt))
+                .await
+            {
+                Ok(response) => break response,
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    throttled_warn!(
+                        "RPC request {} failed (attempt {}/{}): {:#}",
+                        method,
+                        attempt,
+                        MAX_ATTEMPTS,
+                        e
+                    );
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.context(format!("RPC request {} failed", method))),
+            }
+        };
+
+        let response = response.with_context(|| format!("Failed to parse {} response", method))?;
+        if let Some(error) = response.error {
+            return Err(RpcCallError {
+                method: method.to_string(),
+                error,
+            }
+            .into());
+        }
+        response
+            .result
+            .ok_or_else(|| anyhow!("RPC response to {} has no result", method))
+    }
+}
+
+impl RpcClient for HttpRpcClient {
+    async fn get_vote_accounts(&self, vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+        let mut config = serde_json::json!({ "commitment": COMMITMENT });
+        if let Some(pubkey) = vote_pubkey {
+            config["votePubkey"] = Value::String(pubkey.to_string());
+        }
+        let result: Value = self
+            .call("getVoteAccounts", serde_json::json!([config]))
+            .await?;
+        // Only the filtered call: the unfiltered one returns the whole cluster
+        if let (Some(debug), Some(_)) = (&self.debug, vote_pubkey) {
+            debug.record_vote_accounts(&result);
+        }
+        serde_json::from_value(result).context("Failed to parse getVoteAccounts response")
+    }
+
+    async fn get_cluster_scan(&self) -> Result<ClusterScan> {
+        let config = serde_json::json!({ "commitment": COMMITMENT });
+        self.call("getVoteAccounts", serde_json::json!([config]))
+            .await
+    }
+
+    async fn get_version(&self) -> Result<RpcVersion> {
+        self.call("getVersion", serde_json::json!([])).await
+    }
+
+    async fn is_feature_active(&self, feature_id: &str) -> Result<bool> {
+        // Feature accounts hold a bincode Option<Slot>: the first byte is 1 once activated.
+        // Fetching just that byte base58-encoded gives "2" (activated) or "1" (pending).
+        let response: RpcValue<Option<EncodedAccount>> = self
+            .call(
+                "getAccountInfo",
+                serde_json::json!([
+                    feature_id,
+                    { "encoding": "base58", "dataSlice": { "offset": 0, "length": 1 } }
+                ]),
+            )
+            .await?;
+        Ok(response.value.is_some_and(|account| account.data.0 == "2"))
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        self.call(
+            "getSlot",
+            serde_json::json!([{ "commitment": "processed" }]),
+        )
+        .await
+    }
+
+    async fn get_health(&self) -> Result<RpcHealth> {
+        match self
+            .call::<String>("getHealth", serde_json::json!([]))
+            .await
+        {
+            Ok(_) => Ok(RpcHealth::Ok),
+            Err(e) => match e.downcast_ref::<RpcCallError>() {
+                Some(answered) => Ok(RpcHealth::from_error(&answered.error)),
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn get_leader_schedule(&self, slot: u64, identity: &str) -> Result<Vec<u64>> {
+        let mut schedule: HashMap<String, Vec<u64>> = self
+            .call(
+                "getLeaderSchedule",
+                serde_json::json!([slot, { "identity": identity, "commitment": COMMITMENT }]),
+            )
+            .await?;
+        // Filtered by identity: no entry means no leader slots this epoch
+        Ok(schedule.remove(identity).unwrap_or_default())
+    }
+
+    async fn get_blocks(&self, start: u64, end: u64) -> Result<Vec<u64>> {
+        self.call(
+            "getBlocks",
+            serde_json::json!([start, end, { "commitment": COMMITMENT }]),
+        )
+        .await
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<RpcSignature>> {
+        let mut config = serde_json::json!({ "commitment": COMMITMENT, "limit": limit });
+        if let Some(before) = before {
+            config["before"] = Value::String(before.to_string());
+        }
+        self.call(
+            "getSignaturesForAddress",
+            serde_json::json!([address, config]),
+        )
+        .await
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(&self, data_len: u64) -> Result<u64> {
+        self.call(
+            "getMinimumBalanceForRentExemption",
+            serde_json::json!([data_len, { "commitment": COMMITMENT }]),
+        )
+        .await
+    }
+}
+
+/// POST a JSON-RPC `body` and decode the response envelope straight from the
+/// body, without an intermediate `Value`. A body that is not JSON is an error
+/// of the request (and retried); a result of the wrong shape is returned
+/// inside `Ok`. Both quote the start of the body.
+async fn rpc_post_json<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    body: &Value,
+    sizes: Option<&ResponseSizes>,
+) -> Result<Result<RpcResponse<T>>> {
+    let response = client
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .context("HTTP request failed")?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!("HTTP status {}", status));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .context("Failed to read response body")?;
+    if let Some(sizes) = sizes {
+        let method = body["method"].as_str().unwrap_or_default();
+        sizes.record(method, bytes.len());
+    }
+    match serde_json::from_slice(&bytes) {
+        Ok(parsed) => Ok(Ok(parsed)),
+        Err(e) if e.is_syntax() || e.is_eof() => Err(anyhow!(e).context(format!(
+            "Failed to decode JSON response: {}",
+            body_excerpt(&bytes)
+        ))),
+        Err(e) => Ok(Err(
+            anyhow!(e).context(format!("Response: {}", body_excerpt(&bytes)))
+        )),
+    }
+}
+
+/// The start of a response body for an error message, with its length if cut
+fn body_excerpt(body: &[u8]) -> String {
+    let excerpt = String::from_utf8_lossy(&body[..body.len().min(BODY_EXCERPT_BYTES)]);
+    if body.len() > BODY_EXCERPT_BYTES {
+        format!("{}... ({} bytes)", excerpt, body.len())
+    } else {
+        excerpt.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vote_accounts_parsing() {
+        let json = r#"{
+            "current": [{
+                "activatedStake": 42000000000,
+                "commission": 5,
+                "epochCredits": [[811, 5000, 1000], [812, 6000, 5000]],
+                "epochVoteAccount": true,
+                "lastVote": 350784123,
+                "nodePubkey": "Node1111111111111111111111111111111111111111",
+                "rootSlot": 350784092,
+                "votePubkey": "Vote1111111111111111111111111111111111111111"
+            }],
+            "delinquent": []
+        }"#;
+
+        let accounts: RpcVoteAccounts = serde_json::from_str(json).unwrap();
+        assert_eq!(accounts.current.len(), 1);
+        let account = &accounts.current[0];
+        assert_eq!(account.activated_stake, 42_000_000_000);
+        assert_eq!(account.epoch_credits[1], (812, 6000, 5000));
+        assert_eq!(account.root_slot, 350784092);
+    }
+
+    #[test]
+    fn test_cluster_stake_parsing() {
+        let account = |pubkey: &str, stake: u64| {
+            serde_json::json!({
+                "activatedStake": stake,
+                "commission": 5,
+                "epochCredits": [[812, 6000, 5000]],
+                "epochVoteAccount": true,
+                "lastVote": 350784123,
+                "nodePubkey": "Node1111111111111111111111111111111111111111",
+                "rootSlot": 350784092,
+                "votePubkey": pubkey
+            })
+        };
+        let json = serde_json::json!({
+            "current": [account("A", 30), account("B", 50)],
+            "delinquent": [account("C", 20)]
+        })
+        .to_string();
+
+        let scan: ClusterScan = serde_json::from_str(&json).unwrap();
+        let stake = scan.stake;
+        assert_eq!(
+            stake,
+            ClusterStake {
+                current: 80,
+                delinquent: 20
+            }
+        );
+        assert_eq!(stake.delinquent_ratio(), 0.2);
+        assert_eq!(scan.top_credits.rank(3), Some(1000));
+        // The delinquent account is left out of the mean
+        assert_eq!(scan.current_credits.mean(), Some(1000.0));
+
+        let accounts: RpcVoteAccounts = serde_json::from_str(&json).unwrap();
+        assert_eq!(ClusterStake::from_accounts(&accounts), stake);
+        assert_eq!(ClusterScan::from_accounts(&accounts), scan);
+        assert_eq!(ClusterStake::default().delinquent_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_vote_accounts_find() {
+        let account = |pubkey: &str| RpcVoteAccount {
+            vote_pubkey: pubkey.to_string(),
+            node_pubkey: String::new(),
+            activated_stake: 0,
+            commission: 0,
+            epoch_vote_account: true,
+            epoch_credits: vec![],
+            last_vote: 0,
+            root_slot: 0,
+        };
+        let accounts = RpcVoteAccounts {
+            current: vec![account("A")],
+            delinquent: vec![account("B")],
+        };
+
+        assert_eq!(accounts.find("A").map(|(_, d)| d), Some(false));
+        assert_eq!(accounts.find("B").map(|(_, d)| d), Some(true));
+        assert!(accounts.find("C").is_none());
+    }
+
+    #[test]
+    fn test_leader_schedule_parsing() {
+        let json = r#"{"jsonrpc": "2.0", "result": {"Node1111111111111111111111111111111111111111": [0, 1, 2, 3, 4096]}, "id": 1}"#;
+        let response: RpcResponse<HashMap<String, Vec<u64>>> = serde_json::from_str(json).unwrap();
+        let schedule = response.result.unwrap();
+        assert_eq!(
+            schedule["Node1111111111111111111111111111111111111111"],
+            vec![0, 1, 2, 3, 4096]
+        );
+    }
+
+    #[test]
+    fn test_version_parsing() {
+        let json = r#"{"solana-core": "1.18.23", "feature-set": 4215500110}"#;
+        let version: RpcVersion = serde_json::from_str(json).unwrap();
+        assert_eq!(version.solana_core, "1.18.23");
+        assert_eq!(version.feature_set, Some(4215500110));
+    }
+
+    #[test]
+    fn test_signatures_parsing() {
+        let json = r#"[
+            {"signature": "5h6x", "slot": 350784123, "err": null, "memo": null, "blockTime": 1700000000, "confirmationStatus": "finalized"},
+            {"signature": "3k9q", "slot": 350784122, "err": {"InstructionError": [0, {"Custom": 0}]}, "memo": null, "blockTime": null}
+        ]"#;
+        let signatures: Vec<RpcSignature> = serde_json::from_str(json).unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].signature, "5h6x");
+        assert_eq!(signatures[0].slot, 350784123);
+        assert!(signatures[0].err.is_none());
+        assert!(signatures[1].err.is_some());
+    }
+
+    #[test]
+    fn test_rpc_error_response_parsing() {
+        let json = r#"{"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": 1}"#;
+        let response: RpcResponse<RpcVoteAccounts> = serde_json::from_str(json).unwrap();
+        assert!(response.result.is_none());
+        assert_eq!(response.error.unwrap().code, -32601);
+    }
+
+    #[test]
+    fn test_health_from_error() {
+        let error = |json: &str| serde_json::from_str::<RpcError>(json).unwrap();
+        let behind = error(
+            r#"{"code": -32005, "message": "Node is behind by 42 slots", "data": {"numSlotsBehind": 42}}"#,
+        );
+        assert_eq!(RpcHealth::from_error(&behind), RpcHealth::Behind(Some(42)));
+        let unknown_lag = error(
+            r#"{"code": -32005, "message": "Node is unhealthy", "data": {"numSlotsBehind": null}}"#,
+        );
+        assert_eq!(RpcHealth::from_error(&unknown_lag), RpcHealth::Behind(None));
+        let other = error(r#"{"code": -32601, "message": "Method not found"}"#);
+        assert_eq!(RpcHealth::from_error(&other), RpcHealth::Unknown);
+        assert_eq!(RpcHealth::Behind(Some(42)).status(), "behind");
+    }
+
+    #[tokio::test]
+    async fn test_get_health_reads_the_error() {
+        use serde_json::json;
+        use tokio::net::TcpListener;
+
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                let response = match body["params"][0].as_str() {
+                    Some("behind") => json!({
+                        "jsonrpc": "2.0",
+                        "id": 1,
+                        "error": {
+                            "code": NODE_UNHEALTHY_CODE,
+                            "message": "Node is behind by 42 slots",
+                            "data": { "numSlotsBehind": 42 }
+                        }
+                    }),
+                    _ => json!({ "jsonrpc": "2.0", "id": 1, "result": "ok" }),
+                };
+                axum::Json(response)
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let rpc = HttpRpcClient::new(&format!("http://{}", addr)).unwrap();
+
+        assert_eq!(rpc.get_health().await.unwrap(), RpcHealth::Ok);
+        // The error keeps its message and carries the code for callers
+        let error = rpc
+            .call::<String>("getHealth", serde_json::json!(["behind"]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "RPC error -32005 from getHealth: Node is behind by 42 slots"
+        );
+        let answered = error.downcast_ref::<RpcCallError>().unwrap();
+        assert_eq!(
+            RpcHealth::from_error(&answered.error),
+            RpcHealth::Behind(Some(42))
+        );
+    }
+
+    /// Implements only the methods every client must have
+    struct MinimalRpc;
+
+    impl RpcClient for MinimalRpc {
+        async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+            Ok(RpcVoteAccounts::default())
+        }
+
+        async fn get_version(&self) -> Result<RpcVersion> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_slot(&self) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_optional_methods_are_unsupported_by_default() {
+        let rpc = MinimalRpc;
+        let error = rpc.get_blocks(1, 2).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "getBlocks is not supported by this RPC client"
+        );
+        assert!(rpc.get_health().await.is_err());
+        assert!(rpc.get_leader_schedule(0, "Node111").await.is_err());
+        assert!(
+            rpc.get_signatures_for_address("Vote111", None, 1)
+                .await
+                .is_err()
+        );
+        assert!(
+            rpc.get_minimum_balance_for_rent_exemption(3762)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_sizes_and_shape_errors() {
+        use serde_json::json;
+        use tokio::net::TcpListener;
+
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                let result = match body["method"].as_str().unwrap() {
+                    "getSlot" => json!(350_784_123),
+                    _ => json!({ "unexpected": "x".repeat(300) }),
+                };
+                axum::Json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let metrics = Metrics::new().unwrap();
+        let rpc = HttpRpcClient::new(&format!("http://{}", addr))
+            .unwrap()
+            .with_response_sizes(&metrics, 64);
+
+        assert_eq!(rpc.get_slot().await.unwrap(), 350_784_123);
+        let slot_bytes = r#"{"id":1,"jsonrpc":"2.0","result":350784123}"#.len();
+        let histogram = metrics.rpc_response_bytes.with_label_values(&["getSlot"]);
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(histogram.get_sample_sum(), slot_bytes as f64);
+
+        // The parse error quotes the start of the body
+        let error = rpc.get_version().await.unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains(r#"Response: {"id":1"#), "{}", message);
+        assert!(message.contains("xxx..."), "{}", message);
+        let version_bytes = metrics
+            .rpc_response_bytes
+            .with_label_values(&["getVersion"])
+            .get_sample_sum() as u64;
+        assert!(version_bytes > 300);
+        assert_eq!(
+            metrics.rpc_received_bytes.get(),
+            slot_bytes as u64 + version_bytes
+        );
+    }
+
+    #[test]
+    fn test_body_excerpt() {
+        assert_eq!(body_excerpt(b"<html>502</html>"), "<html>502</html>");
+        let long = vec![b'a'; 1000];
+        assert_eq!(
+            body_excerpt(&long),
+            format!("{}... (1000 bytes)", "a".repeat(BODY_EXCERPT_BYTES))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::metrics::Metrics;
+use crate::rpc::{RpcClient, RpcHealth};
+use crate::throttled_warn;
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often `getHealth` is asked
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The health the RPC node last reported, shared with the poller so that it
+/// can tell fresh data from a lagging node's
+#[derive(Debug, Default)]
+pub struct RpcHealthWatch {
+    last: Mutex<Option<RpcHealth>>,
+}
+
+impl RpcHealthWatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last health seen, None before the first check
+    pub fn current(&self) -> Option<RpcHealth> {
+        *self.last.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the node last said it is behind the cluster
+    pub fn is_behind(&self) -> bool {
+        matches!(self.current(), Some(RpcHealth::Behind(_)))
+    }
+
+    /// Export `health` as `rpc_health` and `rpc_health_behind_slots` and
+    /// keep it. Returns the health seen before.
+    pub fn observe(&self, health: RpcHealth, metrics: &Metrics) -> Option<RpcHealth> {
+        for status in RpcHealth::STATUSES {
+            metrics
+                .rpc_health
+                .with_label_values(&[status])
+                .set((status == health.status()) as i64);
+        }
+        match health {
+            RpcHealth::Ok => metrics.rpc_health_behind_slots.set(0),
+            RpcHealth::Behind(Some(slots)) => metrics.rpc_health_behind_slots.set(slots as i64),
+            RpcHealth::Behind(None) | RpcHealth::Unknown => {}
+        }
+
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        last.replace(health)
+    }
+}
+
+/// Ask the node for its health and record it in `watch`. A failed call
+/// counts as unknown.
+pub async fn check_health<R: RpcClient>(
+    rpc: &R,
+    watch: &RpcHealthWatch,
+    metrics: &Metrics,
+) -> RpcHealth {
+    let health = match rpc.get_health().await {
+        Ok(health) => health,
+        Err(e) => {
+            throttled_warn!("getHealth failed: {:#}", e);
+            RpcHealth::Unknown
+        }
+    };
+
+    let previous = watch.observe(health, metrics);
+    if previous.map(|p| p.status()) != Some(health.status()) {
+        match health {
+            RpcHealth::Ok => info!("RPC node is healthy"),
+            RpcHealth::Behind(Some(slots)) => warn!(
+                "RPC node is {} slots behind the cluster; polled data is not counted as fresh",
+                slots
+            ),
+            RpcHealth::Behind(None) => {
+                warn!("RPC node is behind the cluster; polled data is not counted as fresh")
+            }
+            RpcHealth::Unknown => warn!("RPC node health is unknown"),
+        }
+    }
+    health
+}
+
+/// Check the node's health every [`HEALTH_CHECK_INTERVAL`]
+pub async fn run_health_check<R: RpcClient>(rpc: &R, watch: &RpcHealthWatch, metrics: &Metrics) {
+    loop {
+        check_health(rpc, watch, metrics).await;
+        tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poller::tests::MutableTestRpc;
+
+    #[tokio::test]
+    async fn test_transitions_between_ok_and_behind() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = MutableTestRpc::new(vec![]);
+        let watch = RpcHealthWatch::new();
+        let status = |status: &str| metrics.rpc_health.with_label_values(&[status]).get();
+        assert_eq!(watch.current(), None);
+
+        assert_eq!(check_health(&rpc, &watch, &metrics).await, RpcHealth::Ok);
+        assert!(!watch.is_behind());
+        assert_eq!(
+            (status("ok"), status("behind"), status("unknown")),
+            (1, 0, 0)
+        );
+
+        rpc.set_health(Some(RpcHealth::Behind(Some(150))));
+        check_health(&rpc, &watch, &metrics).await;
+        assert!(watch.is_behind());
+        assert_eq!(
+            (status("ok"), status("behind"), status("unknown")),
+            (0, 1, 0)
+        );
+        assert_eq!(metrics.rpc_health_behind_slots.get(), 150);
+
+        // Without a number the last one is kept
+        rpc.set_health(Some(RpcHealth::Behind(None)));
+        check_health(&rpc, &watch, &metrics).await;
+        assert!(watch.is_behind());
+        assert_eq!(metrics.rpc_health_behind_slots.get(), 150);
+
+        rpc.set_health(Some(RpcHealth::Ok));
+        check_health(&rpc, &watch, &metrics).await;
+        assert!(!watch.is_behind());
+        assert_eq!(
+            (status("ok"), status("behind"), status("unknown")),
+            (1, 0, 0)
+        );
+        assert_eq!(metrics.rpc_health_behind_slots.get(), 0);
+
+        // An unreachable node is neither healthy nor known to be behind
+        rpc.set_health(None);
+        assert_eq!(
+            check_health(&rpc, &watch, &metrics).await,
+            RpcHealth::Unknown
+        );
+        assert!(!watch.is_behind());
+        assert_eq!(
+            (status("ok"), status("behind"), status("unknown")),
+            (0, 0, 1)
+        );
+    }
+}
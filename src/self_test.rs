@@ -0,0 +1,438 @@
+use crate::clock::ManualClock;
+use crate::credits::CreditsModel;
+use crate::metrics::Metrics;
+use crate::poller::{PollState, poll_once};
+use crate::rpc::{RpcClient, RpcSignature, RpcVersion, RpcVoteAccounts};
+#[cfg(feature = "ws")]
+use crate::ws::VoteTracker;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// WebSocket session: the subscription confirmation, then account notifications
+#[cfg(feature = "ws")]
+const WS_SESSION: &str = include_str!("../fixtures/self_test/ws_session.jsonl");
+
+/// Responses to the RPC calls of successive polls
+const POLL_RESPONSES: &str = include_str!("../fixtures/self_test/poll_responses.jsonl");
+
+/// Families each path has to export, one per line under `## ws` / `## poll`
+const EXPECTED_FAMILIES: &str = include_str!("../fixtures/self_test/families.txt");
+
+/// Vote account the poll fixtures are recorded for
+const VOTE_PUBKEY: &str = "Vote1111111111111111111111111111111111111111";
+
+/// Outcome of one self-test check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed,
+    /// What went wrong, one line each
+    Failed(Vec<String>),
+    Skipped(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub outcome: CheckOutcome,
+}
+
+impl SelfTestCheck {
+    fn new(name: &'static str, failures: Vec<String>) -> Self {
+        let outcome = if failures.is_empty() {
+            CheckOutcome::Passed
+        } else {
+            CheckOutcome::Failed(failures)
+        };
+        Self { name, outcome }
+    }
+}
+
+/// Result of `tvc_tracker self-test`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn failed(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| matches!(check.outcome, CheckOutcome::Failed(_)))
+            .count()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// One line of the WebSocket session fixture
+#[cfg(feature = "ws")]
+#[derive(Debug, Deserialize)]
+struct SessionMessage {
+    received_ms: u64,
+    message: Value,
+}
+
+/// One poll of the poll fixture: the JSON-RPC response per method
+#[derive(Debug, Deserialize)]
+struct RecordedPoll {
+    received_ms: u64,
+    responses: BTreeMap<String, Value>,
+}
+
+/// Feed the bundled fixtures through the notification parsing, the tracker,
+/// the poller and the metrics, and check the results. Runs offline.
+pub async fn run_self_test() -> Result<SelfTestReport> {
+    let expected = expected_families()?;
+    let mut report = SelfTestReport::default();
+    ws_checks(&mut report, &expected["ws"]).await?;
+    poll_checks(&mut report, &expected["poll"]).await?;
+    Ok(report)
+}
+
+/// Expected family names by section
+fn expected_families() -> Result<BTreeMap<&'static str, BTreeSet<&'static str>>> {
+    let mut sections: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut section = None;
+    for line in EXPECTED_FAMILIES.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("## ") {
+            section = Some(name);
+            sections.entry(name).or_default();
+        } else if !line.is_empty() {
+            let name = section.context("families.txt: family before the first section")?;
+            sections.entry(name).or_default().insert(line);
+        }
+    }
+    for name in ["ws", "poll"] {
+        if !sections.contains_key(name) {
+            return Err(anyhow!("families.txt: no `## {}` section", name));
+        }
+    }
+    Ok(sections)
+}
+
+/// Families in `expected` that `metrics` does not export
+fn missing_families(metrics: &Metrics, expected: &BTreeSet<&str>) -> Vec<String> {
+    let exported: BTreeSet<String> = metrics
+        .snapshot()
+        .families
+        .into_iter()
+        .map(|family| family.name)
+        .collect();
+    expected
+        .iter()
+        .filter(|name| !exported.contains(**name))
+        .map(|name| format!("- {} (expected, not exported)", name))
+        .collect()
+}
+
+/// The tracker's accounting after an update: histogram credits plus missed
+/// credits within the expected maximum, and the 5m, 1h and epoch windows
+/// each holding at least what the narrower one holds
+#[cfg(feature = "ws")]
+fn tracker_inconsistencies(tracker: &VoteTracker, update: usize) -> (Vec<String>, Vec<String>) {
+    let mut epoch = Vec::new();
+    let (credits, missed, expected, consistent) = tracker.verify_epoch_consistency();
+    if !consistent {
+        epoch.push(format!(
+            "notification {}: credits {} + missed {} exceed the expected {}",
+            update, credits, missed, expected
+        ));
+    }
+
+    let mut windows = Vec::new();
+    let views = [
+        (
+            "5m",
+            tracker.window_histogram(300),
+            tracker.window_missed(300),
+        ),
+        (
+            "1h",
+            tracker.window_histogram(3600),
+            tracker.window_missed(3600),
+        ),
+        ("epoch", tracker.epoch_histogram(), missed),
+    ];
+    for pair in views.windows(2) {
+        let (narrow, narrow_hist, narrow_missed) = &pair[0];
+        let (wide, wide_hist, wide_missed) = &pair[1];
+        for (what, narrow_value, wide_value) in [
+            (
+                "votes",
+                VoteTracker::histogram_total(narrow_hist),
+                VoteTracker::histogram_total(wide_hist),
+            ),
+            (
+                "credits",
+                VoteTracker::histogram_credits(narrow_hist),
+                VoteTracker::histogram_credits(wide_hist),
+            ),
+            ("missed credits", *narrow_missed, *wide_missed),
+        ] {
+            if narrow_value > wide_value {
+                windows.push(format!(
+                    "notification {}: {} {} {} > {} {}",
+                    update, narrow, what, narrow_value, wide, wide_value
+                ));
+            }
+        }
+    }
+    (epoch, windows)
+}
+
+#[cfg(feature = "ws")]
+async fn ws_checks(report: &mut SelfTestReport, expected: &BTreeSet<&str>) -> Result<()> {
+    use crate::ws::{ForkPressureClassifier, SubscriptionOptions, WsMessage, process_notification};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    let session: Vec<SessionMessage> = parse_lines(WS_SESSION, "ws_session.jsonl")?;
+    let start = session.first().map_or(0, |m| m.received_ms);
+    let clock = Arc::new(ManualClock::new(start));
+    let tracker = Arc::new(RwLock::new(
+        VoteTracker::new(CreditsModel::default()).with_clock(clock.clone()),
+    ));
+    let metrics = Arc::new(Metrics::new()?);
+    let mut fork_pressure = ForkPressureClassifier::default();
+    let mut last_fingerprint = None;
+    let options = SubscriptionOptions::default();
+
+    let mut parsing = Vec::new();
+    let mut processing = Vec::new();
+    let mut epoch = Vec::new();
+    let mut windows = Vec::new();
+    let mut notifications = 0;
+    for (index, recorded) in session.iter().enumerate() {
+        let line = index + 1;
+        clock.set(recorded.received_ms);
+        // Through the text, as the socket reader gets it
+        let text = recorded.message.to_string();
+        let params = match serde_json::from_str::<WsMessage>(&text) {
+            Ok(WsMessage::Notification { params, .. }) => *params,
+            Ok(_) => continue,
+            Err(e) => {
+                parsing.push(format!("line {}: {}", line, e));
+                continue;
+            }
+        };
+        notifications += 1;
+        let processed = process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut fork_pressure,
+            &mut last_fingerprint,
+            &options,
+        )
+        .await;
+        if let Err(e) = processed {
+            processing.push(format!("line {}: {:#}", line, e));
+            continue;
+        }
+        let (epoch_issues, window_issues) = tracker_inconsistencies(&*tracker.read().await, line);
+        epoch.extend(epoch_issues);
+        windows.extend(window_issues);
+    }
+    if notifications == 0 {
+        parsing.push("no notification in the session".to_string());
+    }
+
+    report
+        .checks
+        .push(SelfTestCheck::new("ws: session parses", parsing));
+    report.checks.push(SelfTestCheck::new(
+        "ws: notifications processed",
+        processing,
+    ));
+    report
+        .checks
+        .push(SelfTestCheck::new("ws: epoch consistency", epoch));
+    report
+        .checks
+        .push(SelfTestCheck::new("ws: windows nest", windows));
+    report.checks.push(SelfTestCheck::new(
+        "ws: metric families",
+        missing_families(&metrics, expected),
+    ));
+    Ok(())
+}
+
+#[cfg(not(feature = "ws"))]
+async fn ws_checks(report: &mut SelfTestReport, _expected: &BTreeSet<&str>) -> Result<()> {
+    report.checks.push(SelfTestCheck {
+        name: "ws",
+        outcome: CheckOutcome::Skipped("built without the `ws` feature"),
+    });
+    Ok(())
+}
+
+/// Answers each call with the response recorded for the current poll
+struct FixtureRpc {
+    poll: Mutex<BTreeMap<String, Value>>,
+}
+
+impl FixtureRpc {
+    fn result<T: DeserializeOwned>(&self, method: &str) -> Result<T> {
+        let poll = self.poll.lock().unwrap_or_else(|e| e.into_inner());
+        let response = poll
+            .get(method)
+            .ok_or_else(|| anyhow!("{} not recorded", method))?;
+        let result = response
+            .get("result")
+            .ok_or_else(|| anyhow!("{} response without a result", method))?;
+        serde_json::from_value(result.clone()).with_context(|| format!("{} result", method))
+    }
+}
+
+impl RpcClient for FixtureRpc {
+    async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+        self.result("getVoteAccounts")
+    }
+
+    async fn get_version(&self) -> Result<RpcVersion> {
+        self.result("getVersion")
+    }
+
+    async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+        Err(anyhow!("getAccountInfo not recorded"))
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        self.result("getSlot")
+    }
+
+    async fn get_blocks(&self, _start: u64, _end: u64) -> Result<Vec<u64>> {
+        self.result("getBlocks")
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        _address: &str,
+        _before: Option<&str>,
+        _limit: usize,
+    ) -> Result<Vec<RpcSignature>> {
+        self.result("getSignaturesForAddress")
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(&self, _data_len: u64) -> Result<u64> {
+        self.result("getMinimumBalanceForRentExemption")
+    }
+}
+
+async fn poll_checks(report: &mut SelfTestReport, expected: &BTreeSet<&str>) -> Result<()> {
+    use std::sync::Arc;
+
+    let polls: Vec<RecordedPoll> = parse_lines(POLL_RESPONSES, "poll_responses.jsonl")?;
+    let start = polls.first().map_or(0, |poll| poll.received_ms);
+    let clock = Arc::new(ManualClock::new(start));
+    let metrics = Metrics::new()?;
+    let rpc = FixtureRpc {
+        poll: Mutex::new(BTreeMap::new()),
+    };
+    let mut state = PollState::new().with_clock(clock.clone());
+
+    let mut failures = Vec::new();
+    for (index, poll) in polls.into_iter().enumerate() {
+        clock.set(poll.received_ms);
+        *rpc.poll.lock().unwrap_or_else(|e| e.into_inner()) = poll.responses;
+        if let Err(e) = poll_once(
+            &rpc,
+            VOTE_PUBKEY,
+            &mut state,
+            CreditsModel::default(),
+            &metrics,
+        )
+        .await
+        {
+            failures.push(format!("poll {}: {:#}", index + 1, e));
+        }
+    }
+
+    report
+        .checks
+        .push(SelfTestCheck::new("poll: responses applied", failures));
+    report.checks.push(SelfTestCheck::new(
+        "poll: metric families",
+        missing_families(&metrics, expected),
+    ));
+    Ok(())
+}
+
+/// Parse a JSON lines fixture
+fn parse_lines<T: DeserializeOwned>(text: &str, file: &str) -> Result<Vec<T>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| format!("{} line {}", file, index + 1))
+        })
+        .collect()
+}
+
+/// One line per check, failures indented below it
+pub fn format_self_test(report: &SelfTestReport) -> String {
+    let mut out = String::new();
+    for check in &report.checks {
+        match &check.outcome {
+            CheckOutcome::Passed => {
+                let _ = writeln!(out, "ok    {}", check.name);
+            }
+            CheckOutcome::Skipped(reason) => {
+                let _ = writeln!(out, "skip  {} ({})", check.name, reason);
+            }
+            CheckOutcome::Failed(failures) => {
+                let _ = writeln!(out, "FAIL  {}", check.name);
+                for failure in failures {
+                    let _ = writeln!(out, "        {}", failure);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bundled_fixtures_pass() {
+        let report = run_self_test().await.unwrap();
+        assert!(report.passed(), "{}", format_self_test(&report));
+        assert!(report.checks.len() >= 2);
+    }
+
+    #[test]
+    fn test_report_lists_failures() {
+        let report = SelfTestReport {
+            checks: vec![
+                SelfTestCheck::new("ws: session parses", vec![]),
+                SelfTestCheck::new(
+                    "poll: metric families",
+                    vec!["- solana_epoch (expected, not exported)".to_string()],
+                ),
+            ],
+        };
+        assert_eq!(report.failed(), 1);
+        assert_eq!(
+            format_self_test(&report),
+            "ok    ws: session parses\nFAIL  poll: metric families\n        - solana_epoch (expected, not exported)\n"
+        );
+    }
+
+    #[cfg(feature = "ws")]
+    #[test]
+    fn test_windows_nest_on_a_fresh_tracker() {
+        let (epoch, windows) = tracker_inconsistencies(&VoteTracker::default(), 1);
+        assert!(epoch.is_empty());
+        assert!(windows.is_empty());
+    }
+}
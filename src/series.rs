@@ -0,0 +1,192 @@
+use crate::clock::{SharedClock, system_clock};
+use crate::config::Mode;
+use crate::metrics::{HistogramWindow, Metrics};
+
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Samples kept per series: the last hour at one-minute resolution
+pub const SERIES_CAPACITY: usize = 60;
+
+/// How often the series are sampled
+pub const SERIES_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Series served by `GET /series`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeriesMetric {
+    /// `solana_vote_credits_efficiency_5m`
+    Efficiency,
+    /// `solana_missed_vote_credits_5m`
+    Missed,
+}
+
+/// The gauges of every series at one point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SeriesSample {
+    /// Unix seconds
+    timestamp: u64,
+    /// None while the 5m window had no data: before the first update, while
+    /// warming up, or after a cluster halt
+    efficiency: Option<f64>,
+    missed: Option<f64>,
+}
+
+/// One-minute samples of the 5m efficiency and missed credits over the last
+/// hour, for sparklines drawn without querying Prometheus
+#[derive(Debug)]
+pub struct MetricSeries {
+    samples: Mutex<VecDeque<SeriesSample>>,
+    clock: SharedClock,
+}
+
+impl Default for MetricSeries {
+    fn default() -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::with_capacity(SERIES_CAPACITY)),
+            clock: system_clock(),
+        }
+    }
+}
+
+impl MetricSeries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `clock` for the timestamps instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sample the gauges, dropping the oldest sample once
+    /// [`SERIES_CAPACITY`] are kept
+    pub fn sample(&self, metrics: &Metrics, mode: Mode) {
+        let has_data = metrics.last_data_update(mode).is_some()
+            && metrics.warming.get() == 0
+            && metrics.has_window_data(HistogramWindow::FiveMinutes);
+        let sample = SeriesSample {
+            timestamp: self.clock.now_millis() / 1000,
+            efficiency: has_data.then(|| metrics.vote_credits_efficiency_5m.get()),
+            missed: has_data.then(|| metrics.missed_5m.get() as f64),
+        };
+
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() == SERIES_CAPACITY {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// The newest `points` samples of `metric` as (unix seconds, value)
+    /// pairs, oldest first; all of them while fewer were taken
+    pub fn points(&self, metric: SeriesMetric, points: usize) -> Vec<(u64, Option<f64>)> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let skip = samples.len().saturating_sub(points);
+        samples
+            .iter()
+            .skip(skip)
+            .map(|sample| {
+                let value = match metric {
+                    SeriesMetric::Efficiency => sample.efficiency,
+                    SeriesMetric::Missed => sample.missed,
+                };
+                (sample.timestamp, value)
+            })
+            .collect()
+    }
+}
+
+/// Sample the series every [`SERIES_INTERVAL`], whether or not anything
+/// scrapes `/metrics`
+pub async fn run_series_sampler(series: &MetricSeries, metrics: &Metrics, mode: Mode) {
+    let mut ticker = tokio::time::interval(SERIES_INTERVAL);
+    loop {
+        ticker.tick().await;
+        series.sample(metrics, mode);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    const NOW: u64 = 1_700_000_000;
+
+    /// Metrics with data in the 5m window since `NOW`
+    fn metrics_with_data() -> Metrics {
+        let metrics = Metrics::new().unwrap();
+        metrics.ws_last_message.set(NOW as i64);
+        metrics
+    }
+
+    #[test]
+    fn test_ring_keeps_the_last_hour() {
+        let clock = Arc::new(ManualClock::new(NOW * 1000));
+        let series = MetricSeries::new().with_clock(clock.clone());
+        let metrics = metrics_with_data();
+
+        for minute in 0..75 {
+            metrics
+                .vote_credits_efficiency_5m
+                .set(minute as f64 / 100.0);
+            metrics.missed_5m.set(minute);
+            series.sample(&metrics, Mode::Ws);
+            clock.advance_secs(60);
+        }
+
+        // The first 15 minutes rolled out
+        let efficiency = series.points(SeriesMetric::Efficiency, SERIES_CAPACITY);
+        assert_eq!(efficiency.len(), SERIES_CAPACITY);
+        assert_eq!(efficiency[0], (NOW + 15 * 60, Some(0.15)));
+        assert_eq!(efficiency[59], (NOW + 74 * 60, Some(0.74)));
+        assert!(
+            efficiency
+                .windows(2)
+                .all(|pair| pair[1].0 - pair[0].0 == 60)
+        );
+
+        let missed = series.points(SeriesMetric::Missed, 3);
+        assert_eq!(
+            missed,
+            vec![
+                (NOW + 72 * 60, Some(72.0)),
+                (NOW + 73 * 60, Some(73.0)),
+                (NOW + 74 * 60, Some(74.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fewer_points_than_requested_while_filling() {
+        let clock = Arc::new(ManualClock::new(NOW * 1000));
+        let series = MetricSeries::new().with_clock(clock.clone());
+        assert!(series.points(SeriesMetric::Efficiency, 60).is_empty());
+
+        // No data yet: the sample is a gap
+        let metrics = Metrics::new().unwrap();
+        series.sample(&metrics, Mode::Ws);
+        clock.advance_secs(60);
+        let metrics = metrics_with_data();
+        metrics.vote_credits_efficiency_5m.set(0.98);
+        series.sample(&metrics, Mode::Ws);
+
+        assert_eq!(
+            series.points(SeriesMetric::Efficiency, 60),
+            vec![(NOW, None), (NOW + 60, Some(0.98))]
+        );
+
+        // Nor while the 5m window lacks history
+        metrics.set_window_coverage(HistogramWindow::FiveMinutes, 0.0);
+        series.sample(&metrics, Mode::Ws);
+        assert_eq!(
+            series.points(SeriesMetric::Missed, 1),
+            vec![(NOW + 60, None)]
+        );
+    }
+}
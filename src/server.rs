@@ -0,0 +1,1647 @@
+use crate::api::{SCHEMA_VERSION, StatusResponse};
+use crate::clock::unix_now;
+use crate::config::{Mode, PortConflictPolicy};
+use crate::debug_dump::DebugState;
+use crate::discontinuity::{
+    DISCONTINUITIES_CAPACITY, Discontinuities, Discontinuity, DiscontinuityEvent,
+};
+use crate::maintenance::{Maintenance, MaintenanceWindow};
+use crate::metrics::{CatalogEntry, Metrics, alias_map};
+use crate::poller::{AccountSnapshot, snapshot_from_vote_account};
+use crate::report::{SummarySnapshot, format_summary};
+use crate::rpc::RpcClient;
+use crate::series::{MetricSeries, SERIES_CAPACITY, SeriesMetric};
+use crate::snapshot::{self, SNAPSHOT_CONTENT_TYPE};
+use crate::ws::{
+    PriorVoter, PriorVotersWatch, RECENT_SLOTS_CAPACITY, SlotRecord, TrackerBaseline, VoteTracker,
+    WsConnectionClock, read_tracker, write_tracker,
+};
+
+use anyhow::{Context, Result, anyhow};
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, MatchedPath, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::{Notify, RwLock};
+use tracing::{info, warn};
+
+/// Shared state of the HTTP server
+pub struct AppState<R> {
+    pub metrics: Arc<Metrics>,
+    pub tracker: Arc<RwLock<VoteTracker>>,
+    pub rpc: Arc<R>,
+    pub vote_pubkey: String,
+    /// Data source, deciding which timestamp marks the last data update
+    pub mode: Mode,
+    /// Age of the last data update beyond which `/metrics` fails with 503
+    pub metrics_max_staleness: Option<Duration>,
+    /// WebSocket connection timing (never connected in poll mode)
+    pub ws_connection: Arc<WsConnectionClock>,
+    /// Latest raw RPC payloads for `POST /debug/dump`
+    pub debug: Arc<DebugState>,
+    /// Bearer token required by the admin endpoints
+    pub admin_token: Option<String>,
+    /// Planned maintenance toggled by `POST /admin/maintenance`
+    pub maintenance: Arc<Maintenance>,
+    /// Epoch rollovers, reconnects, resets and the like for `GET /discontinuities`
+    pub discontinuities: Arc<Discontinuities>,
+    /// One-minute samples for `GET /series`
+    pub series: Arc<MetricSeries>,
+    /// Prior authorized voters for `GET /debug/prior_voters`
+    pub prior_voters: Arc<PriorVotersWatch>,
+    pub service_discovery: ServiceDiscovery,
+}
+
+/// What `/sd` advertises for this instance
+#[derive(Debug, Clone, Default)]
+pub struct ServiceDiscovery {
+    /// host:port to scrape (the request's Host header if unset)
+    pub advertise_addr: Option<String>,
+    pub labels: BTreeMap<String, String>,
+}
+
+impl ServiceDiscovery {
+    /// Labels: `vote_pubkey` plus `cluster` and `instance_name` when configured
+    pub fn new(
+        advertise_addr: Option<String>,
+        vote_pubkey: &str,
+        cluster: Option<&str>,
+        instance_name: Option<&str>,
+    ) -> Self {
+        let mut labels = BTreeMap::new();
+        labels.insert("vote_pubkey".to_string(), vote_pubkey.to_string());
+        if let Some(cluster) = cluster {
+            labels.insert("cluster".to_string(), cluster.to_string());
+        }
+        if let Some(instance_name) = instance_name {
+            labels.insert("instance_name".to_string(), instance_name.to_string());
+        }
+        Self {
+            advertise_addr,
+            labels,
+        }
+    }
+}
+
+/// Prometheus HTTP SD target group
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SdTargetGroup {
+    pub targets: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Response of `/healthz`
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    /// Seconds since Prometheus last scraped `/metrics` (None if never)
+    last_scraped_seconds_ago: Option<u64>,
+}
+
+/// Response of the admin endpoints
+#[derive(Debug, Serialize)]
+struct AdminResponse {
+    action: &'static str,
+    requested_by: String,
+    /// Whether the tracker state was changed
+    applied: bool,
+    old: TrackerBaseline,
+    new: TrackerBaseline,
+}
+
+/// Body of `POST /admin/maintenance`
+#[derive(Debug, Deserialize)]
+struct MaintenanceRequest {
+    /// Length of the window; 0 ends the current one
+    duration_secs: u64,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Response of `POST /admin/maintenance`
+#[derive(Debug, Serialize)]
+struct MaintenanceResponse {
+    requested_by: String,
+    /// Window in effect after the request (None if it was ended)
+    maintenance: Option<MaintenanceWindow>,
+}
+
+/// Response of `POST /debug/dump`
+#[derive(Debug, Serialize)]
+struct DumpResponse {
+    requested_by: String,
+    /// Files written, empty if no payload was received yet
+    files: Vec<String>,
+}
+
+/// Rooted slots returned by `GET /debug/slots` without a `limit`
+const DEFAULT_RECENT_SLOTS_LIMIT: usize = 256;
+
+/// Query of `GET /debug/slots`
+#[derive(Debug, Deserialize)]
+struct RecentSlotsQuery {
+    limit: Option<usize>,
+}
+
+/// Query of `GET /series`
+#[derive(Debug, Deserialize)]
+struct SeriesQuery {
+    metric: SeriesMetric,
+    /// Samples to return, at most [`SERIES_CAPACITY`] (the default)
+    points: Option<usize>,
+}
+
+/// Response of `GET /debug/slots`
+#[derive(Debug, Serialize)]
+struct RecentSlotsResponse {
+    /// Rooted slots kept at most
+    capacity: usize,
+    /// Newest first
+    slots: Vec<SlotRecord>,
+}
+
+/// Response of `GET /debug/prior_voters`
+#[derive(Debug, Serialize)]
+struct PriorVotersResponse {
+    entries: usize,
+    /// Oldest first
+    prior_voters: Vec<PriorVoter>,
+}
+
+/// Response of `GET /discontinuities`
+#[derive(Debug, Serialize)]
+struct DiscontinuitiesResponse {
+    /// Discontinuities kept at most
+    capacity: usize,
+    /// Oldest first
+    discontinuities: Vec<Discontinuity>,
+}
+
+/// Build the HTTP router: `/metrics`, `/metrics/aliases`, `/metrics/catalog`, `/healthz`, `/status`, `/discontinuities`, `/summary.txt`, `/snapshot.bin`, `/sd`, plus the admin
+/// (including `/admin/maintenance`) and debug endpoints (`/debug/dump`, `/debug/slots`) with `--debug-endpoints`. Every route is counted by [`track_requests`].
+pub fn router<R: RpcClient + 'static>(
+    state: Arc<AppState<R>>,
+    debug_endpoints: bool,
+) -> axum::Router {
+    let mut app = axum::Router::new()
+        .route("/metrics", get(metrics::<R>))
+        .route("/metrics/aliases", get(metric_aliases))
+        .route("/metrics/catalog", get(metric_catalog::<R>))
+        .route("/healthz", get(healthz::<R>))
+        .route("/status", get(status::<R>))
+        .route("/discontinuities", get(discontinuities::<R>))
+        .route("/summary.txt", get(summary_txt::<R>))
+        .route("/snapshot.bin", get(snapshot_bin::<R>))
+        .route("/series", get(series::<R>))
+        .route("/sd", get(service_discovery::<R>));
+
+    if debug_endpoints {
+        app = app
+            .route("/admin/reset", post(admin_reset::<R>))
+            .route("/admin/reconcile", post(admin_reconcile::<R>))
+            .route("/admin/maintenance", post(admin_maintenance::<R>))
+            .route("/debug/dump", post(debug_dump::<R>))
+            .route("/debug/slots", get(debug_slots::<R>))
+            .route("/debug/prior_voters", get(debug_prior_voters::<R>));
+    }
+
+    app.layer(middleware::from_fn_with_state(
+        state.metrics.clone(),
+        track_requests,
+    ))
+    .with_state(state)
+}
+
+/// Path a new instance under [`PortConflictPolicy::Takeover`] posts to once
+/// it bound the metrics port, to have the previous instance stop accepting
+pub const TAKEOVER_PATH: &str = "/takeover";
+
+/// How long a new instance waits for the previous one to hand the port over
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Signalled when a new instance took the metrics port over
+#[derive(Debug, Default)]
+pub struct PortHandover {
+    notify: Notify,
+}
+
+impl PortHandover {
+    /// Resolves once a new instance asked for the port; pass it to the
+    /// server's graceful shutdown
+    pub async fn requested(self: Arc<Self>) {
+        self.notify.notified().await
+    }
+}
+
+/// The [`TAKEOVER_PATH`] route, served under [`PortConflictPolicy::Takeover`]
+/// alongside [`router`]. Only loopback peers may take the port over.
+pub fn takeover_router(handover: Arc<PortHandover>) -> axum::Router {
+    axum::Router::new()
+        .route(TAKEOVER_PATH, post(takeover))
+        .with_state(handover)
+}
+
+async fn takeover(
+    State(handover): State<Arc<PortHandover>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+) -> StatusCode {
+    if !peer.ip().is_loopback() {
+        return StatusCode::FORBIDDEN;
+    }
+    info!("A new instance took the metrics port over, no longer accepting on it");
+    handover.notify.notify_one();
+    StatusCode::OK
+}
+
+/// First pause between binds under [`PortConflictPolicy::Wait`], doubled
+/// after each attempt up to [`BIND_RETRY_MAX`]
+const BIND_RETRY_INITIAL: Duration = Duration::from_millis(100);
+const BIND_RETRY_MAX: Duration = Duration::from_secs(2);
+
+/// Bind the metrics server on all interfaces. Port 0 binds a free port,
+/// which the listener's `local_addr` reports. A port already in use is
+/// handled by `policy`; `wait` is how long [`PortConflictPolicy::Wait`]
+/// retries.
+pub async fn bind(port: u16, policy: PortConflictPolicy, wait: Duration) -> Result<TcpListener> {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+    if policy == PortConflictPolicy::Takeover {
+        return take_over(addr).await;
+    }
+    let deadline = Instant::now() + wait;
+    let mut backoff = BIND_RETRY_INITIAL;
+    loop {
+        let e = match TcpListener::bind(addr).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => e,
+        };
+        if e.kind() != std::io::ErrorKind::AddrInUse {
+            return Err(e)
+                .with_context(|| format!("Failed to bind the metrics server to {}", addr));
+        }
+
+        let now = Instant::now();
+        match policy {
+            PortConflictPolicy::Wait if now < deadline => {
+                warn!(
+                    "Port {} is in use, retrying the bind in {:?}",
+                    port, backoff
+                );
+                tokio::time::sleep(backoff.min(deadline - now)).await;
+                backoff = (backoff * 2).min(BIND_RETRY_MAX);
+            }
+            PortConflictPolicy::Wait => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Port {} is still in use after {:?}; is a previous instance still running?",
+                        port, wait
+                    )
+                });
+            }
+            PortConflictPolicy::Exit | PortConflictPolicy::Takeover => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Port {} is already in use; is a previous instance still running? \
+                         Stop it, or pass --port-conflict-policy wait or takeover",
+                        port
+                    )
+                });
+            }
+        }
+    }
+}
+
+/// Bind `addr` with SO_REUSEADDR and, on Unix, SO_REUSEPORT, then ask the
+/// instance already listening on it (if any) to stop accepting before
+/// listening. The socket doesn't take connections until it listens, so the
+/// request reaches the previous instance.
+async fn take_over(addr: SocketAddr) -> Result<TcpListener> {
+    let socket = TcpSocket::new_v4()?;
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    socket.set_reuseport(true)?;
+    socket.bind(addr).with_context(|| {
+        format!(
+            "Failed to bind the metrics server to {}; an instance holding the port \
+             can only be taken over if it runs with --port-conflict-policy takeover too",
+            addr
+        )
+    })?;
+
+    let port = socket.local_addr()?.port();
+    if addr.port() != 0 {
+        let url = format!("http://127.0.0.1:{}{}", port, TAKEOVER_PATH);
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(TAKEOVER_TIMEOUT)
+            .build()?;
+        match client.post(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                info!("Took port {} over from the previous instance", port)
+            }
+            Ok(response) => anyhow::bail!(
+                "The instance on port {} refused the takeover: {}",
+                port,
+                response.status()
+            ),
+            // Nothing listening: no previous instance
+            Err(e) if e.is_connect() => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to take port {} over", port));
+            }
+        }
+    }
+    Ok(socket.listen(1024)?)
+}
+
+/// Count requests and their latency per route, and record successful scrapes
+async fn track_requests(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Label by route template so unknown paths can't blow up cardinality
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status();
+
+    metrics
+        .http_requests
+        .with_label_values(&[&path, status.as_str()])
+        .inc();
+    metrics
+        .http_request_duration
+        .with_label_values(&[&path])
+        .observe(start.elapsed().as_secs_f64());
+    if path == "/metrics" && status == StatusCode::OK {
+        metrics.metrics_last_scraped.set(unix_now() as i64);
+    }
+    response
+}
+
+/// `GET /metrics`: the Prometheus exposition. 503 when the last data update is
+/// older than `--metrics-max-staleness-secs`, so the target shows as down
+/// rather than serving frozen gauges.
+async fn metrics<R>(State(state): State<Arc<AppState<R>>>) -> Response {
+    if let Some(max_staleness) = state.metrics_max_staleness {
+        let last_update = state.metrics.last_data_update(state.mode);
+        if let Some(reason) = staleness(last_update, unix_now(), max_staleness) {
+            return (StatusCode::SERVICE_UNAVAILABLE, reason).into_response();
+        }
+    }
+    state.maintenance.export(&state.metrics);
+    let (content_type, body) = state.metrics.render();
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// `GET /metrics/catalog`: every registered family with its source (observed,
+/// derived or exporter), help text and unit
+async fn metric_catalog<R>(State(state): State<Arc<AppState<R>>>) -> Json<Vec<CatalogEntry>> {
+    Json(state.metrics.catalog().to_vec())
+}
+
+/// `GET /metrics/aliases`: deprecated metric names mapped to their current names
+async fn metric_aliases() -> Json<BTreeMap<&'static str, &'static str>> {
+    Json(alias_map())
+}
+
+/// Why data last updated at `last_update` is too old to serve at `now` (unix
+/// seconds), None if it is fresh
+fn staleness(last_update: Option<u64>, now: u64, max_staleness: Duration) -> Option<String> {
+    let Some(last_update) = last_update else {
+        return Some("No data received yet\n".to_string());
+    };
+    let age = now.saturating_sub(last_update);
+    (age > max_staleness.as_secs()).then(|| {
+        format!(
+            "Data is stale: last update {}s ago, limit {}s\n",
+            age,
+            max_staleness.as_secs()
+        )
+    })
+}
+
+/// `GET /healthz`: liveness plus when Prometheus last scraped us.
+/// 503 while the WebSocket subscription keeps getting rejected.
+async fn healthz<R>(State(state): State<Arc<AppState<R>>>) -> Response {
+    let last_scraped = state.metrics.metrics_last_scraped.get() as u64;
+    let subscription_failed = state.metrics.ws_subscription_failed.get() == 1;
+    let (code, status) = if subscription_failed {
+        (StatusCode::SERVICE_UNAVAILABLE, "subscription_failed")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    let body = HealthResponse {
+        status,
+        last_scraped_seconds_ago: (last_scraped > 0)
+            .then(|| unix_now().saturating_sub(last_scraped)),
+    };
+    (code, Json(body)).into_response()
+}
+
+/// `GET /status`: tracker baseline and connection state
+async fn status<R>(State(state): State<Arc<AppState<R>>>) -> Json<StatusResponse> {
+    let (tracker, tracker_state_bytes, unobserved) = {
+        let tracker = read_tracker(&state.tracker, &state.metrics).await;
+        (
+            tracker.baseline(),
+            tracker.memory_footprint(),
+            tracker.unobserved(),
+        )
+    };
+    Json(StatusResponse {
+        schema_version: SCHEMA_VERSION,
+        vote_pubkey: state.vote_pubkey.clone(),
+        tracker,
+        tracker_state_bytes,
+        unobserved,
+        ws_connection_age_seconds: state
+            .ws_connection
+            .connection_age()
+            .map(|age| age.as_secs_f64()),
+    })
+}
+
+/// `GET /discontinuities`: the state changes that break the continuity of
+/// the numbers since startup
+async fn discontinuities<R>(
+    State(state): State<Arc<AppState<R>>>,
+) -> Json<DiscontinuitiesResponse> {
+    Json(DiscontinuitiesResponse {
+        capacity: DISCONTINUITIES_CAPACITY,
+        discontinuities: state.discontinuities.recent(),
+    })
+}
+
+/// `GET /summary.txt`: one line of the main gauges for status bars, without
+/// taking the tracker lock
+async fn summary_txt<R>(State(state): State<Arc<AppState<R>>>) -> Response {
+    let summary = SummarySnapshot::sample(
+        &state.metrics,
+        state.mode,
+        state.ws_connection.connection_age(),
+    );
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        format!("{}\n", format_summary(&summary)),
+    )
+        .into_response()
+}
+
+/// `GET /snapshot.bin`: the tracker snapshot in the compact binary form of
+/// [`snapshot::encode`]
+async fn snapshot_bin<R>(State(state): State<Arc<AppState<R>>>) -> Response {
+    let snapshot = read_tracker(&state.tracker, &state.metrics)
+        .await
+        .snapshot();
+    (
+        [(header::CONTENT_TYPE, SNAPSHOT_CONTENT_TYPE)],
+        snapshot::encode(&snapshot),
+    )
+        .into_response()
+}
+
+/// `GET /series?metric=efficiency&points=60`: the newest `points` one-minute
+/// samples of `metric` as `[unix seconds, value]` pairs, oldest first, the
+/// value null where the window had no data. An unknown metric is rejected
+/// by the query extractor.
+async fn series<R>(
+    State(state): State<Arc<AppState<R>>>,
+    Query(query): Query<SeriesQuery>,
+) -> Response {
+    let points = query.points.unwrap_or(SERIES_CAPACITY);
+    if !(1..=SERIES_CAPACITY).contains(&points) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("points must be between 1 and {}", SERIES_CAPACITY),
+        )
+            .into_response();
+    }
+    Json(state.series.points(query.metric, points)).into_response()
+}
+
+/// `GET /sd`: Prometheus HTTP service discovery for this instance
+async fn service_discovery<R>(
+    State(state): State<Arc<AppState<R>>>,
+    headers: HeaderMap,
+) -> Json<Vec<SdTargetGroup>> {
+    let sd = &state.service_discovery;
+    let target = sd
+        .advertise_addr
+        .clone()
+        .or_else(|| {
+            headers
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "localhost".to_string());
+
+    Json(vec![SdTargetGroup {
+        targets: vec![target],
+        labels: sd.labels.clone(),
+    }])
+}
+
+/// Check the `Authorization: Bearer <token>` header against the admin token,
+/// in constant time so response timing doesn't reveal a matching prefix
+fn is_authorized(headers: &HeaderMap, admin_token: Option<&str>) -> bool {
+    let Some(token) = admin_token else {
+        return false;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| provided.as_bytes().ct_eq(token.as_bytes()).into())
+}
+
+/// Fetch the tracked vote account over HTTP
+async fn fetch_snapshot<R: RpcClient>(state: &AppState<R>) -> Result<AccountSnapshot> {
+    let accounts = state
+        .rpc
+        .get_vote_accounts(Some(&state.vote_pubkey))
+        .await?;
+    let (account, delinquent) = accounts
+        .find(&state.vote_pubkey)
+        .ok_or_else(|| anyhow!("Vote account {} not found", state.vote_pubkey))?;
+    let credits_model = read_tracker(&state.tracker, &state.metrics)
+        .await
+        .credits_model();
+    Ok(snapshot_from_vote_account(
+        account,
+        delinquent,
+        credits_model,
+    ))
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// `POST /admin/reset`: clear the tracker and re-seed it from a fresh fetch
+async fn admin_reset<R: RpcClient>(
+    State(state): State<Arc<AppState<R>>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, state.admin_token.as_deref()) {
+        warn!("Unauthorized /admin/reset from {}", remote);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let snapshot = match fetch_snapshot(&state).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return error_response(StatusCode::BAD_GATEWAY, format!("{:#}", e)),
+    };
+
+    let mut tracker = write_tracker(&state.tracker, &state.metrics).await;
+    let old = tracker.baseline();
+    tracker.reset(
+        snapshot.epoch_info.epoch,
+        snapshot.root_slot,
+        snapshot.credits_this_epoch,
+    );
+    let new = tracker.baseline();
+    info!(
+        "Tracker reset requested by {}: {:?} -> {:?}",
+        remote, old, new
+    );
+    state.discontinuities.record_at(
+        &old,
+        DiscontinuityEvent::Reset {
+            before: old,
+            after: new,
+        },
+    );
+
+    Json(AdminResponse {
+        action: "reset",
+        requested_by: remote.to_string(),
+        applied: true,
+        old,
+        new,
+    })
+    .into_response()
+}
+
+/// `POST /admin/reconcile`: align epoch credits and misses with a fresh fetch now
+async fn admin_reconcile<R: RpcClient>(
+    State(state): State<Arc<AppState<R>>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, state.admin_token.as_deref()) {
+        warn!("Unauthorized /admin/reconcile from {}", remote);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let snapshot = match fetch_snapshot(&state).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return error_response(StatusCode::BAD_GATEWAY, format!("{:#}", e)),
+    };
+
+    let mut tracker = write_tracker(&state.tracker, &state.metrics).await;
+    let old = tracker.baseline();
+    let applied = tracker.reconcile(
+        snapshot.epoch_info.epoch,
+        snapshot.root_slot,
+        snapshot.credits_this_epoch,
+    );
+    let new = tracker.baseline();
+    info!(
+        "Reconcile requested by {} (applied: {}): {:?} -> {:?}",
+        remote, applied, old, new
+    );
+    if applied {
+        state.discontinuities.record_at(
+            &old,
+            DiscontinuityEvent::Reseed {
+                before: old,
+                after: new,
+            },
+        );
+    }
+
+    Json(AdminResponse {
+        action: "reconcile",
+        requested_by: remote.to_string(),
+        applied,
+        old,
+        new,
+    })
+    .into_response()
+}
+
+/// `POST /admin/maintenance`: start a maintenance window, during which
+/// missed credits are counted apart from the totals, or end it early with a
+/// zero duration
+async fn admin_maintenance<R>(
+    State(state): State<Arc<AppState<R>>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !is_authorized(&headers, state.admin_token.as_deref()) {
+        warn!("Unauthorized /admin/maintenance from {}", remote);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let request: MaintenanceRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    info!(
+        "Maintenance of {}s requested by {}",
+        request.duration_secs, remote
+    );
+    let maintenance = state
+        .maintenance
+        .start(Duration::from_secs(request.duration_secs), &request.reason);
+    state.maintenance.export(&state.metrics);
+
+    Json(MaintenanceResponse {
+        requested_by: remote.to_string(),
+        maintenance,
+    })
+    .into_response()
+}
+
+/// `POST /debug/dump`: write the latest raw notification and getVoteAccounts result to files
+async fn debug_dump<R>(
+    State(state): State<Arc<AppState<R>>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, state.admin_token.as_deref()) {
+        warn!("Unauthorized /debug/dump from {}", remote);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let files = match state.debug.dump() {
+        Ok(files) => files,
+        Err(e) => {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e));
+        }
+    };
+    let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+    info!("Debug dump requested by {}: {:?}", remote, files);
+
+    Json(DumpResponse {
+        requested_by: remote.to_string(),
+        files,
+    })
+    .into_response()
+}
+
+/// `GET /debug/slots?limit=N`: outcome of the most recently rooted slots,
+/// newest first (see [`crate::ws::RecentSlots::record_rooted`])
+async fn debug_slots<R>(
+    State(state): State<Arc<AppState<R>>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<RecentSlotsQuery>,
+) -> Response {
+    if !is_authorized(&headers, state.admin_token.as_deref()) {
+        warn!("Unauthorized /debug/slots from {}", remote);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_RECENT_SLOTS_LIMIT);
+    let slots = read_tracker(&state.tracker, &state.metrics)
+        .await
+        .recent_slots()
+        .newest(limit);
+    Json(RecentSlotsResponse {
+        capacity: RECENT_SLOTS_CAPACITY,
+        slots,
+    })
+    .into_response()
+}
+
+/// `GET /debug/prior_voters`: the vote account's previous authorized voters
+/// and the epochs each voted for, to audit failovers by
+async fn debug_prior_voters<R>(
+    State(state): State<Arc<AppState<R>>>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&headers, state.admin_token.as_deref()) {
+        warn!("Unauthorized /debug/prior_voters from {}", remote);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let prior_voters = state.prior_voters.current();
+    Json(PriorVotersResponse {
+        entries: prior_voters.len(),
+        prior_voters,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poller::tests::{MutableTestRpc, vote_account};
+    use crate::ws::SLOTS_PER_EPOCH;
+    use serde_json::Value;
+
+    /// Serve the router on a local port and return its base URL
+    async fn serve(
+        rpc: MutableTestRpc,
+        debug_endpoints: bool,
+    ) -> (String, Arc<RwLock<VoteTracker>>) {
+        serve_with_sd(rpc, debug_endpoints, ServiceDiscovery::default()).await
+    }
+
+    async fn serve_with_sd(
+        rpc: MutableTestRpc,
+        debug_endpoints: bool,
+        service_discovery: ServiceDiscovery,
+    ) -> (String, Arc<RwLock<VoteTracker>>) {
+        let debug = Arc::new(DebugState::new(std::env::temp_dir(), 1));
+        serve_with(rpc, debug_endpoints, service_discovery, debug).await
+    }
+
+    async fn serve_with(
+        rpc: MutableTestRpc,
+        debug_endpoints: bool,
+        service_discovery: ServiceDiscovery,
+        debug: Arc<DebugState>,
+    ) -> (String, Arc<RwLock<VoteTracker>>) {
+        let state = app_state(rpc, service_discovery, debug);
+        let tracker = state.tracker.clone();
+        (serve_state(state, debug_endpoints).await, tracker)
+    }
+
+    fn app_state(
+        rpc: MutableTestRpc,
+        service_discovery: ServiceDiscovery,
+        debug: Arc<DebugState>,
+    ) -> AppState<MutableTestRpc> {
+        AppState {
+            metrics: Arc::new(Metrics::new().unwrap()),
+            tracker: Arc::new(RwLock::new(VoteTracker::default())),
+            rpc: Arc::new(rpc),
+            vote_pubkey: "A".to_string(),
+            mode: Mode::Ws,
+            metrics_max_staleness: None,
+            ws_connection: Arc::new(WsConnectionClock::default()),
+            debug,
+            admin_token: Some("secret".to_string()),
+            maintenance: Arc::new(Maintenance::new()),
+            discontinuities: Arc::new(Discontinuities::new()),
+            series: Arc::new(MetricSeries::new()),
+            prior_voters: Arc::new(PriorVotersWatch::default()),
+            service_discovery,
+        }
+    }
+
+    async fn serve_state(state: AppState<MutableTestRpc>, debug_endpoints: bool) -> String {
+        let app = router(Arc::new(state), debug_endpoints);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap()
+        });
+        format!("http://{}", addr)
+    }
+
+    fn staleness_state(mode: Mode, max_staleness: Option<u64>) -> AppState<MutableTestRpc> {
+        AppState {
+            mode,
+            metrics_max_staleness: max_staleness.map(Duration::from_secs),
+            ..app_state(
+                rpc_at_slot(0, 0),
+                ServiceDiscovery::default(),
+                Arc::new(DebugState::new(std::env::temp_dir(), 1)),
+            )
+        }
+    }
+
+    fn rpc_at_slot(root_index: u64, credits: u64) -> MutableTestRpc {
+        MutableTestRpc::new(vec![vote_account(
+            "A",
+            vec![(10, credits, 0)],
+            10 * SLOTS_PER_EPOCH + root_index,
+        )])
+    }
+
+    #[tokio::test]
+    async fn test_service_discovery_json_shape() {
+        let sd = ServiceDiscovery::new(
+            Some("tracker-1.example:7999".to_string()),
+            "A",
+            Some("mainnet-beta"),
+            Some("tracker-1"),
+        );
+        let (url, _) = serve_with_sd(rpc_at_slot(99, 1600), false, sd).await;
+
+        let body: Value = reqwest::get(format!("{}/sd", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([{
+                "targets": ["tracker-1.example:7999"],
+                "labels": {
+                    "vote_pubkey": "A",
+                    "cluster": "mainnet-beta",
+                    "instance_name": "tracker-1"
+                }
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_service_discovery_defaults_to_host_header() {
+        let sd = ServiceDiscovery::new(None, "A", None, None);
+        let (url, _) = serve_with_sd(rpc_at_slot(99, 1600), false, sd).await;
+
+        let body: Value = reqwest::get(format!("{}/sd", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let host = url.trim_start_matches("http://");
+        assert_eq!(body[0]["targets"][0], host);
+        assert_eq!(body[0]["labels"], serde_json::json!({ "vote_pubkey": "A" }));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_bin_decodes_to_tracker_snapshot() {
+        let state = staleness_state(Mode::Ws, None);
+        state
+            .tracker
+            .write()
+            .await
+            .reset(10, 10 * SLOTS_PER_EPOCH + 99, 1584);
+        let expected = state.tracker.read().await.snapshot();
+        let url = serve_state(state, false).await;
+
+        let response = reqwest::get(format!("{}/snapshot.bin", url)).await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            SNAPSHOT_CONTENT_TYPE
+        );
+        let bytes = response.bytes().await.unwrap();
+        assert_eq!(snapshot::decode(&bytes).unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_served_while_data_is_fresh() {
+        let state = staleness_state(Mode::Ws, Some(120));
+        state.metrics.ws_last_message.set(unix_now() as i64 - 5);
+        let url = serve_state(state, false).await;
+
+        let response = reqwest::get(format!("{}/metrics", url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.text().await.unwrap().contains("ws_last_message"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_metrics_fail_the_scrape() {
+        let state = staleness_state(Mode::Poll, Some(120));
+        let metrics = state.metrics.clone();
+        // A recent notification does not count in poll mode
+        metrics.ws_last_message.set(unix_now() as i64);
+        let url = serve_state(state, false).await;
+
+        let response = reqwest::get(format!("{}/metrics", url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.text().await.unwrap(), "No data received yet\n");
+
+        metrics.rpc_last_success.set(unix_now() as i64 - 300);
+        let response = reqwest::get(format!("{}/metrics", url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.text().await.unwrap();
+        assert!(
+            body.starts_with("Data is stale: last update 30"),
+            "{}",
+            body
+        );
+        assert!(body.ends_with("limit 120s\n"), "{}", body);
+        assert_eq!(metrics.metrics_last_scraped.get(), 0);
+        assert_eq!(
+            metrics
+                .http_requests
+                .with_label_values(&["/metrics", "503"])
+                .get(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_served_without_staleness_limit() {
+        let url = serve_state(staleness_state(Mode::Ws, None), false).await;
+
+        // Nothing received yet, and no limit to enforce
+        let response = reqwest::get(format!("{}/metrics", url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_staleness() {
+        let max = Duration::from_secs(120);
+        assert_eq!(staleness(Some(1_000), 1_120, max), None);
+        assert_eq!(
+            staleness(Some(1_000), 1_121, max).as_deref(),
+            Some("Data is stale: last update 121s ago, limit 120s\n")
+        );
+        assert_eq!(
+            staleness(None, 1_000, max).as_deref(),
+            Some("No data received yet\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scrape_activity_is_tracked() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), false).await;
+
+        let health: Value = reqwest::get(format!("{}/healthz", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["status"], "ok");
+        assert!(health["last_scraped_seconds_ago"].is_null());
+
+        reqwest::get(format!("{}/metrics", url)).await.unwrap();
+        reqwest::get(format!("{}/nope", url)).await.unwrap();
+
+        let health: Value = reqwest::get(format!("{}/healthz", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(health["last_scraped_seconds_ago"].as_u64().unwrap() <= 1);
+
+        let body = reqwest::get(format!("{}/metrics", url))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains(r#"http_requests_total{path="/metrics",status="200"} 1"#));
+        assert!(body.contains(r#"http_requests_total{path="/healthz",status="200"} 2"#));
+        assert!(body.contains(r#"http_requests_total{path="unmatched",status="404"} 1"#));
+        assert!(body.contains(r#"http_request_duration_seconds_count{path="/healthz"} 2"#));
+    }
+
+    #[tokio::test]
+    async fn test_metric_aliases_map_old_to_new_names() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), false).await;
+        let body: Value = reqwest::get(format!("{}/metrics/aliases", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            body["missed_vote_credits_5m"],
+            "solana_missed_vote_credits_5m"
+        );
+        assert_eq!(body.as_object().unwrap().len(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_metric_catalog_classifies_families() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), false).await;
+        let body: Value = reqwest::get(format!("{}/metrics/catalog", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let entry = |name: &str| {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .find(|entry| entry["name"] == name)
+                .unwrap_or_else(|| panic!("{name} missing"))
+                .clone()
+        };
+        assert_eq!(entry("solana_vote_credits_epoch")["source"], "observed");
+        assert_eq!(entry("solana_vote_credits_epoch")["unit"], "credits");
+        let efficiency = entry("solana_vote_credits_efficiency_5m");
+        assert_eq!(efficiency["source"], "derived");
+        assert_eq!(efficiency["unit"], "ratio");
+        assert_eq!(entry("ws_connected")["source"], "exporter");
+        assert_eq!(entry("solana_vote_efficiency_by_hour")["labels"][0], "hour");
+    }
+
+    #[tokio::test]
+    async fn test_summary_txt_before_any_data() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), false).await;
+        let response = reqwest::get(format!("{}/summary.txt", url)).await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(response.text().await.unwrap(), "no data yet | ws down\n");
+    }
+
+    #[tokio::test]
+    async fn test_series_returns_the_samples_taken() {
+        let state = app_state(
+            rpc_at_slot(99, 1600),
+            ServiceDiscovery::default(),
+            Arc::new(DebugState::new(std::env::temp_dir(), 1)),
+        );
+        state.metrics.ws_last_message.set(1_700_000_000);
+        state.metrics.vote_credits_efficiency_5m.set(0.97);
+        state.metrics.missed_5m.set(12);
+        for _ in 0..3 {
+            state.series.sample(&state.metrics, Mode::Ws);
+        }
+        let url = serve_state(state, false).await;
+        let get = async |query: &str| {
+            reqwest::get(format!("{}/series{}", url, query))
+                .await
+                .unwrap()
+        };
+
+        // More points than were taken returns all of them
+        let body: Value = get("?metric=efficiency&points=60")
+            .await
+            .json()
+            .await
+            .unwrap();
+        let points = body.as_array().unwrap();
+        assert_eq!(points.len(), 3);
+        assert!(points[0][0].as_u64().unwrap() > 1_700_000_000);
+        assert_eq!(points[0][1], 0.97);
+        let body: Value = get("?metric=missed&points=2").await.json().await.unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!(body[1][1], 12.0);
+        let body: Value = get("?metric=missed").await.json().await.unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 3);
+
+        for query in [
+            "",
+            "?metric=latency",
+            "?metric=efficiency&points=0",
+            "?metric=efficiency&points=61",
+            "?metric=efficiency&points=many",
+        ] {
+            assert_eq!(
+                get(query).await.status(),
+                reqwest::StatusCode::BAD_REQUEST,
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_unobserved_slots() {
+        let (url, tracker) = serve(rpc_at_slot(99, 1600), false).await;
+        {
+            let mut tracker = tracker.write().await;
+            let root = 10 * SLOTS_PER_EPOCH + 100;
+            tracker.reset(10, root, 1600);
+            tracker.process_update(root + 1, &[], Some(root), 1600, Some(10));
+            tracker.mark_disconnected();
+            tracker.process_update(root + 1001, &[], Some(root + 1000), 1600, Some(10));
+        }
+
+        let status: Value = reqwest::get(format!("{}/status", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            status["unobserved"],
+            serde_json::json!({ "rooted_slots": 1000, "expected_credits": 16000 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_debug_dump_returns_written_files() {
+        let dir = std::env::temp_dir().join(format!("tvc_tracker-server-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let debug = Arc::new(DebugState::new(&dir, 3));
+        let (url, _) = serve_with(
+            rpc_at_slot(99, 1600),
+            true,
+            ServiceDiscovery::default(),
+            debug.clone(),
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let dump = || client.post(format!("{}/debug/dump", url));
+
+        let response = dump().send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        // Nothing received yet
+        let body: Value = dump()
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["files"], serde_json::json!([]));
+
+        debug.record_ws_notification(r#"{"method":"accountNotification"}"#);
+        let body: Value = dump()
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let files = body["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        let path = files[0].as_str().unwrap();
+        assert!(path.ends_with("-ws_notification.json"));
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            r#"{"method":"accountNotification"}"#
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_debug_slots_newest_first() {
+        let (url, tracker) = serve(rpc_at_slot(99, 1600), true).await;
+        {
+            let mut tracker = tracker.write().await;
+            tracker.process_update(101, &[(100, 1, Some(1))], Some(99), 1_600, Some(0));
+            tracker.process_update(101, &[], Some(100), 1_616, Some(0));
+            tracker.process_update(105, &[], Some(104), 1_616, Some(0));
+        }
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("{}/debug/slots", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let get = |query: &'static str| {
+            let request = client
+                .get(format!("{}/debug/slots{}", url, query))
+                .bearer_auth("secret");
+            async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+        };
+
+        let body = get("").await;
+        assert_eq!(body["capacity"], RECENT_SLOTS_CAPACITY);
+        let slots = body["slots"].as_array().unwrap();
+        assert_eq!(slots.len(), 5);
+        assert_eq!(
+            slots[0],
+            serde_json::json!({"slot": 104, "outcome": "missed"})
+        );
+        assert_eq!(
+            slots[4],
+            serde_json::json!({"slot": 100, "outcome": "earned", "credits": 16})
+        );
+
+        let body = get("?limit=2").await;
+        let slots: Vec<u64> = body["slots"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["slot"].as_u64().unwrap())
+            .collect();
+        assert_eq!(slots, vec![104, 103]);
+    }
+
+    #[tokio::test]
+    async fn test_debug_prior_voters() {
+        let state = app_state(
+            rpc_at_slot(99, 1600),
+            ServiceDiscovery::default(),
+            Arc::new(DebugState::new(std::env::temp_dir(), 1)),
+        );
+        let prior_voters = state.prior_voters.clone();
+        let metrics = state.metrics.clone();
+        let url = serve_state(state, true).await;
+        let client = reqwest::Client::new();
+        let get = || {
+            client
+                .get(format!("{}/debug/prior_voters", url))
+                .bearer_auth("secret")
+                .send()
+        };
+
+        let body: Value = get().await.unwrap().json().await.unwrap();
+        assert_eq!(body, serde_json::json!({"entries": 0, "prior_voters": []}));
+
+        let voter = |pubkey: &str, from, to| PriorVoter {
+            authorized_pubkey: pubkey.to_string(),
+            epoch_of_last_authorized_switch: from,
+            target_epoch: to,
+        };
+        prior_voters.observe(
+            &[voter("Spare111", 851, 853), voter("Primary111", 612, 851)],
+            &metrics,
+        );
+        let body: Value = get().await.unwrap().json().await.unwrap();
+        assert_eq!(body["entries"], 2);
+        assert_eq!(
+            body["prior_voters"][0],
+            serde_json::json!({
+                "authorized_pubkey": "Primary111",
+                "epoch_of_last_authorized_switch": 612,
+                "target_epoch": 851
+            })
+        );
+
+        let response = client
+            .get(format!("{}/debug/prior_voters", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_bind_to_ephemeral_port() {
+        let listener = bind(0, PortConflictPolicy::Exit, Duration::ZERO)
+            .await
+            .unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert_ne!(port, 0);
+        assert!(listener.local_addr().unwrap().ip().is_unspecified());
+
+        let app = router(
+            Arc::new(app_state(
+                rpc_at_slot(99, 1600),
+                ServiceDiscovery::default(),
+                Arc::new(DebugState::new(std::env::temp_dir(), 1)),
+            )),
+            false,
+        );
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap()
+        });
+        let response = reqwest::get(format!("http://127.0.0.1:{}/status", port))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    /// A port held the way a previous instance holds it
+    fn taken_port() -> (std::net::TcpListener, u16) {
+        let taken = std::net::TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+        (taken, port)
+    }
+
+    #[tokio::test]
+    async fn test_taken_port_fails_fast_by_default() {
+        let (_taken, port) = taken_port();
+        let started = Instant::now();
+        let e = bind(port, PortConflictPolicy::Exit, Duration::from_secs(30))
+            .await
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let message = format!("{:#}", e);
+        assert!(message.contains("already in use"), "{}", message);
+        assert!(message.contains("--port-conflict-policy"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_wait_binds_once_the_port_is_released() {
+        let (taken, port) = taken_port();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            drop(taken);
+        });
+        let listener = bind(port, PortConflictPolicy::Wait, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(listener.local_addr().unwrap().port(), port);
+    }
+
+    #[tokio::test]
+    async fn test_wait_gives_up_after_the_timeout() {
+        let (_taken, port) = taken_port();
+        let started = Instant::now();
+        let e = bind(port, PortConflictPolicy::Wait, Duration::from_millis(300))
+            .await
+            .unwrap_err();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(format!("{:#}", e).contains("still in use"), "{:#}", e);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_takeover_stops_the_previous_instance_accepting() {
+        let previous = bind(0, PortConflictPolicy::Takeover, Duration::ZERO)
+            .await
+            .unwrap();
+        let port = previous.local_addr().unwrap().port();
+        let handover = Arc::new(PortHandover::default());
+        let app = takeover_router(handover.clone()).route("/healthz", get(|| async { "previous" }));
+        let previous = tokio::spawn(async move {
+            axum::serve(
+                previous,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(handover.requested())
+            .await
+            .unwrap()
+        });
+
+        let next = bind(port, PortConflictPolicy::Takeover, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(next.local_addr().unwrap().port(), port);
+        tokio::time::timeout(Duration::from_secs(5), previous)
+            .await
+            .expect("previous instance still serving")
+            .unwrap();
+
+        // Scrapes now only reach the new instance
+        let app = axum::Router::new().route("/healthz", get(|| async { "next" }));
+        tokio::spawn(async move { axum::serve(next, app).await.unwrap() });
+        let url = format!("http://127.0.0.1:{}/healthz", port);
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        for _ in 0..8 {
+            let body = client.get(&url).send().await.unwrap().text().await;
+            assert_eq!(body.unwrap(), "next");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_takeover_needs_the_previous_instance_to_allow_it() {
+        // An instance that bound without SO_REUSEPORT can't be taken over
+        let (_taken, port) = taken_port();
+        let e = bind(port, PortConflictPolicy::Takeover, Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", e).contains("takeover too"), "{:#}", e);
+    }
+
+    #[tokio::test]
+    async fn test_admin_endpoints_require_debug_flag() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), false).await;
+        let response = reqwest::Client::new()
+            .post(format!("{}/admin/reset", url))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_admin_reset_requires_token() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), true).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/admin/reset", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .post(format!("{}/admin/reset", url))
+            .bearer_auth("wrong")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_token_must_match_exactly() {
+        let bearer = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+        assert!(is_authorized(&bearer("Bearer secret"), Some("secret")));
+        for value in ["Bearer secre", "Bearer secret2", "Bearer ", "secret"] {
+            assert!(!is_authorized(&bearer(value), Some("secret")), "{}", value);
+        }
+        assert!(!is_authorized(&bearer("Bearer secret"), None));
+    }
+
+    #[tokio::test]
+    async fn test_admin_reset_reseeds_tracker() {
+        let (url, tracker) = serve(rpc_at_slot(99, 1500), true).await;
+
+        let body: Value = reqwest::Client::new()
+            .post(format!("{}/admin/reset", url))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(body["action"], "reset");
+        assert_eq!(body["old"]["epoch"], Value::Null);
+        assert_eq!(body["new"]["epoch"], 10);
+        assert_eq!(body["new"]["epoch_credits"], 1500);
+        assert_eq!(body["new"]["epoch_missed"], 100);
+        assert!(
+            body["requested_by"]
+                .as_str()
+                .unwrap()
+                .starts_with("127.0.0.1:")
+        );
+        assert_eq!(tracker.read().await.current_epoch_credits(), 1500);
+    }
+
+    #[tokio::test]
+    async fn test_admin_maintenance_toggles_window() {
+        let (url, _) = serve(rpc_at_slot(99, 1600), true).await;
+        let client = reqwest::Client::new();
+        let maintenance = async |body: Value| {
+            client
+                .post(format!("{}/admin/maintenance", url))
+                .bearer_auth("secret")
+                .json(&body)
+                .send()
+                .await
+                .unwrap()
+        };
+        let metrics = async || {
+            reqwest::get(format!("{}/metrics", url))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        };
+
+        let body: Value =
+            maintenance(serde_json::json!({"duration_secs": 3600, "reason": "upgrade"}))
+                .await
+                .json()
+                .await
+                .unwrap();
+        assert_eq!(body["maintenance"]["reason"], "upgrade");
+        assert!(metrics().await.contains("tvc_maintenance_active 1"));
+
+        let body: Value = maintenance(serde_json::json!({"duration_secs": 0}))
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["maintenance"], Value::Null);
+        assert!(metrics().await.contains("tvc_maintenance_active 0"));
+
+        let response = maintenance(serde_json::json!({"reason": "no duration"})).await;
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_admin_reconcile() {
+        let rpc = rpc_at_slot(199, 3000);
+        let (url, tracker) = serve(rpc, true).await;
+        tracker
+            .write()
+            .await
+            .reset(10, 10 * SLOTS_PER_EPOCH + 99, 1600);
+
+        let body: Value = reqwest::Client::new()
+            .post(format!("{}/admin/reconcile", url))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(body["applied"], true);
+        assert_eq!(body["old"]["epoch_credits"], 1600);
+        assert_eq!(body["new"]["epoch_credits"], 3000);
+        assert_eq!(body["new"]["epoch_missed"], 200);
+    }
+
+    #[tokio::test]
+    async fn test_discontinuities_record_reset_and_reseed() {
+        let (url, tracker) = serve(rpc_at_slot(199, 3000), true).await;
+        let client = reqwest::Client::new();
+        let discontinuities = async || -> Value {
+            reqwest::get(format!("{}/discontinuities", url))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap()
+        };
+        let body = discontinuities().await;
+        assert_eq!(body["capacity"], DISCONTINUITIES_CAPACITY);
+        assert_eq!(body["discontinuities"], serde_json::json!([]));
+
+        for action in ["reset", "reconcile"] {
+            let response = client
+                .post(format!("{}/admin/{}", url, action))
+                .bearer_auth("secret")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            if action == "reset" {
+                // Credits the reconcile realigns
+                tracker
+                    .write()
+                    .await
+                    .reset(10, 10 * SLOTS_PER_EPOCH + 99, 1600);
+            }
+        }
+
+        let body = discontinuities().await;
+        let records = body["discontinuities"].as_array().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["kind"], "reset");
+        assert_eq!(records[0]["epoch"], Value::Null);
+        assert_eq!(records[0]["before"]["epoch"], Value::Null);
+        assert_eq!(records[0]["after"]["epoch_credits"], 3000);
+        assert_eq!(records[1]["kind"], "reseed");
+        assert_eq!(records[1]["epoch"], 10);
+        assert_eq!(records[1]["slot"], 10 * SLOTS_PER_EPOCH + 99);
+        assert_eq!(records[1]["before"]["epoch_credits"], 1600);
+        assert_eq!(records[1]["after"]["epoch_credits"], 3000);
+        assert!(records[1]["timestamp_ms"].as_u64().unwrap() > 0);
+    }
+}
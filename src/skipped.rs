@@ -0,0 +1,148 @@
+use crate::metrics::Metrics;
+use crate::rpc::RpcClient;
+use crate::ws::VoteTracker;
+
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+/// Widest slot range a single getBlocks request may cover
+pub const MAX_GET_BLOCKS_RANGE: u64 = 500_000;
+
+/// How often the rooted ranges queued by the tracker are checked, batching
+/// the updates in between into one getBlocks request
+const SKIPPED_SLOTS_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Fetch the blocks of the rooted ranges the tracker has queued and count the
+/// slots without one. Each slot is fetched once. Returns the skipped slots found.
+pub async fn refresh_skipped_slots<R: RpcClient>(
+    rpc: &R,
+    tracker: &RwLock<VoteTracker>,
+    metrics: &Metrics,
+) -> Result<u64> {
+    let Some((first, last)) = tracker
+        .read()
+        .await
+        .skipped_slot_check_span(MAX_GET_BLOCKS_RANGE)
+    else {
+        return Ok(0);
+    };
+
+    let blocks = rpc.get_blocks(first, last).await?;
+    let skipped = tracker
+        .write()
+        .await
+        .apply_produced_blocks(first, last, &blocks);
+    debug!(
+        "Slots {}..={}: {} blocks, {} skipped",
+        first,
+        last,
+        blocks.len(),
+        skipped
+    );
+    metrics.cluster_skipped_slots.inc_by(skipped);
+    Ok(skipped)
+}
+
+/// Keep checking the tracker's rooted ranges for skipped slots, for the
+/// skip-adjusted missed credits
+pub async fn run_skipped_slots<R: RpcClient>(
+    rpc: &R,
+    tracker: Arc<RwLock<VoteTracker>>,
+    metrics: &Metrics,
+) {
+    loop {
+        tokio::time::sleep(SKIPPED_SLOTS_CHECK_INTERVAL).await;
+        if let Err(e) = refresh_skipped_slots(rpc, &tracker, metrics).await {
+            warn!("Skipped slot check failed: {:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{RpcVersion, RpcVoteAccounts};
+    use crate::ws::SLOTS_PER_EPOCH;
+    use anyhow::anyhow;
+    use std::sync::Mutex;
+
+    /// Serves every slot but the multiples of 10 as produced, recording the
+    /// getBlocks ranges
+    #[derive(Default)]
+    struct BlocksRpc {
+        requests: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl RpcClient for BlocksRpc {
+        async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_version(&self) -> Result<RpcVersion> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_slot(&self) -> Result<u64> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_blocks(&self, start: u64, end: u64) -> Result<Vec<u64>> {
+            self.requests.lock().unwrap().push((start, end));
+            Ok((start..=end).filter(|slot| slot % 10 != 0).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_checks_each_rooted_slot_once() {
+        let rpc = BlocksRpc::default();
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 5 * SLOTS_PER_EPOCH;
+        let tracker = RwLock::new(VoteTracker::default().with_skipped_slot_tracking(true));
+        {
+            let mut tracker = tracker.write().await;
+            tracker.reset(5, epoch_start + 100, 1616);
+            // Two updates root 100 slots, all credits of the 10 skipped ones missed
+            tracker.process_update(
+                epoch_start + 151,
+                &[],
+                Some(epoch_start + 150),
+                2336,
+                Some(5),
+            );
+            tracker.process_update(
+                epoch_start + 201,
+                &[],
+                Some(epoch_start + 200),
+                3056,
+                Some(5),
+            );
+            assert_eq!(tracker.epoch_missed(), 160);
+        }
+
+        assert_eq!(
+            refresh_skipped_slots(&rpc, &tracker, &metrics)
+                .await
+                .unwrap(),
+            10
+        );
+        assert_eq!(
+            refresh_skipped_slots(&rpc, &tracker, &metrics)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            *rpc.requests.lock().unwrap(),
+            vec![(epoch_start + 101, epoch_start + 200)]
+        );
+        assert_eq!(metrics.cluster_skipped_slots.get(), 10);
+        assert_eq!(tracker.read().await.epoch_missed_skip_adjusted(), Some(0));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::ws::{EpochInfo, TrackerSnapshot, WorstWindow};
+
+use std::fmt;
+
+/// Schema version written as the first byte
+pub const SNAPSHOT_VERSION: u8 = 2;
+
+/// Oldest schema version still decoded: version 1 lacks the tracked slot range
+pub const MIN_SNAPSHOT_VERSION: u8 = 1;
+
+/// Content type of `/snapshot.bin`
+pub const SNAPSHOT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Why bytes don't decode into a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Written by a newer (or unknown) schema
+    UnsupportedVersion(u8),
+    /// Ended before the snapshot was complete
+    Truncated,
+    /// Option tag other than 0 or 1
+    InvalidTag(u8),
+    /// Bytes left over after the snapshot
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported snapshot version {} (supported: {} to {})",
+                version, MIN_SNAPSHOT_VERSION, SNAPSHOT_VERSION
+            ),
+            DecodeError::Truncated => write!(f, "Snapshot is truncated"),
+            DecodeError::InvalidTag(tag) => write!(f, "Invalid option tag {}", tag),
+            DecodeError::TrailingBytes(count) => {
+                write!(f, "{} trailing bytes after the snapshot", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Encode `snapshot` for `GET /snapshot.bin`: the schema version byte, then
+/// the fields in declaration order. Integers are little-endian `u64`, floats
+/// little-endian `f64` bits and options a `0`/`1` tag followed by the value
+/// when present. Any change to the layout bumps [`SNAPSHOT_VERSION`].
+pub fn encode(snapshot: &TrackerSnapshot) -> Vec<u8> {
+    let mut out = vec![SNAPSHOT_VERSION];
+    put_option(&mut out, snapshot.epoch_info, |out, info| {
+        put_u64(out, info.epoch);
+        put_u64(out, info.slot_index);
+        put_u64(out, info.epoch_start_slot);
+        put_u64(out, info.slots_in_epoch);
+    });
+    put_option(&mut out, snapshot.first_epoch, put_u64);
+    put_u64(&mut out, snapshot.current_epoch_credits);
+    put_u64(&mut out, snapshot.epoch_missed);
+    put_u64(&mut out, snapshot.tracked_credits);
+    put_u64(&mut out, snapshot.tracked_missed);
+    put_option(&mut out, snapshot.worst_window_5m, put_worst_window);
+    put_option(&mut out, snapshot.epoch_worst_window_5m, put_worst_window);
+    put_option(&mut out, snapshot.epoch_first_tracked_slot, put_u64);
+    put_option(&mut out, snapshot.epoch_last_tracked_slot, put_u64);
+    out
+}
+
+/// Decode bytes produced by [`encode`], or by an older version down to
+/// [`MIN_SNAPSHOT_VERSION`]
+pub fn decode(bytes: &[u8]) -> Result<TrackerSnapshot, DecodeError> {
+    let (&version, rest) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+    if !(MIN_SNAPSHOT_VERSION..=SNAPSHOT_VERSION).contains(&version) {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    let mut reader = Reader(rest);
+    let mut snapshot = TrackerSnapshot {
+        epoch_info: reader.option(|r| {
+            Ok(EpochInfo {
+                epoch: r.u64()?,
+                slot_index: r.u64()?,
+                epoch_start_slot: r.u64()?,
+                slots_in_epoch: r.u64()?,
+            })
+        })?,
+        first_epoch: reader.option(Reader::u64)?,
+        current_epoch_credits: reader.u64()?,
+        epoch_missed: reader.u64()?,
+        tracked_credits: reader.u64()?,
+        tracked_missed: reader.u64()?,
+        worst_window_5m: reader.option(Reader::worst_window)?,
+        epoch_worst_window_5m: reader.option(Reader::worst_window)?,
+        epoch_first_tracked_slot: None,
+        epoch_last_tracked_slot: None,
+    };
+    if version >= 2 {
+        snapshot.epoch_first_tracked_slot = reader.option(Reader::u64)?;
+        snapshot.epoch_last_tracked_slot = reader.option(Reader::u64)?;
+    }
+    match reader.0.len() {
+        0 => Ok(snapshot),
+        trailing => Err(DecodeError::TrailingBytes(trailing)),
+    }
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_worst_window(out: &mut Vec<u8>, window: WorstWindow) {
+    out.extend_from_slice(&window.efficiency.to_le_bytes());
+    put_u64(out, window.timestamp);
+}
+
+fn put_option<T>(out: &mut Vec<u8>, value: Option<T>, put: impl FnOnce(&mut Vec<u8>, T)) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+/// Remaining input
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        if self.0.len() < N {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(head.try_into().expect("length checked"))
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn worst_window(&mut self) -> Result<WorstWindow, DecodeError> {
+        Ok(WorstWindow {
+            efficiency: f64::from_le_bytes(self.take()?),
+            timestamp: self.u64()?,
+        })
+    }
+
+    fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<Option<T>, DecodeError> {
+        match self.take::<1>()? {
+            [0] => Ok(None),
+            [1] => read(self).map(Some),
+            [tag] => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_snapshot() -> TrackerSnapshot {
+        TrackerSnapshot {
+            epoch_info: Some(EpochInfo::from_slot(500 * 432_000 + 1234)),
+            first_epoch: Some(499),
+            current_epoch_credits: 19_744,
+            epoch_missed: 80,
+            tracked_credits: 6_019_744,
+            tracked_missed: 1_280,
+            worst_window_5m: Some(WorstWindow {
+                efficiency: 0.875,
+                timestamp: 1_700_000_000,
+            }),
+            epoch_worst_window_5m: None,
+            epoch_first_tracked_slot: Some(500 * 432_000),
+            epoch_last_tracked_slot: Some(500 * 432_000 + 1234),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for snapshot in [full_snapshot(), TrackerSnapshot::default()] {
+            let bytes = encode(&snapshot);
+            assert_eq!(bytes[0], SNAPSHOT_VERSION);
+            assert_eq!(decode(&bytes), Ok(snapshot));
+        }
+    }
+
+    #[test]
+    fn test_version_1_decodes_without_tracked_slots() {
+        let mut bytes = encode(&full_snapshot());
+        // Version 1 ends before the two tracked slot options (tag + u64 each)
+        bytes.truncate(bytes.len() - 18);
+        bytes[0] = 1;
+        let snapshot = decode(&bytes).unwrap();
+        assert_eq!(snapshot.epoch_first_tracked_slot, None);
+        assert_eq!(snapshot.epoch_last_tracked_slot, None);
+        assert_eq!(snapshot.tracked_credits, 6_019_744);
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        let mut bytes = encode(&full_snapshot());
+        bytes[0] = SNAPSHOT_VERSION + 1;
+        let err = decode(&bytes).unwrap_err();
+        assert_eq!(err, DecodeError::UnsupportedVersion(SNAPSHOT_VERSION + 1));
+        assert!(err.to_string().contains("Unsupported snapshot version"));
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected() {
+        let bytes = encode(&full_snapshot());
+        assert_eq!(decode(&[]), Err(DecodeError::Truncated));
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode(&trailing), Err(DecodeError::TrailingBytes(1)));
+
+        let mut bad_tag = bytes;
+        bad_tag[1] = 7;
+        assert_eq!(decode(&bad_tag), Err(DecodeError::InvalidTag(7)));
+    }
+}
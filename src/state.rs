@@ -0,0 +1,399 @@
+use crate::discontinuity::{Discontinuities, DiscontinuityEvent};
+use crate::metrics::Metrics;
+use crate::poller::{PersistedPoll, PollStateSlot, snapshot_from_vote_account};
+use crate::rpc::RpcClient;
+use crate::ws::{
+    EpochResult, HourlyProfile, OfflineGap, PerfectSlotStreak, TrackerBaseline, TrackerHistory,
+    VoteTracker,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// How often the state file is rewritten
+pub const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tracker state kept across restarts in `--state-file`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedState {
+    /// Unix milliseconds at which the state was saved
+    pub saved_at: u64,
+    pub history: TrackerHistory,
+    #[serde(default)]
+    pub hourly_profile: HourlyProfile,
+    #[serde(default)]
+    pub perfect_slot_streak: PerfectSlotStreak,
+    /// Epoch, root and credits at the save, for the offline catch-up
+    #[serde(default)]
+    pub baseline: Option<TrackerBaseline>,
+    /// Result of the last finished epoch, for the next epoch's comparison
+    #[serde(default)]
+    pub previous_epoch: Option<EpochResult>,
+    /// Counters and window history of the poller (poll mode)
+    #[serde(default)]
+    pub poll: Option<PersistedPoll>,
+}
+
+impl PersistedState {
+    pub fn from_tracker(tracker: &VoteTracker) -> Self {
+        Self {
+            saved_at: tracker.now_millis(),
+            history: tracker.history(),
+            hourly_profile: tracker.hourly_profile().clone(),
+            perfect_slot_streak: tracker.perfect_slot_streak(),
+            baseline: Some(tracker.baseline()),
+            previous_epoch: tracker.previous_epoch(),
+            poll: None,
+        }
+    }
+
+    /// Add the poller's state
+    pub fn with_poll(mut self, poll: Option<PersistedPoll>) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// Restore the saved state into a freshly started tracker
+    pub fn restore(self, tracker: &mut VoteTracker) {
+        tracker.restore_history(self.history);
+        tracker.restore_hourly_profile(self.hourly_profile);
+        tracker.restore_perfect_slot_streak(self.perfect_slot_streak);
+        tracker.restore_previous_epoch(self.previous_epoch);
+    }
+}
+
+/// Read the state file, None if it doesn't exist yet
+pub fn load_state(path: &Path) -> Result<Option<PersistedState>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let state = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse state file {}", path.display()))?;
+    Ok(Some(state))
+}
+
+/// Write the state file atomically (write a temporary file, then rename it)
+pub fn save_state(path: &Path, state: &PersistedState) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let content = serde_json::to_string(state)?;
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Restore the tracker from the state file if there is one. A missing or
+/// unreadable file just means starting fresh. Returns the saved baseline
+/// for [`catch_up_offline_gap`].
+pub async fn restore_tracker(
+    path: &Path,
+    tracker: &RwLock<VoteTracker>,
+) -> Option<TrackerBaseline> {
+    match load_state(path) {
+        Ok(Some(state)) => {
+            info!(
+                "Restoring tracker state saved at {} from {}",
+                state.saved_at / 1000,
+                path.display()
+            );
+            let baseline = state.baseline;
+            state.restore(&mut *tracker.write().await);
+            baseline
+        }
+        Ok(None) => {
+            info!("No state file at {}, starting fresh", path.display());
+            None
+        }
+        Err(e) => {
+            warn!("Ignoring state file: {:#}", e);
+            None
+        }
+    }
+}
+
+/// The poller's state from the state file, if there is one. A missing or
+/// unreadable file, or one without a poll section, means polling from scratch.
+pub fn restore_poll(path: &Path) -> Option<PersistedPoll> {
+    match load_state(path) {
+        Ok(state) => state.and_then(|state| state.poll),
+        Err(e) => {
+            warn!("Ignoring state file: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Fold the credits earned and missed while the exporter was down into the
+/// restored tracker: from the saved root to the vote account's current one
+pub async fn catch_up_offline_gap<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    saved: &TrackerBaseline,
+    tracker: &RwLock<VoteTracker>,
+    metrics: &Metrics,
+) -> Result<OfflineGap> {
+    let accounts = rpc.get_vote_accounts(Some(vote_pubkey)).await?;
+    let (account, delinquent) = accounts
+        .find(vote_pubkey)
+        .ok_or_else(|| anyhow!("Vote account {} not found", vote_pubkey))?;
+    let mut tracker = tracker.write().await;
+    let snapshot = snapshot_from_vote_account(account, delinquent, tracker.credits_model());
+    let offline = tracker.apply_offline_gap(
+        saved,
+        snapshot.epoch_info.epoch,
+        snapshot.root_slot,
+        snapshot.credits_this_epoch,
+        snapshot.last_epoch_credits,
+    )?;
+    metrics.offline_catchup_credits.set(offline.credits as i64);
+    metrics.offline_catchup_missed.set(offline.missed() as i64);
+    info!(
+        "Caught up {} slots rooted while offline: {} credits earned, {} missed",
+        offline.rooted_slots,
+        offline.credits,
+        offline.missed()
+    );
+    Ok(offline)
+}
+
+/// Record the restore in `discontinuities`: from the `saved` baseline to
+/// the tracker's, which includes the offline catch-up once applied
+pub async fn record_restore(
+    discontinuities: &Discontinuities,
+    saved: &TrackerBaseline,
+    tracker: &RwLock<VoteTracker>,
+) {
+    let restored = tracker.read().await.baseline();
+    discontinuities.record_at(
+        &restored,
+        DiscontinuityEvent::StateRestore {
+            before: *saved,
+            after: restored,
+        },
+    );
+}
+
+/// Save the tracker and poller state every `interval`
+pub async fn run_state_saver(
+    path: &Path,
+    tracker: Arc<RwLock<VoteTracker>>,
+    poll: PollStateSlot,
+    interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+        save_tracker(path, &tracker, &poll).await;
+    }
+}
+
+/// Save the tracker and poller state once, logging failures
+pub async fn save_tracker(path: &Path, tracker: &RwLock<VoteTracker>, poll: &PollStateSlot) {
+    let poll = poll.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let state = PersistedState::from_tracker(&*tracker.read().await).with_poll(poll);
+    if let Err(e) = save_state(path, &state) {
+        warn!("Failed to save tracker state: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::credits::CreditsModel;
+    use crate::poller::PollState;
+    use crate::poller::tests::vote_account;
+    use crate::ws::SLOTS_PER_EPOCH;
+
+    fn scratch_file(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("tvc_tracker-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_state_round_trip() {
+        let path = scratch_file("state");
+        let clock = Arc::new(ManualClock::new(1_704_117_540_000));
+        let mut tracker = VoteTracker::default().with_clock(clock.clone());
+        let epoch_start = SLOTS_PER_EPOCH;
+        tracker.process_update(
+            epoch_start + 1001,
+            &[],
+            Some(epoch_start + 1000),
+            16_016,
+            Some(1),
+        );
+        clock.advance_secs(30);
+        tracker.process_update(
+            epoch_start + 1011,
+            &[],
+            Some(epoch_start + 1010),
+            16_096,
+            Some(1),
+        );
+        let tracker = RwLock::new(tracker);
+
+        assert!(load_state(&path).unwrap().is_none());
+        save_tracker(&path, &tracker, &PollStateSlot::default()).await;
+
+        let restored = RwLock::new(VoteTracker::default().with_clock(clock.clone()));
+        let saved = restore_tracker(&path, &restored).await;
+        let (original, restored) = (tracker.read().await, restored.read().await);
+        assert_eq!(saved, Some(original.baseline()));
+        assert_eq!(restored.history(), original.history());
+        assert_eq!(restored.hourly_profile(), original.hourly_profile());
+        assert_eq!(
+            restored.perfect_slot_streak(),
+            original.perfect_slot_streak()
+        );
+        assert_eq!(
+            restored
+                .hourly_profile()
+                .efficiency(13, restored.now_millis()),
+            Some(0.5)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tracked_slots_survive_restart() {
+        let path = scratch_file("tracked-slots");
+        let epoch_start = SLOTS_PER_EPOCH;
+        let mut tracker = VoteTracker::default();
+        tracker.process_update(
+            epoch_start + 1001,
+            &[],
+            Some(epoch_start + 1000),
+            16_016,
+            Some(1),
+        );
+        tracker.process_update(
+            epoch_start + 1011,
+            &[],
+            Some(epoch_start + 1010),
+            16_176,
+            Some(1),
+        );
+        save_tracker(&path, &RwLock::new(tracker), &PollStateSlot::default()).await;
+
+        // Restarted mid-epoch, 990 slots later
+        let restored = RwLock::new(VoteTracker::default());
+        let saved = restore_tracker(&path, &restored).await.unwrap();
+        assert_eq!(saved.epoch_first_tracked_slot, Some(epoch_start + 1000));
+        let mut restored = restored.write().await;
+        restored
+            .apply_offline_gap(&saved, 1, epoch_start + 2000, 16_176 + 15_840, None)
+            .unwrap();
+        assert_eq!(
+            restored.epoch_first_tracked_slot(),
+            Some(epoch_start + 1000)
+        );
+        assert_eq!(restored.epoch_last_tracked_slot(), Some(epoch_start + 2000));
+
+        // State files written before the range was saved start it at the saved root
+        let old = TrackerBaseline {
+            epoch_first_tracked_slot: None,
+            ..saved
+        };
+        let mut tracker = VoteTracker::default();
+        tracker
+            .apply_offline_gap(&old, 1, epoch_start + 2000, 16_176 + 15_840, None)
+            .unwrap();
+        assert_eq!(tracker.epoch_first_tracked_slot(), Some(epoch_start + 1010));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_restore_is_recorded_with_offline_gap() {
+        let path = scratch_file("discontinuity");
+        let epoch_start = SLOTS_PER_EPOCH;
+        let mut tracker = VoteTracker::default();
+        tracker.process_update(
+            epoch_start + 1001,
+            &[],
+            Some(epoch_start + 1000),
+            16_016,
+            Some(1),
+        );
+        save_tracker(&path, &RwLock::new(tracker), &PollStateSlot::default()).await;
+
+        let restored = RwLock::new(VoteTracker::default());
+        let saved = restore_tracker(&path, &restored).await.unwrap();
+        restored
+            .write()
+            .await
+            .apply_offline_gap(&saved, 1, epoch_start + 2000, 16_016 + 15_840, None)
+            .unwrap();
+        let discontinuities = Discontinuities::new();
+        record_restore(&discontinuities, &saved, &restored).await;
+
+        let recent = discontinuities.recent();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].epoch, Some(1));
+        assert_eq!(recent[0].slot, Some(epoch_start + 2000));
+        let DiscontinuityEvent::StateRestore { before, after } = recent[0].event else {
+            panic!("not a restore: {:?}", recent[0].event);
+        };
+        assert_eq!(before, saved);
+        assert_eq!(before.root_slot, Some(epoch_start + 1000));
+        assert_eq!(after.root_slot, Some(epoch_start + 2000));
+        assert_eq!(after.epoch_credits, 16_016 + 15_840);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_state_file_is_ignored() {
+        let path = scratch_file("corrupt");
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(load_state(&path).is_err());
+
+        let tracker = RwLock::new(VoteTracker::default());
+        assert_eq!(restore_tracker(&path, &tracker).await, None);
+        assert!(tracker.read().await.history().entries.is_empty());
+        assert_eq!(restore_poll(&path), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_poll_state_round_trip() {
+        let path = scratch_file("poll");
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let slot = PollStateSlot::default();
+        let mut state = PollState::new()
+            .with_clock(clock.clone())
+            .with_persistence(Some(slot.clone()));
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let poll = |credits: u64, root: u64| {
+            snapshot_from_vote_account(
+                &vote_account("A", vec![(10, credits, 0)], epoch_start + root),
+                false,
+                CreditsModel::default(),
+            )
+        };
+        state.apply(&poll(1600, 99));
+        clock.advance_secs(60);
+        state.apply(&poll(3100, 199));
+
+        let tracker = RwLock::new(VoteTracker::default());
+        save_tracker(&path, &tracker, &slot).await;
+        let saved = restore_poll(&path).unwrap();
+        assert_eq!(Some(saved.clone()), state.persisted());
+        assert_eq!(saved.missed_total, 100);
+        assert_eq!(saved.history.len(), 2);
+
+        // Files written before the poll section still restore the tracker
+        let mut old: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        old.as_object_mut().unwrap().remove("poll");
+        std::fs::write(&path, old.to_string()).unwrap();
+        assert_eq!(restore_poll(&path), None);
+        assert!(load_state(&path).unwrap().is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,381 @@
+use crate::credits::CreditsModel;
+use crate::poller::snapshot_from_vote_account;
+use crate::report::format_percent;
+use crate::rpc::{RpcClient, RpcVoteAccount};
+use crate::ws::{EpochInfo, TipExpectation};
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Width of the row labels in the text output
+const LABEL_WIDTH: usize = 22;
+
+/// The tracker's accounting for one vote account, recomputed from a single
+/// getVoteAccounts entry, with whatever doesn't add up
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Verification {
+    pub vote_pubkey: String,
+    /// Epoch of the cluster's tip
+    pub epoch_info: EpochInfo,
+    pub tip_slot: u64,
+    pub root_slot: u64,
+    /// Raw (epoch, credits, previous credits) entries, oldest first
+    pub epoch_credits: Vec<(u64, u64, u64)>,
+    /// Credits this epoch as the tracker computes them
+    pub credits_this_epoch: u64,
+    /// Credits this epoch as `solana vote-account` shows them: credits minus
+    /// previous credits of the entry for the tip's epoch, 0 without one
+    pub cli_credits_this_epoch: u64,
+    pub expected_max_rooted: u64,
+    pub expected_max_tip: u64,
+    pub missed_rooted: u64,
+    pub missed_tip: u64,
+    pub efficiency_rooted: f64,
+    pub efficiency_tip: f64,
+    /// Internal contradictions; empty when the accounting is consistent
+    pub inconsistencies: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
+
+/// Recompute the accounting of `account` at `tip_slot` (in the epoch of
+/// `epoch_info`) with the library functions and check it against the raw
+/// epochCredits and the `solana vote-account` semantics
+pub fn verify_vote_account(
+    account: &RpcVoteAccount,
+    epoch_info: EpochInfo,
+    tip_slot: u64,
+    tip_allowance_slots: u64,
+    model: CreditsModel,
+) -> Verification {
+    let snapshot = snapshot_from_vote_account(account, false, model);
+    let credits_this_epoch = snapshot.credits_this_epoch;
+    let expected_max_rooted = snapshot.expected_max_rooted();
+    let tip = TipExpectation::new(
+        &snapshot.epoch_info,
+        account.root_slot,
+        tip_slot,
+        tip_allowance_slots,
+        credits_this_epoch,
+        &model,
+    );
+    let cli_credits_this_epoch = account
+        .epoch_credits
+        .iter()
+        .find(|(epoch, _, _)| *epoch == epoch_info.epoch)
+        .map_or(0, |(_, credits, previous)| {
+            credits.saturating_sub(*previous)
+        });
+
+    let mut inconsistencies = Vec::new();
+    for pair in account.epoch_credits.windows(2) {
+        let ((epoch, credits, _), (next_epoch, _, next_previous)) = (pair[0], pair[1]);
+        if next_epoch <= epoch {
+            inconsistencies.push(format!(
+                "epochCredits out of order: epoch {} follows epoch {}",
+                next_epoch, epoch
+            ));
+        } else if next_previous != credits {
+            inconsistencies.push(format!(
+                "Epoch {} starts from {} previous credits, but epoch {} ended at {}",
+                next_epoch, next_previous, epoch, credits
+            ));
+        }
+    }
+    for (epoch, credits, previous) in &account.epoch_credits {
+        if credits < previous {
+            inconsistencies.push(format!(
+                "Epoch {} has fewer credits ({}) than previous credits ({})",
+                epoch, credits, previous
+            ));
+        }
+    }
+    if account.root_slot > tip_slot {
+        inconsistencies.push(format!(
+            "Root slot {} is ahead of the tip {}",
+            account.root_slot, tip_slot
+        ));
+    }
+    if snapshot.epoch_info.epoch == epoch_info.epoch && credits_this_epoch != cli_credits_this_epoch
+    {
+        inconsistencies.push(format!(
+            "Credits this epoch {} differ from credits - previous credits {}",
+            credits_this_epoch, cli_credits_this_epoch
+        ));
+    }
+    if credits_this_epoch > expected_max_rooted {
+        inconsistencies.push(format!(
+            "Credits this epoch {} exceed the {} the rooted slots allow at {} per slot",
+            credits_this_epoch, expected_max_rooted, model.max_credits_per_slot
+        ));
+    }
+
+    let efficiency = |expected: u64| {
+        if expected == 0 {
+            0.0
+        } else {
+            credits_this_epoch as f64 / expected as f64
+        }
+    };
+    Verification {
+        vote_pubkey: account.vote_pubkey.clone(),
+        epoch_info,
+        tip_slot,
+        root_slot: account.root_slot,
+        epoch_credits: account.epoch_credits.clone(),
+        credits_this_epoch,
+        cli_credits_this_epoch,
+        expected_max_rooted,
+        expected_max_tip: tip.expected_max,
+        missed_rooted: snapshot.missed_this_epoch(),
+        missed_tip: tip.provisional_missed,
+        efficiency_rooted: efficiency(expected_max_rooted),
+        efficiency_tip: efficiency(tip.expected_max),
+        inconsistencies,
+    }
+}
+
+/// Fetch the vote account and the tip, and verify the accounting
+pub async fn fetch_verification<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    tip_allowance_slots: u64,
+    model: CreditsModel,
+) -> Result<Verification> {
+    let tip_slot = rpc.get_slot().await?;
+    let accounts = rpc.get_vote_accounts(Some(vote_pubkey)).await?;
+    let (account, _) = accounts
+        .find(vote_pubkey)
+        .ok_or_else(|| anyhow!("Vote account {} not found in getVoteAccounts", vote_pubkey))?;
+    Ok(verify_vote_account(
+        account,
+        EpochInfo::from_slot(tip_slot),
+        tip_slot,
+        tip_allowance_slots,
+        model,
+    ))
+}
+
+/// Format the verification as labelled rows, the raw epochCredits and the
+/// inconsistencies found
+pub fn format_verification(verification: &Verification) -> String {
+    let v = verification;
+    let mut out = String::new();
+    let mut row = |label: &str, value: String| {
+        let _ = writeln!(out, "{:<LABEL_WIDTH$}  {}", label, value);
+    };
+
+    row("Vote account", v.vote_pubkey.clone());
+    row(
+        "Epoch",
+        format!(
+            "{} (slot index {} of {})",
+            v.epoch_info.epoch, v.epoch_info.slot_index, v.epoch_info.slots_in_epoch
+        ),
+    );
+    row("Tip slot", v.tip_slot.to_string());
+    row("Root slot", v.root_slot.to_string());
+    row("Credits this epoch", v.credits_this_epoch.to_string());
+    row("solana vote-account", v.cli_credits_this_epoch.to_string());
+    row(
+        "Expected (rooted)",
+        format!(
+            "{} (missed {}, efficiency {})",
+            v.expected_max_rooted,
+            v.missed_rooted,
+            format_percent(v.efficiency_rooted)
+        ),
+    );
+    row(
+        "Expected (tip)",
+        format!(
+            "{} (missed {}, efficiency {})",
+            v.expected_max_tip,
+            v.missed_tip,
+            format_percent(v.efficiency_tip)
+        ),
+    );
+
+    out.push_str("\nepochCredits (epoch, credits, previous credits, earned):\n");
+    for (epoch, credits, previous) in &v.epoch_credits {
+        let _ = writeln!(
+            out,
+            "  {:>6}  {:>14}  {:>14}  {:>10}",
+            epoch,
+            credits,
+            previous,
+            credits.saturating_sub(*previous)
+        );
+    }
+
+    if v.is_consistent() {
+        out.push_str("\nConsistent");
+    } else {
+        let _ = write!(out, "\nInconsistencies: {}", v.inconsistencies.len());
+        for inconsistency in &v.inconsistencies {
+            let _ = write!(out, "\n  - {}", inconsistency);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::SLOTS_PER_EPOCH;
+
+    const EPOCH: u64 = 812;
+
+    fn account(epoch_credits: Vec<(u64, u64, u64)>, root_slot: u64) -> RpcVoteAccount {
+        RpcVoteAccount {
+            vote_pubkey: "Vote111".to_string(),
+            node_pubkey: String::new(),
+            activated_stake: 0,
+            commission: 0,
+            epoch_vote_account: true,
+            epoch_credits,
+            last_vote: root_slot + 32,
+            root_slot,
+        }
+    }
+
+    /// Two completed epochs and 8000 credits in the current one, with the
+    /// root 999 slots into it
+    fn healthy_account() -> RpcVoteAccount {
+        account(
+            vec![
+                (EPOCH - 2, 5_000_000, 0),
+                (EPOCH - 1, 11_000_000, 5_000_000),
+                (EPOCH, 11_008_000, 11_000_000),
+            ],
+            EPOCH * SLOTS_PER_EPOCH + 999,
+        )
+    }
+
+    fn verify(account: &RpcVoteAccount, tip_slot: u64) -> Verification {
+        verify_vote_account(
+            account,
+            EpochInfo::from_slot(tip_slot),
+            tip_slot,
+            32,
+            CreditsModel::default(),
+        )
+    }
+
+    #[test]
+    fn test_consistent_account() {
+        let tip_slot = EPOCH * SLOTS_PER_EPOCH + 1_531;
+        let v = verify(&healthy_account(), tip_slot);
+        assert!(v.is_consistent(), "{:?}", v.inconsistencies);
+        assert_eq!(v.credits_this_epoch, 8000);
+        assert_eq!(v.cli_credits_this_epoch, 8000);
+        // 1000 rooted slots, 1500 up to the tip less the allowance
+        assert_eq!(v.expected_max_rooted, 16_000);
+        assert_eq!(v.missed_rooted, 8000);
+        assert_eq!(v.efficiency_rooted, 0.5);
+        assert_eq!(v.expected_max_tip, 24_000);
+        assert_eq!(v.missed_tip, 16_000);
+        assert_eq!(v.efficiency_tip, 8000.0 / 24_000.0);
+        assert_eq!(v.epoch_credits.len(), 3);
+    }
+
+    #[test]
+    fn test_no_entry_for_the_current_epoch_yet() {
+        let mut account = healthy_account();
+        account.epoch_credits.pop();
+        let v = verify(&account, EPOCH * SLOTS_PER_EPOCH + 1_100);
+        assert!(v.is_consistent(), "{:?}", v.inconsistencies);
+        assert_eq!((v.credits_this_epoch, v.cli_credits_this_epoch), (0, 0));
+        assert_eq!(v.efficiency_rooted, 0.0);
+    }
+
+    #[test]
+    fn test_broken_credit_chain_is_flagged() {
+        let account = account(
+            vec![
+                (EPOCH - 2, 5_000_000, 0),
+                // Starts below where the previous epoch ended
+                (EPOCH - 1, 11_000_000, 4_000_000),
+                // Credits went backwards
+                (EPOCH, 10_000_000, 11_000_000),
+            ],
+            EPOCH * SLOTS_PER_EPOCH + 999,
+        );
+        let v = verify(&account, EPOCH * SLOTS_PER_EPOCH + 1_100);
+        assert_eq!(v.inconsistencies.len(), 2, "{:?}", v.inconsistencies);
+        assert!(v.inconsistencies[0].contains("starts from 4000000 previous credits"));
+        assert!(v.inconsistencies[1].contains("fewer credits"));
+    }
+
+    #[test]
+    fn test_out_of_order_epochs_are_flagged() {
+        let account = account(
+            vec![(EPOCH, 100, 0), (EPOCH - 1, 200, 100)],
+            EPOCH * SLOTS_PER_EPOCH + 999,
+        );
+        let v = verify(&account, EPOCH * SLOTS_PER_EPOCH + 1_100);
+        assert!(!v.is_consistent());
+        assert!(v.inconsistencies[0].contains("out of order"));
+    }
+
+    #[test]
+    fn test_credits_beyond_the_rooted_slots_are_flagged() {
+        let account = healthy_account();
+        // With 1 credit per slot (no TVC) 8000 credits can't fit in 1000 slots
+        let v = verify_vote_account(
+            &account,
+            EpochInfo::from_slot(account.root_slot),
+            account.root_slot + 10,
+            32,
+            CreditsModel::new(1),
+        );
+        assert_eq!(v.inconsistencies.len(), 1);
+        assert!(v.inconsistencies[0].contains("exceed the 1000"));
+    }
+
+    #[test]
+    fn test_root_ahead_of_the_tip_is_flagged() {
+        let account = healthy_account();
+        let v = verify(&account, account.root_slot - 1);
+        assert!(
+            v.inconsistencies
+                .iter()
+                .any(|inconsistency| inconsistency.contains("ahead of the tip"))
+        );
+    }
+
+    #[test]
+    fn test_epoch_boundary_is_not_a_credit_mismatch() {
+        // The tip crossed into the next epoch, the root not yet: the tracker
+        // still reports the root's epoch
+        let account = healthy_account();
+        let v = verify(&account, (EPOCH + 1) * SLOTS_PER_EPOCH + 5);
+        assert!(v.is_consistent(), "{:?}", v.inconsistencies);
+        assert_eq!(v.credits_this_epoch, 8000);
+        assert_eq!(v.cli_credits_this_epoch, 0);
+    }
+
+    #[test]
+    fn test_format_verification() {
+        let tip_slot = EPOCH * SLOTS_PER_EPOCH + 1_531;
+        let text = format_verification(&verify(&healthy_account(), tip_slot));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[4].split_whitespace().collect::<Vec<_>>(),
+            vec!["Credits", "this", "epoch", "8000"]
+        );
+        assert!(text.contains("16000 (missed 8000, efficiency 50.00%)"));
+        assert!(text.contains("     812        11008000        11000000        8000"));
+        assert!(text.ends_with("Consistent"));
+
+        let mut account = healthy_account();
+        account.epoch_credits[2].2 = 11_000_001;
+        let text = format_verification(&verify(&account, tip_slot));
+        assert!(text.contains("Inconsistencies: 1\n  - Epoch 812 starts from"));
+    }
+}
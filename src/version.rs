@@ -0,0 +1,184 @@
+use crate::credits::{CreditsModel, MAX_CREDITS_PER_SLOT};
+use crate::metrics::Metrics;
+use crate::rpc::{RpcClient, RpcVersion};
+use crate::ws::VoteTracker;
+
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Feature gate account for timely vote credits (SIMD-0033)
+pub const TVC_FEATURE_ID: &str = "tvcF6b1TRz353zKuhBjinZkKzjmihXmBAHJdjNYw1sQ";
+
+/// Credits per rooted slot before TVC: one credit per vote that gets rooted
+pub const PRE_TVC_CREDITS_PER_SLOT: u64 = 1;
+
+/// How often the RPC version and feature set are re-checked
+const VERSION_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+
+/// Export the RPC node's version as `solana_rpc_version_info{version, feature_set} 1`
+pub async fn check_version<R: RpcClient>(rpc: &R, metrics: &Metrics) -> Result<RpcVersion> {
+    let version = rpc.get_version().await?;
+    let feature_set = version
+        .feature_set
+        .map(|f| f.to_string())
+        .unwrap_or_default();
+
+    // Drop the previous label set so an upgraded node reports a single series
+    metrics.rpc_version_info.reset();
+    metrics
+        .rpc_version_info
+        .with_label_values(&[&version.solana_core, &feature_set])
+        .set(1);
+    Ok(version)
+}
+
+/// Check whether TVC is active on the cluster and export `solana_tvc_feature_active`
+pub async fn check_tvc_feature<R: RpcClient>(rpc: &R, metrics: &Metrics) -> Result<bool> {
+    let active = rpc.is_feature_active(TVC_FEATURE_ID).await?;
+    metrics.tvc_feature_active.set(active as i64);
+    Ok(active)
+}
+
+/// Credits model for the cluster's TVC activation state
+pub fn credits_model_for(tvc_active: bool) -> CreditsModel {
+    if tvc_active {
+        CreditsModel::new(MAX_CREDITS_PER_SLOT)
+    } else {
+        CreditsModel::new(PRE_TVC_CREDITS_PER_SLOT)
+    }
+}
+
+/// Pick the credits model: the configured max credits per slot if set,
+/// otherwise detected from the TVC feature (TVC assumed if the check fails)
+pub async fn resolve_credits_model<R: RpcClient>(
+    rpc: &R,
+    max_credits_per_slot: Option<u64>,
+    metrics: &Metrics,
+) -> CreditsModel {
+    if let Some(max_credits) = max_credits_per_slot {
+        return CreditsModel::new(max_credits);
+    }
+    match check_tvc_feature(rpc, metrics).await {
+        Ok(active) => credits_model_for(active),
+        Err(e) => {
+            warn!("TVC feature check failed, assuming TVC is active: {:#}", e);
+            CreditsModel::default()
+        }
+    }
+}
+
+/// Check version and TVC activation at startup and then daily. With a
+/// tracker, switch it to the pre-TVC credit model when the feature is not
+/// active (None when the model is pinned by --max-credits-per-slot).
+pub async fn run_version_check<R: RpcClient>(
+    rpc: &R,
+    tracker: Option<Arc<RwLock<VoteTracker>>>,
+    metrics: &Metrics,
+) {
+    loop {
+        match check_version(rpc, metrics).await {
+            Ok(version) => info!(
+                "RPC node version {} (feature set {:?})",
+                version.solana_core, version.feature_set
+            ),
+            Err(e) => warn!("getVersion failed: {:#}", e),
+        }
+
+        // Keep the current model if the check fails rather than guessing
+        match check_tvc_feature(rpc, metrics).await {
+            Ok(active) => {
+                if let Some(tracker) = &tracker {
+                    let model = credits_model_for(active);
+                    let mut tracker = tracker.write().await;
+                    if tracker.credits_model() != model {
+                        info!(
+                            "TVC feature active: {}, using {} max credits per slot",
+                            active, model.max_credits_per_slot
+                        );
+                        tracker.set_credits_model(model);
+                    }
+                }
+            }
+            Err(e) => warn!("TVC feature check failed: {:#}", e),
+        }
+
+        tokio::time::sleep(VERSION_CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::RpcVoteAccounts;
+
+    struct VersionRpc {
+        tvc_active: bool,
+    }
+
+    impl RpcClient for VersionRpc {
+        async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+            Ok(RpcVoteAccounts::default())
+        }
+
+        async fn get_version(&self) -> Result<RpcVersion> {
+            Ok(RpcVersion {
+                solana_core: "1.18.23".to_string(),
+                feature_set: Some(4215500110),
+            })
+        }
+
+        async fn is_feature_active(&self, feature_id: &str) -> Result<bool> {
+            assert_eq!(feature_id, TVC_FEATURE_ID);
+            Ok(self.tvc_active)
+        }
+
+        async fn get_slot(&self) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_version_exports_info() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = VersionRpc { tvc_active: true };
+        check_version(&rpc, &metrics).await.unwrap();
+
+        assert_eq!(
+            metrics
+                .rpc_version_info
+                .with_label_values(&["1.18.23", "4215500110"])
+                .get(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tvc_inactive_falls_back_to_one_credit_per_slot() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = VersionRpc { tvc_active: false };
+        let active = check_tvc_feature(&rpc, &metrics).await.unwrap();
+
+        assert!(!active);
+        assert_eq!(metrics.tvc_feature_active.get(), 0);
+        assert_eq!(credits_model_for(active).max_credits_per_slot, 1);
+        assert_eq!(
+            credits_model_for(true).max_credits_per_slot,
+            MAX_CREDITS_PER_SLOT
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configured_credits_model_skips_detection() {
+        let metrics = Metrics::new().unwrap();
+        let rpc = VersionRpc { tvc_active: false };
+
+        let model = resolve_credits_model(&rpc, Some(4), &metrics).await;
+        assert_eq!(model, CreditsModel::new(4));
+
+        let model = resolve_credits_model(&rpc, None, &metrics).await;
+        assert_eq!(model, CreditsModel::new(1));
+    }
+}
@@ -0,0 +1,396 @@
+use crate::metrics::Metrics;
+use crate::rpc::RpcClient;
+
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Signatures requested per getSignaturesForAddress page (the RPC maximum)
+pub const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+/// Pages fetched per check at most; more new signatures than that are
+/// counted only up to there
+const MAX_PAGES_PER_CHECK: usize = 20;
+
+/// How often the vote account's new signatures are counted
+const VOTE_TXS_CHECK_INTERVAL: Duration = Duration::from_secs(180);
+
+/// Default base fee per signature
+pub const DEFAULT_LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Span of the checks the per-day fee estimate is based on
+const FEE_ESTIMATE_WINDOW_MS: u64 = 3_600_000;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// What a vote transaction costs: the base fee of each of its signatures
+/// (two when the authorized voter isn't the identity paying the fee)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoteFees {
+    pub lamports_per_signature: u64,
+    pub signatures_per_tx: u64,
+}
+
+impl Default for VoteFees {
+    fn default() -> Self {
+        Self {
+            lamports_per_signature: DEFAULT_LAMPORTS_PER_SIGNATURE,
+            signatures_per_tx: 1,
+        }
+    }
+}
+
+impl VoteFees {
+    pub fn lamports_per_tx(&self) -> u64 {
+        self.lamports_per_signature * self.signatures_per_tx
+    }
+}
+
+/// Vote transactions counted by the checks of the last hour
+#[derive(Debug, Clone, Default)]
+pub struct VoteTxRate {
+    /// `(start_ms, end_ms, count)` of each counted check, oldest first
+    checks: VecDeque<(u64, u64, u64)>,
+}
+
+impl VoteTxRate {
+    /// Add the `count` transactions landed between `start_ms` and `end_ms`
+    pub fn record(&mut self, start_ms: u64, end_ms: u64, count: u64) {
+        self.checks.push_back((start_ms, end_ms, count));
+    }
+
+    /// Transactions per day over the checks ending in the hour before
+    /// `now_ms`, `None` without one
+    pub fn per_day(&mut self, now_ms: u64) -> Option<f64> {
+        let cutoff = now_ms.saturating_sub(FEE_ESTIMATE_WINDOW_MS);
+        while self.checks.front().is_some_and(|&(_, end, _)| end < cutoff) {
+            self.checks.pop_front();
+        }
+        let &(start, _, _) = self.checks.front()?;
+        let &(_, end, _) = self.checks.back()?;
+        let count: u64 = self.checks.iter().map(|&(_, _, count)| count).sum();
+        let span_ms = end.saturating_sub(start).max(1);
+        Some(count as f64 * MS_PER_DAY / span_ms as f64)
+    }
+}
+
+/// Add the fees of `count` new transactions and update the per-day estimate,
+/// removing it when `txs_per_day` is unknown
+pub fn update_fee_metrics(
+    metrics: &Metrics,
+    fees: VoteFees,
+    count: Option<u64>,
+    txs_per_day: Option<f64>,
+) {
+    if let Some(count) = count {
+        metrics
+            .vote_fees_lamports
+            .with_label_values(&[])
+            .inc_by(count * fees.lamports_per_tx());
+    }
+    match txs_per_day {
+        Some(txs) => metrics
+            .vote_fees_per_day_estimate
+            .with_label_values(&[])
+            .set(txs * fees.lamports_per_tx() as f64),
+        None => metrics.vote_fees_per_day_estimate.reset(),
+    }
+}
+
+/// Counts the vote transactions landed for a vote account by paging
+/// getSignaturesForAddress back to the newest signature seen by the previous check
+#[derive(Debug, Clone)]
+pub struct VoteTxCounter {
+    vote_pubkey: String,
+    /// Whether the first check has set the starting point
+    started: bool,
+    /// Newest signature counted so far
+    last_seen: Option<String>,
+}
+
+impl VoteTxCounter {
+    pub fn new(vote_pubkey: &str) -> Self {
+        Self {
+            vote_pubkey: vote_pubkey.to_string(),
+            started: false,
+            last_seen: None,
+        }
+    }
+
+    /// Count the signatures landed since the previous check. The first check
+    /// only notes the newest signature and returns `None`, so the account's
+    /// history isn't counted. Pages end at the last seen signature or an empty
+    /// page, not at a short one, as nodes may return fewer than requested. A
+    /// failed page leaves the starting point as it was, for the next check.
+    pub async fn refresh<R: RpcClient>(
+        &mut self,
+        rpc: &R,
+        metrics: &Metrics,
+    ) -> Result<Option<u64>> {
+        if !self.started {
+            let newest = rpc
+                .get_signatures_for_address(&self.vote_pubkey, None, 1)
+                .await?;
+            self.last_seen = newest.into_iter().next().map(|entry| entry.signature);
+            self.started = true;
+            return Ok(None);
+        }
+
+        let mut newest = None;
+        let mut before: Option<String> = None;
+        let mut count = 0;
+        let mut reached = false;
+        for _ in 0..MAX_PAGES_PER_CHECK {
+            let page = rpc
+                .get_signatures_for_address(
+                    &self.vote_pubkey,
+                    before.as_deref(),
+                    SIGNATURES_PAGE_LIMIT,
+                )
+                .await?;
+            if newest.is_none() {
+                newest = page.first().map(|entry| entry.signature.clone());
+            }
+            let seen = self
+                .last_seen
+                .as_ref()
+                .and_then(|last_seen| page.iter().position(|entry| &entry.signature == last_seen));
+            if let Some(position) = seen {
+                count += position;
+                reached = true;
+                break;
+            }
+            count += page.len();
+            match page.last() {
+                Some(oldest) => before = Some(oldest.signature.clone()),
+                None => {
+                    reached = true;
+                    break;
+                }
+            }
+        }
+        if !reached {
+            warn!(
+                "More than {} vote transactions since the last check, older ones not counted",
+                count
+            );
+        }
+        if newest.is_some() {
+            self.last_seen = newest;
+        }
+
+        let count = count as u64;
+        debug!("{} new vote transactions", count);
+        metrics.vote_transactions.inc_by(count);
+        Ok(Some(count))
+    }
+}
+
+/// Keep counting the vote account's landed transactions and their fees
+/// (--count-vote-txs)
+pub async fn run_vote_tx_counter<R: RpcClient>(
+    rpc: &R,
+    vote_pubkey: &str,
+    fees: VoteFees,
+    metrics: &Metrics,
+) {
+    let mut counter = VoteTxCounter::new(vote_pubkey);
+    let mut rate = VoteTxRate::default();
+    let base = Instant::now();
+    let mut last_check: Option<u64> = None;
+    loop {
+        let started = base.elapsed().as_millis() as u64;
+        let mut counted = None;
+        match counter.refresh(rpc, metrics).await {
+            Ok(count) => {
+                if let (Some(count), Some(last_check)) = (count, last_check) {
+                    let minutes = started.saturating_sub(last_check).max(1) as f64 / 60_000.0;
+                    metrics
+                        .vote_transactions_per_minute
+                        .set(count as f64 / minutes);
+                    rate.record(last_check, started, count);
+                    counted = Some(count);
+                }
+                last_check = Some(started);
+            }
+            Err(e) => warn!("Vote transaction count failed: {:#}", e),
+        }
+        update_fee_metrics(metrics, fees, counted, rate.per_day(started));
+        tokio::time::sleep(VOTE_TXS_CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{RpcSignature, RpcVersion, RpcVoteAccounts};
+    use anyhow::anyhow;
+    use std::sync::Mutex;
+
+    /// Serves `signatures` (newest first) in pages of at most `page_cap`,
+    /// recording the `before` of each request
+    struct SignaturesRpc {
+        signatures: Mutex<Vec<String>>,
+        page_cap: usize,
+        requests: Mutex<Vec<Option<String>>>,
+    }
+
+    impl SignaturesRpc {
+        fn new(page_cap: usize) -> Self {
+            Self {
+                signatures: Mutex::new(vec!["s0".to_string()]),
+                page_cap,
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Land `count` more transactions
+        fn land(&self, count: usize) {
+            let mut signatures = self.signatures.lock().unwrap();
+            let next = signatures.len();
+            for i in next..next + count {
+                signatures.insert(0, format!("s{}", i));
+            }
+        }
+    }
+
+    impl RpcClient for SignaturesRpc {
+        async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_version(&self) -> Result<RpcVersion> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_slot(&self) -> Result<u64> {
+            Err(anyhow!("not used"))
+        }
+
+        async fn get_signatures_for_address(
+            &self,
+            address: &str,
+            before: Option<&str>,
+            limit: usize,
+        ) -> Result<Vec<RpcSignature>> {
+            assert_eq!(address, "Vote111");
+            self.requests
+                .lock()
+                .unwrap()
+                .push(before.map(str::to_string));
+            let signatures = self.signatures.lock().unwrap();
+            let start = before.map_or(0, |before| {
+                signatures.iter().position(|s| s == before).unwrap() + 1
+            });
+            Ok(signatures[start..]
+                .iter()
+                .take(limit.min(self.page_cap))
+                .map(|signature| RpcSignature {
+                    signature: signature.clone(),
+                    slot: 0,
+                    err: None,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_counts_only_new_signatures() {
+        let rpc = SignaturesRpc::new(SIGNATURES_PAGE_LIMIT);
+        let metrics = Metrics::new().unwrap();
+        let mut counter = VoteTxCounter::new("Vote111");
+
+        // The history before the first check isn't counted
+        rpc.land(10);
+        assert_eq!(counter.refresh(&rpc, &metrics).await.unwrap(), None);
+        rpc.land(5);
+        assert_eq!(counter.refresh(&rpc, &metrics).await.unwrap(), Some(5));
+        assert_eq!(counter.refresh(&rpc, &metrics).await.unwrap(), Some(0));
+        rpc.land(3);
+        assert_eq!(counter.refresh(&rpc, &metrics).await.unwrap(), Some(3));
+        assert_eq!(metrics.vote_transactions.get(), 8);
+    }
+
+    #[test]
+    fn test_fee_arithmetic() {
+        let fees = VoteFees {
+            lamports_per_signature: 5000,
+            signatures_per_tx: 2,
+        };
+        assert_eq!(VoteFees::default().lamports_per_tx(), 5000);
+        assert_eq!(fees.lamports_per_tx(), 10_000);
+
+        // 450 transactions every 3 minutes: one per 0.4s slot
+        let mut rate = VoteTxRate::default();
+        rate.record(0, 180_000, 450);
+        rate.record(180_000, 360_000, 450);
+        assert_eq!(rate.per_day(360_000), Some(216_000.0));
+
+        let metrics = Metrics::new().unwrap();
+        update_fee_metrics(&metrics, fees, Some(450), rate.per_day(360_000));
+        update_fee_metrics(&metrics, fees, Some(450), rate.per_day(360_000));
+        assert_eq!(
+            metrics.vote_fees_lamports.with_label_values(&[]).get(),
+            9_000_000
+        );
+        assert_eq!(
+            metrics
+                .vote_fees_per_day_estimate
+                .with_label_values(&[])
+                .get(),
+            2_160_000_000.0
+        );
+
+        // Only the last hour counts
+        rate.record(4_000_000, 4_180_000, 900);
+        assert_eq!(rate.per_day(4_180_000), Some(432_000.0));
+    }
+
+    #[test]
+    fn test_fee_metrics_absent_without_counts() {
+        let metrics = Metrics::new().unwrap();
+        let fees = VoteFees::default();
+        let mut rate = VoteTxRate::default();
+        // The first check only sets the starting point
+        update_fee_metrics(&metrics, fees, None, rate.per_day(0));
+        let text = metrics.render().1;
+        assert!(!text.contains("solana_vote_fees_lamports_total"));
+        assert!(!text.contains("solana_vote_fees_per_day_estimate"));
+
+        rate.record(0, 180_000, 450);
+        update_fee_metrics(&metrics, fees, Some(450), rate.per_day(180_000));
+        let text = metrics.render().1;
+        assert!(text.contains(r#"solana_vote_fees_lamports_total{source="derived"} 2250000"#));
+        assert!(text.contains(r#"solana_vote_fees_per_day_estimate{source="derived"} 1080000000"#));
+
+        // Checks failing for over an hour remove the estimate, not the total
+        update_fee_metrics(&metrics, fees, None, rate.per_day(3_900_000));
+        let text = metrics.render().1;
+        assert!(text.contains(r#"solana_vote_fees_lamports_total{source="derived"} 2250000"#));
+        assert!(!text.contains("solana_vote_fees_per_day_estimate"));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_pages_are_followed() {
+        // The node returns at most 2 signatures whatever the limit
+        let rpc = SignaturesRpc::new(2);
+        let metrics = Metrics::new().unwrap();
+        let mut counter = VoteTxCounter::new("Vote111");
+
+        assert_eq!(counter.refresh(&rpc, &metrics).await.unwrap(), None);
+        rpc.land(5);
+        rpc.requests.lock().unwrap().clear();
+        assert_eq!(counter.refresh(&rpc, &metrics).await.unwrap(), Some(5));
+        assert_eq!(
+            *rpc.requests.lock().unwrap(),
+            vec![None, Some("s4".to_string()), Some("s2".to_string())]
+        );
+        assert_eq!(metrics.vote_transactions.get(), 5);
+    }
+}
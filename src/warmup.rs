@@ -0,0 +1,160 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+
+/// Default notifications to process before the performance gauges are exported (ws mode)
+pub const DEFAULT_WARMUP_NOTIFICATIONS: u64 = 10;
+
+/// Default slots the root has to advance by before the performance gauges
+/// are exported (ws mode)
+pub const DEFAULT_WARMUP_ROOTED_SLOTS: u64 = 32;
+
+/// Families left out of `/metrics` while warming up, by name prefix. Right
+/// after startup they read zero or worse (an efficiency of 0, a latency of
+/// 17), which would end up in long-range aggregates; absent series are
+/// better than wrong ones.
+pub const WARMUP_SUPPRESSED: &[&str] = &[
+    "solana_vote_credits_efficiency",
+    "solana_vote_credits_per_slot",
+    "solana_vote_credits_histogram",
+    "solana_vote_credits_projected",
+    "solana_vote_latency_slots",
+    "solana_vote_efficiency_by_hour",
+    "solana_missed_vote_credits",
+    "missed_vote_credits",
+    "solana_perfect_slot_streak",
+];
+
+/// Data needed before the performance gauges are exported (--warmup-* flags)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmupConfig {
+    pub notifications: u64,
+    pub rooted_slots: u64,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            notifications: DEFAULT_WARMUP_NOTIFICATIONS,
+            rooted_slots: DEFAULT_WARMUP_ROOTED_SLOTS,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct WarmupProgress {
+    notifications: u64,
+    first_root: Option<u64>,
+    rooted_slots: u64,
+}
+
+/// Whether the tracker has seen enough data for its performance gauges to be
+/// meaningful: one full poll, or `notifications` notifications over which the
+/// root advanced by `rooted_slots` slots. Once warm it stays warm.
+#[derive(Debug)]
+pub struct Warmup {
+    config: WarmupConfig,
+    progress: Mutex<WarmupProgress>,
+    warm: AtomicBool,
+}
+
+impl Default for Warmup {
+    /// Warm from the start
+    fn default() -> Self {
+        Self::new(WarmupConfig {
+            notifications: 0,
+            rooted_slots: 0,
+        })
+    }
+}
+
+impl Warmup {
+    pub fn new(config: WarmupConfig) -> Self {
+        Self {
+            config,
+            progress: Mutex::new(WarmupProgress::default()),
+            warm: AtomicBool::new(config.notifications == 0 && config.rooted_slots == 0),
+        }
+    }
+
+    pub fn is_warm(&self) -> bool {
+        self.warm.load(Ordering::Relaxed)
+    }
+
+    /// Count a processed notification with the root it carried. Returns true
+    /// if this notification finished the warm-up.
+    pub fn observe_notification(&self, root_slot: Option<u64>) -> bool {
+        if self.is_warm() {
+            return false;
+        }
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        progress.notifications += 1;
+        if let Some(root) = root_slot {
+            let first = *progress.first_root.get_or_insert(root);
+            progress.rooted_slots = progress.rooted_slots.max(root.saturating_sub(first));
+        }
+        if progress.notifications >= self.config.notifications
+            && progress.rooted_slots >= self.config.rooted_slots
+        {
+            info!(
+                "Warmed up after {} notifications and {} rooted slots",
+                progress.notifications, progress.rooted_slots
+            );
+            return self.finish();
+        }
+        false
+    }
+
+    /// End the warm-up (a full poll was applied). Returns true if it was
+    /// still warming.
+    pub fn finish(&self) -> bool {
+        !self.warm.swap(true, Ordering::Relaxed)
+    }
+
+    /// Whether the family `name` is left out of `/metrics` right now
+    pub fn suppresses(&self, name: &str) -> bool {
+        !self.is_warm()
+            && WARMUP_SUPPRESSED
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warm_after_notifications_and_rooted_slots() {
+        let warmup = Warmup::new(WarmupConfig {
+            notifications: 3,
+            rooted_slots: 32,
+        });
+        assert!(warmup.suppresses("solana_vote_credits_efficiency_5m"));
+        assert!(!warmup.suppresses("ws_connected"));
+
+        // Enough notifications, but the root has advanced by only 2 slots
+        for root in [100, 101, 102] {
+            assert!(!warmup.observe_notification(Some(root)));
+        }
+        assert!(!warmup.is_warm());
+        assert!(!warmup.observe_notification(None));
+        assert!(warmup.observe_notification(Some(132)));
+        assert!(warmup.is_warm());
+        assert!(!warmup.suppresses("solana_vote_credits_efficiency_5m"));
+
+        // Stays warm
+        assert!(!warmup.observe_notification(Some(50)));
+        assert!(warmup.is_warm());
+    }
+
+    #[test]
+    fn test_full_poll_ends_warmup() {
+        let warmup = Warmup::new(WarmupConfig::default());
+        assert!(!warmup.is_warm());
+        assert!(warmup.finish());
+        assert!(!warmup.finish());
+        assert!(warmup.is_warm());
+        assert!(Warmup::default().is_warm());
+    }
+}
@@ -0,0 +1,448 @@
+use crate::clock::unix_now;
+use crate::cluster::ClusterComparison;
+use crate::credits::CreditsModel;
+use crate::metrics::Metrics;
+use crate::poller::{PollState, snapshot_from_vote_account};
+use crate::rpc::{ClusterScan, RpcClient, RpcVoteAccount, RpcVoteAccounts};
+
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How the watchlist validators are fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WatchlistFetch {
+    /// One unfiltered getVoteAccounts per interval, which also exports the
+    /// cluster stake and ranks
+    #[default]
+    Bulk,
+    /// One getVoteAccounts filtered to a validator at a time, staggered
+    /// evenly across the interval
+    PerValidator,
+}
+
+/// Read a watchlist file: one vote pubkey per line, `#` starts a comment
+pub fn load_watchlist(path: &str) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read watchlist file {}", path))?;
+    Ok(parse_watchlist(&content))
+}
+
+fn parse_watchlist(content: &str) -> Vec<String> {
+    let mut pubkeys: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let pubkey = line.split('#').next().unwrap_or_default().trim();
+        if !pubkey.is_empty() && !pubkeys.iter().any(|p| p == pubkey) {
+            pubkeys.push(pubkey.to_string());
+        }
+    }
+    pubkeys
+}
+
+/// Tracks a set of vote accounts from periodic `getVoteAccounts` snapshots
+#[derive(Debug)]
+pub struct Watchlist {
+    pubkeys: Vec<String>,
+    credits_model: CreditsModel,
+    /// Per-validator state, with the same epoch rollover handling as poll mode
+    states: HashMap<String, PollState>,
+    /// File the pubkeys are re-read from at the start of every round
+    file: Option<String>,
+}
+
+impl Watchlist {
+    pub fn new(pubkeys: Vec<String>, credits_model: CreditsModel) -> Self {
+        Self {
+            pubkeys,
+            credits_model,
+            states: HashMap::new(),
+            file: None,
+        }
+    }
+
+    /// Pick up edits of the watchlist `path` without a restart
+    pub fn with_file(mut self, path: &str) -> Self {
+        self.file = Some(path.to_string());
+        self
+    }
+
+    pub fn pubkeys(&self) -> &[String] {
+        &self.pubkeys
+    }
+
+    /// Replace the tracked pubkeys, dropping the state and metrics of the
+    /// ones no longer listed
+    pub fn set_pubkeys(&mut self, pubkeys: Vec<String>, metrics: &Metrics) {
+        for removed in self.pubkeys.iter().filter(|p| !pubkeys.contains(p)) {
+            self.states.remove(removed);
+            let labels = [removed.as_str()];
+            let _ = metrics.watchlist_credits_epoch.remove_label_values(&labels);
+            let _ = metrics
+                .watchlist_efficiency_epoch
+                .remove_label_values(&labels);
+            let _ = metrics.watchlist_missed_epoch.remove_label_values(&labels);
+            let _ = metrics.watchlist_delinquent.remove_label_values(&labels);
+            let _ = metrics.watchlist_last_refresh.remove_label_values(&labels);
+        }
+        self.pubkeys = pubkeys;
+    }
+
+    /// Re-read the watchlist file, keeping the current list if it can't be read
+    pub fn reload(&mut self, metrics: &Metrics) {
+        let Some(path) = &self.file else {
+            return;
+        };
+        match load_watchlist(path) {
+            Ok(pubkeys) if pubkeys != self.pubkeys => {
+                info!(
+                    "Watchlist {} changed: {} vote accounts, was {}",
+                    path,
+                    pubkeys.len(),
+                    self.pubkeys.len()
+                );
+                self.set_pubkeys(pubkeys, metrics);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Keeping the watchlist: {:#}", e),
+        }
+    }
+
+    /// Fold a cluster-wide `getVoteAccounts` response into the per-validator state
+    pub fn apply(&mut self, accounts: &RpcVoteAccounts, metrics: &Metrics) {
+        for pubkey in self.pubkeys.clone() {
+            self.apply_one(&pubkey, accounts, metrics);
+        }
+    }
+
+    /// Fold the account of `pubkey` from a (filtered) `getVoteAccounts`
+    /// response into its state
+    pub fn apply_one(&mut self, pubkey: &str, accounts: &RpcVoteAccounts, metrics: &Metrics) {
+        let Some((account, delinquent)) = accounts.find(pubkey) else {
+            warn!(
+                "Watchlist vote account {} not found in getVoteAccounts",
+                pubkey
+            );
+            return;
+        };
+        self.apply_account(account, delinquent, metrics);
+    }
+
+    fn apply_account(&mut self, account: &RpcVoteAccount, delinquent: bool, metrics: &Metrics) {
+        let pubkey = &account.vote_pubkey;
+        let snapshot = snapshot_from_vote_account(account, delinquent, self.credits_model);
+        self.states
+            .entry(pubkey.clone())
+            .or_default()
+            .apply(&snapshot);
+
+        let labels = [pubkey.as_str()];
+        metrics
+            .watchlist_credits_epoch
+            .with_label_values(&labels)
+            .set(snapshot.credits_this_epoch as i64);
+        metrics
+            .watchlist_efficiency_epoch
+            .with_label_values(&labels)
+            .set(snapshot.efficiency());
+        metrics
+            .watchlist_missed_epoch
+            .with_label_values(&labels)
+            .set(snapshot.missed_this_epoch() as i64);
+        metrics
+            .watchlist_delinquent
+            .with_label_values(&labels)
+            .set(delinquent as i64);
+        metrics
+            .watchlist_last_refresh
+            .with_label_values(&labels)
+            .set(unix_now() as i64);
+    }
+}
+
+/// Round-robin order of the per-validator fetches: each validator once per
+/// round, in list order. Validators added mid-round are fetched in that
+/// round, removed ones are skipped.
+#[derive(Debug, Default)]
+pub struct RefreshSchedule {
+    /// Validators fetched this round
+    fetched: HashSet<String>,
+}
+
+impl RefreshSchedule {
+    /// First validator of `pubkeys` not fetched this round
+    fn pending<'a>(&self, pubkeys: &'a [String]) -> Option<&'a String> {
+        pubkeys.iter().find(|p| !self.fetched.contains(*p))
+    }
+
+    /// Whether every validator of `pubkeys` was fetched this round (or none
+    /// was fetched yet)
+    pub fn round_done(&self, pubkeys: &[String]) -> bool {
+        self.fetched.is_empty() || self.pending(pubkeys).is_none()
+    }
+
+    pub fn start_round(&mut self) {
+        self.fetched.clear();
+    }
+
+    /// The validator to fetch next (None once all of `pubkeys` were fetched
+    /// this round)
+    pub fn next<'a>(&mut self, pubkeys: &'a [String]) -> Option<&'a str> {
+        let pubkey = self.pending(pubkeys)?;
+        self.fetched.insert(pubkey.clone());
+        Some(pubkey)
+    }
+}
+
+/// Time between the per-validator fetches to spread a round over `interval`
+pub fn fetch_spacing(interval: Duration, validators: usize) -> Duration {
+    interval / validators.max(1) as u32
+}
+
+/// Refresh the watchlist (and the cluster stake and rank metrics) from the
+/// cluster-wide vote accounts forever
+pub async fn run_watchlist<R: RpcClient>(
+    rpc: &R,
+    mut watchlist: Watchlist,
+    comparison: &ClusterComparison,
+    interval: Duration,
+    metrics: &Metrics,
+) {
+    info!(
+        "Watching {} vote accounts every {:?}",
+        watchlist.pubkeys().len(),
+        interval
+    );
+
+    loop {
+        watchlist.reload(metrics);
+        match rpc.get_vote_accounts(None).await {
+            Ok(accounts) => {
+                watchlist.apply(&accounts, metrics);
+                let scan = ClusterScan::from_accounts(&accounts);
+                metrics.set_cluster_stake(&scan.stake);
+                comparison.export(&scan, Some(&accounts), metrics);
+            }
+            Err(e) => {
+                metrics.rpc_errors.inc();
+                warn!("Watchlist getVoteAccounts failed: {:#}", e);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Refresh the watchlist one validator at a time with filtered
+/// `getVoteAccounts`, each once per `interval` with the requests staggered
+/// evenly across it, forever. A failed fetch is not retried before the
+/// validator's next turn, and a fetch slower than the spacing delays the
+/// next one rather than bunching them up.
+pub async fn run_watchlist_per_validator<R: RpcClient>(
+    rpc: &R,
+    mut watchlist: Watchlist,
+    interval: Duration,
+    metrics: &Metrics,
+) {
+    info!(
+        "Watching {} vote accounts one at a time, each every {:?}",
+        watchlist.pubkeys().len(),
+        interval
+    );
+
+    let mut schedule = RefreshSchedule::default();
+    loop {
+        let started = Instant::now();
+        if schedule.round_done(watchlist.pubkeys()) {
+            schedule.start_round();
+            watchlist.reload(metrics);
+        }
+        let spacing = fetch_spacing(interval, watchlist.pubkeys().len());
+        if let Some(pubkey) = schedule.next(watchlist.pubkeys()).map(str::to_string) {
+            refresh_one(rpc, &mut watchlist, &pubkey, metrics).await;
+        }
+        tokio::time::sleep(spacing.saturating_sub(started.elapsed())).await;
+    }
+}
+
+/// Fetch and apply one watchlist validator, counting a failure as an RPC error
+async fn refresh_one<R: RpcClient>(
+    rpc: &R,
+    watchlist: &mut Watchlist,
+    pubkey: &str,
+    metrics: &Metrics,
+) {
+    match rpc.get_vote_accounts(Some(pubkey)).await {
+        Ok(accounts) => watchlist.apply_one(pubkey, &accounts, metrics),
+        Err(e) => {
+            metrics.rpc_errors.inc();
+            warn!("Watchlist getVoteAccounts for {} failed: {:#}", pubkey, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poller::tests::{MutableTestRpc, vote_account};
+    use crate::ws::SLOTS_PER_EPOCH;
+
+    fn pubkeys(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_watchlist() {
+        let content = "# my delegations\nA\n\n  B  # backup\nA\n";
+        assert_eq!(parse_watchlist(content), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_watchlist_apply_exports_per_validator_metrics() {
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        let mut watchlist = Watchlist::new(
+            vec!["A".to_string(), "B".to_string()],
+            CreditsModel::default(),
+        );
+
+        let accounts = RpcVoteAccounts {
+            current: vec![
+                vote_account("A", vec![(10, 1600, 0)], epoch_start + 99),
+                vote_account("Other", vec![(10, 1600, 0)], epoch_start + 99),
+            ],
+            delinquent: vec![vote_account("B", vec![(10, 800, 0)], epoch_start + 99)],
+        };
+        watchlist.apply(&accounts, &metrics);
+
+        let credits = |pk: &str| {
+            metrics
+                .watchlist_credits_epoch
+                .with_label_values(&[pk])
+                .get()
+        };
+        assert_eq!(credits("A"), 1600);
+        assert_eq!(credits("B"), 800);
+        assert_eq!(
+            metrics
+                .watchlist_efficiency_epoch
+                .with_label_values(&["B"])
+                .get(),
+            0.5
+        );
+        assert_eq!(
+            metrics.watchlist_delinquent.with_label_values(&["A"]).get(),
+            0
+        );
+        assert_eq!(
+            metrics.watchlist_delinquent.with_label_values(&["B"]).get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .watchlist_missed_epoch
+                .with_label_values(&["B"])
+                .get(),
+            800
+        );
+
+        assert!(
+            metrics
+                .watchlist_last_refresh
+                .with_label_values(&["B"])
+                .get()
+                > 0
+        );
+
+        // Validators outside the watchlist are not exported
+        let rendered = metrics.render().1;
+        assert!(!rendered.contains("vote_pubkey=\"Other\""));
+    }
+
+    #[test]
+    fn test_refresh_schedule_follows_list_changes() {
+        let mut schedule = RefreshSchedule::default();
+        let list = pubkeys(&["A", "B", "C"]);
+        assert!(schedule.round_done(&list));
+        assert_eq!(schedule.next(&list), Some("A"));
+        assert!(!schedule.round_done(&list));
+        assert_eq!(schedule.next(&list), Some("B"));
+        assert_eq!(schedule.next(&list), Some("C"));
+        assert!(schedule.round_done(&list));
+        assert_eq!(schedule.next(&list), None);
+        schedule.start_round();
+        assert_eq!(schedule.next(&list), Some("A"));
+
+        // B is dropped and D added mid-round: C and D finish the round
+        let list = pubkeys(&["A", "C", "D"]);
+        assert_eq!(schedule.next(&list), Some("C"));
+        assert_eq!(schedule.next(&list), Some("D"));
+        assert!(schedule.round_done(&list));
+        schedule.start_round();
+        assert_eq!(schedule.next(&list), Some("A"));
+
+        assert_eq!(schedule.next(&[]), None);
+        assert_eq!(
+            fetch_spacing(Duration::from_secs(60), 40),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            fetch_spacing(Duration::from_secs(60), 0),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_per_validator_fetches_are_staggered_and_reloaded() {
+        let path = std::env::temp_dir().join(format!("tvc_watchlist_{}", std::process::id()));
+        std::fs::write(&path, "A\nB\nC\n").unwrap();
+        let path = path.to_str().unwrap().to_string();
+        let metrics = Metrics::new().unwrap();
+        let epoch_start = 10 * SLOTS_PER_EPOCH;
+        // C is missing from the cluster: skipped until its next turn
+        let rpc = MutableTestRpc::new(
+            ["A", "B", "D"]
+                .iter()
+                .map(|pk| vote_account(pk, vec![(10, 1600, 0)], epoch_start + 99))
+                .collect(),
+        );
+        let watchlist = Watchlist::new(load_watchlist(&path).unwrap(), CreditsModel::default())
+            .with_file(&path);
+
+        // 20s apart for 3 validators, then 30s apart for 2 once the file
+        // changes: A, B, C at 0s, 20s, 40s and A, D at 60s, 90s
+        let run = run_watchlist_per_validator(&rpc, watchlist, Duration::from_secs(60), &metrics);
+        let edit = async {
+            tokio::time::sleep(Duration::from_secs(50)).await;
+            std::fs::write(&path, "A\nD # new\n").unwrap();
+        };
+        let _ =
+            tokio::time::timeout(Duration::from_secs(100), async { tokio::join!(run, edit) }).await;
+
+        let requests = rpc.vote_account_requests.lock().unwrap().clone();
+        let expected: Vec<_> = ["A", "B", "C", "A", "D"]
+            .iter()
+            .map(|pk| Some(pk.to_string()))
+            .collect();
+        assert_eq!(requests, expected);
+
+        let refreshed = |pk: &str| {
+            metrics
+                .watchlist_last_refresh
+                .with_label_values(&[pk])
+                .get()
+        };
+        assert!(refreshed("A") > 0);
+        assert!(refreshed("D") > 0);
+        assert_eq!(
+            metrics
+                .watchlist_credits_epoch
+                .with_label_values(&["D"])
+                .get(),
+            1600
+        );
+        // B's series went with it
+        let rendered = metrics.render().1;
+        assert!(!rendered.contains("vote_pubkey=\"B\""));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
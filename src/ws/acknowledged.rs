@@ -0,0 +1,220 @@
+use crate::ws::shape::AccountShape;
+use crate::ws::types::{AccountData, NotificationParams};
+
+use serde_json::Value;
+use std::fmt;
+
+/// Slots a notification may be ahead of the finalized slot fetched over HTTP
+/// and still count as finalized. Confirmed and processed banks run ~32 slots
+/// ahead of the root; the first notification also arrives a little later.
+pub const UNFINALIZED_LEAD_SLOTS: u64 = 16;
+
+/// Encoding of account data as delivered by the provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataEncoding {
+    /// Parsed by the node (`{"program": ..., "parsed": ...}`)
+    JsonParsed,
+    /// Encoded string (`[data, encoding]`), e.g. when the node fell back to base64
+    Encoded(String),
+    /// Neither shape
+    Unrecognized,
+}
+
+impl DataEncoding {
+    pub fn of(data: &AccountData) -> Self {
+        match data {
+            AccountData::Parsed { .. } => Self::JsonParsed,
+            AccountData::Raw(_, encoding) => Self::Encoded(encoding.clone()),
+        }
+    }
+
+    /// Encoding of the `data` field of a getAccountInfo value
+    pub fn of_value(data: &Value) -> Self {
+        serde_json::from_value::<AccountData>(data.clone())
+            .map(|data| Self::of(&data))
+            .unwrap_or(Self::Unrecognized)
+    }
+}
+
+impl fmt::Display for DataEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::JsonParsed => write!(f, "jsonParsed"),
+            Self::Encoded(encoding) => write!(f, "{}", encoding),
+            Self::Unrecognized => write!(f, "unrecognized"),
+        }
+    }
+}
+
+/// Commitment the notifications are delivered at, judged from how far the
+/// first one is ahead of the finalized slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectiveCommitment {
+    Finalized,
+    /// Ahead of the root: confirmed or processed, one sample can't tell which
+    Unfinalized,
+}
+
+impl EffectiveCommitment {
+    pub const ALL: [Self; 2] = [Self::Finalized, Self::Unfinalized];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Finalized => "finalized",
+            Self::Unfinalized => "unfinalized",
+        }
+    }
+}
+
+/// The vote account fetched over HTTP with the subscription's parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedAccount {
+    /// Context slot of the response
+    pub slot: u64,
+    /// Encoding of the data; None if the account does not exist
+    pub encoding: Option<DataEncoding>,
+    /// Layout of the data; None if the account does not exist or the data
+    /// is in neither known shape
+    pub shape: Option<AccountShape>,
+}
+
+/// What the provider delivers on the subscription, compared with the same
+/// request over HTTP
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcknowledgedSubscription {
+    pub http_encoding: Option<DataEncoding>,
+    pub ws_encoding: DataEncoding,
+    /// Slots the first notification is ahead of the HTTP response
+    pub lead_slots: u64,
+}
+
+impl AcknowledgedSubscription {
+    /// Compare the first notification of a subscription with the HTTP fetch
+    pub fn compare(fetched: &FetchedAccount, first: &NotificationParams) -> Self {
+        Self {
+            http_encoding: fetched.encoding.clone(),
+            ws_encoding: DataEncoding::of(&first.result.value.data),
+            lead_slots: first.result.context.slot.saturating_sub(fetched.slot),
+        }
+    }
+
+    /// The subscription delivers another encoding than HTTP for the same request
+    pub fn encoding_mismatch(&self) -> bool {
+        self.http_encoding
+            .as_ref()
+            .is_some_and(|http| *http != self.ws_encoding)
+    }
+
+    pub fn commitment(&self) -> EffectiveCommitment {
+        if self.lead_slots > UNFINALIZED_LEAD_SLOTS {
+            EffectiveCommitment::Unfinalized
+        } else {
+            EffectiveCommitment::Finalized
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARSED_DATA: &str = r#"{
+        "program": "vote",
+        "parsed": {
+            "info": {"votes": [], "rootSlot": null, "epochCredits": []},
+            "type": "vote"
+        }
+    }"#;
+
+    fn notification(slot: u64, data: &str) -> NotificationParams {
+        serde_json::from_str(&format!(
+            r#"{{
+                "result": {{
+                    "context": {{"slot": {}}},
+                    "value": {{
+                        "lamports": 1,
+                        "data": {},
+                        "owner": "Vote111111111111111111111111111111111111111",
+                        "executable": false,
+                        "rentEpoch": 0
+                    }}
+                }},
+                "subscription": 1
+            }}"#,
+            slot, data
+        ))
+        .unwrap()
+    }
+
+    fn fetched(slot: u64, data: &str) -> FetchedAccount {
+        FetchedAccount {
+            slot,
+            encoding: Some(DataEncoding::of_value(&serde_json::from_str(data).unwrap())),
+            shape: None,
+        }
+    }
+
+    #[test]
+    fn test_encoding_of_value() {
+        let encoding = |data: &str| DataEncoding::of_value(&serde_json::from_str(data).unwrap());
+        assert_eq!(encoding(PARSED_DATA), DataEncoding::JsonParsed);
+        assert_eq!(
+            encoding(r#"["AAAA", "base64"]"#),
+            DataEncoding::Encoded("base64".to_string())
+        );
+        assert_eq!(
+            encoding(r#"{"program": "vote"}"#),
+            DataEncoding::Unrecognized
+        );
+        assert_eq!(DataEncoding::JsonParsed.to_string(), "jsonParsed");
+    }
+
+    #[test]
+    fn test_downgraded_encoding_is_a_mismatch() {
+        let ack = AcknowledgedSubscription::compare(
+            &fetched(1000, PARSED_DATA),
+            &notification(1002, r#"["AAAA", "base64"]"#),
+        );
+        assert!(ack.encoding_mismatch());
+        assert_eq!(ack.ws_encoding, DataEncoding::Encoded("base64".to_string()));
+        assert_eq!(ack.commitment(), EffectiveCommitment::Finalized);
+
+        // The account is not parseable at all: both fall back alike
+        let ack = AcknowledgedSubscription::compare(
+            &fetched(1000, r#"["AAAA", "base64"]"#),
+            &notification(1001, r#"["AAAA", "base64"]"#),
+        );
+        assert!(!ack.encoding_mismatch());
+
+        let ack = AcknowledgedSubscription::compare(
+            &FetchedAccount {
+                slot: 1000,
+                encoding: None,
+                shape: None,
+            },
+            &notification(1001, PARSED_DATA),
+        );
+        assert!(!ack.encoding_mismatch());
+    }
+
+    #[test]
+    fn test_commitment_from_lead_over_finalized_slot() {
+        let at = |slot| {
+            AcknowledgedSubscription::compare(
+                &fetched(1000, PARSED_DATA),
+                &notification(slot, PARSED_DATA),
+            )
+            .commitment()
+        };
+        assert_eq!(at(990), EffectiveCommitment::Finalized);
+        assert_eq!(
+            at(1000 + UNFINALIZED_LEAD_SLOTS),
+            EffectiveCommitment::Finalized
+        );
+        assert_eq!(
+            at(1000 + UNFINALIZED_LEAD_SLOTS + 1),
+            EffectiveCommitment::Unfinalized
+        );
+        assert_eq!(at(1033), EffectiveCommitment::Unfinalized);
+    }
+}
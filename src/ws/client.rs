@@ -1,25 +1,127 @@
-use crate::metrics::Metrics;
-use crate::ws::tracker::VoteTracker;
+use crate::clock::unix_now;
+use crate::cluster::ClusterAverages;
+use crate::debug_dump::DebugState;
+use crate::discontinuity::{Discontinuities, DiscontinuityEvent};
+use crate::events::{EventSender, TrackerEvent, emit};
+use crate::journal::{Journal, JournalUpdate};
+use crate::metrics::{DisabledMetrics, HistogramWindow, Metrics};
+use crate::poller::{CreditHistory, DEFAULT_MIN_CREDIT_HISTORY_EPOCHS};
+use crate::proxy::{ProxyUrl, WsStream, connect_ws};
+use crate::rent::{RentExemption, export_balance};
+use crate::resolve::ResolveOverride;
+use crate::rpc::{COMMITMENT, HttpRpcClient};
+use crate::ws::acknowledged::{AcknowledgedSubscription, EffectiveCommitment, FetchedAccount};
+use crate::ws::connection::{
+    DEFAULT_FIRST_MESSAGE_TIMEOUT_SECS, WsConnectionClock, http_to_ws_url,
+};
+use crate::ws::fork::{ForkPressureClassifier, ForkPressureConfig, ForkRegime};
+use crate::ws::lock::{read_tracker, write_tracker};
+use crate::ws::prior_voters::PriorVotersWatch;
+use crate::ws::queue::{DEFAULT_NOTIFICATION_QUEUE_CAPACITY, NotificationQueue};
+use crate::ws::shape::{AccountShape, AccountShapeWatch};
+use crate::ws::tracker::{CreditStallChange, Regime, VoteTracker};
 use crate::ws::types::*;
+use crate::{throttled_error, throttled_warn};
 
 use anyhow::{Context, Result, anyhow};
+use futures_util::future::BoxFuture;
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{error, info, warn};
-
-/// Convert HTTP URL to WebSocket URL
-fn http_to_ws_url(http_url: &str) -> String {
-    if http_url.starts_with("https://") {
-        http_url.replace("https://", "wss://")
-    } else if http_url.starts_with("http://") {
-        http_url.replace("http://", "ws://")
-    } else if http_url.starts_with("wss://") || http_url.starts_with("ws://") {
-        http_url.to_string()
-    } else {
-        format!("wss://{}", http_url)
+use tokio::sync::{RwLock, watch};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::field::Empty;
+use tracing::{Span, error, info, instrument, warn};
+
+/// Delay before reconnecting after a WebSocket error
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How long to wait for the accountSubscribe confirmation
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Consecutive subscribe errors after which the subscription is reported as failed
+const SUBSCRIBE_FAILURE_THRESHOLD: u32 = 3;
+
+/// How long to wait for the accountUnsubscribe reply on shutdown
+const UNSUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Difference between the histogram and counter 5m efficiencies beyond which
+/// the values behind them are logged (at debug level)
+const EFFICIENCY_SKEW_LOG_THRESHOLD: f64 = 0.01;
+
+/// accountSubscribe was rejected or never confirmed. Unlike transport errors this
+/// usually points at configuration (wrong pubkey, provider without WS support).
+#[derive(Debug)]
+struct SubscribeError(String);
+
+impl std::fmt::Display for SubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Subscription failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for SubscribeError {}
+
+/// accountSubscribe was confirmed but no notification followed in time, as with
+/// providers that only stream accounts fetched over HTTP first
+#[derive(Debug)]
+struct SilentSubscription(Duration);
+
+impl std::fmt::Display for SilentSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Subscription confirmed but no notification within {}s",
+            self.0.as_secs_f64()
+        )
+    }
+}
+
+impl std::error::Error for SilentSubscription {}
+
+/// What a request awaiting its reply was sent for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PendingRequest {
+    Subscribe,
+    Unsubscribe { subscription: u64 },
+}
+
+/// JSON-RPC ids of the subscription's requests. Ids keep increasing across
+/// reconnects and a reply is only trusted when its id is pending on the
+/// current connection, so late or foreign replies can't confirm anything.
+#[derive(Debug)]
+struct RequestIds {
+    next: u64,
+    pending: BTreeMap<u64, PendingRequest>,
+}
+
+impl RequestIds {
+    fn new() -> Self {
+        Self {
+            next: 1,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Forget requests of the previous connection, their replies never come
+    fn reset_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Allocate the id of a new request
+    fn start(&mut self, request: PendingRequest) -> u64 {
+        let id = self.next;
+        self.next += 1;
+        self.pending.insert(id, request);
+        id
+    }
+
+    /// Take the request a reply with `id` answers; None for unknown ids and
+    /// for repeated replies
+    fn resolve(&mut self, id: u64) -> Option<PendingRequest> {
+        self.pending.remove(&id)
     }
 }
 
@@ -29,51 +131,234 @@ pub async fn run_vote_subscription(
     vote_pubkey: &str,
     metrics: Arc<Metrics>,
     tracker: Arc<RwLock<VoteTracker>>,
+    clock: Arc<WsConnectionClock>,
+    debug: Arc<DebugState>,
+    options: SubscriptionOptions,
 ) -> Result<()> {
     let ws_url = http_to_ws_url(rpc_url);
     info!("Starting WebSocket subscription to {}", ws_url);
+    let proxy = ProxyUrl::for_ws(options.proxy.as_ref(), &ws_url)?;
+    if let Some(proxy) = &proxy {
+        info!("Connecting the WebSocket through proxy {}", proxy);
+    }
 
+    let ctx = SubscriptionContext {
+        vote_pubkey,
+        proxy: proxy.as_ref(),
+        metrics: &metrics,
+        tracker: &tracker,
+        clock: &clock,
+        debug: &debug,
+        options: &options,
+    };
+    let mut subscribe_failures = 0;
+    let mut requests = RequestIds::new();
     loop {
-        match subscribe_loop(&ws_url, vote_pubkey, &metrics, &tracker).await {
+        let result = subscribe_loop(
+            &ws_url,
+            ctx,
+            SUBSCRIBE_TIMEOUT,
+            &mut subscribe_failures,
+            &mut requests,
+        )
+        .await;
+        if shutdown_set(options.shutdown.as_ref()) {
+            info!("WebSocket subscription stopped");
+            return Ok(());
+        }
+        metrics.ws_connected.set(0);
+        metrics.ws_connection_uptime.set(0.0);
+        let baseline = {
+            let mut tracker = write_tracker(&tracker, &metrics).await;
+            tracker.mark_disconnected();
+            tracker.baseline()
+        };
+        // Count the failed attempt itself; the wait below is counted on the next call
+        metrics
+            .ws_disconnected_seconds
+            .inc_by(clock.disconnected().as_secs_f64());
+
+        if let Some(discontinuities) = &options.discontinuities {
+            discontinuities.record_at(
+                &baseline,
+                DiscontinuityEvent::WsReconnect {
+                    closed_normally: result.is_ok(),
+                },
+            );
+        }
+        match result {
             Ok(()) => {
-                warn!("WebSocket connection closed normally, reconnecting...");
-                metrics.ws_connected.set(0);
+                throttled_warn!("WebSocket connection closed normally, reconnecting...");
             }
             Err(e) => {
-                error!("WebSocket error: {:#}, reconnecting in 5s...", e);
-                metrics.ws_connected.set(0);
-                metrics.ws_errors.inc();
-                tokio::time::sleep(Duration::from_secs(5)).await;
+                throttled_error!("WebSocket error: {:#}, reconnecting in 5s...", e);
+                record_error(&e, &metrics, &mut subscribe_failures);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                metrics
+                    .ws_disconnected_seconds
+                    .inc_by(clock.disconnected().as_secs_f64());
             }
         }
+        metrics.ws_reconnects.inc();
+    }
+}
+
+/// Count an error as a silent subscription, a subscribe error or a connection
+/// error and flag the subscription as failed once subscribe errors repeat
+fn record_error(e: &anyhow::Error, metrics: &Metrics, subscribe_failures: &mut u32) {
+    metrics.ws_errors.inc();
+    if e.downcast_ref::<SilentSubscription>().is_some() {
+        metrics.ws_silent_subscriptions.inc();
+        return;
+    }
+    if e.downcast_ref::<SubscribeError>().is_none() {
+        metrics.ws_connection_errors.inc();
+        return;
+    }
+
+    metrics.ws_subscribe_errors.inc();
+    *subscribe_failures += 1;
+    if *subscribe_failures >= SUBSCRIBE_FAILURE_THRESHOLD {
+        error!(
+            "accountSubscribe failed {} times in a row, check --vote-pubkey and the provider's WebSocket support",
+            subscribe_failures
+        );
+        metrics.ws_subscription_failed.set(1);
+    }
+}
+
+/// Optional behaviour of the vote subscription
+#[derive(Debug, Clone)]
+pub struct SubscriptionOptions {
+    /// Receives every tracker update when --journal-dir is set
+    pub journal: Option<Arc<Journal>>,
+    /// Credit history shorter than this flags the account as young
+    pub min_credit_history_epochs: usize,
+    /// Fetches the vote account over HTTP after each confirmation
+    /// (--ws-warmup-fetch), unless `check_rpc`'s fetch already does
+    pub warmup_rpc: Option<Arc<HttpRpcClient>>,
+    /// Fetches the vote account with the subscription's parameters after each
+    /// confirmation, to check the encoding and commitment of the notifications
+    pub check_rpc: Option<Arc<HttpRpcClient>>,
+    /// A confirmed subscription without a notification for this long is reconnected
+    pub first_message_timeout: Duration,
+    /// --proxy-url; without it the proxy environment variables apply
+    pub proxy: Option<ProxyUrl>,
+    /// --rpc-resolve addresses to connect to instead of looking the host up
+    pub resolve: Vec<ResolveOverride>,
+    /// Receives epoch rollovers and suspected restarts
+    pub events: Option<EventSender>,
+    /// Records epoch rollovers and reconnects
+    pub discontinuities: Option<Arc<Discontinuities>>,
+    /// Cluster averages for the epoch summaries
+    pub cluster_averages: Option<Arc<ClusterAverages>>,
+    /// Set to true to unsubscribe, close the connection and return
+    pub shutdown: Option<watch::Receiver<bool>>,
+    /// Rent-exempt minimum the account balance is compared with
+    pub rent_exemption: Option<RentExemption>,
+    /// Thresholds of the calm / turbulent fork pressure classification
+    pub fork_pressure: ForkPressureConfig,
+    /// Notifications waiting to be processed before the oldest are shed
+    pub notification_queue_capacity: usize,
+    /// Layout of the account data seen last, kept across reconnects
+    pub account_shape: Arc<AccountShapeWatch>,
+    /// Prior authorized voters seen last, kept across reconnects
+    pub prior_voters: Arc<PriorVotersWatch>,
+}
+
+impl Default for SubscriptionOptions {
+    fn default() -> Self {
+        Self {
+            journal: None,
+            min_credit_history_epochs: DEFAULT_MIN_CREDIT_HISTORY_EPOCHS,
+            warmup_rpc: None,
+            check_rpc: None,
+            first_message_timeout: Duration::from_secs(DEFAULT_FIRST_MESSAGE_TIMEOUT_SECS),
+            proxy: None,
+            resolve: Vec::new(),
+            events: None,
+            discontinuities: None,
+            cluster_averages: None,
+            shutdown: None,
+            rent_exemption: None,
+            fork_pressure: ForkPressureConfig::default(),
+            notification_queue_capacity: DEFAULT_NOTIFICATION_QUEUE_CAPACITY,
+            account_shape: Arc::default(),
+            prior_voters: Arc::default(),
+        }
+    }
+}
+
+/// What one subscription reads from and reports into
+#[derive(Clone, Copy)]
+struct SubscriptionContext<'a> {
+    vote_pubkey: &'a str,
+    /// HTTP CONNECT proxy the WebSocket is tunnelled through
+    proxy: Option<&'a ProxyUrl>,
+    metrics: &'a Arc<Metrics>,
+    tracker: &'a Arc<RwLock<VoteTracker>>,
+    clock: &'a WsConnectionClock,
+    /// Keeps the latest raw notification for `POST /debug/dump`
+    debug: &'a DebugState,
+    options: &'a SubscriptionOptions,
+}
+
+/// Whether shutdown was requested through `shutdown`
+fn shutdown_set(shutdown: Option<&watch::Receiver<bool>>) -> bool {
+    shutdown.is_some_and(|rx| *rx.borrow())
+}
+
+/// Resolve once shutdown is requested; never without a receiver or once its
+/// sender is gone
+async fn shutdown_requested(shutdown: &mut Option<watch::Receiver<bool>>) {
+    if let Some(rx) = shutdown {
+        if rx.wait_for(|&requested| requested).await.is_ok() {
+            return;
+        }
     }
+    std::future::pending().await
 }
 
 async fn subscribe_loop(
     ws_url: &str,
-    vote_pubkey: &str,
-    metrics: &Arc<Metrics>,
-    tracker: &Arc<RwLock<VoteTracker>>,
+    ctx: SubscriptionContext<'_>,
+    subscribe_timeout: Duration,
+    subscribe_failures: &mut u32,
+    requests: &mut RequestIds,
 ) -> Result<()> {
-    let (ws_stream, _) = connect_async(ws_url)
+    let SubscriptionContext {
+        vote_pubkey,
+        proxy,
+        metrics,
+        tracker,
+        clock,
+        debug,
+        options,
+    } = ctx;
+    let (ws_stream, _) = connect_ws(ws_url, proxy, &options.resolve)
         .await
         .context("Failed to connect to WebSocket")?;
 
     info!("WebSocket connected");
     metrics.ws_connected.set(1);
+    metrics
+        .ws_disconnected_seconds
+        .inc_by(clock.connected().as_secs_f64());
 
     let (mut write, mut read) = ws_stream.split();
+    requests.reset_pending();
+    let mut shutdown = options.shutdown.clone();
 
     // Subscribe to vote account with jsonParsed encoding and finalized commitment
     let subscribe_msg = serde_json::json!({
         "jsonrpc": "2.0",
-        "id": 1,
+        "id": requests.start(PendingRequest::Subscribe),
         "method": "accountSubscribe",
         "params": [
             vote_pubkey,
             {
                 "encoding": "jsonParsed",
-                "commitment": "finalized"
+                "commitment": COMMITMENT
             }
         ]
     });
@@ -86,64 +371,312 @@ async fn subscribe_loop(
     info!("Subscribed to vote account: {}", vote_pubkey);
 
     let mut subscription_id: Option<u64> = None;
+    // Fork pressure is judged per connection: context slots jump across a reconnect
+    let mut fork_pressure = ForkPressureClassifier::new(options.fork_pressure);
+    let mut last_fingerprint = None;
+    let subscribe_deadline = tokio::time::Instant::now() + subscribe_timeout;
+    // Set between the confirmation (or the HTTP fetch after it) and the first notification
+    let mut first_message_deadline = None;
+    // The HTTP fetch after the confirmation, polled alongside the socket
+    let mut fetch: Option<BoxFuture<'_, Option<FetchedAccount>>> = None;
+    // The account fetched over HTTP, until the first notification is compared with it
+    let mut fetched = None;
+    // The first notification, kept if it arrives while the fetch is still running
+    let mut first_notification: Option<NotificationParams> = None;
+    let mut awaiting_first = false;
+    // The reader only parses and enqueues, so a slow update can't back up the
+    // socket; if processing falls behind, the oldest pending notifications go
+    let queue = NotificationQueue::new(options.notification_queue_capacity);
 
-    while let Some(msg) = read.next().await {
-        let msg = msg.context("WebSocket receive error")?;
-
-        match msg {
-            Message::Text(text) => match serde_json::from_str::<WsMessage>(&text) {
-                Ok(WsMessage::SubscriptionResult { result, .. }) => {
-                    subscription_id = Some(result);
-                    info!("Subscription confirmed, id: {}", result);
-                }
-                Ok(WsMessage::Notification { params, .. }) => {
-                    if let Err(e) = process_notification(&params, metrics, tracker).await {
-                        warn!("Error processing notification: {:#}", e);
-                    } else {
-                        // Update last successful message timestamp
-                        let now = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs() as i64;
-                        metrics.ws_last_message.set(now);
+    let reader = async {
+        let result = async {
+            loop {
+                let next = tokio::select! {
+                    next = async {
+                        match (subscription_id, first_message_deadline) {
+                            (None, _) => tokio::time::timeout_at(subscribe_deadline, read.next())
+                                .await
+                                .map_err(|_| {
+                                    SubscribeError(format!(
+                                        "no confirmation within {}s",
+                                        subscribe_timeout.as_secs_f64()
+                                    ))
+                                    .into()
+                                }),
+                            (Some(_), Some(deadline)) => {
+                                tokio::time::timeout_at(deadline, read.next())
+                                    .await
+                                    .map_err(|_| SilentSubscription(options.first_message_timeout).into())
+                            }
+                            (Some(_), None) => Ok::<_, anyhow::Error>(read.next().await),
+                        }
+                    } => next?,
+                    account = async { fetch.as_mut().unwrap().await }, if fetch.is_some() => {
+                        fetch = None;
+                        if let Some(shape) = account.as_ref().and_then(|a| a.shape.clone()) {
+                            options.account_shape.observe(shape, metrics);
+                        }
+                        match (account, first_notification.take()) {
+                            (Some(account), Some(first)) => {
+                                check_acknowledged(&account, &first, metrics)
+                            }
+                            (account, _) => fetched = account,
+                        }
+                        // The provider may only start streaming once the fetch is done
+                        if awaiting_first {
+                            first_message_deadline =
+                                Some(tokio::time::Instant::now() + options.first_message_timeout);
+                        }
+                        continue;
                     }
+                    () = shutdown_requested(&mut shutdown) => {
+                        if let Some(subscription) = subscription_id {
+                            unsubscribe(&mut write, &mut read, requests, subscription).await?;
+                        }
+                        let _ = write.send(Message::Close(None)).await;
+                        return Ok(());
+                    }
+                };
+                let Some(msg) = next else {
+                    break;
+                };
+                let msg = msg.context("WebSocket receive error")?;
+                if let Some(age) = clock.connection_age() {
+                    metrics.ws_connection_uptime.set(age.as_secs_f64());
                 }
-                Ok(WsMessage::Error { error, .. }) => {
-                    return Err(anyhow!("RPC error {}: {}", error.code, error.message));
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to parse WebSocket message: {}, raw: {}",
-                        e,
-                        &text[..text.len().min(200)]
-                    );
+
+                match msg {
+                    Message::Text(text) => match serde_json::from_str::<WsMessage>(&text) {
+                        Ok(WsMessage::SubscriptionResult { result, id, .. })
+                            if requests.resolve(id) == Some(PendingRequest::Subscribe) =>
+                        {
+                            subscription_id = Some(result);
+                            *subscribe_failures = 0;
+                            metrics.ws_subscription_failed.set(0);
+                            info!("Subscription confirmed, id: {}", result);
+                            awaiting_first = true;
+                            if options.check_rpc.is_some() || options.warmup_rpc.is_some() {
+                                // Off the reader, which keeps draining the socket meanwhile;
+                                // the first message timeout starts once it completes
+                                fetch = Some(Box::pin(fetch_after_confirmation(vote_pubkey, options)));
+                            } else {
+                                first_message_deadline =
+                                    Some(tokio::time::Instant::now() + options.first_message_timeout);
+                            }
+                        }
+                        Ok(WsMessage::Notification { params, .. }) => {
+                            first_message_deadline = None;
+                            if let Some(fetched) = fetched.take() {
+                                check_acknowledged(&fetched, &params, metrics);
+                            } else if awaiting_first && fetch.is_some() {
+                                first_notification = Some((*params).clone());
+                            }
+                            awaiting_first = false;
+                            debug.record_ws_notification(&text);
+                            if queue.push(*params) {
+                                metrics.ws_notifications_shed.inc();
+                            }
+                        }
+                        Ok(WsMessage::SubscriptionResult { id, .. })
+                        | Ok(WsMessage::UnsubscribeResult { id, .. }) => {
+                            warn!("Ignoring reply to unknown request id {}", id);
+                        }
+                        Ok(WsMessage::Error { error, id, .. }) => match requests.resolve(id) {
+                            Some(PendingRequest::Subscribe) => {
+                                return Err(SubscribeError(format!(
+                                    "RPC error {}: {}",
+                                    error.code, error.message
+                                ))
+                                .into());
+                            }
+                            _ => warn!(
+                                "Ignoring RPC error {} for request id {}: {}",
+                                error.code, id, error.message
+                            ),
+                        },
+                        Err(e) => {
+                            warn!(
+                                "Failed to parse WebSocket message: {}, raw: {}",
+                                e,
+                                &text[..text.len().min(200)]
+                            );
+                        }
+                    },
+                    Message::Ping(data) => {
+                        write.send(Message::Pong(data)).await?;
+                    }
+                    Message::Close(_) => {
+                        info!("WebSocket closed by server");
+                        break;
+                    }
+                    _ => {}
                 }
-            },
-            Message::Ping(data) => {
-                write.send(Message::Pong(data)).await?;
             }
-            Message::Close(_) => {
-                info!("WebSocket closed by server");
-                break;
+
+            if let Some(id) = subscription_id {
+                info!("Subscription {} ended", id);
+            }
+            Ok(())
+        }
+        .await;
+        queue.close();
+        result
+    };
+
+    // Drains the queue, including what is left once the reader is done
+    let processor = async {
+        while let Some(params) = queue.pop().await {
+            let processed = process_notification(
+                &params,
+                metrics,
+                tracker,
+                &mut fork_pressure,
+                &mut last_fingerprint,
+                options,
+            )
+            .await;
+            if let Err(e) = processed {
+                warn!("Error processing notification: {:#}", e);
+            } else {
+                // Update last successful message timestamp
+                metrics.ws_last_message.set(unix_now() as i64);
+            }
+        }
+    };
+
+    let (result, ()) = tokio::join!(reader, processor);
+    result
+}
+
+/// The HTTP fetch after a subscription confirmation: the subscription check
+/// (returning the account), which also warms the subscription up, or else
+/// the warm-up alone. Failures are logged.
+async fn fetch_after_confirmation(
+    vote_pubkey: &str,
+    options: &SubscriptionOptions,
+) -> Option<FetchedAccount> {
+    if let Some(rpc) = &options.check_rpc {
+        return match rpc.fetch_subscribed_account(vote_pubkey).await {
+            Ok(account) => Some(account),
+            Err(e) => {
+                warn!("Subscription check getAccountInfo failed: {:#}", e);
+                None
             }
-            _ => {}
+        };
+    }
+    if let Some(rpc) = &options.warmup_rpc {
+        match rpc.warm_up_account(vote_pubkey).await {
+            Ok(()) => info!(
+                "Fetched {} over HTTP to warm up the subscription",
+                vote_pubkey
+            ),
+            Err(e) => warn!("Warm-up getAccountInfo failed: {:#}", e),
         }
     }
+    None
+}
 
-    if let Some(id) = subscription_id {
-        info!("Subscription {} ended", id);
+/// Compare the first notification with the account fetched over HTTP with the
+/// same parameters, export the result and warn about discrepancies
+fn check_acknowledged(fetched: &FetchedAccount, first: &NotificationParams, metrics: &Metrics) {
+    let ack = AcknowledgedSubscription::compare(fetched, first);
+    if ack.encoding_mismatch() {
+        if let Some(http_encoding) = &ack.http_encoding {
+            warn!(
+                "Subscription delivers {} data while getAccountInfo returns {} for the same request; the provider changed the encoding",
+                ack.ws_encoding, http_encoding
+            );
+        }
     }
+    if ack.commitment() == EffectiveCommitment::Unfinalized {
+        warn!(
+            "Notifications are {} slots ahead of the finalized slot; the provider seems to ignore commitment {}",
+            ack.lead_slots, COMMITMENT
+        );
+    }
+    metrics.set_acknowledged_subscription(&ack);
+}
 
+/// Send accountUnsubscribe and wait briefly for its confirmation, dropping
+/// notifications that arrive meanwhile
+async fn unsubscribe(
+    write: &mut SplitSink<WsStream, Message>,
+    read: &mut SplitStream<WsStream>,
+    requests: &mut RequestIds,
+    subscription: u64,
+) -> Result<()> {
+    let unsubscribe_msg = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": requests.start(PendingRequest::Unsubscribe { subscription }),
+        "method": "accountUnsubscribe",
+        "params": [subscription]
+    });
+    write
+        .send(Message::Text(unsubscribe_msg.to_string()))
+        .await
+        .context("Failed to send unsubscribe message")?;
+
+    let confirmed = tokio::time::timeout(UNSUBSCRIBE_TIMEOUT, async {
+        while let Some(Ok(msg)) = read.next().await {
+            let Message::Text(text) = msg else {
+                continue;
+            };
+            match serde_json::from_str::<WsMessage>(&text) {
+                Ok(WsMessage::UnsubscribeResult { result, id, .. })
+                    if requests.resolve(id).is_some() =>
+                {
+                    return result;
+                }
+                _ => {}
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+    if confirmed {
+        info!("Unsubscribed from subscription {}", subscription);
+    } else {
+        warn!(
+            "accountUnsubscribe for subscription {} was not confirmed",
+            subscription
+        );
+    }
     Ok(())
 }
 
-async fn process_notification(
+/// What a notification can change in the tracker. One with the same
+/// fingerprint as the previous notification (a lamport-only or metadata
+/// write) is a no-op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateFingerprint {
+    pub root_slot: Option<u64>,
+    pub newest_vote_slot: Option<u64>,
+    pub epoch: Option<u64>,
+    pub epoch_credits: u64,
+}
+
+/// Apply one account notification to the tracker and export the result,
+/// skipping the tracker when it repeats `last_fingerprint`
+#[instrument(
+    name = "notification",
+    skip_all,
+    fields(context_slot = params.result.context.slot, new_votes = Empty, missed_credits = Empty)
+)]
+pub async fn process_notification(
     params: &NotificationParams,
     metrics: &Arc<Metrics>,
     tracker: &Arc<RwLock<VoteTracker>>,
+    fork_pressure: &mut ForkPressureClassifier,
+    last_fingerprint: &mut Option<UpdateFingerprint>,
+    options: &SubscriptionOptions,
 ) -> Result<()> {
     let context_slot = params.result.context.slot;
     let value = &params.result.value;
+    export_balance(metrics, value.lamports, options.rent_exemption);
+    options
+        .account_shape
+        .observe(AccountShape::of(&value.data), metrics);
 
     // Extract parsed vote account data
     let vote_info = match &value.data {
@@ -152,6 +685,9 @@ async fn process_notification(
             return Err(anyhow!("Expected jsonParsed data, got raw"));
         }
     };
+    options
+        .prior_voters
+        .observe(&vote_info.prior_voters, metrics);
 
     // Extract votes as (slot, confirmation_count, latency) tuples
     let votes: Vec<(u64, u32, Option<u32>)> = vote_info
@@ -160,8 +696,9 @@ async fn process_notification(
         .map(|v| (v.slot, v.confirmation_count, v.latency))
         .collect();
 
-    // Get credits info from epochCredits
-    let current_epoch_entry = vote_info.epoch_credits.last();
+    // Get credits info from epochCredits, newest epoch first even if the
+    // provider returned the entries out of order
+    let current_epoch_entry = vote_info.epoch_credits.iter().max_by_key(|ec| ec.epoch);
 
     // Credits earned THIS epoch = credits - previous_credits
     // This is what `solana vote-account` shows for the current epoch
@@ -172,20 +709,149 @@ async fn process_notification(
     // Get epoch directly from epochCredits (more accurate than calculating from root_slot)
     let epoch = current_epoch_entry.map(|ec| ec.epoch);
 
+    metrics.set_credit_history(
+        &CreditHistory::from_epochs(vote_info.epoch_credits.iter().map(|ec| ec.epoch)),
+        epoch.unwrap_or_default(),
+        options.min_credit_history_epochs,
+    );
+
+    let fork_regime = fork_pressure.observe(context_slot, vote_info.root_slot);
+    metrics
+        .fork_regime_turbulent
+        .set((fork_regime == ForkRegime::Turbulent) as i64);
+
+    // Same tower and credits as the previous notification: nothing for the
+    // tracker to do. Returning Ok still counts as a fresh message.
+    let fingerprint = UpdateFingerprint {
+        root_slot: vote_info.root_slot,
+        newest_vote_slot: votes.iter().map(|&(slot, _, _)| slot).max(),
+        epoch,
+        epoch_credits,
+    };
+    metrics.ws_notifications.inc();
+    let noop = last_fingerprint.replace(fingerprint) == Some(fingerprint);
+    if noop {
+        metrics.ws_noop_notifications.inc();
+    }
+    metrics
+        .ws_noop_ratio
+        .set(metrics.ws_noop_notifications.get() as f64 / metrics.ws_notifications.get() as f64);
+    if noop {
+        return Ok(());
+    }
+
     // Process the update
-    let result = {
-        let mut tracker = tracker.write().await;
-        tracker.process_update(
+    let (t, mut result) = {
+        let mut tracker = write_tracker(tracker, metrics).await;
+        tracker.set_fork_regime(fork_regime);
+        let t = tracker.now_millis();
+        let result = tracker.process_update(
             context_slot,
             &votes,
             vote_info.root_slot,
             epoch_credits,
             epoch,
-        )
+        );
+        (t, result)
     };
+    if let Some(journal) = &options.journal {
+        let update = JournalUpdate::new(
+            t,
+            context_slot,
+            &votes,
+            vote_info.root_slot,
+            epoch_credits,
+            epoch,
+            &result,
+        );
+        if !journal.record(update) {
+            metrics.journal_records_dropped.inc();
+        }
+    }
+    metrics
+        .unobserved_rooted_slots
+        .inc_by(result.unobserved.rooted_slots);
+    metrics
+        .unobserved_expected_credits
+        .inc_by(result.unobserved.expected_credits);
+    Span::current()
+        .record("new_votes", result.new_votes)
+        .record("missed_credits", result.missed_credits);
+
+    if let Some(summary) = &mut result.epoch_summary {
+        summary.set_cluster_average(options.cluster_averages.as_deref());
+    }
+    if let Some(summary) = &result.epoch_summary {
+        info!(
+            "Epoch {} finished: {} credits, {} missed while tracked ({} expected on {} slots rooted unobserved), worst 5m efficiency {}, longest perfect streak {} slots",
+            summary.epoch,
+            summary.credits,
+            summary.missed,
+            summary.unobserved.unwrap_or_default().expected_credits,
+            summary.unobserved.unwrap_or_default().rooted_slots,
+            summary
+                .worst_window_5m
+                .map(|w| format!("{:.4} at {}", w.efficiency, w.timestamp))
+                .unwrap_or_else(|| "n/a".to_string()),
+            summary
+                .perfect_slot_streak
+                .map_or(0, |streak| streak.epoch_max)
+        );
+        if let Some(comparison) = &summary.comparison {
+            info!("Epoch {} {}", summary.epoch, comparison);
+        }
+        if let Some(cluster) = &summary.cluster {
+            info!("Epoch {} credits {}", summary.epoch, cluster);
+        }
+        emit(options.events.as_ref(), TrackerEvent::from(summary));
+        if let Some(discontinuities) = &options.discontinuities {
+            discontinuities.record(
+                epoch,
+                vote_info.root_slot,
+                DiscontinuityEvent::EpochRollover {
+                    from_epoch: summary.epoch,
+                    to_epoch: epoch,
+                    credits: summary.credits,
+                    missed: summary.missed,
+                },
+            );
+        }
+    }
+
+    if let Some(restart) = &result.restart {
+        warn!(
+            "Suspected validator restart: no votes between slot {} and {} ({} slots), tower height {} -> {}",
+            restart.last_vote_slot,
+            restart.resumed_vote_slot,
+            restart.gap_slots(),
+            restart.tower_height_before,
+            restart.tower_height_after
+        );
+        metrics.validator_restarts.inc();
+        emit(options.events.as_ref(), TrackerEvent::from(restart));
+    }
+    match &result.credit_stall {
+        Some(CreditStallChange::Started { updates, latencies }) => warn!(
+            "Votes landing without credits: {} updates rooted new votes and the epoch credits did not increase, recent latencies {:?}",
+            updates, latencies
+        ),
+        Some(CreditStallChange::Ended) => info!("Vote credits resumed"),
+        None => {}
+    }
+    metrics.votes_catch_up.inc_by(result.catch_up_votes);
+    metrics.votes_never_rooted.inc_by(result.never_rooted);
+    metrics.state_evictions.inc_by(result.state_evictions);
+    metrics
+        .missed_maintenance_total
+        .inc_by(result.maintenance_missed);
+    for delay_ms in &result.root_delays_ms {
+        metrics.slot_root_delay.observe(*delay_ms as f64 / 1000.0);
+    }
+    record_votes_by_credits(metrics, &result.update_histogram);
 
     // Update metrics
-    update_histogram_metrics(metrics, tracker).await;
+    publish_tracker_metrics(&*read_tracker(tracker, metrics).await, metrics);
+    metrics.observe_warmup_notification(vote_info.root_slot);
 
     if result.new_votes > 0 || result.missed_credits > 0 {
         tracing::debug!(
@@ -199,68 +865,154 @@ async fn process_notification(
     Ok(())
 }
 
-async fn update_histogram_metrics(metrics: &Arc<Metrics>, tracker: &Arc<RwLock<VoteTracker>>) {
-    let tracker = tracker.read().await;
+/// Add the votes of one update to the monotonic per-credit counters
+fn record_votes_by_credits(metrics: &Metrics, update_histogram: &[u64; 17]) {
+    for (credits, count) in update_histogram.iter().enumerate() {
+        if *count > 0 {
+            metrics
+                .votes_by_credits
+                .with_label_values(&[&credits.to_string()])
+                .inc_by(*count);
+        }
+    }
+}
+
+/// Publish the gauges derived from the tracker as one update cycle. Sync so
+/// that a scrape never sees part of it: nothing between the first and the
+/// last write can yield.
+fn publish_tracker_metrics(tracker: &VoteTracker, metrics: &Metrics) {
+    let _publication = metrics.begin_publication();
+
+    metrics
+        .vote_regime_catch_up
+        .set((tracker.regime() == Regime::CatchUp) as i64);
+    metrics
+        .votes_without_credits
+        .set(tracker.votes_without_credits() as i64);
+    metrics
+        .epoch_source_disagreement
+        .set(tracker.epoch_source_disagreement() as i64);
+    metrics.set_hourly_profile(tracker.hourly_profile(), tracker.now_millis());
+    metrics
+        .tracker_state_bytes
+        .set(tracker.memory_footprint() as i64);
+    let streak = tracker.perfect_slot_streak();
+    metrics
+        .perfect_slot_streak_current
+        .set(streak.current as i64);
+    metrics
+        .perfect_slot_streak_epoch_max
+        .set(streak.epoch_max as i64);
 
     // Get histograms for each window
     let hist_5m = tracker.window_histogram(300);
     let hist_1h = tracker.window_histogram(3600);
     let hist_epoch = tracker.epoch_histogram();
 
-    // Get missed credits for each window
-    let missed_5m = tracker.window_missed(300);
-    let missed_1h = tracker.window_missed(3600);
+    // Credits and missed credits for each window, from one window start
+    let (credits_5m, missed_5m) = tracker.window_credits_and_missed(300);
+    let (credits_1h, missed_1h) = tracker.window_credits_and_missed(3600);
 
-    // Update histogram count metrics
-    for credits in 0..=16u64 {
-        let credits_str = credits.to_string();
-
-        metrics
-            .vote_credits_histogram_count
-            .with_label_values(&["5m", &credits_str])
-            .set(hist_5m[credits as usize] as i64);
+    // Windows the history barely covers (startup, or a cluster halt that
+    // aged it out) keep their gauges unset rather than read as zero
+    let has_data_5m =
+        metrics.set_window_coverage(HistogramWindow::FiveMinutes, tracker.window_coverage(300));
+    let has_data_1h =
+        metrics.set_window_coverage(HistogramWindow::OneHour, tracker.window_coverage(3600));
 
+    // Sample size behind the 5m/1h windows
+    let windows = [
+        (HistogramWindow::FiveMinutes, 300),
+        (HistogramWindow::OneHour, 3600),
+    ];
+    for (window, window_secs) in windows {
+        for (regime, credits) in tracker.window_by_fork_regime(window_secs) {
+            let labels = [window.label(), regime.label()];
+            metrics
+                .missed_by_fork_regime
+                .with_label_values(&labels)
+                .set(credits.missed as i64);
+            if let Some(efficiency) = credits.efficiency() {
+                metrics
+                    .vote_credits_efficiency_by_fork_regime
+                    .with_label_values(&labels)
+                    .set(efficiency);
+            }
+        }
         metrics
-            .vote_credits_histogram_count
-            .with_label_values(&["1h", &credits_str])
-            .set(hist_1h[credits as usize] as i64);
-
+            .updates_in_window
+            .with_label_values(&[window.label()])
+            .set(tracker.window_updates(window_secs) as i64);
         metrics
-            .vote_credits_histogram_count
-            .with_label_values(&["epoch", &credits_str])
-            .set(hist_epoch[credits as usize] as i64);
+            .slots_in_window
+            .with_label_values(&[window.label()])
+            .set(tracker.window_rooted_slots(window_secs) as i64);
     }
 
-    // Update histogram fraction metrics
-    let frac_5m = VoteTracker::histogram_fractions(&hist_5m);
-    let frac_1h = VoteTracker::histogram_fractions(&hist_1h);
-    let frac_epoch = VoteTracker::histogram_fractions(&hist_epoch);
-
-    for credits in 0..=16u64 {
-        let credits_str = credits.to_string();
-
-        metrics
-            .vote_credits_histogram_fraction
-            .with_label_values(&["5m", &credits_str])
-            .set(frac_5m[credits as usize]);
+    if let Some(accounting) = tracker.missed_accounting() {
+        metrics.set_missed_accounting(&accounting);
+    }
 
-        metrics
-            .vote_credits_histogram_fraction
-            .with_label_values(&["1h", &credits_str])
-            .set(frac_1h[credits as usize]);
+    // Cross-check the 5m efficiency against the credit counter
+    if let Some(check) = tracker.window_cross_check(300) {
+        if let Some(skew) = check.skew() {
+            metrics.efficiency_computation_skew_5m.set(skew);
+            if skew > EFFICIENCY_SKEW_LOG_THRESHOLD {
+                tracing::debug!(
+                    "5m efficiency skew {:.4}: histogram {} credits + {} missed, counter {} credits of {} over {} rooted slots",
+                    skew,
+                    check.histogram_credits,
+                    check.missed,
+                    check.counter_credits,
+                    check.counter_expected,
+                    check.rooted_slots
+                );
+            }
+        }
+    }
 
-        metrics
-            .vote_credits_histogram_fraction
-            .with_label_values(&["epoch", &credits_str])
-            .set(frac_epoch[credits as usize]);
+    // Update histogram count and fraction metrics for the --histogram-windows
+    let histograms = [
+        (HistogramWindow::FiveMinutes, &hist_5m),
+        (HistogramWindow::OneHour, &hist_1h),
+        (HistogramWindow::Epoch, &hist_epoch),
+    ];
+    for (window, hist) in histograms {
+        if !metrics.histogram_windows.contains(&window) {
+            continue;
+        }
+        if metrics.is_enabled(DisabledMetrics::HISTOGRAM) {
+            for (credits, count) in hist.iter().enumerate() {
+                metrics
+                    .vote_credits_histogram_count
+                    .with_label_values(&[window.label(), &credits.to_string()])
+                    .set(*count as i64);
+            }
+        }
+        if metrics.is_enabled(DisabledMetrics::FRACTIONS) {
+            let fractions = VoteTracker::histogram_fractions(hist);
+            for (credits, fraction) in fractions.iter().enumerate() {
+                metrics
+                    .vote_credits_histogram_fraction
+                    .with_label_values(&[window.label(), &credits.to_string()])
+                    .set(*fraction);
+            }
+        }
     }
 
     // Update missed_vote_credits from WebSocket tracking
     // This uses epoch_credits as source of truth for consistency
     let missed_epoch = tracker.epoch_missed();
-    metrics.missed_5m.set(missed_5m as i64);
-    metrics.missed_1h.set(missed_1h as i64);
+    if has_data_5m {
+        metrics.missed_5m.set(missed_5m as i64);
+    }
+    if has_data_1h {
+        metrics.missed_1h.set(missed_1h as i64);
+    }
     metrics.missed_current_epoch.set(missed_epoch as i64);
+    metrics
+        .missed_current_epoch_maintenance
+        .set(tracker.epoch_missed_maintenance() as i64);
 
     // Calculate totals and credits from histograms (for 5m/1h windowed metrics)
     let total_votes_5m = VoteTracker::histogram_total(&hist_5m);
@@ -270,11 +1022,12 @@ async fn update_histogram_metrics(metrics: &Arc<Metrics>, tracker: &Arc<RwLock<V
     let hist_credits_1h = VoteTracker::histogram_credits(&hist_1h);
 
     // For windowed efficiency: expected = credits + missed
-    let expected_5m = hist_credits_5m + missed_5m;
-    let expected_1h = hist_credits_1h + missed_1h;
+    let expected_5m = credits_5m + missed_5m;
+    let expected_1h = credits_1h + missed_1h;
 
     // Get epoch info for projections
     let epoch_info = tracker.epoch_info();
+    let credits_model = tracker.credits_model();
     let slots_in_epoch = epoch_info.map(|e| e.slots_in_epoch).unwrap_or(432_000);
     let current_epoch_credits = tracker.current_epoch_credits();
 
@@ -282,45 +1035,100 @@ async fn update_histogram_metrics(metrics: &Arc<Metrics>, tracker: &Arc<RwLock<V
     let slots_elapsed = epoch_info.map(|e| e.slot_index + 1).unwrap_or(1);
     let remaining_slots = slots_in_epoch.saturating_sub(slots_elapsed);
 
-    // 5-minute metrics
-    let avg_credits_5m = if total_votes_5m > 0 {
-        hist_credits_5m as f64 / total_votes_5m as f64
-    } else {
-        0.0
-    };
+    // 5-minute metrics (no rate without votes in the window)
+    let avg_credits_5m =
+        (has_data_5m && total_votes_5m > 0).then(|| hist_credits_5m as f64 / total_votes_5m as f64);
 
-    if expected_5m > 0 {
-        let eff_5m = hist_credits_5m as f64 / expected_5m as f64;
+    if let Some(avg_credits_5m) = avg_credits_5m.filter(|_| expected_5m > 0) {
+        let eff_5m = credits_5m as f64 / expected_5m as f64;
         metrics.vote_credits_efficiency_5m.set(eff_5m);
         metrics.vote_credits_per_slot_5m.set(avg_credits_5m);
-        // Latency: credits = 17 - latency, so latency = 17 - credits
-        // Latency 1 = 16 credits (fastest), Latency 17 = 0 credits
-        metrics.vote_latency_slots_5m.set(17.0 - avg_credits_5m);
+        // Latency: credits = max + 1 - latency, so latency = max + 1 - credits
+        // Latency 1 = max credits (fastest), Latency max + 1 = 0 credits
+        metrics
+            .vote_latency_slots_5m
+            .set(credits_model.implied_latency(avg_credits_5m));
     }
 
-    // Projected credits at epoch end: actual + (remaining_slots × 5m_rate)
-    let projected_5m =
-        current_epoch_credits as i64 + (avg_credits_5m * remaining_slots as f64) as i64;
-    metrics.projected_credits_5m.set(projected_5m);
-
     // 1-hour metrics
-    let avg_credits_1h = if total_votes_1h > 0 {
-        hist_credits_1h as f64 / total_votes_1h as f64
-    } else {
-        0.0
-    };
+    let avg_credits_1h =
+        (has_data_1h && total_votes_1h > 0).then(|| hist_credits_1h as f64 / total_votes_1h as f64);
 
-    if expected_1h > 0 {
-        let eff_1h = hist_credits_1h as f64 / expected_1h as f64;
+    if let Some(avg_credits_1h) = avg_credits_1h.filter(|_| expected_1h > 0) {
+        let eff_1h = credits_1h as f64 / expected_1h as f64;
         metrics.vote_credits_efficiency_1h.set(eff_1h);
         metrics.vote_credits_per_slot_1h.set(avg_credits_1h);
-        metrics.vote_latency_slots_1h.set(17.0 - avg_credits_1h);
+        metrics
+            .vote_latency_slots_1h
+            .set(credits_model.implied_latency(avg_credits_1h));
+    }
+
+    // Leader-adjusted efficiency, once the leader schedule for this epoch is known
+    if let Some(leader_slots) = tracker.leader_slot_count() {
+        metrics.leader_slots_current_epoch.set(leader_slots as i64);
+        if let Some(eff) = tracker
+            .window_efficiency_adjusted(300)
+            .filter(|_| has_data_5m)
+        {
+            metrics.vote_credits_efficiency_adjusted_5m.set(eff);
+        }
+        if let Some(eff) = tracker
+            .window_efficiency_adjusted(3600)
+            .filter(|_| has_data_1h)
+        {
+            metrics.vote_credits_efficiency_adjusted_1h.set(eff);
+        }
+        if let Some(eff) = tracker.epoch_efficiency_adjusted() {
+            metrics.vote_credits_efficiency_adjusted_epoch.set(eff);
+        }
+    }
+    if let Some(missed) = tracker.epoch_missed_skip_adjusted() {
+        metrics
+            .missed_current_epoch_skip_adjusted
+            .set(missed as i64);
     }
 
-    // Projected credits at epoch end: actual + (remaining_slots × 1h_rate)
-    let projected_1h =
-        current_epoch_credits as i64 + (avg_credits_1h * remaining_slots as f64) as i64;
-    metrics.projected_credits_1h.set(projected_1h);
+    if metrics.is_enabled(DisabledMetrics::PROJECTIONS) {
+        // Projected credits at epoch end: actual + (remaining_slots × 5m / 1h rate),
+        // at most the epoch's maximum
+        let projected = |rate: f64| {
+            credits_model.projected_credits(
+                current_epoch_credits,
+                rate,
+                remaining_slots,
+                slots_in_epoch,
+            ) as i64
+        };
+        if let Some(rate) = avg_credits_5m {
+            metrics.projected_credits_5m.set(projected(rate));
+        }
+        if let Some(rate) = avg_credits_1h {
+            metrics.projected_credits_1h.set(projected(rate));
+        }
+
+        // Smoothed projection (less sensitive to short incidents than the windowed ones)
+        if let Some(projected) = tracker.projected_credits_smoothed() {
+            metrics.projected_credits_smoothed.set(projected as i64);
+        }
+    }
+
+    // Worst 5m window this epoch (1.0 until a fully populated window was observed)
+    match tracker.snapshot().epoch_worst_window_5m {
+        Some(worst) => {
+            metrics
+                .vote_credits_efficiency_5m_epoch_min
+                .set(worst.efficiency);
+            metrics
+                .vote_credits_efficiency_5m_epoch_min_timestamp
+                .set(worst.timestamp as i64);
+        }
+        None => {
+            metrics.vote_credits_efficiency_5m_epoch_min.set(1.0);
+            metrics
+                .vote_credits_efficiency_5m_epoch_min_timestamp
+                .set(0);
+        }
+    }
 
     // Set epoch info metrics using vote account data (covers entire epoch)
     if let Some(epoch_info) = tracker.epoch_info() {
@@ -333,9 +1141,27 @@ async fn update_histogram_metrics(metrics: &Arc<Metrics>, tracker: &Arc<RwLock<V
             .total_epoch_credits
             .set(current_epoch_credits as i64);
 
-        // Maximum possible credits at current slot = (slot_index + 1) × 16
-        let epoch_max_at_slot = (epoch_info.slot_index + 1) * 16;
+        // Maximum possible credits at current slot = (slot_index + 1) × max credits per slot,
+        // or up to the tip minus the allowance with --expected-anchor tip
+        let root_max_at_slot = credits_model.expected_credits(epoch_info.slot_index + 1);
+        let primary_tip = metrics.set_anchored_expectations(
+            tracker.expected_anchor(),
+            root_max_at_slot,
+            current_epoch_credits,
+            tracker.tip_expectation(),
+        );
+        let epoch_max_at_slot = primary_tip.map_or(root_max_at_slot, |tip| tip.expected_max);
         metrics.epoch_expected_max.set(epoch_max_at_slot as i64);
+        metrics.set_tracked_slots(
+            tracker.epoch_first_tracked_slot(),
+            tracker.epoch_last_tracked_slot(),
+        );
+        if let Some(tip) = primary_tip {
+            let missed = tip
+                .provisional_missed
+                .saturating_sub(tracker.epoch_missed_maintenance());
+            metrics.missed_current_epoch.set(missed as i64);
+        }
 
         // Epoch-level metrics from vote account data (covers entire epoch, not just since tracker started)
         if epoch_max_at_slot > 0 {
@@ -346,7 +1172,1017 @@ async fn update_histogram_metrics(metrics: &Arc<Metrics>, tracker: &Arc<RwLock<V
             metrics.vote_credits_per_slot_epoch.set(avg_credits_epoch);
             metrics
                 .vote_latency_slots_epoch
-                .set(17.0 - avg_credits_epoch);
+                .set(credits_model.implied_latency(avg_credits_epoch));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::credits::credits_from_latency;
+    use crate::ws::tracker::SLOTS_PER_EPOCH;
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    /// Poll `condition` every 10ms until it holds, failing after 5s
+    async fn wait_for(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("condition not reached");
+    }
+
+    /// Fake WS server answering the first message of each connection with `reply`
+    /// (or nothing), returning its URL
+    async fn mock_ws(reply: Option<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = accept_async(stream).await.unwrap();
+                    while let Some(Ok(msg)) = ws.next().await {
+                        if let (Message::Text(_), Some(reply)) = (msg, reply) {
+                            ws.send(Message::Text(reply.to_string())).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    async fn subscribe_once(url: &str, timeout: Duration, metrics: &Arc<Metrics>) -> Result<()> {
+        subscribe_with(url, timeout, metrics, &SubscriptionOptions::default()).await
+    }
+
+    async fn subscribe_with(
+        url: &str,
+        timeout: Duration,
+        metrics: &Arc<Metrics>,
+        options: &SubscriptionOptions,
+    ) -> Result<()> {
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let clock = WsConnectionClock::default();
+        let debug = DebugState::new(std::env::temp_dir(), 1);
+        let ctx = SubscriptionContext {
+            vote_pubkey: "A",
+            proxy: None,
+            metrics,
+            tracker: &tracker,
+            clock: &clock,
+            debug: &debug,
+            options,
+        };
+        let mut failures = 0;
+        subscribe_loop(url, ctx, timeout, &mut failures, &mut RequestIds::new()).await
+    }
+
+    /// Fake provider that confirms subscriptions but only streams (one
+    /// notification, then closes) once the account was fetched over HTTP.
+    /// Returns the WS URL, the HTTP URL and the getAccountInfo count.
+    async fn mock_warmup_provider() -> (String, String, Arc<AtomicU64>) {
+        mock_provider(serde_json::json!({"context": {"slot": 1}, "value": null})).await
+    }
+
+    /// [`mock_warmup_provider`] answering getAccountInfo with `account`
+    async fn mock_provider(account: serde_json::Value) -> (String, String, Arc<AtomicU64>) {
+        mock_slow_provider(account, Duration::ZERO).await
+    }
+
+    /// [`mock_provider`] taking `delay` to answer getAccountInfo
+    async fn mock_slow_provider(
+        account: serde_json::Value,
+        delay: Duration,
+    ) -> (String, String, Arc<AtomicU64>) {
+        let fetches = Arc::new(AtomicU64::new(0));
+        let counter = fetches.clone();
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |body: axum::Json<serde_json::Value>| async move {
+                assert_eq!(body["method"], "getAccountInfo");
+                tokio::time::sleep(delay).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                axum::Json(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "result": account,
+                    "id": 1
+                }))
+            }),
+        );
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_url = format!("http://{}", http.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(http, app).await.unwrap() });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let fetched = fetches.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let fetched = fetched.clone();
+                tokio::spawn(async move {
+                    let mut ws = accept_async(stream).await.unwrap();
+                    ws.next().await;
+                    let confirmation = r#"{"jsonrpc":"2.0","result":7,"id":1}"#;
+                    ws.send(Message::Text(confirmation.to_string()))
+                        .await
+                        .unwrap();
+                    while fetched.load(Ordering::SeqCst) == 0 {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                    let notification = format!(
+                        r#"{{"jsonrpc":"2.0","method":"accountNotification","params":{}}}"#,
+                        NOTIFICATION_PARAMS
+                    );
+                    ws.send(Message::Text(notification)).await.unwrap();
+                    ws.close(None).await.unwrap();
+                    while ws.next().await.is_some() {}
+                });
+            }
+        });
+        (ws_url, http_url, fetches)
+    }
+
+    #[tokio::test]
+    async fn test_warmup_fetch_starts_the_stream() {
+        let (ws_url, http_url, fetches) = mock_warmup_provider().await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let options = SubscriptionOptions {
+            warmup_rpc: Some(Arc::new(HttpRpcClient::new(&http_url).unwrap())),
+            first_message_timeout: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        subscribe_with(&ws_url, SUBSCRIBE_TIMEOUT, &metrics, &options)
+            .await
+            .unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert!(metrics.ws_last_message.get() > 0);
+        assert_eq!(metrics.epoch_credits_history_len.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_first_message_timeout_starts_after_the_fetch() {
+        // The fetch takes longer than the first message timeout; the
+        // provider streams once it is done
+        let (ws_url, http_url, fetches) = mock_slow_provider(
+            serde_json::json!({"context": {"slot": 1}, "value": null}),
+            Duration::from_millis(500),
+        )
+        .await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let options = SubscriptionOptions {
+            warmup_rpc: Some(Arc::new(HttpRpcClient::new(&http_url).unwrap())),
+            first_message_timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+
+        subscribe_with(&ws_url, SUBSCRIBE_TIMEOUT, &metrics, &options)
+            .await
+            .unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.ws_silent_subscriptions.get(), 0);
+        assert!(metrics.ws_last_message.get() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_check_doubles_as_the_warmup() {
+        let (ws_url, http_url, fetches) = mock_warmup_provider().await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let rpc = Arc::new(HttpRpcClient::new(&http_url).unwrap());
+        let options = SubscriptionOptions {
+            warmup_rpc: Some(rpc.clone()),
+            check_rpc: Some(rpc),
+            first_message_timeout: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        subscribe_with(&ws_url, SUBSCRIBE_TIMEOUT, &metrics, &options)
+            .await
+            .unwrap();
+        // One getAccountInfo per subscription, not a check and a warm-up
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert!(metrics.ws_last_message.get() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_check_flags_downgraded_encoding() {
+        // HTTP falls back to base64 at the finalized slot 960, while the
+        // subscription streams jsonParsed at slot 1000
+        let (ws_url, http_url, fetches) = mock_provider(serde_json::json!({
+            "context": {"slot": 960},
+            "value": {
+                "lamports": 1,
+                "data": ["AAAA", "base64"],
+                "owner": "Vote111111111111111111111111111111111111111",
+                "executable": false,
+                "rentEpoch": 0
+            }
+        }))
+        .await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let options = SubscriptionOptions {
+            check_rpc: Some(Arc::new(HttpRpcClient::new(&http_url).unwrap())),
+            first_message_timeout: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        subscribe_with(&ws_url, SUBSCRIBE_TIMEOUT, &metrics, &options)
+            .await
+            .unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.ws_encoding_mismatch.get(), 1);
+        let level = |level| {
+            metrics
+                .ws_commitment_effective
+                .with_label_values(&[level])
+                .get()
+        };
+        assert_eq!(level("unfinalized"), 1);
+        assert_eq!(level("finalized"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_silent_subscription_forces_reconnect() {
+        let (ws_url, _, fetches) = mock_warmup_provider().await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let options = SubscriptionOptions {
+            first_message_timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+
+        let err = subscribe_with(&ws_url, SUBSCRIBE_TIMEOUT, &metrics, &options)
+            .await
+            .unwrap_err();
+        assert!(
+            err.downcast_ref::<SilentSubscription>().is_some(),
+            "{}",
+            err
+        );
+        assert_eq!(fetches.load(Ordering::SeqCst), 0);
+        assert_eq!(metrics.ws_last_message.get(), 0);
+
+        let mut failures = 0;
+        record_error(&err, &metrics, &mut failures);
+        assert_eq!(metrics.ws_silent_subscriptions.get(), 1);
+        assert_eq!(metrics.ws_connection_errors.get(), 0);
+        assert_eq!(metrics.ws_subscribe_errors.get(), 0);
+        assert_eq!(metrics.ws_errors.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_rejection_is_a_subscribe_error() {
+        let url = mock_ws(Some(
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid param"},"id":1}"#,
+        ))
+        .await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+
+        let mut failures = 0;
+        for _ in 0..SUBSCRIBE_FAILURE_THRESHOLD {
+            let err = subscribe_once(&url, SUBSCRIBE_TIMEOUT, &metrics)
+                .await
+                .unwrap_err();
+            assert!(err.downcast_ref::<SubscribeError>().is_some());
+            assert_eq!(metrics.ws_subscription_failed.get(), 0);
+            record_error(&err, &metrics, &mut failures);
         }
+
+        assert_eq!(metrics.ws_subscribe_errors.get(), 3);
+        assert_eq!(metrics.ws_connection_errors.get(), 0);
+        assert_eq!(metrics.ws_errors.get(), 3);
+        assert_eq!(metrics.ws_subscription_failed.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_confirmation_times_out() {
+        let url = mock_ws(None).await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+
+        let err = subscribe_once(&url, Duration::from_millis(200), &metrics)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no confirmation"), "{}", err);
+        assert!(err.downcast_ref::<SubscribeError>().is_some());
+    }
+
+    #[test]
+    fn test_replies_are_matched_to_pending_requests() {
+        let mut requests = RequestIds::new();
+        let subscribe = requests.start(PendingRequest::Subscribe);
+        let unsubscribe = requests.start(PendingRequest::Unsubscribe { subscription: 7 });
+        assert_eq!((subscribe, unsubscribe), (1, 2));
+
+        // Out of order, unknown and repeated replies
+        assert_eq!(
+            requests.resolve(unsubscribe),
+            Some(PendingRequest::Unsubscribe { subscription: 7 })
+        );
+        assert_eq!(requests.resolve(99), None);
+        assert_eq!(requests.resolve(subscribe), Some(PendingRequest::Subscribe));
+        assert_eq!(requests.resolve(subscribe), None);
+
+        // A reconnect drops pending requests but never reuses their ids
+        let stale = requests.start(PendingRequest::Subscribe);
+        requests.reset_pending();
+        assert_eq!(requests.resolve(stale), None);
+        assert_eq!(requests.start(PendingRequest::Subscribe), 4);
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_with_unknown_id_is_ignored() {
+        let url = mock_ws(Some(r#"{"jsonrpc":"2.0","result":7,"id":99}"#)).await;
+        let metrics = Arc::new(Metrics::new().unwrap());
+
+        let err = subscribe_once(&url, Duration::from_millis(200), &metrics)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no confirmation"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_unsubscribes() {
+        // Answers each request by its id, followed by a notification after the
+        // confirmation, and records the requests
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+        let recorded = received.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let result = match request["method"].as_str() {
+                    Some("accountSubscribe") => serde_json::json!(7),
+                    _ => serde_json::json!(true),
+                };
+                let reply =
+                    serde_json::json!({"jsonrpc": "2.0", "result": result, "id": request["id"]});
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+                if request["method"] == "accountSubscribe" {
+                    let notification = format!(
+                        r#"{{"jsonrpc":"2.0","method":"accountNotification","params":{}}}"#,
+                        NOTIFICATION_PARAMS
+                    );
+                    ws.send(Message::Text(notification)).await.unwrap();
+                }
+                recorded.lock().unwrap().push(request);
+            }
+        });
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let options = SubscriptionOptions {
+            shutdown: Some(shutdown_rx),
+            ..Default::default()
+        };
+
+        let subscribed = metrics.clone();
+        let subscription = tokio::spawn(async move {
+            subscribe_with(&url, SUBSCRIBE_TIMEOUT, &metrics, &options).await
+        });
+        wait_for(|| subscribed.ws_last_message.get() > 0).await;
+        shutdown_tx.send(true).unwrap();
+        subscription.await.unwrap().unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1]["method"], "accountUnsubscribe");
+        assert_eq!(received[1]["params"], serde_json::json!([7]));
+        assert_eq!(received[1]["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_slow_processing_sheds_oldest_notifications() {
+        // Confirms the subscription, then streams 20 notifications, each with
+        // its context slot as the balance, and closes
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            let Some(Ok(Message::Text(text))) = ws.next().await else {
+                return;
+            };
+            let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let reply = serde_json::json!({"jsonrpc": "2.0", "result": 7, "id": request["id"]});
+            ws.send(Message::Text(reply.to_string())).await.unwrap();
+            for slot in 1000..1020 {
+                let mut params: serde_json::Value =
+                    serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+                params["result"]["context"]["slot"] = slot.into();
+                params["result"]["value"]["lamports"] = slot.into();
+                let notification = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "accountNotification",
+                    "params": params
+                });
+                ws.send(Message::Text(notification.to_string()))
+                    .await
+                    .unwrap();
+            }
+            ws.close(None).await.unwrap();
+            while ws.next().await.is_some() {}
+        });
+
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let clock = WsConnectionClock::default();
+        let debug = DebugState::new(std::env::temp_dir(), 1);
+        let options = SubscriptionOptions {
+            notification_queue_capacity: 4,
+            ..Default::default()
+        };
+        let ctx = SubscriptionContext {
+            vote_pubkey: "A",
+            proxy: None,
+            metrics: &metrics,
+            tracker: &tracker,
+            clock: &clock,
+            debug: &debug,
+            options: &options,
+        };
+
+        // The processor stalls on the tracker lock until the stream is read
+        let stall = tracker.clone().write_owned().await;
+        let (mut failures, mut requests) = (0, RequestIds::new());
+        let (result, ()) = tokio::join!(
+            subscribe_loop(&url, ctx, SUBSCRIBE_TIMEOUT, &mut failures, &mut requests),
+            async {
+                wait_for(|| metrics.ws_notifications_shed.get() >= 15).await;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                drop(stall);
+            }
+        );
+        result.unwrap();
+
+        // One notification taken before the stall, the newest four after it
+        let shed = metrics.ws_notifications_shed.get();
+        assert!((15..=16).contains(&shed), "shed {}", shed);
+        assert_eq!(metrics.vote_account_lamports.get(), 1019);
+        assert!(metrics.ws_last_message.get() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_connect_failure_is_a_connection_error() {
+        // Nothing listens on a freshly released port
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let metrics = Arc::new(Metrics::new().unwrap());
+
+        let err = subscribe_once(&url, SUBSCRIBE_TIMEOUT, &metrics)
+            .await
+            .unwrap_err();
+        let mut failures = 0;
+        record_error(&err, &metrics, &mut failures);
+
+        assert_eq!(metrics.ws_connection_errors.get(), 1);
+        assert_eq!(metrics.ws_subscribe_errors.get(), 0);
+        assert_eq!(failures, 0);
+    }
+
+    #[test]
+    fn test_votes_by_credits_accumulate() {
+        let metrics = Metrics::new().unwrap();
+        let mut tracker = VoteTracker::default();
+
+        let first = tracker.process_update(1002, &[(1000, 2, Some(1))], Some(990), 16, Some(0));
+        record_votes_by_credits(&metrics, &first.update_histogram);
+        let second = tracker.process_update(
+            1003,
+            &[(1000, 3, Some(1)), (1001, 2, Some(3)), (1002, 1, Some(1))],
+            Some(991),
+            32,
+            Some(0),
+        );
+        record_votes_by_credits(&metrics, &second.update_histogram);
+        // Votes are only counted once rooted
+        assert_eq!(metrics.votes_by_credits.with_label_values(&["16"]).get(), 0);
+        let third = tracker.process_update(1004, &[], Some(1003), 64, Some(0));
+        record_votes_by_credits(&metrics, &third.update_histogram);
+
+        let count = |credits: &str| metrics.votes_by_credits.with_label_values(&[credits]).get();
+        assert_eq!(count("16"), 2);
+        assert_eq!(count("14"), 1);
+        assert_eq!(count("0"), 0);
+    }
+
+    /// accountNotification params for a vote account with two epochs of credits
+    const NOTIFICATION_PARAMS: &str = r#"{
+                "result": {
+                    "context": {"slot": 1000},
+                    "value": {
+                        "lamports": 1,
+                        "data": {
+                            "program": "vote",
+                            "parsed": {
+                                "info": {
+                                    "votes": [{"confirmationCount": 1, "latency": 1, "slot": 999}],
+                                    "rootSlot": 990,
+                                    "epochCredits": [
+                                        {"credits": "1000", "epoch": 0, "previousCredits": "0"},
+                                        {"credits": "2000", "epoch": 1, "previousCredits": "1000"}
+                                    ]
+                                },
+                                "type": "vote"
+                            }
+                        },
+                        "owner": "Vote111111111111111111111111111111111111111",
+                        "executable": false,
+                        "rentEpoch": 0
+                    }
+                },
+                "subscription": 1
+            }"#;
+
+    #[tokio::test]
+    async fn test_notification_exports_credit_history() {
+        let params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let options = SubscriptionOptions {
+            min_credit_history_epochs: 64,
+            rent_exemption: Some(RentExemption {
+                minimum_balance: 27_074_400,
+            }),
+            ..Default::default()
+        };
+
+        process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut ForkPressureClassifier::default(),
+            &mut None,
+            &options,
+        )
+        .await
+        .unwrap();
+        // The fixture's account holds a single lamport
+        assert_eq!(metrics.vote_account_lamports.get(), 1);
+        assert_eq!(metrics.vote_account_rent_exempt.get(), 0);
+        assert_eq!(
+            metrics.vote_account_rent_exempt_margin.get(),
+            1 - 27_074_400
+        );
+        assert_eq!(metrics.epoch_credits_history_len.get(), 2);
+        assert_eq!(metrics.epoch_credits_oldest_epoch.get(), 0);
+        assert_eq!(
+            metrics
+                .vote_account_age_epochs
+                .with_label_values(&["64"])
+                .get(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsorted_epoch_credits_keep_the_epoch() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let mut fork_pressure = ForkPressureClassifier::default();
+        let mut last_fingerprint = None;
+        let options = SubscriptionOptions::default();
+
+        // The second notification lists the current epoch's entry first
+        for (context_slot, shuffled) in [(1000, false), (1001, true), (1002, false)] {
+            let mut params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+            params.result.context.slot = context_slot;
+            if let AccountData::Parsed { parsed, .. } = &mut params.result.value.data {
+                if shuffled {
+                    parsed.info.epoch_credits.reverse();
+                }
+            }
+            process_notification(
+                &params,
+                &metrics,
+                &tracker,
+                &mut fork_pressure,
+                &mut last_fingerprint,
+                &options,
+            )
+            .await
+            .unwrap();
+            let tracker = tracker.read().await;
+            assert_eq!(tracker.epoch_info().unwrap().epoch, 1);
+            assert_eq!(tracker.current_epoch_credits(), 1000);
+        }
+        assert_eq!(metrics.epoch.get(), 1);
+        assert_eq!(metrics.epoch_credits_unsorted.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_fingerprint_skips_the_tracker() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let mut fork_pressure = ForkPressureClassifier::default();
+        let mut last_fingerprint = None;
+        let options = SubscriptionOptions::default();
+
+        // The second notification only changes the context slot and balance,
+        // the third carries a new vote
+        for (context_slot, lamports, new_vote) in
+            [(1000, 1, false), (1001, 2, false), (1002, 3, true)]
+        {
+            let mut params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+            params.result.context.slot = context_slot;
+            params.result.value.lamports = lamports;
+            if new_vote {
+                if let AccountData::Parsed { parsed, .. } = &mut params.result.value.data {
+                    let mut vote = parsed.info.votes.last().unwrap().clone();
+                    vote.slot += 1;
+                    parsed.info.votes.push(vote);
+                }
+            }
+            let before = tracker.read().await.snapshot();
+            process_notification(
+                &params,
+                &metrics,
+                &tracker,
+                &mut fork_pressure,
+                &mut last_fingerprint,
+                &options,
+            )
+            .await
+            .unwrap();
+            // The balance is exported either way
+            assert_eq!(metrics.vote_account_lamports.get(), lamports as i64);
+            if context_slot == 1001 {
+                assert_eq!(tracker.read().await.snapshot(), before);
+            }
+        }
+        assert_eq!(metrics.ws_notifications.get(), 3);
+        assert_eq!(metrics.ws_noop_notifications.get(), 1);
+        assert!((metrics.ws_noop_ratio.get() - 1.0 / 3.0).abs() < 1e-9);
+        // Only the two updates were published
+        assert_eq!(metrics.update_generation.get(), 2);
+        assert_eq!(last_fingerprint.unwrap().newest_vote_slot, Some(1000));
+    }
+
+    #[test]
+    fn test_tracker_metrics_publish_without_yielding() {
+        // No runtime here: the publication can't await between its writes
+        let metrics = Metrics::new().unwrap();
+        publish_tracker_metrics(&VoteTracker::default(), &metrics);
+        publish_tracker_metrics(&VoteTracker::default(), &metrics);
+        assert_eq!(metrics.update_generation.get(), 2);
+        assert_eq!(metrics.vote_credits_efficiency_5m_epoch_min.get(), 1.0);
+    }
+
+    #[test]
+    fn test_smoothed_projection_rides_out_a_dip_the_5m_one_follows() {
+        // 4 slots rooted every 1.6s at 16 credits per slot, with a 10-minute
+        // dip to 8 credits per slot (latency 9) after 20 minutes
+        let start_millis = 1_700_000_000_000;
+        let clock = Arc::new(ManualClock::new(start_millis));
+        let mut tracker = VoteTracker::default().with_clock(clock.clone());
+        let metrics = Metrics::new().unwrap();
+        let start = SLOTS_PER_EPOCH + 100_000;
+        let step = 4;
+        let dip = 750..1125;
+
+        // Projection at the given rate from the tracker's current position
+        let at_rate = |tracker: &VoteTracker, rate: u64| {
+            let info = tracker.epoch_info().unwrap();
+            let remaining = info.slots_in_epoch - (info.slot_index + 1);
+            (tracker.current_epoch_credits() + rate * remaining) as i64
+        };
+
+        let mut credits = 0;
+        let (mut smoothed, mut projected_5m) = (Vec::new(), Vec::new());
+        for i in 0..3000u64 {
+            let root = start + step * i;
+            let latency = if dip.contains(&i) { 9 } else { 1 };
+            let votes: Vec<_> = (1..=step).map(|j| (root + j, 1, Some(latency))).collect();
+            tracker.process_update(root + step + 1, &votes, Some(root), credits, Some(1));
+            credits += step * credits_from_latency(latency);
+            clock.set(start_millis + 1600 * (i + 1));
+            publish_tracker_metrics(&tracker, &metrics);
+
+            // Skip the first minutes, while the 5m window fills and the
+            // level settles
+            if i >= 500 {
+                smoothed.push(metrics.projected_credits_smoothed.get());
+                projected_5m.push(metrics.projected_credits_5m.get());
+            }
+            if i == dip.end - 1 {
+                // The 5m window holds only dip votes: the 5m projection
+                // extends the dip over the rest of the epoch
+                assert_eq!(metrics.projected_credits_5m.get(), at_rate(&tracker, 8));
+                assert!(metrics.projected_credits_smoothed.get() > at_rate(&tracker, 13));
+            }
+        }
+
+        let swing = |series: &[i64]| series.iter().max().unwrap() - series.iter().min().unwrap();
+        assert!(
+            swing(&smoothed) < swing(&projected_5m) / 2,
+            "smoothed swing {} should be well below the 5m swing {}",
+            swing(&smoothed),
+            swing(&projected_5m)
+        );
+
+        // After the recovery the 5m projection is back at full credits, the
+        // smoothed one converges towards it from below
+        assert_eq!(metrics.projected_credits_5m.get(), at_rate(&tracker, 16));
+        let rate = tracker.smoothed_rate().unwrap();
+        assert!(rate > 15.0 && rate < 16.0, "smoothed rate {rate}");
+        assert_eq!(
+            metrics.projected_credits_smoothed.get(),
+            tracker.projected_credits_smoothed().unwrap() as i64
+        );
+        assert!(metrics.projected_credits_smoothed.get() < at_rate(&tracker, 16));
+        assert!(metrics.projected_credits_smoothed.get() > at_rate(&tracker, 15));
+    }
+
+    #[tokio::test]
+    async fn test_noop_notification_keeps_the_stream_fresh() {
+        // Sends the fixture, waits for it to be processed, clears the
+        // freshness timestamp and sends the same notification again
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let served = metrics.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            let Some(Ok(Message::Text(text))) = ws.next().await else {
+                return;
+            };
+            let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let reply = serde_json::json!({"jsonrpc": "2.0", "result": 7, "id": request["id"]});
+            ws.send(Message::Text(reply.to_string())).await.unwrap();
+            let notification = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "accountNotification",
+                "params": serde_json::from_str::<serde_json::Value>(NOTIFICATION_PARAMS).unwrap()
+            });
+            ws.send(Message::Text(notification.to_string()))
+                .await
+                .unwrap();
+            wait_for(|| served.ws_last_message.get() > 0).await;
+            served.ws_last_message.set(0);
+            ws.send(Message::Text(notification.to_string()))
+                .await
+                .unwrap();
+            ws.close(None).await.unwrap();
+            while ws.next().await.is_some() {}
+        });
+
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let clock = WsConnectionClock::default();
+        let debug = DebugState::new(std::env::temp_dir(), 1);
+        let options = SubscriptionOptions::default();
+        let ctx = SubscriptionContext {
+            vote_pubkey: "A",
+            proxy: None,
+            metrics: &metrics,
+            tracker: &tracker,
+            clock: &clock,
+            debug: &debug,
+            options: &options,
+        };
+        let (mut failures, mut requests) = (0, RequestIds::new());
+        subscribe_loop(&url, ctx, SUBSCRIBE_TIMEOUT, &mut failures, &mut requests)
+            .await
+            .unwrap();
+
+        assert_eq!(metrics.ws_notifications.get(), 2);
+        assert_eq!(metrics.ws_noop_notifications.get(), 1);
+        assert!(metrics.ws_last_message.get() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_encoded_notification_still_exports_the_data_length() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let options = SubscriptionOptions::default();
+
+        let mut params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+        params.result.value.data = AccountData::Raw("AAECAwQ=".to_string(), "base64".to_string());
+        let result = process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut ForkPressureClassifier::default(),
+            &mut None,
+            &options,
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(metrics.vote_account_data_bytes.get(), 5);
+        assert_eq!(
+            metrics
+                .vote_account_state_info
+                .with_label_values(&["unknown", "unknown"])
+                .get(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_epoch_rollover_is_recorded_as_discontinuity() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let discontinuities = Arc::new(Discontinuities::new());
+        let options = SubscriptionOptions {
+            discontinuities: Some(discontinuities.clone()),
+            ..Default::default()
+        };
+
+        let mut params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+        process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut ForkPressureClassifier::default(),
+            &mut None,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert!(discontinuities.recent().is_empty());
+
+        // The account moves into epoch 2 with 16 credits
+        params.result.context.slot = 1001;
+        if let AccountData::Parsed { parsed, .. } = &mut params.result.value.data {
+            parsed.info.root_slot = Some(991);
+            parsed.info.epoch_credits.push(EpochCreditsEntry {
+                epoch: 2,
+                credits: 2016,
+                previous_credits: 2000,
+            });
+        }
+        process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut ForkPressureClassifier::default(),
+            &mut None,
+            &options,
+        )
+        .await
+        .unwrap();
+
+        let recent = discontinuities.recent();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].epoch, Some(2));
+        assert_eq!(recent[0].slot, Some(991));
+        assert_eq!(
+            recent[0].event,
+            DiscontinuityEvent::EpochRollover {
+                from_epoch: 1,
+                to_epoch: Some(2),
+                credits: 1000,
+                missed: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disabled_metrics_are_not_updated() {
+        let params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+        let metrics = Arc::new(
+            Metrics::with_disabled(DisabledMetrics::HISTOGRAM | DisabledMetrics::PROJECTIONS)
+                .unwrap(),
+        );
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+        let options = SubscriptionOptions::default();
+
+        process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut ForkPressureClassifier::default(),
+            &mut None,
+            &options,
+        )
+        .await
+        .unwrap();
+        let count = &metrics.vote_credits_histogram_count;
+        assert_eq!(count.with_label_values(&["epoch", "16"]).get(), 0);
+        assert_eq!(metrics.projected_credits_1h.get(), 0);
+
+        let (_, body) = metrics.render();
+        assert!(!body.contains("solana_vote_credits_histogram_count"));
+        assert!(!body.contains("solana_vote_credits_projected"));
+        assert!(
+            body.contains(
+                r#"solana_vote_credits_histogram_fraction{credits="16",source="derived",window="epoch"} 0"#
+            )
+        );
+        assert!(body.contains(r#"solana_vote_credits_epoch{source="observed"} 1000"#));
+    }
+
+    #[tokio::test]
+    async fn test_histogram_windows_limit_the_histogram_series() {
+        let params: NotificationParams = serde_json::from_str(NOTIFICATION_PARAMS).unwrap();
+        let metrics = Arc::new(
+            Metrics::new()
+                .unwrap()
+                .with_histogram_windows(&[HistogramWindow::FiveMinutes]),
+        );
+        let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+
+        process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut ForkPressureClassifier::default(),
+            &mut None,
+            &SubscriptionOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let (_, body) = metrics.render();
+        for family in [
+            "solana_vote_credits_histogram_count",
+            "solana_vote_credits_histogram_fraction",
+        ] {
+            let windows: BTreeSet<&str> = body
+                .lines()
+                .filter(|line| line.starts_with(family))
+                .filter_map(|line| line.split("window=\"").nth(1))
+                .filter_map(|rest| rest.split('"').next())
+                .collect();
+            assert_eq!(windows, BTreeSet::from(["5m"]), "{}", family);
+        }
+        // The scalar gauges still cover every window, once it has data
+        assert!(body.contains(r#"tvc_window_history_coverage{window="1h"} 0"#));
+        assert!(!body.contains("solana_missed_vote_credits_1h "));
+        metrics.set_window_coverage(HistogramWindow::OneHour, 1.0);
+        assert!(
+            metrics
+                .render()
+                .1
+                .contains("solana_missed_vote_credits_1h ")
+        );
+        assert!(body.contains("solana_vote_credits_efficiency_epoch "));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_window_is_counted() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (close_tx, close_rx) = tokio::sync::oneshot::channel::<()>();
+
+        // First connection is closed on request, the second handshake is held back 1.5s
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(stream).await.unwrap();
+            close_rx.await.unwrap();
+            ws.close(None).await.unwrap();
+            drop(ws);
+
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            let mut ws = accept_async(stream).await.unwrap();
+            while ws.next().await.is_some() {}
+        });
+
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let clock = Arc::new(WsConnectionClock::default());
+        let discontinuities = Arc::new(Discontinuities::new());
+        let (task_metrics, task_clock) = (metrics.clone(), clock.clone());
+        let options = SubscriptionOptions {
+            discontinuities: Some(discontinuities.clone()),
+            ..Default::default()
+        };
+        tokio::spawn(async move {
+            let url = format!("http://{}", addr);
+            let tracker = Arc::new(RwLock::new(VoteTracker::default()));
+            let debug = Arc::new(DebugState::new(std::env::temp_dir(), 1));
+            run_vote_subscription(&url, "A", task_metrics, tracker, task_clock, debug, options)
+                .await
+        });
+
+        // Startup before the first connection is not downtime
+        wait_for(|| clock.connection_age().is_some()).await;
+        assert_eq!(metrics.ws_disconnected_seconds.get(), 0.0);
+
+        close_tx.send(()).unwrap();
+        wait_for(|| metrics.ws_reconnects.get() == 1).await;
+        wait_for(|| clock.connection_age().is_some()).await;
+        let reconnects = discontinuities.recent();
+        assert_eq!(reconnects.len(), 1);
+        assert_eq!(
+            reconnects[0].event,
+            DiscontinuityEvent::WsReconnect {
+                closed_normally: true
+            }
+        );
+
+        let downtime = metrics.ws_disconnected_seconds.get();
+        assert!((1.4..2.5).contains(&downtime), "downtime {}", downtime);
+        assert!(clock.connection_age().unwrap() < Duration::from_secs(1));
     }
 }
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Headline numbers of a finished epoch, kept to compare the next one against
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EpochResult {
+    pub epoch: u64,
+    pub credits: u64,
+    pub missed: u64,
+    /// Average vote latency in slots (None without votes to average)
+    pub avg_latency: Option<f64>,
+}
+
+impl EpochResult {
+    /// Credits earned over credits earned plus missed (None if neither)
+    pub fn efficiency(&self) -> Option<f64> {
+        let expected = self.credits + self.missed;
+        (expected > 0).then(|| self.credits as f64 / expected as f64)
+    }
+}
+
+/// Change from the previous finished epoch to the one that just ended
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EpochComparison {
+    /// Epoch compared against; earlier than the one before if the exporter
+    /// was down over a boundary
+    pub previous_epoch: u64,
+    pub credits_delta: i64,
+    pub missed_delta: i64,
+    pub efficiency_delta: Option<f64>,
+    /// Positive when votes landed slower
+    pub avg_latency_delta: Option<f64>,
+}
+
+impl EpochComparison {
+    /// Compare `current` against `previous` (None unless it is an earlier epoch)
+    pub fn between(previous: &EpochResult, current: &EpochResult) -> Option<Self> {
+        if previous.epoch >= current.epoch {
+            return None;
+        }
+        let delta = |previous: Option<f64>, current: Option<f64>| Some(current? - previous?);
+        Some(Self {
+            previous_epoch: previous.epoch,
+            credits_delta: current.credits as i64 - previous.credits as i64,
+            missed_delta: current.missed as i64 - previous.missed as i64,
+            efficiency_delta: delta(previous.efficiency(), current.efficiency()),
+            avg_latency_delta: delta(previous.avg_latency, current.avg_latency),
+        })
+    }
+}
+
+impl fmt::Display for EpochComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "vs epoch {}: credits {:+}, missed {:+}",
+            self.previous_epoch, self.credits_delta, self.missed_delta
+        )?;
+        if let Some(delta) = self.efficiency_delta {
+            write!(f, ", efficiency {:+.4}", delta)?;
+        }
+        if let Some(delta) = self.avg_latency_delta {
+            write!(f, ", avg latency {:+.3} slots", delta)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(epoch: u64, credits: u64, missed: u64, avg_latency: Option<f64>) -> EpochResult {
+        EpochResult {
+            epoch,
+            credits,
+            missed,
+            avg_latency,
+        }
+    }
+
+    #[test]
+    fn test_comparison_deltas() {
+        let previous = result(500, 6_000, 2_000, Some(1.5));
+        let current = result(501, 7_000, 1_000, Some(1.25));
+        let comparison = EpochComparison::between(&previous, &current).unwrap();
+        assert_eq!(comparison.previous_epoch, 500);
+        assert_eq!(comparison.credits_delta, 1_000);
+        assert_eq!(comparison.missed_delta, -1_000);
+        assert_eq!(comparison.efficiency_delta, Some(0.875 - 0.75));
+        assert_eq!(comparison.avg_latency_delta, Some(-0.25));
+        assert_eq!(
+            comparison.to_string(),
+            "vs epoch 500: credits +1000, missed -1000, efficiency +0.1250, avg latency -0.250 slots"
+        );
+
+        // Poll mode has no latencies to compare
+        let comparison =
+            EpochComparison::between(&result(500, 0, 0, None), &result(502, 10, 0, None)).unwrap();
+        assert_eq!(comparison.efficiency_delta, None);
+        assert_eq!(comparison.avg_latency_delta, None);
+    }
+
+    #[test]
+    fn test_no_comparison_against_same_or_later_epoch() {
+        let current = result(500, 7_000, 1_000, None);
+        assert_eq!(EpochComparison::between(&current, &current), None);
+        assert_eq!(
+            EpochComparison::between(&result(501, 0, 0, None), &current),
+            None
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Convert HTTP URL to WebSocket URL
+pub fn http_to_ws_url(http_url: &str) -> String {
+    if http_url.starts_with("https://") {
+        http_url.replace("https://", "wss://")
+    } else if http_url.starts_with("http://") {
+        http_url.replace("http://", "ws://")
+    } else if http_url.starts_with("wss://") || http_url.starts_with("ws://") {
+        http_url.to_string()
+    } else {
+        format!("wss://{}", http_url)
+    }
+}
+
+/// Seconds a confirmed subscription may stay silent before it is dropped
+pub const DEFAULT_FIRST_MESSAGE_TIMEOUT_SECS: u64 = 60;
+
+/// How long shutdown waits for the subscription to unsubscribe and close
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Connection timing shared between the subscription loop and `/status`
+#[derive(Debug, Default)]
+pub struct WsConnectionClock {
+    state: Mutex<ConnectionState>,
+}
+
+#[derive(Debug, Default)]
+enum ConnectionState {
+    /// No attempt finished yet: startup is not counted as downtime
+    #[default]
+    NeverConnected,
+    Connected {
+        since: Instant,
+    },
+    /// Downtime up to `accounted_until` has already been reported
+    Disconnected {
+        accounted_until: Instant,
+    },
+}
+
+impl WsConnectionClock {
+    /// Mark the connection as up, returning downtime not yet reported
+    pub fn connected(&self) -> Duration {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let downtime = match *state {
+            ConnectionState::Disconnected { accounted_until } => now - accounted_until,
+            _ => Duration::ZERO,
+        };
+        *state = ConnectionState::Connected { since: now };
+        downtime
+    }
+
+    /// Mark the connection as down (or a reconnect attempt as failed),
+    /// returning downtime not yet reported
+    pub fn disconnected(&self) -> Duration {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let downtime = match *state {
+            ConnectionState::Disconnected { accounted_until } => now - accounted_until,
+            _ => Duration::ZERO,
+        };
+        *state = ConnectionState::Disconnected {
+            accounted_until: now,
+        };
+        downtime
+    }
+
+    /// Age of the current connection, None while disconnected
+    pub fn connection_age(&self) -> Option<Duration> {
+        match *self.state.lock().unwrap() {
+            ConnectionState::Connected { since } => Some(since.elapsed()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_to_ws_url() {
+        assert_eq!(
+            http_to_ws_url("https://api.mainnet-beta.solana.com"),
+            "wss://api.mainnet-beta.solana.com"
+        );
+        assert_eq!(
+            http_to_ws_url("http://127.0.0.1:8899"),
+            "ws://127.0.0.1:8899"
+        );
+        assert_eq!(http_to_ws_url("ws://127.0.0.1:8900"), "ws://127.0.0.1:8900");
+        assert_eq!(http_to_ws_url("rpc.example.com"), "wss://rpc.example.com");
+    }
+}
@@ -0,0 +1,262 @@
+use crate::ws::windowed::WindowedCredits;
+
+use std::collections::VecDeque;
+
+/// Default number of recent notifications the repeat ratio is taken over
+pub const DEFAULT_FORK_WINDOW_NOTIFICATIONS: usize = 32;
+
+/// Default share of recent notifications not advancing the context slot
+/// above which the cluster counts as forking
+pub const DEFAULT_FORK_MAX_REPEAT_RATIO: f64 = 0.1;
+
+/// Default context slots the root may stand still for before the cluster
+/// counts as forking (the root normally follows within a slot or two)
+pub const DEFAULT_FORK_ROOT_STALL_SLOTS: u64 = 32;
+
+/// Whether the cluster was forking while an update was accounted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForkRegime {
+    #[default]
+    Calm,
+    Turbulent,
+}
+
+impl ForkRegime {
+    pub const ALL: [ForkRegime; 2] = [ForkRegime::Calm, ForkRegime::Turbulent];
+
+    /// Value of the `regime` metric label
+    pub fn label(&self) -> &'static str {
+        match self {
+            ForkRegime::Calm => "calm",
+            ForkRegime::Turbulent => "turbulent",
+        }
+    }
+}
+
+/// Thresholds of [`ForkPressureClassifier`] (--fork-* flags)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForkPressureConfig {
+    pub window_notifications: usize,
+    pub max_repeat_ratio: f64,
+    pub root_stall_slots: u64,
+}
+
+impl Default for ForkPressureConfig {
+    fn default() -> Self {
+        Self {
+            window_notifications: DEFAULT_FORK_WINDOW_NOTIFICATIONS,
+            max_repeat_ratio: DEFAULT_FORK_MAX_REPEAT_RATIO,
+            root_stall_slots: DEFAULT_FORK_ROOT_STALL_SLOTS,
+        }
+    }
+}
+
+/// Estimates fork pressure from the notification stream. Calm, the vote
+/// account is notified about once per context slot and its root advances
+/// with it. While the cluster forks, notifications repeat or go back to
+/// context slots already seen, and the root stalls while context slots
+/// carry on.
+#[derive(Debug, Clone, Default)]
+pub struct ForkPressureClassifier {
+    config: ForkPressureConfig,
+    /// Whether each recent notification failed to advance the context slot
+    repeats: VecDeque<bool>,
+    max_context_slot: Option<u64>,
+    /// Root last seen and the context slot at which it last advanced
+    root: Option<(u64, u64)>,
+    regime: ForkRegime,
+}
+
+impl ForkPressureClassifier {
+    pub fn new(config: ForkPressureConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Add a notification and return the regime its update belongs to
+    pub fn observe(&mut self, context_slot: u64, root_slot: Option<u64>) -> ForkRegime {
+        let repeat = self.max_context_slot.is_some_and(|max| context_slot <= max);
+        self.max_context_slot = self.max_context_slot.max(Some(context_slot));
+        self.repeats.push_back(repeat);
+        while self.repeats.len() > self.config.window_notifications.max(1) {
+            self.repeats.pop_front();
+        }
+
+        let mut stalled_slots = 0;
+        if let Some(root) = root_slot {
+            match self.root {
+                Some((last_root, since)) if root <= last_root => {
+                    stalled_slots = context_slot.saturating_sub(since);
+                }
+                _ => self.root = Some((root, context_slot)),
+            }
+        }
+
+        let turbulent = self.repeat_ratio() > self.config.max_repeat_ratio
+            || stalled_slots >= self.config.root_stall_slots;
+        self.regime = if turbulent {
+            ForkRegime::Turbulent
+        } else {
+            ForkRegime::Calm
+        };
+        self.regime
+    }
+
+    pub fn regime(&self) -> ForkRegime {
+        self.regime
+    }
+
+    /// Share of the recent notifications that didn't advance the context slot
+    pub fn repeat_ratio(&self) -> f64 {
+        if self.repeats.is_empty() {
+            return 0.0;
+        }
+        self.repeats.iter().filter(|r| **r).count() as f64 / self.repeats.len() as f64
+    }
+}
+
+/// Credits earned and missed over some span
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegimeCredits {
+    pub credits: u64,
+    pub missed: u64,
+}
+
+impl RegimeCredits {
+    pub fn expected(&self) -> u64 {
+        self.credits + self.missed
+    }
+
+    /// None until something was expected
+    pub fn efficiency(&self) -> Option<f64> {
+        let expected = self.expected();
+        (expected > 0).then(|| self.credits as f64 / expected as f64)
+    }
+}
+
+/// Credits and missed credits of the updates accounted while turbulent, with
+/// a history for the windows (the calm share is the rest of the window)
+#[derive(Debug, Clone, Default)]
+pub struct ForkRegimeTotals {
+    /// Credits and missed credits of turbulent updates that reached the
+    /// windowed histograms
+    turbulent: WindowedCredits,
+}
+
+impl ForkRegimeTotals {
+    /// Account one update's windowed credits and missed credits
+    pub fn record(&mut self, regime: ForkRegime, credits: u64, missed: u64) {
+        if regime == ForkRegime::Turbulent {
+            self.turbulent.add(credits, missed);
+        }
+    }
+
+    /// Add a history entry at `now` and drop entries before `cutoff`
+    pub fn push_history(&mut self, now: u64, cutoff: u64) {
+        self.turbulent.push_history(now, cutoff);
+    }
+
+    /// Drop history entries before `cutoff`
+    pub fn prune_history(&mut self, cutoff: u64) {
+        self.turbulent.prune_history(cutoff);
+    }
+
+    /// Estimated bytes held by the history
+    pub fn memory_footprint(&self) -> usize {
+        self.turbulent.memory_footprint()
+    }
+
+    /// Turbulent credits and missed credits since `start` (unix millis)
+    pub fn turbulent_since(&self, start: u64) -> RegimeCredits {
+        let (credits, missed) = self.turbulent.since(start);
+        RegimeCredits { credits, missed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(slots: &[(u64, u64)]) -> Vec<ForkRegime> {
+        let mut classifier = ForkPressureClassifier::new(ForkPressureConfig {
+            window_notifications: 10,
+            ..Default::default()
+        });
+        slots
+            .iter()
+            .map(|(context, root)| classifier.observe(*context, Some(*root)))
+            .collect()
+    }
+
+    #[test]
+    fn test_calm_sequence_stays_calm() {
+        // One notification per slot, the root 32 behind, some slots skipped
+        let slots: Vec<(u64, u64)> = (1000..1100)
+            .filter(|slot| slot % 7 != 0)
+            .map(|slot| (slot, slot - 32))
+            .collect();
+        assert!(
+            classify(&slots)
+                .iter()
+                .all(|regime| *regime == ForkRegime::Calm)
+        );
+    }
+
+    #[test]
+    fn test_repeated_context_slots_are_turbulent() {
+        // Every other notification goes back to a slot already seen
+        let mut slots = Vec::new();
+        for slot in 1000..1020 {
+            slots.push((slot, slot - 32));
+            slots.push((slot - 1, slot - 32));
+        }
+        let regimes = classify(&slots);
+        assert_eq!(regimes[0], ForkRegime::Calm);
+        assert_eq!(*regimes.last().unwrap(), ForkRegime::Turbulent);
+
+        // Calm again once the repeats leave the window
+        let calm: Vec<(u64, u64)> = (1020..1040).map(|slot| (slot, slot - 32)).collect();
+        let mut classifier = ForkPressureClassifier::new(ForkPressureConfig {
+            window_notifications: 10,
+            ..Default::default()
+        });
+        for (context, root) in slots.iter().chain(&calm) {
+            classifier.observe(*context, Some(*root));
+        }
+        assert_eq!(classifier.regime(), ForkRegime::Calm);
+        assert_eq!(classifier.repeat_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_stalled_root_is_turbulent() {
+        // Context slots advance while the root stays at 968
+        let slots: Vec<(u64, u64)> = (1000..1040)
+            .map(|slot| (slot, (slot - 32).min(968)))
+            .collect();
+        let regimes = classify(&slots);
+        assert_eq!(regimes[31], ForkRegime::Calm);
+        assert_eq!(regimes[32], ForkRegime::Turbulent);
+        assert_eq!(*regimes.last().unwrap(), ForkRegime::Turbulent);
+    }
+
+    #[test]
+    fn test_totals_window_counts_turbulent_updates_only() {
+        let mut totals = ForkRegimeTotals::default();
+        totals.record(ForkRegime::Calm, 160, 0);
+        totals.push_history(1_000, 0);
+        totals.record(ForkRegime::Turbulent, 100, 60);
+        totals.push_history(2_000, 0);
+
+        assert_eq!(
+            totals.turbulent_since(500),
+            RegimeCredits {
+                credits: 100,
+                missed: 60
+            }
+        );
+        assert_eq!(totals.turbulent_since(2_500), RegimeCredits::default());
+        assert_eq!(totals.turbulent_since(500).efficiency(), Some(0.625));
+    }
+}
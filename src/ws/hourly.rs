@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+const MILLIS_PER_HOUR: u64 = 3600 * 1000;
+const MILLIS_PER_DAY: u64 = 24 * MILLIS_PER_HOUR;
+
+/// Weight kept per day of age: older days are weighted out with a time
+/// constant of about a week, so each bucket is a rolling ~7-day average
+pub const PROFILE_DAY_DECAY: f64 = 6.0 / 7.0;
+
+/// Buckets not updated for this many days are considered stale and not exported
+pub const PROFILE_MAX_AGE_DAYS: u64 = 7;
+
+/// Decayed credits earned and expected during one UTC hour of the day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct HourBucket {
+    pub credits: f64,
+    pub expected: f64,
+    /// Day (days since the unix epoch) the sums were last decayed to
+    pub day: u64,
+}
+
+/// Efficiency by UTC hour of day, to spot recurring daily dips
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HourlyProfile {
+    buckets: [HourBucket; 24],
+}
+
+impl HourlyProfile {
+    /// Add credits earned and expected at `now_millis` (unix) to its hour's bucket,
+    /// decaying the bucket first by the days since it was last updated
+    pub fn record(&mut self, now_millis: u64, credits: u64, expected: u64) {
+        let day = now_millis / MILLIS_PER_DAY;
+        let bucket = &mut self.buckets[hour_of_day(now_millis)];
+        if day > bucket.day {
+            let days = (day - bucket.day).min(i32::MAX as u64) as i32;
+            let weight = PROFILE_DAY_DECAY.powi(days);
+            bucket.credits *= weight;
+            bucket.expected *= weight;
+            bucket.day = day;
+        }
+        bucket.credits += credits as f64;
+        bucket.expected += expected as f64;
+    }
+
+    /// Decayed efficiency of `hour` (0-23), None without recent data
+    pub fn efficiency(&self, hour: usize, now_millis: u64) -> Option<f64> {
+        let bucket = self.buckets.get(hour)?;
+        let age_days = (now_millis / MILLIS_PER_DAY).saturating_sub(bucket.day);
+        if bucket.expected <= 0.0 || age_days >= PROFILE_MAX_AGE_DAYS {
+            return None;
+        }
+        Some(bucket.credits / bucket.expected)
+    }
+
+    pub fn buckets(&self) -> &[HourBucket; 24] {
+        &self.buckets
+    }
+}
+
+/// UTC hour of day (0-23) of a unix timestamp in milliseconds
+pub fn hour_of_day(millis: u64) -> usize {
+    ((millis % MILLIS_PER_DAY) / MILLIS_PER_HOUR) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01 00:00:00 UTC
+    const MIDNIGHT: u64 = 1_704_067_200_000;
+
+    fn at(day: u64, hour: u64, minute: u64) -> u64 {
+        MIDNIGHT + day * MILLIS_PER_DAY + hour * MILLIS_PER_HOUR + minute * 60_000
+    }
+
+    #[test]
+    fn test_hour_of_day() {
+        assert_eq!(hour_of_day(at(0, 0, 0)), 0);
+        assert_eq!(hour_of_day(at(0, 13, 59)), 13);
+        assert_eq!(hour_of_day(at(0, 14, 0)), 14);
+        assert_eq!(hour_of_day(at(3, 23, 59)), 23);
+    }
+
+    #[test]
+    fn test_updates_land_in_their_hour() {
+        let mut profile = HourlyProfile::default();
+        profile.record(at(0, 13, 59), 160, 160);
+        // Crossing into 14:00 starts a different bucket
+        profile.record(at(0, 14, 0), 120, 160);
+        profile.record(at(0, 14, 30), 40, 160);
+
+        let now = at(0, 15, 0);
+        assert_eq!(profile.efficiency(13, now), Some(1.0));
+        assert_eq!(profile.efficiency(14, now), Some(0.5));
+        assert_eq!(profile.efficiency(15, now), None);
+    }
+
+    #[test]
+    fn test_old_days_are_weighted_out() {
+        let mut profile = HourlyProfile::default();
+        profile.record(at(0, 14, 0), 80, 160); // a bad day: 0.5
+        profile.record(at(1, 14, 0), 160, 160); // then a perfect one
+
+        let now = at(1, 15, 0);
+        let w = PROFILE_DAY_DECAY;
+        let expected = (80.0 * w + 160.0) / (160.0 * w + 160.0);
+        assert!((profile.efficiency(14, now).unwrap() - expected).abs() < 1e-12);
+
+        // A week of perfect days later the bad day is mostly weighted out
+        for day in 2..9 {
+            profile.record(at(day, 14, 0), 160, 160);
+        }
+        let bad_weight = w.powi(8);
+        let total_weight: f64 = (0..9).map(|age| w.powi(age)).sum();
+        let expected = 1.0 - 0.5 * bad_weight / total_weight;
+        let efficiency = profile.efficiency(14, at(8, 15, 0)).unwrap();
+        assert!((efficiency - expected).abs() < 1e-12);
+        assert!(efficiency > 0.97);
+    }
+
+    #[test]
+    fn test_stale_buckets_are_dropped() {
+        let mut profile = HourlyProfile::default();
+        profile.record(at(0, 14, 0), 80, 160);
+        assert_eq!(profile.efficiency(14, at(6, 23, 0)), Some(0.5));
+        assert_eq!(profile.efficiency(14, at(7, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_clock_going_back_a_day_does_not_decay() {
+        let mut profile = HourlyProfile::default();
+        profile.record(at(1, 14, 0), 80, 160);
+        profile.record(at(0, 14, 0), 160, 160);
+        assert_eq!(profile.buckets()[14].day, MIDNIGHT / MILLIS_PER_DAY + 1);
+        assert_eq!(profile.efficiency(14, at(1, 15, 0)), Some(0.75));
+    }
+}
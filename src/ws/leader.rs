@@ -0,0 +1,156 @@
+use crate::ws::windowed::WindowedCredits;
+
+use std::collections::BTreeSet;
+use std::mem::size_of;
+
+/// Own leader slots of one epoch, with the credits earned and expected on
+/// them so efficiency can also be reported with those slots left out
+#[derive(Debug, Clone, Default)]
+pub struct LeaderSlots {
+    /// Epoch of the schedule (None until one was set)
+    epoch: Option<u64>,
+    /// Absolute leader slots
+    slots: BTreeSet<u64>,
+    /// Credits of rooted leader-slot votes that reached the windowed
+    /// histograms and missed credits attributed to leader slots
+    windowed: WindowedCredits,
+    /// Credits earned and expected this epoch while tracked, over all slots
+    epoch_total_credits: u64,
+    epoch_total_expected: u64,
+    /// Credits earned and expected this epoch on leader slots
+    epoch_leader_credits: u64,
+    epoch_leader_expected: u64,
+}
+
+impl LeaderSlots {
+    /// Replace the schedule with `slot_indices` (offsets into `epoch`)
+    pub fn set_schedule(&mut self, epoch: u64, epoch_start_slot: u64, slot_indices: &[u64]) {
+        self.epoch = Some(epoch);
+        self.slots = slot_indices
+            .iter()
+            .map(|index| epoch_start_slot + index)
+            .collect();
+    }
+
+    /// The same schedule with the sums and history cleared
+    pub fn schedule_only(&self) -> Self {
+        Self {
+            epoch: self.epoch,
+            slots: self.slots.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Epoch of the current schedule
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    /// Number of leader slots in the schedule
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn contains(&self, slot: u64) -> bool {
+        self.slots.contains(&slot)
+    }
+
+    /// Leader slots in `(after, through]`
+    pub fn count_between(&self, after: u64, through: u64) -> u64 {
+        if through <= after {
+            return 0;
+        }
+        self.slots.range(after + 1..=through).count() as u64
+    }
+
+    /// Forget the epoch sums at an epoch rollover
+    pub fn start_epoch(&mut self) {
+        self.epoch_total_credits = 0;
+        self.epoch_total_expected = 0;
+        self.epoch_leader_credits = 0;
+        self.epoch_leader_expected = 0;
+    }
+
+    /// Account one rooted range: `total_*` over all its slots, `leader_*` over
+    /// its leader slots. `windowed_credits` are the leader credits that went
+    /// into the windowed histograms and `leader_missed` the missed credits
+    /// attributed to leader slots.
+    pub fn record_rooted(
+        &mut self,
+        total: (u64, u64),
+        leader: (u64, u64),
+        windowed_credits: u64,
+        leader_missed: u64,
+    ) {
+        self.epoch_total_credits += total.0;
+        self.epoch_total_expected += total.1;
+        self.epoch_leader_credits += leader.0;
+        self.epoch_leader_expected += leader.1;
+        self.windowed.add(windowed_credits, leader_missed);
+    }
+
+    /// Add a history entry at `now` and drop entries before `cutoff`
+    pub fn push_history(&mut self, now: u64, cutoff: u64) {
+        self.windowed.push_history(now, cutoff);
+    }
+
+    /// Drop history entries before `cutoff`
+    pub fn prune_history(&mut self, cutoff: u64) {
+        self.windowed.prune_history(cutoff);
+    }
+
+    /// Estimated bytes held by the leader slots and history
+    pub fn memory_footprint(&self) -> usize {
+        self.slots.len() * size_of::<u64>() + self.windowed.memory_footprint()
+    }
+
+    /// Leader credits and missed credits since `start` (unix millis)
+    pub fn window(&self, start: u64) -> (u64, u64) {
+        self.windowed.since(start)
+    }
+
+    /// Efficiency this epoch (while tracked) with the leader slots left out
+    pub fn epoch_efficiency(&self) -> Option<f64> {
+        let expected = self
+            .epoch_total_expected
+            .saturating_sub(self.epoch_leader_expected);
+        let credits = self
+            .epoch_total_credits
+            .saturating_sub(self.epoch_leader_credits);
+        (expected > 0).then(|| credits.min(expected) as f64 / expected as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_uses_absolute_slots() {
+        let mut leader = LeaderSlots::default();
+        leader.set_schedule(2, 864_000, &[0, 1, 2, 3, 100]);
+        assert_eq!(leader.epoch(), Some(2));
+        assert_eq!(leader.len(), 5);
+        assert!(leader.contains(864_100));
+        assert!(!leader.contains(100));
+        assert_eq!(leader.count_between(864_000, 864_100), 4);
+        assert_eq!(leader.count_between(864_100, 864_100), 0);
+    }
+
+    #[test]
+    fn test_window_counts_windowed_leader_credits() {
+        let mut leader = LeaderSlots::default();
+        leader.record_rooted((160, 160), (16, 32), 16, 16);
+        leader.push_history(1_000, 0);
+        leader.record_rooted((160, 160), (0, 16), 0, 16);
+        leader.push_history(2_000, 0);
+
+        assert_eq!(leader.window(1_500), (0, 16));
+        assert_eq!(leader.window(500), (16, 32));
+        assert_eq!(leader.schedule_only().window(500), (0, 0));
+    }
+}
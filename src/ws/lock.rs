@@ -0,0 +1,135 @@
+use crate::metrics::{DisabledMetrics, Metrics};
+use crate::ws::tracker::VoteTracker;
+
+use prometheus::Histogram;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::time::Instant;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Guard of the shared tracker lock that records how long it was held when
+/// dropped (with --lock-metrics)
+pub struct TrackerGuard<G> {
+    guard: G,
+    hold: Option<(Histogram, Instant)>,
+}
+
+impl<G> Deref for TrackerGuard<G>
+where
+    G: Deref<Target = VoteTracker>,
+{
+    type Target = VoteTracker;
+
+    fn deref(&self) -> &VoteTracker {
+        &self.guard
+    }
+}
+
+impl<G> DerefMut for TrackerGuard<G>
+where
+    G: DerefMut<Target = VoteTracker>,
+{
+    fn deref_mut(&mut self) -> &mut VoteTracker {
+        &mut self.guard
+    }
+}
+
+impl<G> Drop for TrackerGuard<G> {
+    fn drop(&mut self) {
+        if let Some((hold, acquired)) = &self.hold {
+            hold.observe(acquired.elapsed().as_secs_f64());
+        }
+    }
+}
+
+/// Lock the shared tracker for reading, timing the wait and hold
+pub async fn read_tracker<'a>(
+    tracker: &'a RwLock<VoteTracker>,
+    metrics: &Metrics,
+) -> TrackerGuard<RwLockReadGuard<'a, VoteTracker>> {
+    timed_lock(metrics, "read", tracker.read()).await
+}
+
+/// Lock the shared tracker for writing, timing the wait and hold
+pub async fn write_tracker<'a>(
+    tracker: &'a RwLock<VoteTracker>,
+    metrics: &Metrics,
+) -> TrackerGuard<RwLockWriteGuard<'a, VoteTracker>> {
+    timed_lock(metrics, "write", tracker.write()).await
+}
+
+async fn timed_lock<G>(
+    metrics: &Metrics,
+    op: &str,
+    acquire: impl Future<Output = G>,
+) -> TrackerGuard<G> {
+    if !metrics.is_enabled(DisabledMetrics::LOCKS) {
+        return TrackerGuard {
+            guard: acquire.await,
+            hold: None,
+        };
+    }
+    let started = Instant::now();
+    let guard = acquire.await;
+    let acquired = Instant::now();
+    metrics
+        .tracker_lock_wait
+        .with_label_values(&[op])
+        .observe(acquired.duration_since(started).as_secs_f64());
+    TrackerGuard {
+        guard,
+        hold: Some((metrics.tracker_lock_hold.with_label_values(&[op]), acquired)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_count(metrics: &Metrics, op: &str) -> (u64, u64) {
+        (
+            metrics
+                .tracker_lock_wait
+                .with_label_values(&[op])
+                .get_sample_count(),
+            metrics
+                .tracker_lock_hold
+                .with_label_values(&[op])
+                .get_sample_count(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_lock_timings_are_recorded_when_enabled() {
+        let tracker = RwLock::new(VoteTracker::default());
+        let metrics = Metrics::with_disabled(DisabledMetrics::empty()).unwrap();
+
+        let guard = read_tracker(&tracker, &metrics).await;
+        assert_eq!(guard.current_epoch_credits(), 0);
+        // Hold time is observed once the guard is released
+        assert_eq!(sample_count(&metrics, "read"), (1, 0));
+        drop(guard);
+        assert_eq!(sample_count(&metrics, "read"), (1, 1));
+
+        write_tracker(&tracker, &metrics)
+            .await
+            .reset(5, 5 * 432_000, 0);
+        assert_eq!(sample_count(&metrics, "write"), (1, 1));
+        assert_eq!(
+            tracker.read().await.epoch_info().map(|info| info.epoch),
+            Some(5)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_timings_are_off_by_default() {
+        let tracker = RwLock::new(VoteTracker::default());
+        let metrics = Metrics::new().unwrap();
+
+        drop(read_tracker(&tracker, &metrics).await);
+        drop(write_tracker(&tracker, &metrics).await);
+        assert_eq!(sample_count(&metrics, "read"), (0, 0));
+        assert_eq!(sample_count(&metrics, "write"), (0, 0));
+        assert!(!metrics.render().1.contains("tracker_lock"));
+    }
+}
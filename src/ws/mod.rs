@@ -1,7 +1,53 @@
+mod acknowledged;
+#[cfg(feature = "ws")]
 mod client;
+mod comparison;
+mod connection;
+mod fork;
+mod hourly;
+mod leader;
+mod lock;
+mod prior_voters;
+mod queue;
+mod shape;
+mod skipped;
+mod slots;
 mod tracker;
 mod types;
+mod windowed;
 
-pub use client::run_vote_subscription;
-pub use tracker::{EpochInfo, MAX_CREDITS_PER_SLOT, SLOTS_PER_EPOCH, VoteTracker};
+pub use acknowledged::{
+    AcknowledgedSubscription, DataEncoding, EffectiveCommitment, FetchedAccount,
+    UNFINALIZED_LEAD_SLOTS,
+};
+#[cfg(feature = "ws")]
+pub use client::{
+    SubscriptionOptions, UpdateFingerprint, process_notification, run_vote_subscription,
+};
+pub use comparison::{EpochComparison, EpochResult};
+pub use connection::{
+    DEFAULT_FIRST_MESSAGE_TIMEOUT_SECS, SHUTDOWN_GRACE, WsConnectionClock, http_to_ws_url,
+};
+pub use fork::{
+    DEFAULT_FORK_MAX_REPEAT_RATIO, DEFAULT_FORK_ROOT_STALL_SLOTS,
+    DEFAULT_FORK_WINDOW_NOTIFICATIONS, ForkPressureClassifier, ForkPressureConfig, ForkRegime,
+    ForkRegimeTotals, RegimeCredits,
+};
+pub use hourly::{HourBucket, HourlyProfile, PROFILE_DAY_DECAY, PROFILE_MAX_AGE_DAYS, hour_of_day};
+pub use leader::LeaderSlots;
+pub use lock::{TrackerGuard, read_tracker, write_tracker};
+pub use prior_voters::PriorVotersWatch;
+pub use queue::{DEFAULT_NOTIFICATION_QUEUE_CAPACITY, NotificationQueue};
+pub use shape::{AccountShape, AccountShapeWatch};
+pub use skipped::{MAX_PENDING_SKIP_RANGES, RootedRange, SkippedSlots};
+pub use slots::{RECENT_SLOTS_CAPACITY, RecentSlots, SlotOutcome, SlotRecord};
+pub use tracker::{
+    CATCH_UP_ENTER_LATENCY, CATCH_UP_EXIT_LATENCY, CreditStallChange, DEFAULT_CREDIT_STALL_UPDATES,
+    DEFAULT_PROJECTION_ALPHA, DEFAULT_TIP_ALLOWANCE_SLOTS, EfficiencyCrossCheck, EpochInfo,
+    EpochSummary, ExpectedAnchor, MissedAccounting, NEVER_ROOTED_BOUND_SLOTS, OfflineGap,
+    PerfectSlotStreak, REGIME_WINDOW_VOTES, RESTART_MIN_GAP_SLOTS, ROOT_DELAY_MAX_AGE_MS,
+    ROOT_DELAY_MAX_SLOTS, Regime, RegimeClassifier, SLOTS_PER_EPOCH, SuspectedRestart,
+    TipExpectation, TrackerBaseline, TrackerHistory, TrackerSnapshot, UnobservedSlots,
+    UpdateResult, VoteTracker, WINDOW_BASE_MAX_LAG_MS, WorstWindow, interpolate_counter,
+};
 pub use types::*;
@@ -0,0 +1,97 @@
+use crate::metrics::Metrics;
+use crate::ws::types::PriorVoter;
+
+use std::sync::Mutex;
+use tracing::warn;
+
+/// The vote account's prior voters seen last, kept across reconnects: a new
+/// entry means the authorized voter was rotated, as a hot-spare failover does
+#[derive(Debug, Default)]
+pub struct PriorVotersWatch {
+    last: Mutex<Option<Vec<PriorVoter>>>,
+}
+
+impl PriorVotersWatch {
+    /// The prior voters seen last, oldest first (empty before the first
+    /// notification)
+    pub fn current(&self) -> Vec<PriorVoter> {
+        let last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        last.clone().unwrap_or_default()
+    }
+
+    /// Export the number of `voters` as `solana_prior_voter_entries` and warn
+    /// about each entry not in the last list seen. The first list seen holds
+    /// no rotation. Returns the new entries.
+    pub fn observe(&self, voters: &[PriorVoter], metrics: &Metrics) -> Vec<PriorVoter> {
+        metrics.prior_voter_entries.set(voters.len() as i64);
+
+        // Ring order wraps once the program has kept 32 voters
+        let mut voters = voters.to_vec();
+        voters.sort_by_key(|voter| (voter.target_epoch, voter.epoch_of_last_authorized_switch));
+
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let added: Vec<PriorVoter> = match last.as_ref() {
+            Some(previous) => voters
+                .iter()
+                .filter(|voter| !previous.contains(voter))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        for voter in &added {
+            warn!(
+                "AUTHORIZED VOTER ROTATED: {} stopped voting at epoch {} (authorized since epoch {})",
+                voter.authorized_pubkey, voter.target_epoch, voter.epoch_of_last_authorized_switch
+            );
+        }
+        *last = Some(voters);
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::types::AccountData;
+
+    fn rotated_account() -> Vec<PriorVoter> {
+        let data: AccountData =
+            serde_json::from_str(include_str!("../../fixtures/vote_accounts/rotated.json"))
+                .unwrap();
+        let AccountData::Parsed { parsed, .. } = data else {
+            panic!("Expected Parsed data");
+        };
+        parsed.info.prior_voters
+    }
+
+    #[test]
+    fn test_new_entries_are_rotations() {
+        let metrics = Metrics::new().unwrap();
+        let watch = PriorVotersWatch::default();
+        assert!(watch.current().is_empty());
+
+        // Rotations before the tracker started are history, not news
+        let mut voters = rotated_account();
+        assert!(watch.observe(&voters, &metrics).is_empty());
+        assert!(watch.observe(&voters, &metrics).is_empty());
+        assert_eq!(metrics.prior_voter_entries.get(), 3);
+
+        // Failed over to the spare at epoch 910
+        let rotation = PriorVoter {
+            authorized_pubkey: "Primary111".to_string(),
+            epoch_of_last_authorized_switch: 907,
+            target_epoch: 910,
+        };
+        voters.insert(0, rotation.clone());
+        assert_eq!(watch.observe(&voters, &metrics), vec![rotation.clone()]);
+        assert_eq!(metrics.prior_voter_entries.get(), 4);
+
+        // Kept oldest first, whatever the ring order
+        let current = watch.current();
+        assert_eq!(current[0].target_epoch, 851);
+        assert_eq!(current[3], rotation);
+
+        // Seen once
+        assert!(watch.observe(&voters, &metrics).is_empty());
+    }
+}
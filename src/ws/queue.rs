@@ -0,0 +1,155 @@
+use crate::ws::types::NotificationParams;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// Default number of notifications read from the socket and waiting to be processed
+pub const DEFAULT_NOTIFICATION_QUEUE_CAPACITY: usize = 64;
+
+/// Notifications read from the socket and not processed yet, between the
+/// socket reader and the processor. When full, the oldest pending
+/// notification of the same subscription is dropped: an account notification
+/// carries the whole account, so the newer one supersedes it.
+#[derive(Debug)]
+pub struct NotificationQueue {
+    capacity: usize,
+    state: Mutex<QueueState>,
+    ready: Notify,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    pending: VecDeque<NotificationParams>,
+    closed: bool,
+}
+
+impl NotificationQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(QueueState::default()),
+            ready: Notify::new(),
+        }
+    }
+
+    /// Enqueue a notification. Returns true if a pending one was shed to make room.
+    pub fn push(&self, params: NotificationParams) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let shed = state.pending.len() >= self.capacity;
+        if shed {
+            let oldest = state
+                .pending
+                .iter()
+                .position(|pending| pending.subscription == params.subscription)
+                .unwrap_or(0);
+            state.pending.remove(oldest);
+        }
+        state.pending.push_back(params);
+        drop(state);
+        self.ready.notify_one();
+        shed
+    }
+
+    /// The oldest pending notification, waiting for one if there is none.
+    /// None once the queue is closed and drained.
+    pub async fn pop(&self) -> Option<NotificationParams> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(params) = state.pending.pop_front() {
+                    return Some(params);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// The reader is done: `pop` drains what is pending, then returns None
+    pub fn close(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.ready.notify_one();
+    }
+
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pending
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn notification(subscription: u64, slot: u64) -> NotificationParams {
+        serde_json::from_value(serde_json::json!({
+            "result": {
+                "context": {"slot": slot},
+                "value": {
+                    "lamports": 1,
+                    "data": ["", "base64"],
+                    "owner": "Vote111111111111111111111111111111111111111",
+                    "executable": false,
+                    "rentEpoch": 0
+                }
+            },
+            "subscription": subscription
+        }))
+        .unwrap()
+    }
+
+    fn slot(params: &NotificationParams) -> u64 {
+        params.result.context.slot
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_sheds_oldest_of_the_subscription() {
+        let queue = NotificationQueue::new(3);
+        assert!(!queue.push(notification(2, 10)));
+        assert!(!queue.push(notification(1, 11)));
+        assert!(!queue.push(notification(1, 12)));
+        // Drops slot 11, the oldest of subscription 1, not subscription 2's
+        assert!(queue.push(notification(1, 13)));
+        assert_eq!(queue.len(), 3);
+
+        let mut slots = Vec::new();
+        queue.close();
+        while let Some(params) = queue.pop().await {
+            slots.push(slot(&params));
+        }
+        assert_eq!(slots, [10, 12, 13]);
+    }
+
+    #[tokio::test]
+    async fn test_pop_waits_for_push_and_ends_after_close() {
+        let queue = Arc::new(NotificationQueue::new(DEFAULT_NOTIFICATION_QUEUE_CAPACITY));
+        let consumer = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                let mut slots = Vec::new();
+                while let Some(params) = queue.pop().await {
+                    slots.push(slot(&params));
+                }
+                slots
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        queue.push(notification(1, 10));
+        queue.push(notification(1, 11));
+        queue.close();
+        assert_eq!(consumer.await.unwrap(), [10, 11]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::metrics::Metrics;
+use crate::ws::types::{AccountData, VoteStateVersion};
+
+use std::fmt;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Layout of the vote account data: what a state migration changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountShape {
+    /// Length of the data in bytes (unknown for encodings other than
+    /// jsonParsed with `space` and base64)
+    pub data_bytes: Option<u64>,
+    /// `type` of the parsed data (unknown for encoded data)
+    pub account_type: Option<String>,
+    /// Unknown for encoded data and for a pre-V4 account with an empty tower
+    pub version: Option<VoteStateVersion>,
+}
+
+impl AccountShape {
+    pub fn of(data: &AccountData) -> Self {
+        let (account_type, version) = match data {
+            AccountData::Parsed { parsed, .. } => (
+                Some(parsed.account_type.clone()),
+                parsed.info.state_version(),
+            ),
+            AccountData::Raw(_, _) => (None, None),
+        };
+        Self {
+            data_bytes: data.data_len(),
+            account_type,
+            version,
+        }
+    }
+
+    /// This shape with the parts it doesn't know taken from `previous`
+    fn or(self, previous: &AccountShape) -> Self {
+        Self {
+            data_bytes: self.data_bytes.or(previous.data_bytes),
+            account_type: self.account_type.or_else(|| previous.account_type.clone()),
+            version: self.version.or(previous.version),
+        }
+    }
+}
+
+impl fmt::Display for AccountShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.data_bytes {
+            Some(bytes) => write!(f, "{} bytes", bytes)?,
+            None => write!(f, "unknown length")?,
+        }
+        write!(
+            f,
+            ", type {}, state {}",
+            self.account_type.as_deref().unwrap_or("unknown"),
+            self.version.map_or("unknown", VoteStateVersion::label)
+        )
+    }
+}
+
+/// The account shape seen last, kept across reconnects to catch a state
+/// migration mid-run: parsing that silently loses fields after one shows up
+/// as a change here first
+#[derive(Debug, Default)]
+pub struct AccountShapeWatch {
+    last: Mutex<Option<AccountShape>>,
+}
+
+impl AccountShapeWatch {
+    /// Export `shape` as `solana_vote_account_data_bytes` and
+    /// `solana_vote_account_state_info`, and warn if it differs from the last
+    /// one seen. Parts `shape` doesn't know keep their last value. Returns
+    /// whether the shape changed.
+    pub fn observe(&self, shape: AccountShape, metrics: &Metrics) -> bool {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let shape = match last.as_ref() {
+            Some(previous) => shape.or(previous),
+            None => shape,
+        };
+        if last.as_ref() == Some(&shape) {
+            return false;
+        }
+
+        if let Some(bytes) = shape.data_bytes {
+            metrics.vote_account_data_bytes.set(bytes as i64);
+        }
+        metrics.vote_account_state_info.reset();
+        metrics
+            .vote_account_state_info
+            .with_label_values(&[
+                shape.account_type.as_deref().unwrap_or("unknown"),
+                shape.version.map_or("unknown", VoteStateVersion::label),
+            ])
+            .set(1);
+
+        let changed = last.is_some();
+        if let Some(previous) = last.as_ref() {
+            warn!(
+                "VOTE ACCOUNT LAYOUT CHANGED: {} -> {}. The vote state was likely migrated; check that vote latencies and credits are still parsed",
+                previous, shape
+            );
+        }
+        *last = Some(shape);
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    fn parsed(space: u64, extra: &str) -> AccountData {
+        serde_json::from_str(&format!(
+            r#"{{
+                "program": "vote",
+                "parsed": {{
+                    "info": {{
+                        "votes": [{{"confirmationCount": 1, "latency": 1, "slot": 100}}],
+                        "rootSlot": 99,
+                        "epochCredits": []{}
+                    }},
+                    "type": "vote"
+                }},
+                "space": {}
+            }}"#,
+            extra, space
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_shape_changes_are_detected_and_exported() {
+        let metrics = Metrics::new().unwrap();
+        let watch = AccountShapeWatch::default();
+        let state = |account_type: &str, version: &str| {
+            metrics
+                .vote_account_state_info
+                .with_label_values(&[account_type, version])
+                .get()
+        };
+
+        // The first shape seen is not a change
+        assert!(!watch.observe(AccountShape::of(&parsed(3762, "")), &metrics));
+        assert!(!watch.observe(AccountShape::of(&parsed(3762, "")), &metrics));
+        assert_eq!(metrics.vote_account_data_bytes.get(), 3762);
+        assert_eq!(state("vote", "v3"), 1);
+
+        // Encoded data only knows its length, which is unchanged
+        let raw = AccountData::Raw(STANDARD.encode(vec![0; 3762]), "base64".to_string());
+        assert!(!watch.observe(AccountShape::of(&raw), &metrics));
+        assert_eq!(state("vote", "v3"), 1);
+
+        // Migrated to V4 with the same length
+        let v4 = parsed(3762, r#", "inflationRewardsCommissionBps": 500"#);
+        assert!(watch.observe(AccountShape::of(&v4), &metrics));
+        assert_eq!(state("vote", "v4"), 1);
+        assert_eq!(state("vote", "v3"), 0);
+
+        // Resized
+        assert!(watch.observe(
+            AccountShape::of(&parsed(4096, r#", "inflationRewardsCommissionBps": 500"#)),
+            &metrics
+        ));
+        assert_eq!(metrics.vote_account_data_bytes.get(), 4096);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::credits::CreditsModel;
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+/// Rooted ranges waiting for the cluster's block list, beyond which the
+/// oldest are dropped unchecked (about an hour of updates)
+pub const MAX_PENDING_SKIP_RANGES: usize = 10_000;
+
+/// Slots rooted by one tracker update: `(after, through]`, with the credits
+/// missed over the range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootedRange {
+    pub epoch: u64,
+    pub after: u64,
+    pub through: u64,
+    pub missed: u64,
+}
+
+/// Slots rooted without a block (skipped cluster-wide), which no vote can
+/// earn credits on, and the missed credits they account for. Rooted ranges
+/// queue up until the block list covering them was fetched.
+#[derive(Debug, Clone, Default)]
+pub struct SkippedSlots {
+    enabled: bool,
+    pending: VecDeque<RootedRange>,
+    /// (epoch, skipped slots, missed credits on them) of the newest epoch checked
+    epoch_sums: Option<(u64, u64, u64)>,
+}
+
+impl SkippedSlots {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Queue a rooted range for checking
+    pub fn record_rooted(&mut self, range: RootedRange) {
+        if !self.enabled {
+            return;
+        }
+        if self.pending.len() >= MAX_PENDING_SKIP_RANGES {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(range);
+    }
+
+    /// First and last slot of the queued ranges, limited to whole ranges
+    /// within `max_slots` slots (but at least the oldest range) so one block
+    /// list request covers them
+    pub fn pending_span(&self, max_slots: u64) -> Option<(u64, u64)> {
+        let oldest = self.pending.front()?;
+        let first = oldest.after + 1;
+        let last = self
+            .pending
+            .iter()
+            .take_while(|range| range.through - first < max_slots)
+            .last()
+            .unwrap_or(oldest)
+            .through;
+        Some((first, last))
+    }
+
+    /// Settle the queued ranges ending by `last` against `blocks`, the sorted
+    /// slots with a block in `first..=last`. Returns the skipped slots found.
+    pub fn apply_blocks(
+        &mut self,
+        first: u64,
+        last: u64,
+        blocks: &[u64],
+        model: &CreditsModel,
+    ) -> u64 {
+        let produced_through = |slot: u64| blocks.partition_point(|block| *block <= slot) as u64;
+        let mut skipped_total = 0;
+        while let Some(range) = self.pending.front().copied() {
+            if range.through > last {
+                break;
+            }
+            self.pending.pop_front();
+            if range.after + 1 < first {
+                continue;
+            }
+            let produced = produced_through(range.through) - produced_through(range.after);
+            let skipped = (range.through - range.after).saturating_sub(produced);
+            skipped_total += skipped;
+
+            // Only the missed credits of the range can have been lost to skips
+            let skipped_missed = model.expected_credits(skipped).min(range.missed);
+            match &mut self.epoch_sums {
+                Some((epoch, slots, missed)) if *epoch == range.epoch => {
+                    *slots += skipped;
+                    *missed += skipped_missed;
+                }
+                Some((epoch, _, _)) if *epoch > range.epoch => {}
+                _ => self.epoch_sums = Some((range.epoch, skipped, skipped_missed)),
+            }
+        }
+        skipped_total
+    }
+
+    /// Skipped slots found in `epoch` and the missed credits they account for
+    pub fn epoch_totals(&self, epoch: u64) -> (u64, u64) {
+        match self.epoch_sums {
+            Some((sums_epoch, slots, missed)) if sums_epoch == epoch => (slots, missed),
+            _ => (0, 0),
+        }
+    }
+
+    /// Estimated bytes held by the queued ranges
+    pub fn memory_footprint(&self) -> usize {
+        self.pending.len() * size_of::<RootedRange>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(after: u64, through: u64, missed: u64) -> RootedRange {
+        RootedRange {
+            epoch: 1,
+            after,
+            through,
+            missed,
+        }
+    }
+
+    #[test]
+    fn test_pending_span_covers_whole_ranges() {
+        let mut skipped = SkippedSlots::new(true);
+        assert_eq!(skipped.pending_span(100), None);
+
+        skipped.record_rooted(range(100, 110, 0));
+        skipped.record_rooted(range(110, 150, 0));
+        skipped.record_rooted(range(150, 300, 0));
+        assert_eq!(skipped.pending_span(1000), Some((101, 300)));
+        assert_eq!(skipped.pending_span(100), Some((101, 150)));
+        // The oldest range is covered even beyond the limit
+        assert_eq!(skipped.pending_span(5), Some((101, 110)));
+
+        let mut disabled = SkippedSlots::new(false);
+        disabled.record_rooted(range(100, 110, 0));
+        assert_eq!(disabled.pending_span(100), None);
+    }
+
+    #[test]
+    fn test_apply_blocks_attributes_missed_credits_to_skipped_slots() {
+        let model = CreditsModel::default();
+        let mut skipped = SkippedSlots::new(true);
+        // 103 and 104 skipped, the range missed exactly their credits
+        skipped.record_rooted(range(100, 105, 32));
+        // 107 skipped, but only 10 credits were missed
+        skipped.record_rooted(range(105, 110, 10));
+        // Beyond the block list
+        skipped.record_rooted(range(110, 120, 0));
+
+        let blocks = [101, 102, 105, 106, 108, 109, 110];
+        assert_eq!(skipped.apply_blocks(101, 110, &blocks, &model), 3);
+        assert_eq!(skipped.epoch_totals(1), (3, 42));
+        assert_eq!(skipped.epoch_totals(2), (0, 0));
+        assert_eq!(skipped.pending_span(1000), Some((111, 120)));
+
+        // A newer epoch starts the sums over
+        skipped.record_rooted(RootedRange {
+            epoch: 2,
+            ..range(120, 122, 16)
+        });
+        assert_eq!(skipped.apply_blocks(111, 122, &[121], &model), 11);
+        assert_eq!(skipped.epoch_totals(1), (0, 0));
+        assert_eq!(skipped.epoch_totals(2), (1, 16));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::credits::CreditsModel;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Rooted slots kept for `GET /debug/slots`
+pub const RECENT_SLOTS_CAPACITY: usize = 512;
+
+/// What a rooted slot earned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SlotOutcome {
+    /// Voted with the lowest latency, earning the maximum
+    Earned { credits: u64 },
+    /// Voted late, earning less than the maximum (possibly nothing)
+    Late { credits: u64 },
+    /// No vote on the slot was rooted (also a slot skipped cluster-wide)
+    Missed,
+    /// The votes rooted with the range don't add up to the credits the
+    /// account gained over it, so the range's misses can't be placed
+    Partial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SlotRecord {
+    pub slot: u64,
+    #[serde(flatten)]
+    pub outcome: SlotOutcome,
+}
+
+/// Ring buffer of the outcomes of the most recently rooted slots
+#[derive(Debug, Clone, Default)]
+pub struct RecentSlots {
+    slots: VecDeque<SlotRecord>,
+}
+
+impl RecentSlots {
+    /// Record the slots rooted by one update, `(after, through]`.
+    ///
+    /// `rooted_votes` maps the slots of the votes rooted with the range to
+    /// their credits. When those add up to `credits`, the credits the account
+    /// gained over the range, every slot is attributed exactly: voted slots
+    /// earned their vote's credits and the others were missed. Otherwise (a
+    /// vote not seen in any notification, a latency inferred from the context
+    /// slot, credits from a vote outside the range) the whole range is
+    /// recorded as [`SlotOutcome::Partial`].
+    pub fn record_rooted(
+        &mut self,
+        after: u64,
+        through: u64,
+        rooted_votes: &BTreeMap<u64, u64>,
+        credits: u64,
+        model: &CreditsModel,
+    ) {
+        let exact = rooted_votes.values().sum::<u64>() == credits;
+        // Only the newest slots of a long range fit
+        let first = (after + 1).max(through.saturating_sub(RECENT_SLOTS_CAPACITY as u64 - 1));
+        for slot in first..=through {
+            let outcome = match rooted_votes.get(&slot) {
+                _ if !exact => SlotOutcome::Partial,
+                Some(&credits) if credits >= model.max_credits_per_slot => {
+                    SlotOutcome::Earned { credits }
+                }
+                Some(&credits) => SlotOutcome::Late { credits },
+                None => SlotOutcome::Missed,
+            };
+            if self.slots.len() == RECENT_SLOTS_CAPACITY {
+                self.slots.pop_front();
+            }
+            self.slots.push_back(SlotRecord { slot, outcome });
+        }
+    }
+
+    /// Up to `limit` records, newest first
+    pub fn newest(&self, limit: usize) -> Vec<SlotRecord> {
+        self.slots.iter().rev().take(limit).copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn votes(entries: &[(u64, u64)]) -> BTreeMap<u64, u64> {
+        entries.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_exact_attribution() {
+        let mut recent = RecentSlots::default();
+        let model = CreditsModel::default();
+        // Slot 102 has no vote: 4 slots, 16 + 13 + 16 = 45 of 64 credits
+        recent.record_rooted(
+            100,
+            104,
+            &votes(&[(101, 16), (103, 13), (104, 16)]),
+            45,
+            &model,
+        );
+        assert_eq!(
+            recent.newest(10),
+            vec![
+                SlotRecord {
+                    slot: 104,
+                    outcome: SlotOutcome::Earned { credits: 16 }
+                },
+                SlotRecord {
+                    slot: 103,
+                    outcome: SlotOutcome::Late { credits: 13 }
+                },
+                SlotRecord {
+                    slot: 102,
+                    outcome: SlotOutcome::Missed
+                },
+                SlotRecord {
+                    slot: 101,
+                    outcome: SlotOutcome::Earned { credits: 16 }
+                },
+            ]
+        );
+        assert_eq!(recent.newest(1)[0].slot, 104);
+        assert_eq!(
+            serde_json::to_value(recent.newest(2)).unwrap(),
+            serde_json::json!([
+                {"slot": 104, "outcome": "earned", "credits": 16},
+                {"slot": 103, "outcome": "late", "credits": 13},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unexplained_credits_mark_the_range_partial() {
+        let mut recent = RecentSlots::default();
+        let model = CreditsModel::default();
+        // The account gained 30 credits, the rooted votes account for 32
+        recent.record_rooted(100, 103, &votes(&[(101, 16), (102, 16)]), 30, &model);
+        assert_eq!(recent.len(), 3);
+        assert!(
+            recent
+                .newest(10)
+                .iter()
+                .all(|record| record.outcome == SlotOutcome::Partial)
+        );
+    }
+
+    #[test]
+    fn test_ring_is_bounded() {
+        let mut recent = RecentSlots::default();
+        let model = CreditsModel::default();
+        for after in (0..1000).step_by(4) {
+            recent.record_rooted(after, after + 4, &BTreeMap::new(), 0, &model);
+        }
+        assert_eq!(recent.len(), RECENT_SLOTS_CAPACITY);
+        assert_eq!(recent.newest(1)[0].slot, 1000);
+        assert_eq!(
+            recent.newest(usize::MAX).last().unwrap().slot,
+            1000 - RECENT_SLOTS_CAPACITY as u64 + 1
+        );
+
+        // A range longer than the ring keeps its newest slots
+        recent.record_rooted(1000, 5000, &BTreeMap::new(), 0, &model);
+        assert_eq!(recent.len(), RECENT_SLOTS_CAPACITY);
+        assert_eq!(
+            recent.newest(usize::MAX).last().unwrap().slot,
+            5000 - RECENT_SLOTS_CAPACITY as u64 + 1
+        );
+    }
+}
@@ -1,18 +1,480 @@
-use std::collections::{HashSet, VecDeque};
-use std::time::Instant;
+use crate::clock::{SharedClock, system_clock};
+use crate::cluster::{ClusterAverage, ClusterAverages};
+use crate::credits::CreditsModel;
+use crate::maintenance::Maintenance;
+use crate::ws::comparison::{EpochComparison, EpochResult};
+use crate::ws::fork::{ForkRegime, ForkRegimeTotals, RegimeCredits};
+use crate::ws::hourly::HourlyProfile;
+use crate::ws::leader::LeaderSlots;
+use crate::ws::skipped::{RootedRange, SkippedSlots};
+use crate::ws::slots::RecentSlots;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::mem::size_of;
+use std::sync::Arc;
 
 /// Slots per epoch on mainnet (constant, never changes)
 pub const SLOTS_PER_EPOCH: u64 = 432_000;
 
-/// Maximum credits per slot (TVC: 16 for fastest vote, 0 for slowest)
-pub const MAX_CREDITS_PER_SLOT: u64 = 16;
+/// Default smoothing factor per rooted slot for the smoothed projection
+/// (time constant of ~5000 slots, roughly half an hour)
+pub const DEFAULT_PROJECTION_ALPHA: f64 = 0.0002;
 
-/// Histogram entry: (timestamp, credits_bucket_counts, missed_credits_cumulative)
+/// Minimum jump (in slots) between the previous highest vote and the first new vote
+/// for a collapsed tower to count as a restart. Fork switches move a few slots.
+pub const RESTART_MIN_GAP_SLOTS: u64 = 150;
+
+/// Votes in the rolling window used to classify the voting regime
+pub const REGIME_WINDOW_VOTES: usize = 32;
+
+/// Median latency (slots) above which voting is considered catching up
+pub const CATCH_UP_ENTER_LATENCY: u64 = 4;
+
+/// Median latency (slots) at or below which catch-up is considered over.
+/// Lower than the entry threshold so a median hovering in between doesn't flap.
+pub const CATCH_UP_EXIT_LATENCY: u64 = 2;
+
+/// Default consecutive updates whose new votes root without earning credits
+/// before votes are reported as landing without credits
+pub const DEFAULT_CREDIT_STALL_UPDATES: u32 = 5;
+
+/// Latencies of recent new votes kept for the votes-without-credits report
+const CREDIT_STALL_LATENCIES: usize = 32;
+
+/// Slots behind the tip after which a vote that dropped out of the tower
+/// without being rooted (e.g. it was on an abandoned fork) is discarded
+pub const NEVER_ROOTED_BOUND_SLOTS: u64 = 512;
+
+/// How long the rolling history is kept (longest window)
+const HISTORY_RETENTION_MS: u64 = 3600 * 1000;
+
+/// Notifications arrive every slot or two; a window baseline older than the
+/// window start by more than this lies before a gap in the updates
+pub const WINDOW_BASE_MAX_LAG_MS: u64 = 10_000;
+
+/// Votes not rooted this long after they were first seen are dropped from the
+/// root delay tracking (a stalled root would otherwise report hour-long delays)
+pub const ROOT_DELAY_MAX_AGE_MS: u64 = 5 * 60 * 1000;
+
+/// Most votes whose first-seen time is kept for the root delay
+pub const ROOT_DELAY_MAX_SLOTS: usize = 1024;
+
+/// Default slots behind the tip at which the tip-anchored expectation is taken
+pub const DEFAULT_TIP_ALLOWANCE_SLOTS: u64 = 2;
+
+/// Slot the expected max credits (and so missed credits) are anchored to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedAnchor {
+    /// Rooted slots: every expected credit is already decided
+    #[default]
+    Root,
+    /// The tip minus an allowance: no lag behind the chain, but misses are
+    /// provisional until the slots root
+    Tip,
+}
+
+/// Histogram entry: (unix millis, credits_bucket_counts, missed_credits_cumulative)
 /// credits_bucket_counts\[i\] = count of votes that earned i credits (0..=16)
-type HistEntry = (Instant, [u64; 17], u64);
+pub type HistEntry = (u64, [u64; 17], u64);
+
+/// Value of a cumulative counter at `at`, interpolated between `base` at
+/// `base_t` and `next` at `next_t` (`base_t < at <= next_t`). A window whose
+/// baseline lies before an update gap then only counts the part of the
+/// counters' growth across the gap that falls inside it.
+pub fn interpolate_counter(base: (u64, u64), next: (u64, u64), at: u64) -> u64 {
+    let ((base_t, base), (next_t, next)) = (base, next);
+    let span = next_t.saturating_sub(base_t);
+    if span == 0 {
+        return next;
+    }
+    let elapsed = at.clamp(base_t, next_t) - base_t;
+    let growth = next.saturating_sub(base) as u128 * elapsed as u128 / span as u128;
+    base + growth as u64
+}
 
-/// Calculate epoch info from a slot number
+/// Rolling history behind the windowed histograms, serializable so it can
+/// survive a restart
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackerHistory {
+    pub entries: VecDeque<HistEntry>,
+    pub cumulative_histogram: [u64; 17],
+    pub cumulative_missed: u64,
+    /// Cumulative rooted slots at each entry (missing in older state files)
+    #[serde(default)]
+    pub rooted_slots: VecDeque<u64>,
+    #[serde(default)]
+    pub cumulative_rooted_slots: u64,
+    /// Cumulative credits gained by the account at each entry (missing in
+    /// older state files)
+    #[serde(default)]
+    pub credits: VecDeque<u64>,
+    #[serde(default)]
+    pub cumulative_credits: u64,
+}
+
+/// Counters at the start of a window, see [`VoteTracker::window_base`]
+struct WindowBase {
+    histogram: [u64; 17],
+    /// Credits of `histogram`, interpolated as a total like `credits` so the
+    /// two agree at an interpolated window start
+    histogram_credits: u64,
+    missed: u64,
+    rooted_slots: u64,
+    /// Maximum credits of `rooted_slots`, interpolated in credits
+    expected: u64,
+    credits: u64,
+    /// History entries after the baseline, i.e. updates within the window
+    updates: u64,
+}
+
+/// A window's efficiency computed two ways over the same window start: from
+/// the histogram of rooted votes plus the missed credits (the exported
+/// efficiency), and from the account's credit counter over the rooted slots
+/// (what `rate()` over the counters computes in PromQL)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EfficiencyCrossCheck {
+    /// Credits of the votes rooted in the window
+    pub histogram_credits: u64,
+    pub missed: u64,
+    /// Credits the account gained in the window
+    pub counter_credits: u64,
+    pub rooted_slots: u64,
+    /// Maximum credits over the rooted slots
+    pub counter_expected: u64,
+}
+
+impl EfficiencyCrossCheck {
+    pub fn histogram_efficiency(&self) -> Option<f64> {
+        let expected = self.histogram_credits + self.missed;
+        (expected > 0).then(|| self.histogram_credits as f64 / expected as f64)
+    }
+
+    pub fn counter_efficiency(&self) -> Option<f64> {
+        (self.counter_expected > 0)
+            .then(|| self.counter_credits as f64 / self.counter_expected as f64)
+    }
+
+    /// Absolute difference of the two efficiencies (None if either is undefined)
+    pub fn skew(&self) -> Option<f64> {
+        Some((self.histogram_efficiency()? - self.counter_efficiency()?).abs())
+    }
+}
+
+/// The epoch's missed credits counted two ways: from a snapshot of the
+/// account (rooted expectation minus credits earned, what the poller
+/// computes) and by accumulating the misses of each update since the
+/// epoch's first tracked root, on top of what the snapshot there showed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissedAccounting {
+    pub snapshot: u64,
+    pub incremental: u64,
+}
+
+impl MissedAccounting {
+    /// Absolute difference of the two counts
+    pub fn skew(&self) -> u64 {
+        self.snapshot.abs_diff(self.incremental)
+    }
+
+    /// Skew over the larger count (0 when neither missed anything)
+    pub fn skew_ratio(&self) -> f64 {
+        match self.snapshot.max(self.incremental) {
+            0 => 0.0,
+            larger => self.skew() as f64 / larger as f64,
+        }
+    }
+}
+
+/// Lowest windowed efficiency observed, with the unix timestamp it occurred at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WorstWindow {
+    pub efficiency: f64,
+    pub timestamp: u64,
+}
+
+impl WorstWindow {
+    /// Return whichever of `current` and the existing low is lower
+    fn lower(existing: Option<WorstWindow>, current: WorstWindow) -> WorstWindow {
+        match existing {
+            Some(worst) if worst.efficiency <= current.efficiency => worst,
+            _ => current,
+        }
+    }
+}
+
+/// Consecutive rooted slots that earned the full credits, and the longest
+/// such run of the epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerfectSlotStreak {
+    /// Epoch the streaks belong to
+    pub epoch: u64,
+    pub current: u64,
+    pub epoch_max: u64,
+}
+
+impl PerfectSlotStreak {
+    /// Extend the current streak by `slots` perfect rooted slots
+    fn extend(&mut self, slots: u64) {
+        self.current += slots;
+        self.epoch_max = self.epoch_max.max(self.current);
+    }
+}
+
+/// Point-in-time copy of the tracker state, for reporting outside the lock
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TrackerSnapshot {
+    pub epoch_info: Option<EpochInfo>,
+    /// First epoch seen since the tracker started
+    pub first_epoch: Option<u64>,
+    pub current_epoch_credits: u64,
+    pub epoch_missed: u64,
+    /// Credits earned across all epochs while tracked (from epoch_credits deltas)
+    pub tracked_credits: u64,
+    /// Credits missed across all epochs while tracked
+    pub tracked_missed: u64,
+    /// Worst 5-minute efficiency observed since the tracker started
+    pub worst_window_5m: Option<WorstWindow>,
+    /// Worst 5-minute efficiency observed this epoch
+    pub epoch_worst_window_5m: Option<WorstWindow>,
+    /// First and last root slot tracked this epoch
+    pub epoch_first_tracked_slot: Option<u64>,
+    pub epoch_last_tracked_slot: Option<u64>,
+}
+
+/// Epoch-level baseline of the tracker, reported by reset/reconcile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TrackerBaseline {
+    pub epoch: Option<u64>,
+    pub root_slot: Option<u64>,
+    pub epoch_credits: u64,
+    pub epoch_missed: u64,
+    pub tracked_credits: u64,
+    pub tracked_missed: u64,
+    /// First root slot tracked in `epoch` (missing in older state files)
+    #[serde(default)]
+    pub epoch_first_tracked_slot: Option<u64>,
+    /// Credits `epoch` missed before its first tracked slot, not in
+    /// `epoch_missed` (missing in older state files)
+    #[serde(default)]
+    pub epoch_missed_untracked: u64,
+}
+
+/// Whether votes are landing normally or the validator is catching up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Regime {
+    #[default]
+    Steady,
+    CatchUp,
+}
+
+/// Hysteretic classifier over the median latency of the last
+/// [`REGIME_WINDOW_VOTES`] votes
+#[derive(Debug, Clone, Default)]
+pub struct RegimeClassifier {
+    recent_latencies: VecDeque<u64>,
+    regime: Regime,
+}
+
+impl RegimeClassifier {
+    /// Estimated bytes held by the latency window
+    pub fn memory_footprint(&self) -> usize {
+        self.recent_latencies.len() * size_of::<u64>()
+    }
+
+    /// Add a vote's latency and return the regime it belongs to
+    pub fn observe(&mut self, latency: u64) -> Regime {
+        self.recent_latencies.push_back(latency);
+        if self.recent_latencies.len() > REGIME_WINDOW_VOTES {
+            self.recent_latencies.pop_front();
+        }
+
+        // Only switch on a full window, so a few slow votes at startup don't count
+        if self.recent_latencies.len() == REGIME_WINDOW_VOTES {
+            let median = self.median_latency();
+            self.regime = match self.regime {
+                Regime::Steady if median > CATCH_UP_ENTER_LATENCY => Regime::CatchUp,
+                Regime::CatchUp if median <= CATCH_UP_EXIT_LATENCY => Regime::Steady,
+                regime => regime,
+            };
+        }
+        self.regime
+    }
+
+    pub fn regime(&self) -> Regime {
+        self.regime
+    }
+
+    fn median_latency(&self) -> u64 {
+        let mut sorted: Vec<u64> = self.recent_latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    }
+}
+
+/// A vote pattern that looks like a validator restart: voting stopped (or the
+/// tower was rebuilt from an older slot) and resumed with a collapsed tower
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuspectedRestart {
+    /// Highest vote slot before the gap
+    pub last_vote_slot: u64,
+    /// Lowest new vote slot after the gap
+    pub resumed_vote_slot: u64,
+    pub tower_height_before: usize,
+    pub tower_height_after: usize,
+}
+
+impl SuspectedRestart {
+    /// Slots between the last vote before and the first vote after the restart
+    pub fn gap_slots(&self) -> u64 {
+        self.resumed_vote_slot.abs_diff(self.last_vote_slot)
+    }
+}
+
+/// Votes landing while the epoch credits stand still, started or ended by an update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreditStallChange {
+    /// Consecutive updates rooted slots with new votes in the tower and no
+    /// credits; the latencies of their new votes, oldest first
+    Started { updates: u32, latencies: Vec<u64> },
+    /// Credits resumed
+    Ended,
+}
+
+/// Consecutive updates whose new votes earned nothing when the root advanced
+#[derive(Debug, Clone, Default)]
+struct CreditStall {
+    updates: u32,
+    latencies: VecDeque<u64>,
+    active: bool,
+}
+
+/// Summary of a finished epoch, produced at epoch rollover
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochSummary {
+    pub epoch: u64,
+    /// Credits earned in the epoch (last value seen from the vote account)
+    pub credits: u64,
+    /// Credits missed while tracked during the epoch
+    pub missed: u64,
+    /// Worst 5-minute efficiency observed during the epoch
+    pub worst_window_5m: Option<WorstWindow>,
+    /// Perfect slot streaks at the end of the epoch (WebSocket mode only)
+    pub perfect_slot_streak: Option<PerfectSlotStreak>,
+    /// Change in activated stake at the boundary (poll mode only, the vote account
+    /// data seen over WebSocket carries no stake)
+    pub stake_delta_lamports: Option<i64>,
+    /// Slots rooted while the WebSocket was reconnecting (WebSocket mode only)
+    pub unobserved: Option<UnobservedSlots>,
+    /// First and last root slot tracked in the epoch, the range the numbers cover
+    pub first_tracked_slot: Option<u64>,
+    pub last_tracked_slot: Option<u64>,
+    /// Average latency of the votes rooted in the epoch (WebSocket mode only)
+    pub avg_latency: Option<f64>,
+    /// Change from the previous finished epoch (None for the first one seen)
+    pub comparison: Option<EpochComparison>,
+    /// Credits against the cluster's stake-weighted mean, as of the last
+    /// cluster scan in the epoch (None without one)
+    pub cluster: Option<ClusterAverage>,
+}
+
+impl EpochSummary {
+    /// Take the cluster average of the epoch from `averages`
+    pub fn set_cluster_average(&mut self, averages: Option<&ClusterAverages>) {
+        self.cluster = averages.and_then(|averages| averages.for_epoch(self.epoch));
+    }
+
+    pub fn result(&self) -> EpochResult {
+        EpochResult {
+            epoch: self.epoch,
+            credits: self.credits,
+            missed: self.missed,
+            avg_latency: self.avg_latency,
+        }
+    }
+
+    /// Compare against the previous epoch's result, then make this one the previous
+    pub fn compare_with_previous(&mut self, previous: &mut Option<EpochResult>) {
+        let current = self.result();
+        self.comparison = previous
+            .as_ref()
+            .and_then(|previous| EpochComparison::between(previous, &current));
+        *previous = Some(current);
+    }
+}
+
+/// Slots rooted between the root seen before a WebSocket disconnect and the
+/// first one after resubscribing. Their votes were never seen, so the
+/// histograms and latencies don't cover them; the credits they allowed bound
+/// what the per-vote metrics can't account for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UnobservedSlots {
+    pub rooted_slots: u64,
+    /// Max credits the slots allowed
+    pub expected_credits: u64,
+}
+
+impl UnobservedSlots {
+    fn add(&mut self, other: UnobservedSlots) {
+        self.rooted_slots += other.rooted_slots;
+        self.expected_credits += other.expected_credits;
+    }
+}
+
+/// Slots rooted while the exporter was down, between the root in the state
+/// file and the vote account's root at startup. Unlike [`UnobservedSlots`]
+/// the credits are known (from the account), only their latencies aren't.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OfflineGap {
+    pub rooted_slots: u64,
+    /// Credits earned over the slots
+    pub credits: u64,
+    /// Max credits the slots allowed
+    pub expected_credits: u64,
+}
+
+impl OfflineGap {
+    fn new(rooted_slots: u64, credits: u64, credits_model: &CreditsModel) -> Self {
+        Self {
+            rooted_slots,
+            credits,
+            expected_credits: credits_model.expected_credits(rooted_slots),
+        }
+    }
+
+    pub fn missed(&self) -> u64 {
+        self.expected_credits.saturating_sub(self.credits)
+    }
+
+    fn add(&mut self, other: OfflineGap) {
+        self.rooted_slots += other.rooted_slots;
+        self.credits += other.credits;
+        self.expected_credits += other.expected_credits;
+    }
+}
+
+impl TrackerSnapshot {
+    /// Credits that could have been earned while tracked (earned + missed)
+    pub fn tracked_expected(&self) -> u64 {
+        self.tracked_credits + self.tracked_missed
+    }
+
+    /// Efficiency over the whole tracked period (None until something was expected)
+    pub fn tracked_efficiency(&self) -> Option<f64> {
+        let expected = self.tracked_expected();
+        if expected == 0 {
+            return None;
+        }
+        Some(self.tracked_credits as f64 / expected as f64)
+    }
+}
+
+/// Calculate epoch info from a slot number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct EpochInfo {
     pub epoch: u64,
     pub slot_index: u64,
@@ -46,34 +508,102 @@ impl EpochInfo {
     }
 
     /// Calculate expected max credits based on rooted slots
-    pub fn expected_max_credits(&self, root_slot: u64) -> u64 {
-        self.rooted_slots_elapsed(root_slot) * MAX_CREDITS_PER_SLOT
+    pub fn expected_max_credits(&self, root_slot: u64, model: &CreditsModel) -> u64 {
+        model.expected_credits(self.rooted_slots_elapsed(root_slot))
+    }
+}
+
+/// Expectation anchored at the tip. Slots between the root and the anchor are
+/// pending: their credits are not decided yet, so misses against this
+/// expectation are provisional and shrink as the root catches up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TipExpectation {
+    /// Tip minus the allowance, clamped to [root, epoch end]
+    pub anchor_slot: u64,
+    pub expected_max: u64,
+    /// Expected max minus credits earned
+    pub provisional_missed: u64,
+    /// Slots after the root up to the anchor
+    pub pending_slots: u64,
+}
+
+impl TipExpectation {
+    pub fn new(
+        info: &EpochInfo,
+        root_slot: u64,
+        tip_slot: u64,
+        allowance_slots: u64,
+        credits: u64,
+        model: &CreditsModel,
+    ) -> Self {
+        let epoch_end = info.epoch_start_slot + info.slots_in_epoch - 1;
+        let anchor_slot = tip_slot
+            .saturating_sub(allowance_slots)
+            .max(root_slot)
+            .min(epoch_end);
+        let expected_max = info.expected_max_credits(anchor_slot, model);
+        Self {
+            anchor_slot,
+            expected_max,
+            provisional_missed: expected_max.saturating_sub(credits),
+            pending_slots: info
+                .rooted_slots_elapsed(anchor_slot)
+                .saturating_sub(info.rooted_slots_elapsed(root_slot)),
+        }
     }
 }
 
+/// A vote seen in the tower whose slot the root hasn't passed yet
+#[derive(Debug, Clone, Copy)]
+struct PendingVote {
+    credits: u64,
+    catch_up: bool,
+    /// Whether the vote was in the most recent tower
+    in_tower: bool,
+}
+
 /// Tracks per-vote TVC credits and builds histograms
 /// Uses epoch_credits as source of truth for missed credits accounting
 /// All epoch info is derived from slot numbers (no HTTP required)
 #[derive(Debug)]
 pub struct VoteTracker {
-    /// Previously seen vote slots (to detect new votes)
-    prev_votes: HashSet<u64>,
+    /// Votes not rooted yet, by slot. They only reach the histograms once the
+    /// root passes them, so votes on a fork that gets abandoned are never counted.
+    pending_votes: BTreeMap<u64, PendingVote>,
+    /// When each recent vote slot was first seen (unix millis), for the root delay
+    vote_first_seen: BTreeMap<u64, u64>,
     /// Previous root slot (for missed slot detection)
     prev_root_slot: Option<u64>,
     /// Previous epoch credits value (for delta calculation)
     prev_epoch_credits: Option<u64>,
     /// Histogram of credits earned this epoch: counts\[i\] = votes earning i credits
     epoch_histogram: [u64; 17],
-    /// Current epoch info (derived from slot)
+    /// Current epoch info (epoch from epochCredits, slot index from the root)
     epoch_info: Option<EpochInfo>,
+    /// Whether the root slot was in another epoch than epochCredits' newest
+    /// entry at the last update
+    epoch_source_disagreement: bool,
     /// Rolling history for time-windowed histograms
     hist: VecDeque<HistEntry>,
     /// Cumulative histogram (for computing deltas)
     cumulative_histogram: [u64; 17],
     /// Cumulative missed credits (for window calculations)
     cumulative_missed: u64,
+    /// Cumulative rooted slots at each history entry (index-aligned with `hist`)
+    hist_rooted_slots: VecDeque<u64>,
+    /// Cumulative rooted slots seen by the updates
+    cumulative_rooted_slots: u64,
+    /// Cumulative credits gained at each history entry (index-aligned with `hist`)
+    hist_credits: VecDeque<u64>,
     /// Missed credits this epoch
     epoch_missed: u64,
+    /// Missed credits this epoch during maintenance (not in `epoch_missed`)
+    epoch_missed_maintenance: u64,
+    /// Missed credits this epoch before its first tracked root, from the
+    /// account at that root (not in `epoch_missed`)
+    epoch_missed_untracked: u64,
+    /// Planned maintenance, whose missed credits stay out of the totals
+    maintenance: Option<Arc<Maintenance>>,
     /// Actual credits earned since tracker started this epoch (from deltas)
     epoch_actual_credits: u64,
     /// First root_slot seen this epoch (for expected calculation)
@@ -81,200 +611,1223 @@ pub struct VoteTracker {
     /// Current epoch credits from vote account (credits - previous_credits)
     /// This is what `solana vote-account` shows for the current epoch
     current_epoch_credits: u64,
+    /// First epoch seen since the tracker started
+    first_epoch: Option<u64>,
+    /// Cumulative actual credits across epochs (for run-level reporting)
+    cumulative_credits: u64,
+    /// Worst 5-minute efficiency observed since the tracker started
+    worst_window_5m: Option<WorstWindow>,
+    /// Worst 5-minute efficiency observed this epoch (reset at epoch rollover)
+    epoch_worst_window_5m: Option<WorstWindow>,
+    /// Fork pressure the next update is accounted under, set by the WS client
+    fork_regime: ForkRegime,
+    /// Credits and missed credits of the updates accounted while turbulent
+    fork_regimes: ForkRegimeTotals,
+    /// Runs of rooted slots without missed credits (reset at epoch rollover)
+    perfect_streak: PerfectSlotStreak,
+    /// Result of the last finished epoch, compared against at the next rollover
+    previous_epoch: Option<EpochResult>,
+    /// Smoothing factor per rooted slot for the smoothed credits rate
+    projection_alpha: f64,
+    /// Exponential moving average of the credits per rooted slot
+    smoothed_rate: Option<f64>,
+    /// Credits awarded per rooted slot
+    credits_model: CreditsModel,
+    /// Highest vote slot in the previous non-empty tower (for restart detection)
+    prev_max_vote_slot: Option<u64>,
+    /// Number of votes in the previous non-empty tower
+    prev_tower_height: usize,
+    /// Steady vs catch-up classification of new votes
+    regime: RegimeClassifier,
+    /// Updates with new votes and a rooted range that earned no credits
+    credit_stall: CreditStall,
+    /// Such updates in a row before the votes are reported as earning nothing
+    credit_stall_updates: u32,
+    /// Keep catch-up votes out of the windowed (5m/1h) histograms
+    exclude_catch_up: bool,
+    /// Wall clock for history timestamps
+    clock: SharedClock,
+    /// Latest context slot (the tip as seen by the subscription)
+    tip_slot: Option<u64>,
+    /// Anchor of the primary expected max / missed metrics
+    expected_anchor: ExpectedAnchor,
+    /// Slots behind the tip for the tip-anchored expectation
+    tip_allowance_slots: u64,
+    /// Credits earned vs expected by UTC hour of day (kept across resets)
+    hourly_profile: HourlyProfile,
+    /// Own leader slots and their credits, for the leader-adjusted efficiency
+    leader_slots: LeaderSlots,
+    /// Rooted slots without a block, for the skip-adjusted missed credits
+    skipped_slots: SkippedSlots,
+    /// Outcome of the most recently rooted slots, for `GET /debug/slots`
+    recent_slots: RecentSlots,
+    /// Last root seen before the WebSocket disconnected, until the next root
+    disconnect_root: Option<u64>,
+    /// Slots rooted while reconnecting, since the tracker started
+    unobserved: UnobservedSlots,
+    /// Slots rooted while reconnecting, this epoch
+    epoch_unobserved: UnobservedSlots,
+    /// Slots rooted while the exporter was down, folded in at startup
+    offline: OfflineGap,
+    /// Cap on [`VoteTracker::memory_footprint`], enforced by dropping the oldest history
+    max_state_bytes: Option<usize>,
+    /// Whether history was dropped, to stay under the cap or as it aged out;
+    /// windows reaching past the oldest entry are then measured from it
+    /// instead of from zero
+    history_evicted: bool,
 }
 
 impl VoteTracker {
-    pub fn new() -> Self {
+    pub fn new(credits_model: CreditsModel) -> Self {
         Self {
-            prev_votes: HashSet::new(),
+            pending_votes: BTreeMap::new(),
+            vote_first_seen: BTreeMap::new(),
             prev_root_slot: None,
             prev_epoch_credits: None,
             epoch_histogram: [0; 17],
             epoch_info: None,
+            epoch_source_disagreement: false,
             hist: VecDeque::new(),
             cumulative_histogram: [0; 17],
             cumulative_missed: 0,
+            hist_rooted_slots: VecDeque::new(),
+            cumulative_rooted_slots: 0,
+            hist_credits: VecDeque::new(),
             epoch_missed: 0,
+            epoch_missed_maintenance: 0,
+            epoch_missed_untracked: 0,
+            maintenance: None,
             epoch_actual_credits: 0,
             epoch_first_root_slot: None,
             current_epoch_credits: 0,
+            first_epoch: None,
+            cumulative_credits: 0,
+            worst_window_5m: None,
+            epoch_worst_window_5m: None,
+            fork_regime: ForkRegime::Calm,
+            fork_regimes: ForkRegimeTotals::default(),
+            perfect_streak: PerfectSlotStreak::default(),
+            previous_epoch: None,
+            projection_alpha: DEFAULT_PROJECTION_ALPHA,
+            smoothed_rate: None,
+            credits_model,
+            prev_max_vote_slot: None,
+            prev_tower_height: 0,
+            regime: RegimeClassifier::default(),
+            credit_stall: CreditStall::default(),
+            credit_stall_updates: DEFAULT_CREDIT_STALL_UPDATES,
+            exclude_catch_up: false,
+            clock: system_clock(),
+            tip_slot: None,
+            expected_anchor: ExpectedAnchor::Root,
+            tip_allowance_slots: DEFAULT_TIP_ALLOWANCE_SLOTS,
+            hourly_profile: HourlyProfile::default(),
+            leader_slots: LeaderSlots::default(),
+            skipped_slots: SkippedSlots::default(),
+            recent_slots: RecentSlots::default(),
+            disconnect_root: None,
+            unobserved: UnobservedSlots::default(),
+            epoch_unobserved: UnobservedSlots::default(),
+            offline: OfflineGap::default(),
+            max_state_bytes: None,
+            history_evicted: false,
         }
     }
 
-    /// Get current epoch credits from vote account (credits - previous_credits)
-    /// This matches what `solana vote-account` shows for the current epoch
-    pub fn current_epoch_credits(&self) -> u64 {
-        self.current_epoch_credits
+    /// Anchor the primary expected max / missed metrics at the root or the tip
+    pub fn with_expected_anchor(
+        mut self,
+        anchor: ExpectedAnchor,
+        tip_allowance_slots: u64,
+    ) -> Self {
+        self.expected_anchor = anchor;
+        self.tip_allowance_slots = tip_allowance_slots;
+        self
     }
 
-    /// Get current epoch info (if available)
-    pub fn epoch_info(&self) -> Option<EpochInfo> {
-        self.epoch_info
+    pub fn expected_anchor(&self) -> ExpectedAnchor {
+        self.expected_anchor
     }
 
-    /// Get expected max credits for the current epoch
-    pub fn epoch_expected_max(&self) -> u64 {
-        match (
-            self.epoch_info,
-            self.prev_root_slot,
-            self.epoch_first_root_slot,
-        ) {
-            (Some(_info), Some(root), Some(first_root)) => {
-                // Calculate slots elapsed since we started tracking this epoch
-                let slots_tracked = root.saturating_sub(first_root).saturating_add(1);
-                slots_tracked * MAX_CREDITS_PER_SLOT
-            }
-            _ => 0,
+    /// Expectation anchored at the latest context slot (None before the first update with a root)
+    pub fn tip_expectation(&self) -> Option<TipExpectation> {
+        Some(TipExpectation::new(
+            &self.epoch_info?,
+            self.prev_root_slot?,
+            self.tip_slot?,
+            self.tip_allowance_slots,
+            self.current_epoch_credits,
+            &self.credits_model,
+        ))
+    }
+
+    /// Use `clock` for history timestamps instead of the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Cap the estimated state size, dropping the oldest window history beyond it
+    pub fn with_max_state_bytes(mut self, max_state_bytes: Option<usize>) -> Self {
+        self.max_state_bytes = max_state_bytes;
+        self
+    }
+
+    /// Count missed credits during `maintenance` separately from the totals
+    pub fn with_maintenance(mut self, maintenance: Option<Arc<Maintenance>>) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    /// Queue rooted ranges for the skipped slot check (--track-skipped-slots)
+    pub fn with_skipped_slot_tracking(mut self, enabled: bool) -> Self {
+        self.skipped_slots = SkippedSlots::new(enabled);
+        self
+    }
+
+    /// Estimated bytes held by the growing structures: the window history,
+    /// the pending votes, the regime window, the leader slots and history and
+    /// the rooted ranges awaiting the skipped slot check
+    /// (entries × entry size, not counting allocator slack)
+    pub fn memory_footprint(&self) -> usize {
+        self.hist.len() * (size_of::<HistEntry>() + 2 * size_of::<u64>())
+            + self.pending_votes.len() * (size_of::<u64>() + size_of::<PendingVote>())
+            + self.vote_first_seen.len() * 2 * size_of::<u64>()
+            + self.regime.memory_footprint()
+            + self.leader_slots.memory_footprint()
+            + self.fork_regimes.memory_footprint()
+            + self.skipped_slots.memory_footprint()
+    }
+
+    /// Drop the oldest quarter of the window history (always keeping the newest
+    /// entry) until the state fits under the cap. Returns the entries dropped.
+    fn enforce_max_state_bytes(&mut self) -> u64 {
+        let Some(max_state_bytes) = self.max_state_bytes else {
+            return 0;
+        };
+        let mut evicted = 0;
+        while self.memory_footprint() > max_state_bytes && self.hist.len() > 1 {
+            let count = (self.hist.len() / 4).clamp(1, self.hist.len() - 1);
+            self.hist.drain(..count);
+            self.hist_rooted_slots.drain(..count);
+            self.hist_credits.drain(..count);
+            evicted += count as u64;
+            let oldest = self.hist.front().map_or(0, |(t, _, _)| *t);
+            self.leader_slots.prune_history(oldest);
+            self.fork_regimes.prune_history(oldest);
         }
+        if evicted > 0 {
+            self.history_evicted = true;
+        }
+        evicted
     }
 
-    /// Get actual credits earned this epoch (from epoch_credits tracking)
-    pub fn epoch_actual(&self) -> u64 {
-        self.epoch_actual_credits
+    /// Rolling history behind the windowed histograms
+    pub fn history(&self) -> TrackerHistory {
+        TrackerHistory {
+            entries: self.hist.clone(),
+            cumulative_histogram: self.cumulative_histogram,
+            cumulative_missed: self.cumulative_missed,
+            rooted_slots: self.hist_rooted_slots.clone(),
+            cumulative_rooted_slots: self.cumulative_rooted_slots,
+            credits: self.hist_credits.clone(),
+            cumulative_credits: self.cumulative_credits,
+        }
     }
 
-    /// Process a vote account update and return histogram updates
-    ///
-    /// Uses epoch_credits as source of truth for missed credits calculation.
-    /// The histogram tracks per-vote credits, and missed slots are inferred
-    /// from the difference between expected and actual credits.
+    /// Restore a history saved with [`VoteTracker::history`]
+    pub fn restore_history(&mut self, history: TrackerHistory) {
+        self.hist = history.entries;
+        self.history_evicted = false;
+        self.cumulative_histogram = history.cumulative_histogram;
+        self.cumulative_missed = history.cumulative_missed;
+        self.cumulative_rooted_slots = history.cumulative_rooted_slots;
+        // Older state files have no rooted slots: count from the restore on
+        self.hist_rooted_slots = if history.rooted_slots.len() == self.hist.len() {
+            history.rooted_slots
+        } else {
+            vec![self.cumulative_rooted_slots; self.hist.len()].into()
+        };
+        self.cumulative_credits = history.cumulative_credits;
+        self.hist_credits = if history.credits.len() == self.hist.len() {
+            history.credits
+        } else {
+            vec![self.cumulative_credits; self.hist.len()].into()
+        };
+    }
+
+    /// Remember the current root as the start of a range rooted unobserved,
+    /// closed by the first root after resubscribing. Repeated calls before
+    /// that keep the earliest root.
+    pub fn mark_disconnected(&mut self) {
+        if self.disconnect_root.is_none() {
+            self.disconnect_root = self.prev_root_slot;
+        }
+    }
+
+    /// Slots rooted while reconnecting since the tracker started
+    pub fn unobserved(&self) -> UnobservedSlots {
+        self.unobserved
+    }
+
+    /// Slots rooted while the exporter was down, see [`VoteTracker::apply_offline_gap`]
+    pub fn offline(&self) -> OfflineGap {
+        self.offline
+    }
+
+    /// Fold the slots rooted while the exporter was down into a tracker
+    /// restored from a state file. `saved` is the baseline in the file; the
+    /// rest is the vote account now (`previous_epoch_credits` are the
+    /// credits of the epoch before `epoch`).
     ///
-    /// `votes` is a slice of (slot, confirmation_count, latency) tuples
-    /// `epoch_credits` is the credits earned THIS epoch (credits - previous_credits from vote account)
-    /// `epoch` is the current epoch from epochCredits (more accurate than calculating from root_slot)
-    pub fn process_update(
+    /// The gap's credits and misses go into the run totals and one history
+    /// entry, spreading them over the windows reaching into the downtime;
+    /// the histograms only ever hold observed votes. Gaps crossing more than
+    /// one epoch boundary can't be reconstructed from the account and are
+    /// refused, as is a tracker that has already seen an update.
+    pub fn apply_offline_gap(
         &mut self,
-        context_slot: u64,
-        votes: &[(u64, u32, Option<u32>)], // (slot, confirmation_count, latency)
-        root_slot: Option<u64>,
-        epoch_credits: u64, // Credits earned THIS epoch (credits - previous_credits)
-        epoch: Option<u64>, // Current epoch from epochCredits
-    ) -> UpdateResult {
-        let now = Instant::now();
+        saved: &TrackerBaseline,
+        epoch: u64,
+        root_slot: u64,
+        epoch_credits: u64,
+        previous_epoch_credits: Option<u64>,
+    ) -> Result<OfflineGap> {
+        if self.prev_root_slot.is_some() {
+            bail!("tracker has already seen a root");
+        }
+        let (Some(saved_epoch), Some(saved_root)) = (saved.epoch, saved.root_slot) else {
+            bail!("state file has no root slot");
+        };
+        if epoch < saved_epoch || root_slot < saved_root {
+            bail!(
+                "vote account (epoch {}, root {}) is behind the state file (epoch {}, root {})",
+                epoch,
+                root_slot,
+                saved_epoch,
+                saved_root
+            );
+        }
 
-        // Build epoch info using epoch from epochCredits (more accurate)
-        // and slot_index from root_slot
-        let current_epoch_info = match (epoch, root_slot) {
-            (Some(e), Some(rs)) => {
-                // Calculate slot_index within epoch from root_slot
-                let slot_index = rs % SLOTS_PER_EPOCH;
-                Some(EpochInfo {
-                    epoch: e,
-                    slot_index,
-                    epoch_start_slot: e * SLOTS_PER_EPOCH,
-                    slots_in_epoch: SLOTS_PER_EPOCH,
-                })
+        let epoch_start_slot = epoch * SLOTS_PER_EPOCH;
+        // The part of the gap in the current epoch, and the one before it
+        let (current, previous) = match epoch - saved_epoch {
+            0 => {
+                let credits = epoch_credits.saturating_sub(saved.epoch_credits);
+                let gap = OfflineGap::new(root_slot - saved_root, credits, &self.credits_model);
+                (gap, None)
             }
-            (Some(e), None) => Some(EpochInfo {
-                epoch: e,
-                slot_index: 0,
-                epoch_start_slot: e * SLOTS_PER_EPOCH,
-                slots_in_epoch: SLOTS_PER_EPOCH,
-            }),
-            (None, Some(rs)) => Some(EpochInfo::from_slot(rs)),
-            (None, None) => None,
+            1 => {
+                let Some(previous_epoch_credits) = previous_epoch_credits else {
+                    bail!("vote account has no credits for epoch {}", saved_epoch);
+                };
+                let previous = OfflineGap::new(
+                    (epoch_start_slot - 1).saturating_sub(saved_root),
+                    previous_epoch_credits.saturating_sub(saved.epoch_credits),
+                    &self.credits_model,
+                );
+                let current = OfflineGap::new(
+                    root_slot.saturating_sub(epoch_start_slot) + 1,
+                    epoch_credits,
+                    &self.credits_model,
+                );
+                (current, Some(previous))
+            }
+            epochs => bail!(
+                "exporter was down across {} epoch boundaries, too long to reconstruct",
+                epochs
+            ),
         };
+        let mut gap = current;
+        gap.add(previous.unwrap_or_default());
 
-        // Detect epoch change - reset epoch histogram and missed counter
-        let epoch_changed = self.epoch_info.is_some()
-            && current_epoch_info.is_some()
-            && self.epoch_info.unwrap().epoch != current_epoch_info.unwrap().epoch;
-
-        if epoch_changed {
-            self.epoch_histogram = [0; 17];
-            self.epoch_missed = 0;
-            self.epoch_actual_credits = 0;
-            self.prev_epoch_credits = None;
-            self.prev_root_slot = None;
-            self.epoch_first_root_slot = root_slot;
+        let info = EpochInfo {
+            epoch,
+            slot_index: root_slot % SLOTS_PER_EPOCH,
+            epoch_start_slot,
+            slots_in_epoch: SLOTS_PER_EPOCH,
+        };
+        self.epoch_info = Some(info);
+        self.first_epoch = Some(saved_epoch);
+        self.prev_root_slot = Some(root_slot);
+        self.prev_epoch_credits = Some(epoch_credits);
+        self.current_epoch_credits = epoch_credits;
+        if previous.is_some() {
+            self.epoch_first_root_slot = Some(epoch_start_slot);
+            self.epoch_missed = current.missed();
+        } else {
+            self.epoch_first_root_slot = Some(saved.epoch_first_tracked_slot.unwrap_or(saved_root));
+            self.epoch_missed = saved.epoch_missed + current.missed();
+            self.epoch_missed_untracked = saved.epoch_missed_untracked;
         }
+        self.epoch_actual_credits = current.credits;
+
+        // Credits of a history from an older state file start at zero: move
+        // them onto the saved total, as if nothing was gained in the history
+        let shift = saved
+            .tracked_credits
+            .saturating_sub(self.cumulative_credits);
+        self.hist_credits
+            .iter_mut()
+            .for_each(|credits| *credits += shift);
+        self.cumulative_credits = saved.tracked_credits + gap.credits;
+        self.cumulative_missed += gap.missed();
+        self.cumulative_rooted_slots += gap.rooted_slots;
+        self.hist.push_back((
+            self.clock.now_millis(),
+            self.cumulative_histogram,
+            self.cumulative_missed,
+        ));
+        self.hist_rooted_slots
+            .push_back(self.cumulative_rooted_slots);
+        self.hist_credits.push_back(self.cumulative_credits);
+        self.offline.add(gap);
+        Ok(gap)
+    }
 
-        // Track first root slot of this epoch for expected calculation
-        if self.epoch_first_root_slot.is_none() {
-            self.epoch_first_root_slot = root_slot;
-        }
+    /// Outcome of the most recently rooted slots
+    pub fn recent_slots(&self) -> &RecentSlots {
+        &self.recent_slots
+    }
 
-        // Store current epoch credits from vote account
-        self.current_epoch_credits = epoch_credits;
+    /// Current and epoch-longest runs of rooted slots without missed credits
+    pub fn perfect_slot_streak(&self) -> PerfectSlotStreak {
+        self.perfect_streak
+    }
 
-        self.epoch_info = current_epoch_info;
+    /// Restore streaks saved from [`VoteTracker::perfect_slot_streak`]. They
+    /// are dropped by the first update of another epoch.
+    pub fn restore_perfect_slot_streak(&mut self, streak: PerfectSlotStreak) {
+        self.perfect_streak = streak;
+    }
 
-        // Get current vote slots
-        let current_votes: HashSet<u64> = votes.iter().map(|(slot, _, _)| *slot).collect();
+    /// Result of the last finished epoch
+    pub fn previous_epoch(&self) -> Option<EpochResult> {
+        self.previous_epoch
+    }
 
-        // Build a map of slot -> latency for new vote credit calculation
-        let vote_latencies: std::collections::HashMap<u64, Option<u32>> = votes
-            .iter()
-            .map(|(slot, _, latency)| (*slot, *latency))
-            .collect();
+    /// Restore a result saved from [`VoteTracker::previous_epoch`]
+    pub fn restore_previous_epoch(&mut self, previous: Option<EpochResult>) {
+        self.previous_epoch = previous;
+    }
 
-        // Find new votes (in current but not in previous)
-        let new_votes: Vec<u64> = current_votes
-            .difference(&self.prev_votes)
-            .copied()
-            .collect();
+    /// Efficiency by UTC hour of day
+    pub fn hourly_profile(&self) -> &HourlyProfile {
+        &self.hourly_profile
+    }
 
-        // Calculate credits for each new vote
-        let mut update_histogram = [0u64; 17];
+    /// Restore a profile saved from [`VoteTracker::hourly_profile`]
+    pub fn restore_hourly_profile(&mut self, profile: HourlyProfile) {
+        self.hourly_profile = profile;
+    }
 
+    /// Set the own leader slots of `epoch` (indices into the epoch), from
+    /// `getLeaderSchedule` for the validator identity
+    pub fn set_leader_slots(&mut self, epoch: u64, slot_indices: &[u64]) {
+        self.leader_slots
+            .set_schedule(epoch, epoch * SLOTS_PER_EPOCH, slot_indices);
+    }
+
+    /// Epoch of the leader schedule set with [`VoteTracker::set_leader_slots`]
+    pub fn leader_schedule_epoch(&self) -> Option<u64> {
+        self.leader_slots.epoch()
+    }
+
+    /// Own leader slots this epoch (None without a schedule for the current epoch)
+    pub fn leader_slot_count(&self) -> Option<usize> {
+        self.current_leader_slots().map(LeaderSlots::len)
+    }
+
+    /// First and last slot of the rooted ranges awaiting the skipped slot
+    /// check, covering at most `max_slots` slots past the oldest range
+    pub fn skipped_slot_check_span(&self, max_slots: u64) -> Option<(u64, u64)> {
+        self.skipped_slots.pending_span(max_slots)
+    }
+
+    /// Settle the rooted ranges up to `last` against the slots with a block in
+    /// `first..=last` (sorted, as from getBlocks). Returns the skipped slots found.
+    pub fn apply_produced_blocks(&mut self, first: u64, last: u64, blocks: &[u64]) -> u64 {
+        self.skipped_slots
+            .apply_blocks(first, last, blocks, &self.credits_model)
+    }
+
+    /// Missed credits this epoch less those on slots the cluster skipped, as
+    /// far as checked (None unless skipped slots are tracked)
+    pub fn epoch_missed_skip_adjusted(&self) -> Option<u64> {
+        if !self.skipped_slots.is_enabled() {
+            return None;
+        }
+        let epoch = self.epoch_info?.epoch;
+        let (_, skipped_missed) = self.skipped_slots.epoch_totals(epoch);
+        Some(self.epoch_missed.saturating_sub(skipped_missed))
+    }
+
+    fn current_leader_slots(&self) -> Option<&LeaderSlots> {
+        let epoch = self.epoch_info?.epoch;
+        (self.leader_slots.epoch() == Some(epoch)).then_some(&self.leader_slots)
+    }
+
+    /// Current wall-clock time of the tracker's clock, in unix milliseconds
+    pub fn now_millis(&self) -> u64 {
+        self.clock.now_millis()
+    }
+
+    /// Set the smoothing factor (per rooted slot) used for the smoothed projection
+    pub fn with_projection_alpha(mut self, alpha: f64) -> Self {
+        self.projection_alpha = alpha;
+        self
+    }
+
+    /// Report votes landing without credits after `updates` such updates in a row
+    pub fn with_credit_stall_updates(mut self, updates: u32) -> Self {
+        self.credit_stall_updates = updates.max(1);
+        self
+    }
+
+    /// Whether new votes keep landing while the epoch credits don't increase
+    pub fn votes_without_credits(&self) -> bool {
+        self.credit_stall.active
+    }
+
+    /// Keep catch-up votes out of the windowed (5m/1h) histograms and averages.
+    /// They are still counted in the epoch histogram.
+    pub fn with_exclude_catch_up(mut self, exclude: bool) -> Self {
+        self.exclude_catch_up = exclude;
+        self
+    }
+
+    /// Regime of the most recent votes
+    pub fn regime(&self) -> Regime {
+        self.regime.regime()
+    }
+
+    /// Whether the root slot and epochCredits put the last update in different epochs
+    pub fn epoch_source_disagreement(&self) -> bool {
+        self.epoch_source_disagreement
+    }
+
+    /// Replace the credits model used for per-vote, expected and missed credits
+    pub fn set_credits_model(&mut self, credits_model: CreditsModel) {
+        self.credits_model = credits_model;
+    }
+
+    /// Credits model used for per-vote, expected and missed credits
+    pub fn credits_model(&self) -> CreditsModel {
+        self.credits_model
+    }
+
+    /// Current epoch-level baseline
+    pub fn baseline(&self) -> TrackerBaseline {
+        TrackerBaseline {
+            epoch: self.epoch_info.map(|info| info.epoch),
+            root_slot: self.prev_root_slot,
+            epoch_credits: self.current_epoch_credits,
+            epoch_missed: self.epoch_missed,
+            tracked_credits: self.cumulative_credits,
+            tracked_missed: self.cumulative_missed,
+            epoch_first_tracked_slot: self.epoch_first_root_slot,
+            epoch_missed_untracked: self.epoch_missed_untracked,
+        }
+    }
+
+    /// Clear all epoch and cumulative state and re-seed it from a vote account
+    /// snapshot (epoch, root slot and credits earned this epoch).
+    /// Missed credits restart from the rooted expectation of the epoch.
+    pub fn reset(&mut self, epoch: u64, root_slot: u64, epoch_credits: u64) {
+        let hourly_profile = std::mem::take(&mut self.hourly_profile);
+        let previous_epoch = self.previous_epoch;
+        let leader_slots = self.leader_slots.schedule_only();
+        *self = Self::new(self.credits_model)
+            .with_projection_alpha(self.projection_alpha)
+            .with_exclude_catch_up(self.exclude_catch_up)
+            .with_credit_stall_updates(self.credit_stall_updates)
+            .with_clock(self.clock.clone())
+            .with_expected_anchor(self.expected_anchor, self.tip_allowance_slots)
+            .with_max_state_bytes(self.max_state_bytes)
+            .with_skipped_slot_tracking(self.skipped_slots.is_enabled())
+            .with_maintenance(self.maintenance.clone());
+        self.hourly_profile = hourly_profile;
+        self.previous_epoch = previous_epoch;
+        self.leader_slots = leader_slots;
+
+        let info = EpochInfo {
+            epoch,
+            slot_index: root_slot % SLOTS_PER_EPOCH,
+            epoch_start_slot: epoch * SLOTS_PER_EPOCH,
+            slots_in_epoch: SLOTS_PER_EPOCH,
+        };
+        self.epoch_info = Some(info);
+        self.first_epoch = Some(epoch);
+        self.prev_root_slot = Some(root_slot);
+        self.prev_epoch_credits = Some(epoch_credits);
+        self.epoch_first_root_slot = Some(root_slot);
+        self.current_epoch_credits = epoch_credits;
+        self.epoch_missed = info
+            .expected_max_credits(root_slot, &self.credits_model)
+            .saturating_sub(epoch_credits);
+    }
+
+    /// Align the epoch credits and missed credits with a fresh vote account
+    /// snapshot, keeping the histograms and run totals. Returns false (and
+    /// changes nothing) if the snapshot is for another epoch or behind the tracker.
+    pub fn reconcile(&mut self, epoch: u64, root_slot: u64, epoch_credits: u64) -> bool {
+        let same_epoch = self.epoch_info.map(|info| info.epoch) == Some(epoch);
+        let behind = self.prev_root_slot.is_some_and(|root| root_slot < root);
+        if !same_epoch || behind {
+            return false;
+        }
+
+        let info = self.epoch_info.expect("checked above");
+        self.epoch_info = Some(EpochInfo {
+            slot_index: root_slot % SLOTS_PER_EPOCH,
+            ..info
+        });
+        self.prev_root_slot = Some(root_slot);
+        self.prev_epoch_credits = Some(epoch_credits);
+        self.current_epoch_credits = epoch_credits;
+        self.epoch_missed = info
+            .expected_max_credits(root_slot, &self.credits_model)
+            .saturating_sub(epoch_credits)
+            .saturating_sub(self.epoch_missed_maintenance);
+        self.epoch_missed_untracked = 0;
+        true
+    }
+
+    /// Exponential moving average of the credits earned per rooted slot
+    pub fn smoothed_rate(&self) -> Option<f64> {
+        self.smoothed_rate
+    }
+
+    /// Projected credits at epoch end using the smoothed rate:
+    /// credits_so_far + smoothed_rate × remaining rooted slots
+    pub fn projected_credits_smoothed(&self) -> Option<u64> {
+        let (info, rate) = (self.epoch_info?, self.smoothed_rate?);
+        let remaining_slots = info.slots_in_epoch.saturating_sub(info.slot_index + 1);
+        Some(self.credits_model.projected_credits(
+            self.current_epoch_credits,
+            rate,
+            remaining_slots,
+            info.slots_in_epoch,
+        ))
+    }
+
+    /// Take a point-in-time copy of the tracker state
+    pub fn snapshot(&self) -> TrackerSnapshot {
+        TrackerSnapshot {
+            epoch_info: self.epoch_info,
+            first_epoch: self.first_epoch,
+            current_epoch_credits: self.current_epoch_credits,
+            epoch_missed: self.epoch_missed,
+            tracked_credits: self.cumulative_credits,
+            tracked_missed: self.cumulative_missed,
+            worst_window_5m: self.worst_window_5m,
+            epoch_worst_window_5m: self.epoch_worst_window_5m,
+            epoch_first_tracked_slot: self.epoch_first_tracked_slot(),
+            epoch_last_tracked_slot: self.epoch_last_tracked_slot(),
+        }
+    }
+
+    /// Get current epoch credits from vote account (credits - previous_credits)
+    /// This matches what `solana vote-account` shows for the current epoch
+    pub fn current_epoch_credits(&self) -> u64 {
+        self.current_epoch_credits
+    }
+
+    /// Get current epoch info (if available)
+    pub fn epoch_info(&self) -> Option<EpochInfo> {
+        self.epoch_info
+    }
+
+    /// Get expected max credits for the current epoch
+    pub fn epoch_expected_max(&self) -> u64 {
+        match (
+            self.epoch_info,
+            self.prev_root_slot,
+            self.epoch_first_root_slot,
+        ) {
+            (Some(_info), Some(root), Some(first_root)) => {
+                // Calculate slots elapsed since we started tracking this epoch
+                let slots_tracked = root.saturating_sub(first_root).saturating_add(1);
+                self.credits_model.expected_credits(slots_tracked)
+            }
+            _ => 0,
+        }
+    }
+
+    /// First root slot tracked this epoch
+    pub fn epoch_first_tracked_slot(&self) -> Option<u64> {
+        self.epoch_first_root_slot
+    }
+
+    /// Latest root slot tracked this epoch
+    pub fn epoch_last_tracked_slot(&self) -> Option<u64> {
+        self.prev_root_slot
+    }
+
+    /// Get actual credits earned this epoch (from epoch_credits tracking)
+    pub fn epoch_actual(&self) -> u64 {
+        self.epoch_actual_credits
+    }
+
+    /// Process a vote account update and return histogram updates
+    ///
+    /// Uses epoch_credits as source of truth for missed credits calculation.
+    /// The histogram tracks per-vote credits of rooted votes, and missed slots
+    /// are inferred from the difference between expected and actual credits.
+    ///
+    /// `votes` is a slice of (slot, confirmation_count, latency) tuples
+    /// `epoch_credits` is the credits earned THIS epoch (credits - previous_credits from vote account)
+    /// `epoch` is the current epoch from epochCredits (more accurate than calculating from root_slot)
+    pub fn process_update(
+        &mut self,
+        context_slot: u64,
+        votes: &[(u64, u32, Option<u32>)], // (slot, confirmation_count, latency)
+        root_slot: Option<u64>,
+        epoch_credits: u64, // Credits earned THIS epoch (credits - previous_credits)
+        epoch: Option<u64>, // Current epoch from epochCredits
+    ) -> UpdateResult {
+        // Never stamp an entry before the previous one, so the history stays
+        // ordered if the wall clock jumps backwards
+        let now = self
+            .clock
+            .now_millis()
+            .max(self.hist.back().map_or(0, |(t, _, _)| *t));
+
+        // Build epoch info using epoch from epochCredits (authoritative: the
+        // root can cross into the next epoch before the vote account does)
+        // and slot_index from root_slot
+        self.epoch_source_disagreement =
+            matches!((epoch, root_slot), (Some(e), Some(rs)) if rs / SLOTS_PER_EPOCH != e);
+        let current_epoch_info = match (epoch, root_slot) {
+            (Some(e), Some(rs)) => {
+                // Calculate slot_index within epoch from root_slot, pinned to
+                // the epoch's bounds while the two disagree
+                let epoch_start_slot = e * SLOTS_PER_EPOCH;
+                let slot_index = rs.saturating_sub(epoch_start_slot).min(SLOTS_PER_EPOCH - 1);
+                Some(EpochInfo {
+                    epoch: e,
+                    slot_index,
+                    epoch_start_slot,
+                    slots_in_epoch: SLOTS_PER_EPOCH,
+                })
+            }
+            (Some(e), None) => Some(EpochInfo {
+                epoch: e,
+                slot_index: 0,
+                epoch_start_slot: e * SLOTS_PER_EPOCH,
+                slots_in_epoch: SLOTS_PER_EPOCH,
+            }),
+            (None, Some(rs)) => Some(EpochInfo::from_slot(rs)),
+            (None, None) => None,
+        };
+
+        // Detect epoch change - reset epoch histogram and missed counter
+        let epoch_changed = self.epoch_info.is_some()
+            && current_epoch_info.is_some()
+            && self.epoch_info.unwrap().epoch != current_epoch_info.unwrap().epoch;
+
+        let mut epoch_summary = None;
+        if epoch_changed {
+            let avg_latency = (Self::histogram_total(&self.epoch_histogram) > 0)
+                .then(|| self.histogram_avg_latency(&self.epoch_histogram));
+            epoch_summary = self.epoch_info.map(|info| EpochSummary {
+                epoch: info.epoch,
+                credits: self.current_epoch_credits,
+                missed: self.epoch_missed,
+                worst_window_5m: self.epoch_worst_window_5m,
+                perfect_slot_streak: Some(self.perfect_streak),
+                stake_delta_lamports: None,
+                unobserved: Some(self.epoch_unobserved),
+                first_tracked_slot: self.epoch_first_root_slot,
+                last_tracked_slot: self.prev_root_slot,
+                avg_latency,
+                comparison: None,
+                cluster: None,
+            });
+            if let Some(summary) = &mut epoch_summary {
+                summary.compare_with_previous(&mut self.previous_epoch);
+            }
+            self.epoch_unobserved = UnobservedSlots::default();
+            self.epoch_worst_window_5m = None;
+            self.leader_slots.start_epoch();
+            self.epoch_histogram = [0; 17];
+            self.epoch_missed = 0;
+            self.epoch_missed_maintenance = 0;
+            self.epoch_actual_credits = 0;
+            self.prev_epoch_credits = None;
+            self.prev_root_slot = None;
+            self.epoch_first_root_slot = None;
+        }
+
+        // Track first root slot of this epoch for expected calculation, and
+        // what the epoch missed before it
+        if self.epoch_first_root_slot.is_none() {
+            self.epoch_first_root_slot = root_slot;
+            self.epoch_missed_untracked = match (current_epoch_info, root_slot) {
+                (Some(info), Some(root)) => info
+                    .expected_max_credits(root, &self.credits_model)
+                    .saturating_sub(epoch_credits),
+                _ => 0,
+            };
+        }
+
+        // The first root after a reconnect closes the range rooted unobserved
+        let mut unobserved = UnobservedSlots::default();
+        if let (Some(disconnect_root), Some(root)) = (self.disconnect_root, root_slot) {
+            self.disconnect_root = None;
+            let rooted_slots = root.saturating_sub(disconnect_root);
+            unobserved = UnobservedSlots {
+                rooted_slots,
+                expected_credits: self.credits_model.expected_credits(rooted_slots),
+            };
+            self.unobserved.add(unobserved);
+            self.epoch_unobserved.add(unobserved);
+        }
+
+        // Store current epoch credits from vote account
+        self.current_epoch_credits = epoch_credits;
+        self.tip_slot = Some(context_slot);
+
+        self.epoch_info = current_epoch_info;
+        if self.first_epoch.is_none() {
+            self.first_epoch = current_epoch_info.map(|info| info.epoch);
+        }
+        // Streaks start over each epoch, including ones restored from a
+        // state file saved in an earlier epoch
+        if let Some(info) = current_epoch_info {
+            if self.perfect_streak.epoch != info.epoch {
+                self.perfect_streak = PerfectSlotStreak {
+                    epoch: info.epoch,
+                    ..Default::default()
+                };
+            }
+        }
+
+        // Get current vote slots
+        let current_votes: HashSet<u64> = votes.iter().map(|(slot, _, _)| *slot).collect();
+
+        // Settle the pending votes the root has passed. Rooted votes leave the
+        // tower, so a vote counts if it was still in the tower before this update;
+        // one that had already dropped out was abandoned and never earns credits.
+        let mut update_histogram = [0u64; 17];
+        let mut catch_up_votes = 0;
+        let mut never_rooted = 0;
+        // Credits of rooted votes on own leader slots: all, and those that
+        // went into the windowed histograms
+        let mut leader_credits = 0;
+        let mut leader_windowed_credits = 0;
+        // Credits of rooted votes that went into the windowed histograms
+        let mut windowed_credits = 0;
+        let mut root_delays_ms = Vec::new();
+        // Credits of each rooted vote, by slot
+        let mut rooted_votes = BTreeMap::new();
+        if let Some(root) = root_slot {
+            let unrooted = self.pending_votes.split_off(&root.saturating_add(1));
+            let passed = std::mem::replace(&mut self.pending_votes, unrooted);
+            let unrooted = self.vote_first_seen.split_off(&root.saturating_add(1));
+            let first_seen = std::mem::replace(&mut self.vote_first_seen, unrooted);
+            for (slot, vote) in passed {
+                if !vote.in_tower && !current_votes.contains(&slot) {
+                    never_rooted += 1;
+                    continue;
+                }
+                if let Some(seen) = first_seen.get(&slot) {
+                    root_delays_ms.push(now.saturating_sub(*seen));
+                }
+                rooted_votes.insert(slot, vote.credits);
+                let credits = vote.credits as usize;
+                update_histogram[credits] += 1;
+                self.epoch_histogram[credits] += 1;
+                let leader_slot = self.leader_slots.contains(slot);
+                if leader_slot {
+                    leader_credits += vote.credits;
+                }
+                if vote.catch_up {
+                    catch_up_votes += 1;
+                    if self.exclude_catch_up {
+                        continue;
+                    }
+                }
+                self.cumulative_histogram[credits] += 1;
+                windowed_credits += vote.credits;
+                if leader_slot {
+                    leader_windowed_credits += vote.credits;
+                }
+            }
+        }
+
+        // Build a map of slot -> latency for new vote credit calculation
+        let vote_latencies: std::collections::HashMap<u64, Option<u32>> = votes
+            .iter()
+            .map(|(slot, _, latency)| (*slot, *latency))
+            .collect();
+
+        // Find new votes (in the tower, above the root and not pending yet), in
+        // slot order for the regime classifier. Keying by slot means a vote seen
+        // again after a fork flip isn't counted twice.
+        let mut new_votes: Vec<u64> = current_votes
+            .iter()
+            .filter(|slot| {
+                !self.pending_votes.contains_key(slot)
+                    && !root_slot.is_some_and(|root| **slot <= root)
+            })
+            .copied()
+            .collect();
+        new_votes.sort_unstable();
+
+        // Calculate credits for each new vote; they are held until rooted
+        let mut new_vote_latencies = Vec::with_capacity(new_votes.len());
         for vote_slot in &new_votes {
-            let credits = if let Some(Some(latency)) = vote_latencies.get(vote_slot) {
+            let (credits, latency) = if let Some(Some(latency)) = vote_latencies.get(vote_slot) {
                 // Use the latency field from the vote account
-                // latency = 1 means 16 credits, latency = 17 means 0 credits
-                17u64.saturating_sub(*latency as u64).min(16)
+                (
+                    self.credits_model.credits_from_latency(*latency),
+                    *latency as u64,
+                )
             } else {
                 // Fall back to inferring from context slot
                 let gap = context_slot.saturating_sub(*vote_slot);
-                16u64.saturating_sub(gap).min(16)
+                (self.credits_model.credits_from_gap(gap), gap)
             };
+            new_vote_latencies.push(latency);
+            let regime = self.regime.observe(latency);
+            // Keep the earliest sighting if the vote comes back after a fork flip
+            self.vote_first_seen.entry(*vote_slot).or_insert(now);
+            self.pending_votes.insert(
+                *vote_slot,
+                PendingVote {
+                    credits,
+                    catch_up: regime == Regime::CatchUp,
+                    in_tower: true,
+                },
+            );
+        }
 
-            update_histogram[credits as usize] += 1;
-            self.epoch_histogram[credits as usize] += 1;
-            self.cumulative_histogram[credits as usize] += 1;
+        // Remember which pending votes are still in the tower. Ones that dropped
+        // out may come back (another bank for the same slot), but not forever.
+        self.pending_votes.retain(|slot, vote| {
+            vote.in_tower = current_votes.contains(slot);
+            let abandoned =
+                !vote.in_tower && slot.saturating_add(NEVER_ROOTED_BOUND_SLOTS) < context_slot;
+            if abandoned {
+                never_rooted += 1;
+            }
+            !abandoned
+        });
+        self.vote_first_seen
+            .retain(|_, seen| now.saturating_sub(*seen) <= ROOT_DELAY_MAX_AGE_MS);
+        while self.vote_first_seen.len() > ROOT_DELAY_MAX_SLOTS {
+            self.vote_first_seen.pop_first();
         }
 
         // Calculate missed credits using epoch_credits as source of truth
         // This accounts for BOTH late votes AND missed slots
         let mut missed_this_update = 0u64;
+        let mut maintenance_missed = 0u64;
+        let mut rooted_credits = None;
         if let (Some(prev_root), Some(curr_root), Some(prev_credits)) =
             (self.prev_root_slot, root_slot, self.prev_epoch_credits)
         {
             if curr_root > prev_root {
                 let slots_rooted = curr_root - prev_root;
-                let expected_credits = slots_rooted * MAX_CREDITS_PER_SLOT;
+                let expected_credits = self.credits_model.expected_credits(slots_rooted);
                 let actual_delta = epoch_credits.saturating_sub(prev_credits);
+                rooted_credits = Some(actual_delta);
                 missed_this_update = expected_credits.saturating_sub(actual_delta);
-
-                // Add missed credits to cumulative total
-                self.cumulative_missed += missed_this_update;
-                self.epoch_missed += missed_this_update;
+                // Which slots of the range missed is unknown, so any miss
+                // ends the streak
+                if missed_this_update == 0 {
+                    self.perfect_streak.extend(slots_rooted);
+                } else {
+                    self.perfect_streak.current = 0;
+                }
+
+                // Add missed credits to cumulative total, or set them aside
+                // during maintenance
+                if self.maintenance.as_ref().is_some_and(|m| m.is_active()) {
+                    maintenance_missed = missed_this_update;
+                    self.epoch_missed_maintenance += missed_this_update;
+                } else {
+                    self.cumulative_missed += missed_this_update;
+                    self.epoch_missed += missed_this_update;
+                }
+                self.cumulative_rooted_slots += slots_rooted;
                 self.epoch_actual_credits += actual_delta;
+                self.cumulative_credits += actual_delta;
+                self.hourly_profile
+                    .record(now, actual_delta, expected_credits);
+
+                // Attribute to the leader slots in the rooted range what they
+                // missed, as far as the missed credits of the range allow
+                let leader_expected = self
+                    .credits_model
+                    .expected_credits(self.leader_slots.count_between(prev_root, curr_root));
+                let leader_missed = leader_expected
+                    .saturating_sub(leader_credits)
+                    .min(missed_this_update);
+                self.leader_slots.record_rooted(
+                    (actual_delta, expected_credits),
+                    (leader_credits, leader_expected),
+                    leader_windowed_credits,
+                    leader_missed,
+                );
+                if let Some(info) = current_epoch_info {
+                    self.skipped_slots.record_rooted(RootedRange {
+                        epoch: info.epoch,
+                        after: prev_root,
+                        through: curr_root,
+                        missed: missed_this_update,
+                    });
+                }
+                self.recent_slots.record_rooted(
+                    prev_root,
+                    curr_root,
+                    &rooted_votes,
+                    actual_delta,
+                    &self.credits_model,
+                );
+
+                self.update_smoothed_rate(actual_delta, slots_rooted);
             }
         }
+        self.fork_regimes.record(
+            self.fork_regime,
+            windowed_credits,
+            missed_this_update - maintenance_missed,
+        );
 
         // Store history entry for windowed calculations
         self.hist
             .push_back((now, self.cumulative_histogram, self.cumulative_missed));
+        self.hist_rooted_slots
+            .push_back(self.cumulative_rooted_slots);
+        self.hist_credits.push_back(self.cumulative_credits);
 
         // Prune history older than 1 hour
-        let cutoff = now - std::time::Duration::from_secs(3600);
+        let cutoff = now.saturating_sub(HISTORY_RETENTION_MS);
         while let Some((t, _, _)) = self.hist.front() {
             if *t < cutoff {
                 self.hist.pop_front();
+                self.hist_rooted_slots.pop_front();
+                self.hist_credits.pop_front();
+                self.history_evicted = true;
             } else {
                 break;
             }
         }
+        self.leader_slots.push_history(now, cutoff);
+        self.fork_regimes.push_history(now, cutoff);
+        let state_evictions = self.enforce_max_state_bytes();
+
+        // Track the worst 5m window, skipping the startup period where the
+        // window is not yet fully covered by history
+        if self.window_is_populated(300) && self.window_expected(300) > 0 {
+            let current = WorstWindow {
+                efficiency: self.window_efficiency(300),
+                timestamp: now / 1000,
+            };
+            self.worst_window_5m = Some(WorstWindow::lower(self.worst_window_5m, current));
+            self.epoch_worst_window_5m =
+                Some(WorstWindow::lower(self.epoch_worst_window_5m, current));
+        }
+
+        let credit_stall = self.observe_credit_stall(&new_vote_latencies, rooted_credits);
+        let restart = self.detect_restart(&new_votes, votes.len());
+        if let Some(max_vote_slot) = current_votes.iter().max() {
+            self.prev_max_vote_slot = Some(*max_vote_slot);
+            self.prev_tower_height = votes.len();
+        }
 
         // Update state
-        self.prev_votes = current_votes;
         self.prev_root_slot = root_slot;
         self.prev_epoch_credits = Some(epoch_credits);
 
         UpdateResult {
             new_votes: new_votes.len() as u64,
+            new_vote_slots: new_votes,
             missed_credits: missed_this_update,
+            maintenance_missed,
             update_histogram,
+            epoch_summary,
+            restart,
+            credit_stall,
+            catch_up_votes,
+            never_rooted,
+            state_evictions,
+            root_delays_ms,
+            unobserved,
+        }
+    }
+
+    /// Count updates whose new votes land while the credits stand still:
+    /// the root advanced, yet the rooted range earned nothing. Updates that
+    /// root nothing say nothing about credits and are skipped; a rooted range
+    /// without new votes breaks the run. Returns the change if the stall
+    /// started or ended with this update.
+    fn observe_credit_stall(
+        &mut self,
+        new_vote_latencies: &[u64],
+        rooted_credits: Option<u64>,
+    ) -> Option<CreditStallChange> {
+        let stall = &mut self.credit_stall;
+        match rooted_credits {
+            None => return None,
+            Some(0) if !new_vote_latencies.is_empty() => {
+                stall.updates += 1;
+                stall.latencies.extend(new_vote_latencies);
+                while stall.latencies.len() > CREDIT_STALL_LATENCIES {
+                    stall.latencies.pop_front();
+                }
+            }
+            Some(0) => {
+                stall.updates = 0;
+                stall.latencies.clear();
+                return None;
+            }
+            Some(_) => {
+                stall.updates = 0;
+                stall.latencies.clear();
+                return std::mem::take(&mut stall.active).then_some(CreditStallChange::Ended);
+            }
+        }
+        if stall.active || stall.updates < self.credit_stall_updates {
+            return None;
+        }
+        stall.active = true;
+        Some(CreditStallChange::Started {
+            updates: stall.updates,
+            latencies: stall.latencies.iter().copied().collect(),
+        })
+    }
+
+    /// A restart shows up as new votes far from the previous highest vote (a gap
+    /// while the validator was down, or a jump back when its tower was rebuilt
+    /// from an older snapshot) together with a tower that lost at least half its
+    /// votes because the old lockouts expired. Fork switches move only a few
+    /// slots, and catch-up votes after the restart continue from the new tower.
+    fn detect_restart(&self, new_votes: &[u64], tower_height: usize) -> Option<SuspectedRestart> {
+        let last_vote_slot = self.prev_max_vote_slot?;
+        let resumed_vote_slot = *new_votes.iter().min()?;
+        let restart = SuspectedRestart {
+            last_vote_slot,
+            resumed_vote_slot,
+            tower_height_before: self.prev_tower_height,
+            tower_height_after: tower_height,
+        };
+        let tower_collapsed = tower_height * 2 <= self.prev_tower_height;
+        (restart.gap_slots() >= RESTART_MIN_GAP_SLOTS && tower_collapsed).then_some(restart)
+    }
+
+    /// Fold the rate observed over `slots_rooted` slots into the smoothed rate.
+    /// The smoothing factor applies per slot, so sparse and dense updates
+    /// decay the old level equally per unit of chain progress. This is the
+    /// level equation of Holt's method only: a trend term would extend a
+    /// short dip over the rest of the epoch, which the projection is meant
+    /// to ride out.
+    fn update_smoothed_rate(&mut self, actual_delta: u64, slots_rooted: u64) {
+        let rate = (actual_delta as f64 / slots_rooted as f64)
+            .min(self.credits_model.max_credits_per_slot as f64);
+        self.smoothed_rate = Some(match self.smoothed_rate {
+            Some(level) => {
+                let weight = 1.0 - (1.0 - self.projection_alpha).powf(slots_rooted as f64);
+                level + weight * (rate - level)
+            }
+            None => rate,
+        });
+    }
+
+    /// Start of a window ending now, in unix millis. Entries stamped after
+    /// now (wall clock went backwards) count as zero elapsed, i.e. in the window.
+    fn window_start(&self, window_secs: u64) -> u64 {
+        self.clock.now_millis().saturating_sub(window_secs * 1000)
+    }
+
+    /// Counters at the window start (the window's baseline): the last history
+    /// entry before it, or, when that entry lies more than
+    /// [`WINDOW_BASE_MAX_LAG_MS`] before it (an update gap), the values
+    /// interpolated towards the first entry in the window. After evictions a
+    /// window reaching past the history is truncated to it.
+    fn window_base(&self, window_secs: u64) -> WindowBase {
+        let start = self.window_start(window_secs);
+        // Entries are never stamped before the previous one
+        let in_window = self.hist.partition_point(|(t, _, _)| *t < start);
+        let updates = (self.hist.len() - in_window) as u64;
+        let Some(base_index) = in_window.checked_sub(1) else {
+            return match self.hist.front().filter(|_| self.history_evicted) {
+                Some((_, histogram, missed)) => WindowBase {
+                    histogram: *histogram,
+                    histogram_credits: Self::histogram_credits(histogram),
+                    missed: *missed,
+                    rooted_slots: self.hist_rooted_slots[0],
+                    expected: self
+                        .credits_model
+                        .expected_credits(self.hist_rooted_slots[0]),
+                    credits: self.hist_credits[0],
+                    updates: updates - 1,
+                },
+                None => WindowBase {
+                    histogram: [0; 17],
+                    histogram_credits: 0,
+                    missed: 0,
+                    rooted_slots: 0,
+                    expected: 0,
+                    credits: 0,
+                    updates,
+                },
+            };
+        };
+        let (base_t, base_hist, base_missed) = self.hist[base_index];
+        let base_rooted = self.hist_rooted_slots[base_index];
+        let base_credits = self.hist_credits[base_index];
+        let next = self.hist.get(in_window);
+        match next {
+            Some((next_t, next_hist, next_missed)) if start - base_t > WINDOW_BASE_MAX_LAG_MS => {
+                let mut histogram = [0; 17];
+                for (i, count) in histogram.iter_mut().enumerate() {
+                    *count =
+                        interpolate_counter((base_t, base_hist[i]), (*next_t, next_hist[i]), start);
+                }
+                let next_rooted = self.hist_rooted_slots[in_window];
+                let next_credits = self.hist_credits[in_window];
+                WindowBase {
+                    histogram,
+                    histogram_credits: interpolate_counter(
+                        (base_t, Self::histogram_credits(&base_hist)),
+                        (*next_t, Self::histogram_credits(next_hist)),
+                        start,
+                    ),
+                    missed: interpolate_counter(
+                        (base_t, base_missed),
+                        (*next_t, *next_missed),
+                        start,
+                    ),
+                    rooted_slots: interpolate_counter(
+                        (base_t, base_rooted),
+                        (*next_t, next_rooted),
+                        start,
+                    ),
+                    expected: interpolate_counter(
+                        (base_t, self.credits_model.expected_credits(base_rooted)),
+                        (*next_t, self.credits_model.expected_credits(next_rooted)),
+                        start,
+                    ),
+                    credits: interpolate_counter(
+                        (base_t, base_credits),
+                        (*next_t, next_credits),
+                        start,
+                    ),
+                    updates,
+                }
+            }
+            _ => WindowBase {
+                histogram: base_hist,
+                histogram_credits: Self::histogram_credits(&base_hist),
+                missed: base_missed,
+                rooted_slots: base_rooted,
+                expected: self.credits_model.expected_credits(base_rooted),
+                credits: base_credits,
+                updates,
+            },
         }
     }
 
@@ -284,18 +1837,8 @@ impl VoteTracker {
             return [0; 17];
         }
 
-        let now = Instant::now();
-        let start = now - std::time::Duration::from_secs(window_secs);
-
-        // Find the last entry BEFORE the window start
-        // If no entry exists before the window, use zeros as baseline
-        let base = self
-            .hist
-            .iter()
-            .rev()
-            .find(|(t, _, _)| *t < start)
-            .map(|(_, h, _)| *h)
-            .unwrap_or([0; 17]);
+        // Baseline at the window start (zeros if the history doesn't reach it)
+        let base = self.window_base(window_secs).histogram;
 
         // Calculate delta from baseline to current
         let mut result = [0u64; 17];
@@ -305,27 +1848,84 @@ impl VoteTracker {
         result
     }
 
+    /// Whether the history reaches back at least `window_secs`
+    /// (false during startup, while the window is still under-populated)
+    pub fn window_is_populated(&self, window_secs: u64) -> bool {
+        let start = self.window_start(window_secs);
+        self.hist.front().is_some_and(|(t, _, _)| *t < start)
+    }
+
+    /// Fraction of a time window the history spans, from its oldest entry
+    /// (or the window start) to its newest. Falls towards 0 while no updates
+    /// arrive, e.g. while the cluster is halted, and starts over from the
+    /// first update after a halt that aged the history out.
+    pub fn window_coverage(&self, window_secs: u64) -> f64 {
+        let (Some((oldest, _, _)), Some((newest, _, _))) = (self.hist.front(), self.hist.back())
+        else {
+            return 0.0;
+        };
+        let start = self.window_start(window_secs);
+        let end = (*newest).min(self.clock.now_millis());
+        let spanned = end.saturating_sub((*oldest).max(start));
+        (spanned as f64 / (window_secs * 1000) as f64).min(1.0)
+    }
+
     /// Get missed credits for a time window
     pub fn window_missed(&self, window_secs: u64) -> u64 {
         if self.hist.is_empty() {
             return 0;
         }
 
-        let now = Instant::now();
-        let start = now - std::time::Duration::from_secs(window_secs);
-
-        // Find the last entry BEFORE the window start
-        let base = self
-            .hist
-            .iter()
-            .rev()
-            .find(|(t, _, _)| *t < start)
-            .map(|(_, _, m)| *m)
-            .unwrap_or(0);
+        // Baseline at the window start (zero if the history doesn't reach it)
+        let base = self.window_base(window_secs).missed;
 
         self.cumulative_missed.saturating_sub(base)
     }
 
+    /// Number of updates contributing to a time window (the sample size
+    /// behind its efficiency)
+    pub fn window_updates(&self, window_secs: u64) -> u64 {
+        if self.hist.is_empty() {
+            return 0;
+        }
+        self.window_base(window_secs).updates
+    }
+
+    /// Rooted slots covered by a time window
+    pub fn window_rooted_slots(&self, window_secs: u64) -> u64 {
+        if self.hist.is_empty() {
+            return 0;
+        }
+        let base = self.window_base(window_secs).rooted_slots;
+        self.cumulative_rooted_slots.saturating_sub(base)
+    }
+
+    /// The window's efficiency from the histogram and from the credit counter,
+    /// both from one window start (None while the window is under-populated).
+    /// They differ when the credits of the rooted votes don't match what the
+    /// account gained: excluded catch-up votes, votes the notifications missed,
+    /// latencies inferred from the context slot, or misses set aside during
+    /// maintenance.
+    pub fn window_cross_check(&self, window_secs: u64) -> Option<EfficiencyCrossCheck> {
+        if !self.window_is_populated(window_secs) {
+            return None;
+        }
+        let base = self.window_base(window_secs);
+        let rooted_slots = self
+            .cumulative_rooted_slots
+            .saturating_sub(base.rooted_slots);
+        Some(EfficiencyCrossCheck {
+            histogram_credits: self.window_credits_since(&base),
+            missed: self.cumulative_missed.saturating_sub(base.missed),
+            counter_credits: self.cumulative_credits.saturating_sub(base.credits),
+            rooted_slots,
+            counter_expected: self
+                .credits_model
+                .expected_credits(self.cumulative_rooted_slots)
+                .saturating_sub(base.expected),
+        })
+    }
+
     /// Get epoch histogram
     pub fn epoch_histogram(&self) -> [u64; 17] {
         self.epoch_histogram
@@ -336,6 +1936,26 @@ impl VoteTracker {
         self.epoch_missed
     }
 
+    /// Missed credits this epoch during maintenance (not in [`Self::epoch_missed`])
+    pub fn epoch_missed_maintenance(&self) -> u64 {
+        self.epoch_missed_maintenance
+    }
+
+    /// The epoch's missed credits from the account at the current root and
+    /// accumulated update by update (None before a root is seen)
+    pub fn missed_accounting(&self) -> Option<MissedAccounting> {
+        let info = self.epoch_info?;
+        let root = self.prev_root_slot?;
+        Some(MissedAccounting {
+            snapshot: info
+                .expected_max_credits(root, &self.credits_model)
+                .saturating_sub(self.current_epoch_credits),
+            incremental: self.epoch_missed_untracked
+                + self.epoch_missed
+                + self.epoch_missed_maintenance,
+        })
+    }
+
     /// Calculate total votes in histogram
     pub fn histogram_total(hist: &[u64; 17]) -> u64 {
         hist.iter().sum()
@@ -364,12 +1984,12 @@ impl VoteTracker {
 
     /// Calculate efficiency from histogram (actual credits / max possible)
     /// This is a more accurate per-vote efficiency than epoch-level totals
-    pub fn histogram_efficiency(hist: &[u64; 17]) -> f64 {
+    pub fn histogram_efficiency(&self, hist: &[u64; 17]) -> f64 {
         let total_votes = Self::histogram_total(hist);
         if total_votes == 0 {
             return 0.0;
         }
-        let max_credits = total_votes * 16; // Max 16 credits per vote
+        let max_credits = total_votes * self.credits_model.max_credits_per_slot;
         let actual_credits: u64 = hist
             .iter()
             .enumerate()
@@ -392,16 +2012,16 @@ impl VoteTracker {
         total_credits as f64 / total_votes as f64
     }
 
-    /// Calculate average latency from histogram (17 - avg_credits)
-    /// Latency 1 = 16 credits (fastest), Latency 17 = 0 credits (slowest)
-    pub fn histogram_avg_latency(hist: &[u64; 17]) -> f64 {
-        (MAX_CREDITS_PER_SLOT as f64 + 1.0) - Self::histogram_avg_credits(hist)
+    /// Calculate average latency from histogram (max + 1 - avg_credits)
+    /// Under TVC latency 1 = 16 credits (fastest), latency 17 = 0 credits (slowest)
+    pub fn histogram_avg_latency(&self, hist: &[u64; 17]) -> f64 {
+        self.credits_model
+            .implied_latency(Self::histogram_avg_credits(hist))
     }
 
     /// Get total credits for a time window (from histogram)
     pub fn window_credits(&self, window_secs: u64) -> u64 {
-        let hist = self.window_histogram(window_secs);
-        Self::histogram_credits(&hist)
+        self.window_credits_and_missed(window_secs).0
     }
 
     /// Get expected max credits for a time window
@@ -409,16 +2029,84 @@ impl VoteTracker {
     pub fn window_expected(&self, window_secs: u64) -> u64 {
         // Window expected = window actual credits + window missed credits
         // This is more accurate than estimating from time
-        self.window_credits(window_secs) + self.window_missed(window_secs)
+        let (credits, missed) = self.window_credits_and_missed(window_secs);
+        credits + missed
     }
 
     /// Calculate efficiency for a time window
     pub fn window_efficiency(&self, window_secs: u64) -> f64 {
-        let expected = self.window_expected(window_secs);
+        let (credits, missed) = self.window_credits_and_missed(window_secs);
+        let expected = credits + missed;
         if expected == 0 {
             return 0.0;
         }
-        self.window_credits(window_secs) as f64 / expected as f64
+        credits as f64 / expected as f64
+    }
+
+    /// Histogram credits and missed credits of a window, both measured from
+    /// one window start so the clock can't move between them
+    pub fn window_credits_and_missed(&self, window_secs: u64) -> (u64, u64) {
+        if self.hist.is_empty() {
+            return (0, 0);
+        }
+        let base = self.window_base(window_secs);
+        (
+            self.window_credits_since(&base),
+            self.cumulative_missed.saturating_sub(base.missed),
+        )
+    }
+
+    /// Credits of the votes rooted since `base`
+    fn window_credits_since(&self, base: &WindowBase) -> u64 {
+        Self::histogram_credits(&self.cumulative_histogram).saturating_sub(base.histogram_credits)
+    }
+
+    /// Window efficiency with the votes on own leader slots and their expected
+    /// credits left out (None without a leader schedule for the current epoch)
+    pub fn window_efficiency_adjusted(&self, window_secs: u64) -> Option<f64> {
+        let (leader_credits, leader_missed) = self
+            .current_leader_slots()?
+            .window(self.window_start(window_secs));
+        let credits = self
+            .window_credits(window_secs)
+            .saturating_sub(leader_credits);
+        let missed = self
+            .window_missed(window_secs)
+            .saturating_sub(leader_missed);
+        let expected = credits + missed;
+        (expected > 0).then(|| credits as f64 / expected as f64)
+    }
+
+    /// Account the following updates under `regime`
+    pub fn set_fork_regime(&mut self, regime: ForkRegime) {
+        self.fork_regime = regime;
+    }
+
+    pub fn fork_regime(&self) -> ForkRegime {
+        self.fork_regime
+    }
+
+    /// Windowed credits and missed credits split by the fork pressure their
+    /// updates were accounted under
+    pub fn window_by_fork_regime(&self, window_secs: u64) -> [(ForkRegime, RegimeCredits); 2] {
+        let turbulent = self
+            .fork_regimes
+            .turbulent_since(self.window_start(window_secs));
+        let calm = RegimeCredits {
+            credits: self
+                .window_credits(window_secs)
+                .saturating_sub(turbulent.credits),
+            missed: self
+                .window_missed(window_secs)
+                .saturating_sub(turbulent.missed),
+        };
+        [(ForkRegime::Calm, calm), (ForkRegime::Turbulent, turbulent)]
+    }
+
+    /// Efficiency over the part of the epoch tracked, with own leader slots
+    /// left out (None without a leader schedule for the current epoch)
+    pub fn epoch_efficiency_adjusted(&self) -> Option<f64> {
+        self.current_leader_slots()?.epoch_efficiency()
     }
 
     // ============ Consistency verification methods ============
@@ -451,20 +2139,53 @@ impl VoteTracker {
 
 impl Default for VoteTracker {
     fn default() -> Self {
-        Self::new()
+        Self::new(CreditsModel::default())
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct UpdateResult {
     pub new_votes: u64,
+    /// Slots of the new votes, in slot order
+    pub new_vote_slots: Vec<u64>,
     pub missed_credits: u64,
+    /// Part of `missed_credits` missed during maintenance, kept out of the totals
+    pub maintenance_missed: u64,
+    /// Credits of the votes rooted by this update
     pub update_histogram: [u64; 17],
+    /// Summary of the previous epoch, set on the update that rolled the epoch
+    pub epoch_summary: Option<EpochSummary>,
+    /// Set when this update's votes look like the validator restarted
+    pub restart: Option<SuspectedRestart>,
+    /// Set when votes started or stopped landing without credits
+    pub credit_stall: Option<CreditStallChange>,
+    /// Rooted votes that were classified as catch-up when they landed
+    pub catch_up_votes: u64,
+    /// Votes discarded because they left the tower without being rooted
+    pub never_rooted: u64,
+    /// Window history entries dropped to stay under the state size cap
+    pub state_evictions: u64,
+    /// Wall time from first seeing each vote rooted by this update to its root
+    /// (millis), for votes seen within the last few minutes
+    pub root_delays_ms: Vec<u64>,
+    /// Slots rooted since the last root seen before a reconnect
+    pub unobserved: UnobservedSlots,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::ManualClock;
+    use crate::credits::{MAX_CREDITS_PER_SLOT, credits_from_latency};
+    use crate::metrics::MIN_WINDOW_COVERAGE;
+    use crate::ws::slots::SlotOutcome;
+    use std::time::Duration;
+
+    /// Tracker on a manual clock starting at an arbitrary wall-clock time
+    fn tracker_with_clock() -> (VoteTracker, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        (VoteTracker::default().with_clock(clock.clone()), clock)
+    }
 
     // Helper: simulate epoch_credits (credits earned this epoch)
     fn sim_epoch_credits(root_slot: u64, epoch_start: u64) -> u64 {
@@ -476,6 +2197,17 @@ mod tests {
         Some(epoch_start / SLOTS_PER_EPOCH)
     }
 
+    // Helper: move the root to root_slot, rooting the pending votes up to it
+    fn root_through(tracker: &mut VoteTracker, root_slot: u64, epoch_start: u64) -> UpdateResult {
+        tracker.process_update(
+            root_slot + 1,
+            &[],
+            Some(root_slot),
+            sim_epoch_credits(root_slot, epoch_start),
+            epoch_from_start(epoch_start),
+        )
+    }
+
     // ============ EpochInfo Tests ============
 
     #[test]
@@ -529,23 +2261,62 @@ mod tests {
         let info = EpochInfo::from_slot(SLOTS_PER_EPOCH + 100);
 
         // 101 slots * 16 credits/slot
-        let expected = info.expected_max_credits(SLOTS_PER_EPOCH + 100);
+        let expected = info.expected_max_credits(SLOTS_PER_EPOCH + 100, &CreditsModel::default());
         assert_eq!(expected, 101 * MAX_CREDITS_PER_SLOT);
     }
 
     // ============ VoteTracker Tests ============
 
     #[test]
-    fn test_vote_tracker_new() {
-        let tracker = VoteTracker::new();
-        assert!(tracker.prev_votes.is_empty());
+    fn test_tip_expectation() {
+        let info = EpochInfo::from_slot(SLOTS_PER_EPOCH + 99);
+        let model = CreditsModel::default();
+        let root = SLOTS_PER_EPOCH + 99;
+
+        let tip = TipExpectation::new(&info, root, root + 34, 4, 1500, &model);
+        assert_eq!(tip.anchor_slot, root + 30);
+        assert_eq!(tip.expected_max, 130 * 16);
+        assert_eq!(tip.provisional_missed, 130 * 16 - 1500);
+        assert_eq!(tip.pending_slots, 30);
+
+        // Never anchored behind the root...
+        let tip = TipExpectation::new(&info, root, root + 1, 4, 1500, &model);
+        assert_eq!((tip.anchor_slot, tip.pending_slots), (root, 0));
+        assert_eq!(tip.expected_max, info.expected_max_credits(root, &model));
+
+        // ...or past the end of the epoch
+        let tip = TipExpectation::new(&info, root, 3 * SLOTS_PER_EPOCH, 4, 0, &model);
+        assert_eq!(tip.anchor_slot, 2 * SLOTS_PER_EPOCH - 1);
+        assert_eq!(tip.expected_max, model.expected_credits(SLOTS_PER_EPOCH));
+    }
+
+    #[test]
+    fn test_tracker_tip_expectation_follows_context_slot() {
+        let mut tracker = VoteTracker::default().with_expected_anchor(ExpectedAnchor::Tip, 2);
+        assert_eq!(tracker.tip_expectation(), None);
+
+        let root = SLOTS_PER_EPOCH + 99;
+        tracker.process_update(root + 32, &[], Some(root), 1600, Some(1));
+        let tip = tracker.tip_expectation().unwrap();
+        assert_eq!(tip.anchor_slot, root + 30);
+        assert_eq!(tip.provisional_missed, 130 * 16 - 1600);
+
+        // Reset keeps the anchor settings
+        tracker.reset(1, root, 1600);
+        assert_eq!(tracker.expected_anchor(), ExpectedAnchor::Tip);
+    }
+
+    #[test]
+    fn test_vote_tracker_new() {
+        let tracker = VoteTracker::default();
+        assert!(tracker.pending_votes.is_empty());
         assert_eq!(tracker.epoch_histogram, [0; 17]);
         assert!(tracker.epoch_info.is_none());
     }
 
     #[test]
     fn test_credits_calculation_with_latency() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH; // Epoch 1
 
         // First update establishes baseline - latency=1 means 16 credits
@@ -558,7 +2329,8 @@ mod tests {
             epoch_from_start(epoch_start),
         );
         assert_eq!(result.new_votes, 1);
-        assert_eq!(result.update_histogram[16], 1); // latency=1 → 16 credits
+        assert_eq!(result.update_histogram, [0; 17]); // pending until rooted
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1000)].credits, 16); // latency=1
 
         // Add more votes with different latencies
         let votes = vec![
@@ -573,7 +2345,8 @@ mod tests {
             epoch_from_start(epoch_start),
         );
         assert_eq!(result.new_votes, 1);
-        assert_eq!(result.update_histogram[15], 1); // 15 credits
+        assert_eq!(result.update_histogram[16], 1); // slot 1000 rooted
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1001)].credits, 15);
 
         // Test with latency=3 → 14 credits
         let votes = vec![
@@ -589,12 +2362,13 @@ mod tests {
             epoch_from_start(epoch_start),
         );
         assert_eq!(result.new_votes, 1);
-        assert_eq!(result.update_histogram[14], 1); // latency=3 → 14 credits
+        assert_eq!(result.update_histogram[15], 1); // slot 1001 rooted
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1002)].credits, 14); // latency=3
     }
 
     #[test]
     fn test_credits_calculation_fallback() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // Test fallback when latency is None (use context_slot - vote_slot)
@@ -607,7 +2381,7 @@ mod tests {
             epoch_from_start(epoch_start),
         );
         assert_eq!(result.new_votes, 1);
-        assert_eq!(result.update_histogram[16], 1); // gap=0 → 16 credits
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1000)].credits, 16); // gap=0
 
         // Vote for slot 1001 seen at context slot 1003 (gap=2)
         let votes = vec![(epoch_start + 1000, 2, None), (epoch_start + 1001, 1, None)];
@@ -619,12 +2393,13 @@ mod tests {
             epoch_from_start(epoch_start),
         );
         assert_eq!(result.new_votes, 1);
-        assert_eq!(result.update_histogram[14], 1); // gap=2 → 14 credits
+        assert_eq!(result.update_histogram[16], 1); // slot 1000 rooted
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1001)].credits, 14); // gap=2
     }
 
     #[test]
     fn test_epoch_reset() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch1_start = SLOTS_PER_EPOCH;
         let epoch2_start = 2 * SLOTS_PER_EPOCH;
 
@@ -674,6 +2449,420 @@ mod tests {
         assert_eq!(tracker.epoch_info.unwrap().epoch, 2);
     }
 
+    #[test]
+    fn test_root_in_next_epoch_does_not_reset_early() {
+        let mut tracker = VoteTracker::default();
+        let epoch1_start = SLOTS_PER_EPOCH;
+        let epoch2_start = 2 * SLOTS_PER_EPOCH;
+        let last_slot = epoch2_start - 1;
+
+        tracker.process_update(
+            last_slot,
+            &[(last_slot - 1, 1, Some(1))],
+            Some(last_slot - 2),
+            1600,
+            Some(1),
+        );
+        let votes = [(last_slot - 1, 2, Some(1)), (last_slot, 1, Some(1))];
+        tracker.process_update(last_slot + 1, &votes, Some(last_slot - 2), 1600, Some(1));
+        assert!(!tracker.epoch_source_disagreement());
+
+        // The root crosses the boundary while epochCredits still has epoch 1
+        let result = tracker.process_update(
+            epoch2_start + 2,
+            &[(epoch2_start + 1, 1, Some(1))],
+            Some(epoch2_start),
+            1632,
+            Some(1),
+        );
+        assert!(result.epoch_summary.is_none());
+        assert!(tracker.epoch_source_disagreement());
+        assert_eq!(tracker.epoch_histogram.iter().sum::<u64>(), 2);
+        let info = tracker.epoch_info.unwrap();
+        assert_eq!((info.epoch, info.slot_index), (1, SLOTS_PER_EPOCH - 1));
+        assert_eq!(info.epoch_start_slot, epoch1_start);
+
+        // The reset waits for the epochCredits entry to roll
+        let result = tracker.process_update(
+            epoch2_start + 3,
+            &[(epoch2_start + 2, 1, Some(1))],
+            Some(epoch2_start + 1),
+            16,
+            Some(2),
+        );
+        assert_eq!(result.epoch_summary.map(|summary| summary.epoch), Some(1));
+        assert!(!tracker.epoch_source_disagreement());
+        assert_eq!(tracker.epoch_histogram.iter().sum::<u64>(), 1);
+        assert_eq!(tracker.epoch_info.unwrap().slot_index, 1);
+    }
+
+    #[test]
+    fn test_reconnect_gap_is_counted_as_unobserved() {
+        let mut tracker = VoteTracker::default();
+        let root = SLOTS_PER_EPOCH + 1000;
+        tracker.process_update(root + 1, &[], Some(root), 1600, Some(1));
+        assert_eq!(tracker.unobserved(), UnobservedSlots::default());
+
+        // Reconnecting twice before a root is seen again covers one range
+        tracker.mark_disconnected();
+        tracker.mark_disconnected();
+        let result = tracker.process_update(root + 1001, &[], Some(root + 1000), 1600, Some(1));
+        let gap = UnobservedSlots {
+            rooted_slots: 1000,
+            expected_credits: 16_000,
+        };
+        assert_eq!(result.unobserved, gap);
+        assert_eq!(tracker.unobserved(), gap);
+
+        // Updates on the new connection add nothing
+        let result = tracker.process_update(root + 1002, &[], Some(root + 1001), 1616, Some(1));
+        assert_eq!(result.unobserved, UnobservedSlots::default());
+        assert_eq!(tracker.unobserved(), gap);
+
+        let next_root = 2 * SLOTS_PER_EPOCH + 10;
+        let result = tracker.process_update(next_root + 1, &[], Some(next_root), 16, Some(2));
+        assert_eq!(result.epoch_summary.unwrap().unobserved, Some(gap));
+        tracker.process_update(next_root + 2, &[], Some(next_root + 1), 32, Some(2));
+        tracker.mark_disconnected();
+        let result = tracker.process_update(next_root + 3, &[], Some(next_root + 2), 48, Some(2));
+        assert_eq!(result.unobserved.rooted_slots, 1);
+        assert_eq!(tracker.unobserved().rooted_slots, 1001);
+    }
+
+    #[test]
+    fn test_missed_accounting_loses_a_reconnect_without_root() {
+        let mut tracker = VoteTracker::default();
+        assert_eq!(tracker.missed_accounting(), None);
+
+        // Starting mid-epoch, the misses before the first root count for both
+        let root = SLOTS_PER_EPOCH + 1000;
+        tracker.process_update(root + 1, &[], Some(root), 15_000, Some(1));
+        let start = tracker.missed_accounting().unwrap();
+        assert!(start.snapshot > 0);
+        assert_eq!(start.skew(), 0);
+        assert_eq!(start.skew_ratio(), 0.0);
+        tracker.process_update(root + 11, &[], Some(root + 10), 15_100, Some(1));
+        let tracked = tracker.missed_accounting().unwrap();
+        assert_eq!(tracked.snapshot, start.snapshot + 60);
+        assert_eq!(tracked.skew(), 0);
+
+        // The first notification after resubscribing has no root: the 100
+        // slots rooted across the gap, 600 credits short, are never compared
+        tracker.mark_disconnected();
+        tracker.process_update(root + 112, &[], None, 16_100, Some(1));
+        tracker.process_update(root + 111, &[], Some(root + 110), 16_100, Some(1));
+        let accounting = tracker.missed_accounting().unwrap();
+        assert_eq!(accounting.snapshot, tracked.snapshot + 600);
+        assert_eq!(accounting.incremental, tracked.snapshot);
+        assert_eq!(accounting.skew(), 600);
+        assert_eq!(accounting.skew_ratio(), 600.0 / accounting.snapshot as f64);
+
+        // A new epoch starts both counts over
+        let next_root = 2 * SLOTS_PER_EPOCH + 10;
+        tracker.process_update(next_root + 1, &[], Some(next_root), 100, Some(2));
+        assert_eq!(tracker.missed_accounting().unwrap().skew(), 0);
+    }
+
+    #[test]
+    fn test_window_is_split_by_fork_regime() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let root = SLOTS_PER_EPOCH + 1000;
+        let mut credits = 16_016;
+        tracker.process_update(root + 33, &[], Some(root), credits, Some(1));
+        // Calm: 10 slots and 160 credits per update
+        for i in 1..=5 {
+            clock.advance_secs(4);
+            credits += 160;
+            let root = root + 10 * i;
+            tracker.process_update(root + 33, &[], Some(root), credits, Some(1));
+        }
+        // Turbulent: 60 of the 160 missed per update
+        tracker.set_fork_regime(ForkRegime::Turbulent);
+        for i in 6..=8 {
+            clock.advance_secs(4);
+            credits += 100;
+            let root = root + 10 * i;
+            tracker.process_update(root + 33, &[], Some(root), credits, Some(1));
+        }
+        assert_eq!(tracker.fork_regime(), ForkRegime::Turbulent);
+
+        let [(calm_regime, calm), (turbulent_regime, turbulent)] =
+            tracker.window_by_fork_regime(300);
+        assert_eq!(
+            (calm_regime, turbulent_regime),
+            (ForkRegime::Calm, ForkRegime::Turbulent)
+        );
+        assert_eq!(calm.missed, 0);
+        assert_eq!(turbulent.missed, 180);
+        assert_eq!(calm.missed + turbulent.missed, tracker.window_missed(300));
+
+        // A window starting after the first turbulent update holds the other two
+        let [(_, calm), (_, turbulent)] = tracker.window_by_fork_regime(7);
+        assert_eq!((calm.missed, turbulent.missed), (0, 120));
+    }
+
+    #[test]
+    fn test_history_without_credits_counts_credits_from_restore() {
+        let (mut before, clock) = tracker_with_clock();
+        let root = SLOTS_PER_EPOCH + 1000;
+        before.process_update(root + 1, &[], Some(root), 16_016, Some(1));
+        before.process_update(root + 11, &[], Some(root + 10), 16_176, Some(1));
+        let saved = before.baseline();
+        // As saved by a version without the credits column
+        let mut history = before.history();
+        history.credits.clear();
+        history.cumulative_credits = 0;
+
+        clock.advance_secs(600);
+        let mut tracker = VoteTracker::default().with_clock(clock.clone());
+        tracker.restore_history(history);
+        tracker
+            .apply_offline_gap(&saved, 1, root + 1010, 16_176 + 15_000, None)
+            .unwrap();
+        // Only the gap counts as gained within the history
+        let tracked = saved.tracked_credits;
+        assert_eq!(
+            tracker.hist_credits,
+            VecDeque::from([tracked, tracked, tracked + 15_000])
+        );
+    }
+
+    #[test]
+    fn test_offline_gap_within_epoch() {
+        let (mut before, clock) = tracker_with_clock();
+        let root = SLOTS_PER_EPOCH + 1000;
+        before.process_update(root + 1, &[], Some(root), 16_016, Some(1));
+        before.process_update(root + 11, &[], Some(root + 10), 16_176, Some(1));
+        let saved = before.baseline();
+
+        // Down for 10 minutes, 1000 slots rooted and 1000 credits missed
+        clock.advance_secs(600);
+        let mut tracker = VoteTracker::default().with_clock(clock.clone());
+        tracker.restore_history(before.history());
+        let root = root + 1010;
+        let gap = tracker
+            .apply_offline_gap(&saved, 1, root, 16_176 + 15_000, None)
+            .unwrap();
+        assert_eq!(
+            gap,
+            OfflineGap {
+                rooted_slots: 1000,
+                credits: 15_000,
+                expected_credits: 16_000,
+            }
+        );
+        assert_eq!(tracker.offline(), gap);
+        let baseline = tracker.baseline();
+        assert_eq!(baseline.root_slot, Some(root));
+        assert_eq!(baseline.epoch_missed, saved.epoch_missed + 1000);
+        assert_eq!(baseline.tracked_credits, saved.tracked_credits + 15_000);
+        assert_eq!(baseline.tracked_missed, 1000);
+        assert_eq!(tracker.window_missed(3600), 1000);
+        // The hour also covers the 10 slots rooted before the save
+        assert_eq!(tracker.window_rooted_slots(3600), 1010);
+        assert_eq!(tracker.cumulative_histogram, before.cumulative_histogram);
+        let mut credits = before.hist_credits.clone();
+        credits.push_back(baseline.tracked_credits);
+        assert_eq!(tracker.hist_credits, credits);
+
+        // Updates carry on from the caught-up root
+        tracker.process_update(root + 11, &[], Some(root + 10), 16_176 + 15_160, Some(1));
+        assert_eq!(tracker.baseline().epoch_missed, saved.epoch_missed + 1000);
+        assert!(
+            tracker
+                .apply_offline_gap(&saved, 1, root + 10, 31_336, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_offline_gap_across_epoch_boundary() {
+        let mut before = VoteTracker::default();
+        let root = 2 * SLOTS_PER_EPOCH - 1001;
+        before.process_update(root + 1, &[], Some(root), 100_000, Some(1));
+        let saved = before.baseline();
+
+        // 1000 slots left in epoch 1 (200 credits missed), 500 in epoch 2 (50 missed)
+        let mut tracker = VoteTracker::default();
+        let root = 2 * SLOTS_PER_EPOCH + 499;
+        assert!(
+            tracker
+                .apply_offline_gap(&saved, 2, root, 7_950, None)
+                .is_err()
+        );
+        let gap = tracker
+            .apply_offline_gap(&saved, 2, root, 7_950, Some(100_000 + 15_800))
+            .unwrap();
+        assert_eq!(gap.rooted_slots, 1500);
+        assert_eq!(gap.credits, 15_800 + 7_950);
+        assert_eq!(gap.missed(), 250);
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.epoch_info.unwrap().epoch, 2);
+        assert_eq!(snapshot.first_epoch, Some(1));
+        assert_eq!(snapshot.epoch_missed, 50);
+        assert_eq!(snapshot.current_epoch_credits, 7_950);
+        assert_eq!(snapshot.tracked_missed, 250);
+    }
+
+    #[test]
+    fn test_offline_gap_too_large_is_refused() {
+        let mut before = VoteTracker::default();
+        let root = SLOTS_PER_EPOCH + 1000;
+        before.process_update(root + 1, &[], Some(root), 16_016, Some(1));
+        let saved = before.baseline();
+
+        let mut tracker = VoteTracker::default();
+        let root = 3 * SLOTS_PER_EPOCH + 10;
+        let err = tracker
+            .apply_offline_gap(&saved, 3, root, 176, Some(6_900_000))
+            .unwrap_err();
+        assert!(err.to_string().contains("2 epoch boundaries"));
+        // A root behind the state file is refused too
+        assert!(
+            tracker
+                .apply_offline_gap(&saved, 1, SLOTS_PER_EPOCH + 10, 176, None)
+                .is_err()
+        );
+        assert_eq!(tracker.baseline(), TrackerBaseline::default());
+        assert_eq!(tracker.offline(), OfflineGap::default());
+        assert!(tracker.history().entries.is_empty());
+    }
+
+    #[test]
+    fn test_perfect_slot_streak_breaks_and_resumes() {
+        let mut tracker = VoteTracker::default();
+        let epoch1_start = SLOTS_PER_EPOCH;
+        let update = |tracker: &mut VoteTracker, root: u64, credits: u64, epoch: u64| {
+            tracker.process_update(root + 1, &[], Some(root), credits, Some(epoch))
+        };
+
+        update(&mut tracker, epoch1_start + 100, 1_600, 1);
+        update(&mut tracker, epoch1_start + 110, 1_760, 1);
+        update(&mut tracker, epoch1_start + 115, 1_840, 1);
+        assert_eq!(tracker.perfect_slot_streak().current, 15);
+
+        // 10 credits short over 5 slots
+        update(&mut tracker, epoch1_start + 120, 1_910, 1);
+        let streak = tracker.perfect_slot_streak();
+        assert_eq!((streak.current, streak.epoch_max), (0, 15));
+
+        update(&mut tracker, epoch1_start + 140, 2_230, 1);
+        let streak = tracker.perfect_slot_streak();
+        assert_eq!((streak.current, streak.epoch_max), (20, 20));
+
+        // Rollover: the summary keeps the finished epoch's streaks
+        let epoch2_start = 2 * SLOTS_PER_EPOCH;
+        let result = update(&mut tracker, epoch2_start + 10, 176, 2);
+        assert_eq!(
+            result.epoch_summary.unwrap().perfect_slot_streak,
+            Some(PerfectSlotStreak {
+                epoch: 1,
+                current: 20,
+                epoch_max: 20,
+            })
+        );
+        assert_eq!(
+            tracker.perfect_slot_streak(),
+            PerfectSlotStreak {
+                epoch: 2,
+                current: 0,
+                epoch_max: 0,
+            }
+        );
+
+        update(&mut tracker, epoch2_start + 13, 224, 2);
+        let streak = tracker.perfect_slot_streak();
+        assert_eq!((streak.current, streak.epoch_max), (3, 3));
+    }
+
+    #[test]
+    fn test_recent_slots_from_rooted_votes() {
+        let mut tracker = VoteTracker::default();
+        let start = SLOTS_PER_EPOCH + 100;
+        let votes = [(start + 1, 2, Some(1)), (start + 3, 1, Some(4))];
+        tracker.process_update(start + 4, &votes, Some(start), 1_600, Some(1));
+        assert!(tracker.recent_slots().is_empty());
+
+        // Both votes rooted, nothing on start + 2: 16 + 13 credits
+        tracker.process_update(start + 5, &[], Some(start + 3), 1_629, Some(1));
+        let outcomes = |tracker: &VoteTracker| -> Vec<(u64, SlotOutcome)> {
+            tracker
+                .recent_slots()
+                .newest(usize::MAX)
+                .iter()
+                .map(|record| (record.slot, record.outcome))
+                .collect()
+        };
+        assert_eq!(
+            outcomes(&tracker),
+            vec![
+                (start + 3, SlotOutcome::Late { credits: 13 }),
+                (start + 2, SlotOutcome::Missed),
+                (start + 1, SlotOutcome::Earned { credits: 16 }),
+            ]
+        );
+
+        // Credits from votes never seen: the range can't be attributed
+        tracker.process_update(start + 6, &[], Some(start + 5), 1_661, Some(1));
+        assert_eq!(
+            outcomes(&tracker)[..2],
+            [
+                (start + 5, SlotOutcome::Partial),
+                (start + 4, SlotOutcome::Partial),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restored_perfect_slot_streak() {
+        let epoch_start = 5 * SLOTS_PER_EPOCH;
+        let saved = PerfectSlotStreak {
+            epoch: 5,
+            current: 40,
+            epoch_max: 90,
+        };
+
+        // Restarted in the same epoch: the streak carries on
+        let mut tracker = VoteTracker::default();
+        tracker.restore_perfect_slot_streak(saved);
+        tracker.process_update(
+            epoch_start + 501,
+            &[],
+            Some(epoch_start + 500),
+            8_016,
+            Some(5),
+        );
+        tracker.process_update(
+            epoch_start + 511,
+            &[],
+            Some(epoch_start + 510),
+            8_176,
+            Some(5),
+        );
+        let streak = tracker.perfect_slot_streak();
+        assert_eq!((streak.current, streak.epoch_max), (50, 90));
+
+        // Restarted after the epoch ended: the saved streak is dropped
+        let mut tracker = VoteTracker::default();
+        tracker.restore_perfect_slot_streak(saved);
+        tracker.process_update(
+            epoch_start + SLOTS_PER_EPOCH + 11,
+            &[],
+            Some(epoch_start + SLOTS_PER_EPOCH + 10),
+            176,
+            Some(6),
+        );
+        assert_eq!(
+            tracker.perfect_slot_streak(),
+            PerfectSlotStreak {
+                epoch: 6,
+                current: 0,
+                epoch_max: 0,
+            }
+        );
+    }
+
     #[test]
     fn test_histogram_fractions() {
         let hist = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 20, 70];
@@ -705,7 +2894,7 @@ mod tests {
 
     #[test]
     fn test_window_histogram() {
-        let mut tracker = VoteTracker::new();
+        let (mut tracker, clock) = tracker_with_clock();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // First update
@@ -719,6 +2908,7 @@ mod tests {
         );
 
         // Second update
+        clock.advance_secs(10);
         let votes = vec![
             (epoch_start + 1000, 2, Some(1)),
             (epoch_start + 1001, 1, Some(2)), // 15 credits (new)
@@ -732,6 +2922,7 @@ mod tests {
         );
 
         // Third update
+        clock.advance_secs(10);
         let votes = vec![
             (epoch_start + 1000, 3, Some(1)),
             (epoch_start + 1001, 2, Some(2)),
@@ -745,6 +2936,10 @@ mod tests {
             epoch_from_start(epoch_start),
         );
 
+        // Each vote reaches the histograms in the update that roots it
+        clock.advance_secs(10);
+        root_through(&mut tracker, epoch_start + 1002, epoch_start);
+
         // Check cumulative histogram
         assert_eq!(tracker.cumulative_histogram[16], 2); // Two votes at 16 credits
         assert_eq!(tracker.cumulative_histogram[15], 1); // One vote at 15 credits
@@ -754,23 +2949,30 @@ mod tests {
         assert_eq!(hist[15], 1);
 
         assert_eq!(hist, tracker.epoch_histogram());
+
+        // 285s later only the first root (20s before the last) is out of the 5m window
+        clock.advance_secs(285);
+        let hist = tracker.window_histogram(300);
+        assert_eq!(hist[16], 1);
+        assert_eq!(hist[15], 1);
+        assert_eq!(tracker.window_histogram(3600), tracker.epoch_histogram());
     }
 
     #[test]
     fn test_latency_to_credits_edge_cases() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // Test latency = 17 (0 credits - very slow)
         let votes = vec![(epoch_start + 1000, 1, Some(17))];
-        let result = tracker.process_update(
+        tracker.process_update(
             epoch_start + 1000,
             &votes,
             Some(epoch_start + 999),
             sim_epoch_credits(epoch_start + 999, epoch_start),
             epoch_from_start(epoch_start),
         );
-        assert_eq!(result.update_histogram[0], 1);
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1000)].credits, 0);
 
         // Test latency = 1 (16 credits - fastest)
         let votes = vec![
@@ -784,10 +2986,10 @@ mod tests {
             sim_epoch_credits(epoch_start + 1000, epoch_start),
             epoch_from_start(epoch_start),
         );
-        assert_eq!(result.update_histogram[16], 1);
+        assert_eq!(result.update_histogram[0], 1);
+        assert_eq!(tracker.pending_votes[&(epoch_start + 1001)].credits, 16);
 
         // Test latency > 17 (should still be 0 credits)
-        tracker.prev_votes.clear();
         let votes = vec![(epoch_start + 2000, 1, Some(100))];
         let result = tracker.process_update(
             epoch_start + 2000,
@@ -796,12 +2998,13 @@ mod tests {
             sim_epoch_credits(epoch_start + 1999, epoch_start),
             epoch_from_start(epoch_start),
         );
-        assert_eq!(result.update_histogram[0], 1);
+        assert_eq!(result.update_histogram[16], 1);
+        assert_eq!(tracker.pending_votes[&(epoch_start + 2000)].credits, 0);
     }
 
     #[test]
     fn test_multiple_updates_accumulate() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // First batch
@@ -813,7 +3016,7 @@ mod tests {
             sim_epoch_credits(epoch_start + 999, epoch_start),
             epoch_from_start(epoch_start),
         );
-        assert_eq!(tracker.cumulative_histogram[16], 1);
+        assert_eq!(tracker.cumulative_histogram[16], 0);
 
         // Second batch
         let votes = vec![
@@ -827,7 +3030,7 @@ mod tests {
             sim_epoch_credits(epoch_start + 1000, epoch_start),
             epoch_from_start(epoch_start),
         );
-        assert_eq!(tracker.cumulative_histogram[16], 2);
+        assert_eq!(tracker.cumulative_histogram[16], 1);
 
         // Third batch
         let votes = vec![
@@ -842,6 +3045,9 @@ mod tests {
             sim_epoch_credits(epoch_start + 1001, epoch_start),
             epoch_from_start(epoch_start),
         );
+        assert_eq!(tracker.cumulative_histogram[16], 2);
+
+        root_through(&mut tracker, epoch_start + 1002, epoch_start);
         assert_eq!(tracker.cumulative_histogram[16], 3);
 
         assert_eq!(
@@ -852,7 +3058,7 @@ mod tests {
 
     #[test]
     fn test_duplicate_votes_not_counted() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // First update
@@ -867,7 +3073,7 @@ mod tests {
             sim_epoch_credits(epoch_start + 999, epoch_start),
             epoch_from_start(epoch_start),
         );
-        assert_eq!(tracker.cumulative_histogram[16], 2);
+        assert_eq!(tracker.pending_votes.len(), 2);
 
         // Same votes again (should not be counted again)
         let votes = vec![
@@ -882,12 +3088,93 @@ mod tests {
             epoch_from_start(epoch_start),
         );
         assert_eq!(result.new_votes, 0);
+        assert_eq!(tracker.cumulative_histogram[16], 1); // slot 1000 rooted
+
+        // Still in the tower after the root passed it: rooted once
+        let votes = vec![(epoch_start + 1001, 3, Some(1))];
+        let result = tracker.process_update(
+            epoch_start + 1003,
+            &votes,
+            Some(epoch_start + 1001),
+            sim_epoch_credits(epoch_start + 1001, epoch_start),
+            epoch_from_start(epoch_start),
+        );
+        assert_eq!(result.new_votes, 0);
+        assert_eq!(tracker.cumulative_histogram[16], 2);
+        let result = root_through(&mut tracker, epoch_start + 1002, epoch_start);
+        assert_eq!(result.update_histogram, [0; 17]);
         assert_eq!(tracker.cumulative_histogram[16], 2); // Still 2
     }
 
+    #[test]
+    fn test_abandoned_fork_votes_never_rooted() {
+        let mut tracker = VoteTracker::default();
+        let epoch_start = SLOTS_PER_EPOCH;
+        let slot = |offset: u64| epoch_start + 1000 + offset;
+        let update = |tracker: &mut VoteTracker, votes: &[u64], root: u64| {
+            let votes: Vec<_> = votes.iter().map(|v| (slot(*v), 1, Some(1))).collect();
+            tracker.process_update(
+                slot(3),
+                &votes,
+                Some(slot(root)),
+                sim_epoch_credits(slot(root), epoch_start),
+                epoch_from_start(epoch_start),
+            )
+        };
+
+        // Two banks for the same context slot disagree about the vote on slot 1
+        assert_eq!(update(&mut tracker, &[0, 1], 0).new_votes, 1);
+        assert_eq!(update(&mut tracker, &[0, 2], 0).new_votes, 1);
+        // Flipping back doesn't count slot 1 again
+        assert_eq!(update(&mut tracker, &[0, 1], 0).new_votes, 0);
+
+        // The fork without slot 1 wins and gets rooted
+        update(&mut tracker, &[0, 2, 3], 0);
+        let result = update(&mut tracker, &[3], 2);
+        assert_eq!(result.never_rooted, 1);
+        assert_eq!(VoteTracker::histogram_total(&result.update_histogram), 1);
+        assert_eq!(VoteTracker::histogram_total(&tracker.epoch_histogram()), 1);
+        assert_eq!(
+            VoteTracker::histogram_total(&tracker.window_histogram(3600)),
+            1
+        );
+        assert!(tracker.pending_votes.keys().eq([slot(3)].iter()));
+    }
+
+    #[test]
+    fn test_dropped_vote_discarded_after_bound() {
+        let mut tracker = VoteTracker::default();
+        let epoch_start = SLOTS_PER_EPOCH;
+        let root = epoch_start + 999;
+        let vote = epoch_start + 1000;
+
+        tracker.process_update(vote, &[(vote, 1, Some(1))], Some(root), 16, Some(1));
+        // The vote drops out of the tower while the root is stuck
+        let result = tracker.process_update(
+            vote + NEVER_ROOTED_BOUND_SLOTS,
+            &[(vote + 1, 1, Some(1))],
+            Some(root),
+            16,
+            Some(1),
+        );
+        assert_eq!(result.never_rooted, 0);
+        assert!(tracker.pending_votes.contains_key(&vote));
+
+        let result = tracker.process_update(
+            vote + NEVER_ROOTED_BOUND_SLOTS + 1,
+            &[(vote + 1, 2, Some(1))],
+            Some(root),
+            16,
+            Some(1),
+        );
+        assert_eq!(result.never_rooted, 1);
+        assert!(!tracker.pending_votes.contains_key(&vote));
+        assert!(tracker.pending_votes.contains_key(&(vote + 1)));
+    }
+
     #[test]
     fn test_fresh_tracker_1h_equals_epoch() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         let votes: Vec<(u64, u32, Option<u32>)> = vec![
@@ -904,6 +3191,7 @@ mod tests {
             sim_epoch_credits(epoch_start + 999, epoch_start),
             epoch_from_start(epoch_start),
         );
+        root_through(&mut tracker, epoch_start + 1004, epoch_start);
 
         let hist_1h = tracker.window_histogram(3600);
         let hist_epoch = tracker.epoch_histogram();
@@ -927,7 +3215,7 @@ mod tests {
 
     #[test]
     fn test_missed_credits_tracking() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // First update: establishes baseline, no missed credits yet
@@ -977,9 +3265,148 @@ mod tests {
         assert_eq!(tracker.epoch_missed, 16);
     }
 
+    #[test]
+    fn test_missed_credits_without_tvc() {
+        // Pre-TVC clusters pay 1 credit per rooted slot
+        let mut tracker = VoteTracker::new(CreditsModel::new(1));
+        let epoch_start = SLOTS_PER_EPOCH;
+
+        tracker.process_update(
+            epoch_start + 1000,
+            &[(epoch_start + 1000, 1, None)],
+            Some(epoch_start + 999),
+            1000,
+            Some(1),
+        );
+        // 10 slots rooted, 8 credits earned: 2 missed
+        let result = tracker.process_update(
+            epoch_start + 1010,
+            &[(epoch_start + 1010, 1, None)],
+            Some(epoch_start + 1009),
+            1008,
+            Some(1),
+        );
+        assert_eq!(result.missed_credits, 2);
+        assert_eq!(tracker.epoch_expected_max(), 11);
+    }
+
+    #[test]
+    fn test_hourly_profile_crosses_hour_boundary() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let epoch_start = SLOTS_PER_EPOCH;
+        let update = |tracker: &mut VoteTracker, root: u64, credits: u64| {
+            tracker.process_update(
+                epoch_start + root + 1,
+                &[],
+                Some(epoch_start + root),
+                credits,
+                Some(1),
+            );
+        };
+
+        // 2024-01-01 13:59:00 UTC: baseline, then 10 perfect slots
+        clock.set(1_704_117_540_000);
+        update(&mut tracker, 1000, 16_016);
+        clock.advance_secs(30);
+        update(&mut tracker, 1010, 16_176);
+        // Past 14:00 half the expected credits are missed
+        clock.advance_secs(40);
+        update(&mut tracker, 1020, 16_256);
+
+        let now = tracker.now_millis();
+        assert_eq!(tracker.hourly_profile().efficiency(13, now), Some(1.0));
+        assert_eq!(tracker.hourly_profile().efficiency(14, now), Some(0.5));
+
+        // The profile outlives a reset
+        tracker.reset(1, epoch_start + 1020, 16_256);
+        assert_eq!(tracker.hourly_profile().efficiency(14, now), Some(0.5));
+    }
+
+    #[test]
+    fn test_leader_adjusted_efficiency() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let epoch_start = SLOTS_PER_EPOCH;
+        // Every 20th slot is an own leader slot (5%), on which the validator
+        // doesn't vote; all other votes land with max credits
+        let is_leader = |slot: u64| slot % 20 == 0;
+        let leader_indices: Vec<u64> = (0..SLOTS_PER_EPOCH).filter(|i| is_leader(*i)).collect();
+        let earned = |root: u64| (1..=root).filter(|s| !is_leader(*s)).count() as u64 * 16;
+
+        for step in 0..100 {
+            let root = 1000 + step * 20;
+            let votes: Vec<(u64, u32, Option<u32>)> = (root + 1..=root + 20)
+                .filter(|s| !is_leader(*s))
+                .map(|s| (epoch_start + s, 1, Some(1)))
+                .collect();
+            tracker.process_update(
+                epoch_start + root + 21,
+                &votes,
+                Some(epoch_start + root),
+                earned(root),
+                Some(1),
+            );
+            if step == 0 {
+                assert_eq!(tracker.window_efficiency_adjusted(300), None);
+                tracker.set_leader_slots(1, &leader_indices);
+            }
+            clock.advance_secs(10);
+        }
+
+        assert_eq!(tracker.leader_slot_count(), Some(leader_indices.len()));
+        assert!((tracker.window_efficiency(300) - 0.95).abs() < 1e-12);
+        assert_eq!(tracker.window_efficiency_adjusted(300), Some(1.0));
+        assert_eq!(tracker.window_efficiency_adjusted(3600), Some(1.0));
+        assert_eq!(tracker.epoch_efficiency_adjusted(), Some(1.0));
+
+        // A schedule for another epoch isn't applied
+        tracker.set_leader_slots(2, &leader_indices);
+        assert_eq!(tracker.window_efficiency_adjusted(300), None);
+        assert_eq!(tracker.leader_slot_count(), None);
+    }
+
+    #[test]
+    fn test_reset_reseeds_from_snapshot() {
+        let mut tracker = VoteTracker::default();
+        let epoch_start = SLOTS_PER_EPOCH;
+        tracker.process_update(
+            epoch_start + 1000,
+            &[(epoch_start + 1000, 1, Some(1))],
+            Some(epoch_start + 999),
+            16_000,
+            Some(1),
+        );
+        tracker.cumulative_missed = 500;
+
+        // 200 slots rooted in epoch 2, 3000 of 3200 credits earned
+        tracker.reset(2, 2 * SLOTS_PER_EPOCH + 199, 3000);
+        let baseline = tracker.baseline();
+        assert_eq!(baseline.epoch, Some(2));
+        assert_eq!(baseline.epoch_credits, 3000);
+        assert_eq!(baseline.epoch_missed, 200);
+        assert_eq!(baseline.tracked_missed, 0);
+        assert_eq!(tracker.epoch_histogram(), [0; 17]);
+    }
+
+    #[test]
+    fn test_reconcile_only_applies_fresher_snapshot() {
+        let mut tracker = VoteTracker::default();
+        tracker.reset(2, 2 * SLOTS_PER_EPOCH + 199, 3000);
+
+        // Lagging RPC or another epoch: nothing changes
+        assert!(!tracker.reconcile(2, 2 * SLOTS_PER_EPOCH + 100, 1000));
+        assert!(!tracker.reconcile(3, 3 * SLOTS_PER_EPOCH + 300, 1000));
+        assert_eq!(tracker.baseline().epoch_credits, 3000);
+
+        assert!(tracker.reconcile(2, 2 * SLOTS_PER_EPOCH + 299, 4500));
+        let baseline = tracker.baseline();
+        assert_eq!(baseline.root_slot, Some(2 * SLOTS_PER_EPOCH + 299));
+        assert_eq!(baseline.epoch_credits, 4500);
+        assert_eq!(baseline.epoch_missed, 300 * 16 - 4500);
+    }
+
     #[test]
     fn test_window_missed() {
-        let mut tracker = VoteTracker::new();
+        let (mut tracker, clock) = tracker_with_clock();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // Establish baseline
@@ -1016,13 +3443,271 @@ mod tests {
 
         // For a fresh tracker, 5m missed should equal epoch missed
         assert_eq!(missed_5m, missed_epoch);
+
+        // Once the update is older than the window it no longer counts
+        clock.advance_secs(301);
+        assert_eq!(tracker.window_missed(300), 0);
+        assert_eq!(tracker.window_missed(3600), missed_epoch);
+    }
+
+    /// Updates every 4 minutes voting on the next 40 slots each, every other
+    /// update with a few slow votes; returns the credits gained
+    fn sparse_updates(tracker: &mut VoteTracker, clock: &ManualClock, updates: u64) -> u64 {
+        let start = SLOTS_PER_EPOCH + 1000;
+        let mut credits = 0;
+        for i in 0..updates {
+            let root = start + 40 * i;
+            let votes: Vec<_> = (1..=40)
+                .map(|j| (root + j, 1, Some(1 + ((i % 2) * (j % 4)) as u32)))
+                .collect();
+            tracker.process_update(root + 41, &votes, Some(root), credits, Some(1));
+            credits += votes
+                .iter()
+                .map(|(_, _, latency)| credits_from_latency(latency.unwrap()))
+                .sum::<u64>();
+            clock.advance_secs(240);
+        }
+        credits
+    }
+
+    #[test]
+    fn test_efficiency_cross_check_agrees_across_sparse_updates() {
+        let (mut tracker, clock) = tracker_with_clock();
+        assert_eq!(tracker.window_cross_check(300), None);
+        sparse_updates(&mut tracker, &clock, 10);
+
+        // The window starts between two updates 4 minutes apart, so its
+        // baseline is interpolated. Missed and expected credits are
+        // interpolated apart and may each round down a credit.
+        for _ in 0..8 {
+            let check = tracker.window_cross_check(300).unwrap();
+            assert_eq!(check.histogram_credits, check.counter_credits);
+            assert!(
+                (check.histogram_credits + check.missed).abs_diff(check.counter_expected) <= 1,
+                "{:?}",
+                check
+            );
+            assert!(check.skew().unwrap() < 0.01, "{:?}", check);
+            assert_eq!(
+                check.histogram_efficiency(),
+                Some(tracker.window_efficiency(300))
+            );
+            clock.advance_secs(7);
+        }
+        let check = tracker.window_cross_check(1200).unwrap();
+        assert_eq!(check.histogram_credits, check.counter_credits);
+        assert!(check.skew().unwrap() < 0.01, "{:?}", check);
+    }
+
+    #[test]
+    fn test_efficiency_cross_check_skew_from_unseen_votes() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let credits = sparse_updates(&mut tracker, &clock, 3);
+        // 400 slots rooted earning half the maximum, but no vote was seen on
+        // them: the histogram counts all of it as missed
+        let root = SLOTS_PER_EPOCH + 1000 + 40 * 3;
+        tracker.process_update(root + 1, &[], Some(root), credits, Some(1));
+        clock.advance_secs(1);
+        tracker.process_update(root + 401, &[], Some(root + 400), credits + 3_200, Some(1));
+
+        let check = tracker.window_cross_check(300).unwrap();
+        assert_eq!(check.counter_credits, check.histogram_credits + 3_200);
+        assert!(check.skew().unwrap() > 0.3, "{:?}", check);
+    }
+
+    #[test]
+    fn test_window_baseline_interpolated_across_update_gap() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let epoch_start = SLOTS_PER_EPOCH;
+        let root = epoch_start + 1000;
+        tracker.process_update(root + 2, &[(root + 1, 1, Some(1))], Some(root), 16, Some(1));
+
+        // 20 minutes without notifications, then 3000 slots rooted without credits
+        clock.advance_secs(1200);
+        tracker.process_update(root + 3002, &[], Some(root + 3000), 32, Some(1));
+        // One minute of perfect voting after the gap
+        clock.advance_secs(60);
+        tracker.process_update(root + 3152, &[], Some(root + 3150), 32 + 150 * 16, Some(1));
+        let gap_missed = 3000 * 16 - 16;
+        assert_eq!(tracker.epoch_missed(), gap_missed);
+
+        // The 5m window starts 960s into the gap: only the last 240s of it count
+        assert_eq!(
+            tracker.window_missed(300),
+            gap_missed - gap_missed * 960 / 1200
+        );
+        assert_eq!(tracker.window_missed(3600), gap_missed);
+        // The vote rooted at the end of the gap is in the window
+        assert_eq!(
+            VoteTracker::histogram_total(&tracker.window_histogram(300)),
+            1
+        );
+    }
+
+    #[test]
+    fn test_missed_credits_during_maintenance_are_set_aside() {
+        let (tracker, clock) = tracker_with_clock();
+        let maintenance = Arc::new(Maintenance::new().with_clock(clock.clone()));
+        let mut tracker = tracker.with_maintenance(Some(maintenance.clone()));
+        let root = SLOTS_PER_EPOCH + 1000;
+        tracker.process_update(root + 2, &[], Some(root), 16, Some(1));
+
+        // 10 slots rooted without credits during maintenance
+        maintenance.start(Duration::from_secs(60), "upgrade");
+        let result = tracker.process_update(root + 12, &[], Some(root + 10), 16, Some(1));
+        assert_eq!(result.missed_credits, 160);
+        assert_eq!(result.maintenance_missed, 160);
+        assert_eq!(tracker.epoch_missed(), 0);
+        assert_eq!(tracker.epoch_missed_maintenance(), 160);
+        assert_eq!(tracker.window_missed(300), 0);
+
+        // Once the window expires misses count again
+        clock.advance_secs(60);
+        let result = tracker.process_update(root + 22, &[], Some(root + 20), 16, Some(1));
+        assert_eq!(result.maintenance_missed, 0);
+        assert_eq!(tracker.epoch_missed(), 160);
+        assert_eq!(tracker.window_missed(300), 160);
+
+        // Reconciling keeps the maintenance misses out of the epoch total
+        assert!(tracker.reconcile(1, root + 20, 16));
+        assert_eq!(tracker.epoch_missed(), (1020 + 1) * 16 - 16 - 160);
+    }
+
+    #[test]
+    fn test_window_sample_size_dense_history() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let root = SLOTS_PER_EPOCH + 1000;
+        // An update per second for 10 minutes, each rooting one slot
+        for i in 0..600 {
+            tracker.process_update(root + i + 2, &[], Some(root + i), 16 * (i + 1), Some(1));
+            clock.advance_secs(1);
+        }
+
+        assert_eq!(tracker.window_updates(300), 300);
+        assert_eq!(tracker.window_rooted_slots(300), 300);
+        // The first update only sets the baseline, it roots nothing
+        assert_eq!(tracker.window_updates(3600), 600);
+        assert_eq!(tracker.window_rooted_slots(3600), 599);
+    }
+
+    #[test]
+    fn test_window_sample_size_sparse_history() {
+        let (mut tracker, clock) = tracker_with_clock();
+        assert_eq!(tracker.window_updates(300), 0);
+        assert_eq!(tracker.window_rooted_slots(300), 0);
+
+        let root = SLOTS_PER_EPOCH + 1000;
+        tracker.process_update(root + 2, &[], Some(root), 16, Some(1));
+        clock.advance_secs(1200);
+        tracker.process_update(root + 3002, &[], Some(root + 3000), 32, Some(1));
+        clock.advance_secs(60);
+        tracker.process_update(root + 3152, &[], Some(root + 3150), 32, Some(1));
+
+        // Same baseline as the missed credits: 240s of the 1200s gap count
+        assert_eq!(tracker.window_updates(300), 2);
+        assert_eq!(tracker.window_rooted_slots(300), 3000 * 240 / 1200 + 150);
+        assert_eq!(
+            tracker.window_missed(300),
+            (3000 * 16 - 16) - (3000 * 16 - 16) * 960 / 1200 + 150 * 16
+        );
+        assert_eq!(tracker.window_updates(3600), 3);
+        assert_eq!(tracker.window_rooted_slots(3600), 3150);
+
+        clock.advance_secs(301);
+        assert_eq!(tracker.window_updates(300), 0);
+        assert_eq!(tracker.window_rooted_slots(300), 0);
+    }
+
+    #[test]
+    fn test_interpolate_counter() {
+        assert_eq!(interpolate_counter((1000, 100), (2000, 200), 1250), 125);
+        assert_eq!(interpolate_counter((1000, 100), (2000, 200), 2000), 200);
+        // Outside the pair the ends apply, a counter that went back stays put
+        assert_eq!(interpolate_counter((1000, 100), (2000, 200), 500), 100);
+        assert_eq!(interpolate_counter((1000, 100), (2000, 50), 1500), 100);
+        assert_eq!(interpolate_counter((1000, 100), (1000, 200), 1000), 200);
+    }
+
+    /// Three updates one minute apart, some votes late and some credits missed
+    fn run_history(tracker: &mut VoteTracker, clock: &ManualClock) {
+        let epoch_start = SLOTS_PER_EPOCH;
+        for (i, latency) in [1u32, 3, 1].into_iter().enumerate() {
+            let i = i as u64;
+            tracker.process_update(
+                epoch_start + 1001 + i * 2,
+                &[(epoch_start + 1000 + i * 2, 1, Some(latency))],
+                Some(epoch_start + 999 + i * 2),
+                16 + i * 24,
+                Some(1),
+            );
+            clock.advance_secs(60);
+        }
+    }
+
+    #[test]
+    fn test_history_survives_serialization() {
+        let (mut tracker, clock) = tracker_with_clock();
+        run_history(&mut tracker, &clock);
+
+        let json = serde_json::to_string(&tracker.history()).unwrap();
+        let mut restored = VoteTracker::default().with_clock(clock.clone());
+        restored.restore_history(serde_json::from_str(&json).unwrap());
+
+        assert_eq!(restored.history(), tracker.history());
+        for window in [60, 90, 150, 300, 3600] {
+            assert_eq!(
+                restored.window_histogram(window),
+                tracker.window_histogram(window)
+            );
+            assert_eq!(
+                restored.window_missed(window),
+                tracker.window_missed(window)
+            );
+            assert_eq!(
+                restored.window_is_populated(window),
+                tracker.window_is_populated(window)
+            );
+            assert_eq!(
+                restored.window_rooted_slots(window),
+                tracker.window_rooted_slots(window)
+            );
+        }
+        // The windows really differ, so the comparison above is meaningful
+        assert_ne!(tracker.window_histogram(90), tracker.window_histogram(3600));
+    }
+
+    #[test]
+    fn test_clock_jump_backwards_keeps_history_ordered() {
+        let (mut tracker, clock) = tracker_with_clock();
+        run_history(&mut tracker, &clock);
+        let last = tracker.history().entries.back().unwrap().0;
+
+        // Wall clock steps back 10 minutes
+        clock.set(last - 600_000);
+        tracker.process_update(
+            SLOTS_PER_EPOCH + 1004,
+            &[(SLOTS_PER_EPOCH + 1003, 1, Some(1))],
+            Some(SLOTS_PER_EPOCH + 996),
+            88,
+            Some(1),
+        );
+
+        let entries = tracker.history().entries;
+        assert!(
+            entries
+                .iter()
+                .zip(entries.iter().skip(1))
+                .all(|(a, b)| a.0 <= b.0)
+        );
+        // Entries "from the future" count as zero elapsed: all inside the window
+        assert_eq!(tracker.window_histogram(60), tracker.epoch_histogram());
     }
 
     // ============ Consistency Tests ============
 
     #[test]
     fn test_consistency_credits_plus_missed_equals_expected() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // Simulate 100 slots with mixed voting performance
@@ -1061,14 +3746,14 @@ mod tests {
 
     #[test]
     fn test_histogram_efficiency_consistency() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
-        // Add 10 votes at 16 credits each
+        // Add 10 votes at 16 credits each, each rooted by the next update
         for i in 0..10 {
-            let votes = vec![(epoch_start + i, 1, Some(1))];
+            let votes = vec![(epoch_start + i + 1, 1, Some(1))];
             tracker.process_update(
-                epoch_start + i,
+                epoch_start + i + 1,
                 &votes,
                 Some(epoch_start + i),
                 (i + 1) * 16,
@@ -1078,7 +3763,7 @@ mod tests {
 
         // Efficiency should be 1.0 (100%)
         let hist = tracker.epoch_histogram();
-        let efficiency = VoteTracker::histogram_efficiency(&hist);
+        let efficiency = tracker.histogram_efficiency(&hist);
         assert!(
             (efficiency - 1.0).abs() < 0.001,
             "Efficiency should be 1.0 for all 16-credit votes"
@@ -1092,9 +3777,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_histogram_summaries_follow_the_credits_model() {
+        let mut hist = [0u64; 17];
+        hist[1] = 3;
+        hist[0] = 1;
+
+        let single = VoteTracker::new(CreditsModel::new(1));
+        assert_eq!(single.histogram_efficiency(&hist), 0.75);
+        assert_eq!(single.histogram_avg_latency(&hist), 1.25);
+
+        // The same votes are far from full credits under TVC
+        let tvc = VoteTracker::default();
+        assert_eq!(tvc.histogram_efficiency(&hist), 3.0 / 64.0);
+        assert_eq!(tvc.histogram_avg_latency(&hist), 16.25);
+    }
+
     #[test]
     fn test_window_credits_matches_histogram_credits() {
-        let mut tracker = VoteTracker::new();
+        let mut tracker = VoteTracker::default();
         let epoch_start = SLOTS_PER_EPOCH;
 
         // Add various votes
@@ -1107,10 +3808,11 @@ mod tests {
         tracker.process_update(
             epoch_start + 4,
             &votes,
-            Some(epoch_start + 4),
-            49,      // 16 + 14 + 12 + 7 = 49
+            Some(epoch_start),
+            16,
             Some(1), // epoch 1
         );
+        root_through(&mut tracker, epoch_start + 4, epoch_start);
 
         let hist = tracker.window_histogram(300);
         let hist_credits = VoteTracker::histogram_credits(&hist);
@@ -1122,4 +3824,684 @@ mod tests {
         );
         assert_eq!(hist_credits, 16 + 14 + 12 + 7);
     }
+
+    /// Seed a history entry older than `age_secs` so windows count as populated
+    fn seed_old_history(tracker: &mut VoteTracker, age_secs: u64) {
+        let t = tracker.clock.now_millis() - age_secs * 1000;
+        tracker
+            .hist
+            .push_front((t, tracker.cumulative_histogram, tracker.cumulative_missed));
+        tracker
+            .hist_rooted_slots
+            .push_front(tracker.cumulative_rooted_slots);
+        tracker.hist_credits.push_front(tracker.cumulative_credits);
+    }
+
+    #[test]
+    fn test_snapshot_tracks_run_totals() {
+        let mut tracker = VoteTracker::default();
+        let epoch_start = SLOTS_PER_EPOCH;
+        seed_old_history(&mut tracker, 400);
+
+        tracker.process_update(
+            epoch_start + 1000,
+            &[(epoch_start + 1000, 1, Some(1))],
+            Some(epoch_start + 999),
+            16,
+            Some(1),
+        );
+        // Root advances 2 slots but only 16 credits earned: 16 missed
+        tracker.process_update(
+            epoch_start + 1002,
+            &[
+                (epoch_start + 1000, 2, Some(1)),
+                (epoch_start + 1002, 1, Some(1)),
+            ],
+            Some(epoch_start + 1001),
+            32,
+            Some(1),
+        );
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.first_epoch, Some(1));
+        assert_eq!(snapshot.tracked_credits, 16);
+        assert_eq!(snapshot.tracked_missed, 16);
+        assert_eq!(snapshot.tracked_expected(), 32);
+        assert_eq!(snapshot.tracked_efficiency(), Some(0.5));
+
+        // Only slot 1000 is rooted: 16 credits in the window against 16 missed
+        let worst = snapshot.worst_window_5m.expect("worst window recorded");
+        assert!((worst.efficiency - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_worst_window_skipped_during_warmup() {
+        let mut tracker = VoteTracker::default();
+        let epoch_start = SLOTS_PER_EPOCH;
+
+        tracker.process_update(
+            epoch_start + 1000,
+            &[(epoch_start + 1000, 1, Some(1))],
+            Some(epoch_start + 999),
+            16,
+            Some(1),
+        );
+        tracker.process_update(
+            epoch_start + 1002,
+            &[(epoch_start + 1002, 1, Some(9))],
+            Some(epoch_start + 1001),
+            24,
+            Some(1),
+        );
+
+        // History does not cover 5 minutes yet
+        assert!(!tracker.window_is_populated(300));
+        assert!(tracker.snapshot().epoch_worst_window_5m.is_none());
+    }
+
+    #[test]
+    fn test_epoch_worst_window_resets_at_rollover() {
+        let mut tracker = VoteTracker::default();
+        let epoch1_start = SLOTS_PER_EPOCH;
+        let epoch2_start = 2 * SLOTS_PER_EPOCH;
+        seed_old_history(&mut tracker, 400);
+
+        tracker.process_update(
+            epoch1_start + 1000,
+            &[(epoch1_start + 1000, 1, Some(1))],
+            Some(epoch1_start + 999),
+            16,
+            Some(1),
+        );
+        // Dip: 2 slots rooted, only 8 credits earned
+        tracker.process_update(
+            epoch1_start + 1002,
+            &[(epoch1_start + 1002, 1, Some(9))],
+            Some(epoch1_start + 1001),
+            24,
+            Some(1),
+        );
+        let epoch1_low = tracker
+            .snapshot()
+            .epoch_worst_window_5m
+            .expect("low recorded in epoch 1");
+        assert!(epoch1_low.efficiency < 1.0);
+
+        let result = tracker.process_update(
+            epoch2_start + 1,
+            &[(epoch2_start + 1, 1, Some(1))],
+            Some(epoch2_start),
+            16,
+            Some(2),
+        );
+
+        // The finished epoch's low is carried in the summary
+        let summary = result.epoch_summary.expect("summary at rollover");
+        assert_eq!(summary.epoch, 1);
+        assert_eq!(summary.credits, 24);
+        assert_eq!(summary.worst_window_5m, Some(epoch1_low));
+
+        // The new epoch starts over, while the run-level low is kept
+        let snapshot = tracker.snapshot();
+        assert_ne!(snapshot.epoch_worst_window_5m, Some(epoch1_low));
+        assert_eq!(snapshot.worst_window_5m, Some(epoch1_low));
+    }
+
+    #[test]
+    fn test_epoch_summary_compared_with_previous_epoch() {
+        let mut tracker = VoteTracker::default();
+        // Two votes per epoch with the given latency, rooted before the rollover
+        let mut epoch_with_latency = |epoch: u64, latency: u32| {
+            let start = epoch * SLOTS_PER_EPOCH;
+            tracker.process_update(
+                start + 1000,
+                &[(start + 1000, 1, Some(latency))],
+                Some(start + 999),
+                16,
+                Some(epoch),
+            );
+            tracker.process_update(
+                start + 1002,
+                &[(start + 1002, 1, Some(latency))],
+                Some(start + 1001),
+                16 + 17 - latency as u64,
+                Some(epoch),
+            );
+            tracker.process_update(
+                start + 1004,
+                &[],
+                Some(start + 1003),
+                16 + 2 * (17 - latency as u64),
+                Some(epoch),
+            );
+            tracker.process_update(
+                (epoch + 1) * SLOTS_PER_EPOCH + 1,
+                &[],
+                Some((epoch + 1) * SLOTS_PER_EPOCH),
+                0,
+                Some(epoch + 1),
+            )
+        };
+
+        epoch_with_latency(1, 1);
+        let first = epoch_with_latency(2, 1)
+            .epoch_summary
+            .expect("summary at rollover");
+        assert_eq!(first.avg_latency, Some(1.0));
+        let second = epoch_with_latency(3, 3)
+            .epoch_summary
+            .expect("summary at rollover");
+        assert_eq!(second.avg_latency, Some(3.0));
+        let comparison = second.comparison.expect("compared with epoch 2");
+        assert_eq!(comparison.previous_epoch, 2);
+        assert_eq!(comparison.credits_delta, -4);
+        assert_eq!(comparison.avg_latency_delta, Some(2.0));
+        assert_eq!(tracker.previous_epoch(), Some(second.result()));
+    }
+
+    #[test]
+    fn test_tracked_slots_across_rollover() {
+        let mut tracker = VoteTracker::default();
+        let epoch1_start = SLOTS_PER_EPOCH;
+        let epoch2_start = 2 * SLOTS_PER_EPOCH;
+        assert_eq!(tracker.epoch_first_tracked_slot(), None);
+
+        tracker.process_update(
+            epoch1_start + 1001,
+            &[],
+            Some(epoch1_start + 1000),
+            16,
+            Some(1),
+        );
+        tracker.process_update(
+            epoch1_start + 1501,
+            &[],
+            Some(epoch1_start + 1500),
+            32,
+            Some(1),
+        );
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.epoch_first_tracked_slot, Some(epoch1_start + 1000));
+        assert_eq!(snapshot.epoch_last_tracked_slot, Some(epoch1_start + 1500));
+
+        let result =
+            tracker.process_update(epoch2_start + 6, &[], Some(epoch2_start + 5), 16, Some(2));
+        let summary = result.epoch_summary.expect("summary at rollover");
+        assert_eq!(summary.first_tracked_slot, Some(epoch1_start + 1000));
+        assert_eq!(summary.last_tracked_slot, Some(epoch1_start + 1500));
+
+        // The new epoch's range starts at its first root
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.epoch_first_tracked_slot, Some(epoch2_start + 5));
+        assert_eq!(snapshot.epoch_last_tracked_slot, Some(epoch2_start + 5));
+        assert_eq!(
+            tracker.baseline().epoch_first_tracked_slot,
+            Some(epoch2_start + 5)
+        );
+    }
+
+    #[test]
+    fn test_smoothed_projection_requires_data() {
+        let mut tracker = VoteTracker::default();
+        assert_eq!(tracker.projected_credits_smoothed(), None);
+
+        let epoch_start = SLOTS_PER_EPOCH;
+        tracker.process_update(epoch_start + 11, &[], Some(epoch_start + 10), 176, Some(1));
+        // First update only establishes the baseline
+        assert_eq!(tracker.projected_credits_smoothed(), None);
+
+        tracker.process_update(epoch_start + 13, &[], Some(epoch_start + 12), 208, Some(1));
+        assert_eq!(tracker.smoothed_rate(), Some(16.0));
+        let remaining = SLOTS_PER_EPOCH - 13;
+        assert_eq!(
+            tracker.projected_credits_smoothed(),
+            Some(208 + 16 * remaining)
+        );
+    }
+
+    /// Tower of `height` consecutive votes ending at `top`, all with `latency`
+    fn tower(top: u64, height: u64, latency: u32) -> Vec<(u64, u32, Option<u32>)> {
+        (top + 1 - height..=top)
+            .map(|slot| (slot, (top + 1 - slot) as u32, Some(latency)))
+            .collect()
+    }
+
+    /// Feed a tower to the tracker, rooted just below its bottom vote
+    fn vote(tracker: &mut VoteTracker, votes: &[(u64, u32, Option<u32>)]) -> UpdateResult {
+        let top = votes.iter().map(|(slot, _, _)| *slot).max().unwrap();
+        let root = votes.iter().map(|(slot, _, _)| *slot).min().unwrap() - 1;
+        tracker.process_update(
+            top + 1,
+            votes,
+            Some(root),
+            sim_epoch_credits(root, SLOTS_PER_EPOCH),
+            Some(1),
+        )
+    }
+
+    /// Steady voting with a full tower up to `top`
+    fn vote_steadily(tracker: &mut VoteTracker, top: u64) {
+        for t in top - 10..=top {
+            assert_eq!(vote(tracker, &tower(t, 31, 1)).restart, None);
+        }
+    }
+
+    #[test]
+    fn test_restart_detected_after_clean_restart() {
+        let mut tracker = VoteTracker::default();
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+
+        // Down for 400 slots, then the first vote pops every expired lockout
+        let result = vote(&mut tracker, &tower(top + 400, 1, 30));
+        assert_eq!(
+            result.restart,
+            Some(SuspectedRestart {
+                last_vote_slot: top,
+                resumed_vote_slot: top + 400,
+                tower_height_before: 31,
+                tower_height_after: 1,
+            })
+        );
+        assert_eq!(result.restart.unwrap().gap_slots(), 400);
+
+        // The tower rebuilding afterwards is not another restart
+        for height in 2..=31 {
+            let result = vote(&mut tracker, &tower(top + 399 + height, height, 1));
+            assert_eq!(result.restart, None);
+        }
+    }
+
+    #[test]
+    fn test_restart_detected_during_replay_catch_up() {
+        let mut tracker = VoteTracker::default();
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+
+        // Catching up after replay: a batch of late votes lands at once
+        let result = vote(&mut tracker, &tower(top + 2000, 12, 25));
+        let restart = result.restart.expect("restart detected");
+        assert_eq!(restart.resumed_vote_slot, top + 1989);
+        assert_eq!(restart.tower_height_after, 12);
+
+        // High-latency catch-up votes on consecutive slots don't count again
+        for t in top + 2001..top + 2020 {
+            assert_eq!(vote(&mut tracker, &tower(t, 12, 20)).restart, None);
+        }
+    }
+
+    #[test]
+    fn test_restart_detected_when_tower_restarts_below_previous_votes() {
+        let mut tracker = VoteTracker::default();
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+
+        // Tower rebuilt from an older snapshot: votes jump back, then move forward
+        let result = vote(&mut tracker, &tower(top - 300, 2, 1));
+        let restart = result.restart.expect("restart detected");
+        assert_eq!(restart.last_vote_slot, top);
+        assert_eq!(restart.gap_slots(), 301);
+
+        assert_eq!(vote(&mut tracker, &tower(top - 297, 5, 1)).restart, None);
+    }
+
+    #[test]
+    fn test_fork_switch_is_not_a_restart() {
+        let mut tracker = VoteTracker::default();
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+
+        // Switching forks pops the votes on the abandoned fork and votes a few slots on
+        let mut switched = tower(top - 8, 23, 1);
+        switched.push((top + 2, 1, Some(2)));
+        assert_eq!(vote(&mut tracker, &switched).restart, None);
+
+        // A short outage also collapses the tower, but is below the gap threshold
+        let top = top + 20;
+        vote_steadily(&mut tracker, top);
+        let result = vote(&mut tracker, &tower(top + RESTART_MIN_GAP_SLOTS - 1, 1, 1));
+        assert_eq!(result.restart, None);
+    }
+
+    /// Feed a tower rooted just below its bottom vote, with the epoch credits
+    /// standing at `credits`
+    fn vote_with_credits(
+        tracker: &mut VoteTracker,
+        votes: &[(u64, u32, Option<u32>)],
+        credits: u64,
+    ) -> UpdateResult {
+        let top = votes.iter().map(|(slot, _, _)| *slot).max().unwrap();
+        let root = votes.iter().map(|(slot, _, _)| *slot).min().unwrap() - 1;
+        tracker.process_update(top + 1, votes, Some(root), credits, Some(1))
+    }
+
+    #[test]
+    fn test_votes_without_credits_detected() {
+        let mut tracker = VoteTracker::default().with_credit_stall_updates(3);
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+        let stuck = sim_epoch_credits(top - 31, SLOTS_PER_EPOCH);
+
+        // The root keeps advancing over new votes, the credits don't
+        for (i, latency) in [2, 3].into_iter().enumerate() {
+            let result =
+                vote_with_credits(&mut tracker, &tower(top + 1 + i as u64, 31, latency), stuck);
+            assert_eq!(result.credit_stall, None);
+        }
+        let result = vote_with_credits(&mut tracker, &tower(top + 3, 31, 4), stuck);
+        assert_eq!(
+            result.credit_stall,
+            Some(CreditStallChange::Started {
+                updates: 3,
+                latencies: vec![2, 3, 4],
+            })
+        );
+        assert!(tracker.votes_without_credits());
+
+        // Reported once
+        let result = vote_with_credits(&mut tracker, &tower(top + 4, 31, 1), stuck);
+        assert_eq!(result.credit_stall, None);
+        assert!(tracker.votes_without_credits());
+    }
+
+    #[test]
+    fn test_votes_without_credits_not_reported_while_earning() {
+        let mut tracker = VoteTracker::default().with_credit_stall_updates(3);
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+        for t in top + 1..top + 50 {
+            assert_eq!(vote(&mut tracker, &tower(t, 31, 1)).credit_stall, None);
+        }
+
+        // Short of the window, then credits again
+        let stuck = sim_epoch_credits(top + 49 - 31, SLOTS_PER_EPOCH);
+        for t in top + 50..top + 52 {
+            let result = vote_with_credits(&mut tracker, &tower(t, 31, 1), stuck);
+            assert_eq!(result.credit_stall, None);
+        }
+        assert_eq!(
+            vote(&mut tracker, &tower(top + 52, 31, 1)).credit_stall,
+            None
+        );
+
+        // Updates that root nothing don't count, whatever the credits
+        let stuck = sim_epoch_credits(top + 52 - 31, SLOTS_PER_EPOCH);
+        for t in top + 53..top + 58 {
+            let mut votes = tower(t, 31, 1);
+            votes.retain(|(slot, _, _)| *slot > top + 52 - 31);
+            let result = tracker.process_update(t + 1, &votes, Some(top + 52 - 31), stuck, Some(1));
+            assert_eq!(result.credit_stall, None);
+        }
+        assert!(!tracker.votes_without_credits());
+    }
+
+    #[test]
+    fn test_votes_without_credits_clears_when_credits_resume() {
+        let mut tracker = VoteTracker::default().with_credit_stall_updates(2);
+        let top = SLOTS_PER_EPOCH + 1000;
+        vote_steadily(&mut tracker, top);
+        let stuck = sim_epoch_credits(top - 31, SLOTS_PER_EPOCH);
+        vote_with_credits(&mut tracker, &tower(top + 1, 31, 1), stuck);
+        let result = vote_with_credits(&mut tracker, &tower(top + 2, 31, 1), stuck);
+        assert!(matches!(
+            result.credit_stall,
+            Some(CreditStallChange::Started { updates: 2, .. })
+        ));
+
+        let result = vote_with_credits(&mut tracker, &tower(top + 3, 31, 1), stuck + 16);
+        assert_eq!(result.credit_stall, Some(CreditStallChange::Ended));
+        assert!(!tracker.votes_without_credits());
+
+        // A reset keeps the window
+        tracker.reset(1, top + 50, sim_epoch_credits(top + 50, SLOTS_PER_EPOCH));
+        vote_steadily(&mut tracker, top + 100);
+        let stuck = sim_epoch_credits(top + 100 - 31, SLOTS_PER_EPOCH);
+        vote_with_credits(&mut tracker, &tower(top + 101, 31, 1), stuck);
+        let result = vote_with_credits(&mut tracker, &tower(top + 102, 31, 1), stuck);
+        assert!(result.credit_stall.is_some());
+    }
+
+    /// Feed `count` votes of `latency` and return the last regime
+    fn observe_n(classifier: &mut RegimeClassifier, count: usize, latency: u64) -> Regime {
+        (0..count)
+            .map(|_| classifier.observe(latency))
+            .last()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_regime_enters_catch_up_on_high_median() {
+        let mut classifier = RegimeClassifier::default();
+        assert_eq!(observe_n(&mut classifier, 32, 1), Regime::Steady);
+
+        // Fewer than half slow votes leave the median alone
+        assert_eq!(observe_n(&mut classifier, 15, 20), Regime::Steady);
+        // Half the window slow moves the (upper) median over the threshold
+        assert_eq!(observe_n(&mut classifier, 1, 20), Regime::CatchUp);
+    }
+
+    #[test]
+    fn test_regime_hysteresis_and_recovery() {
+        let mut classifier = RegimeClassifier::default();
+        observe_n(&mut classifier, 32, 10);
+        assert_eq!(classifier.regime(), Regime::CatchUp);
+
+        // A median between the thresholds doesn't leave catch-up...
+        assert_eq!(observe_n(&mut classifier, 32, 3), Regime::CatchUp);
+        // ...until it drops to the exit threshold
+        assert_eq!(observe_n(&mut classifier, 32, 2), Regime::Steady);
+        // ...and doesn't re-enter until it exceeds the entry threshold
+        assert_eq!(observe_n(&mut classifier, 32, 4), Regime::Steady);
+        assert_eq!(observe_n(&mut classifier, 32, 5), Regime::CatchUp);
+    }
+
+    /// Steady voting one slot per update, 2s apart, from `first_top`.
+    /// Returns the state evictions.
+    fn vote_for(
+        tracker: &mut VoteTracker,
+        clock: &ManualClock,
+        first_top: u64,
+        updates: u64,
+    ) -> u64 {
+        let mut evictions = 0;
+        for top in first_top..first_top + updates {
+            evictions += vote(tracker, &tower(top, 31, 1)).state_evictions;
+            clock.advance_secs(2);
+        }
+        evictions
+    }
+
+    #[test]
+    fn test_memory_footprint_counts_history_and_pending_votes() {
+        let (mut tracker, clock) = tracker_with_clock();
+        assert_eq!(tracker.memory_footprint(), 0);
+
+        vote_for(&mut tracker, &clock, SLOTS_PER_EPOCH + 100, 10);
+        let footprint = tracker.memory_footprint();
+        assert!(footprint >= 10 * size_of::<HistEntry>() + 31 * size_of::<PendingVote>());
+
+        // Ten more window, leader and fork regime history entries; the tower
+        // stays the same height
+        vote_for(&mut tracker, &clock, SLOTS_PER_EPOCH + 110, 10);
+        assert_eq!(
+            tracker.memory_footprint(),
+            footprint
+                + 10 * (size_of::<HistEntry>()
+                    + 2 * size_of::<u64>()
+                    + 2 * size_of::<(u64, u64, u64)>())
+        );
+    }
+
+    #[test]
+    fn test_max_state_bytes_evicts_oldest_history() {
+        const MAX_STATE_BYTES: usize = 8 * 1024;
+        let (tracker, clock) = tracker_with_clock();
+        let mut tracker = tracker.with_max_state_bytes(Some(MAX_STATE_BYTES));
+        let first_top = SLOTS_PER_EPOCH + 100;
+
+        let mut evictions = 0;
+        for i in 0..400 {
+            evictions += vote_for(&mut tracker, &clock, first_top + i, 1);
+            assert!(tracker.memory_footprint() <= MAX_STATE_BYTES);
+        }
+        assert!(evictions > 0);
+        assert!(!tracker.hist.is_empty());
+
+        // The windows are truncated to the remaining history: fewer votes than
+        // the 150 rooted in the last 5 minutes, but all of them at 16 credits
+        let hist_5m = tracker.window_histogram(300);
+        let kept = tracker.hist.len() as u64 - 1;
+        assert_eq!(hist_5m[16], kept);
+        assert_eq!(VoteTracker::histogram_total(&hist_5m), kept);
+        assert!(kept > 0 && kept < 150);
+        assert_eq!(tracker.window_missed(300), 0);
+        assert_eq!(tracker.window_histogram(3600), hist_5m);
+
+        // The cap survives a reset
+        tracker.reset(1, first_top + 400, 0);
+        let evictions = vote_for(&mut tracker, &clock, first_top + 400, 400);
+        assert!(evictions > 0);
+        assert!(tracker.memory_footprint() <= MAX_STATE_BYTES);
+    }
+
+    #[test]
+    fn test_windows_recover_after_cluster_halt() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let first_top = SLOTS_PER_EPOCH + 100;
+        let ceiling = tracker.credits_model.expected_credits(SLOTS_PER_EPOCH);
+        vote_for(&mut tracker, &clock, first_top, 2000);
+        assert!(tracker.window_coverage(3600) > 0.99);
+        assert_eq!(
+            VoteTracker::histogram_total(&tracker.window_histogram(300)),
+            150
+        );
+
+        // Halted for 6 hours: the history no longer reaches into the windows
+        clock.advance_secs(6 * 3600);
+        assert_eq!(tracker.window_coverage(300), 0.0);
+        assert_eq!(tracker.window_coverage(3600), 0.0);
+
+        // The first update after the restart ages all of it out. The windows
+        // start over from it, rather than counting every vote since startup.
+        vote_for(&mut tracker, &clock, first_top + 2000, 1);
+        assert_eq!(tracker.window_coverage(300), 0.0);
+        assert_eq!(tracker.window_histogram(300), [0; 17]);
+        assert_eq!(tracker.window_histogram(3600), [0; 17]);
+        assert_eq!(tracker.window_credits_and_missed(3600), (0, 0));
+        assert!(tracker.projected_credits_smoothed().unwrap() <= ceiling);
+
+        // A minute later the 5m window has data again, the 1h one not yet
+        vote_for(&mut tracker, &clock, first_top + 2001, 30);
+        assert!(tracker.window_coverage(300) >= MIN_WINDOW_COVERAGE);
+        assert!(tracker.window_coverage(3600) < MIN_WINDOW_COVERAGE);
+        let hist_5m = tracker.window_histogram(300);
+        assert_eq!(
+            (hist_5m[16], VoteTracker::histogram_total(&hist_5m)),
+            (30, 30)
+        );
+        assert_eq!(tracker.window_missed(300), 0);
+        assert!(tracker.projected_credits_smoothed().unwrap() <= ceiling);
+    }
+
+    #[test]
+    fn test_no_evictions_without_cap() {
+        let (mut tracker, clock) = tracker_with_clock();
+        assert_eq!(
+            vote_for(&mut tracker, &clock, SLOTS_PER_EPOCH + 100, 400),
+            0
+        );
+        assert_eq!(
+            VoteTracker::histogram_total(&tracker.window_histogram(300)),
+            150
+        );
+    }
+
+    #[test]
+    fn test_root_delay_from_first_sighting() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let vote = SLOTS_PER_EPOCH + 1000;
+        let root = vote - 1;
+
+        tracker.process_update(vote, &[(vote, 1, Some(1))], Some(root), 0, Some(1));
+        clock.advance_secs(4);
+        // Seen again (deeper in the tower) and joined by a newer vote
+        let result = tracker.process_update(
+            vote + 1,
+            &[(vote, 2, Some(1)), (vote + 1, 1, Some(1))],
+            Some(root),
+            0,
+            Some(1),
+        );
+        assert!(result.root_delays_ms.is_empty());
+
+        clock.advance_secs(9);
+        let result = tracker.process_update(vote + 40, &[], Some(vote + 1), 32, Some(1));
+        assert_eq!(result.root_delays_ms, vec![13_000, 9_000]);
+        assert!(tracker.vote_first_seen.is_empty());
+    }
+
+    #[test]
+    fn test_root_delay_tracking_is_bounded() {
+        let (mut tracker, clock) = tracker_with_clock();
+        let root = SLOTS_PER_EPOCH + 999;
+
+        // The root stalls while the votes keep moving
+        for i in 1..=2 * ROOT_DELAY_MAX_SLOTS as u64 {
+            let vote = root + i;
+            tracker.process_update(vote, &[(vote, 1, Some(1))], Some(root), 0, Some(1));
+            assert!(tracker.vote_first_seen.len() <= ROOT_DELAY_MAX_SLOTS);
+        }
+        assert_eq!(tracker.vote_first_seen.len(), ROOT_DELAY_MAX_SLOTS);
+
+        // Sightings older than the max age are dropped, so a late root reports nothing
+        let vote = root + 3 * ROOT_DELAY_MAX_SLOTS as u64;
+        tracker.process_update(vote, &[(vote, 1, Some(1))], Some(root), 0, Some(1));
+        clock.advance_secs(ROOT_DELAY_MAX_AGE_MS / 1000 + 1);
+        let result = tracker.process_update(
+            vote + 1,
+            &[(vote, 2, Some(1)), (vote + 1, 1, Some(1))],
+            Some(root),
+            0,
+            Some(1),
+        );
+        assert_eq!(tracker.vote_first_seen.len(), 1);
+        assert!(result.root_delays_ms.is_empty());
+        let result = tracker.process_update(vote + 40, &[], Some(vote + 1), 32, Some(1));
+        assert_eq!(result.root_delays_ms, vec![0]);
+    }
+
+    #[test]
+    fn test_regime_needs_full_window() {
+        let mut classifier = RegimeClassifier::default();
+        assert_eq!(
+            observe_n(&mut classifier, REGIME_WINDOW_VOTES - 1, 30),
+            Regime::Steady
+        );
+        assert_eq!(classifier.observe(30), Regime::CatchUp);
+    }
+
+    #[test]
+    fn test_catch_up_votes_excluded_from_windows() {
+        let mut tracker = VoteTracker::default().with_exclude_catch_up(true);
+        let top = SLOTS_PER_EPOCH + 1000;
+
+        // 32 fast votes, then 40 slow ones (catch-up starts with the 16th slow vote)
+        vote(&mut tracker, &tower(top, 31, 1));
+        vote(&mut tracker, &tower(top + 1, 31, 1));
+        let result = vote(&mut tracker, &tower(top + 41, 31, 14));
+        let result2 = vote(&mut tracker, &tower(top + 50, 31, 14));
+        assert_eq!(tracker.regime(), Regime::CatchUp);
+        let result3 = root_through(&mut tracker, top + 50, SLOTS_PER_EPOCH);
+
+        // Counted as they root
+        let caught_up = result.catch_up_votes + result2.catch_up_votes + result3.catch_up_votes;
+        assert_eq!(caught_up, 40 - 15);
+
+        // Epoch histogram keeps every vote, the windows only the steady ones
+        let epoch = VoteTracker::histogram_total(&tracker.epoch_histogram());
+        let window = VoteTracker::histogram_total(&tracker.window_histogram(300));
+        assert_eq!(epoch, 72);
+        assert_eq!(window, 72 - caught_up);
+        assert_eq!(tracker.window_histogram(300)[3], 15);
+    }
 }
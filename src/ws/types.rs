@@ -1,4 +1,7 @@
-use serde::{Deserialize, Deserializer};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
 
 /// Vote tower entry from parsed vote account
 #[derive(Debug, Clone, Deserialize)]
@@ -22,6 +25,18 @@ pub struct EpochCreditsEntry {
     pub previous_credits: u64,
 }
 
+/// Authorized voter replaced at `target_epoch`, as kept by the vote
+/// program's ring of the last 32 voters
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct PriorVoter {
+    pub authorized_pubkey: String,
+    /// Epoch the voter was authorized from
+    pub epoch_of_last_authorized_switch: u64,
+    /// First epoch voted by its successor
+    pub target_epoch: u64,
+}
+
 /// Deserialize a value that could be either a string or a number
 fn string_or_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
@@ -40,13 +55,91 @@ where
     }
 }
 
-/// Parsed vote account info
+/// Deserialize an optional value that could be either a string or a number
+fn string_or_u64_opt<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "string_or_u64")] u64);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(n)| n))
+}
+
+/// Parsed vote account info. The fields after `epoch_credits` only exist
+/// from VoteStateV4 (SIMD-0185) on, which replaces the commission with
+/// separate inflation and block revenue commissions in basis points.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VoteAccountInfo {
     pub votes: Vec<VoteLockout>,
     pub root_slot: Option<u64>,
     pub epoch_credits: Vec<EpochCreditsEntry>,
+    /// Previous authorized voters in ring order, unused slots left out.
+    /// Empty for VoteStateV4, which no longer keeps them.
+    #[serde(default)]
+    pub prior_voters: Vec<PriorVoter>,
+    #[serde(default)]
+    pub inflation_rewards_commission_bps: Option<u16>,
+    #[serde(default)]
+    pub block_revenue_commission_bps: Option<u16>,
+    #[serde(default)]
+    pub inflation_rewards_collector: Option<String>,
+    #[serde(default)]
+    pub block_revenue_collector: Option<String>,
+    #[serde(default, deserialize_with = "string_or_u64_opt")]
+    pub pending_delegator_rewards: Option<u64>,
+    #[serde(default)]
+    pub bls_pubkey_compressed: Option<String>,
+}
+
+/// Vote state layout, told apart by the fields each one adds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteStateVersion {
+    /// Tower without vote latencies
+    V1_14_11,
+    /// Tower with vote latencies (timely vote credits)
+    V3,
+    /// Split commissions and reward collectors
+    V4,
+}
+
+impl VoteStateVersion {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::V1_14_11 => "v1_14_11",
+            Self::V3 => "v3",
+            Self::V4 => "v4",
+        }
+    }
+}
+
+impl fmt::Display for VoteStateVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl VoteAccountInfo {
+    /// The state version the fields point to; None for a pre-V4 account with
+    /// an empty tower, whose votes can't show whether latencies are recorded
+    pub fn state_version(&self) -> Option<VoteStateVersion> {
+        let v4 = self.inflation_rewards_commission_bps.is_some()
+            || self.block_revenue_commission_bps.is_some()
+            || self.inflation_rewards_collector.is_some()
+            || self.block_revenue_collector.is_some()
+            || self.pending_delegator_rewards.is_some()
+            || self.bls_pubkey_compressed.is_some();
+        if v4 {
+            Some(VoteStateVersion::V4)
+        } else if self.votes.iter().any(|vote| vote.latency.is_some()) {
+            Some(VoteStateVersion::V3)
+        } else if !self.votes.is_empty() {
+            Some(VoteStateVersion::V1_14_11)
+        } else {
+            None
+        }
+    }
 }
 
 /// Parsed vote account data wrapper
@@ -64,10 +157,27 @@ pub enum AccountData {
     Parsed {
         parsed: ParsedVoteData,
         program: String,
+        /// Length of the account data in bytes
+        #[serde(default)]
+        space: Option<u64>,
     },
     Raw(String, String),
 }
 
+impl AccountData {
+    /// Length of the account data in bytes: `space` of parsed data, the
+    /// decoded length of base64 data (None for other encodings)
+    pub fn data_len(&self) -> Option<u64> {
+        match self {
+            AccountData::Parsed { space, .. } => *space,
+            AccountData::Raw(data, encoding) if encoding == "base64" => {
+                STANDARD.decode(data).ok().map(|bytes| bytes.len() as u64)
+            }
+            AccountData::Raw(_, _) => None,
+        }
+    }
+}
+
 /// Account value in notification
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -106,13 +216,18 @@ pub enum WsMessage {
     Notification {
         jsonrpc: String,
         method: String,
-        params: NotificationParams,
+        params: Box<NotificationParams>,
     },
     SubscriptionResult {
         jsonrpc: String,
         result: u64,
         id: u64,
     },
+    UnsubscribeResult {
+        jsonrpc: String,
+        result: bool,
+        id: u64,
+    },
     Error {
         jsonrpc: String,
         error: WsError,
@@ -228,6 +343,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ws_unsubscribe_result() {
+        let json = r#"{"jsonrpc": "2.0", "result": true, "id": 2}"#;
+
+        let msg: WsMessage = serde_json::from_str(json).unwrap();
+        match msg {
+            WsMessage::UnsubscribeResult { result, id, .. } => {
+                assert!(result);
+                assert_eq!(id, 2);
+            }
+            _ => panic!("Expected UnsubscribeResult"),
+        }
+    }
+
     #[test]
     fn test_ws_error() {
         let json = r#"{
@@ -271,7 +400,8 @@ mod tests {
                                     ]
                                 },
                                 "type": "vote"
-                            }
+                            },
+                            "space": 3762
                         },
                         "owner": "Vote111111111111111111111111111111111111111",
                         "executable": false,
@@ -288,11 +418,18 @@ mod tests {
                 assert_eq!(params.result.context.slot, 387331601);
                 assert_eq!(params.subscription, 13086);
 
-                if let AccountData::Parsed { parsed, program } = &params.result.value.data {
+                if let AccountData::Parsed {
+                    parsed,
+                    program,
+                    space,
+                } = &params.result.value.data
+                {
+                    assert_eq!(*space, Some(3762));
                     assert_eq!(program, "vote");
                     assert_eq!(parsed.info.votes.len(), 2);
                     assert_eq!(parsed.info.votes[0].latency, Some(1));
                     assert_eq!(parsed.info.epoch_credits[0].credits, 1000);
+                    assert_eq!(parsed.info.state_version(), Some(VoteStateVersion::V3));
                 } else {
                     panic!("Expected Parsed data");
                 }
@@ -300,4 +437,102 @@ mod tests {
             _ => panic!("Expected Notification"),
         }
     }
+
+    #[test]
+    fn test_v4_account_parsing() {
+        // VoteStateV4 (SIMD-0185): split commissions and collectors, no
+        // commission or priorVoters, pending rewards as a string
+        let json = r#"{
+            "program": "vote",
+            "parsed": {
+                "info": {
+                    "authorizedVoters": [{"authorizedVoter": "Node111", "epoch": 908}],
+                    "authorizedWithdrawer": "Withdraw111",
+                    "blockRevenueCollector": "Node111",
+                    "blockRevenueCommissionBps": 10000,
+                    "blsPubkeyCompressed": null,
+                    "epochCredits": [
+                        {"credits": "1000", "epoch": 908, "previousCredits": "500"}
+                    ],
+                    "inflationRewardsCollector": "Vote111",
+                    "inflationRewardsCommissionBps": 500,
+                    "lastTimestamp": {"slot": 387331570, "timestamp": 1700000000},
+                    "nodePubkey": "Node111",
+                    "pendingDelegatorRewards": "1234567",
+                    "rootSlot": 387331569,
+                    "votes": [
+                        {"confirmationCount": 31, "latency": 1, "slot": 387331570},
+                        {"confirmationCount": 30, "latency": 2, "slot": 387331571}
+                    ]
+                },
+                "type": "vote"
+            },
+            "space": 3762
+        }"#;
+
+        let data: AccountData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.data_len(), Some(3762));
+        let AccountData::Parsed { parsed, .. } = data else {
+            panic!("Expected Parsed data");
+        };
+        let info = parsed.info;
+        assert_eq!(info.votes[1].latency, Some(2));
+        assert_eq!(info.root_slot, Some(387331569));
+        assert_eq!(info.epoch_credits[0].credits, 1000);
+        assert_eq!(info.inflation_rewards_commission_bps, Some(500));
+        assert_eq!(info.block_revenue_commission_bps, Some(10000));
+        assert_eq!(info.pending_delegator_rewards, Some(1234567));
+        assert_eq!(info.bls_pubkey_compressed, None);
+        assert_eq!(info.state_version(), Some(VoteStateVersion::V4));
+    }
+
+    #[test]
+    fn test_prior_voters_of_a_rotated_account() {
+        let data: AccountData =
+            serde_json::from_str(include_str!("../../fixtures/vote_accounts/rotated.json"))
+                .unwrap();
+        let AccountData::Parsed { parsed, .. } = data else {
+            panic!("Expected Parsed data");
+        };
+        let info = parsed.info;
+        assert_eq!(info.state_version(), Some(VoteStateVersion::V3));
+        assert_eq!(info.prior_voters.len(), 3);
+        assert_eq!(
+            info.prior_voters[1],
+            PriorVoter {
+                authorized_pubkey: "Spare111".to_string(),
+                epoch_of_last_authorized_switch: 851,
+                target_epoch: 853,
+            }
+        );
+
+        // Served in snake case
+        let served = serde_json::to_value(&info.prior_voters[1]).unwrap();
+        assert_eq!(served["authorized_pubkey"], "Spare111");
+        assert_eq!(served["target_epoch"], 853);
+    }
+
+    #[test]
+    fn test_state_version_of_older_towers() {
+        let info = |votes: &str| -> VoteAccountInfo {
+            serde_json::from_str(&format!(
+                r#"{{"votes": {}, "rootSlot": null, "epochCredits": []}}"#,
+                votes
+            ))
+            .unwrap()
+        };
+        assert_eq!(
+            info(r#"[{"confirmationCount": 1, "slot": 100}]"#).state_version(),
+            Some(VoteStateVersion::V1_14_11)
+        );
+        assert_eq!(info("[]").state_version(), None);
+    }
+
+    #[test]
+    fn test_raw_data_length() {
+        let base64: AccountData = serde_json::from_str(r#"["AAECAwQ=", "base64"]"#).unwrap();
+        assert_eq!(base64.data_len(), Some(5));
+        let zstd: AccountData = serde_json::from_str(r#"["KLUv/Q==", "base64+zstd"]"#).unwrap();
+        assert_eq!(zstd.data_len(), None);
+    }
 }
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+/// Cumulative credits and missed credits with a timestamped history, so the
+/// sums over a trailing window are the change since the last entry before it
+#[derive(Debug, Clone, Default)]
+pub struct WindowedCredits {
+    /// (unix millis, cumulative credits, cumulative missed), stamped in step
+    /// with the tracker's window history
+    hist: VecDeque<(u64, u64, u64)>,
+    cumulative_credits: u64,
+    cumulative_missed: u64,
+}
+
+impl WindowedCredits {
+    pub fn add(&mut self, credits: u64, missed: u64) {
+        self.cumulative_credits += credits;
+        self.cumulative_missed += missed;
+    }
+
+    /// Add a history entry at `now` and drop entries before `cutoff`
+    pub fn push_history(&mut self, now: u64, cutoff: u64) {
+        self.hist
+            .push_back((now, self.cumulative_credits, self.cumulative_missed));
+        self.prune_history(cutoff);
+    }
+
+    /// Drop history entries before `cutoff`
+    pub fn prune_history(&mut self, cutoff: u64) {
+        while self.hist.front().is_some_and(|(t, _, _)| *t < cutoff) {
+            self.hist.pop_front();
+        }
+    }
+
+    /// Estimated bytes held by the history
+    pub fn memory_footprint(&self) -> usize {
+        self.hist.len() * size_of::<(u64, u64, u64)>()
+    }
+
+    /// Credits and missed credits added since `start` (unix millis)
+    pub fn since(&self, start: u64) -> (u64, u64) {
+        let (credits, missed) = self
+            .hist
+            .iter()
+            .rev()
+            .find(|(t, _, _)| *t < start)
+            .map_or((0, 0), |(_, c, m)| (*c, *m));
+        (
+            self.cumulative_credits.saturating_sub(credits),
+            self.cumulative_missed.saturating_sub(missed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_is_delta_from_last_entry_before_start() {
+        let mut windowed = WindowedCredits::default();
+        windowed.add(16, 16);
+        windowed.push_history(1_000, 0);
+        windowed.add(0, 16);
+        windowed.push_history(2_000, 0);
+
+        assert_eq!(windowed.since(1_500), (0, 16));
+        assert_eq!(windowed.since(500), (16, 32));
+
+        // Entries before the cutoff are pruned
+        windowed.push_history(3_000, 1_500);
+        assert_eq!(windowed.hist.len(), 2);
+        assert_eq!(windowed.since(2_500), (0, 0));
+        assert_eq!(windowed.memory_footprint(), 2 * 24);
+    }
+}
@@ -0,0 +1,48 @@
+//! Build check of the minimal poll-only build, tests and examples included, so
+//! code that needs the `ws` or `metrics-server` feature does not creep into the
+//! always-compiled modules or their tests.
+//!
+//! Set `SKIP_FEATURE_CHECK=1` to skip it when iterating locally.
+
+use std::path::Path;
+use std::process::Command;
+
+/// `cargo check` every target of the package with only the given features
+fn check_features(features: &str) -> (bool, String) {
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--quiet",
+            "--all-targets",
+            "--no-default-features",
+            "--features",
+        ])
+        .arg(features)
+        .arg("--manifest-path")
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+        // Own target dir, so it neither waits on nor invalidates the outer build
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("feature-check"),
+        )
+        .output()
+        .expect("run cargo check");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_poll_only_build() {
+    if std::env::var_os("SKIP_FEATURE_CHECK").is_some() {
+        return;
+    }
+    let (ok, stderr) = check_features("http-poll");
+    assert!(ok, "poll-only build failed:\n{}", stderr);
+    assert!(
+        !stderr.contains("warning"),
+        "poll-only build warns:\n{}",
+        stderr
+    );
+}
@@ -0,0 +1,251 @@
+//! Golden-file test of the full metric output for a fixed scenario, so metric
+//! renames and removals show up in review.
+//!
+//! Re-generate `tests/golden/metrics.txt` after an intended change with
+//! `BLESS_GOLDEN=1 cargo test --test golden_metrics`.
+
+use tvc_tracker::Metrics;
+use tvc_tracker::clock::ManualClock;
+use tvc_tracker::credits::CreditsModel;
+use tvc_tracker::poller::{PollState, poll_once};
+use tvc_tracker::rpc::{RpcClient, RpcVersion, RpcVoteAccount, RpcVoteAccounts};
+use tvc_tracker::ws::{
+    ForkPressureClassifier, NotificationParams, SLOTS_PER_EPOCH, SubscriptionOptions, VoteTracker,
+    process_notification,
+};
+
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+/// 2023-11-14 22:13:20 UTC
+const START_MILLIS: u64 = 1_700_000_000_000;
+
+const EPOCH: u64 = 500;
+
+const VOTE_PUBKEY: &str = "Vote1111111111111111111111111111111111111111";
+
+/// Metrics holding wall-clock timestamps that no injected clock reaches
+const VOLATILE_METRICS: &[&str] = &[
+    "rpc_last_success",
+    "ws_last_message",
+    "metrics_last_scraped_seconds",
+];
+
+/// Replace the values of volatile metrics with a placeholder
+fn scrub(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.starts_with('#') {
+                return line.to_string();
+            }
+            let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+            match line.rfind(' ') {
+                Some(value_start) if VOLATILE_METRICS.contains(&&line[..name_end]) => {
+                    format!("{} <scrubbed>", &line[..value_start])
+                }
+                _ => line.to_string(),
+            }
+        })
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// WS path: 60 notifications 2s apart, one vote per slot, every 5th vote
+/// landing 3 slots late, rooted 4 slots behind the newest vote
+async fn ws_scenario() -> String {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let tracker = Arc::new(RwLock::new(
+        VoteTracker::new(CreditsModel::default()).with_clock(clock.clone()),
+    ));
+    let metrics = Arc::new(Metrics::new().unwrap());
+    let model = CreditsModel::default();
+    let latency = |slot: u64| if slot % 5 == 0 { 3 } else { 1 };
+    let first_slot = EPOCH * SLOTS_PER_EPOCH + 1000;
+    let mut fork_pressure = ForkPressureClassifier::default();
+    let mut last_fingerprint = None;
+
+    let mut credits = 0;
+    for i in 0..60 {
+        let slot = first_slot + i;
+        let root = slot - 4;
+        if root >= first_slot {
+            credits += model.credits_from_latency(latency(root));
+        }
+        let votes: Vec<_> = (root + 1..=slot)
+            .map(|s| {
+                json!({
+                    "slot": s,
+                    "confirmationCount": slot - s + 1,
+                    "latency": latency(s),
+                })
+            })
+            .collect();
+        let params: NotificationParams = serde_json::from_value(json!({
+            "result": {
+                "context": {"slot": slot + 1},
+                "value": {
+                    "lamports": 1,
+                    "data": {
+                        "program": "vote",
+                        "parsed": {
+                            "info": {
+                                "votes": votes,
+                                "rootSlot": root,
+                                "epochCredits": [
+                                    {"epoch": EPOCH - 1, "credits": 6_000_000, "previousCredits": 0},
+                                    {"epoch": EPOCH, "credits": 6_000_000 + credits, "previousCredits": 6_000_000}
+                                ]
+                            },
+                            "type": "vote"
+                        }
+                    },
+                    "owner": "Vote111111111111111111111111111111111111111",
+                    "executable": false,
+                    "rentEpoch": 0
+                }
+            },
+            "subscription": 1
+        }))
+        .unwrap();
+
+        process_notification(
+            &params,
+            &metrics,
+            &tracker,
+            &mut fork_pressure,
+            &mut last_fingerprint,
+            &SubscriptionOptions::default(),
+        )
+        .await
+        .unwrap();
+        clock.advance_secs(2);
+    }
+    metrics.snapshot().to_text()
+}
+
+/// Serves a vote account whose credits can be advanced between polls
+struct ScenarioRpc {
+    account: Mutex<RpcVoteAccount>,
+}
+
+impl RpcClient for ScenarioRpc {
+    async fn get_vote_accounts(&self, _vote_pubkey: Option<&str>) -> Result<RpcVoteAccounts> {
+        Ok(RpcVoteAccounts {
+            current: vec![self.account.lock().unwrap().clone()],
+            delinquent: vec![],
+        })
+    }
+
+    async fn get_version(&self) -> Result<RpcVersion> {
+        Err(anyhow!("not used"))
+    }
+
+    async fn is_feature_active(&self, _feature_id: &str) -> Result<bool> {
+        Err(anyhow!("not used"))
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        Ok(self.account.lock().unwrap().root_slot + 40)
+    }
+}
+
+/// Poll path: 10 polls a minute apart, 150 slots rooted per poll with 2% of
+/// the possible credits missed
+async fn poll_scenario() -> String {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let metrics = Metrics::new().unwrap();
+    let model = CreditsModel::default();
+    let epoch_start = EPOCH * SLOTS_PER_EPOCH;
+    let rpc = ScenarioRpc {
+        account: Mutex::new(RpcVoteAccount {
+            vote_pubkey: VOTE_PUBKEY.to_string(),
+            node_pubkey: "Node1111111111111111111111111111111111111111".to_string(),
+            activated_stake: 1_000_000_000_000,
+            commission: 5,
+            epoch_vote_account: true,
+            epoch_credits: vec![],
+            last_vote: 0,
+            root_slot: 0,
+        }),
+    };
+    let mut state = PollState::new().with_clock(clock.clone());
+
+    for i in 1..=10u64 {
+        {
+            let mut account = rpc.account.lock().unwrap();
+            account.root_slot = epoch_start + i * 150 - 1;
+            account.last_vote = account.root_slot + 32;
+            account.epoch_credits = vec![
+                (EPOCH - 1, 6_000_000, 0),
+                (
+                    EPOCH,
+                    6_000_000 + model.expected_credits(i * 150) * 98 / 100,
+                    6_000_000,
+                ),
+            ];
+        }
+        poll_once(&rpc, VOTE_PUBKEY, &mut state, model, &metrics)
+            .await
+            .unwrap();
+        clock.advance_secs(60);
+    }
+    metrics.snapshot().to_text()
+}
+
+/// Lines only in `expected` (-) and only in `actual` (+)
+fn diff(expected: &str, actual: &str) -> String {
+    let expected_lines: HashSet<&str> = expected.lines().collect();
+    let actual_lines: HashSet<&str> = actual.lines().collect();
+    let removed = expected
+        .lines()
+        .filter(|l| !actual_lines.contains(l))
+        .map(|l| format!("- {}", l));
+    let added = actual
+        .lines()
+        .filter(|l| !expected_lines.contains(l))
+        .map(|l| format!("+ {}", l));
+    removed.chain(added).collect::<Vec<_>>().join("\n")
+}
+
+#[tokio::test]
+async fn test_metrics_match_golden_file() {
+    let actual = format!(
+        "## ws\n{}## poll\n{}",
+        scrub(&ws_scenario().await),
+        scrub(&poll_scenario().await)
+    );
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/metrics.txt");
+
+    if std::env::var_os("BLESS_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Cannot read {} ({}); create it with BLESS_GOLDEN=1",
+            path.display(),
+            e
+        )
+    });
+    assert!(
+        expected == actual,
+        "Metric output differs from {}:\n{}\n\nIf the change is intended, re-run with BLESS_GOLDEN=1",
+        path.display(),
+        diff(&expected, &actual)
+    );
+}
+
+#[test]
+fn test_scrub_replaces_volatile_values_only() {
+    let text = "# TYPE rpc_last_success gauge\nrpc_last_success 1700000000\nrpc_up 1\n";
+    assert_eq!(
+        scrub(text),
+        "# TYPE rpc_last_success gauge\nrpc_last_success <scrubbed>\nrpc_up 1\n"
+    );
+}
@@ -0,0 +1,45 @@
+//! Publishes events through a real NATS server and reads them back.
+//!
+//! Skipped unless `NATS_URL` points at a server, e.g.
+//! `nats-server -p 4222 & NATS_URL=nats://127.0.0.1:4222 cargo test --features nats --test nats`.
+
+use tvc_tracker::Metrics;
+use tvc_tracker::bus::{NatsPublisher, run_event_publisher};
+use tvc_tracker::events::{TrackerEvent, emit, event_channel};
+
+use futures_util::StreamExt;
+use serde_json::Value;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_events_reach_a_nats_server() {
+    let Ok(url) = std::env::var("NATS_URL") else {
+        eprintln!("NATS_URL not set, skipping");
+        return;
+    };
+    let subscriber = async_nats::connect(url.as_str()).await.unwrap();
+    let mut messages = subscriber.subscribe("tvc_it.>").await.unwrap();
+    subscriber.flush().await.unwrap();
+
+    let metrics = Metrics::new().unwrap();
+    let events = event_channel();
+    let rx = events.subscribe();
+    emit(
+        Some(&events),
+        TrackerEvent::DelinquencyChanged { delinquent: true },
+    );
+    drop(events);
+    let publisher = NatsPublisher::connect(&url).await.unwrap();
+    run_event_publisher(rx, publisher, "tvc_it", "Vote111", &metrics).await;
+    assert_eq!(metrics.bus_publish_errors.get(), 0);
+
+    let message = tokio::time::timeout(Duration::from_secs(10), messages.next())
+        .await
+        .expect("message within 10s")
+        .unwrap();
+    assert_eq!(message.subject.as_str(), "tvc_it.delinquency_changed");
+    let payload: Value = serde_json::from_slice(&message.payload).unwrap();
+    assert_eq!(payload["type"], "delinquency_changed");
+    assert_eq!(payload["vote_pubkey"], "Vote111");
+    assert_eq!(payload["delinquent"], true);
+}